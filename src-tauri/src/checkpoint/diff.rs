@@ -0,0 +1,668 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::storage::CheckpointStorage;
+
+/// Edit distance beyond which two versions of a file are shown as a full rewrite;
+/// Myers' algorithm keeps O(D²) state, which gets expensive for huge rewrites
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+/// Whether a diff line is unchanged, added or removed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// A line of a hunk with its number on either side (1-based)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub content: String,
+}
+
+/// A run of changes with surrounding context, as in a unified diff
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// How a file differs between the two sides
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// Line-level changes to one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub kind: FileChangeKind,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Binary or unreadable files are compared by existence only and have no hunks
+    pub binary: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// File and line changes between a checkpoint and a later checkpoint or the
+/// working tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointComparison {
+    pub from_checkpoint_id: String,
+    /// `None` when comparing against the current working tree
+    pub to_checkpoint_id: Option<String>,
+    pub files: Vec<FileChange>,
+    pub total_additions: usize,
+    pub total_deletions: usize,
+}
+
+/// The changes a checkpoint made, as a patch `git apply` accepts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointPatch {
+    pub checkpoint_id: String,
+    /// `None` for the first checkpoint of a session, whose patch is relative to
+    /// the commit the session started from
+    pub parent_checkpoint_id: Option<String>,
+    pub patch: String,
+    pub files: Vec<PathBuf>,
+    /// Binary files changed by the checkpoint that the patch can't carry
+    pub skipped_binary: Vec<PathBuf>,
+    /// Changes the patch leaves out or can't be applied cleanly without
+    pub warnings: Vec<String>,
+}
+
+/// File contents at some point in time, keyed by path relative to the project.
+/// `None` marks a file whose content couldn't be read as text.
+pub type FileContents = BTreeMap<PathBuf, Option<String>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Shortest edit script between two line lists (Myers' O(ND) algorithm). Returns
+/// `None` if the files differ in more than `MAX_EDIT_DISTANCE` lines.
+fn edit_script(a: &[&str], b: &[&str]) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_EDIT_DISTANCE) as isize;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    // V before each round, keeping only the diagonals that round can look at
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut found = false;
+    'rounds: for d in 0..=max {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'rounds;
+            }
+            k += 2;
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, round) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| round[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal(x as usize - 1, y as usize - 1));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert(y as usize - 1));
+            } else {
+                edits.push(Edit::Delete(x as usize - 1));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    Some(edits)
+}
+
+/// Lines of a file with their terminators, so a final line without a newline
+/// doesn't compare equal to the same line with one
+fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+fn line_content(line: &str) -> String {
+    line.strip_suffix('\n').unwrap_or(line).to_string()
+}
+
+/// Line-level hunks turning `old` into `new`, with `context` unchanged lines around
+/// each change
+pub fn diff_hunks(old: &str, new: &str, context: usize) -> Vec<DiffHunk> {
+    let a = split_lines(old);
+    let b = split_lines(new);
+
+    // Common prefix and suffix are matched directly to keep the search small
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let middle = edit_script(&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix])
+        .unwrap_or_else(|| {
+            (prefix..a.len() - suffix)
+                .map(|i| Edit::Delete(i - prefix))
+                .chain((prefix..b.len() - suffix).map(|j| Edit::Insert(j - prefix)))
+                .collect()
+        });
+
+    let lines: Vec<DiffLine> = (0..prefix)
+        .map(|i| Edit::Equal(i, i))
+        .chain(middle.into_iter().map(|edit| match edit {
+            Edit::Equal(i, j) => Edit::Equal(i + prefix, j + prefix),
+            Edit::Delete(i) => Edit::Delete(i + prefix),
+            Edit::Insert(j) => Edit::Insert(j + prefix),
+        }))
+        .chain((0..suffix).map(|s| Edit::Equal(a.len() - suffix + s, b.len() - suffix + s)))
+        .map(|edit| match edit {
+            Edit::Equal(i, j) => DiffLine {
+                kind: DiffLineKind::Context,
+                old_line: Some(i + 1),
+                new_line: Some(j + 1),
+                content: line_content(a[i]),
+            },
+            Edit::Delete(i) => DiffLine {
+                kind: DiffLineKind::Removed,
+                old_line: Some(i + 1),
+                new_line: None,
+                content: line_content(a[i]),
+            },
+            Edit::Insert(j) => DiffLine {
+                kind: DiffLineKind::Added,
+                old_line: None,
+                new_line: Some(j + 1),
+                content: line_content(b[j]),
+            },
+        })
+        .collect();
+
+    // Group changes whose context would overlap into one hunk
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.kind != DiffLineKind::Context)
+        .map(|(index, _)| index)
+        .collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for index in changed {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(lines.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let hunk_lines = lines[start..end].to_vec();
+            // Lines of each side before the hunk
+            let old_before = lines[..start]
+                .iter()
+                .filter(|l| l.kind != DiffLineKind::Added)
+                .count();
+            let new_before = lines[..start]
+                .iter()
+                .filter(|l| l.kind != DiffLineKind::Removed)
+                .count();
+            let old_lines = hunk_lines
+                .iter()
+                .filter(|l| l.kind != DiffLineKind::Added)
+                .count();
+            let new_lines = hunk_lines
+                .iter()
+                .filter(|l| l.kind != DiffLineKind::Removed)
+                .count();
+            DiffHunk {
+                old_start: if old_lines == 0 {
+                    old_before
+                } else {
+                    old_before + 1
+                },
+                old_lines,
+                new_start: if new_lines == 0 {
+                    new_before
+                } else {
+                    new_before + 1
+                },
+                new_lines,
+                lines: hunk_lines,
+            }
+        })
+        .collect()
+}
+
+/// Unified diff text turning `old` into `new`, as `diff -u` or `git diff` write it
+pub fn unified_diff(
+    path: &Path,
+    kind: FileChangeKind,
+    old: &str,
+    new: &str,
+    context: usize,
+) -> String {
+    let path = path.to_string_lossy();
+    let (from, to) = match kind {
+        FileChangeKind::Added => ("/dev/null".to_string(), format!("b/{}", path)),
+        FileChangeKind::Deleted => (format!("a/{}", path), "/dev/null".to_string()),
+        FileChangeKind::Modified => (format!("a/{}", path), format!("b/{}", path)),
+    };
+    // Last line of each side when it has no trailing newline
+    let unterminated =
+        |text: &str| (!text.is_empty() && !text.ends_with('\n')).then(|| split_lines(text).len());
+    let (old_unterminated, new_unterminated) = (unterminated(old), unterminated(new));
+
+    let mut out = format!("--- {}\n+++ {}\n", from, to);
+    for hunk in diff_hunks(old, new, context) {
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+        ));
+        for line in &hunk.lines {
+            let marker = match line.kind {
+                DiffLineKind::Context => ' ',
+                DiffLineKind::Added => '+',
+                DiffLineKind::Removed => '-',
+            };
+            out.push(marker);
+            out.push_str(&line.content);
+            out.push('\n');
+            let at_end = match line.kind {
+                DiffLineKind::Removed => line.old_line == old_unterminated,
+                DiffLineKind::Added => line.new_line == new_unterminated,
+                DiffLineKind::Context => line.new_line == new_unterminated,
+            };
+            if at_end {
+                out.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// Compare two sets of file contents
+pub fn diff_contents(from: &FileContents, to: &FileContents, context: usize) -> Vec<FileChange> {
+    let paths: BTreeSet<&PathBuf> = from.keys().chain(to.keys()).collect();
+    let mut changes = Vec::new();
+
+    for path in paths {
+        let (kind, old, new) = match (from.get(path), to.get(path)) {
+            (Some(old), Some(new)) => (FileChangeKind::Modified, old, new),
+            (None, Some(new)) => (FileChangeKind::Added, &None, new),
+            (Some(old), None) => (FileChangeKind::Deleted, old, &None),
+            (None, None) => continue,
+        };
+
+        let binary = match kind {
+            FileChangeKind::Modified => old.is_none() || new.is_none(),
+            FileChangeKind::Added => new.is_none(),
+            FileChangeKind::Deleted => old.is_none(),
+        };
+        if kind == FileChangeKind::Modified && (binary || old == new) {
+            continue;
+        }
+
+        let hunks = if binary {
+            Vec::new()
+        } else {
+            diff_hunks(
+                old.as_deref().unwrap_or(""),
+                new.as_deref().unwrap_or(""),
+                context,
+            )
+        };
+        let count = |kind: DiffLineKind| {
+            hunks
+                .iter()
+                .flat_map(|h| &h.lines)
+                .filter(|l| l.kind == kind)
+                .count()
+        };
+        changes.push(FileChange {
+            path: path.clone(),
+            kind,
+            additions: count(DiffLineKind::Added),
+            deletions: count(DiffLineKind::Removed),
+            binary,
+            hunks,
+        });
+    }
+    changes
+}
+
+/// Git-style patch of the changes from `from` to `to`. Checkpoints don't record
+/// file modes, so files are written as regular, non-executable files.
+pub fn checkpoint_patch(
+    checkpoint_id: String,
+    parent_checkpoint_id: Option<String>,
+    from: &FileContents,
+    to: &FileContents,
+) -> CheckpointPatch {
+    let mut patch = String::new();
+    let mut files = Vec::new();
+    let mut skipped_binary = Vec::new();
+
+    for change in diff_contents(from, to, 3) {
+        if change.binary {
+            skipped_binary.push(change.path);
+            continue;
+        }
+        let text = |contents: &FileContents| {
+            contents
+                .get(&change.path)
+                .cloned()
+                .flatten()
+                .unwrap_or_default()
+        };
+        let (old, new) = (text(from), text(to));
+
+        let path = change.path.to_string_lossy();
+        patch.push_str(&format!("diff --git a/{} b/{}\n", path, path));
+        match change.kind {
+            FileChangeKind::Added => patch.push_str("new file mode 100644\n"),
+            FileChangeKind::Deleted => patch.push_str("deleted file mode 100644\n"),
+            FileChangeKind::Modified => {}
+        }
+        // Empty files are created or removed by the header alone
+        if !old.is_empty() || !new.is_empty() {
+            patch.push_str(&unified_diff(&change.path, change.kind, &old, &new, 3));
+        }
+        files.push(change.path);
+    }
+
+    let warnings = if skipped_binary.is_empty() {
+        Vec::new()
+    } else {
+        vec![format!(
+            "The patch leaves out {} binary files: {}",
+            skipped_binary.len(),
+            skipped_binary
+                .iter()
+                .map(|path| path.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )]
+    };
+
+    CheckpointPatch {
+        checkpoint_id,
+        parent_checkpoint_id,
+        patch,
+        files,
+        skipped_binary,
+        warnings,
+    }
+}
+
+/// Every file as it was at a checkpoint. Checkpoints only store the files that
+/// changed since their parent, so the snapshots along the chain from the root are
+/// layered on top of each other.
+pub fn checkpoint_contents(
+    storage: &CheckpointStorage,
+    project_id: &str,
+    session_id: &str,
+    checkpoint_id: &str,
+) -> Result<FileContents> {
+    let mut chain = Vec::new();
+    let mut seen = HashSet::new();
+    let mut next = Some(checkpoint_id.to_string());
+    while let Some(id) = next {
+        if !seen.insert(id.clone()) {
+            bail!("Checkpoint {} has a cyclic parent chain", checkpoint_id);
+        }
+        let (checkpoint, files, _) = storage.load_checkpoint(project_id, session_id, &id)?;
+        next = checkpoint.parent_checkpoint_id;
+        chain.push(files);
+    }
+
+    let mut contents = FileContents::new();
+    for files in chain.into_iter().rev() {
+        for file in files {
+            if file.is_deleted {
+                contents.remove(&file.file_path);
+            } else {
+                contents.insert(file.file_path, Some(file.content));
+            }
+        }
+    }
+    Ok(contents)
+}
+
+/// Current contents of the project, skipping hidden directories like checkpoints do
+pub fn working_tree_contents(project_path: &Path) -> FileContents {
+    let mut contents = FileContents::new();
+    let walker = walkdir::WalkDir::new(project_path)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !entry.file_name().to_string_lossy().starts_with('.')
+        });
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if let Ok(relative) = entry.path().strip_prefix(project_path) {
+            contents.insert(
+                relative.to_path_buf(),
+                std::fs::read_to_string(entry.path()).ok(),
+            );
+        }
+    }
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_hunks_and_contents() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let hunks = diff_hunks(old, new, 1);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(
+            (
+                hunks[0].old_start,
+                hunks[0].old_lines,
+                hunks[0].new_start,
+                hunks[0].new_lines
+            ),
+            (1, 3, 1, 3)
+        );
+        assert_eq!(
+            hunks[0]
+                .lines
+                .iter()
+                .map(|l| (l.kind, l.content.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (DiffLineKind::Context, "a"),
+                (DiffLineKind::Removed, "b"),
+                (DiffLineKind::Added, "B"),
+                (DiffLineKind::Context, "c"),
+            ]
+        );
+        assert_eq!(
+            (
+                hunks[1].old_start,
+                hunks[1].old_lines,
+                hunks[1].new_start,
+                hunks[1].new_lines
+            ),
+            (10, 1, 10, 2)
+        );
+        assert_eq!(hunks[1].lines[1].new_line, Some(11));
+
+        // Wider context merges the two changes into one hunk
+        assert_eq!(diff_hunks(old, new, 4).len(), 1);
+        assert!(diff_hunks(old, old, 3).is_empty());
+
+        let added = diff_hunks("", "x\ny\n", 3);
+        assert_eq!(
+            (added[0].old_start, added[0].old_lines, added[0].new_start),
+            (0, 0, 1)
+        );
+        assert_eq!(
+            unified_diff(
+                Path::new("src/new.rs"),
+                FileChangeKind::Added,
+                "",
+                "x\ny\n",
+                3
+            ),
+            "--- /dev/null\n+++ b/src/new.rs\n@@ -0,0 +1,2 @@\n+x\n+y\n"
+        );
+
+        let from = FileContents::from([
+            (PathBuf::from("same.txt"), Some("1\n".to_string())),
+            (PathBuf::from("gone.txt"), Some("bye\n".to_string())),
+            (PathBuf::from("logo.png"), None),
+            (PathBuf::from("edit.txt"), Some("one\ntwo\n".to_string())),
+        ]);
+        let to = FileContents::from([
+            (PathBuf::from("same.txt"), Some("1\n".to_string())),
+            (PathBuf::from("logo.png"), None),
+            (PathBuf::from("edit.txt"), Some("one\n2\n".to_string())),
+            (PathBuf::from("new.bin"), None),
+        ]);
+        let changes: Vec<(String, FileChangeKind, usize, usize, bool)> =
+            diff_contents(&from, &to, 3)
+                .into_iter()
+                .map(|c| {
+                    (
+                        c.path.to_string_lossy().to_string(),
+                        c.kind,
+                        c.additions,
+                        c.deletions,
+                        c.binary,
+                    )
+                })
+                .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    "edit.txt".to_string(),
+                    FileChangeKind::Modified,
+                    1,
+                    1,
+                    false
+                ),
+                ("gone.txt".to_string(), FileChangeKind::Deleted, 0, 1, false),
+                ("new.bin".to_string(), FileChangeKind::Added, 0, 0, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_patch() {
+        let parent = FileContents::from([
+            (PathBuf::from("main.rs"), Some("fn main() {}".to_string())),
+            (PathBuf::from("old.txt"), Some("x\n".to_string())),
+            (PathBuf::from("icon.png"), None),
+        ]);
+        let checkpoint = FileContents::from([
+            (
+                PathBuf::from("main.rs"),
+                Some("fn main() {}\n// done\n".to_string()),
+            ),
+            (PathBuf::from("empty.txt"), Some(String::new())),
+            (PathBuf::from("icon.bin"), None),
+        ]);
+        let patch = checkpoint_patch("cp".to_string(), None, &parent, &checkpoint);
+        assert_eq!(
+            patch.skipped_binary,
+            vec![PathBuf::from("icon.bin"), PathBuf::from("icon.png")]
+        );
+        assert_eq!(
+            patch.warnings,
+            vec!["The patch leaves out 2 binary files: icon.bin, icon.png".to_string()]
+        );
+        assert_eq!(
+            patch.files,
+            vec![
+                PathBuf::from("empty.txt"),
+                PathBuf::from("main.rs"),
+                PathBuf::from("old.txt"),
+            ]
+        );
+        assert_eq!(
+            patch.patch,
+            "diff --git a/empty.txt b/empty.txt\n\
+             new file mode 100644\n\
+             diff --git a/main.rs b/main.rs\n\
+             --- a/main.rs\n\
+             +++ b/main.rs\n\
+             @@ -1,1 +1,2 @@\n\
+             -fn main() {}\n\
+             \\ No newline at end of file\n\
+             +fn main() {}\n\
+             +// done\n\
+             diff --git a/old.txt b/old.txt\n\
+             deleted file mode 100644\n\
+             --- a/old.txt\n\
+             +++ /dev/null\n\
+             @@ -1,1 +0,0 @@\n\
+             -x\n"
+        );
+    }
+}
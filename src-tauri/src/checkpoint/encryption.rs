@@ -0,0 +1,219 @@
+//! Encryption of checkpoint contents at rest
+//!
+//! When enabled, snapshotted file contents and session messages are sealed with
+//! XChaCha20-Poly1305 after compression. The key is generated on first use and
+//! kept in the OS keychain, never on disk. Sealed data carries a short header so
+//! plaintext and encrypted checkpoints can live side by side, and turning
+//! encryption off leaves existing data readable.
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::keychain;
+
+/// Marks sealed data; zstd frames never start with it
+const MAGIC: &[u8] = b"OPCE\x01";
+
+/// Bytes of the random nonce stored after the header
+const NONCE_LEN: usize = 24;
+
+/// Keychain account holding the base64-encoded checkpoint key
+const KEYCHAIN_ACCOUNT: &str = "checkpoint-encryption-key";
+
+/// Whether new checkpoint data is encrypted
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Cipher loaded from the keychain, kept for the lifetime of the process
+static KEYCHAIN_CIPHER: Mutex<Option<Arc<CheckpointCipher>>> = Mutex::new(None);
+
+/// Symmetric cipher for checkpoint data
+pub struct CheckpointCipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl CheckpointCipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            cipher: XChaCha20Poly1305::new(Key::from_slice(key)),
+        }
+    }
+
+    /// A cipher with a fresh random key, returned alongside the key
+    pub fn generate() -> (Self, [u8; 32]) {
+        let key: [u8; 32] = XChaCha20Poly1305::generate_key(&mut OsRng).into();
+        (Self::new(&key), key)
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| anyhow!("Failed to encrypt checkpoint data"))?;
+
+        let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if !is_encrypted(sealed) || sealed.len() < MAGIC.len() + NONCE_LEN {
+            bail!("Checkpoint data is not encrypted");
+        }
+        let (nonce, ciphertext) = sealed[MAGIC.len()..].split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                anyhow!("Failed to decrypt checkpoint data; the keychain key may have changed")
+            })
+    }
+}
+
+/// Whether data was sealed by [`CheckpointCipher::encrypt`]
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// The cipher for the key in the OS keychain. With `create`, a key is generated
+/// and stored if there is none yet.
+pub fn keychain_cipher(create: bool) -> Result<Arc<CheckpointCipher>> {
+    let mut cached = KEYCHAIN_CIPHER
+        .lock()
+        .map_err(|_| anyhow!("Checkpoint key cache is poisoned"))?;
+    if let Some(cipher) = cached.as_ref() {
+        return Ok(Arc::clone(cipher));
+    }
+
+    let entry = keychain::entry(KEYCHAIN_ACCOUNT).context("Failed to open keychain entry")?;
+    let engine = base64::engine::general_purpose::STANDARD;
+    let cipher = match entry.get_password() {
+        Ok(encoded) => {
+            let key: [u8; 32] = engine
+                .decode(encoded.trim())
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| anyhow!("Checkpoint key in the keychain is malformed"))?;
+            CheckpointCipher::new(&key)
+        }
+        Err(keyring::Error::NoEntry) if create => {
+            let (cipher, key) = CheckpointCipher::generate();
+            entry
+                .set_password(&engine.encode(key))
+                .context("Failed to store checkpoint key in keychain")?;
+            cipher
+        }
+        Err(keyring::Error::NoEntry) => bail!("No checkpoint encryption key in the keychain"),
+        Err(e) => return Err(e).context("Failed to read checkpoint key from keychain"),
+    };
+
+    let cipher = Arc::new(cipher);
+    *cached = Some(Arc::clone(&cipher));
+    Ok(cipher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::storage::CheckpointStorage;
+    use crate::checkpoint::{Checkpoint, CheckpointMetadata, CheckpointPaths, FileSnapshot};
+    use std::fs;
+
+    #[test]
+    fn test_encrypted_checkpoint_round_trip() {
+        let (cipher, _) = CheckpointCipher::generate();
+        let sealed = cipher.encrypt(b"secret").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), b"secret");
+        assert!(CheckpointCipher::generate().0.decrypt(&sealed).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let plain = CheckpointStorage::new(dir.path().to_path_buf());
+        let encrypted =
+            CheckpointStorage::new(dir.path().to_path_buf()).with_cipher(Arc::new(cipher));
+        plain.init_storage("project", "session").unwrap();
+
+        let save = |storage: &CheckpointStorage, id: &str, content: &str| {
+            let checkpoint = Checkpoint {
+                id: id.to_string(),
+                session_id: "session".to_string(),
+                project_id: "project".to_string(),
+                message_index: 0,
+                timestamp: chrono::Utc::now(),
+                description: None,
+                parent_checkpoint_id: None,
+                metadata: CheckpointMetadata {
+                    total_tokens: 0,
+                    model_used: String::new(),
+                    user_prompt: String::new(),
+                    file_changes: 1,
+                    snapshot_size: 0,
+                },
+            };
+            let snapshot = FileSnapshot {
+                checkpoint_id: id.to_string(),
+                file_path: "src/lib.rs".into(),
+                content: content.to_string(),
+                hash: CheckpointStorage::calculate_file_hash(content),
+                is_deleted: false,
+                permissions: None,
+                size: content.len() as u64,
+            };
+            storage
+                .save_checkpoint(
+                    "project",
+                    "session",
+                    &checkpoint,
+                    vec![snapshot],
+                    "{\"m\":1}",
+                )
+                .unwrap();
+        };
+        save(&plain, "old", "fn plain() {}");
+        save(&encrypted, "new", "fn proprietary() {}");
+
+        let paths = CheckpointPaths::new(&dir.path().to_path_buf(), "project", "session");
+        let pool = |content: &str| {
+            fs::read(paths.file_snapshot_path("", &CheckpointStorage::calculate_file_hash(content)))
+                .unwrap()
+        };
+        assert!(is_encrypted(&pool("fn proprietary() {}")));
+        assert!(is_encrypted(
+            &fs::read(paths.checkpoint_messages_file("new")).unwrap()
+        ));
+        assert!(!is_encrypted(&pool("fn plain() {}")));
+
+        // Plaintext and encrypted checkpoints load side by side
+        let (_, files, messages) = encrypted
+            .load_checkpoint("project", "session", "new")
+            .unwrap();
+        assert_eq!(files[0].content, "fn proprietary() {}");
+        assert_eq!(messages, "{\"m\":1}");
+        let (_, files, _) = encrypted
+            .load_checkpoint("project", "session", "old")
+            .unwrap();
+        assert_eq!(files[0].content, "fn plain() {}");
+
+        // Existing plaintext is sealed in place
+        assert_eq!(encrypted.reseal_all().unwrap(), 2);
+        assert!(is_encrypted(&pool("fn plain() {}")));
+        let (_, files, _) = encrypted
+            .load_checkpoint("project", "session", "old")
+            .unwrap();
+        assert_eq!(files[0].content, "fn plain() {}");
+        assert_eq!(encrypted.reseal_all().unwrap(), 0);
+    }
+}
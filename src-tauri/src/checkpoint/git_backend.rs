@@ -0,0 +1,407 @@
+//! Checkpoints recorded as commits in the project's own git repository
+//!
+//! Each checkpoint becomes a commit of the whole working tree, untracked files
+//! included, under `refs/opcode/checkpoints/<session>/<checkpoint>`. The commits
+//! are built with a throwaway index, so neither the user's branches nor their
+//! staging area are touched, and they can be inspected with `git log` or
+//! `git diff` like any other commit.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::diff::FileContents;
+use super::Checkpoint;
+use crate::commands::run_diffs::{parse_name_status, run_git, snapshot_worktree};
+
+/// Namespace for checkpoint refs, outside `refs/heads` so they never show up as branches
+pub const CHECKPOINT_REF_PREFIX: &str = "refs/opcode/checkpoints";
+
+/// Identity for checkpoint commits, so they work without a configured git user
+const COMMIT_NAME: &str = "opcode";
+const COMMIT_EMAIL: &str = "opcode@localhost";
+
+/// Paths passed to a single `git checkout-index` call
+const CHECKOUT_BATCH: usize = 200;
+
+pub fn checkpoint_ref(session_id: &str, checkpoint_id: &str) -> String {
+    format!("{}/{}/{}", CHECKPOINT_REF_PREFIX, session_id, checkpoint_id)
+}
+
+fn git(project_path: &Path, args: &[&str]) -> Result<String> {
+    run_git(project_path, args, None).map_err(|e| anyhow!(e))
+}
+
+fn resolve_commit(project_path: &Path, reference: &str) -> Option<String> {
+    git(
+        project_path,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", reference),
+        ],
+    )
+    .ok()
+    .map(|commit| commit.trim().to_string())
+}
+
+pub fn is_git_repo(project_path: &Path) -> bool {
+    git(project_path, &["rev-parse", "--is-inside-work-tree"]).is_ok()
+}
+
+/// Commit of a checkpoint, if it was recorded in git
+pub fn checkpoint_commit(
+    project_path: &Path,
+    session_id: &str,
+    checkpoint_id: &str,
+) -> Option<String> {
+    resolve_commit(project_path, &checkpoint_ref(session_id, checkpoint_id))
+}
+
+/// Commit the current working tree for a checkpoint. The commit's parent is the
+/// parent checkpoint's commit, or HEAD for the first checkpoint of a session.
+pub fn record_checkpoint(project_path: &Path, checkpoint: &Checkpoint) -> Result<String> {
+    let tree = snapshot_worktree(project_path)
+        .ok_or_else(|| anyhow!("Failed to snapshot {} with git", project_path.display()))?;
+    let parent = checkpoint
+        .parent_checkpoint_id
+        .as_deref()
+        .and_then(|id| checkpoint_commit(project_path, &checkpoint.session_id, id))
+        .or_else(|| resolve_commit(project_path, "HEAD"));
+
+    let mut message = format!("opcode checkpoint {}\n\n", checkpoint.id);
+    if let Some(description) = &checkpoint.description {
+        message.push_str(description);
+        message.push_str("\n\n");
+    }
+    message.push_str(&format!("Session: {}\n", checkpoint.session_id));
+
+    let mut cmd = Command::new("git");
+    cmd.current_dir(project_path).args(["commit-tree", &tree]);
+    if let Some(parent) = &parent {
+        cmd.args(["-p", parent]);
+    }
+    let output = cmd
+        .args(["-m", &message])
+        .env("GIT_AUTHOR_NAME", COMMIT_NAME)
+        .env("GIT_AUTHOR_EMAIL", COMMIT_EMAIL)
+        .env("GIT_COMMITTER_NAME", COMMIT_NAME)
+        .env("GIT_COMMITTER_EMAIL", COMMIT_EMAIL)
+        .output()
+        .context("Failed to run git commit-tree")?;
+    if !output.status.success() {
+        bail!(
+            "git commit-tree failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    git(
+        project_path,
+        &[
+            "update-ref",
+            &checkpoint_ref(&checkpoint.session_id, &checkpoint.id),
+            &commit,
+        ],
+    )?;
+    Ok(commit)
+}
+
+/// Delete a checkpoint's ref, so git can reclaim its commit once nothing else
+/// reaches it
+pub fn delete_checkpoint_ref(
+    project_path: &Path,
+    session_id: &str,
+    checkpoint_id: &str,
+) -> Result<()> {
+    git(
+        project_path,
+        &[
+            "update-ref",
+            "-d",
+            &checkpoint_ref(session_id, checkpoint_id),
+        ],
+    )
+    .map(|_| ())
+}
+
+/// Commit a session's first checkpoint started from: the parent of its git
+/// commit when it was recorded in git, the current HEAD otherwise
+pub fn root_base_commit(
+    project_path: &Path,
+    session_id: &str,
+    checkpoint_id: &str,
+) -> Option<String> {
+    match checkpoint_commit(project_path, session_id, checkpoint_id) {
+        Some(commit) => resolve_commit(project_path, &format!("{}^", commit)),
+        None => resolve_commit(project_path, "HEAD"),
+    }
+}
+
+/// Every file under the project directory at a commit, keyed by path relative
+/// to the project. Hidden directories are left out like checkpoints leave them
+/// out, and binary files have no content.
+pub fn commit_contents(project_path: &Path, commit: &str) -> Result<FileContents> {
+    let listing = git(project_path, &["ls-tree", "-r", "-z", commit, "--", "."])?;
+    let blobs: Vec<(&str, &str)> = listing
+        .split('\0')
+        .filter_map(|entry| {
+            let (meta, path) = entry.split_once('\t')?;
+            let mut meta = meta.split(' ');
+            let (kind, object) = (meta.nth(1)?, meta.next()?);
+            let hidden = Path::new(path).parent().is_some_and(|dir| {
+                dir.components()
+                    .any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+            });
+            (kind == "blob" && !hidden).then_some((object, path))
+        })
+        .collect();
+
+    let mut child = Command::new("git")
+        .current_dir(project_path)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run git cat-file")?;
+    let input: String = blobs
+        .iter()
+        .map(|(object, _)| format!("{}\n", object))
+        .collect();
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Failed to open git cat-file input")?;
+    // Written from another thread so a full output pipe can't block the input
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .context("Failed to read git cat-file output")?;
+    let _ = writer.join();
+    if !output.status.success() {
+        bail!(
+            "git cat-file failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    // Each object is a `<object> <type> <size>` line, its content and a newline
+    let mut contents = FileContents::new();
+    let mut rest = output.stdout.as_slice();
+    for (_, path) in blobs {
+        let header_end = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(|| anyhow!("Truncated git cat-file output"))?;
+        let header = String::from_utf8_lossy(&rest[..header_end]);
+        let size: usize = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse().ok())
+            .ok_or_else(|| anyhow!("Unexpected git cat-file output: {}", header))?;
+        let body = rest
+            .get(header_end + 1..header_end + 1 + size)
+            .ok_or_else(|| anyhow!("Truncated git cat-file output"))?;
+        contents.insert(PathBuf::from(path), String::from_utf8(body.to_vec()).ok());
+        rest = rest.get(header_end + 2 + size..).unwrap_or_default();
+    }
+    Ok(contents)
+}
+
+/// Put the project directory back to a checkpoint's commit, returning how many
+/// files changed. Only paths under the project directory are touched, and
+/// ignored files are left alone.
+pub fn restore_checkpoint(
+    project_path: &Path,
+    session_id: &str,
+    checkpoint_id: &str,
+) -> Result<usize> {
+    let commit = checkpoint_commit(project_path, session_id, checkpoint_id)
+        .ok_or_else(|| anyhow!("Checkpoint {} has no git commit", checkpoint_id))?;
+    let current = snapshot_worktree(project_path)
+        .ok_or_else(|| anyhow!("Failed to snapshot {} with git", project_path.display()))?;
+
+    let changes = parse_name_status(&git(
+        project_path,
+        &[
+            "diff",
+            "--name-status",
+            "--no-renames",
+            "--relative",
+            &commit,
+            &current,
+            "--",
+            ".",
+        ],
+    )?);
+
+    // Files created after the checkpoint go away
+    for change in changes.iter().filter(|change| change.status == "A") {
+        let path = project_path.join(&change.path);
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).context(format!("Failed to delete {}", path.display())),
+        }
+        let mut dir = path.parent();
+        while let Some(parent) = dir {
+            if parent == project_path || fs::remove_dir(parent).is_err() {
+                break;
+            }
+            dir = parent.parent();
+        }
+    }
+
+    // Everything else is written back from the checkpoint's tree
+    let to_checkout: Vec<&str> = changes
+        .iter()
+        .filter(|change| change.status != "A")
+        .map(|change| change.path.as_str())
+        .collect();
+    if !to_checkout.is_empty() {
+        let temp_index =
+            std::env::temp_dir().join(format!("opcode-index-{}", uuid::Uuid::new_v4().simple()));
+        let result =
+            run_git(project_path, &["read-tree", &commit], Some(&temp_index)).and_then(|_| {
+                to_checkout.chunks(CHECKOUT_BATCH).try_for_each(|paths| {
+                    let mut args = vec!["checkout-index", "-f", "--"];
+                    args.extend_from_slice(paths);
+                    run_git(project_path, &args, Some(&temp_index)).map(|_| ())
+                })
+            });
+        let _ = fs::remove_file(&temp_index);
+        result.map_err(|e| anyhow!(e))?;
+    }
+
+    Ok(changes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointMetadata;
+
+    #[test]
+    fn test_record_and_restore_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        git(repo, &["init", "--quiet"]).unwrap();
+        let project = repo.join("app");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(repo.join("outside.txt"), "keep").unwrap();
+        fs::write(project.join("src/main.rs"), "v1").unwrap();
+        fs::write(project.join("notes.txt"), "notes").unwrap();
+        assert!(is_git_repo(&project));
+
+        let checkpoint = Checkpoint {
+            id: "cp1".to_string(),
+            session_id: "session".to_string(),
+            project_id: "project".to_string(),
+            message_index: 0,
+            timestamp: chrono::Utc::now(),
+            description: Some("Before refactor".to_string()),
+            parent_checkpoint_id: None,
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: String::new(),
+                user_prompt: String::new(),
+                file_changes: 0,
+                snapshot_size: 0,
+            },
+        };
+        let commit = record_checkpoint(&project, &checkpoint).unwrap();
+        assert_eq!(
+            checkpoint_commit(&project, "session", "cp1").as_deref(),
+            Some(commit.as_str())
+        );
+        // The user's index and branches are untouched
+        assert_eq!(
+            git(repo, &["status", "--porcelain"])
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+        assert!(resolve_commit(repo, "HEAD").is_none());
+
+        fs::write(project.join("src/main.rs"), "v2").unwrap();
+        fs::remove_file(project.join("notes.txt")).unwrap();
+        fs::create_dir_all(project.join("new/dir")).unwrap();
+        fs::write(project.join("new/dir/file.rs"), "new").unwrap();
+        fs::write(repo.join("outside.txt"), "changed").unwrap();
+
+        assert_eq!(restore_checkpoint(&project, "session", "cp1").unwrap(), 3);
+        assert_eq!(
+            fs::read_to_string(project.join("src/main.rs")).unwrap(),
+            "v1"
+        );
+        assert_eq!(
+            fs::read_to_string(project.join("notes.txt")).unwrap(),
+            "notes"
+        );
+        assert!(!project.join("new").exists());
+        // Files outside the project directory are out of scope
+        assert_eq!(
+            fs::read_to_string(repo.join("outside.txt")).unwrap(),
+            "changed"
+        );
+        delete_checkpoint_ref(&project, "session", "cp1").unwrap();
+        assert!(checkpoint_commit(&project, "session", "cp1").is_none());
+    }
+
+    #[test]
+    fn test_commit_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        git(repo, &["init", "--quiet"]).unwrap();
+        let project = repo.join("app");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::create_dir_all(project.join(".cache")).unwrap();
+        fs::write(repo.join("outside.txt"), "out").unwrap();
+        fs::write(project.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(project.join(".env"), "KEY=1").unwrap();
+        fs::write(project.join(".cache/state"), "cached").unwrap();
+        fs::write(
+            project.join("logo.png"),
+            [0x89, b'P', b'N', b'G', 0xff, 0x00],
+        )
+        .unwrap();
+
+        let checkpoint = Checkpoint {
+            id: "cp1".to_string(),
+            session_id: "session".to_string(),
+            project_id: "project".to_string(),
+            message_index: 0,
+            timestamp: chrono::Utc::now(),
+            description: None,
+            parent_checkpoint_id: None,
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: String::new(),
+                user_prompt: String::new(),
+                file_changes: 0,
+                snapshot_size: 0,
+            },
+        };
+        let commit = record_checkpoint(&project, &checkpoint).unwrap();
+        // Recorded without a HEAD to start from
+        assert!(root_base_commit(&project, "session", "cp1").is_none());
+
+        assert_eq!(
+            commit_contents(&project, &commit).unwrap(),
+            FileContents::from([
+                (PathBuf::from(".env"), Some("KEY=1".to_string())),
+                (PathBuf::from("logo.png"), None),
+                (
+                    PathBuf::from("src/main.rs"),
+                    Some("fn main() {}\n".to_string())
+                ),
+            ])
+        );
+    }
+}
@@ -8,9 +8,10 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use super::{
+    git_backend,
     storage::{self, CheckpointStorage},
-    Checkpoint, CheckpointMetadata, CheckpointPaths, CheckpointResult, CheckpointStrategy,
-    FileSnapshot, FileState, FileTracker, SessionTimeline,
+    Checkpoint, CheckpointBackend, CheckpointMetadata, CheckpointPaths, CheckpointResult,
+    CheckpointStrategy, FileSnapshot, FileState, FileTracker, SessionTimeline,
 };
 
 /// Manages checkpoint operations for a session
@@ -271,7 +272,7 @@ impl CheckpointManager {
 
         // Save checkpoint
         let messages_content = messages.join("\n");
-        let result = self.storage.save_checkpoint(
+        let mut result = self.storage.save_checkpoint(
             &self.project_id,
             &self.session_id,
             &checkpoint,
@@ -279,6 +280,15 @@ impl CheckpointManager {
             &messages_content,
         )?;
 
+        // Mirror the checkpoint into the project's git repository if enabled
+        if self.timeline.read().await.backend == CheckpointBackend::Git {
+            if let Err(e) = git_backend::record_checkpoint(&self.project_path, &checkpoint) {
+                result
+                    .warnings
+                    .push(format!("Failed to record checkpoint in git: {}", e));
+            }
+        }
+
         // Reload timeline from disk so in-memory timeline has updated nodes and total_checkpoints
         let claude_dir = self.storage.claude_dir.clone();
         let paths = CheckpointPaths::new(&claude_dir, &self.project_id, &self.session_id);
@@ -482,84 +492,98 @@ impl CheckpointManager {
             Ok(())
         }
 
-        let mut current_files = Vec::new();
-        let _ =
-            collect_all_project_files(&self.project_path, &self.project_path, &mut current_files);
-
-        // Create a set of files that should exist after restore
-        let mut checkpoint_files = std::collections::HashSet::new();
-        for snapshot in &file_snapshots {
-            if !snapshot.is_deleted {
-                checkpoint_files.insert(snapshot.file_path.clone());
-            }
-        }
-
-        // Delete files that exist now but shouldn't exist in the checkpoint
         let mut warnings = Vec::new();
         let mut files_processed = 0;
 
-        for current_file in current_files {
-            if !checkpoint_files.contains(&current_file) {
-                // This file exists now but not in the checkpoint, so delete it
-                let full_path = self.project_path.join(&current_file);
-                match fs::remove_file(&full_path) {
-                    Ok(_) => {
-                        files_processed += 1;
-                        log::info!("Deleted file not in checkpoint: {:?}", current_file);
-                    }
-                    Err(e) => {
-                        warnings.push(format!(
-                            "Failed to delete {}: {}",
-                            current_file.display(),
-                            e
-                        ));
-                    }
+        let use_git = self.timeline.read().await.backend == CheckpointBackend::Git
+            && git_backend::checkpoint_commit(&self.project_path, &self.session_id, checkpoint_id)
+                .is_some();
+        if use_git {
+            files_processed = git_backend::restore_checkpoint(
+                &self.project_path,
+                &self.session_id,
+                checkpoint_id,
+            )?;
+        } else {
+            let mut current_files = Vec::new();
+            let _ = collect_all_project_files(
+                &self.project_path,
+                &self.project_path,
+                &mut current_files,
+            );
+
+            // Create a set of files that should exist after restore
+            let mut checkpoint_files = std::collections::HashSet::new();
+            for snapshot in &file_snapshots {
+                if !snapshot.is_deleted {
+                    checkpoint_files.insert(snapshot.file_path.clone());
                 }
             }
-        }
 
-        // Clean up empty directories
-        fn remove_empty_dirs(
-            dir: &std::path::Path,
-            base: &std::path::Path,
-        ) -> Result<bool, std::io::Error> {
-            if dir == base {
-                return Ok(false); // Don't remove the base directory
+            // Delete files that exist now but shouldn't exist in the checkpoint
+            for current_file in current_files {
+                if !checkpoint_files.contains(&current_file) {
+                    // This file exists now but not in the checkpoint, so delete it
+                    let full_path = self.project_path.join(&current_file);
+                    match fs::remove_file(&full_path) {
+                        Ok(_) => {
+                            files_processed += 1;
+                            log::info!("Deleted file not in checkpoint: {:?}", current_file);
+                        }
+                        Err(e) => {
+                            warnings.push(format!(
+                                "Failed to delete {}: {}",
+                                current_file.display(),
+                                e
+                            ));
+                        }
+                    }
+                }
             }
 
-            let mut is_empty = true;
-            for entry in fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    if !remove_empty_dirs(&path, base)? {
+            // Clean up empty directories
+            fn remove_empty_dirs(
+                dir: &std::path::Path,
+                base: &std::path::Path,
+            ) -> Result<bool, std::io::Error> {
+                if dir == base {
+                    return Ok(false); // Don't remove the base directory
+                }
+
+                let mut is_empty = true;
+                for entry in fs::read_dir(dir)? {
+                    let entry = entry?;
+                    let path = entry.path();
+                    if path.is_dir() {
+                        if !remove_empty_dirs(&path, base)? {
+                            is_empty = false;
+                        }
+                    } else {
                         is_empty = false;
                     }
-                } else {
-                    is_empty = false;
                 }
-            }
 
-            if is_empty {
-                fs::remove_dir(dir)?;
-                Ok(true)
-            } else {
-                Ok(false)
+                if is_empty {
+                    fs::remove_dir(dir)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
             }
-        }
-
-        // Clean up any empty directories left after file deletion
-        let _ = remove_empty_dirs(&self.project_path, &self.project_path);
 
-        // Restore files from checkpoint
-        for snapshot in &file_snapshots {
-            match self.restore_file_snapshot(snapshot).await {
-                Ok(_) => files_processed += 1,
-                Err(e) => warnings.push(format!(
-                    "Failed to restore {}: {}",
-                    snapshot.file_path.display(),
-                    e
-                )),
+            // Clean up any empty directories left after file deletion
+            let _ = remove_empty_dirs(&self.project_path, &self.project_path);
+
+            // Restore files from checkpoint
+            for snapshot in &file_snapshots {
+                match self.restore_file_snapshot(snapshot).await {
+                    Ok(_) => files_processed += 1,
+                    Err(e) => warnings.push(format!(
+                        "Failed to restore {}: {}",
+                        snapshot.file_path.display(),
+                        e
+                    )),
+                }
             }
         }
 
@@ -634,6 +658,15 @@ impl CheckpointManager {
         self.timeline.read().await.clone()
     }
 
+    /// Reload the timeline from disk after checkpoints were pruned behind our back
+    pub async fn reload_timeline(&self) -> Result<()> {
+        let paths =
+            CheckpointPaths::new(&self.storage.claude_dir, &self.project_id, &self.session_id);
+        let timeline = self.storage.load_timeline(&paths.timeline_file)?;
+        *self.timeline.write().await = timeline;
+        Ok(())
+    }
+
     /// List all checkpoints
     pub async fn list_checkpoints(&self) -> Vec<Checkpoint> {
         let timeline = self.timeline.read().await;
@@ -764,6 +797,27 @@ impl CheckpointManager {
         Ok(())
     }
 
+    /// Choose where checkpoints are recorded. The git backend needs the project to
+    /// be inside a git repository.
+    pub async fn set_backend(&self, backend: CheckpointBackend) -> Result<()> {
+        if backend == CheckpointBackend::Git && !git_backend::is_git_repo(&self.project_path) {
+            anyhow::bail!(
+                "{} is not inside a git repository",
+                self.project_path.display()
+            );
+        }
+
+        let mut timeline = self.timeline.write().await;
+        timeline.backend = backend;
+
+        let paths =
+            CheckpointPaths::new(&self.storage.claude_dir, &self.project_id, &self.session_id);
+        self.storage
+            .save_timeline(&paths.timeline_file, &timeline)?;
+
+        Ok(())
+    }
+
     /// Get files modified since a given timestamp
     pub async fn get_files_modified_since(&self, since: DateTime<Utc>) -> Vec<PathBuf> {
         let tracker = self.file_tracker.read().await;
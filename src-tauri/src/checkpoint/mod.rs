@@ -3,7 +3,12 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+pub mod diff;
+pub mod encryption;
+pub mod git_backend;
 pub mod manager;
+pub mod project_timeline;
+pub mod retention;
 pub mod state;
 pub mod storage;
 
@@ -93,6 +98,9 @@ pub struct SessionTimeline {
     pub checkpoint_strategy: CheckpointStrategy,
     /// Total number of checkpoints in timeline
     pub total_checkpoints: usize,
+    /// Where checkpoints are recorded besides opcode's own storage
+    #[serde(default)]
+    pub backend: CheckpointBackend,
 }
 
 /// Strategy for automatic checkpoint creation
@@ -109,6 +117,18 @@ pub enum CheckpointStrategy {
     Smart,
 }
 
+/// How checkpoint file state is recorded
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckpointBackend {
+    /// File snapshots in opcode's checkpoint storage only
+    #[default]
+    Files,
+    /// Also commit each checkpoint to hidden refs in the project's git repository,
+    /// and restore from those commits
+    Git,
+}
+
 /// Tracks the state of files for checkpointing
 #[derive(Debug, Clone)]
 pub struct FileTracker {
@@ -140,6 +160,31 @@ pub struct CheckpointResult {
     pub warnings: Vec<String>,
 }
 
+/// Outcome of the validation command run after a checkpoint was created
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationStatus {
+    Running,
+    Passed,
+    Failed,
+    TimedOut,
+    /// The command couldn't be started
+    Error,
+}
+
+/// Result of a post-checkpoint validation command such as `cargo test`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointValidation {
+    pub command: String,
+    pub status: ValidationStatus,
+    pub exit_code: Option<i32>,
+    /// Tail of the combined stdout and stderr
+    pub output_summary: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
 /// Diff between two checkpoints
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CheckpointDiff {
@@ -186,6 +231,7 @@ impl SessionTimeline {
             auto_checkpoint_enabled: false,
             checkpoint_strategy: CheckpointStrategy::default(),
             total_checkpoints: 0,
+            backend: CheckpointBackend::default(),
         }
     }
 
@@ -245,6 +291,10 @@ impl CheckpointPaths {
         self.checkpoint_dir(checkpoint_id).join("messages.jsonl")
     }
 
+    pub fn checkpoint_validation_file(&self, checkpoint_id: &str) -> PathBuf {
+        self.checkpoint_dir(checkpoint_id).join("validation.json")
+    }
+
     #[allow(dead_code)]
     pub fn file_snapshot_path(&self, _checkpoint_id: &str, file_hash: &str) -> PathBuf {
         // In content-addressable storage, files are stored by hash in the content pool
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use super::storage::CheckpointStorage;
+use super::{CheckpointPaths, CheckpointValidation, SessionTimeline};
+
+/// Characters of the originating prompt kept in a timeline entry
+const PROMPT_SNIPPET_CHARS: usize = 160;
+
+/// A checkpoint in the project-wide timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTimelineEntry {
+    pub checkpoint_id: String,
+    pub session_id: String,
+    pub parent_checkpoint_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub description: Option<String>,
+    /// Start of the user prompt that led to the checkpoint, on one line
+    pub prompt_snippet: String,
+    pub model_used: String,
+    pub file_changes: usize,
+    pub total_tokens: u64,
+    /// Whether this is the checkpoint its session is currently on
+    pub is_current: bool,
+    /// Result of the post-checkpoint validation command, if one ran
+    pub validation: Option<CheckpointValidation>,
+}
+
+/// Collapse whitespace and cut a prompt down to a one-line snippet
+pub fn prompt_snippet(prompt: &str) -> String {
+    let line = prompt.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(PROMPT_SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}…", line[..end].trim_end()),
+        None => line,
+    }
+}
+
+/// Timelines of every session of a project that has checkpoints
+fn session_timelines(storage: &CheckpointStorage, project_id: &str) -> Vec<SessionTimeline> {
+    let timelines_dir = storage
+        .claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(".timelines");
+    let Ok(entries) = fs::read_dir(&timelines_dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let session_id = entry.file_name().to_string_lossy().to_string();
+            let paths = CheckpointPaths::new(&storage.claude_dir, project_id, &session_id);
+            storage.load_timeline(&paths.timeline_file).ok()
+        })
+        .collect()
+}
+
+/// Checkpoints from all sessions of a project, newest first. `since` drops older
+/// checkpoints and `limit` caps how many are returned.
+pub fn project_timeline(
+    storage: &CheckpointStorage,
+    project_id: &str,
+    since: Option<DateTime<Utc>>,
+    limit: Option<usize>,
+) -> Vec<ProjectTimelineEntry> {
+    let mut entries = Vec::new();
+    for timeline in session_timelines(storage, project_id) {
+        let Some(root) = &timeline.root_node else {
+            continue;
+        };
+        let mut checkpoints = Vec::new();
+        CheckpointStorage::collect_checkpoints(root, &mut checkpoints);
+        entries.extend(
+            checkpoints
+                .into_iter()
+                .filter(|checkpoint| since.is_none_or(|since| checkpoint.timestamp >= since))
+                .map(|checkpoint| ProjectTimelineEntry {
+                    validation: storage.load_validation(
+                        project_id,
+                        &timeline.session_id,
+                        &checkpoint.id,
+                    ),
+                    is_current: timeline.current_checkpoint_id.as_deref()
+                        == Some(checkpoint.id.as_str()),
+                    session_id: timeline.session_id.clone(),
+                    prompt_snippet: prompt_snippet(&checkpoint.metadata.user_prompt),
+                    checkpoint_id: checkpoint.id,
+                    parent_checkpoint_id: checkpoint.parent_checkpoint_id,
+                    timestamp: checkpoint.timestamp,
+                    description: checkpoint.description,
+                    model_used: checkpoint.metadata.model_used,
+                    file_changes: checkpoint.metadata.file_changes,
+                    total_tokens: checkpoint.metadata.total_tokens,
+                }),
+        );
+    }
+
+    entries.sort_by(|a, b| {
+        b.timestamp
+            .cmp(&a.timestamp)
+            .then_with(|| a.session_id.cmp(&b.session_id))
+    });
+    if let Some(limit) = limit {
+        entries.truncate(limit);
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::{Checkpoint, CheckpointMetadata};
+    use chrono::Duration;
+
+    fn save(storage: &CheckpointStorage, session_id: &str, id: &str, hours_ago: i64, prompt: &str) {
+        storage.init_storage("project", session_id).unwrap();
+        let paths = CheckpointPaths::new(&storage.claude_dir, "project", session_id);
+        let parent_checkpoint_id = storage
+            .load_timeline(&paths.timeline_file)
+            .unwrap()
+            .current_checkpoint_id;
+        let checkpoint = Checkpoint {
+            id: id.to_string(),
+            session_id: session_id.to_string(),
+            project_id: "project".to_string(),
+            message_index: 0,
+            timestamp: Utc::now() - Duration::hours(hours_ago),
+            description: None,
+            parent_checkpoint_id,
+            metadata: CheckpointMetadata {
+                total_tokens: 10,
+                model_used: "sonnet".to_string(),
+                user_prompt: prompt.to_string(),
+                file_changes: 1,
+                snapshot_size: 0,
+            },
+        };
+        storage
+            .save_checkpoint("project", session_id, &checkpoint, Vec::new(), "{}")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_project_timeline_merges_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = CheckpointStorage::new(dir.path().to_path_buf());
+        save(&storage, "one", "a", 50, "Set up the\n  project");
+        save(&storage, "two", "b", 30, "Add a parser");
+        save(&storage, "one", "c", 10, &"long ".repeat(100));
+        save(&storage, "two", "d", 1, "Fix the tests");
+
+        let timeline = project_timeline(&storage, "project", None, None);
+        let ids: Vec<&str> = timeline.iter().map(|e| e.checkpoint_id.as_str()).collect();
+        assert_eq!(ids, vec!["d", "c", "b", "a"]);
+        assert_eq!(timeline[3].prompt_snippet, "Set up the project");
+        assert!(timeline[1].prompt_snippet.ends_with('…'));
+        assert!(timeline[1].prompt_snippet.chars().count() <= PROMPT_SNIPPET_CHARS + 1);
+        assert_eq!(timeline[2].parent_checkpoint_id, None);
+        assert_eq!(timeline[0].parent_checkpoint_id.as_deref(), Some("b"));
+        assert!(timeline[0].is_current && timeline[1].is_current && !timeline[2].is_current);
+
+        let recent = project_timeline(
+            &storage,
+            "project",
+            Some(Utc::now() - Duration::hours(24)),
+            Some(1),
+        );
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].checkpoint_id, "d");
+        assert!(project_timeline(&storage, "other", None, None).is_empty());
+    }
+}
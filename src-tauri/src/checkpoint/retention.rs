@@ -0,0 +1,348 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::storage::CheckpointStorage;
+use super::{Checkpoint, CheckpointPaths};
+
+/// Limits on how many checkpoints are kept. Unset limits don't apply, so the
+/// default policy keeps everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    /// Most recent checkpoints to keep per session
+    pub keep_last: Option<usize>,
+    /// Checkpoints older than this are removed
+    pub max_age_days: Option<u32>,
+    /// Oldest checkpoints across all projects are removed until usage fits
+    pub max_total_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_unlimited(&self) -> bool {
+        self.keep_last.is_none() && self.max_age_days.is_none() && self.max_total_bytes.is_none()
+    }
+}
+
+/// Checkpoint disk usage of one project
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectCheckpointUsage {
+    pub project_id: String,
+    pub sessions: usize,
+    pub checkpoints: usize,
+    pub bytes: u64,
+}
+
+/// Outcome of a garbage collection pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    pub removed_checkpoints: usize,
+    pub removed_content_files: usize,
+    pub bytes_freed: u64,
+    pub before: Vec<ProjectCheckpointUsage>,
+    pub after: Vec<ProjectCheckpointUsage>,
+}
+
+/// A session with a checkpoint timeline
+struct SessionDir {
+    project_id: String,
+    session_id: String,
+    paths: CheckpointPaths,
+}
+
+impl SessionDir {
+    fn base_dir(&self) -> &Path {
+        self.paths
+            .timeline_file
+            .parent()
+            .unwrap_or(&self.paths.timeline_file)
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Every session under `~/.claude/projects/*/.timelines`
+fn session_dirs(claude_dir: &Path) -> Vec<SessionDir> {
+    let mut sessions = Vec::new();
+    let Ok(projects) = fs::read_dir(claude_dir.join("projects")) else {
+        return sessions;
+    };
+    for project in projects.filter_map(|entry| entry.ok()) {
+        let project_id = project.file_name().to_string_lossy().to_string();
+        let Ok(timelines) = fs::read_dir(project.path().join(".timelines")) else {
+            continue;
+        };
+        for session in timelines.filter_map(|entry| entry.ok()) {
+            let session_id = session.file_name().to_string_lossy().to_string();
+            let paths = CheckpointPaths::new(&claude_dir.to_path_buf(), &project_id, &session_id);
+            if paths.timeline_file.is_file() {
+                sessions.push(SessionDir {
+                    project_id: project_id.clone(),
+                    session_id,
+                    paths,
+                });
+            }
+        }
+    }
+    sessions
+}
+
+fn session_checkpoints(storage: &CheckpointStorage, session: &SessionDir) -> Vec<Checkpoint> {
+    let mut checkpoints = Vec::new();
+    if let Ok(timeline) = storage.load_timeline(&session.paths.timeline_file) {
+        if let Some(root) = &timeline.root_node {
+            CheckpointStorage::collect_checkpoints(root, &mut checkpoints);
+        }
+    }
+    checkpoints
+}
+
+/// Checkpoint disk usage per project, largest first
+pub fn disk_usage(storage: &CheckpointStorage) -> Vec<ProjectCheckpointUsage> {
+    let mut projects: HashMap<String, ProjectCheckpointUsage> = HashMap::new();
+    for session in session_dirs(&storage.claude_dir) {
+        let usage = projects
+            .entry(session.project_id.clone())
+            .or_insert_with(|| ProjectCheckpointUsage {
+                project_id: session.project_id.clone(),
+                sessions: 0,
+                checkpoints: 0,
+                bytes: 0,
+            });
+        usage.sessions += 1;
+        usage.checkpoints += session_checkpoints(storage, &session).len();
+        usage.bytes += dir_size(session.base_dir());
+    }
+
+    let mut usage: Vec<ProjectCheckpointUsage> = projects.into_values().collect();
+    usage.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then_with(|| a.project_id.cmp(&b.project_id))
+    });
+    usage
+}
+
+/// Checkpoints of one session that fall outside the count and age limits,
+/// oldest first
+pub fn expired_checkpoints(
+    checkpoints: &[Checkpoint],
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let mut newest_first: Vec<&Checkpoint> = checkpoints.iter().collect();
+    newest_first.sort_by_key(|checkpoint| std::cmp::Reverse(checkpoint.timestamp));
+    let cutoff = policy
+        .max_age_days
+        .map(|days| now - Duration::days(i64::from(days)));
+
+    newest_first
+        .into_iter()
+        .enumerate()
+        .filter(|(index, checkpoint)| {
+            policy.keep_last.is_some_and(|keep| *index >= keep)
+                || cutoff.is_some_and(|cutoff| checkpoint.timestamp < cutoff)
+        })
+        .map(|(_, checkpoint)| checkpoint.id.clone())
+        .rev()
+        .collect()
+}
+
+/// Remove checkpoints outside the retention policy and the file content nothing
+/// references any more. Checkpoints that can't be pruned, like the one a session
+/// is currently on, are kept.
+pub fn collect_garbage(
+    storage: &CheckpointStorage,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Result<GcReport> {
+    let before = disk_usage(storage);
+    let sessions = session_dirs(&storage.claude_dir);
+    let mut removed_checkpoints = 0;
+    let mut removed_content_files = 0;
+
+    let mut prune = |session: &SessionDir, ids: &[String]| -> usize {
+        let removed = ids
+            .iter()
+            .filter(|id| {
+                storage
+                    .prune_checkpoint(&session.project_id, &session.session_id, id)
+                    .map_err(|e| log::debug!("Keeping checkpoint {}: {}", id, e))
+                    .is_ok()
+            })
+            .count();
+        if removed > 0 {
+            match storage.garbage_collect_content(&session.project_id, &session.session_id) {
+                Ok(count) => removed_content_files += count,
+                Err(e) => log::warn!("Failed to garbage collect content: {}", e),
+            }
+        }
+        removed
+    };
+
+    for session in &sessions {
+        let expired = expired_checkpoints(&session_checkpoints(storage, session), policy, now);
+        removed_checkpoints += prune(session, &expired);
+    }
+
+    if let Some(max_total_bytes) = policy.max_total_bytes {
+        let mut sizes: Vec<u64> = sessions.iter().map(|s| dir_size(s.base_dir())).collect();
+        let mut total: u64 = sizes.iter().sum();
+
+        // Oldest checkpoints across all sessions go first
+        let mut candidates: Vec<(DateTime<Utc>, usize, String)> = sessions
+            .iter()
+            .enumerate()
+            .flat_map(|(index, session)| {
+                session_checkpoints(storage, session)
+                    .into_iter()
+                    .map(move |checkpoint| (checkpoint.timestamp, index, checkpoint.id))
+            })
+            .collect();
+        candidates.sort();
+
+        for (_, index, id) in candidates {
+            if total <= max_total_bytes {
+                break;
+            }
+            if prune(&sessions[index], &[id]) > 0 {
+                let size = dir_size(sessions[index].base_dir());
+                total = total - sizes[index] + size;
+                sizes[index] = size;
+                removed_checkpoints += 1;
+            }
+        }
+    }
+
+    let after = disk_usage(storage);
+    let total = |usage: &[ProjectCheckpointUsage]| usage.iter().map(|u| u.bytes).sum::<u64>();
+    Ok(GcReport {
+        removed_checkpoints,
+        removed_content_files,
+        bytes_freed: total(&before).saturating_sub(total(&after)),
+        before,
+        after,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::diff::checkpoint_contents;
+    use crate::checkpoint::{CheckpointMetadata, FileSnapshot};
+
+    fn save(
+        storage: &CheckpointStorage,
+        id: &str,
+        parent: Option<&str>,
+        days_ago: i64,
+        files: &[(&str, Option<&str>)],
+    ) -> Checkpoint {
+        let checkpoint = Checkpoint {
+            id: id.to_string(),
+            session_id: "session".to_string(),
+            project_id: "project".to_string(),
+            message_index: 0,
+            timestamp: Utc::now() - Duration::days(days_ago),
+            description: None,
+            parent_checkpoint_id: parent.map(str::to_string),
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: String::new(),
+                user_prompt: String::new(),
+                file_changes: files.len(),
+                snapshot_size: 0,
+            },
+        };
+        let snapshots = files
+            .iter()
+            .map(|(path, content)| FileSnapshot {
+                checkpoint_id: id.to_string(),
+                file_path: path.into(),
+                content: content.unwrap_or_default().to_string(),
+                hash: CheckpointStorage::calculate_file_hash(content.unwrap_or_default()),
+                is_deleted: content.is_none(),
+                permissions: None,
+                size: 0,
+            })
+            .collect();
+        storage
+            .save_checkpoint("project", "session", &checkpoint, snapshots, "{}")
+            .unwrap();
+        checkpoint
+    }
+
+    #[test]
+    fn test_gc_prunes_old_checkpoints_and_keeps_the_rest_restorable() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = CheckpointStorage::new(dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+
+        let a = save(
+            &storage,
+            "a",
+            None,
+            30,
+            &[("one.txt", Some("1")), ("two.txt", Some("2"))],
+        );
+        let b = save(&storage, "b", Some("a"), 20, &[("one.txt", Some("one"))]);
+        let c = save(&storage, "c", Some("b"), 1, &[("three.txt", Some("3"))]);
+        let expected = checkpoint_contents(&storage, "project", "session", "c").unwrap();
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            expired_checkpoints(&[c.clone(), a.clone(), b.clone()], &policy, Utc::now()),
+            vec!["a".to_string(), "b".to_string()]
+        );
+        let keep_two = RetentionPolicy {
+            keep_last: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            expired_checkpoints(&[a, b, c], &keep_two, Utc::now()),
+            vec!["a".to_string()]
+        );
+
+        let report = collect_garbage(&storage, &policy, Utc::now()).unwrap();
+        assert_eq!(report.removed_checkpoints, 2);
+        assert_eq!(report.before[0].checkpoints, 3);
+        assert_eq!(report.after[0].checkpoints, 1);
+        assert!(report.after[0].bytes < report.before[0].bytes);
+
+        // The surviving checkpoint inherited the files it didn't snapshot itself
+        assert_eq!(
+            checkpoint_contents(&storage, "project", "session", "c").unwrap(),
+            expected
+        );
+        let paths = CheckpointPaths::new(&dir.path().to_path_buf(), "project", "session");
+        let timeline = storage.load_timeline(&paths.timeline_file).unwrap();
+        let root = timeline.root_node.unwrap();
+        assert_eq!(root.checkpoint.id, "c");
+        assert_eq!(root.checkpoint.parent_checkpoint_id, None);
+        assert_eq!(timeline.total_checkpoints, 1);
+
+        // The current checkpoint is never removed
+        let everything = RetentionPolicy {
+            max_total_bytes: Some(0),
+            ..Default::default()
+        };
+        let report = collect_garbage(&storage, &everything, Utc::now()).unwrap();
+        assert_eq!(report.removed_checkpoints, 0);
+    }
+}
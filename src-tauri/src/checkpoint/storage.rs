@@ -2,17 +2,24 @@ use anyhow::{Context, Result};
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use uuid::Uuid;
 use zstd::stream::{decode_all, encode_all};
 
+use super::encryption::{self, CheckpointCipher};
+use super::git_backend;
 use super::{
-    Checkpoint, CheckpointPaths, CheckpointResult, FileSnapshot, SessionTimeline, TimelineNode,
+    Checkpoint, CheckpointBackend, CheckpointPaths, CheckpointResult, CheckpointValidation,
+    FileSnapshot, SessionTimeline, TimelineNode,
 };
+use crate::commands::claude::get_project_path_from_sessions;
 
 /// Manages checkpoint storage operations
 pub struct CheckpointStorage {
     pub claude_dir: PathBuf,
     compression_level: i32,
+    /// Key used instead of the keychain one; new data is always encrypted with it
+    cipher: Option<Arc<CheckpointCipher>>,
 }
 
 impl CheckpointStorage {
@@ -21,6 +28,44 @@ impl CheckpointStorage {
         Self {
             claude_dir,
             compression_level: 3, // Default zstd compression level
+            cipher: None,
+        }
+    }
+
+    /// Encrypt with the given cipher rather than the key in the keychain
+    pub fn with_cipher(mut self, cipher: Arc<CheckpointCipher>) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    fn encrypts(&self) -> bool {
+        self.cipher.is_some() || encryption::is_enabled()
+    }
+
+    fn cipher(&self, create: bool) -> Result<Arc<CheckpointCipher>> {
+        match &self.cipher {
+            Some(cipher) => Ok(Arc::clone(cipher)),
+            None => encryption::keychain_cipher(create),
+        }
+    }
+
+    /// Compress data for disk, encrypting it if encryption is on
+    fn seal(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let compressed = encode_all(data, self.compression_level).context("Failed to compress")?;
+        if self.encrypts() {
+            self.cipher(true)?.encrypt(&compressed)
+        } else {
+            Ok(compressed)
+        }
+    }
+
+    /// Read back data written by `seal`, whether or not it was encrypted
+    fn open(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if encryption::is_encrypted(data) {
+            let compressed = self.cipher(false)?.decrypt(data)?;
+            decode_all(&compressed[..]).context("Failed to decompress")
+        } else {
+            decode_all(data).context("Failed to decompress")
         }
     }
 
@@ -65,7 +110,8 @@ impl CheckpointStorage {
 
         // Save messages (compressed)
         let messages_path = paths.checkpoint_messages_file(&checkpoint.id);
-        let compressed_messages = encode_all(messages.as_bytes(), self.compression_level)
+        let compressed_messages = self
+            .seal(messages.as_bytes())
             .context("Failed to compress messages")?;
         fs::write(&messages_path, compressed_messages)
             .context("Failed to write compressed messages")?;
@@ -108,9 +154,9 @@ impl CheckpointStorage {
         // Only write the content if it doesn't already exist
         if !content_file.exists() {
             // Compress and save file content
-            let compressed_content =
-                encode_all(snapshot.content.as_bytes(), self.compression_level)
-                    .context("Failed to compress file content")?;
+            let compressed_content = self
+                .seal(snapshot.content.as_bytes())
+                .context("Failed to compress file content")?;
             fs::write(&content_file, compressed_content)
                 .context("Failed to write file content to pool")?;
         }
@@ -164,7 +210,8 @@ impl CheckpointStorage {
         let compressed_messages =
             fs::read(&messages_path).context("Failed to read compressed messages")?;
         let messages = String::from_utf8(
-            decode_all(&compressed_messages[..]).context("Failed to decompress messages")?,
+            self.open(&compressed_messages)
+                .context("Failed to decompress messages")?,
         )
         .context("Invalid UTF-8 in messages")?;
 
@@ -213,7 +260,7 @@ impl CheckpointStorage {
                 let compressed_content =
                     fs::read(&content_file).context("Failed to read file content from pool")?;
                 String::from_utf8(
-                    decode_all(&compressed_content[..])
+                    self.open(&compressed_content)
                         .context("Failed to decompress file content")?,
                 )
                 .context("Invalid UTF-8 in file content")?
@@ -237,6 +284,37 @@ impl CheckpointStorage {
         Ok(snapshots)
     }
 
+    /// Record the validation result of a checkpoint
+    pub fn save_validation(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+        validation: &CheckpointValidation,
+    ) -> Result<()> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        if !paths.checkpoint_dir(checkpoint_id).exists() {
+            anyhow::bail!("Checkpoint not found: {}", checkpoint_id);
+        }
+        fs::write(
+            paths.checkpoint_validation_file(checkpoint_id),
+            serde_json::to_string_pretty(validation)?,
+        )
+        .context("Failed to write checkpoint validation")
+    }
+
+    /// Load the validation result of a checkpoint, if it was validated
+    pub fn load_validation(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Option<CheckpointValidation> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let json = fs::read_to_string(paths.checkpoint_validation_file(checkpoint_id)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
     /// Save timeline to disk
     pub fn save_timeline(&self, timeline_path: &Path, timeline: &SessionTimeline) -> Result<()> {
         let timeline_json =
@@ -357,8 +435,9 @@ impl CheckpointStorage {
         let mut removed_count = 0;
 
         for checkpoint in all_checkpoints.into_iter().take(to_remove) {
-            if self.remove_checkpoint(&paths, &checkpoint.id).is_ok() {
-                removed_count += 1;
+            match self.prune_checkpoint(project_id, session_id, &checkpoint.id) {
+                Ok(()) => removed_count += 1,
+                Err(e) => log::debug!("Keeping checkpoint {}: {}", checkpoint.id, e),
             }
         }
 
@@ -377,8 +456,126 @@ impl CheckpointStorage {
         Ok(removed_count)
     }
 
+    /// Remove a checkpoint while keeping the rest of the session restorable.
+    /// Snapshots only hold the files that changed since the parent, so the file
+    /// references of the removed checkpoint are folded into each child that doesn't
+    /// override them, and the children are re-attached to its parent. The current
+    /// checkpoint and a root with several branches can't be removed.
+    pub fn prune_checkpoint(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<()> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let mut timeline = self.load_timeline(&paths.timeline_file)?;
+
+        if timeline.current_checkpoint_id.as_deref() == Some(checkpoint_id) {
+            anyhow::bail!("Checkpoint {} is the current checkpoint", checkpoint_id);
+        }
+        let node = timeline
+            .find_checkpoint(checkpoint_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Checkpoint not found: {}", checkpoint_id))?;
+        let parent_id = node.checkpoint.parent_checkpoint_id.clone();
+        if parent_id.is_none() && node.children.len() > 1 {
+            anyhow::bail!(
+                "Checkpoint {} is the root of several branches",
+                checkpoint_id
+            );
+        }
+
+        let refs_dir = paths.files_dir.join("refs");
+        let removed_refs = refs_dir.join(checkpoint_id);
+        for child in &node.children {
+            if removed_refs.exists() {
+                let child_refs = refs_dir.join(&child.checkpoint.id);
+                fs::create_dir_all(&child_refs)
+                    .context("Failed to create checkpoint refs directory")?;
+                for entry in fs::read_dir(&removed_refs)? {
+                    let entry = entry?;
+                    let target = child_refs.join(entry.file_name());
+                    if !target.exists() {
+                        fs::copy(entry.path(), &target).context("Failed to copy file reference")?;
+                    }
+                }
+            }
+
+            let mut checkpoint = child.checkpoint.clone();
+            checkpoint.parent_checkpoint_id = parent_id.clone();
+            fs::write(
+                paths.checkpoint_metadata_file(&checkpoint.id),
+                serde_json::to_string_pretty(&checkpoint)?,
+            )
+            .context("Failed to write checkpoint metadata")?;
+        }
+
+        match &mut timeline.root_node {
+            Some(root) if root.checkpoint.id == checkpoint_id => {
+                let mut new_root = root.children.pop();
+                if let Some(new_root) = &mut new_root {
+                    new_root.checkpoint.parent_checkpoint_id = None;
+                }
+                timeline.root_node = new_root;
+            }
+            Some(root) => {
+                Self::splice_out_node(root, checkpoint_id);
+            }
+            None => {}
+        }
+        timeline.total_checkpoints = timeline.total_checkpoints.saturating_sub(1);
+        self.save_timeline(&paths.timeline_file, &timeline)?;
+
+        self.remove_checkpoint(&paths, checkpoint_id)?;
+        if timeline.backend == CheckpointBackend::Git {
+            self.delete_git_ref(project_id, session_id, checkpoint_id);
+        }
+        Ok(())
+    }
+
+    /// Drop a pruned checkpoint's ref from the project's repository, so the
+    /// retention policy frees its git objects too
+    fn delete_git_ref(&self, project_id: &str, session_id: &str, checkpoint_id: &str) {
+        let project_dir = self.claude_dir.join("projects").join(project_id);
+        let deleted = get_project_path_from_sessions(&project_dir)
+            .map_err(anyhow::Error::msg)
+            .and_then(|project_path| {
+                git_backend::delete_checkpoint_ref(
+                    Path::new(&project_path),
+                    session_id,
+                    checkpoint_id,
+                )
+            });
+        if let Err(e) = deleted {
+            log::warn!(
+                "Failed to delete the git ref of checkpoint {}: {}",
+                checkpoint_id,
+                e
+            );
+        }
+    }
+
+    /// Replace a node in the tree with its children
+    fn splice_out_node(node: &mut TimelineNode, checkpoint_id: &str) -> bool {
+        if let Some(index) = node
+            .children
+            .iter()
+            .position(|child| child.checkpoint.id == checkpoint_id)
+        {
+            let removed = node.children.remove(index);
+            for (offset, mut child) in removed.children.into_iter().enumerate() {
+                child.checkpoint.parent_checkpoint_id = Some(node.checkpoint.id.clone());
+                node.children.insert(index + offset, child);
+            }
+            return true;
+        }
+        node.children
+            .iter_mut()
+            .any(|child| Self::splice_out_node(child, checkpoint_id))
+    }
+
     /// Collect all checkpoints from the tree in order
-    fn collect_checkpoints(node: &TimelineNode, checkpoints: &mut Vec<Checkpoint>) {
+    pub fn collect_checkpoints(node: &TimelineNode, checkpoints: &mut Vec<Checkpoint>) {
         checkpoints.push(node.checkpoint.clone());
         for child in &node.children {
             Self::collect_checkpoints(child, checkpoints);
@@ -457,4 +654,59 @@ impl CheckpointStorage {
 
         Ok(removed_count)
     }
+
+    /// Rewrite a sealed file if its encryption doesn't match the current setting
+    fn reseal_file(&self, path: &Path) -> Result<bool> {
+        let data = fs::read(path)?;
+        if encryption::is_encrypted(&data) == self.encrypts() {
+            return Ok(false);
+        }
+        let resealed = self.seal(&self.open(&data)?)?;
+
+        // Write next to the original and swap, so an interrupted run loses nothing
+        let temp_path = path.with_extension("reseal");
+        fs::write(&temp_path, resealed)?;
+        fs::rename(&temp_path, path)?;
+        Ok(true)
+    }
+
+    /// Encrypt or decrypt the messages and file contents of every stored
+    /// checkpoint to match the current setting, returning how many files changed
+    pub fn reseal_all(&self) -> Result<usize> {
+        let mut resealed = 0;
+        let Ok(projects) = fs::read_dir(self.claude_dir.join("projects")) else {
+            return Ok(0);
+        };
+        for project in projects.filter_map(|entry| entry.ok()) {
+            let project_id = project.file_name().to_string_lossy().to_string();
+            let Ok(sessions) = fs::read_dir(project.path().join(".timelines")) else {
+                continue;
+            };
+            for session in sessions.filter_map(|entry| entry.ok()) {
+                let session_id = session.file_name().to_string_lossy().to_string();
+                let paths = CheckpointPaths::new(&self.claude_dir, &project_id, &session_id);
+
+                let mut files = Vec::new();
+                if let Ok(checkpoints) = fs::read_dir(&paths.checkpoints_dir) {
+                    files.extend(checkpoints.filter_map(|entry| {
+                        let checkpoint_id = entry.ok()?.file_name();
+                        Some(paths.checkpoint_messages_file(&checkpoint_id.to_string_lossy()))
+                    }));
+                }
+                if let Ok(pool) = fs::read_dir(paths.files_dir.join("content_pool")) {
+                    files.extend(pool.filter_map(|entry| Some(entry.ok()?.path())));
+                }
+
+                for file in files.iter().filter(|file| file.is_file()) {
+                    if self
+                        .reseal_file(file)
+                        .with_context(|| format!("Failed to reseal {}", file.display()))?
+                    {
+                        resealed += 1;
+                    }
+                }
+            }
+        }
+        Ok(resealed)
+    }
 }
@@ -43,13 +43,22 @@ pub struct ClaudeInstallation {
 /// Main function to find the Claude binary
 /// Checks database first for stored path and preference, then prioritizes accordingly
 pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, String> {
+    let db_path = app_handle
+        .path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join("agents.db"));
+    find_claude_binary_with_db(db_path.as_deref())
+}
+
+/// Find the claude binary, preferring the path stored in the given agents database
+pub fn find_claude_binary_with_db(db_path: Option<&std::path::Path>) -> Result<String, String> {
     info!("Searching for claude binary...");
 
     // First check if we have a stored path and preference in the database
-    if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
-        let db_path = app_data_dir.join("agents.db");
+    if let Some(db_path) = db_path {
         if db_path.exists() {
-            if let Ok(conn) = rusqlite::Connection::open(&db_path) {
+            if let Ok(conn) = rusqlite::Connection::open(db_path) {
                 // Check for stored path first
                 if let Ok(stored_path) = conn.query_row(
                     "SELECT value FROM app_settings WHERE key = 'claude_binary_path'",
@@ -0,0 +1,514 @@
+use clap::{Parser, Subcommand};
+use rusqlite::{params, Connection};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use opcode_lib::claude_binary::{create_command_with_env, find_claude_binary_with_db};
+use opcode_lib::commands::agent_env::load_agent_env;
+use opcode_lib::commands::agents::{
+    build_agent_args, load_agent, load_run_launch_config, open_database, queue_agent_run,
+    write_agent_hooks, Agent,
+};
+use opcode_lib::commands::artifacts::{capture_run_artifacts, parse_declared_artifacts};
+use opcode_lib::commands::budgets::{load_agent_budget, RunUsageTracker};
+use opcode_lib::commands::cancellation::grace_period;
+use opcode_lib::commands::metrics::store_run_usage;
+use opcode_lib::commands::pricing::load_pricing_settings;
+use opcode_lib::commands::retries::RetryCondition;
+use opcode_lib::commands::run_diffs::{capture_run_diff, record_run_base, snapshot_worktree};
+use opcode_lib::commands::run_search::index_finished_run;
+use opcode_lib::commands::structured_output::store_structured_output;
+use opcode_lib::process::{normalize_project_path, own_std_process_group, ProcessTree};
+
+#[derive(Parser)]
+#[command(name = "opcode-cli")]
+#[command(about = "Opcode CLI - Run agents headlessly from scripts and CI")]
+struct Args {
+    /// Path to agents.db (defaults to the one used by the Opcode app)
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// List agents
+    Agents {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// List recent agent runs
+    Runs {
+        /// Only show runs of this agent (ID or name)
+        #[arg(short, long)]
+        agent: Option<String>,
+        #[arg(short, long, default_value = "20")]
+        limit: u32,
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run an agent and stream its output
+    Run {
+        /// Agent ID or name
+        agent: String,
+        /// Project directory to run in
+        #[arg(short, long, default_value = ".")]
+        project: PathBuf,
+        /// Task for the agent (defaults to the agent's default task)
+        #[arg(short, long)]
+        task: Option<String>,
+        /// Model override
+        #[arg(short, long)]
+        model: Option<String>,
+        /// Template parameter as name=value (repeatable)
+        #[arg(long = "param", value_name = "NAME=VALUE")]
+        params: Vec<String>,
+        /// Print the raw stream-json output
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// agents.db of the desktop app, so the CLI sees the same agents and run history
+fn default_db_path() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|dir| dir.join("opcode.asterisk.so").join("agents.db"))
+        .ok_or_else(|| "Could not determine the data directory; pass --db".to_string())
+}
+
+/// Find an agent by ID or, failing that, by case-insensitive name
+fn find_agent(conn: &Connection, agent: &str) -> Result<Agent, String> {
+    if let Ok(id) = agent.parse::<i64>() {
+        return load_agent(conn, id);
+    }
+
+    let id: i64 = conn
+        .query_row(
+            "SELECT id FROM agents WHERE name = ?1 COLLATE NOCASE ORDER BY id LIMIT 1",
+            params![agent],
+            |row| row.get(0),
+        )
+        .map_err(|_| format!("Agent '{}' not found", agent))?;
+    load_agent(conn, id)
+}
+
+fn list_agents(conn: &Connection, json: bool) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM agents ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let agents = ids
+        .into_iter()
+        .map(|id| load_agent(conn, id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&agents).map_err(|e| e.to_string())?
+        );
+        return Ok(());
+    }
+
+    for agent in agents {
+        println!(
+            "{:>5}  {:<30}  {}",
+            agent.id.unwrap_or_default(),
+            agent.name,
+            agent.model
+        );
+    }
+    Ok(())
+}
+
+fn list_runs(conn: &Connection, agent: Option<&str>, limit: u32, json: bool) -> Result<(), String> {
+    let agent_id = agent
+        .map(|agent| find_agent(conn, agent).and_then(|a| a.id.ok_or("Agent has no id".into())))
+        .transpose()?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, agent_name, status, model, project_path, created_at, completed_at
+             FROM agent_runs WHERE ?1 IS NULL OR agent_id = ?1 ORDER BY id DESC LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let runs: Vec<JsonValue> = stmt
+        .query_map(params![agent_id, limit], |row| {
+            Ok(serde_json::json!({
+                "id": row.get::<_, i64>(0)?,
+                "agent_name": row.get::<_, String>(1)?,
+                "status": row.get::<_, String>(2)?,
+                "model": row.get::<_, String>(3)?,
+                "project_path": row.get::<_, String>(4)?,
+                "created_at": row.get::<_, String>(5)?,
+                "completed_at": row.get::<_, Option<String>>(6)?,
+            }))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&runs).map_err(|e| e.to_string())?
+        );
+        return Ok(());
+    }
+
+    for run in runs {
+        let field = |key: &str| run[key].as_str().unwrap_or_default().to_string();
+        println!(
+            "{:>6}  {:<20}  {:<15}  {:<20}  {}",
+            run["id"],
+            field("agent_name"),
+            field("status"),
+            field("created_at"),
+            field("project_path")
+        );
+    }
+    Ok(())
+}
+
+/// Render one line of stream-json output for a terminal; None for lines not worth showing
+fn render_stream_line(json: &JsonValue) -> Option<String> {
+    match json.get("type").and_then(|t| t.as_str()) {
+        Some("system") if json.get("subtype").and_then(|s| s.as_str()) == Some("init") => json
+            .get("session_id")
+            .and_then(|s| s.as_str())
+            .map(|sid| format!("[session {}]", sid)),
+        Some("assistant") => {
+            let blocks = json.get("message")?.get("content")?.as_array()?;
+            let lines: Vec<String> = blocks
+                .iter()
+                .filter_map(|block| match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => block.get("text").and_then(|t| t.as_str()).map(String::from),
+                    Some("tool_use") => {
+                        let name = block.get("name").and_then(|n| n.as_str()).unwrap_or("tool");
+                        let input = block
+                            .get("input")
+                            .map(|i| i.to_string())
+                            .unwrap_or_default();
+                        let input: String = input.chars().take(120).collect();
+                        Some(format!("→ {} {}", name, input))
+                    }
+                    _ => None,
+                })
+                .collect();
+            (!lines.is_empty()).then(|| lines.join("\n"))
+        }
+        Some("result") => {
+            let failed = json.get("is_error").and_then(|e| e.as_bool()) == Some(true);
+            let mut summary = String::from(if failed { "✗ Failed" } else { "✓ Done" });
+            if let Some(ms) = json.get("duration_ms").and_then(|d| d.as_u64()) {
+                summary.push_str(&format!(" in {:.1}s", ms as f64 / 1000.0));
+            }
+            if let Some(cost) = json.get("total_cost_usd").and_then(|c| c.as_f64()) {
+                summary.push_str(&format!(" (${:.4})", cost));
+            }
+            Some(summary)
+        }
+        _ => None,
+    }
+}
+
+/// Run an agent in the foreground, recording it in agents.db like a run started from the app.
+///
+/// Returns whether the run completed successfully.
+#[allow(clippy::too_many_arguments)]
+fn run_agent(
+    conn: &mut Connection,
+    db_path: &Path,
+    agent: &str,
+    project: &Path,
+    task: Option<String>,
+    model: Option<String>,
+    params: Vec<String>,
+    json: bool,
+) -> Result<bool, String> {
+    let agent = find_agent(conn, agent)?;
+    let agent_id = agent.id.ok_or("Agent has no id")?;
+    let project = project
+        .canonicalize()
+        .map_err(|e| format!("Project directory not found: {}", e))?;
+    let project_path = normalize_project_path(&project.to_string_lossy());
+    let task = task
+        .or_else(|| agent.default_task.clone())
+        .ok_or("No task given and the agent has no default task")?;
+    let model = model.unwrap_or_else(|| agent.model.clone());
+
+    let mut parameters = HashMap::new();
+    for param in &params {
+        let (name, value) = param
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid parameter '{}', expected NAME=VALUE", param))?;
+        parameters.insert(
+            name.trim().to_string(),
+            JsonValue::String(value.to_string()),
+        );
+    }
+
+    // Claim the run straight away so the app's queue dispatcher never launches it too
+    let run_id = {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let run_id = queue_agent_run(&tx, &agent, &project_path, &task, &model, Some(parameters))?;
+        tx.execute(
+            "UPDATE agent_runs SET status = 'running', queue_position = NULL WHERE id = ?1",
+            params![run_id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        run_id
+    };
+
+    let (system_prompt, read_only, permission_profile) =
+        load_run_launch_config(conn, run_id, &agent)?;
+    if let Some(hooks_json) = agent.hooks.as_ref().filter(|_| !read_only) {
+        write_agent_hooks(&project_path, hooks_json)?;
+    }
+    let claude_path = find_claude_binary_with_db(Some(db_path))?;
+    // The stored task has its {{placeholders}} filled in
+    let task: String = conn
+        .query_row(
+            "SELECT task FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let args = build_agent_args(
+        &task,
+        system_prompt,
+        &model,
+        permission_profile.as_ref(),
+        read_only,
+    );
+    let agent_env = load_agent_env(conn, agent_id)?.resolve()?;
+    let budget = load_agent_budget(conn, agent_id)?;
+    if let Some(base_tree) = snapshot_worktree(Path::new(&project_path)) {
+        record_run_base(conn, run_id, &base_tree);
+    }
+
+    let mut cmd = create_command_with_env(&claude_path);
+    cmd.envs(agent_env)
+        .args(args)
+        .current_dir(&project_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+    own_std_process_group(&mut cmd);
+
+    let started_at = std::time::SystemTime::now();
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = conn.execute(
+                "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                params![run_id],
+            );
+            return Err(format!("Failed to spawn Claude: {}", e));
+        }
+    };
+    conn.execute(
+        "UPDATE agent_runs SET pid = ?1, process_started_at = ?2 WHERE id = ?3",
+        params![child.id() as i64, chrono::Utc::now().to_rfc3339(), run_id],
+    )
+    .map_err(|e| e.to_string())?;
+    // Claude's tool commands are stopped along with it
+    let tree = ProcessTree::attach(child.id());
+    if !json {
+        eprintln!("Run {}: {} in {}", run_id, agent.name, project_path);
+    }
+
+    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
+    let mut output = String::new();
+    let mut session_id = String::new();
+    let mut error_result = false;
+    let mut over_budget = None;
+    let mut declared_artifacts = Vec::new();
+    let mut usage = RunUsageTracker::default();
+
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else { break };
+        output.push_str(&line);
+        output.push('\n');
+        if json {
+            println!("{}", line);
+        }
+
+        let Ok(message) = serde_json::from_str::<JsonValue>(&line) else {
+            continue;
+        };
+        if !json {
+            if let Some(rendered) = render_stream_line(&message) {
+                println!("{}", rendered);
+            }
+        }
+
+        match message.get("type").and_then(|t| t.as_str()) {
+            Some("system") if session_id.is_empty() => {
+                if let Some(sid) = message.get("session_id").and_then(|s| s.as_str()) {
+                    session_id = sid.to_string();
+                    let _ = conn.execute(
+                        "UPDATE agent_runs SET session_id = ?1 WHERE id = ?2",
+                        params![session_id, run_id],
+                    );
+                }
+            }
+            Some("result") => {
+                error_result |= message.get("is_error").and_then(|e| e.as_bool()) == Some(true);
+                if let Some(result) = message.get("result").and_then(|r| r.as_str()) {
+                    declared_artifacts.extend(parse_declared_artifacts(result));
+                }
+            }
+            _ => {}
+        }
+
+        usage.record(&message);
+        if let Some(reason) = usage.exceeded(&budget) {
+            eprintln!("Stopping run {}: {}", run_id, reason);
+            stop_run(&mut child, tree.as_ref(), grace_period(conn));
+            over_budget = Some(reason);
+            break;
+        }
+    }
+
+    let exit_status = child.wait().map_err(|e| e.to_string())?;
+    let (status, failure_reason) = if let Some(reason) = over_budget {
+        ("budget_exceeded", Some(reason))
+    } else if error_result {
+        (
+            "failed",
+            Some(RetryCondition::ErrorResult.as_str().to_string()),
+        )
+    } else if !exit_status.success() {
+        (
+            "failed",
+            Some(RetryCondition::NonZeroExit.as_str().to_string()),
+        )
+    } else {
+        ("completed", None)
+    };
+    conn.execute(
+        "UPDATE agent_runs SET session_id = ?1, status = ?2, failure_reason = ?3, completed_at = CURRENT_TIMESTAMP WHERE id = ?4",
+        params![session_id, status, failure_reason, run_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    store_run_usage(conn, run_id, &usage);
+    store_structured_output(conn, run_id, &output);
+    capture_run_diff(db_path, run_id);
+    if let Some(app_data_dir) = db_path.parent() {
+        capture_run_artifacts(app_data_dir, run_id, started_at, &declared_artifacts);
+    }
+    index_finished_run(db_path, run_id, &output);
+
+    if !json {
+        eprintln!("Run {} {}", run_id, status);
+    }
+    Ok(status == "completed")
+}
+
+/// Interrupt a run and everything it started, then kill whatever is left once
+/// the grace period is over, as cancelling it in the app does
+fn stop_run(child: &mut std::process::Child, tree: Option<&ProcessTree>, grace: Duration) {
+    if !grace.is_zero() && tree.is_some_and(|tree| tree.interrupt()) {
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline {
+            if matches!(child.try_wait(), Ok(Some(_))) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+    match tree {
+        Some(tree) => {
+            tree.kill();
+        }
+        None => {
+            let _ = child.kill();
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Args::parse();
+
+    let result = args
+        .db
+        .map(Ok)
+        .unwrap_or_else(default_db_path)
+        .and_then(|db_path| {
+            let mut conn = open_database(&db_path)
+                .map_err(|e| format!("Failed to open {}: {}", db_path.display(), e))?;
+            // Budgets and recorded costs use the user's prices, as in the app
+            load_pricing_settings(&conn);
+            match args.command {
+                CliCommand::Agents { json } => list_agents(&conn, json).map(|_| true),
+                CliCommand::Runs { agent, limit, json } => {
+                    list_runs(&conn, agent.as_deref(), limit, json).map(|_| true)
+                }
+                CliCommand::Run {
+                    agent,
+                    project,
+                    task,
+                    model,
+                    params,
+                    json,
+                } => run_agent(
+                    &mut conn, &db_path, &agent, &project, task, model, params, json,
+                ),
+            }
+        });
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_stream_line() {
+        let assistant: JsonValue = serde_json::from_str(
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Fixing the test"},{"type":"tool_use","name":"Bash","input":{"command":"cargo test"}}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            render_stream_line(&assistant).as_deref(),
+            Some("Fixing the test\n→ Bash {\"command\":\"cargo test\"}")
+        );
+
+        let result: JsonValue = serde_json::from_str(
+            r#"{"type":"result","is_error":false,"duration_ms":1500,"total_cost_usd":0.0123}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            render_stream_line(&result).as_deref(),
+            Some("✓ Done in 1.5s ($0.0123)")
+        );
+
+        let user: JsonValue = serde_json::from_str(r#"{"type":"user"}"#).unwrap();
+        assert_eq!(render_stream_line(&user), None);
+    }
+}
@@ -0,0 +1,251 @@
+use log::warn;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::keychain;
+
+/// An environment variable injected into an agent's Claude process
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentEnvVar {
+    pub name: String,
+    /// Plain value; always None for secrets, which never leave the keychain
+    pub value: Option<String>,
+    pub secret: bool,
+}
+
+/// Whether a name is a valid environment variable name
+pub fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Keychain entry holding one agent secret
+fn secret_entry(agent_id: i64, name: &str) -> Result<keyring::Entry, String> {
+    keychain::entry(&format!("agent-{}-{}", agent_id, name))
+        .map_err(|e| format!("Failed to open keychain entry: {}", e))
+}
+
+fn delete_secret(agent_id: i64, name: &str) -> Result<(), String> {
+    keychain::delete_secret(&format!("agent-{}-{}", agent_id, name))
+        .map_err(|e| format!("Failed to remove secret {} from keychain: {}", name, e))
+}
+
+/// An agent's variables as stored. They are read while the database is
+/// locked and their secrets from the keychain once it isn't.
+pub struct StoredAgentEnv {
+    agent_id: i64,
+    /// (name, plain value, whether it's a secret)
+    vars: Vec<(String, Option<String>, bool)>,
+}
+
+/// Read an agent's variables
+pub fn load_agent_env(conn: &Connection, agent_id: i64) -> Result<StoredAgentEnv, String> {
+    let vars = conn
+        .prepare(
+            "SELECT name, value, is_secret FROM agent_env_vars WHERE agent_id = ?1 ORDER BY name",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map(params![agent_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect()
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(StoredAgentEnv { agent_id, vars })
+}
+
+impl StoredAgentEnv {
+    /// The values to set on the agent's process, reading secrets from the keychain
+    pub fn resolve(self) -> Result<Vec<(String, String)>, String> {
+        let agent_id = self.agent_id;
+        self.vars
+            .into_iter()
+            .map(|(name, value, secret)| {
+                let value = if secret {
+                    secret_entry(agent_id, &name)?.get_password().map_err(|e| {
+                        format!("Failed to read secret {} from keychain: {}", name, e)
+                    })?
+                } else {
+                    value.unwrap_or_default()
+                };
+                Ok((name, value))
+            })
+            .collect()
+    }
+
+    /// Remove the agent's secrets from the keychain
+    pub fn delete_secrets(&self) {
+        for (name, _, _) in self.vars.iter().filter(|(_, _, secret)| *secret) {
+            if let Err(e) = delete_secret(self.agent_id, name) {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    /// Copy the variables to another agent, giving it its own keychain copy
+    /// of each secret. Secrets that can't be read from the keychain are skipped.
+    pub fn copy_to(self, db: &AgentDb, to_agent_id: i64) {
+        let agent_id = self.agent_id;
+        let copied: Vec<_> = self
+            .vars
+            .into_iter()
+            .filter(|(name, _, secret)| {
+                if !secret {
+                    return true;
+                }
+                let copied = secret_entry(agent_id, name)
+                    .and_then(|entry| entry.get_password().map_err(|e| e.to_string()))
+                    .and_then(|password| {
+                        secret_entry(to_agent_id, name)?
+                            .set_password(&password)
+                            .map_err(|e| e.to_string())
+                    });
+                if let Err(e) = &copied {
+                    warn!(
+                        "Failed to copy secret {} to agent {}: {}",
+                        name, to_agent_id, e
+                    );
+                }
+                copied.is_ok()
+            })
+            .collect();
+
+        let Ok(conn) = db.0.lock() else {
+            return;
+        };
+        for (name, value, secret) in copied {
+            if let Err(e) = conn.execute(
+                "INSERT OR REPLACE INTO agent_env_vars (agent_id, name, value, is_secret) VALUES (?1, ?2, ?3, ?4)",
+                params![to_agent_id, name, value, secret],
+            ) {
+                warn!("Failed to copy environment variable {}: {}", name, e);
+            }
+        }
+    }
+}
+
+/// List the environment variables configured for an agent (secret values are withheld)
+#[tauri::command]
+pub async fn get_agent_env(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<Vec<AgentEnvVar>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT name, value, is_secret FROM agent_env_vars WHERE agent_id = ?1 ORDER BY name",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let vars = stmt
+        .query_map(params![agent_id], |row| {
+            let secret: bool = row.get(2)?;
+            Ok(AgentEnvVar {
+                name: row.get(0)?,
+                value: if secret { None } else { row.get(1)? },
+                secret,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(vars)
+}
+
+/// Set an environment variable for an agent's runs.
+///
+/// Secrets are written to the OS keychain and only referenced from the database.
+#[tauri::command]
+pub async fn set_agent_env_var(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    name: String,
+    value: String,
+    secret: bool,
+) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if !is_valid_env_name(&name) {
+        return Err(format!("Invalid environment variable name '{}'", name));
+    }
+
+    // The keychain is written before the database is locked, and a secret
+    // switched to a plain value is removed from it after
+    if secret {
+        secret_entry(agent_id, &name)?
+            .set_password(&value)
+            .map_err(|e| format!("Failed to store secret in keychain: {}", e))?;
+    }
+    let was_secret = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let was_secret: bool = conn
+            .query_row(
+                "SELECT is_secret FROM agent_env_vars WHERE agent_id = ?1 AND name = ?2",
+                params![agent_id, name],
+                |row| row.get(0),
+            )
+            .unwrap_or(false);
+        conn.execute(
+            "INSERT OR REPLACE INTO agent_env_vars (agent_id, name, value, is_secret) VALUES (?1, ?2, ?3, ?4)",
+            params![agent_id, name, (!secret).then_some(value), secret],
+        )
+        .map_err(|e| format!("Failed to save environment variable: {}", e))?;
+        was_secret
+    };
+    if was_secret && !secret {
+        delete_secret(agent_id, &name)?;
+    }
+
+    Ok(())
+}
+
+/// Remove an environment variable (and its keychain secret) from an agent
+#[tauri::command]
+pub async fn delete_agent_env_var(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    name: String,
+) -> Result<(), String> {
+    let secret = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let secret: bool = conn
+            .query_row(
+                "SELECT is_secret FROM agent_env_vars WHERE agent_id = ?1 AND name = ?2",
+                params![agent_id, name],
+                |row| row.get(0),
+            )
+            .unwrap_or(false);
+        conn.execute(
+            "DELETE FROM agent_env_vars WHERE agent_id = ?1 AND name = ?2",
+            params![agent_id, name],
+        )
+        .map_err(|e| e.to_string())?;
+        secret
+    };
+    if secret {
+        delete_secret(agent_id, &name)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_env_name() {
+        assert!(is_valid_env_name("DEPLOY_TOKEN"));
+        assert!(is_valid_env_name("_private1"));
+        assert!(!is_valid_env_name(""));
+        assert!(!is_valid_env_name("1ST"));
+        assert!(!is_valid_env_name("MY-VAR"));
+        assert!(!is_valid_env_name("A=B"));
+    }
+}
@@ -0,0 +1,226 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Type of a declared agent parameter
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterType {
+    String,
+    Number,
+    Boolean,
+}
+
+/// A `{{name}}` placeholder an agent's prompts expect to be filled in
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub param_type: ParameterType,
+    /// Used when a run doesn't supply a value; parameters without one are required
+    pub default: Option<JsonValue>,
+    pub description: Option<String>,
+}
+
+/// Check that a value matches the declared type, accepting strings that parse as it
+fn coerce_value(param: &AgentParameter, value: &JsonValue) -> Result<String, String> {
+    let mismatch = || {
+        format!(
+            "Parameter '{}' expects a {:?} value, got {}",
+            param.name, param.param_type, value
+        )
+    };
+
+    match (param.param_type, value) {
+        (ParameterType::String, JsonValue::String(s)) => Ok(s.clone()),
+        (ParameterType::String, JsonValue::Number(n)) => Ok(n.to_string()),
+        (ParameterType::String, JsonValue::Bool(b)) => Ok(b.to_string()),
+        (ParameterType::Number, JsonValue::Number(n)) => Ok(n.to_string()),
+        (ParameterType::Number, JsonValue::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .map(|_| s.trim().to_string())
+            .map_err(|_| mismatch()),
+        (ParameterType::Boolean, JsonValue::Bool(b)) => Ok(b.to_string()),
+        (ParameterType::Boolean, JsonValue::String(s)) => match s.trim() {
+            "true" | "false" => Ok(s.trim().to_string()),
+            _ => Err(mismatch()),
+        },
+        _ => Err(mismatch()),
+    }
+}
+
+/// Resolve the values for a run from the declared schema and the supplied map.
+///
+/// Declared parameters are type-checked and fall back to their defaults. Extra
+/// values that aren't declared are passed through as-is.
+pub fn resolve_parameters(
+    schema: &[AgentParameter],
+    supplied: &HashMap<String, JsonValue>,
+) -> Result<HashMap<String, String>, String> {
+    let mut resolved = HashMap::new();
+
+    for param in schema {
+        let value = supplied
+            .get(&param.name)
+            .filter(|v| !v.is_null())
+            .or(param.default.as_ref())
+            .ok_or_else(|| format!("Missing value for required parameter '{}'", param.name))?;
+        resolved.insert(param.name.clone(), coerce_value(param, value)?);
+    }
+
+    for (name, value) in supplied {
+        if !resolved.contains_key(name) {
+            let value = match value {
+                JsonValue::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            resolved.insert(name.clone(), value);
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Replace `{{name}}` placeholders with resolved values; unknown placeholders are left alone
+pub fn render_prompt(template: &str, values: &HashMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match values.get(name) {
+                    Some(value) => rendered.push_str(value),
+                    None => rendered.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                rendered.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+/// Load an agent's declared parameters
+pub fn load_agent_parameters(
+    conn: &Connection,
+    agent_id: i64,
+) -> Result<Vec<AgentParameter>, String> {
+    let parameters: Option<String> = conn
+        .query_row(
+            "SELECT parameters FROM agent_parameter_schemas WHERE agent_id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    match parameters {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid parameter schema for agent {}: {}", agent_id, e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Get the parameters declared for an agent
+#[tauri::command]
+pub async fn get_agent_parameters(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<Vec<AgentParameter>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_agent_parameters(&conn, agent_id)
+}
+
+/// Declare the parameters an agent's system prompt and default task use
+#[tauri::command]
+pub async fn set_agent_parameters(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    parameters: Vec<AgentParameter>,
+) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for param in &parameters {
+        let name = param.name.trim();
+        if name.is_empty() || name.contains("{{") || name.contains("}}") {
+            return Err(format!("Invalid parameter name '{}'", param.name));
+        }
+        if !seen.insert(name.to_string()) {
+            return Err(format!("Duplicate parameter '{}'", name));
+        }
+        if let Some(default) = &param.default {
+            coerce_value(param, default)?;
+        }
+    }
+
+    let json = serde_json::to_string(&parameters).map_err(|e| e.to_string())?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_parameter_schemas (agent_id, parameters) VALUES (?1, ?2)",
+        params![agent_id, json],
+    )
+    .map_err(|e| format!("Failed to save agent parameters: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn param(name: &str, param_type: ParameterType, default: Option<JsonValue>) -> AgentParameter {
+        AgentParameter {
+            name: name.to_string(),
+            param_type,
+            default,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_parameters_applies_defaults_and_types() {
+        let schema = vec![
+            param("branch", ParameterType::String, Some(json!("main"))),
+            param("depth", ParameterType::Number, None),
+            param("strict", ParameterType::Boolean, Some(json!(false))),
+        ];
+
+        let mut supplied = HashMap::new();
+        supplied.insert("depth".to_string(), json!("3"));
+        supplied.insert("extra".to_string(), json!("x"));
+        let resolved = resolve_parameters(&schema, &supplied).unwrap();
+        assert_eq!(resolved["branch"], "main");
+        assert_eq!(resolved["depth"], "3");
+        assert_eq!(resolved["strict"], "false");
+        assert_eq!(resolved["extra"], "x");
+
+        assert!(resolve_parameters(&schema, &HashMap::new()).is_err());
+        supplied.insert("depth".to_string(), json!("deep"));
+        assert!(resolve_parameters(&schema, &supplied).is_err());
+    }
+
+    #[test]
+    fn test_render_prompt_leaves_unknown_placeholders() {
+        let mut values = HashMap::new();
+        values.insert("name".to_string(), "opcode".to_string());
+        assert_eq!(
+            render_prompt("Hello {{ name }}, {{missing}} {{", &values),
+            "Hello opcode, {{missing}} {{"
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::{load_agent, Agent, AgentDb};
+
+const REVISION_COLUMNS: &str = "id, agent_id, revision, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at";
+
+/// A saved version of an agent's definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRevision {
+    pub id: i64,
+    pub agent_id: i64,
+    /// Revision number, counting up from 1 per agent
+    pub revision: i64,
+    pub name: String,
+    pub icon: String,
+    pub system_prompt: String,
+    pub default_task: Option<String>,
+    pub model: String,
+    pub enable_file_read: bool,
+    pub enable_file_write: bool,
+    pub enable_network: bool,
+    pub hooks: Option<String>,
+    pub created_at: String,
+}
+
+/// One line of a text diff
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "text", rename_all = "snake_case")]
+pub enum DiffLine {
+    Unchanged(String),
+    Added(String),
+    Removed(String),
+}
+
+/// What changed between two revisions of an agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRevisionDiff {
+    /// None when `to` is the agent's first revision
+    pub from: Option<AgentRevision>,
+    pub to: AgentRevision,
+    pub changed_fields: Vec<String>,
+    pub system_prompt: Vec<DiffLine>,
+    pub default_task: Vec<DiffLine>,
+}
+
+fn row_to_revision(row: &Row) -> rusqlite::Result<AgentRevision> {
+    Ok(AgentRevision {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        revision: row.get(2)?,
+        name: row.get(3)?,
+        icon: row.get(4)?,
+        system_prompt: row.get(5)?,
+        default_task: row.get(6)?,
+        model: row.get(7)?,
+        enable_file_read: row.get(8)?,
+        enable_file_write: row.get(9)?,
+        enable_network: row.get(10)?,
+        hooks: row.get(11)?,
+        created_at: row.get(12)?,
+    })
+}
+
+fn load_revision(
+    conn: &Connection,
+    agent_id: i64,
+    revision: i64,
+) -> Result<Option<AgentRevision>, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM agent_revisions WHERE agent_id = ?1 AND revision = ?2",
+            REVISION_COLUMNS
+        ),
+        params![agent_id, revision],
+        row_to_revision,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn latest_revision(conn: &Connection, agent_id: i64) -> Result<Option<AgentRevision>, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM agent_revisions WHERE agent_id = ?1 ORDER BY revision DESC LIMIT 1",
+            REVISION_COLUMNS
+        ),
+        params![agent_id],
+        row_to_revision,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Names of the agent fields that differ between two revisions
+fn changed_fields(from: &AgentRevision, to: &AgentRevision) -> Vec<String> {
+    let fields = [
+        ("name", from.name != to.name),
+        ("icon", from.icon != to.icon),
+        ("system_prompt", from.system_prompt != to.system_prompt),
+        ("default_task", from.default_task != to.default_task),
+        ("model", from.model != to.model),
+        (
+            "enable_file_read",
+            from.enable_file_read != to.enable_file_read,
+        ),
+        (
+            "enable_file_write",
+            from.enable_file_write != to.enable_file_write,
+        ),
+        ("enable_network", from.enable_network != to.enable_network),
+        ("hooks", from.hooks != to.hooks),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+fn matches_agent(revision: &AgentRevision, agent: &Agent) -> bool {
+    revision.name == agent.name
+        && revision.icon == agent.icon
+        && revision.system_prompt == agent.system_prompt
+        && revision.default_task == agent.default_task
+        && revision.model == agent.model
+        && revision.enable_file_read == agent.enable_file_read
+        && revision.enable_file_write == agent.enable_file_write
+        && revision.enable_network == agent.enable_network
+        && revision.hooks == agent.hooks
+}
+
+/// Line diff of two texts, based on their longest common subsequence of lines
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    diff_items(&old, &new)
+}
+
+/// Diff of two sequences of texts, based on their longest common subsequence
+pub fn diff_items(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    // lcs[i][j] = length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Unchanged(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff.extend(old[i..].iter().map(|l| DiffLine::Removed(l.to_string())));
+    diff.extend(new[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+    diff
+}
+
+/// Snapshot an agent's current definition as a new revision, unless it matches the latest one.
+///
+/// Returns the id of the revision describing the agent as it is now.
+pub fn record_agent_revision(conn: &Connection, agent_id: i64) -> Result<i64, String> {
+    let agent = load_agent(conn, agent_id)?;
+    let latest = latest_revision(conn, agent_id)?;
+    if let Some(latest) = latest.as_ref().filter(|r| matches_agent(r, &agent)) {
+        return Ok(latest.id);
+    }
+
+    conn.execute(
+        "INSERT INTO agent_revisions (agent_id, revision, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            agent_id,
+            latest.map_or(1, |r| r.revision + 1),
+            agent.name,
+            agent.icon,
+            agent.system_prompt,
+            agent.default_task,
+            agent.model,
+            agent.enable_file_read,
+            agent.enable_file_write,
+            agent.enable_network,
+            agent.hooks
+        ],
+    )
+    .map_err(|e| format!("Failed to save agent revision: {}", e))?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Replace an agent's definition with the revision a run was queued with.
+///
+/// Runs queued before an edit still launch with the prompt they were queued with.
+pub fn apply_run_revision(conn: &Connection, run_id: i64, agent: &mut Agent) -> Result<(), String> {
+    let revision = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM agent_revisions WHERE id = (SELECT agent_revision_id FROM agent_runs WHERE id = ?1)",
+                REVISION_COLUMNS
+            ),
+            params![run_id],
+            row_to_revision,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    if let Some(revision) = revision {
+        agent.system_prompt = revision.system_prompt;
+        agent.default_task = revision.default_task;
+        agent.enable_file_read = revision.enable_file_read;
+        agent.enable_file_write = revision.enable_file_write;
+        agent.enable_network = revision.enable_network;
+        agent.hooks = revision.hooks;
+    }
+    Ok(())
+}
+
+/// List an agent's revisions, newest first
+#[tauri::command]
+pub async fn list_agent_revisions(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<Vec<AgentRevision>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    // Agents created before revisions existed get their first one on demand
+    record_agent_revision(&conn, agent_id)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_revisions WHERE agent_id = ?1 ORDER BY revision DESC",
+            REVISION_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let revisions = stmt
+        .query_map(params![agent_id], row_to_revision)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(revisions)
+}
+
+/// Diff two revisions of an agent; `from_revision` defaults to the one before `to_revision`
+#[tauri::command]
+pub async fn diff_agent_revisions(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    from_revision: Option<i64>,
+    to_revision: i64,
+) -> Result<AgentRevisionDiff, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let to = load_revision(&conn, agent_id, to_revision)?
+        .ok_or_else(|| format!("Revision {} not found", to_revision))?;
+    let from = match from_revision {
+        Some(revision) => Some(
+            load_revision(&conn, agent_id, revision)?
+                .ok_or_else(|| format!("Revision {} not found", revision))?,
+        ),
+        None => load_revision(&conn, agent_id, to_revision - 1)?,
+    };
+
+    let (changed_fields, system_prompt, default_task) = match &from {
+        Some(from) => (
+            changed_fields(from, &to),
+            diff_lines(&from.system_prompt, &to.system_prompt),
+            diff_lines(
+                from.default_task.as_deref().unwrap_or_default(),
+                to.default_task.as_deref().unwrap_or_default(),
+            ),
+        ),
+        None => (
+            Vec::new(),
+            diff_lines("", &to.system_prompt),
+            diff_lines("", to.default_task.as_deref().unwrap_or_default()),
+        ),
+    };
+
+    Ok(AgentRevisionDiff {
+        from,
+        to,
+        changed_fields,
+        system_prompt,
+        default_task,
+    })
+}
+
+/// Get the agent revision a run was started with
+#[tauri::command]
+pub async fn get_run_agent_revision(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Option<AgentRevision>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM agent_revisions WHERE id = (SELECT agent_revision_id FROM agent_runs WHERE id = ?1)",
+            REVISION_COLUMNS
+        ),
+        params![run_id],
+        row_to_revision,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Restore an agent to a previous revision.
+///
+/// The restored definition is saved as a new revision, so the rollback itself can be undone.
+#[tauri::command]
+pub async fn rollback_agent(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    revision: i64,
+) -> Result<Agent, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let target = load_revision(&conn, agent_id, revision)?
+        .ok_or_else(|| format!("Revision {} not found", revision))?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    // Keep the current definition in history before overwriting it
+    record_agent_revision(&tx, agent_id)?;
+    tx.execute(
+        "UPDATE agents SET name = ?1, icon = ?2, system_prompt = ?3, default_task = ?4, model = ?5, enable_file_read = ?6, enable_file_write = ?7, enable_network = ?8, hooks = ?9, updated_at = CURRENT_TIMESTAMP WHERE id = ?10",
+        params![
+            target.name,
+            target.icon,
+            target.system_prompt,
+            target.default_task,
+            target.model,
+            target.enable_file_read,
+            target.enable_file_write,
+            target.enable_network,
+            target.hooks,
+            agent_id
+        ],
+    )
+    .map_err(|e| format!("Failed to roll back agent: {}", e))?;
+    record_agent_revision(&tx, agent_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    info!("Rolled back agent {} to revision {}", agent_id, revision);
+    load_agent(&conn, agent_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let old = "You are a reviewer.\nBe concise.\nUse bullet points.";
+        let new = "You are a reviewer.\nBe thorough.\nUse bullet points.\nCite files.";
+
+        assert_eq!(
+            diff_lines(old, new),
+            vec![
+                DiffLine::Unchanged("You are a reviewer.".to_string()),
+                DiffLine::Removed("Be concise.".to_string()),
+                DiffLine::Added("Be thorough.".to_string()),
+                DiffLine::Unchanged("Use bullet points.".to_string()),
+                DiffLine::Added("Cite files.".to_string()),
+            ]
+        );
+        assert_eq!(diff_lines("", "a"), vec![DiffLine::Added("a".to_string())]);
+    }
+}
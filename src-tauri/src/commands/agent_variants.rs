@@ -0,0 +1,184 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agent_env::load_agent_env;
+use crate::commands::agent_revisions::record_agent_revision;
+use crate::commands::agents::{load_agent, Agent, AgentDb};
+
+/// Per-agent settings tables a duplicate gets its own copy of.
+/// Schedules and file watches are left out so a copy never starts running on its own.
+const COPIED_SETTINGS_TABLES: &[&str] = &[
+    "agent_parameter_schemas",
+    "agent_budgets",
+    "agent_retry_policies",
+    "agent_output_schemas",
+    "agent_notification_rules",
+    "agent_run_limits",
+    "agent_crash_restart_policies",
+    "agent_notification_channels",
+];
+
+/// An agent's place in a family of variants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentVariants {
+    /// The agent this one was duplicated from as a variant
+    pub original: Option<Agent>,
+    /// Agents duplicated from this one as variants, oldest first
+    pub variants: Vec<Agent>,
+}
+
+/// Copy an agent's rows in a per-agent settings table to another agent
+fn copy_agent_rows(
+    conn: &Connection,
+    table: &str,
+    from_agent_id: i64,
+    to_agent_id: i64,
+) -> Result<(), String> {
+    let columns: Vec<String> = conn
+        .prepare(&format!("PRAGMA table_info({})", table))
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(1))?.collect())
+        .map_err(|e| e.to_string())?;
+    let selected: Vec<&str> = columns
+        .iter()
+        .map(|c| if c == "agent_id" { "?2" } else { c.as_str() })
+        .collect();
+
+    conn.execute(
+        &format!(
+            "INSERT INTO {table} ({}) SELECT {} FROM {table} WHERE agent_id = ?1",
+            columns.join(", "),
+            selected.join(", "),
+        ),
+        params![from_agent_id, to_agent_id],
+    )
+    .map_err(|e| format!("Failed to copy {}: {}", table, e))?;
+    Ok(())
+}
+
+/// Default name for a copy: "Name (copy)", "Name (copy 2)", ... whichever is free
+fn copy_name(conn: &Connection, name: &str) -> Result<String, String> {
+    let mut candidate = format!("{} (copy)", name);
+    for n in 2.. {
+        let taken = conn
+            .query_row(
+                "SELECT 1 FROM agents WHERE name = ?1",
+                params![candidate],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .is_some();
+        if !taken {
+            break;
+        }
+        candidate = format!("{} (copy {})", name, n);
+    }
+    Ok(candidate)
+}
+
+/// Clone an agent with its prompt, model, icon, permissions and settings into a new agent.
+///
+/// With `as_variant` the copy is linked back to the original so variations of a
+/// prompt can be listed together.
+#[tauri::command]
+pub async fn duplicate_agent(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    name: Option<String>,
+    as_variant: Option<bool>,
+) -> Result<Agent, String> {
+    let (agent_env, new_id) = {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let original = load_agent(&conn, agent_id)?;
+        let name = match name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()) {
+            Some(name) => name,
+            None => copy_name(&conn, &original.name)?,
+        };
+
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, permission_profile, variant_of)
+             SELECT ?2, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, permission_profile, ?3
+             FROM agents WHERE id = ?1",
+            params![agent_id, name, as_variant.unwrap_or(false).then_some(agent_id)],
+        )
+        .map_err(|e| e.to_string())?;
+        let new_id = tx.last_insert_rowid();
+
+        for table in COPIED_SETTINGS_TABLES {
+            copy_agent_rows(&tx, table, agent_id, new_id)?;
+        }
+        record_agent_revision(&tx, new_id)?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        (load_agent_env(&conn, agent_id)?, new_id)
+    };
+    // Secrets are copied in the keychain without holding the database lock
+    agent_env.copy_to(&db, new_id);
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_agent(&conn, new_id)
+}
+
+/// Get the agent an agent was duplicated from and the variants duplicated from it
+#[tauri::command]
+pub async fn get_agent_variants(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<AgentVariants, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let original_id: Option<i64> = conn
+        .query_row(
+            "SELECT variant_of FROM agents WHERE id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let variant_ids: Vec<i64> = conn
+        .prepare("SELECT id FROM agents WHERE variant_of = ?1 ORDER BY id")
+        .and_then(|mut stmt| {
+            stmt.query_map(params![agent_id], |row| row.get(0))?
+                .collect()
+        })
+        .map_err(|e| e.to_string())?;
+
+    Ok(AgentVariants {
+        original: original_id.map(|id| load_agent(&conn, id)).transpose()?,
+        variants: variant_ids
+            .into_iter()
+            .map(|id| load_agent(&conn, id))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_agent_rows_and_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agents (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+             CREATE TABLE agent_budgets (agent_id INTEGER PRIMARY KEY, max_cost_usd REAL, max_tokens INTEGER);
+             INSERT INTO agents VALUES (1, 'Fixer'), (2, 'Fixer (copy)');
+             INSERT INTO agent_budgets VALUES (1, 2.5, 1000);",
+        )
+        .unwrap();
+
+        copy_agent_rows(&conn, "agent_budgets", 1, 3).unwrap();
+        let copied: (f64, i64) = conn
+            .query_row(
+                "SELECT max_cost_usd, max_tokens FROM agent_budgets WHERE agent_id = 3",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(copied, (2.5, 1000));
+
+        assert_eq!(copy_name(&conn, "Fixer").unwrap(), "Fixer (copy 2)");
+        assert_eq!(copy_name(&conn, "Reviewer").unwrap(), "Reviewer (copy)");
+    }
+}
@@ -3,9 +3,10 @@ use chrono;
 use dirs;
 use log::{debug, error, info, warn};
 use reqwest;
-use rusqlite::{params, Connection, Result as SqliteResult};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use std::io::{BufRead, BufReader};
 use std::process::Stdio;
 use std::sync::Mutex;
@@ -14,6 +15,51 @@ use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::io::{AsyncBufReadExt, BufReader as TokioBufReader};
 use tokio::process::Command;
 
+use crate::commands::agent_env::load_agent_env;
+use crate::commands::agent_parameters::{
+    load_agent_parameters, render_prompt, resolve_parameters, AgentParameter,
+};
+use crate::commands::agent_revisions::{apply_run_revision, record_agent_revision};
+use crate::commands::artifacts::{capture_run_artifacts, parse_declared_artifacts};
+use crate::commands::budgets::{
+    load_agent_budget, stop_over_budget_run, RunUsageTracker, UsageSnapshot,
+};
+use crate::commands::burn_rate::spawn_burn_rate_reporter;
+use crate::commands::cancellation::grace_period;
+use crate::commands::crash_restarts::{
+    is_crash, load_crash_output, load_crash_restart_policy, queue_crash_restart,
+    save_crash_restart_policy, CrashRestartPolicy,
+};
+use crate::commands::issue_links::comment_on_run_issues;
+use crate::commands::metrics::record_run_usage;
+use crate::commands::notification_actions::{grant_approved_tools, load_approved_tools};
+use crate::commands::notification_channels::{
+    link_agent_channels_by_name, load_agent_channel_names,
+};
+use crate::commands::notifications::notify_run_finished;
+use crate::commands::permissions::{
+    load_permission_profile, save_permission_profile, PermissionProfile,
+};
+use crate::commands::pty_mode::{load_pty_mode, spawn_on_pty, RunOutput};
+use crate::commands::retries::{schedule_retry, RetryCondition};
+use crate::commands::run_diffs::{capture_run_diff, record_run_base, snapshot_worktree};
+use crate::commands::run_limits::{
+    load_run_limits, save_run_limits, spawn_limit_watchdog, RunLimits,
+};
+use crate::commands::run_logs::{agent_run_log_name, logs_root, RunOutputLog};
+use crate::commands::run_recovery::{take_resume_session, RESUME_PROMPT};
+use crate::commands::run_search::{index_finished_run, index_run_text};
+use crate::commands::settings::save_setting;
+use crate::commands::stream_batching::{load_stream_batching, StreamBatcher};
+use crate::commands::structured_output::{
+    load_output_schema, output_instructions, record_structured_output,
+};
+use crate::commands::usage_index::create_usage_tables;
+use crate::commands::worktrees::{abandon_task_worktree, create_task_worktree};
+use crate::process::CancelOutcome;
+use crate::stderr_rules::{classify_stderr, StderrSeverity};
+use crate::stream_json::{parse_line, StreamMessage};
+
 /// Finds the full path to the claude binary
 /// This is necessary because macOS apps have a limited PATH environment
 fn find_claude_binary(app_handle: &AppHandle) -> Result<String, String> {
@@ -48,7 +94,7 @@ pub struct AgentRun {
     pub model: String,
     pub project_path: String,
     pub session_id: String, // UUID session ID from Claude Code
-    pub status: String,     // 'pending', 'running', 'completed', 'failed', 'cancelled'
+    pub status: String, // 'pending', 'queued', 'running', 'completed', 'failed', 'cancelled', 'interrupted'
     pub pid: Option<u32>,
     pub process_started_at: Option<String>,
     pub created_at: String,
@@ -73,6 +119,9 @@ pub struct AgentRunWithMetrics {
     pub output: Option<String>, // Real-time JSONL content
 }
 
+/// Current `.opcode.json` format version written by export
+pub const AGENT_EXPORT_VERSION: u32 = 2;
+
 /// Agent export format
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AgentExport {
@@ -90,8 +139,82 @@ pub struct AgentData {
     pub default_task: Option<String>,
     pub model: String,
     pub hooks: Option<String>,
+    // Permission settings were added in version 2; version 1 files fall back to the old defaults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_file_read: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_file_write: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_network: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Vec<AgentParameter>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_profile: Option<PermissionProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_limits: Option<RunLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crash_restart_policy: Option<CrashRestartPolicy>,
+    /// Names of the notification channels the agent is linked to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification_channels: Option<Vec<String>>,
+}
+
+/// What to do when an imported agent's name is already taken
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportConflictStrategy {
+    /// Import under a new, unused name
+    #[default]
+    Rename,
+    /// Replace the existing agent's configuration in place
+    Overwrite,
+    /// Keep the existing agent and import nothing
+    Skip,
+}
+
+/// Validate an agent export before importing it
+pub fn validate_agent_export(export: &AgentExport) -> Result<(), String> {
+    if export.version == 0 || export.version > AGENT_EXPORT_VERSION {
+        return Err(format!(
+            "Unsupported export version: {}. This version of the app supports versions 1 to {}.",
+            export.version, AGENT_EXPORT_VERSION
+        ));
+    }
+
+    let agent = &export.agent;
+    let mut problems = Vec::new();
+    if agent.name.trim().is_empty() {
+        problems.push("name is empty".to_string());
+    }
+    if agent.icon.trim().is_empty() {
+        problems.push("icon is empty".to_string());
+    }
+    if agent.system_prompt.trim().is_empty() {
+        problems.push("system_prompt is empty".to_string());
+    }
+    if agent.model.trim().is_empty() {
+        problems.push("model is empty".to_string());
+    }
+    if let Some(hooks) = &agent.hooks {
+        if let Err(e) = serde_json::from_str::<JsonValue>(hooks) {
+            problems.push(format!("hooks is not valid JSON: {}", e));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid agent export: {}", problems.join(", ")))
+    }
 }
 
+/// Tools withheld from read-only runs so they can't modify the project
+pub const READ_ONLY_DISALLOWED_TOOLS: &[&str] =
+    &["Bash", "Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// How often a running agent's last output time is written to the database
+const OUTPUT_HEARTBEAT_SECS: u64 = 5;
+
 /// Database connection state
 pub struct AgentDb(pub Mutex<Connection>);
 
@@ -213,6 +336,29 @@ pub async fn get_agent_run_with_metrics(run: AgentRun) -> AgentRunWithMetrics {
     }
 }
 
+/// app_settings keys whose values the settings audit leaves out
+const AUDIT_SECRET_KEYS: &[&str] = &["webhook_token"];
+
+/// AUDIT_SECRET_KEYS as an SQL list
+fn audit_secret_keys_sql() -> String {
+    AUDIT_SECRET_KEYS
+        .iter()
+        .map(|key| format!("'{}'", key))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// SQL for the value of `value` the settings audit records for the setting
+/// `key`: masked for secret settings
+fn audited_value(value: &str, key: &str) -> String {
+    format!(
+        "CASE WHEN {value} IS NOT NULL AND {key} IN ({keys}) THEN '********' ELSE {value} END",
+        value = value,
+        key = key,
+        keys = audit_secret_keys_sql(),
+    )
+}
+
 /// Initialize the agents database
 pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
     let app_dir = app
@@ -221,7 +367,11 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         .expect("Failed to get app data dir");
     std::fs::create_dir_all(&app_dir).expect("Failed to create app data dir");
 
-    let db_path = app_dir.join("agents.db");
+    open_database(&app_dir.join("agents.db"))
+}
+
+/// Open an agents database, creating and migrating its schema as needed
+pub fn open_database(db_path: &std::path::Path) -> SqliteResult<Connection> {
     let conn = Connection::open(db_path)?;
 
     // Create agents table
@@ -262,6 +412,8 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agents ADD COLUMN enable_network BOOLEAN DEFAULT 0",
         [],
     );
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN permission_profile TEXT", []);
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN variant_of INTEGER", []);
 
     // Create agent_runs table
     conn.execute(
@@ -279,6 +431,23 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
             process_started_at TEXT,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
             completed_at TEXT,
+            queue_position INTEGER,
+            attempt INTEGER DEFAULT 1,
+            retry_of_run_id INTEGER,
+            failure_reason TEXT,
+            git_base_tree TEXT,
+            git_end_tree TEXT,
+            git_diff TEXT,
+            changed_files TEXT,
+            changes_reverted_at TEXT,
+            parameters TEXT,
+            read_only BOOLEAN DEFAULT 0,
+            batch_id INTEGER,
+            agent_revision_id INTEGER,
+            resume_pending BOOLEAN DEFAULT 0,
+            last_output_at TEXT,
+            total_tokens INTEGER,
+            cost_usd REAL,
             FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
         )",
         [],
@@ -295,6 +464,56 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agent_runs ADD COLUMN process_started_at TEXT",
         [],
     );
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN queue_position INTEGER",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN attempt INTEGER DEFAULT 1",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN retry_of_run_id INTEGER",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN failure_reason TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN git_base_tree TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN git_end_tree TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN git_diff TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN changed_files TEXT", []);
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN changes_reverted_at TEXT",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN parameters TEXT", []);
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN read_only BOOLEAN DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN batch_id INTEGER", []);
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN agent_revision_id INTEGER",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN resume_pending BOOLEAN DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN last_output_at TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN total_tokens INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN cost_usd REAL", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN worktree_id INTEGER", []);
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN crash_restarts INTEGER DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN crash_output TEXT", []);
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN background BOOLEAN DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN pr_url TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN approved_tools TEXT", []);
 
     // Drop old columns that are no longer needed (data is now read from JSONL files)
     // Note: SQLite doesn't support DROP COLUMN, so we'll ignore errors for existing columns
@@ -320,6 +539,463 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     )?;
 
+    // Create agent_retry_policies table for automatic retries of failed runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_retry_policies (
+            agent_id INTEGER PRIMARY KEY,
+            max_attempts INTEGER NOT NULL DEFAULT 1,
+            backoff_seconds INTEGER NOT NULL DEFAULT 30,
+            backoff_multiplier REAL NOT NULL DEFAULT 2.0,
+            retry_on TEXT NOT NULL DEFAULT '[\"timeout\",\"nonzero_exit\",\"error_result\"]',
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_parameter_schemas table for {{placeholder}} parameters
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_parameter_schemas (
+            agent_id INTEGER PRIMARY KEY,
+            parameters TEXT NOT NULL DEFAULT '[]',
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_budgets table for per-run cost and token limits
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_budgets (
+            agent_id INTEGER PRIMARY KEY,
+            max_cost_usd REAL,
+            max_tokens INTEGER,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_run_limits table for per-run time, memory and output limits
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_limits (
+            agent_id INTEGER PRIMARY KEY,
+            max_runtime_secs INTEGER,
+            max_memory_mb INTEGER,
+            max_output_bytes INTEGER,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_crash_restart_policies table for relaunching crashed runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_crash_restart_policies (
+            agent_id INTEGER PRIMARY KEY,
+            max_restarts INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_run_artifacts table for files captured from runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_artifacts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            sha256 TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            source TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create full-text index over run prompts and output (rowid is the run id)
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS agent_runs_fts USING fts5(task, output, tokenize = 'porter unicode61')",
+        [],
+    )?;
+
+    // Create session_index_files table tracking how far each Claude session file is indexed
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_index_files (
+            path TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            project_path TEXT,
+            indexed_bytes INTEGER NOT NULL DEFAULT 0,
+            indexed_lines INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Create full-text index over the messages of every Claude session
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS session_messages_fts USING fts5(
+            text, path UNINDEXED, line UNINDEXED, uuid UNINDEXED, role UNINDEXED, timestamp UNINDEXED,
+            tokenize = 'porter unicode61'
+        )",
+        [],
+    )?;
+
+    // Create agent_env_vars table; secret values live in the OS keychain
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_env_vars (
+            agent_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            value TEXT,
+            is_secret BOOLEAN NOT NULL DEFAULT 0,
+            PRIMARY KEY (agent_id, name),
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_revisions table keeping every saved version of an agent
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            revision INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            icon TEXT NOT NULL,
+            system_prompt TEXT NOT NULL,
+            default_task TEXT,
+            model TEXT NOT NULL,
+            enable_file_read BOOLEAN NOT NULL,
+            enable_file_write BOOLEAN NOT NULL,
+            enable_network BOOLEAN NOT NULL,
+            hooks TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (agent_id, revision),
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_notification_rules table with what to send when an agent's run ends
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_notification_rules (
+            agent_id INTEGER NOT NULL,
+            event TEXT NOT NULL,
+            channel TEXT NOT NULL,
+            webhook_url TEXT,
+            message_template TEXT,
+            payload_template TEXT,
+            PRIMARY KEY (agent_id, event),
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create notification_channels table with the Slack, Discord and webhook targets runs are reported to
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notification_channels (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            url TEXT NOT NULL,
+            payload_template TEXT,
+            events TEXT NOT NULL,
+            is_global BOOLEAN NOT NULL DEFAULT 0,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Create agent_notification_channels table linking agents to the channels their runs are reported to
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_notification_channels (
+            agent_id INTEGER NOT NULL,
+            channel_id INTEGER NOT NULL,
+            PRIMARY KEY (agent_id, channel_id),
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE,
+            FOREIGN KEY (channel_id) REFERENCES notification_channels(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create issue_links table linking sessions and runs to Jira, Linear and GitHub issues
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS issue_links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            target_type TEXT NOT NULL,
+            target_id TEXT NOT NULL,
+            tracker TEXT NOT NULL,
+            issue_key TEXT NOT NULL,
+            url TEXT NOT NULL,
+            post_comment BOOLEAN NOT NULL DEFAULT 0,
+            commented_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(target_type, target_id, url)
+        )",
+        [],
+    )?;
+
+    // Create agent_output_schemas table with the JSON Schema an agent's result must match
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_output_schemas (
+            agent_id INTEGER PRIMARY KEY,
+            schema TEXT NOT NULL,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_run_outputs table with the structured result extracted from each run
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_outputs (
+            run_id INTEGER PRIMARY KEY,
+            data TEXT,
+            valid BOOLEAN NOT NULL,
+            errors TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (run_id) REFERENCES agent_runs(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_run_batches table grouping runs of one task across several projects
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_batches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            agent_name TEXT NOT NULL,
+            task TEXT NOT NULL,
+            model TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_run_comparisons table pairing the two runs of an A/B model comparison
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_comparisons (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            project_path TEXT NOT NULL,
+            task TEXT NOT NULL,
+            isolation TEXT NOT NULL,
+            run_a_id INTEGER NOT NULL,
+            run_b_id INTEGER NOT NULL,
+            worktree_a TEXT,
+            worktree_b TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create task_worktrees table for git worktrees that sessions and runs work in
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS task_worktrees (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_path TEXT NOT NULL,
+            repo_root TEXT NOT NULL,
+            worktree_path TEXT NOT NULL,
+            working_dir TEXT NOT NULL,
+            branch TEXT NOT NULL,
+            base_branch TEXT,
+            task TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'active',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            finished_at TEXT
+        )",
+        [],
+    )?;
+
+    // Create prompt_templates table for reusable prompts, global or per project
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS prompt_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            description TEXT,
+            content TEXT NOT NULL,
+            variables TEXT NOT NULL DEFAULT '[]',
+            project_path TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Create session_titles table for generated and user-given session titles
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_titles (
+            session_id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            user_set BOOLEAN NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Create session_stream_filters table with what of a session's output reaches the UI
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_stream_filters (
+            session_id TEXT PRIMARY KEY,
+            filter TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Create session_stats table caching per-session figures for project statistics
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_stats (
+            path TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            modified INTEGER NOT NULL,
+            messages INTEGER NOT NULL,
+            duration_secs INTEGER NOT NULL,
+            total_tokens INTEGER NOT NULL,
+            cost_usd REAL NOT NULL
+        )",
+        [],
+    )?;
+
+    // Create session_tool_uses table with how often each session used each tool
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_tool_uses (
+            path TEXT NOT NULL,
+            project_id TEXT NOT NULL,
+            tool TEXT NOT NULL,
+            count INTEGER NOT NULL,
+            PRIMARY KEY (path, tool)
+        )",
+        [],
+    )?;
+
+    // Create the usage index and its monthly rollups
+    create_usage_tables(&conn)?;
+
+    // Create cost_anomalies table for sessions and runs that cost far more than usual
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS cost_anomalies (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            subject_id TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            cost_usd REAL NOT NULL,
+            median_cost_usd REAL NOT NULL,
+            ratio REAL NOT NULL,
+            reason TEXT NOT NULL,
+            detected_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            dismissed_at TEXT,
+            UNIQUE (kind, subject_id)
+        )",
+        [],
+    )?;
+
+    // Create shadow_runs table for prompts sent to a second model alongside a session
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS shadow_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_path TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            prompt TEXT NOT NULL,
+            primary_model TEXT NOT NULL,
+            shadow_model TEXT NOT NULL,
+            primary_offset INTEGER NOT NULL DEFAULT 0,
+            primary_response TEXT,
+            shadow_session_id TEXT,
+            shadow_response TEXT,
+            status TEXT NOT NULL DEFAULT 'running',
+            error TEXT,
+            shadow_total_tokens INTEGER NOT NULL DEFAULT 0,
+            shadow_cost_usd REAL NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            completed_at TEXT
+        )",
+        [],
+    )?;
+
+    // Create checkpoint_validation_hooks table for commands run after each checkpoint
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS checkpoint_validation_hooks (
+            project_path TEXT PRIMARY KEY,
+            command TEXT NOT NULL,
+            timeout_secs INTEGER NOT NULL DEFAULT 600,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Create mcp_project_servers table for per-project MCP server flags
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mcp_project_servers (
+            project_path TEXT NOT NULL,
+            server_name TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (project_path, server_name)
+        )",
+        [],
+    )?;
+
+    // Create agent_schedules table for cron-style scheduled runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_schedules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            cron_expression TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            task TEXT,
+            model TEXT,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            last_run_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create agent_file_watches table for file-change triggered runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_file_watches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            project_path TEXT NOT NULL,
+            glob_pattern TEXT NOT NULL,
+            task TEXT,
+            model TEXT,
+            debounce_ms INTEGER NOT NULL DEFAULT 2000,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            last_triggered_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create workspaces table for named groups of projects
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS workspaces (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Create workspace_projects table with the project paths in each workspace
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS workspace_projects (
+            workspace_id INTEGER NOT NULL,
+            project_path TEXT NOT NULL,
+            PRIMARY KEY (workspace_id, project_path),
+            FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
     // Create settings table for app-wide settings
     conn.execute(
         "CREATE TABLE IF NOT EXISTS app_settings (
@@ -342,6 +1018,87 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     )?;
 
+    // Create audit table recording every settings mutation
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings_audit (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            key TEXT NOT NULL,
+            old_value TEXT,
+            new_value TEXT,
+            changed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Secret settings are audited as changed without their values. Triggers
+    // from before that are replaced, and values they already copied scrubbed.
+    conn.execute(
+        &format!(
+            "UPDATE settings_audit SET old_value = {}, new_value = {} WHERE key IN ({})",
+            audited_value("old_value", "key"),
+            audited_value("new_value", "key"),
+            audit_secret_keys_sql()
+        ),
+        [],
+    )?;
+    for trigger in [
+        "audit_app_settings_insert",
+        "audit_app_settings_update",
+        "audit_app_settings_delete",
+    ] {
+        conn.execute(&format!("DROP TRIGGER IF EXISTS {}", trigger), [])?;
+    }
+
+    // Capture inserts before they happen so INSERT OR REPLACE still sees the previous value
+    conn.execute(
+        &format!(
+            "CREATE TRIGGER audit_app_settings_insert
+             BEFORE INSERT ON app_settings
+             FOR EACH ROW
+             WHEN NEW.value IS NOT (SELECT value FROM app_settings WHERE key = NEW.key)
+             BEGIN
+                 INSERT INTO settings_audit (key, old_value, new_value)
+                 VALUES (NEW.key, {}, {});
+             END",
+            audited_value(
+                "(SELECT value FROM app_settings WHERE key = NEW.key)",
+                "NEW.key"
+            ),
+            audited_value("NEW.value", "NEW.key"),
+        ),
+        [],
+    )?;
+
+    conn.execute(
+        &format!(
+            "CREATE TRIGGER audit_app_settings_update
+             AFTER UPDATE OF value ON app_settings
+             FOR EACH ROW
+             WHEN OLD.value IS NOT NEW.value
+             BEGIN
+                 INSERT INTO settings_audit (key, old_value, new_value)
+                 VALUES (NEW.key, {}, {});
+             END",
+            audited_value("OLD.value", "NEW.key"),
+            audited_value("NEW.value", "NEW.key"),
+        ),
+        [],
+    )?;
+
+    conn.execute(
+        &format!(
+            "CREATE TRIGGER audit_app_settings_delete
+             AFTER DELETE ON app_settings
+             FOR EACH ROW
+             BEGIN
+                 INSERT INTO settings_audit (key, old_value, new_value)
+                 VALUES (OLD.key, {}, NULL);
+             END",
+            audited_value("OLD.value", "OLD.key"),
+        ),
+        [],
+    )?;
+
     Ok(conn)
 }
 
@@ -407,6 +1164,7 @@ pub async fn create_agent(
     .map_err(|e| e.to_string())?;
 
     let id = conn.last_insert_rowid();
+    record_agent_revision(&conn, id)?;
 
     // Fetch the created agent
     let agent = conn
@@ -453,6 +1211,9 @@ pub async fn update_agent(
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     let model = model.unwrap_or_else(|| "sonnet".to_string());
 
+    // Agents saved before revisions existed keep their old definition in history
+    record_agent_revision(&conn, id)?;
+
     // Build dynamic query based on provided parameters
     let mut query =
         "UPDATE agents SET name = ?1, icon = ?2, system_prompt = ?3, default_task = ?4, model = ?5, hooks = ?6"
@@ -492,6 +1253,7 @@ pub async fn update_agent(
         rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())),
     )
     .map_err(|e| e.to_string())?;
+    record_agent_revision(&conn, id)?;
 
     // Fetch the updated agent
     let agent = conn
@@ -523,10 +1285,21 @@ pub async fn update_agent(
 /// Delete an agent
 #[tauri::command]
 pub async fn delete_agent(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let agent_env = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let agent_env = load_agent_env(&conn, id)?;
 
-    conn.execute("DELETE FROM agents WHERE id = ?1", params![id])
+        conn.execute("DELETE FROM agents WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        // Its variants stay around as standalone agents
+        conn.execute(
+            "UPDATE agents SET variant_of = NULL WHERE variant_of = ?1",
+            params![id],
+        )
         .map_err(|e| e.to_string())?;
+        agent_env
+    };
+    agent_env.delete_secrets();
 
     Ok(())
 }
@@ -535,7 +1308,11 @@ pub async fn delete_agent(db: State<'_, AgentDb>, id: i64) -> Result<(), String>
 #[tauri::command]
 pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_agent(&conn, id)
+}
 
+/// Load a single agent by ID
+pub fn load_agent(conn: &Connection, id: i64) -> Result<Agent, String> {
     let agent = conn
         .query_row(
             "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
@@ -677,13 +1454,26 @@ pub async fn list_agent_runs_with_metrics(
 }
 
 /// Execute a CC agent with streaming output
+///
+/// The run is queued first and starts as soon as the run queue's concurrency
+/// limits allow, which is immediately when there is a free slot.
+///
+/// With `use_worktree`, the run works in a fresh git worktree on a branch named
+/// after the task, which can be merged or discarded once the run is done.
+///
+/// A `background` run is started at reduced CPU and I/O priority so it doesn't
+/// slow down the rest of the machine.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_agent(
     app: AppHandle,
     agent_id: i64,
     project_path: String,
     task: String,
     model: Option<String>,
+    parameters: Option<HashMap<String, JsonValue>>,
+    use_worktree: Option<bool>,
+    background: Option<bool>,
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
 ) -> Result<i64, String> {
@@ -693,56 +1483,250 @@ pub async fn execute_agent(
     let agent = get_agent(db.clone(), agent_id).await?;
     let execution_model = model.unwrap_or(agent.model.clone());
 
-    // Create .claude/settings.json with agent hooks if it doesn't exist
-    if let Some(hooks_json) = &agent.hooks {
-        let claude_dir = std::path::Path::new(&project_path).join(".claude");
-        let settings_path = claude_dir.join("settings.json");
-
-        // Create .claude directory if it doesn't exist
-        if !claude_dir.exists() {
-            std::fs::create_dir_all(&claude_dir)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
-            info!("Created .claude directory at: {:?}", claude_dir);
+    let worktree = if use_worktree.unwrap_or(false) {
+        let worktrees_dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get app data dir: {}", e))?
+            .join("worktrees");
+        Some(create_task_worktree(&db, worktrees_dir, &project_path, &task).await?)
+    } else {
+        None
+    };
+
+    // Create a new run record at the back of the queue
+    let queued = (|| -> Result<i64, String> {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let working_dir = worktree
+            .as_ref()
+            .map_or(project_path.as_str(), |w| w.working_dir.as_str());
+        let run_id = queue_agent_run(
+            &conn,
+            &agent,
+            working_dir,
+            &task,
+            &execution_model,
+            parameters,
+        )?;
+        if let Some(worktree) = &worktree {
+            conn.execute(
+                "UPDATE agent_runs SET worktree_id = ?1 WHERE id = ?2",
+                params![worktree.id, run_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        if background.unwrap_or(false) {
+            conn.execute(
+                "UPDATE agent_runs SET background = 1 WHERE id = ?1",
+                params![run_id],
+            )
+            .map_err(|e| e.to_string())?;
         }
+        Ok(run_id)
+    })();
+    let run_id = match queued {
+        Ok(run_id) => run_id,
+        Err(e) => {
+            if let Some(worktree) = worktree {
+                abandon_task_worktree(&db, worktree).await;
+            }
+            return Err(e);
+        }
+    };
 
-        // Check if settings.json already exists
-        if !settings_path.exists() {
-            // Parse the hooks JSON
-            let hooks: serde_json::Value = serde_json::from_str(hooks_json)
-                .map_err(|e| format!("Failed to parse agent hooks: {}", e))?;
+    // Launch right away if there is capacity; otherwise the dispatcher starts it later
+    let launched = crate::commands::queue::dispatch_queued_runs(&app, db, registry).await?;
+    if let Some((_, Err(e))) = launched.into_iter().find(|(id, _)| *id == run_id) {
+        return Err(e);
+    }
 
-            // Create a settings object with just the hooks
-            let settings = serde_json::json!({
-                "hooks": hooks
-            });
+    let _ = app.emit("agent-queue-updated", true);
+    Ok(run_id)
+}
 
-            // Write the settings file
-            let settings_content = serde_json::to_string_pretty(&settings)
-                .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+/// Add a run to the back of the queue without dispatching it.
+///
+/// `{{placeholders}}` in the task are filled in here; the system prompt is
+/// rendered from the stored values at launch.
+pub fn queue_agent_run(
+    conn: &Connection,
+    agent: &Agent,
+    project_path: &str,
+    task: &str,
+    model: &str,
+    parameters: Option<HashMap<String, JsonValue>>,
+) -> Result<i64, String> {
+    let agent_id = agent.id.ok_or("Agent has no id")?;
+    let schema = load_agent_parameters(conn, agent_id)?;
+    let values = resolve_parameters(&schema, &parameters.unwrap_or_default())?;
+    let task = render_prompt(task, &values);
+    let values_json = if values.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&values).map_err(|e| e.to_string())?)
+    };
 
-            std::fs::write(&settings_path, settings_content)
-                .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+    // Link the run to the exact agent definition it was queued with
+    let revision_id = record_agent_revision(conn, agent_id)?;
 
-            info!(
-                "Created settings.json with agent hooks at: {:?}",
-                settings_path
-            );
-        } else {
-            info!("settings.json already exists at: {:?}", settings_path);
+    conn.execute(
+        "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, queue_position, parameters, agent_revision_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'queued', (SELECT COALESCE(MAX(queue_position), 0) + 1 FROM agent_runs), ?8, ?9)",
+        params![agent_id, agent.name, agent.icon, task, model, project_path, "", values_json, revision_id],
+    )
+    .map_err(|e| e.to_string())?;
+    let run_id = conn.last_insert_rowid();
+
+    // Searchable by prompt right away; the output is indexed when the run finishes
+    index_run_text(conn, run_id, &task, "");
+
+    Ok(run_id)
+}
+
+/// Build the Claude arguments for an agent run
+pub fn build_agent_args(
+    task: &str,
+    system_prompt: String,
+    model: &str,
+    permission_profile: Option<&PermissionProfile>,
+    read_only: bool,
+) -> Vec<String> {
+    let mut args = vec![
+        "-p".to_string(),
+        task.to_string(),
+        "--system-prompt".to_string(),
+        system_prompt,
+        "--model".to_string(),
+        model.to_string(),
+        "--output-format".to_string(),
+        "stream-json".to_string(),
+        "--verbose".to_string(),
+    ];
+    let mut disallowed_tools: Vec<String> = Vec::new();
+    match permission_profile {
+        Some(profile) => {
+            // Anything the profile doesn't allow is denied, since -p can't prompt
+            let rules = profile.to_rules();
+            args.push("--allowedTools".to_string());
+            args.push(rules.allowed.join(","));
+            disallowed_tools.extend(rules.disallowed);
         }
+        None => args.push("--dangerously-skip-permissions".to_string()),
+    }
+    if read_only {
+        disallowed_tools.extend(READ_ONLY_DISALLOWED_TOOLS.iter().map(|t| t.to_string()));
+    }
+    if !disallowed_tools.is_empty() {
+        args.push("--disallowedTools".to_string());
+        args.push(disallowed_tools.join(","));
     }
 
-    // Create a new run record
-    let run_id = {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
-        conn.execute(
-            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![agent_id, agent.name, agent.icon, task, execution_model, project_path, ""],
+    args
+}
+
+/// Create .claude/settings.json with an agent's hooks unless the project already has one
+pub fn write_agent_hooks(project_path: &str, hooks_json: &str) -> Result<(), String> {
+    let claude_dir = std::path::Path::new(project_path).join(".claude");
+    let settings_path = claude_dir.join("settings.json");
+
+    // Create .claude directory if it doesn't exist
+    if !claude_dir.exists() {
+        std::fs::create_dir_all(&claude_dir)
+            .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+        info!("Created .claude directory at: {:?}", claude_dir);
+    }
+
+    // Check if settings.json already exists
+    if !settings_path.exists() {
+        // Parse the hooks JSON
+        let hooks: serde_json::Value = serde_json::from_str(hooks_json)
+            .map_err(|e| format!("Failed to parse agent hooks: {}", e))?;
+
+        // Create a settings object with just the hooks
+        let settings = serde_json::json!({
+            "hooks": hooks
+        });
+
+        // Write the settings file
+        let settings_content = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+        std::fs::write(&settings_path, settings_content)
+            .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+
+        info!(
+            "Created settings.json with agent hooks at: {:?}",
+            settings_path
+        );
+    } else {
+        info!("settings.json already exists at: {:?}", settings_path);
+    }
+
+    Ok(())
+}
+
+/// Load what a queued run needs at launch: its system prompt rendered with the
+/// parameter values resolved when it was queued, whether it is read-only, and
+/// the agent's permission profile
+pub fn load_run_launch_config(
+    conn: &Connection,
+    run_id: i64,
+    agent: &Agent,
+) -> Result<(String, bool, Option<PermissionProfile>), String> {
+    let agent_id = agent.id.ok_or("Agent has no id")?;
+    let permission_profile = load_permission_profile(conn, agent_id)?;
+    let (values, read_only): (Option<String>, bool) = conn
+        .query_row(
+            "SELECT parameters, COALESCE(read_only, 0) FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
         )
         .map_err(|e| e.to_string())?;
-        conn.last_insert_rowid()
+    let values: HashMap<String, String> = values
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let mut system_prompt = render_prompt(&agent.system_prompt, &values);
+    if let Some(schema) = load_output_schema(conn, agent_id)? {
+        system_prompt.push_str(&output_instructions(&schema));
+    }
+
+    Ok((system_prompt, read_only, permission_profile))
+}
+
+/// Start a queued agent run
+pub async fn launch_agent_run(
+    app: AppHandle,
+    run_id: i64,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<(), String> {
+    let run = get_agent_run(db.clone(), run_id).await?;
+    let mut agent = get_agent(db.clone(), run.agent_id).await?;
+    let project_path = run.project_path;
+    let task = run.task;
+    let execution_model = run.model;
+
+    let (system_prompt, read_only, permission_profile, resume_session, approved_tools) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        apply_run_revision(&conn, run_id, &mut agent)?;
+        let (system_prompt, read_only, permission_profile) =
+            load_run_launch_config(&conn, run_id, &agent)?;
+        (
+            system_prompt,
+            read_only,
+            permission_profile,
+            take_resume_session(&conn, run_id)?,
+            load_approved_tools(&conn, run_id),
+        )
     };
 
+    // Create .claude/settings.json with agent hooks if it doesn't exist
+    // (read-only runs leave the project untouched)
+    if let Some(hooks_json) = agent.hooks.as_ref().filter(|_| !read_only) {
+        write_agent_hooks(&project_path, hooks_json)?;
+    }
+
     // Find Claude binary
     info!("Running agent '{}'", agent.name);
     let claude_path = match find_claude_binary(&app) {
@@ -753,25 +1737,48 @@ pub async fn execute_agent(
         }
     };
 
-    // Build arguments
-    let args = vec![
-        "-p".to_string(),
-        task.clone(),
-        "--system-prompt".to_string(),
-        agent.system_prompt.clone(),
-        "--model".to_string(),
-        execution_model.clone(),
-        "--output-format".to_string(),
-        "stream-json".to_string(),
-        "--verbose".to_string(),
-        "--dangerously-skip-permissions".to_string(),
-    ];
+    // Build arguments; an interrupted run continues its own session instead of starting over
+    let mut args = build_agent_args(
+        if resume_session.is_some() {
+            RESUME_PROMPT
+        } else {
+            &task
+        },
+        system_prompt,
+        &execution_model,
+        permission_profile.as_ref(),
+        read_only,
+    );
+    // Tools the user approved after the run was denied them
+    grant_approved_tools(&mut args, &approved_tools);
+    // Agent runs always start Claude natively
+    args.extend(crate::commands::mcp_project_servers::mcp_config_args(
+        &app,
+        &project_path,
+        &crate::shell_environment::ShellEnvironment::Native,
+    ));
+    if let Some(session_id) = &resume_session {
+        info!("Resuming session {} for run {}", session_id, run_id);
+        args.push("--resume".to_string());
+        args.push(session_id.clone());
+    } else {
+        // Snapshot the project so the run's own changes can be diffed and reverted later.
+        // A resumed run keeps the snapshot from its first launch.
+        let project = std::path::PathBuf::from(&project_path);
+        let base_tree = tauri::async_runtime::spawn_blocking(move || snapshot_worktree(&project))
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(base_tree) = base_tree {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            record_run_base(&conn, run_id, &base_tree);
+        }
+    }
 
     // Always use system binary execution (sidecar removed)
     spawn_agent_system(
         app,
         run_id,
-        agent_id,
+        run.agent_id,
         agent.name.clone(),
         claude_path,
         args,
@@ -781,7 +1788,9 @@ pub async fn execute_agent(
         db,
         registry,
     )
-    .await
+    .await?;
+
+    Ok(())
 }
 
 /// Creates a system binary command for agent execution
@@ -789,9 +1798,13 @@ fn create_agent_system_command(
     claude_path: &str,
     args: Vec<String>,
     project_path: &str,
+    agent_env: Vec<(String, String)>,
 ) -> Command {
     let mut cmd = create_command_with_env(claude_path);
 
+    // The agent's own variables win over the inherited environment
+    cmd.envs(agent_env);
+
     // Add all arguments
     for arg in args {
         cmd.arg(arg);
@@ -801,6 +1814,7 @@ fn create_agent_system_command(
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+    crate::process::own_process_group(&mut cmd);
 
     cmd
 }
@@ -819,41 +1833,73 @@ async fn spawn_agent_system(
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
 ) -> Result<i64, String> {
-    // Build the command
-    let mut cmd = create_agent_system_command(&claude_path, args, &project_path);
+    // Build the command with the agent's environment variables and secrets
+    let (agent_env, background) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let background: bool = conn
+            .query_row(
+                "SELECT COALESCE(background, 0) FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        (load_agent_env(&conn, agent_id)?, background)
+    };
+    let agent_env = agent_env.resolve()?;
+    let mut cmd = create_agent_system_command(&claude_path, args, &project_path, agent_env);
+    let pty_mode = load_pty_mode(&db);
 
-    // Spawn the process
+    // Spawn the process, on a pseudo-terminal in PTY mode
     info!("🚀 Spawning Claude system process...");
-    let mut child = cmd.spawn().map_err(|e| {
-        error!("❌ Failed to spawn Claude process: {}", e);
-        format!("Failed to spawn Claude: {}", e)
-    })?;
-
-    info!("🔌 Using Stdio::null() for stdin - no input expected");
+    let mut child = None;
+    let mut pty_exit = None;
+    let (pid, stdout_lines, stderr_reader) = if pty_mode.enabled {
+        let pty = spawn_on_pty(cmd.as_std(), run_id, &pty_mode).map_err(|e| {
+            error!("❌ Failed to spawn Claude process on a terminal: {}", e);
+            e
+        })?;
+        info!("🖥️ Using a {}x{} terminal", pty_mode.cols, pty_mode.rows);
+        pty_exit = Some(pty.exit);
+        (pty.pid, RunOutput::Pty(pty.output), None)
+    } else {
+        let mut spawned = cmd.spawn().map_err(|e| {
+            error!("❌ Failed to spawn Claude process: {}", e);
+            format!("Failed to spawn Claude: {}", e)
+        })?;
+        info!("🔌 Using Stdio::null() for stdin - no input expected");
+
+        // Get stdout and stderr
+        let stdout = spawned.stdout.take().ok_or("Failed to get stdout")?;
+        let stderr = spawned.stderr.take().ok_or("Failed to get stderr")?;
+        info!("📡 Set up stdout/stderr readers");
+        let pid = spawned.id().unwrap_or(0);
+        child = Some(spawned);
+        (
+            pid,
+            RunOutput::Pipe(TokioBufReader::new(stdout).lines()),
+            Some(TokioBufReader::new(stderr)),
+        )
+    };
 
     // Get the PID and register the process
-    let pid = child.id().unwrap_or(0);
     let now = chrono::Utc::now().to_rfc3339();
     info!("✅ Claude process spawned successfully with PID: {}", pid);
+    if background && !crate::process::set_background_priority(pid) {
+        warn!("Failed to lower the priority of background run {}", run_id);
+    }
 
-    // Update the database with PID and status
-    {
+    // Update the database with PID and status. A run relaunched after a crash
+    // carries on from the output it wrote before, cleared now that it has started.
+    let earlier_output = {
         let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let earlier_output = load_crash_output(&conn, run_id)?;
         conn.execute(
-            "UPDATE agent_runs SET status = 'running', pid = ?1, process_started_at = ?2 WHERE id = ?3",
+            "UPDATE agent_runs SET status = 'running', pid = ?1, process_started_at = ?2, queue_position = NULL, crash_output = NULL WHERE id = ?3",
             params![pid as i64, now, run_id],
         ).map_err(|e| e.to_string())?;
         info!("📝 Updated database with running status and PID");
-    }
-
-    // Get stdout and stderr
-    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
-    let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
-    info!("📡 Set up stdout/stderr readers");
-
-    // Create readers
-    let stdout_reader = TokioBufReader::new(stdout);
-    let stderr_reader = TokioBufReader::new(stderr);
+        earlier_output
+    };
 
     // Create variables we need for the spawned tasks
     let app_dir = app
@@ -864,8 +1910,11 @@ async fn spawn_agent_system(
 
     // Shared state for collecting session ID and live output
     let session_id = std::sync::Arc::new(Mutex::new(String::new()));
-    let live_output = std::sync::Arc::new(Mutex::new(String::new()));
+    let live_output = std::sync::Arc::new(Mutex::new(earlier_output.clone().unwrap_or_default()));
     let start_time = std::time::Instant::now();
+    let started_at = std::time::SystemTime::now();
+    let declared_artifacts = std::sync::Arc::new(Mutex::new(Vec::<String>::new()));
+    let declared_artifacts_clone = declared_artifacts.clone();
 
     // Spawn tasks to read stdout and stderr
     let app_handle = app.clone();
@@ -875,14 +1924,58 @@ async fn spawn_agent_system(
     let first_output = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
     let first_output_clone = first_output.clone();
     let db_path_for_stdout = db_path.clone(); // Clone the db_path for the stdout task
+    let error_result = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let error_result_clone = error_result.clone();
+    let saw_result = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let saw_result_clone = saw_result.clone();
+    let budget = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_agent_budget(&conn, agent_id)?
+    };
+    let budget_exceeded = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let budget_exceeded_clone = budget_exceeded.clone();
+    let (usage_tx, usage_rx) = tokio::sync::watch::channel(UsageSnapshot::default());
+    let usage_rx_for_limits = usage_rx.clone();
+    spawn_burn_rate_reporter(app.clone(), run_id, usage_rx);
+    let run_limits = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_run_limits(&conn, agent_id)?
+    };
+    let output_bytes = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+    let output_bytes_clone = output_bytes.clone();
+    let output_bytes_stderr = output_bytes.clone();
+    let batcher = StreamBatcher::new(app.clone(), &load_stream_batching(&db));
+    batcher.serve_replays(&format!("agent-output:{}", run_id));
+    // Keep the raw output for post-mortems once the run is gone
+    let output_log = std::sync::Arc::new(RunOutputLog::open(
+        &logs_root(&app_dir),
+        &agent_run_log_name(run_id),
+    ));
+    let output_log_stderr = output_log.clone();
 
     let stdout_task = tokio::spawn(async move {
         info!("📖 Starting to read Claude stdout...");
-        let mut lines = stdout_reader.lines();
+        let mut lines = stdout_lines;
         let mut line_count = 0;
+        let mut usage = RunUsageTracker::default();
+        let mut last_heartbeat: Option<std::time::Instant> = None;
 
         while let Ok(Some(line)) = lines.next_line().await {
             line_count += 1;
+            output_bytes_clone
+                .fetch_add(line.len() as u64 + 1, std::sync::atomic::Ordering::Relaxed);
+            output_log.write("stdout", &line);
+
+            // Record activity so a crash leaves a trace of when the run was last alive
+            if last_heartbeat.is_none_or(|t| t.elapsed().as_secs() >= OUTPUT_HEARTBEAT_SECS) {
+                last_heartbeat = Some(std::time::Instant::now());
+                if let Ok(conn) = Connection::open(&db_path_for_stdout) {
+                    let _ = conn.execute(
+                        "UPDATE agent_runs SET last_output_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                        params![run_id],
+                    );
+                }
+            }
 
             // Log first output
             if !first_output_clone.load(std::sync::atomic::Ordering::Relaxed) {
@@ -905,39 +1998,75 @@ async fn spawn_agent_system(
                 output.push('\n');
             }
 
-            // Also store in process registry for cross-session access
-            let _ = registry_clone.append_live_output(run_id, &line);
+            // Surface lines that aren't stream messages instead of dropping them
+            let parsed = parse_line(&line);
+            if let Err(e) = &parsed {
+                warn!(
+                    "Agent run {} wrote a line that isn't a stream message: {}",
+                    run_id, e
+                );
+                let _ = app_handle.emit(&format!("agent-stream-error:{}", run_id), e);
+                let _ = app_handle.emit("agent-stream-error", e);
+            }
+            if let Ok((json, message)) = parsed {
+                if let StreamMessage::Result(result) = &message {
+                    saw_result_clone.store(true, std::sync::atomic::Ordering::Relaxed);
+
+                    // Remember if the run ended with an error result so it counts as failed
+                    if result.is_error {
+                        error_result_clone.store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
 
-            // Extract session ID from JSONL output
-            if let Ok(json) = serde_json::from_str::<JsonValue>(&line) {
-                // Claude Code uses "session_id" (underscore), not "sessionId"
-                if json.get("type").and_then(|t| t.as_str()) == Some("system")
-                    && json.get("subtype").and_then(|s| s.as_str()) == Some("init")
+                    // Collect artifacts the agent declared in its final result
+                    if let Some(text) = &result.result {
+                        if let Ok(mut declared) = declared_artifacts_clone.lock() {
+                            declared.extend(parse_declared_artifacts(text));
+                        }
+                    }
+                }
+
+                // Stream running totals to the UI and enforce the agent's budget
+                if usage.record(&json) {
+                    let snapshot = usage.snapshot();
+                    let _ = app_handle.emit(&format!("agent-usage:{}", run_id), &snapshot);
+                    usage_tx.send_replace(snapshot);
+                }
+                if !budget.is_unlimited()
+                    && !budget_exceeded_clone.load(std::sync::atomic::Ordering::Relaxed)
                 {
-                    if let Some(sid) = json.get("session_id").and_then(|s| s.as_str()) {
-                        if let Ok(mut current_session_id) = session_id_clone.lock() {
-                            if current_session_id.is_empty() {
-                                *current_session_id = sid.to_string();
-                                info!("🔑 Extracted session ID: {}", sid);
-
-                                // Update database immediately with session ID
-                                if let Ok(conn) = Connection::open(&db_path_for_stdout) {
-                                    match conn.execute(
-                                        "UPDATE agent_runs SET session_id = ?1 WHERE id = ?2",
-                                        params![sid, run_id],
-                                    ) {
-                                        Ok(rows) => {
-                                            if rows > 0 {
-                                                info!("✅ Updated agent run {} with session ID immediately", run_id);
-                                            }
-                                        }
-                                        Err(e) => {
-                                            error!(
-                                                "❌ Failed to update session ID immediately: {}",
-                                                e
-                                            );
+                    if let Some(reason) = usage.exceeded(&budget) {
+                        budget_exceeded_clone.store(true, std::sync::atomic::Ordering::Relaxed);
+                        stop_over_budget_run(
+                            &app_handle,
+                            &db_path_for_stdout,
+                            registry_clone.clone(),
+                            run_id,
+                            &reason,
+                        );
+                    }
+                }
+
+                if let StreamMessage::Init(init) = &message {
+                    let sid = init.session_id.as_str();
+                    if let Ok(mut current_session_id) = session_id_clone.lock() {
+                        if current_session_id.is_empty() {
+                            *current_session_id = sid.to_string();
+                            info!("🔑 Extracted session ID: {}", sid);
+
+                            // Update database immediately with session ID
+                            if let Ok(conn) = Connection::open(&db_path_for_stdout) {
+                                match conn.execute(
+                                    "UPDATE agent_runs SET session_id = ?1 WHERE id = ?2",
+                                    params![sid, run_id],
+                                ) {
+                                    Ok(rows) => {
+                                        if rows > 0 {
+                                            info!("✅ Updated agent run {} with session ID immediately", run_id);
                                         }
                                     }
+                                    Err(e) => {
+                                        error!("❌ Failed to update session ID immediately: {}", e);
+                                    }
                                 }
                             }
                         }
@@ -945,11 +2074,15 @@ async fn spawn_agent_system(
                 }
             }
 
-            // Emit the line to the frontend with run_id for isolation
-            let _ = app_handle.emit(&format!("agent-output:{}", run_id), &line);
+            // Also store in process registry for cross-session access, and emit the
+            // line to the frontend with run_id for isolation
+            let _ = registry_clone.append_and_emit_live_output(run_id, &line, |line| {
+                batcher.push(&format!("agent-output:{}", run_id), line);
+            });
             // Also emit to the generic event for backward compatibility
-            let _ = app_handle.emit("agent-output", &line);
+            batcher.push("agent-output", &line);
         }
+        batcher.finish().await;
 
         info!(
             "📖 Finished reading Claude stdout. Total lines: {}",
@@ -962,12 +2095,19 @@ async fn spawn_agent_system(
     let first_error_clone = first_error.clone();
 
     let stderr_task = tokio::spawn(async move {
+        // On a terminal stderr arrives merged into the output
+        let Some(stderr_reader) = stderr_reader else {
+            return;
+        };
         info!("📖 Starting to read Claude stderr...");
         let mut lines = stderr_reader.lines();
         let mut error_count = 0;
 
         while let Ok(Some(line)) = lines.next_line().await {
             error_count += 1;
+            output_bytes_stderr
+                .fetch_add(line.len() as u64 + 1, std::sync::atomic::Ordering::Relaxed);
+            output_log_stderr.write("stderr", &line);
 
             // Log first error
             if !first_error_clone.load(std::sync::atomic::Ordering::Relaxed) {
@@ -975,11 +2115,18 @@ async fn spawn_agent_system(
                 first_error_clone.store(true, std::sync::atomic::Ordering::Relaxed);
             }
 
-            error!("stderr[{}]: {}", error_count, line);
+            let classified = classify_stderr(&line);
+            if classified.severity >= StderrSeverity::Error {
+                error!("stderr[{}]: {}", error_count, line);
+            } else {
+                warn!("stderr[{}]: {}", error_count, line);
+            }
             // Emit error lines to the frontend with run_id for isolation
             let _ = app_handle_stderr.emit(&format!("agent-error:{}", run_id), &line);
             // Also emit to the generic event for backward compatibility
             let _ = app_handle_stderr.emit("agent-error", &line);
+            // Classified lines on their own channel, so real errors stand out
+            let _ = app_handle_stderr.emit(&format!("agent-stderr:{}", run_id), &classified);
         }
 
         if error_count > 0 {
@@ -992,10 +2139,20 @@ async fn spawn_agent_system(
         }
     });
 
-    // Register the process in the registry for live output tracking (after stdout/stderr setup)
-    registry
-        .0
-        .register_process(
+    // Register the process in the registry for live output tracking (after stdout/stderr setup).
+    // A process on a terminal is owned by its reader thread, so it is tracked by PID only.
+    match child {
+        Some(child) => registry.0.register_process(
+            run_id,
+            agent_id,
+            agent_name,
+            pid,
+            project_path.clone(),
+            task.clone(),
+            execution_model.clone(),
+            child,
+        ),
+        None => registry.0.register_sidecar_process(
             run_id,
             agent_id,
             agent_name,
@@ -1003,12 +2160,29 @@ async fn spawn_agent_system(
             project_path.clone(),
             task.clone(),
             execution_model.clone(),
-            child,
-        )
-        .map_err(|e| format!("Failed to register process: {}", e))?;
+        ),
+    }
+    .map_err(|e| format!("Failed to register process: {}", e))?;
     info!("📋 Registered process in registry");
+    if let Some(earlier) = &earlier_output {
+        let _ = registry
+            .0
+            .append_live_output(run_id, earlier.trim_end_matches('\n'));
+    }
+
+    // Stop the run if it goes over the agent's time, memory or output limits
+    let limit_exceeded = spawn_limit_watchdog(
+        app.clone(),
+        registry.0.clone(),
+        run_id,
+        pid,
+        run_limits,
+        output_bytes,
+        usage_rx_for_limits,
+    );
 
     let db_path_for_monitor = db_path.clone(); // Clone for the monitor task
+    let registry_for_monitor = registry.0.clone();
 
     // Monitor process status and wait for completion
     tokio::spawn(async move {
@@ -1060,15 +2234,40 @@ async fn spawn_agent_system(
                 }
 
                 // Update database
+                let mut marked_failed = false;
                 if let Ok(conn) = Connection::open(&db_path_for_monitor) {
-                    let _ = conn.execute(
-                        "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
-                        params![run_id],
-                    );
+                    marked_failed = conn
+                        .execute(
+                            "UPDATE agent_runs SET status = 'failed', failure_reason = ?2, completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
+                            params![run_id, RetryCondition::Timeout.as_str()],
+                        )
+                        .map(|rows| rows > 0)
+                        .unwrap_or(false);
+                }
+
+                let diff_db_path = db_path_for_monitor.clone();
+                let _ =
+                    tokio::task::spawn_blocking(move || capture_run_diff(&diff_db_path, run_id))
+                        .await;
+                let output = live_output.lock().map(|o| o.clone()).unwrap_or_default();
+                if marked_failed {
+                    notify_run_finished(&app, &db_path_for_monitor, run_id, &output);
                 }
+                let search_db_path = db_path_for_monitor.clone();
+                let _ = tokio::task::spawn_blocking(move || {
+                    record_run_usage(&search_db_path, run_id, &output);
+                    index_finished_run(&search_db_path, run_id, &output)
+                })
+                .await;
 
                 let _ = app.emit("agent-complete", false);
                 let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+                if marked_failed
+                    && !schedule_retry(&app, &db_path_for_monitor, run_id, RetryCondition::Timeout)
+                {
+                    comment_on_run_issues(&db_path_for_monitor, run_id);
+                }
+                notify_run_queue(&app);
                 return;
             }
 
@@ -1091,19 +2290,93 @@ async fn spawn_agent_system(
         };
 
         // Wait for process completion and update status
-        info!("✅ Claude process execution monitoring complete");
+        let exit_timeout = tokio::time::Duration::from_secs(10);
+        let exit = match pty_exit {
+            Some(pty_exit) => tokio::time::timeout(exit_timeout, pty_exit)
+                .await
+                .ok()
+                .and_then(|result| result.ok()),
+            None => tokio::time::timeout(exit_timeout, registry_for_monitor.wait_for_exit(run_id))
+                .await
+                .ok()
+                .and_then(|result| result.ok()),
+        };
+        let exit_code = exit.flatten();
+        info!(
+            "✅ Claude process execution monitoring complete (exit code: {:?})",
+            exit_code
+        );
+
+        // A process that died mid-run continues its session if the agent allows restarts
+        let saw_result = saw_result.load(std::sync::atomic::Ordering::Relaxed);
+        if exit.is_some()
+            && is_crash(exit_code, saw_result)
+            && !budget_exceeded.load(std::sync::atomic::Ordering::Relaxed)
+            && !limit_exceeded.load(std::sync::atomic::Ordering::Relaxed)
+            && !registry_for_monitor.was_interrupted(run_id)
+        {
+            let output = live_output.lock().map(|o| o.clone()).unwrap_or_default();
+            let restart_db_path = db_path_for_monitor.clone();
+            let restart = tokio::task::spawn_blocking(move || {
+                Connection::open(&restart_db_path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|conn| queue_crash_restart(&conn, run_id, exit_code, &output))
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|restart| restart);
+            match restart {
+                Ok(Some((restart, marker))) => {
+                    let _ = app.emit(&format!("agent-output:{}", run_id), &marker);
+                    let _ = app.emit("agent-output", &marker);
+                    let _ = app.emit(
+                        "agent-restarted",
+                        serde_json::json!({
+                            "run_id": run_id,
+                            "restart": restart,
+                            "exit_code": exit_code,
+                        }),
+                    );
+                    notify_run_queue(&app);
+                    return;
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to restart crashed agent run {}: {}", run_id, e),
+            }
+        }
 
-        // Update the run record with session ID and mark as completed - open a new connection
+        let failure = if error_result.load(std::sync::atomic::Ordering::Relaxed) {
+            Some(RetryCondition::ErrorResult)
+        } else if exit_code.is_some_and(|code| code != 0) {
+            Some(RetryCondition::NonZeroExit)
+        } else {
+            None
+        };
+        let final_status = if failure.is_some() {
+            "failed"
+        } else {
+            "completed"
+        };
+        let mut status_updated = false;
+
+        // Update the run record with session ID and final status - open a new connection.
+        // Runs that were cancelled in the meantime keep their status.
         if let Ok(conn) = Connection::open(&db_path_for_monitor) {
             info!(
                 "🔄 Updating database with extracted session ID: {}",
                 extracted_session_id
             );
             match conn.execute(
-                "UPDATE agent_runs SET session_id = ?1, status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?2",
-                params![extracted_session_id, run_id],
+                "UPDATE agent_runs SET session_id = ?1, status = ?3, failure_reason = ?4, completed_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'running'",
+                params![
+                    extracted_session_id,
+                    run_id,
+                    final_status,
+                    failure.map(|f| f.as_str())
+                ],
             ) {
                 Ok(rows_affected) => {
+                    status_updated = rows_affected > 0;
                     if rows_affected > 0 {
                         info!("✅ Successfully updated agent run {} with session ID: {}", run_id, extracted_session_id);
                     } else {
@@ -1121,15 +2394,67 @@ async fn spawn_agent_system(
             );
         }
 
+        // The finished-run steps below each open their own connection to agents.db,
+        // since the monitor doesn't hold the app's shared one
+
+        // Record what the run changed before anything else touches the project
+        let diff_db_path = db_path_for_monitor.clone();
+        let _ = tokio::task::spawn_blocking(move || capture_run_diff(&diff_db_path, run_id)).await;
+
+        // Keep copies of the run's output files so they outlive later edits
+        let declared = declared_artifacts
+            .lock()
+            .map(|d| d.clone())
+            .unwrap_or_default();
+        let artifacts_app_dir = app_dir.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            capture_run_artifacts(&artifacts_app_dir, run_id, started_at, &declared)
+        })
+        .await;
+
+        // Make the run findable by what it said and did
+        let output = live_output.lock().map(|o| o.clone()).unwrap_or_default();
+        notify_run_finished(&app, &db_path_for_monitor, run_id, &output);
+        let search_db_path = db_path_for_monitor.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            record_run_usage(&search_db_path, run_id, &output);
+            record_structured_output(&search_db_path, run_id, &output);
+            index_finished_run(&search_db_path, run_id, &output)
+        })
+        .await;
+
         // Cleanup will be handled by the cleanup_finished_processes function
 
-        let _ = app.emit("agent-complete", true);
-        let _ = app.emit(&format!("agent-complete:{}", run_id), true);
+        let succeeded = failure.is_none()
+            && !budget_exceeded.load(std::sync::atomic::Ordering::Relaxed)
+            && !limit_exceeded.load(std::sync::atomic::Ordering::Relaxed);
+        let _ = app.emit("agent-complete", succeeded);
+        let _ = app.emit(&format!("agent-complete:{}", run_id), succeeded);
+        // A run stopped on purpose exits with an error, but didn't fail
+        let interrupted = registry_for_monitor.was_interrupted(run_id);
+        let retrying = match (failure, status_updated, interrupted) {
+            (Some(condition), true, false) => {
+                schedule_retry(&app, &db_path_for_monitor, run_id, condition)
+            }
+            _ => false,
+        };
+        // A retried run leaves commenting on its issues to its last attempt
+        if status_updated && !retrying {
+            comment_on_run_issues(&db_path_for_monitor, run_id);
+        }
+        notify_run_queue(&app);
     });
 
     Ok(run_id)
 }
 
+/// Wake the run queue dispatcher so waiting runs can take a freed slot
+fn notify_run_queue(app: &AppHandle) {
+    if let Some(queue) = app.try_state::<crate::process::RunQueueState>() {
+        queue.notify();
+    }
+}
+
 /// List all currently running agent sessions
 #[tauri::command]
 pub async fn list_running_sessions(
@@ -1204,50 +2529,56 @@ pub async fn kill_agent_session(
 ) -> Result<bool, String> {
     info!("Attempting to kill agent session {}", run_id);
 
-    // First try to kill using the process registry
-    let killed_via_registry = match registry.0.kill_process(run_id).await {
-        Ok(success) => {
-            if success {
-                info!("Successfully killed process {} via registry", run_id);
-                true
-            } else {
-                warn!("Process {} not found in registry", run_id);
-                false
-            }
-        }
-        Err(e) => {
-            warn!("Failed to kill process {} via registry: {}", run_id, e);
-            false
-        }
-    };
-
-    // If registry kill didn't work, try fallback with PID from database
-    if !killed_via_registry {
-        let pid_result = {
-            let conn = db.0.lock().map_err(|e| e.to_string())?;
-            conn.query_row(
+    // Mark the run cancelled before signalling it, so its exit isn't taken for
+    // a crash and restarted
+    let (grace, pid, updated) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let pid = conn
+            .query_row(
                 "SELECT pid FROM agent_runs WHERE id = ?1 AND status = 'running'",
                 params![run_id],
                 |row| row.get::<_, Option<i64>>(0),
             )
+            .optional()
             .map_err(|e| e.to_string())?
-        };
+            .flatten();
+        let updated = conn.execute(
+            "UPDATE agent_runs SET status = 'cancelled', queue_position = NULL, completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status IN ('running', 'queued')",
+            params![run_id],
+        ).map_err(|e| e.to_string())?;
+        (grace_period(&conn), pid, updated)
+    };
+    notify_run_queue(&app);
 
-        if let Some(pid) = pid_result {
-            info!("Attempting fallback kill for PID {} from database", pid);
-            let _ = registry.0.kill_process_by_pid(run_id, pid as u32)?;
+    // Interrupt the process first so it can finish its current tool call,
+    // escalating to a kill after the grace period
+    let mut outcome = match registry.0.cancel_process(run_id, grace).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            warn!("Failed to cancel process {} via registry: {}", run_id, e);
+            CancelOutcome::NotRunning
         }
+    };
+    let killed_via_registry = outcome != CancelOutcome::NotRunning;
+    if killed_via_registry {
+        info!("Stopped process {} via registry: {:?}", run_id, outcome);
+    } else {
+        warn!("Process {} not found in registry", run_id);
     }
 
-    // Update the database to mark as cancelled
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let updated = conn.execute(
-        "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
-        params![run_id],
-    ).map_err(|e| e.to_string())?;
+    // If registry kill didn't work, try fallback with PID from database
+    if !killed_via_registry {
+        if let Some(pid) = pid {
+            info!("Attempting fallback kill for PID {} from database", pid);
+            if registry.0.kill_process_by_pid(run_id, pid as u32)? {
+                outcome = CancelOutcome::ForceKilled;
+            }
+        }
+    }
 
     // Emit cancellation event with run_id for proper isolation
     let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+    let _ = app.emit(&format!("agent-cancellation:{}", run_id), outcome);
 
     Ok(updated > 0 || killed_via_registry)
 }
@@ -1271,6 +2602,33 @@ pub async fn get_session_status(
     }
 }
 
+/// Whether a process with the given PID still exists
+pub fn is_process_alive(pid: u32) -> bool {
+    if cfg!(target_os = "windows") {
+        // On Windows, use tasklist to check if process exists
+        match std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid)])
+            .args(["/FO", "CSV"])
+            .output()
+        {
+            Ok(output) => {
+                let output_str = String::from_utf8_lossy(&output.stdout);
+                output_str.lines().count() > 1 // Header + process line if exists
+            }
+            Err(_) => false,
+        }
+    } else {
+        // On Unix-like systems, use kill -0 to check if process exists
+        match std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .output()
+        {
+            Ok(output) => output.status.success(),
+            Err(_) => false,
+        }
+    }
+}
+
 /// Cleanup finished processes and update their status
 #[tauri::command]
 pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i64>, String> {
@@ -1292,30 +2650,7 @@ pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i6
     let mut cleaned_up = Vec::new();
 
     for (run_id, pid) in running_processes {
-        // Check if the process is still running
-        let is_running = if cfg!(target_os = "windows") {
-            // On Windows, use tasklist to check if process exists
-            match std::process::Command::new("tasklist")
-                .args(["/FI", &format!("PID eq {}", pid)])
-                .args(["/FO", "CSV"])
-                .output()
-            {
-                Ok(output) => {
-                    let output_str = String::from_utf8_lossy(&output.stdout);
-                    output_str.lines().count() > 1 // Header + process line if exists
-                }
-                Err(_) => false,
-            }
-        } else {
-            // On Unix-like systems, use kill -0 to check if process exists
-            match std::process::Command::new("kill")
-                .args(["-0", &pid.to_string()])
-                .output()
-            {
-                Ok(output) => output.status.success(),
-                Err(_) => false,
-            }
-        };
+        let is_running = is_process_alive(pid as u32);
 
         if !is_running {
             // Process has finished, update status
@@ -1534,27 +2869,48 @@ pub async fn export_agent(db: State<'_, AgentDb>, id: i64) -> Result<String, Str
     // Fetch the agent
     let agent = conn
         .query_row(
-            "SELECT name, icon, system_prompt, default_task, model, hooks FROM agents WHERE id = ?1",
+            "SELECT name, icon, system_prompt, default_task, model, hooks, enable_file_read, enable_file_write, enable_network FROM agents WHERE id = ?1",
             params![id],
             |row| {
-                Ok(serde_json::json!({
-                    "name": row.get::<_, String>(0)?,
-                    "icon": row.get::<_, String>(1)?,
-                    "system_prompt": row.get::<_, String>(2)?,
-                    "default_task": row.get::<_, Option<String>>(3)?,
-                    "model": row.get::<_, String>(4)?,
-                    "hooks": row.get::<_, Option<String>>(5)?
-                }))
+                Ok(AgentData {
+                    name: row.get(0)?,
+                    icon: row.get(1)?,
+                    system_prompt: row.get(2)?,
+                    default_task: row.get(3)?,
+                    model: row.get(4)?,
+                    hooks: row.get(5)?,
+                    enable_file_read: Some(row.get(6)?),
+                    enable_file_write: Some(row.get(7)?),
+                    enable_network: Some(row.get(8)?),
+                    parameters: None,
+                    permission_profile: None,
+                    run_limits: None,
+                    crash_restart_policy: None,
+                    notification_channels: None,
+                })
             },
         )
         .map_err(|e| format!("Failed to fetch agent: {}", e))?;
+    let parameters = load_agent_parameters(&conn, id)?;
+    let run_limits = load_run_limits(&conn, id)?;
+    let crash_restart_policy = load_crash_restart_policy(&conn, id)?;
+    let notification_channels = load_agent_channel_names(&conn, id)?;
+    let agent = AgentData {
+        parameters: (!parameters.is_empty()).then_some(parameters),
+        permission_profile: load_permission_profile(&conn, id)?,
+        run_limits: (!run_limits.is_unlimited()).then_some(run_limits),
+        crash_restart_policy: (crash_restart_policy.max_restarts > 0)
+            .then_some(crash_restart_policy),
+        notification_channels: (!notification_channels.is_empty()).then_some(notification_channels),
+        ..agent
+    };
 
     // Create the export wrapper
-    let export_data = serde_json::json!({
-        "version": 1,
-        "exported_at": chrono::Utc::now().to_rfc3339(),
-        "agent": agent
-    });
+    let export_data = AgentExport {
+        version: AGENT_EXPORT_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        agent,
+    };
 
     // Convert to pretty JSON string
     serde_json::to_string_pretty(&export_data)
@@ -1631,12 +2987,7 @@ pub async fn set_claude_binary_path(db: State<'_, AgentDb>, path: String) -> Res
     }
 
     // Insert or update the setting
-    conn.execute(
-        "INSERT INTO app_settings (key, value) VALUES ('claude_binary_path', ?1)
-         ON CONFLICT(key) DO UPDATE SET value = ?1",
-        params![path],
-    )
-    .map_err(|e| format!("Failed to save Claude binary path: {}", e))?;
+    save_setting(&conn, "claude_binary_path", &path)?;
 
     Ok(())
 }
@@ -1715,53 +3066,139 @@ fn create_command_with_env(program: &str) -> Command {
 
 /// Import an agent from JSON data
 #[tauri::command]
-pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<Agent, String> {
+pub async fn import_agent(
+    db: State<'_, AgentDb>,
+    json_data: String,
+    on_conflict: Option<ImportConflictStrategy>,
+) -> Result<Agent, String> {
     // Parse the JSON data
     let export_data: AgentExport =
         serde_json::from_str(&json_data).map_err(|e| format!("Invalid JSON format: {}", e))?;
 
-    // Validate version
-    if export_data.version != 1 {
-        return Err(format!(
-            "Unsupported export version: {}. This version of the app only supports version 1.",
-            export_data.version
-        ));
-    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    import_agent_export(&conn, export_data, on_conflict.unwrap_or_default())
+}
+
+/// Validate and import an agent export, settling a clash with an existing
+/// agent's name by `strategy`
+pub fn import_agent_export(
+    conn: &Connection,
+    export_data: AgentExport,
+    strategy: ImportConflictStrategy,
+) -> Result<Agent, String> {
+    validate_agent_export(&export_data)?;
 
     let agent_data = export_data.agent;
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     // Check if an agent with the same name already exists
-    let existing_count: i64 = conn
+    let existing_id: Option<i64> = conn
         .query_row(
-            "SELECT COUNT(*) FROM agents WHERE name = ?1",
+            "SELECT id FROM agents WHERE name = ?1 ORDER BY id LIMIT 1",
             params![agent_data.name],
             |row| row.get(0),
         )
+        .optional()
         .map_err(|e| e.to_string())?;
 
-    // If agent with same name exists, append a suffix
-    let final_name = if existing_count > 0 {
-        format!("{} (Imported)", agent_data.name)
-    } else {
-        agent_data.name
+    // Version 1 files carry no permissions; keep the defaults imports always used
+    let enable_file_read = agent_data.enable_file_read.unwrap_or(true);
+    let enable_file_write = agent_data.enable_file_write.unwrap_or(true);
+    let enable_network = agent_data.enable_network.unwrap_or(false);
+
+    let skipped = existing_id.is_some() && strategy == ImportConflictStrategy::Skip;
+
+    let id = match (existing_id, strategy) {
+        (Some(existing_id), ImportConflictStrategy::Skip) => {
+            info!("Skipping import of existing agent '{}'", agent_data.name);
+            existing_id
+        }
+        (Some(existing_id), ImportConflictStrategy::Overwrite) => {
+            conn.execute(
+                "UPDATE agents SET icon = ?1, system_prompt = ?2, default_task = ?3, model = ?4, enable_file_read = ?5, enable_file_write = ?6, enable_network = ?7, hooks = ?8 WHERE id = ?9",
+                params![
+                    agent_data.icon,
+                    agent_data.system_prompt,
+                    agent_data.default_task,
+                    agent_data.model,
+                    enable_file_read,
+                    enable_file_write,
+                    enable_network,
+                    agent_data.hooks,
+                    existing_id
+                ],
+            )
+            .map_err(|e| format!("Failed to overwrite agent: {}", e))?;
+            existing_id
+        }
+        (existing_id, _) => {
+            // If agent with same name exists, find an unused name
+            let final_name = if existing_id.is_some() {
+                let mut candidate = format!("{} (Imported)", agent_data.name);
+                let mut suffix = 2;
+                while conn
+                    .query_row(
+                        "SELECT 1 FROM agents WHERE name = ?1",
+                        params![candidate],
+                        |_| Ok(()),
+                    )
+                    .optional()
+                    .map_err(|e| e.to_string())?
+                    .is_some()
+                {
+                    candidate = format!("{} (Imported {})", agent_data.name, suffix);
+                    suffix += 1;
+                }
+                candidate
+            } else {
+                agent_data.name
+            };
+
+            // Create the agent
+            conn.execute(
+                "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    final_name,
+                    agent_data.icon,
+                    agent_data.system_prompt,
+                    agent_data.default_task,
+                    agent_data.model,
+                    enable_file_read,
+                    enable_file_write,
+                    enable_network,
+                    agent_data.hooks
+                ],
+            )
+            .map_err(|e| format!("Failed to create agent: {}", e))?;
+
+            conn.last_insert_rowid()
+        }
     };
 
-    // Create the agent
-    conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, 1, 1, 0, ?6)",
-        params![
-            final_name,
-            agent_data.icon,
-            agent_data.system_prompt,
-            agent_data.default_task,
-            agent_data.model,
-            agent_data.hooks
-        ],
-    )
-    .map_err(|e| format!("Failed to create agent: {}", e))?;
+    // Skipped imports keep the existing agent untouched, parameters included
+    if let (Some(parameters), false) = (&agent_data.parameters, skipped) {
+        let json = serde_json::to_string(parameters).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO agent_parameter_schemas (agent_id, parameters) VALUES (?1, ?2)",
+            params![id, json],
+        )
+        .map_err(|e| format!("Failed to save agent parameters: {}", e))?;
+    }
 
-    let id = conn.last_insert_rowid();
+    if let (Some(profile), false) = (&agent_data.permission_profile, skipped) {
+        save_permission_profile(conn, id, Some(profile))?;
+    }
+    if let (Some(limits), false) = (&agent_data.run_limits, skipped) {
+        save_run_limits(conn, id, limits)?;
+    }
+    if let (Some(policy), false) = (&agent_data.crash_restart_policy, skipped) {
+        save_crash_restart_policy(conn, id, policy)?;
+    }
+    if let (Some(channels), false) = (&agent_data.notification_channels, skipped) {
+        link_agent_channels_by_name(conn, id, channels)?;
+    }
+    if !skipped {
+        record_agent_revision(conn, id)?;
+    }
 
     // Fetch the created agent
     let agent = conn
@@ -1785,7 +3222,7 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
                 })
             },
         )
-        .map_err(|e| format!("Failed to fetch created agent: {}", e))?;
+        .map_err(|e| format!("Failed to fetch imported agent: {}", e))?;
 
     Ok(agent)
 }
@@ -1795,6 +3232,7 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
 pub async fn import_agent_from_file(
     db: State<'_, AgentDb>,
     file_path: String,
+    on_conflict: Option<ImportConflictStrategy>,
 ) -> Result<Agent, String> {
     // Read the file
     let mut json_data =
@@ -1808,7 +3246,7 @@ pub async fn import_agent_from_file(
     json_data = json_data.trim().to_string();
 
     // Import the agent
-    import_agent(db, json_data).await
+    import_agent(db, json_data, on_conflict).await
 }
 
 // GitHub Agent Import functionality
@@ -1911,13 +3349,7 @@ pub async fn fetch_github_agent_content(download_url: String) -> Result<AgentExp
     let export_data: AgentExport = serde_json::from_str(&json_text)
         .map_err(|e| format!("Invalid agent JSON format: {}", e))?;
 
-    // Validate version
-    if export_data.version != 1 {
-        return Err(format!(
-            "Unsupported agent version: {}",
-            export_data.version
-        ));
-    }
+    validate_agent_export(&export_data)?;
 
     Ok(export_data)
 }
@@ -1938,7 +3370,271 @@ pub async fn import_agent_from_github(
         .map_err(|e| format!("Failed to serialize agent data: {}", e))?;
 
     // Import using existing function
-    import_agent(db, json_data).await
+    import_agent(db, json_data, None).await
+}
+
+/// Directories searched (in order) when importing agents from a repository
+const REPO_AGENT_DIRECTORIES: &[&str] = &[".opcode/agents", "cc_agents", "agents"];
+
+/// A GitHub repository reference parsed from a user-supplied URL
+#[derive(Debug, Clone, PartialEq)]
+struct GitHubRepoRef {
+    owner: String,
+    repo: String,
+    /// Segments after `/tree/`: a branch, which may itself contain `/`,
+    /// followed by an optional path
+    tree: Vec<String>,
+}
+
+impl GitHubRepoRef {
+    /// Every way of splitting the `/tree/` segments into a branch and a path,
+    /// shortest branch first. Git refs can't be prefixes of one another, so at
+    /// most one of them names a branch that exists.
+    fn branch_candidates(&self) -> Vec<(Option<String>, Option<String>)> {
+        if self.tree.is_empty() {
+            return vec![(None, None)];
+        }
+        (1..=self.tree.len())
+            .map(|split| {
+                let path = self.tree[split..].join("/");
+                (
+                    Some(self.tree[..split].join("/")),
+                    Some(path).filter(|p| !p.is_empty()),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Parse `owner/repo`, `https://github.com/owner/repo(.git)` or
+/// `https://github.com/owner/repo/tree/<branch>/<path>` into its parts
+fn parse_github_repo_url(url: &str) -> Result<GitHubRepoRef, String> {
+    let invalid = || {
+        format!(
+            "Invalid GitHub repository '{}'. Expected owner/repo or a github.com URL",
+            url
+        )
+    };
+
+    let trimmed = url.trim().trim_end_matches('/');
+    let (has_scheme, without_scheme) = match trimmed
+        .strip_prefix("https://")
+        .or_else(|| trimmed.strip_prefix("http://"))
+    {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+    let without_host = match without_scheme
+        .strip_prefix("github.com/")
+        .or_else(|| without_scheme.strip_prefix("www.github.com/"))
+    {
+        Some(rest) => rest,
+        None if has_scheme => {
+            return Err(format!(
+                "Only github.com repositories are supported, not '{}'",
+                url
+            ))
+        }
+        None => without_scheme,
+    };
+
+    let parts: Vec<&str> = without_host.split('/').filter(|p| !p.is_empty()).collect();
+    if parts.len() < 2 {
+        return Err(invalid());
+    }
+
+    // Owners are letters, digits and dashes, so another host such as
+    // gitlab.com/a/b doesn't pass for an owner
+    let owner = parts[0];
+    let repo = parts[1].trim_end_matches(".git");
+    let valid_owner = owner.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    let valid_repo = !repo.is_empty()
+        && repo
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid_owner || !valid_repo {
+        return Err(invalid());
+    }
+
+    let tree = if parts.len() >= 4 && parts[2] == "tree" {
+        parts[3..].iter().map(|part| part.to_string()).collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(GitHubRepoRef {
+        owner: owner.to_string(),
+        repo: repo.to_string(),
+        tree,
+    })
+}
+
+/// List agent JSON files in a repository directory, returning None if the directory doesn't exist
+async fn list_repo_agent_files(
+    client: &reqwest::Client,
+    repo_ref: &GitHubRepoRef,
+    branch: Option<&str>,
+    directory: &str,
+) -> Result<Option<Vec<GitHubAgentFile>>, String> {
+    let url = format!(
+        "https://api.github.com/repos/{}/{}/contents/{}",
+        repo_ref.owner, repo_ref.repo, directory
+    );
+    let mut request = client.get(&url);
+    if let Some(branch) = branch {
+        request = request.query(&[("ref", branch)]);
+    }
+
+    let response = request
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "opcode-App")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch from GitHub: {}", e))?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("GitHub API error ({}): {}", status, error_text));
+    }
+
+    // A path that points at a file returns an object instead of an array
+    let api_files: Vec<GitHubApiResponse> = match response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("Failed to parse GitHub response: {}", e))?
+    {
+        JsonValue::Array(items) => items
+            .into_iter()
+            .filter_map(|item| serde_json::from_value(item).ok())
+            .collect(),
+        _ => return Ok(None),
+    };
+
+    let agent_files = api_files
+        .into_iter()
+        .filter(|f| f.name.ends_with(".json") && f.file_type == "file")
+        .filter_map(|f| {
+            f.download_url.map(|download_url| GitHubAgentFile {
+                name: f.name,
+                path: f.path,
+                download_url,
+                size: f.size,
+                sha: f.sha,
+            })
+        })
+        .collect();
+
+    Ok(Some(agent_files))
+}
+
+/// A file that could not be imported from a repository
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepoAgentImportFailure {
+    pub file: String,
+    pub error: String,
+}
+
+/// Result of importing all agents from a repository
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepoAgentImportResult {
+    pub repository: String,
+    pub directory: String,
+    pub imported: Vec<Agent>,
+    pub failed: Vec<RepoAgentImportFailure>,
+}
+
+/// Import every agent found in a GitHub repository's agents directory
+#[tauri::command]
+pub async fn import_agents_from_repo(
+    db: State<'_, AgentDb>,
+    url: String,
+) -> Result<RepoAgentImportResult, String> {
+    let repo_ref = parse_github_repo_url(&url)?;
+    let repository = format!("{}/{}", repo_ref.owner, repo_ref.repo);
+    info!("Importing agents from GitHub repository {}", repository);
+
+    let client = reqwest::Client::new();
+
+    // A branch or directory that doesn't exist is a 404, so the one way of
+    // reading a `/tree/` URL that lists files is the right one
+    let mut found = None;
+    'search: for (branch, path) in repo_ref.branch_candidates() {
+        // An explicit path in the URL wins over the well-known directories
+        let directories: Vec<String> = match path {
+            Some(path) => vec![path],
+            None => REPO_AGENT_DIRECTORIES
+                .iter()
+                .map(|d| d.to_string())
+                .collect(),
+        };
+        for directory in directories {
+            if let Some(files) =
+                list_repo_agent_files(&client, &repo_ref, branch.as_deref(), &directory).await?
+            {
+                found = Some((directory, files));
+                break 'search;
+            }
+        }
+    }
+
+    let (directory, files) = found.ok_or_else(|| {
+        format!(
+            "No agents directory found in {} (looked for {})",
+            repository,
+            REPO_AGENT_DIRECTORIES.join(", ")
+        )
+    })?;
+
+    if files.is_empty() {
+        return Err(format!(
+            "No agent files found in {}/{}",
+            repository, directory
+        ));
+    }
+
+    let mut imported = Vec::new();
+    let mut failed = Vec::new();
+
+    for file in files {
+        // fetch_github_agent_content validates the export format and version
+        let result = match fetch_github_agent_content(file.download_url.clone()).await {
+            Ok(export_data) => match serde_json::to_string(&export_data) {
+                Ok(json_data) => import_agent(db.clone(), json_data, None).await,
+                Err(e) => Err(format!("Failed to serialize agent data: {}", e)),
+            },
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(agent) => imported.push(agent),
+            Err(error) => {
+                warn!("Skipping {} from {}: {}", file.path, repository, error);
+                failed.push(RepoAgentImportFailure {
+                    file: file.path,
+                    error,
+                });
+            }
+        }
+    }
+
+    info!(
+        "Imported {} agents from {} ({} failed)",
+        imported.len(),
+        repository,
+        failed.len()
+    );
+
+    Ok(RepoAgentImportResult {
+        repository,
+        directory,
+        imported,
+        failed,
+    })
 }
 
 /// Load agent session history from JSONL file
@@ -2008,3 +3704,158 @@ pub async fn load_agent_session_history(
         Err(format!("Session file not found: {}", session_id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github_repo_url() {
+        let repo = |tree: &[&str]| GitHubRepoRef {
+            owner: "octo".to_string(),
+            repo: "agents".to_string(),
+            tree: tree.iter().map(|s| s.to_string()).collect(),
+        };
+        assert_eq!(parse_github_repo_url("octo/agents").unwrap(), repo(&[]));
+        assert_eq!(
+            parse_github_repo_url("https://github.com/octo/agents.git").unwrap(),
+            repo(&[])
+        );
+        assert_eq!(
+            parse_github_repo_url("www.github.com/octo/agents/").unwrap(),
+            repo(&[])
+        );
+        assert_eq!(
+            parse_github_repo_url("https://github.com/octo/agents/tree/main/cc_agents/review")
+                .unwrap(),
+            repo(&["main", "cc_agents", "review"])
+        );
+
+        for url in [
+            "",
+            "octo",
+            "https://gitlab.com/octo/agents",
+            "gitlab.com/octo/agents",
+            "https://github.com.evil.com/octo/agents",
+            "octo/.git",
+        ] {
+            assert!(parse_github_repo_url(url).is_err(), "accepted {:?}", url);
+        }
+    }
+
+    #[test]
+    fn test_branch_candidates_allow_slashes_in_branches() {
+        let parsed =
+            parse_github_repo_url("https://github.com/octo/agents/tree/feature/new/agents")
+                .unwrap();
+        assert_eq!(
+            parsed.branch_candidates(),
+            vec![
+                (Some("feature".to_string()), Some("new/agents".to_string())),
+                (Some("feature/new".to_string()), Some("agents".to_string())),
+                (Some("feature/new/agents".to_string()), None),
+            ]
+        );
+
+        let plain = parse_github_repo_url("octo/agents").unwrap();
+        assert_eq!(plain.branch_candidates(), vec![(None, None)]);
+    }
+
+    fn export(version: u32, agent: serde_json::Value) -> AgentExport {
+        serde_json::from_value(serde_json::json!({
+            "version": version,
+            "exported_at": "2025-01-01T00:00:00Z",
+            "agent": agent,
+        }))
+        .unwrap()
+    }
+
+    fn reviewer(system_prompt: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": "Reviewer",
+            "icon": "bot",
+            "system_prompt": system_prompt,
+            "default_task": null,
+            "model": "sonnet",
+            "hooks": null,
+        })
+    }
+
+    #[test]
+    fn test_validate_agent_export() {
+        assert!(validate_agent_export(&export(1, reviewer("Review"))).is_ok());
+        assert!(validate_agent_export(&export(AGENT_EXPORT_VERSION, reviewer("Review"))).is_ok());
+        assert!(validate_agent_export(&export(0, reviewer("Review"))).is_err());
+        assert!(
+            validate_agent_export(&export(AGENT_EXPORT_VERSION + 1, reviewer("Review"))).is_err()
+        );
+
+        let mut bad = reviewer(" ");
+        bad["name"] = "".into();
+        bad["hooks"] = "{not json".into();
+        let error = validate_agent_export(&export(2, bad)).unwrap_err();
+        assert!(error.contains("name is empty"), "{}", error);
+        assert!(error.contains("system_prompt is empty"), "{}", error);
+        assert!(error.contains("hooks is not valid JSON"), "{}", error);
+    }
+
+    #[test]
+    fn test_import_agent_export_permissions_and_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = open_database(&dir.path().join("agents.db")).unwrap();
+
+        // Version 1 files have no permissions and get the old defaults
+        let v1 = import_agent_export(
+            &conn,
+            export(1, reviewer("v1")),
+            ImportConflictStrategy::Rename,
+        )
+        .unwrap();
+        assert_eq!(v1.name, "Reviewer");
+        assert!(v1.enable_file_read && v1.enable_file_write && !v1.enable_network);
+
+        let mut v2 = reviewer("v2");
+        v2["enable_file_read"] = true.into();
+        v2["enable_file_write"] = false.into();
+        v2["enable_network"] = true.into();
+        let renamed =
+            import_agent_export(&conn, export(2, v2.clone()), ImportConflictStrategy::Rename)
+                .unwrap();
+        assert_eq!(renamed.name, "Reviewer (Imported)");
+        assert!(renamed.enable_file_read && !renamed.enable_file_write && renamed.enable_network);
+        let renamed_again =
+            import_agent_export(&conn, export(2, v2.clone()), ImportConflictStrategy::Rename)
+                .unwrap();
+        assert_eq!(renamed_again.name, "Reviewer (Imported 2)");
+
+        let skipped =
+            import_agent_export(&conn, export(2, v2.clone()), ImportConflictStrategy::Skip)
+                .unwrap();
+        assert_eq!(skipped.id, v1.id);
+        assert_eq!(skipped.system_prompt, "v1");
+
+        let overwritten =
+            import_agent_export(&conn, export(2, v2), ImportConflictStrategy::Overwrite).unwrap();
+        assert_eq!(overwritten.id, v1.id);
+        assert_eq!(overwritten.name, "Reviewer");
+        assert_eq!(overwritten.system_prompt, "v2");
+        assert!(!overwritten.enable_file_write && overwritten.enable_network);
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM agents", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+
+        // Invalid exports are rejected before anything is written
+        assert!(import_agent_export(
+            &conn,
+            export(AGENT_EXPORT_VERSION + 1, reviewer("v3")),
+            ImportConflictStrategy::Rename
+        )
+        .is_err());
+        let count_after: i64 = conn
+            .query_row("SELECT COUNT(*) FROM agents", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count_after, 3);
+    }
+}
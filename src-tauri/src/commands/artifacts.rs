@@ -0,0 +1,350 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager, State};
+use zstd::stream::{decode_all, encode_all};
+
+use crate::commands::agents::AgentDb;
+
+/// Directory inside a project whose new or modified files are captured after a run
+pub const ARTIFACTS_OUTPUT_DIR: &str = ".opcode/artifacts";
+
+/// Prefix an agent uses in its final result to declare an artifact anywhere in the project
+pub const ARTIFACT_DECLARATION_PREFIX: &str = "ARTIFACT:";
+
+/// Files larger than this are not captured
+const MAX_ARTIFACT_SIZE: u64 = 50 * 1024 * 1024;
+
+/// A file captured from an agent run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunArtifact {
+    pub id: i64,
+    pub run_id: i64,
+    /// Path relative to the project root
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    pub mime_type: String,
+    /// "output_dir" or "declared"
+    pub source: String,
+    pub created_at: String,
+}
+
+/// Artifact content; text is returned as-is, anything else base64-encoded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunArtifactContent {
+    pub artifact: RunArtifact,
+    pub encoding: String,
+    pub content: String,
+}
+
+/// Directory holding compressed artifact blobs, named by content hash
+fn artifact_store_dir(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("artifacts")
+}
+
+/// Guess a mime type from the file extension
+pub fn guess_mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" | "mjs" => "text/javascript",
+        "ts" | "tsx" | "rs" | "py" | "go" | "java" | "c" | "h" | "cpp" | "sh" | "toml" | "yaml"
+        | "yml" => "text/plain",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Extract paths declared with `ARTIFACT: <path>` lines in an agent's result text
+pub fn parse_declared_artifacts(result: &str) -> Vec<String> {
+    result
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_start_matches(['-', '*']).trim();
+            let path = line.strip_prefix(ARTIFACT_DECLARATION_PREFIX)?.trim();
+            let path = path.trim_matches('`');
+            (!path.is_empty()).then(|| path.to_string())
+        })
+        .collect()
+}
+
+/// Files in the output directory that were written since the run started
+fn output_dir_files(project_path: &Path, since: SystemTime) -> Vec<PathBuf> {
+    let output_dir = project_path.join(ARTIFACTS_OUTPUT_DIR);
+    if !output_dir.is_dir() {
+        return Vec::new();
+    }
+
+    walkdir::WalkDir::new(&output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .is_some_and(|modified| modified >= since)
+        })
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Store one file in the artifact store and record it against the run
+fn store_artifact(
+    conn: &Connection,
+    store_dir: &Path,
+    run_id: i64,
+    project_path: &Path,
+    file: &Path,
+    source: &str,
+) -> Result<(), String> {
+    let metadata = std::fs::metadata(file).map_err(|e| e.to_string())?;
+    if metadata.len() > MAX_ARTIFACT_SIZE {
+        return Err(format!(
+            "{} is larger than the artifact limit",
+            file.display()
+        ));
+    }
+
+    let content = std::fs::read(file).map_err(|e| e.to_string())?;
+    let sha256 = format!("{:x}", Sha256::digest(&content));
+
+    // Identical content is only stored once
+    let blob_path = store_dir.join(format!("{}.zst", sha256));
+    if !blob_path.exists() {
+        let compressed = encode_all(content.as_slice(), 3).map_err(|e| e.to_string())?;
+        std::fs::write(&blob_path, compressed).map_err(|e| e.to_string())?;
+    }
+
+    let relative = file
+        .strip_prefix(project_path)
+        .unwrap_or(file)
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    conn.execute(
+        "INSERT INTO agent_run_artifacts (run_id, path, size, sha256, mime_type, source) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            run_id,
+            relative,
+            content.len() as i64,
+            sha256,
+            guess_mime_type(file),
+            source
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Capture a finished run's artifacts: new files under the output directory plus declared paths.
+pub fn capture_run_artifacts(
+    app_data_dir: &Path,
+    run_id: i64,
+    started_at: SystemTime,
+    declared: &[String],
+) {
+    let db_path = app_data_dir.join("agents.db");
+    let conn = match Connection::open(&db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to open database to capture artifacts: {}", e);
+            return;
+        }
+    };
+
+    let project_path: String = match conn.query_row(
+        "SELECT project_path FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| row.get(0),
+    ) {
+        Ok(path) => path,
+        Err(e) => {
+            warn!("Failed to load run {} for artifact capture: {}", run_id, e);
+            return;
+        }
+    };
+    let project_path = PathBuf::from(project_path);
+
+    let mut files: Vec<(PathBuf, &str)> = output_dir_files(&project_path, started_at)
+        .into_iter()
+        .map(|file| (file, "output_dir"))
+        .collect();
+    for path in declared {
+        let file = project_path.join(path);
+        // Declared paths must stay inside the project
+        let inside = file
+            .canonicalize()
+            .ok()
+            .zip(project_path.canonicalize().ok())
+            .is_some_and(|(file, root)| file.starts_with(root));
+        if inside && file.is_file() && !files.iter().any(|(f, _)| *f == file) {
+            files.push((file, "declared"));
+        }
+    }
+
+    if files.is_empty() {
+        return;
+    }
+
+    let store_dir = artifact_store_dir(app_data_dir);
+    if let Err(e) = std::fs::create_dir_all(&store_dir) {
+        warn!("Failed to create artifact store: {}", e);
+        return;
+    }
+
+    let mut captured = 0;
+    for (file, source) in &files {
+        match store_artifact(&conn, &store_dir, run_id, &project_path, file, source) {
+            Ok(()) => captured += 1,
+            Err(e) => warn!("Skipping artifact {}: {}", file.display(), e),
+        }
+    }
+    info!("Captured {} artifacts for agent run {}", captured, run_id);
+}
+
+fn row_to_artifact(row: &rusqlite::Row) -> rusqlite::Result<RunArtifact> {
+    Ok(RunArtifact {
+        id: row.get(0)?,
+        run_id: row.get(1)?,
+        path: row.get(2)?,
+        size: row.get::<_, i64>(3)?.max(0) as u64,
+        sha256: row.get(4)?,
+        mime_type: row.get(5)?,
+        source: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+fn load_artifact(
+    app: &AppHandle,
+    db: &AgentDb,
+    artifact_id: i64,
+) -> Result<(RunArtifact, Vec<u8>), String> {
+    let artifact = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT id, run_id, path, size, sha256, mime_type, source, created_at FROM agent_run_artifacts WHERE id = ?1",
+            params![artifact_id],
+            row_to_artifact,
+        )
+        .map_err(|e| format!("Artifact {} not found: {}", artifact_id, e))?
+    };
+
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let blob_path = artifact_store_dir(&app_data_dir).join(format!("{}.zst", artifact.sha256));
+    let compressed =
+        std::fs::read(&blob_path).map_err(|e| format!("Artifact content is missing: {}", e))?;
+    let content = decode_all(compressed.as_slice())
+        .map_err(|e| format!("Failed to decompress artifact: {}", e))?;
+
+    Ok((artifact, content))
+}
+
+/// List the artifacts captured from an agent run
+#[tauri::command]
+pub async fn list_run_artifacts(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Vec<RunArtifact>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, run_id, path, size, sha256, mime_type, source, created_at
+             FROM agent_run_artifacts WHERE run_id = ?1 ORDER BY path ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let artifacts = stmt
+        .query_map(params![run_id], row_to_artifact)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(artifacts)
+}
+
+/// Read the stored content of an artifact
+#[tauri::command]
+pub async fn read_run_artifact(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    artifact_id: i64,
+) -> Result<RunArtifactContent, String> {
+    let (artifact, content) = load_artifact(&app, &db, artifact_id)?;
+
+    let (encoding, content) = match String::from_utf8(content) {
+        Ok(text) => ("utf8", text),
+        Err(e) => (
+            "base64",
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, e.into_bytes()),
+        ),
+    };
+
+    Ok(RunArtifactContent {
+        artifact,
+        encoding: encoding.to_string(),
+        content,
+    })
+}
+
+/// Save a copy of an artifact to the given path
+#[tauri::command]
+pub async fn export_run_artifact(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    artifact_id: i64,
+    file_path: String,
+) -> Result<(), String> {
+    let (_, content) = load_artifact(&app, &db, artifact_id)?;
+    std::fs::write(&file_path, content).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_declared_artifacts() {
+        let result =
+            "Done.\n\nARTIFACT: reports/summary.md\n- ARTIFACT: `out/chart.png`\nARTIFACT:\n";
+        assert_eq!(
+            parse_declared_artifacts(result),
+            vec!["reports/summary.md", "out/chart.png"]
+        );
+    }
+
+    #[test]
+    fn test_guess_mime_type() {
+        assert_eq!(guess_mime_type(Path::new("a/report.MD")), "text/markdown");
+        assert_eq!(guess_mime_type(Path::new("chart.png")), "image/png");
+        assert_eq!(
+            guess_mime_type(Path::new("blob")),
+            "application/octet-stream"
+        );
+    }
+}
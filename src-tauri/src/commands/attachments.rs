@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Largest image Claude accepts
+const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Largest other file worth pointing Claude at
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Where images from outside the project are copied, relative to the project
+const ATTACHMENTS_DIR: &str = ".claude/attachments";
+
+/// What kind of file an attachment is
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    Image,
+    File,
+}
+
+/// A file attached to a prompt, ready to hand to Claude
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PreparedAttachment {
+    /// Path the user attached
+    pub source_path: String,
+    /// Path Claude reads, which is a copy inside the project for outside images
+    pub path: String,
+    pub kind: AttachmentKind,
+    /// MIME type of images
+    pub media_type: Option<String>,
+    pub size: u64,
+}
+
+/// Attachments plus the directories Claude must be given access to
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreparedAttachments {
+    pub attachments: Vec<PreparedAttachment>,
+    /// Directories outside the project holding attached files, for `--add-dir`
+    pub extra_dirs: Vec<String>,
+}
+
+/// MIME type of an image, going by its extension
+fn image_media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Whether a file's first bytes match its image type, so a renamed file isn't sent as one
+fn has_image_signature(header: &[u8], media_type: &str) -> bool {
+    match media_type {
+        "image/png" => header.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "image/jpeg" => header.starts_with(&[0xFF, 0xD8, 0xFF]),
+        "image/gif" => header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a"),
+        "image/webp" => header.len() >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WEBP",
+        _ => false,
+    }
+}
+
+fn read_header(path: &Path) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let mut header = Vec::with_capacity(12);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(12).read_to_end(&mut header))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(header)
+}
+
+/// Validate attached files and make them reachable from the project. Images from
+/// outside the project are copied into `.claude/attachments` so they survive the
+/// originals being cleaned up (screenshots often live in temp folders); other
+/// files stay put and their directories are passed with `--add-dir`.
+pub fn prepare_attachments(
+    project_path: &str,
+    paths: &[String],
+) -> Result<PreparedAttachments, String> {
+    let project = Path::new(project_path)
+        .canonicalize()
+        .map_err(|e| format!("Invalid project path {}: {}", project_path, e))?;
+    let mut prepared = PreparedAttachments::default();
+
+    for source in paths {
+        let path = Path::new(source)
+            .canonicalize()
+            .map_err(|e| format!("Cannot attach {}: {}", source, e))?;
+        let metadata =
+            std::fs::metadata(&path).map_err(|e| format!("Cannot attach {}: {}", source, e))?;
+        if !metadata.is_file() {
+            return Err(format!("Cannot attach {}: not a file", source));
+        }
+        let size = metadata.len();
+        let inside_project = path.starts_with(&project);
+
+        let attachment = match image_media_type(&path) {
+            Some(media_type) => {
+                if size > MAX_IMAGE_BYTES {
+                    return Err(format!(
+                        "Image {} is larger than {} MB",
+                        source,
+                        MAX_IMAGE_BYTES / (1024 * 1024)
+                    ));
+                }
+                if !has_image_signature(&read_header(&path)?, media_type) {
+                    return Err(format!("{} is not a valid {} image", source, media_type));
+                }
+
+                let path = if inside_project {
+                    path
+                } else {
+                    let dir = project.join(ATTACHMENTS_DIR);
+                    std::fs::create_dir_all(&dir)
+                        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+                    let file_name = path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let copy = dir.join(format!(
+                        "{}-{}",
+                        &uuid::Uuid::new_v4().simple().to_string()[..8],
+                        file_name
+                    ));
+                    std::fs::copy(&path, &copy)
+                        .map_err(|e| format!("Failed to copy {}: {}", source, e))?;
+                    copy
+                };
+                PreparedAttachment {
+                    source_path: source.clone(),
+                    path: path.to_string_lossy().to_string(),
+                    kind: AttachmentKind::Image,
+                    media_type: Some(media_type.to_string()),
+                    size,
+                }
+            }
+            None => {
+                if size > MAX_FILE_BYTES {
+                    return Err(format!(
+                        "File {} is larger than {} MB",
+                        source,
+                        MAX_FILE_BYTES / (1024 * 1024)
+                    ));
+                }
+                if !inside_project {
+                    if let Some(dir) = path.parent().map(|d| d.to_string_lossy().to_string()) {
+                        if !prepared.extra_dirs.contains(&dir) {
+                            prepared.extra_dirs.push(dir);
+                        }
+                    }
+                }
+                PreparedAttachment {
+                    source_path: source.clone(),
+                    path: path.to_string_lossy().to_string(),
+                    kind: AttachmentKind::File,
+                    media_type: None,
+                    size,
+                }
+            }
+        };
+        prepared.attachments.push(attachment);
+    }
+
+    Ok(prepared)
+}
+
+/// The prompt with its attachments listed for Claude to read
+pub fn prompt_with_attachments(prompt: &str, attachments: &[PreparedAttachment]) -> String {
+    if attachments.is_empty() {
+        return prompt.to_string();
+    }
+    let mut prompt = format!(
+        "{}\n\nAttached files (read them with the Read tool before answering):",
+        prompt.trim_end()
+    );
+    for attachment in attachments {
+        match &attachment.media_type {
+            Some(media_type) => {
+                prompt.push_str(&format!("\n- {} ({})", attachment.path, media_type))
+            }
+            None => prompt.push_str(&format!("\n- {}", attachment.path)),
+        }
+    }
+    prompt
+}
+
+/// Prepare a prompt's attachments, returning the prompt to send and the extra
+/// CLI arguments that give Claude access to them
+pub fn attach_to_prompt(
+    project_path: &str,
+    prompt: String,
+    paths: Option<Vec<String>>,
+) -> Result<(String, Vec<String>), String> {
+    let paths = paths.unwrap_or_default();
+    if paths.is_empty() {
+        return Ok((prompt, Vec::new()));
+    }
+
+    let prepared = prepare_attachments(project_path, &paths)?;
+    log::info!(
+        "Attaching {} files to prompt in {}",
+        prepared.attachments.len(),
+        project_path
+    );
+    let args = prepared
+        .extra_dirs
+        .into_iter()
+        .flat_map(|dir| ["--add-dir".to_string(), dir])
+        .collect();
+    Ok((
+        prompt_with_attachments(&prompt, &prepared.attachments),
+        args,
+    ))
+}
+
+/// Check attachments before sending a prompt, copying outside images into the
+/// project, so the UI can show what Claude will see
+#[tauri::command]
+pub async fn prepare_prompt_attachments(
+    project_path: String,
+    paths: Vec<String>,
+) -> Result<Vec<PreparedAttachment>, String> {
+    prepare_attachments(&project_path, &paths).map(|prepared| prepared.attachments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_attachments() {
+        let project = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+
+        let inside_image = project.path().join("mock.png");
+        std::fs::write(&inside_image, &png).unwrap();
+        let outside_image = outside.path().join("Screenshot 1.PNG");
+        std::fs::write(&outside_image, &png).unwrap();
+        let outside_file = outside.path().join("notes.md");
+        std::fs::write(&outside_file, "# Notes").unwrap();
+        let fake_image = outside.path().join("fake.jpg");
+        std::fs::write(&fake_image, "not a jpeg").unwrap();
+
+        let project_path = project.path().to_string_lossy().to_string();
+        let paths: Vec<String> = [&inside_image, &outside_image, &outside_file]
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let prepared = prepare_attachments(&project_path, &paths).unwrap();
+
+        let [inside, copied, file] = &prepared.attachments[..] else {
+            panic!("expected three attachments");
+        };
+        assert_eq!(inside.kind, AttachmentKind::Image);
+        assert!(!inside.path.contains(ATTACHMENTS_DIR));
+        assert!(copied.path.contains(ATTACHMENTS_DIR) && copied.path.ends_with("Screenshot 1.PNG"));
+        assert!(Path::new(&copied.path).exists());
+        assert_eq!(file.kind, AttachmentKind::File);
+        assert_eq!(
+            prepared.extra_dirs,
+            vec![outside
+                .path()
+                .canonicalize()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()]
+        );
+
+        let prompt = prompt_with_attachments("Match this design", &prepared.attachments);
+        assert!(prompt.starts_with("Match this design\n\nAttached files"));
+        assert!(prompt.contains(&format!("- {} (image/png)", copied.path)));
+
+        let fake = fake_image.to_string_lossy().to_string();
+        assert!(prepare_attachments(&project_path, &[fake]).is_err());
+        let missing = outside
+            .path()
+            .join("gone.png")
+            .to_string_lossy()
+            .to_string();
+        assert!(prepare_attachments(&project_path, &[missing]).is_err());
+        let dir = outside.path().to_string_lossy().to_string();
+        assert!(prepare_attachments(&project_path, &[dir]).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use log::info;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::agents::{
+    get_agent, get_agent_run, kill_agent_session, queue_agent_run, read_session_jsonl, AgentDb,
+    AgentRun,
+};
+use crate::commands::budgets::RunUsageTracker;
+use crate::commands::run_diffs::ChangedFile;
+use crate::process::{normalize_project_path, ProcessRegistryState};
+
+/// One agent task fanned out across several projects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRunBatch {
+    pub id: i64,
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub task: String,
+    pub model: String,
+    pub created_at: String,
+}
+
+/// Outcome of a batch in one project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRunEntry {
+    pub run: AgentRun,
+    pub failure_reason: Option<String>,
+    pub changed_files: Vec<ChangedFile>,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// A batch with the state of every project's run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRunBatchDetail {
+    pub batch: AgentRunBatch,
+    pub runs: Vec<BatchRunEntry>,
+    /// Number of runs per status
+    pub status_counts: BTreeMap<String, usize>,
+    pub total_cost_usd: f64,
+}
+
+fn load_batch(conn: &Connection, batch_id: i64) -> Result<AgentRunBatch, String> {
+    conn.query_row(
+        "SELECT id, agent_id, agent_name, task, model, created_at FROM agent_run_batches WHERE id = ?1",
+        params![batch_id],
+        |row| {
+            Ok(AgentRunBatch {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                agent_name: row.get(2)?,
+                task: row.get(3)?,
+                model: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        },
+    )
+    .map_err(|e| format!("Batch {} not found: {}", batch_id, e))
+}
+
+fn batch_run_ids(conn: &Connection, batch_id: i64, active_only: bool) -> Result<Vec<i64>, String> {
+    let sql = if active_only {
+        "SELECT id FROM agent_runs WHERE batch_id = ?1 AND status IN ('queued', 'running') ORDER BY id"
+    } else {
+        "SELECT id FROM agent_runs WHERE batch_id = ?1 ORDER BY id"
+    };
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map(params![batch_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(ids)
+}
+
+/// Queue the same agent task on every given project.
+///
+/// The runs go through the run queue like any other, so concurrency limits apply.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_batch_run(
+    app: AppHandle,
+    agent_id: i64,
+    project_paths: Vec<String>,
+    task: String,
+    model: Option<String>,
+    parameters: Option<HashMap<String, JsonValue>>,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<AgentRunBatch, String> {
+    let mut projects: Vec<String> = Vec::new();
+    for path in &project_paths {
+        if !std::path::Path::new(path).is_dir() {
+            return Err(format!("Project directory not found: {}", path));
+        }
+        let normalized = normalize_project_path(path);
+        if !projects.contains(&normalized) {
+            projects.push(normalized);
+        }
+    }
+    if projects.is_empty() {
+        return Err("Select at least one project".to_string());
+    }
+
+    let agent = get_agent(db.clone(), agent_id).await?;
+    let model = model.unwrap_or(agent.model.clone());
+
+    // All runs are queued or none are
+    let batch = {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        tx.execute(
+            "INSERT INTO agent_run_batches (agent_id, agent_name, task, model) VALUES (?1, ?2, ?3, ?4)",
+            params![agent_id, agent.name, task, model],
+        )
+        .map_err(|e| e.to_string())?;
+        let batch_id = tx.last_insert_rowid();
+
+        for project in &projects {
+            let run_id = queue_agent_run(&tx, &agent, project, &task, &model, parameters.clone())?;
+            tx.execute(
+                "UPDATE agent_runs SET batch_id = ?1 WHERE id = ?2",
+                params![batch_id, run_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+
+        tx.commit().map_err(|e| e.to_string())?;
+        load_batch(&conn, batch_id)?
+    };
+
+    info!(
+        "Queued batch {} of agent {} across {} projects",
+        batch.id,
+        agent.name,
+        projects.len()
+    );
+
+    crate::commands::queue::dispatch_queued_runs(&app, db, registry).await?;
+    let _ = app.emit("agent-queue-updated", true);
+
+    Ok(batch)
+}
+
+/// List batches, newest first, optionally for a single agent
+#[tauri::command]
+pub async fn list_batch_runs(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+) -> Result<Vec<AgentRunBatch>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id FROM agent_run_batches WHERE ?1 IS NULL OR agent_id = ?1 ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = stmt
+        .query_map(params![agent_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    ids.into_iter().map(|id| load_batch(&conn, id)).collect()
+}
+
+/// Get a batch with each project's status, changed files and cost
+#[tauri::command]
+pub async fn get_batch_run(
+    db: State<'_, AgentDb>,
+    batch_id: i64,
+) -> Result<AgentRunBatchDetail, String> {
+    let (batch, run_ids) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (
+            load_batch(&conn, batch_id)?,
+            batch_run_ids(&conn, batch_id, false)?,
+        )
+    };
+
+    let mut runs = Vec::new();
+    let mut status_counts = BTreeMap::new();
+    for run_id in run_ids {
+        let run = get_agent_run(db.clone(), run_id).await?;
+
+        let (failure_reason, changed_files) = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            conn.query_row(
+                "SELECT failure_reason, changed_files FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                    ))
+                },
+            )
+            .map_err(|e| e.to_string())?
+        };
+
+        let usage = if run.session_id.is_empty() {
+            RunUsageTracker::default()
+        } else {
+            read_session_jsonl(&run.session_id, &run.project_path)
+                .await
+                .map(|jsonl| RunUsageTracker::from_jsonl(&jsonl))
+                .unwrap_or_default()
+        };
+
+        *status_counts.entry(run.status.clone()).or_insert(0) += 1;
+        runs.push(BatchRunEntry {
+            failure_reason,
+            changed_files: changed_files
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+            total_tokens: usage.total_tokens(),
+            cost_usd: usage.cost_usd(),
+            run,
+        });
+    }
+
+    let total_cost_usd = runs.iter().map(|r| r.cost_usd).sum();
+    Ok(AgentRunBatchDetail {
+        batch,
+        runs,
+        status_counts,
+        total_cost_usd,
+    })
+}
+
+/// Cancel every queued or running run in a batch
+#[tauri::command]
+pub async fn cancel_batch_run(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    batch_id: i64,
+) -> Result<usize, String> {
+    let run_ids = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        batch_run_ids(&conn, batch_id, true)?
+    };
+
+    let mut cancelled = 0;
+    for run_id in run_ids {
+        if kill_agent_session(app.clone(), db.clone(), registry.clone(), run_id).await? {
+            cancelled += 1;
+        }
+    }
+
+    info!("Cancelled {} runs of batch {}", cancelled, batch_id);
+    Ok(cancelled)
+}
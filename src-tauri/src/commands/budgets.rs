@@ -0,0 +1,316 @@
+use log::warn;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::cancellation::grace_period;
+use crate::commands::usage::{custom_pricing, token_cost, TokenUsage};
+use crate::process::ProcessRegistry;
+
+/// Per-run spending limits for an agent (None means no limit)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AgentBudget {
+    pub max_cost_usd: Option<f64>,
+    /// Input plus output tokens
+    pub max_tokens: Option<u64>,
+}
+
+impl AgentBudget {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_cost_usd.is_none() && self.max_tokens.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct MessageUsage {
+    model: String,
+    tokens: TokenUsage,
+}
+
+/// Running token and cost totals of a session, sent to the UI as output streams in
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UsageSnapshot {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    /// Input plus output tokens, as counted against budgets
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Cumulative usage of a run, built from its stream-json output
+#[derive(Debug, Default)]
+pub struct RunUsageTracker {
+    /// Usage per assistant message id; streamed chunks of one message repeat its usage
+    messages: HashMap<String, MessageUsage>,
+    anonymous: Vec<MessageUsage>,
+    /// Authoritative total reported by the final result message
+    reported_cost_usd: Option<f64>,
+}
+
+impl RunUsageTracker {
+    /// Build the usage of a finished run from its session JSONL
+    pub fn from_jsonl(jsonl: &str) -> Self {
+        let mut tracker = Self::default();
+        for line in jsonl.lines() {
+            if let Ok(json) = serde_json::from_str::<JsonValue>(line) {
+                tracker.record(&json);
+            }
+        }
+        tracker
+    }
+
+    /// Account for one line of stream-json output; returns whether it carried usage
+    pub fn record(&mut self, json: &JsonValue) -> bool {
+        match json.get("type").and_then(|t| t.as_str()) {
+            Some("assistant") => {
+                let Some(message) = json.get("message") else {
+                    return false;
+                };
+                let Some(usage) = message.get("usage") else {
+                    return false;
+                };
+                let entry = MessageUsage {
+                    model: message
+                        .get("model")
+                        .and_then(|m| m.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    tokens: TokenUsage::from_json(usage),
+                };
+
+                match message.get("id").and_then(|id| id.as_str()) {
+                    Some(id) => {
+                        self.messages.insert(id.to_string(), entry);
+                    }
+                    None => self.anonymous.push(entry),
+                }
+                true
+            }
+            Some("result") => match json.get("total_cost_usd").and_then(|c| c.as_f64()) {
+                Some(cost) => {
+                    self.reported_cost_usd = Some(cost);
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn usages(&self) -> impl Iterator<Item = &MessageUsage> {
+        self.messages.values().chain(self.anonymous.iter())
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.usages()
+            .map(|u| u.tokens.input_tokens + u.tokens.output_tokens)
+            .sum()
+    }
+
+    pub fn cost_usd(&self) -> f64 {
+        match self.reported_cost_usd {
+            // The user's prices take precedence over the cost Claude reported
+            Some(cost) if !self.usages().any(|u| custom_pricing(&u.model).is_some()) => cost,
+            _ => self.usages().map(|u| token_cost(&u.model, &u.tokens)).sum(),
+        }
+    }
+
+    pub fn snapshot(&self) -> UsageSnapshot {
+        let mut tokens = TokenUsage::default();
+        for usage in self.usages() {
+            tokens.add(&usage.tokens);
+        }
+        UsageSnapshot {
+            input_tokens: tokens.input_tokens,
+            output_tokens: tokens.output_tokens,
+            cache_creation_tokens: tokens.cache_creation_tokens,
+            cache_read_tokens: tokens.cache_read_tokens,
+            total_tokens: tokens.input_tokens + tokens.output_tokens,
+            cost_usd: self.cost_usd(),
+        }
+    }
+
+    /// Describe the limit that has been exceeded, if any
+    pub fn exceeded(&self, budget: &AgentBudget) -> Option<String> {
+        if let Some(max_cost) = budget.max_cost_usd {
+            let cost = self.cost_usd();
+            if cost > max_cost {
+                return Some(format!(
+                    "cost ${:.4} exceeded budget ${:.4}",
+                    cost, max_cost
+                ));
+            }
+        }
+        if let Some(max_tokens) = budget.max_tokens {
+            let tokens = self.total_tokens();
+            if tokens > max_tokens {
+                return Some(format!(
+                    "{} tokens exceeded budget of {}",
+                    tokens, max_tokens
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Load an agent's budget, defaulting to no limits
+pub fn load_agent_budget(conn: &Connection, agent_id: i64) -> Result<AgentBudget, String> {
+    let budget = conn
+        .query_row(
+            "SELECT max_cost_usd, max_tokens FROM agent_budgets WHERE agent_id = ?1",
+            params![agent_id],
+            |row| {
+                Ok(AgentBudget {
+                    max_cost_usd: row.get(0)?,
+                    max_tokens: row.get::<_, Option<i64>>(1)?.map(|t| t.max(0) as u64),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(budget.unwrap_or_default())
+}
+
+/// Stop a run that went over its budget.
+///
+/// The run is marked `budget_exceeded` first so the monitor doesn't record it as
+/// completed, then cancelled like a user cancellation, with the configured grace
+/// period.
+pub fn stop_over_budget_run(
+    app: &AppHandle,
+    db_path: &Path,
+    registry: Arc<ProcessRegistry>,
+    run_id: i64,
+    reason: &str,
+) {
+    warn!("Stopping agent run {}: {}", run_id, reason);
+
+    let grace = match Connection::open(db_path) {
+        Ok(conn) => {
+            let _ = conn.execute(
+                "UPDATE agent_runs SET status = 'budget_exceeded', failure_reason = ?2, completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
+                params![run_id, format!("budget_exceeded: {}", reason)],
+            );
+            grace_period(&conn)
+        }
+        Err(e) => {
+            warn!(
+                "Failed to open database to mark run {} over budget: {}",
+                run_id, e
+            );
+            Duration::ZERO
+        }
+    };
+
+    let _ = app.emit(
+        "agent-budget-exceeded",
+        serde_json::json!({ "run_id": run_id, "reason": reason }),
+    );
+
+    tokio::spawn(async move {
+        if let Err(e) = registry.cancel_process(run_id, grace).await {
+            warn!("Failed to stop agent run {}: {}", run_id, e);
+        }
+    });
+}
+
+/// Get the per-run budget for an agent
+#[tauri::command]
+pub async fn get_agent_budget(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<AgentBudget, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_agent_budget(&conn, agent_id)
+}
+
+/// Set the per-run budget for an agent; clearing both limits removes it
+#[tauri::command]
+pub async fn set_agent_budget(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    budget: AgentBudget,
+) -> Result<(), String> {
+    if let Some(max_cost) = budget.max_cost_usd {
+        if !max_cost.is_finite() || max_cost <= 0.0 {
+            return Err("max_cost_usd must be a positive amount".to_string());
+        }
+    }
+    if budget.max_tokens == Some(0) {
+        return Err("max_tokens must be greater than 0".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    if budget.is_unlimited() {
+        conn.execute(
+            "DELETE FROM agent_budgets WHERE agent_id = ?1",
+            params![agent_id],
+        )
+        .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_budgets (agent_id, max_cost_usd, max_tokens) VALUES (?1, ?2, ?3)",
+        params![
+            agent_id,
+            budget.max_cost_usd,
+            budget.max_tokens.map(|t| t as i64)
+        ],
+    )
+    .map_err(|e| format!("Failed to save budget: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_tracker_dedupes_streamed_chunks_and_checks_limits() {
+        let mut tracker = RunUsageTracker::default();
+        let chunk = json!({
+            "type": "assistant",
+            "message": {
+                "id": "msg_1",
+                "model": "claude-sonnet-4-20250514",
+                "usage": { "input_tokens": 1000, "output_tokens": 500 }
+            }
+        });
+        // The same message is streamed once per content block
+        assert!(tracker.record(&chunk));
+        assert!(tracker.record(&chunk));
+        assert!(!tracker.record(&json!({ "type": "system", "subtype": "init" })));
+        assert_eq!(tracker.total_tokens(), 1500);
+        assert_eq!(tracker.snapshot().output_tokens, 500);
+
+        let budget = AgentBudget {
+            max_cost_usd: None,
+            max_tokens: Some(1000),
+        };
+        assert!(tracker.exceeded(&budget).is_some());
+        assert!(tracker.exceeded(&AgentBudget::default()).is_none());
+
+        tracker.record(&json!({ "type": "result", "total_cost_usd": 0.5 }));
+        let budget = AgentBudget {
+            max_cost_usd: Some(0.25),
+            max_tokens: None,
+        };
+        assert_eq!(tracker.cost_usd(), 0.5);
+        assert_eq!(tracker.snapshot().cost_usd, 0.5);
+        assert!(tracker.exceeded(&budget).is_some());
+    }
+}
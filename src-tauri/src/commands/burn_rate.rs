@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
+
+use crate::commands::budgets::UsageSnapshot;
+
+/// Interval between `agent-burn-rate:{run_id}` events
+const BURN_RATE_INTERVAL_SECS: u64 = 10;
+
+/// Span of the trailing window the current rate is measured over
+const BURN_RATE_WINDOW_SECS: u64 = 60;
+
+/// How fast a run is spending, sent to the UI every `BURN_RATE_INTERVAL_SECS`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BurnRateSample {
+    pub elapsed_secs: u64,
+    /// Input plus output tokens so far
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    /// Rates over the last `BURN_RATE_WINDOW_SECS`
+    pub tokens_per_minute: f64,
+    pub cost_per_minute: f64,
+    /// Rates since the run started
+    pub average_tokens_per_minute: f64,
+    pub average_cost_per_minute: f64,
+}
+
+/// Running totals sampled over time, turned into per-minute rates
+pub struct BurnRateTracker {
+    started: Instant,
+    window: Duration,
+    /// (time, total tokens, cost) samples inside the window, oldest first
+    samples: VecDeque<(Instant, u64, f64)>,
+}
+
+impl BurnRateTracker {
+    pub fn new(started: Instant, window: Duration) -> Self {
+        Self {
+            started,
+            window,
+            samples: VecDeque::from([(started, 0, 0.0)]),
+        }
+    }
+
+    /// Record the totals at `now` and compute the rates
+    pub fn sample(&mut self, now: Instant, usage: &UsageSnapshot) -> BurnRateSample {
+        self.samples
+            .push_back((now, usage.total_tokens, usage.cost_usd));
+        // Keep one sample at or before the window start to measure from
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(time, _, _)| now.duration_since(*time) >= self.window)
+        {
+            self.samples.pop_front();
+        }
+
+        let per_minute = |amount: f64, since: Instant| {
+            let minutes = now.duration_since(since).as_secs_f64() / 60.0;
+            if minutes > 0.0 {
+                amount / minutes
+            } else {
+                0.0
+            }
+        };
+        let (window_start, window_tokens, window_cost) = self.samples[0];
+        BurnRateSample {
+            elapsed_secs: now.duration_since(self.started).as_secs(),
+            total_tokens: usage.total_tokens,
+            cost_usd: usage.cost_usd,
+            tokens_per_minute: per_minute(
+                usage.total_tokens.saturating_sub(window_tokens) as f64,
+                window_start,
+            ),
+            cost_per_minute: per_minute((usage.cost_usd - window_cost).max(0.0), window_start),
+            average_tokens_per_minute: per_minute(usage.total_tokens as f64, self.started),
+            average_cost_per_minute: per_minute(usage.cost_usd, self.started),
+        }
+    }
+}
+
+/// Emit the burn rate of a run until the sender of its usage is dropped,
+/// including while it is quiet so the rate falls back towards zero
+pub fn spawn_burn_rate_reporter(
+    app: AppHandle,
+    run_id: i64,
+    mut usage: watch::Receiver<UsageSnapshot>,
+) {
+    tokio::spawn(async move {
+        let mut tracker =
+            BurnRateTracker::new(Instant::now(), Duration::from_secs(BURN_RATE_WINDOW_SECS));
+        let mut interval = tokio::time::interval(Duration::from_secs(BURN_RATE_INTERVAL_SECS));
+        // The first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if usage.has_changed().is_err() {
+                break;
+            }
+            let snapshot = usage.borrow_and_update().clone();
+            let sample = tracker.sample(Instant::now(), &snapshot);
+            let _ = app.emit(&format!("agent-burn-rate:{}", run_id), sample);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(total_tokens: u64, cost_usd: f64) -> UsageSnapshot {
+        UsageSnapshot {
+            total_tokens,
+            cost_usd,
+            ..UsageSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn test_burn_rate_over_trailing_window() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut tracker = BurnRateTracker::new(start, Duration::from_secs(60));
+
+        let sample = tracker.sample(at(30), &usage(1000, 0.5));
+        assert_eq!(sample.tokens_per_minute, 2000.0);
+        assert_eq!(sample.average_cost_per_minute, 1.0);
+
+        tracker.sample(at(60), &usage(3000, 1.5));
+        // Nothing new in the last minute, so the run is idle
+        let sample = tracker.sample(at(120), &usage(3000, 1.5));
+        assert_eq!(sample.tokens_per_minute, 0.0);
+        assert_eq!(sample.cost_per_minute, 0.0);
+        assert_eq!(sample.average_tokens_per_minute, 1500.0);
+        assert_eq!(sample.elapsed_secs, 120);
+
+        // Measured from the last sample before the window, 90 seconds back
+        let sample = tracker.sample(at(150), &usage(4500, 2.25));
+        assert_eq!(sample.tokens_per_minute, 1000.0);
+        assert!((sample.cost_per_minute - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,63 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+
+/// app_settings key holding the cancellation settings as JSON
+const SETTINGS_KEY: &str = "cancellation";
+
+/// Longest grace period that can be configured
+const MAX_GRACE_PERIOD_SECS: u64 = 300;
+
+/// How cancelled sessions and runs are stopped
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CancellationSettings {
+    /// Seconds an interrupted process gets to finish its current tool call
+    /// before it is force-killed; 0 kills it straight away
+    pub grace_period_secs: u64,
+}
+
+impl Default for CancellationSettings {
+    fn default() -> Self {
+        Self {
+            grace_period_secs: 10,
+        }
+    }
+}
+
+fn load_settings(conn: &Connection) -> CancellationSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// How long a cancelled process is given to exit after being interrupted
+pub fn grace_period(conn: &Connection) -> Duration {
+    Duration::from_secs(load_settings(conn).grace_period_secs)
+}
+
+#[tauri::command]
+pub async fn get_cancellation_settings(
+    db: State<'_, AgentDb>,
+) -> Result<CancellationSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_cancellation_settings(
+    db: State<'_, AgentDb>,
+    settings: CancellationSettings,
+) -> Result<(), String> {
+    if settings.grace_period_secs > MAX_GRACE_PERIOD_SECS {
+        return Err(format!(
+            "The grace period can be at most {} seconds",
+            MAX_GRACE_PERIOD_SECS
+        ));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
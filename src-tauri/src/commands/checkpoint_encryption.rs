@@ -0,0 +1,68 @@
+use log::info;
+use rusqlite::{params, Connection};
+use tauri::State;
+
+use crate::checkpoint::encryption;
+use crate::checkpoint::storage::CheckpointStorage;
+use crate::commands::agents::AgentDb;
+use crate::commands::claude::get_claude_dir;
+use crate::commands::settings::save_setting;
+
+/// app_settings key holding whether checkpoints are encrypted at rest
+const ENCRYPTION_KEY: &str = "checkpoint_encryption";
+
+fn load_enabled(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![ENCRYPTION_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .is_ok_and(|value| value == "true")
+}
+
+/// Apply the saved encryption setting; called once at startup
+pub fn load_checkpoint_encryption(conn: &Connection) {
+    let enabled = load_enabled(conn);
+    encryption::set_enabled(enabled);
+    if enabled {
+        info!("Checkpoint encryption at rest is enabled");
+    }
+}
+
+/// Whether checkpoint contents are encrypted at rest
+#[tauri::command]
+pub async fn get_checkpoint_encryption(db: State<'_, AgentDb>) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_enabled(&conn))
+}
+
+/// Turn encryption of checkpoint contents on or off. Existing checkpoints are
+/// rewritten to match; returns how many stored files changed.
+#[tauri::command]
+pub async fn set_checkpoint_encryption(
+    db: State<'_, AgentDb>,
+    enabled: bool,
+) -> Result<usize, String> {
+    // Make sure the key can be created before anything is encrypted with it
+    if enabled {
+        encryption::keychain_cipher(true).map_err(|e| e.to_string())?;
+    }
+
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        save_setting(&conn, ENCRYPTION_KEY, &enabled.to_string())?;
+    }
+    encryption::set_enabled(enabled);
+
+    let storage = CheckpointStorage::new(get_claude_dir().map_err(|e| e.to_string())?);
+    let resealed = tokio::task::spawn_blocking(move || storage.reseal_all())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to rewrite existing checkpoints: {}", e))?;
+    info!(
+        "Checkpoint encryption {}; rewrote {} stored files",
+        if enabled { "enabled" } else { "disabled" },
+        resealed
+    );
+    Ok(resealed)
+}
@@ -0,0 +1,119 @@
+use log::{error, info};
+use rusqlite::Connection;
+use tauri::{AppHandle, Manager, State};
+
+use crate::checkpoint::retention::{
+    collect_garbage, disk_usage, GcReport, ProjectCheckpointUsage, RetentionPolicy,
+};
+use crate::checkpoint::state::CheckpointState;
+use crate::checkpoint::storage::CheckpointStorage;
+use crate::commands::agents::AgentDb;
+use crate::commands::claude::get_claude_dir;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+
+/// app_settings key holding the checkpoint retention policy as JSON
+const RETENTION_POLICY_KEY: &str = "checkpoint_retention";
+
+/// How often the background task enforces the retention policy
+const GC_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+fn load_retention_policy(conn: &Connection) -> RetentionPolicy {
+    load_json_setting(conn, RETENTION_POLICY_KEY)
+}
+
+fn checkpoint_storage() -> Result<CheckpointStorage, String> {
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    Ok(CheckpointStorage::new(claude_dir))
+}
+
+/// Enforce the policy, then bring the timelines of open sessions up to date
+async fn run_gc(
+    checkpoint_state: &CheckpointState,
+    policy: RetentionPolicy,
+) -> Result<GcReport, String> {
+    let storage = checkpoint_storage()?;
+    let report =
+        tokio::task::spawn_blocking(move || collect_garbage(&storage, &policy, chrono::Utc::now()))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Failed to collect checkpoint garbage: {}", e))?;
+
+    if report.removed_checkpoints > 0 {
+        for session_id in checkpoint_state.list_active_sessions().await {
+            if let Some(manager) = checkpoint_state.get_manager(&session_id).await {
+                if let Err(e) = manager.reload_timeline().await {
+                    log::warn!("Failed to reload timeline of {}: {}", session_id, e);
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Start the background task that periodically enforces the retention policy
+pub fn start_checkpoint_gc(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let policy = match app.state::<AgentDb>().0.lock() {
+                Ok(conn) => load_retention_policy(&conn),
+                Err(_) => RetentionPolicy::default(),
+            };
+            if !policy.is_unlimited() {
+                match run_gc(&app.state::<CheckpointState>(), policy).await {
+                    Ok(report) if report.removed_checkpoints > 0 => info!(
+                        "Checkpoint GC removed {} checkpoints, freeing {} bytes",
+                        report.removed_checkpoints, report.bytes_freed
+                    ),
+                    Ok(_) => {}
+                    Err(e) => error!("Checkpoint GC failed: {}", e),
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(GC_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+/// Get the checkpoint retention policy
+#[tauri::command]
+pub async fn get_checkpoint_retention_policy(
+    db: State<'_, AgentDb>,
+) -> Result<RetentionPolicy, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_retention_policy(&conn))
+}
+
+/// Save the checkpoint retention policy. It is enforced by the next GC run.
+#[tauri::command]
+pub async fn set_checkpoint_retention_policy(
+    db: State<'_, AgentDb>,
+    policy: RetentionPolicy,
+) -> Result<(), String> {
+    if policy.keep_last == Some(0) {
+        return Err("At least one checkpoint per session must be kept".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, RETENTION_POLICY_KEY, &policy)?;
+    Ok(())
+}
+
+/// Get checkpoint disk usage per project
+#[tauri::command]
+pub async fn get_checkpoint_disk_usage() -> Result<Vec<ProjectCheckpointUsage>, String> {
+    let storage = checkpoint_storage()?;
+    tokio::task::spawn_blocking(move || disk_usage(&storage))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Enforce the retention policy now, reporting disk usage before and after
+#[tauri::command]
+pub async fn run_checkpoint_gc(
+    db: State<'_, AgentDb>,
+    checkpoint_state: State<'_, CheckpointState>,
+) -> Result<GcReport, String> {
+    let policy = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_retention_policy(&conn)
+    };
+    run_gc(&checkpoint_state, policy).await
+}
@@ -0,0 +1,360 @@
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::checkpoint::storage::CheckpointStorage;
+use crate::checkpoint::{CheckpointValidation, ValidationStatus};
+use crate::commands::agents::AgentDb;
+use crate::commands::claude::get_claude_dir;
+use crate::process::{normalize_project_path, own_process_group, ProcessTree};
+
+/// Seconds a validation command may run before it is killed
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// Lines of output kept in a validation summary
+const SUMMARY_LINES: usize = 40;
+
+/// Upper bound on the summary size, for commands that print very long lines
+const SUMMARY_CHARS: usize = 4000;
+
+/// A command run in a project after each checkpoint, e.g. `cargo test`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationHook {
+    pub project_path: String,
+    pub command: String,
+    pub timeout_secs: u64,
+    pub enabled: bool,
+}
+
+fn load_hook(conn: &Connection, project_path: &str) -> rusqlite::Result<Option<ValidationHook>> {
+    conn.query_row(
+        "SELECT project_path, command, timeout_secs, enabled
+         FROM checkpoint_validation_hooks WHERE project_path = ?1",
+        params![normalize_project_path(project_path)],
+        |row| {
+            Ok(ValidationHook {
+                project_path: row.get(0)?,
+                command: row.get(1)?,
+                timeout_secs: row.get::<_, i64>(2)?.max(1) as u64,
+                enabled: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+}
+
+/// The last lines of a command's output, capped at `SUMMARY_CHARS`
+pub fn output_summary(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    let tail = lines[lines.len().saturating_sub(SUMMARY_LINES)..].join("\n");
+    let excess = tail.chars().count().saturating_sub(SUMMARY_CHARS);
+    match tail.char_indices().nth(excess) {
+        Some((start, _)) if excess > 0 => format!("…{}", &tail[start..]),
+        _ => tail,
+    }
+}
+
+fn shell_command(command: &str) -> tokio::process::Command {
+    #[cfg(target_os = "windows")]
+    {
+        let mut cmd = tokio::process::Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Run a validation command in the project directory and summarize the outcome
+pub async fn run_validation(project_path: &str, hook: &ValidationHook) -> CheckpointValidation {
+    let started_at = Utc::now();
+    let finish = |status, exit_code, output_summary| CheckpointValidation {
+        command: hook.command.clone(),
+        status,
+        exit_code,
+        output_summary,
+        started_at,
+        finished_at: Some(Utc::now()),
+    };
+
+    let mut cmd = shell_command(&hook.command);
+    cmd.current_dir(project_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    own_process_group(&mut cmd);
+    let child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            return finish(
+                ValidationStatus::Error,
+                None,
+                format!("Failed to start: {}", e),
+            )
+        }
+    };
+
+    // The shell's test runners and their workers go down with it
+    let tree = child.id().and_then(ProcessTree::attach);
+
+    let timeout = tokio::time::Duration::from_secs(hook.timeout_secs);
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(Ok(output)) => {
+            let mut combined = String::from_utf8_lossy(&output.stdout).into_owned();
+            combined.push_str(&String::from_utf8_lossy(&output.stderr));
+            let status = if output.status.success() {
+                ValidationStatus::Passed
+            } else {
+                ValidationStatus::Failed
+            };
+            finish(status, output.status.code(), output_summary(&combined))
+        }
+        Ok(Err(e)) => finish(
+            ValidationStatus::Error,
+            None,
+            format!("Failed to wait for command: {}", e),
+        ),
+        Err(_) => {
+            if let Some(tree) = &tree {
+                tree.kill();
+            }
+            finish(
+                ValidationStatus::TimedOut,
+                None,
+                format!("Timed out after {} seconds", hook.timeout_secs),
+            )
+        }
+    }
+}
+
+/// Run the project's validation hook, if it has one, for a freshly created
+/// checkpoint. The result is stored with the checkpoint and announced with a
+/// `checkpoint-validated` event.
+pub fn spawn_checkpoint_validation(
+    app: &AppHandle,
+    project_id: &str,
+    session_id: &str,
+    checkpoint_id: &str,
+    project_path: &str,
+) {
+    let hook = {
+        let db = app.state::<AgentDb>();
+        let Ok(conn) = db.0.lock() else {
+            return;
+        };
+        match load_hook(&conn, project_path) {
+            Ok(Some(hook)) if hook.enabled => hook,
+            Ok(_) => return,
+            Err(e) => {
+                warn!("Failed to load validation hook for {}: {}", project_path, e);
+                return;
+            }
+        }
+    };
+    let storage = match get_claude_dir() {
+        Ok(claude_dir) => CheckpointStorage::new(claude_dir),
+        Err(e) => {
+            warn!("Failed to validate checkpoint {}: {}", checkpoint_id, e);
+            return;
+        }
+    };
+
+    let running = CheckpointValidation {
+        command: hook.command.clone(),
+        status: ValidationStatus::Running,
+        exit_code: None,
+        output_summary: String::new(),
+        started_at: Utc::now(),
+        finished_at: None,
+    };
+    if let Err(e) = storage.save_validation(project_id, session_id, checkpoint_id, &running) {
+        warn!("Failed to validate checkpoint {}: {}", checkpoint_id, e);
+        return;
+    }
+
+    let app = app.clone();
+    let (project_id, session_id, checkpoint_id, project_path) = (
+        project_id.to_string(),
+        session_id.to_string(),
+        checkpoint_id.to_string(),
+        project_path.to_string(),
+    );
+    tauri::async_runtime::spawn(async move {
+        let validation = run_validation(&project_path, &hook).await;
+        info!(
+            "Validation of checkpoint {} finished: {:?}",
+            checkpoint_id, validation.status
+        );
+        if let Err(e) =
+            storage.save_validation(&project_id, &session_id, &checkpoint_id, &validation)
+        {
+            warn!("Failed to record validation of {}: {}", checkpoint_id, e);
+        }
+        let _ = app.emit(
+            "checkpoint-validated",
+            serde_json::json!({
+                "session_id": session_id,
+                "checkpoint_id": checkpoint_id,
+                "validation": validation,
+            }),
+        );
+    });
+}
+
+/// Get the validation hook registered for a project
+#[tauri::command]
+pub async fn get_checkpoint_validation_hook(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<Option<ValidationHook>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_hook(&conn, &project_path).map_err(|e| e.to_string())
+}
+
+/// Register the command to run after each checkpoint in a project
+#[tauri::command]
+pub async fn set_checkpoint_validation_hook(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    command: String,
+    timeout_secs: Option<u64>,
+    enabled: Option<bool>,
+) -> Result<ValidationHook, String> {
+    let command = command.trim().to_string();
+    if command.is_empty() {
+        return Err("Validation command cannot be empty".to_string());
+    }
+    let hook = ValidationHook {
+        project_path: normalize_project_path(&project_path),
+        command,
+        timeout_secs: timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(1),
+        enabled: enabled.unwrap_or(true),
+    };
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO checkpoint_validation_hooks (project_path, command, timeout_secs, enabled)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(project_path) DO UPDATE SET
+            command = excluded.command,
+            timeout_secs = excluded.timeout_secs,
+            enabled = excluded.enabled,
+            updated_at = CURRENT_TIMESTAMP",
+        params![
+            hook.project_path,
+            hook.command,
+            hook.timeout_secs as i64,
+            hook.enabled
+        ],
+    )
+    .map_err(|e| format!("Failed to save validation hook: {}", e))?;
+    Ok(hook)
+}
+
+/// Remove a project's validation hook
+#[tauri::command]
+pub async fn delete_checkpoint_validation_hook(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM checkpoint_validation_hooks WHERE project_path = ?1",
+        params![normalize_project_path(&project_path)],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Get the validation result recorded with a checkpoint
+#[tauri::command]
+pub async fn get_checkpoint_validation(
+    project_id: String,
+    session_id: String,
+    checkpoint_id: String,
+) -> Result<Option<CheckpointValidation>, String> {
+    let storage = CheckpointStorage::new(get_claude_dir().map_err(|e| e.to_string())?);
+    Ok(storage.load_validation(&project_id, &session_id, &checkpoint_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_validation_records_status_and_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let hook = |command: &str, timeout_secs| ValidationHook {
+            project_path: project.clone(),
+            command: command.to_string(),
+            timeout_secs,
+            enabled: true,
+        };
+
+        let passed = run_validation(&project, &hook("echo ok; echo warn >&2", 30)).await;
+        assert_eq!(passed.status, ValidationStatus::Passed);
+        assert_eq!(passed.exit_code, Some(0));
+        assert_eq!(passed.output_summary, "ok\nwarn");
+
+        let failed = run_validation(&project, &hook("seq 1 100; exit 3", 30)).await;
+        assert_eq!(failed.status, ValidationStatus::Failed);
+        assert_eq!(failed.exit_code, Some(3));
+        assert_eq!(failed.output_summary.lines().count(), SUMMARY_LINES);
+        assert!(failed.output_summary.starts_with("61\n"));
+
+        let timed_out = run_validation(&project, &hook("sleep 5", 1)).await;
+        assert_eq!(timed_out.status, ValidationStatus::TimedOut);
+
+        let long = "x".repeat(SUMMARY_CHARS * 2);
+        assert_eq!(output_summary(&long).chars().count(), SUMMARY_CHARS + 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_validation_timeout_kills_the_whole_command() {
+        use sysinfo::{Pid, ProcessStatus, System};
+
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let hook = ValidationHook {
+            project_path: project.clone(),
+            command: "sleep 30 & echo $! > worker.pid; wait".to_string(),
+            timeout_secs: 1,
+            enabled: true,
+        };
+
+        let timed_out = run_validation(&project, &hook).await;
+        assert_eq!(timed_out.status, ValidationStatus::TimedOut);
+
+        let worker: u32 = std::fs::read_to_string(dir.path().join("worker.pid"))
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        let gone = || {
+            let pid = Pid::from_u32(worker);
+            let mut system = System::new();
+            !(system.refresh_process(pid)
+                && system
+                    .process(pid)
+                    .is_some_and(|process| process.status() != ProcessStatus::Zombie))
+        };
+        for _ in 0..50 {
+            if gone() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("worker {} outlived its timed out validation", worker);
+    }
+}
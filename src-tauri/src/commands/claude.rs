@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
@@ -10,16 +11,15 @@ use tauri::{AppHandle, Emitter, Manager};
 use tokio::process::{Child, Command};
 use tokio::sync::Mutex;
 
+use crate::commands::run_logs::{logs_root, session_log_name, RunOutputLog};
+use crate::commands::stream_batching::{load_stream_batching, StreamBatcher};
+use crate::commands::stream_filters::session_stream_filter;
+use crate::process::CancelOutcome;
+use crate::shell_environment::ShellEnvironment;
 #[cfg(windows)]
-use crate::shell_environment::{create_wsl_command, ShellConfig, ShellEnvironment};
-
-#[cfg(windows)]
-use std::os::windows::process::CommandExt;
-
-/// Windows constant for CREATE_NO_WINDOW flag
-/// This prevents console windows from flashing when running background commands
-#[cfg(windows)]
-const CREATE_NO_WINDOW: u32 = 0x08000000;
+use crate::shell_environment::{create_wsl_command, ShellConfig};
+use crate::stderr_rules::{classify_stderr, StderrSeverity};
+use crate::stream_json::{parse_line, StreamMessage};
 
 /// Global state to track current Claude process
 pub struct ClaudeProcessState {
@@ -66,6 +66,8 @@ pub struct Session {
     pub first_message: Option<String>,
     /// Timestamp of the first user message (if available)
     pub message_timestamp: Option<String>,
+    /// Generated or user-given title (if any)
+    pub title: Option<String>,
 }
 
 /// Represents a message entry in the JSONL file
@@ -201,8 +203,19 @@ fn get_shell_config_sync(app_handle: &AppHandle) -> ShellConfig {
     ShellConfig::default()
 }
 
+/// The environment interactive sessions run Claude in
+#[cfg(windows)]
+fn session_shell_environment(app_handle: &AppHandle) -> ShellEnvironment {
+    get_shell_config_sync(app_handle).environment
+}
+
+#[cfg(not(windows))]
+fn session_shell_environment(_app_handle: &AppHandle) -> ShellEnvironment {
+    ShellEnvironment::Native
+}
+
 /// Gets the path to the ~/.claude directory
-fn get_claude_dir() -> Result<PathBuf> {
+pub fn get_claude_dir() -> Result<PathBuf> {
     let claude_path = dirs::home_dir()
         .context("Could not find home directory")?
         .join(".claude");
@@ -224,7 +237,7 @@ fn get_claude_dir() -> Result<PathBuf> {
 }
 
 /// Gets the actual project path by reading the cwd from the JSONL entries
-fn get_project_path_from_sessions(project_dir: &PathBuf) -> Result<String, String> {
+pub fn get_project_path_from_sessions(project_dir: &PathBuf) -> Result<String, String> {
     // Try to read any JSONL file in the directory
     let entries = fs::read_dir(project_dir)
         .map_err(|e| format!("Failed to read project directory: {}", e))?;
@@ -381,6 +394,7 @@ fn create_system_command(claude_path: &str, args: Vec<String>, project_path: &st
     cmd.current_dir(project_path)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+    crate::process::own_process_group(&mut cmd);
 
     cmd
 }
@@ -419,8 +433,8 @@ fn create_system_command_with_shell(
                 tokio_cmd.arg(arg);
             }
 
-            // Set CREATE_NO_WINDOW to prevent terminal flashing
-            tokio_cmd.creation_flags(CREATE_NO_WINDOW);
+            // A hidden console of its own, so it can be interrupted without terminal flashing
+            crate::process::own_process_group(&mut tokio_cmd);
             tokio_cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
             tokio_cmd
@@ -457,8 +471,8 @@ fn create_system_command_with_shell(
             );
 
             cmd.args(["-lc", &bash_command]);
-            // Set CREATE_NO_WINDOW to prevent terminal flashing
-            cmd.creation_flags(CREATE_NO_WINDOW);
+            // A hidden console of its own, so it can be interrupted without terminal flashing
+            crate::process::own_process_group(&mut cmd);
             cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
             cmd
@@ -633,9 +647,20 @@ pub async fn create_project(path: String) -> Result<Project, String> {
     })
 }
 
-/// Gets sessions for a specific project
+/// Gets sessions for a specific project, with their titles
 #[tauri::command]
-pub async fn get_project_sessions(project_id: String) -> Result<Vec<Session>, String> {
+pub async fn get_project_sessions(
+    db: tauri::State<'_, crate::commands::agents::AgentDb>,
+    project_id: String,
+) -> Result<Vec<Session>, String> {
+    let mut sessions = read_project_sessions(project_id).await?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    crate::commands::session_titles::apply_session_titles(&conn, &mut sessions);
+    Ok(sessions)
+}
+
+/// Reads the sessions of a project from its JSONL files, without titles
+pub async fn read_project_sessions(project_id: String) -> Result<Vec<Session>, String> {
     log::info!("Getting sessions for project: {}", project_id);
 
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
@@ -704,6 +729,7 @@ pub async fn get_project_sessions(project_id: String) -> Result<Vec<Session>, St
                     created_at,
                     first_message,
                     message_timestamp,
+                    title: None,
                 });
             }
         }
@@ -1072,7 +1098,8 @@ pub async fn load_session_history(
 
     for line in reader.lines() {
         if let Ok(line) = line {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
+            // Lines from older Claude Code releases are upgraded to the current schema
+            if let Some(json) = crate::transcript::upgrade_line(&line) {
                 messages.push(json);
             }
         }
@@ -1081,6 +1108,24 @@ pub async fn load_session_history(
     Ok(messages)
 }
 
+/// Load a session's transcript as typed entries, whichever Claude Code version wrote
+/// it. Entries and content blocks that aren't understood are returned as raw JSON.
+#[tauri::command]
+pub async fn load_session_transcript(
+    session_id: String,
+    project_id: String,
+) -> Result<Vec<crate::transcript::TranscriptEntry>, String> {
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let session_path = claude_dir
+        .join("projects")
+        .join(&project_id)
+        .join(format!("{}.jsonl", session_id));
+
+    let file =
+        fs::File::open(&session_path).map_err(|e| format!("Failed to open session file: {}", e))?;
+    Ok(crate::transcript::parse_transcript(BufReader::new(file)))
+}
+
 /// Execute a new interactive Claude Code session with streaming output
 #[tauri::command]
 pub async fn execute_claude_code(
@@ -1088,6 +1133,8 @@ pub async fn execute_claude_code(
     project_path: String,
     prompt: String,
     model: String,
+    tab_id: Option<String>,
+    attachments: Option<Vec<String>>,
 ) -> Result<(), String> {
     log::info!(
         "Starting new Claude Code session in: {} with model: {}",
@@ -1096,8 +1143,10 @@ pub async fn execute_claude_code(
     );
 
     let claude_path = find_claude_binary(&app)?;
+    let (prompt, attachment_args) =
+        crate::commands::attachments::attach_to_prompt(&project_path, prompt, attachments)?;
 
-    let args = vec![
+    let mut args = vec![
         "-p".to_string(),
         prompt.clone(),
         "--model".to_string(),
@@ -1107,6 +1156,12 @@ pub async fn execute_claude_code(
         "--verbose".to_string(),
         "--dangerously-skip-permissions".to_string(),
     ];
+    args.extend(attachment_args);
+    args.extend(crate::commands::mcp_project_servers::mcp_config_args(
+        &app,
+        &project_path,
+        &session_shell_environment(&app),
+    ));
 
     // On Windows, use shell-aware command creation
     #[cfg(windows)]
@@ -1119,7 +1174,7 @@ pub async fn execute_claude_code(
     #[cfg(not(windows))]
     let cmd = create_system_command(&claude_path, args, &project_path);
 
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    spawn_claude_process(app, cmd, prompt, model, project_path, tab_id).await
 }
 
 /// Continue an existing Claude Code conversation with streaming output
@@ -1129,6 +1184,8 @@ pub async fn continue_claude_code(
     project_path: String,
     prompt: String,
     model: String,
+    tab_id: Option<String>,
+    attachments: Option<Vec<String>>,
 ) -> Result<(), String> {
     log::info!(
         "Continuing Claude Code conversation in: {} with model: {}",
@@ -1137,8 +1194,10 @@ pub async fn continue_claude_code(
     );
 
     let claude_path = find_claude_binary(&app)?;
+    let (prompt, attachment_args) =
+        crate::commands::attachments::attach_to_prompt(&project_path, prompt, attachments)?;
 
-    let args = vec![
+    let mut args = vec![
         "-c".to_string(), // Continue flag
         "-p".to_string(),
         prompt.clone(),
@@ -1149,6 +1208,12 @@ pub async fn continue_claude_code(
         "--verbose".to_string(),
         "--dangerously-skip-permissions".to_string(),
     ];
+    args.extend(attachment_args);
+    args.extend(crate::commands::mcp_project_servers::mcp_config_args(
+        &app,
+        &project_path,
+        &session_shell_environment(&app),
+    ));
 
     // On Windows, use shell-aware command creation
     #[cfg(windows)]
@@ -1161,7 +1226,7 @@ pub async fn continue_claude_code(
     #[cfg(not(windows))]
     let cmd = create_system_command(&claude_path, args, &project_path);
 
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    spawn_claude_process(app, cmd, prompt, model, project_path, tab_id).await
 }
 
 /// Resume an existing Claude Code session by ID with streaming output
@@ -1172,6 +1237,8 @@ pub async fn resume_claude_code(
     session_id: String,
     prompt: String,
     model: String,
+    tab_id: Option<String>,
+    attachments: Option<Vec<String>>,
 ) -> Result<(), String> {
     log::info!(
         "Resuming Claude Code session: {} in: {} with model: {}",
@@ -1181,8 +1248,10 @@ pub async fn resume_claude_code(
     );
 
     let claude_path = find_claude_binary(&app)?;
+    let (prompt, attachment_args) =
+        crate::commands::attachments::attach_to_prompt(&project_path, prompt, attachments)?;
 
-    let args = vec![
+    let mut args = vec![
         "--resume".to_string(),
         session_id.clone(),
         "-p".to_string(),
@@ -1194,6 +1263,12 @@ pub async fn resume_claude_code(
         "--verbose".to_string(),
         "--dangerously-skip-permissions".to_string(),
     ];
+    args.extend(attachment_args);
+    args.extend(crate::commands::mcp_project_servers::mcp_config_args(
+        &app,
+        &project_path,
+        &session_shell_environment(&app),
+    ));
 
     // On Windows, use shell-aware command creation
     #[cfg(windows)]
@@ -1206,7 +1281,7 @@ pub async fn resume_claude_code(
     #[cfg(not(windows))]
     let cmd = create_system_command(&claude_path, args, &project_path);
 
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    spawn_claude_process(app, cmd, prompt, model, project_path, tab_id).await
 }
 
 /// Cancel the currently running Claude Code execution
@@ -1221,9 +1296,11 @@ pub async fn cancel_claude_execution(
     );
 
     let mut killed = false;
+    let mut outcome = CancelOutcome::NotRunning;
     let mut attempted_methods = Vec::new();
 
-    // Method 1: Try to find and kill via ProcessRegistry using session ID
+    // Method 1: Try to find and stop via ProcessRegistry using session ID,
+    // interrupting it first so it can finish its current tool call
     if let Some(sid) = &session_id {
         let registry = app.state::<crate::process::ProcessRegistryState>();
         match registry.0.get_claude_session_by_id(sid) {
@@ -1234,17 +1311,25 @@ pub async fn cancel_claude_execution(
                     process_info.run_id,
                     process_info.pid
                 );
-                match registry.0.kill_process(process_info.run_id).await {
-                    Ok(success) => {
-                        if success {
-                            log::info!("Successfully killed process via registry");
-                            killed = true;
-                        } else {
-                            log::warn!("Registry kill returned false");
-                        }
+                let grace = {
+                    let db = app.state::<crate::commands::agents::AgentDb>();
+                    let conn = db.0.lock().map_err(|e| e.to_string())?;
+                    crate::commands::cancellation::grace_period(&conn)
+                };
+                match registry.0.cancel_process(process_info.run_id, grace).await {
+                    Ok(CancelOutcome::NotRunning) => {
+                        log::warn!("Registry cancel found no process");
+                    }
+                    Ok(registry_outcome) => {
+                        log::info!(
+                            "Successfully stopped process via registry: {:?}",
+                            registry_outcome
+                        );
+                        outcome = registry_outcome;
+                        killed = true;
                     }
                     Err(e) => {
-                        log::warn!("Failed to kill via registry: {}", e);
+                        log::warn!("Failed to cancel via registry: {}", e);
                     }
                 }
                 attempted_methods.push("registry");
@@ -1276,6 +1361,7 @@ pub async fn cancel_claude_execution(
                 Ok(_) => {
                     log::info!("Successfully killed Claude process via ClaudeProcessState");
                     killed = true;
+                    outcome = CancelOutcome::ForceKilled;
                 }
                 Err(e) => {
                     log::error!(
@@ -1300,6 +1386,7 @@ pub async fn cancel_claude_execution(
                             Ok(output) if output.status.success() => {
                                 log::info!("Successfully killed process via system command");
                                 killed = true;
+                                outcome = CancelOutcome::ForceKilled;
                             }
                             Ok(output) => {
                                 let stderr = String::from_utf8_lossy(&output.stderr);
@@ -1325,6 +1412,7 @@ pub async fn cancel_claude_execution(
     // Always emit cancellation events for UI consistency
     if let Some(sid) = session_id {
         let _ = app.emit(&format!("claude-cancelled:{}", sid), true);
+        let _ = app.emit(&format!("claude-cancellation:{}", sid), outcome);
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         let _ = app.emit(&format!("claude-complete:{}", sid), false);
     }
@@ -1366,20 +1454,45 @@ pub async fn get_claude_session_output(
 }
 
 /// Helper function to spawn Claude process and handle streaming
+///
+/// A process started for a session tab runs alongside those of other tabs and is
+/// stopped by closing the tab; without a tab it replaces the previous untabbed process.
 async fn spawn_claude_process(
     app: AppHandle,
     mut cmd: Command,
     prompt: String,
     model: String,
     project_path: String,
+    tab_id: Option<String>,
 ) -> Result<(), String> {
     use std::sync::Mutex;
     use tokio::io::{AsyncBufReadExt, BufReader};
 
+    let output_log_root = logs_root(&app.path().app_data_dir().map_err(|e| e.to_string())?);
+
+    // Sessions share the scheduler's limit on Claude processes with agent runs
+    crate::commands::queue::check_session_capacity(&app)?;
+
+    // Claim the tab before spawning so a refused start leaves nothing running
+    let tabs = app.state::<crate::process::SessionTabsState>().0.clone();
+    let tab_stop = match &tab_id {
+        Some(tab_id) => Some(tabs.start_process(
+            tab_id,
+            crate::commands::session_tabs::project_lock_enabled(&app),
+        )?),
+        None => None,
+    };
+
     // Spawn the process
-    let mut child = cmd
-        .spawn()
-        .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            if let Some(tab_id) = &tab_id {
+                let _ = tabs.finish_process(tab_id);
+            }
+            return Err(format!("Failed to spawn Claude: {}", e));
+        }
+    };
 
     // Get stdout and stderr
     let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
@@ -1397,16 +1510,24 @@ async fn spawn_claude_process(
     let session_id_holder: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
     let run_id_holder: Arc<Mutex<Option<i64>>> = Arc::new(Mutex::new(None));
 
-    // Store the child process in the global state (for backward compatibility)
+    // Tab processes are owned by their wait task; the untabbed one is stored in the
+    // global state (for backward compatibility)
     let claude_state = app.state::<ClaudeProcessState>();
-    {
-        let mut current_process = claude_state.current_process.lock().await;
-        // If there's already a process running, kill it first
-        if let Some(mut existing_child) = current_process.take() {
-            log::warn!("Killing existing Claude process before starting new one");
-            let _ = existing_child.kill().await;
+    let tab_process = match tab_stop {
+        Some(stop) => Some((child, stop)),
+        None => {
+            let mut current_process = claude_state.current_process.lock().await;
+            // If there's already a process running, kill it first
+            if let Some(mut existing_child) = current_process.take() {
+                log::warn!("Killing existing Claude process before starting new one");
+                let _ = existing_child.kill().await;
+            }
+            *current_process = Some(child);
+            None
         }
-        *current_process = Some(child);
+    };
+    if tab_id.is_some() {
+        crate::commands::session_tabs::notify_tabs_changed(&app);
     }
 
     // Spawn tasks to read stdout and stderr
@@ -1418,65 +1539,158 @@ async fn spawn_claude_process(
     let project_path_clone = project_path.clone();
     let prompt_clone = prompt.clone();
     let model_clone = model.clone();
+    let tab_id_clone = tab_id.clone();
+    let tabs_clone = tabs.clone();
+    let destructive_guard = crate::commands::destructive_checkpoints::DestructiveGuard::load(&app);
+    let batcher = StreamBatcher::new(
+        app.clone(),
+        &load_stream_batching(&app.state::<crate::commands::agents::AgentDb>()),
+    );
+    // Keep the raw output for post-mortems; it is named after the session once
+    // Claude reports its ID
+    let output_log = Arc::new(RunOutputLog::pending(&output_log_root));
+    let output_log_stdout = output_log.clone();
+    let output_log_stderr = output_log.clone();
     let stdout_task = tokio::spawn(async move {
         let mut lines = stdout_reader.lines();
+        let mut usage = crate::commands::budgets::RunUsageTracker::default();
         while let Ok(Some(line)) = lines.next_line().await {
             log::debug!("Claude stdout: {}", line);
+            output_log_stdout.write("stdout", &line);
+
+            // Surface lines that aren't stream messages instead of dropping them
+            let parsed = parse_line(&line);
+            if let Err(ref e) = parsed {
+                log::warn!("Claude wrote a line that isn't a stream message: {}", e);
+                if let Some(ref session_id) = *session_id_holder_clone.lock().unwrap() {
+                    let _ = app_handle.emit(&format!("claude-stream-error:{}", session_id), e);
+                }
+                let _ = app_handle.emit("claude-stream-error", e);
+            }
 
             // Parse the line to check for init message with session ID
-            if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&line) {
-                if msg["type"] == "system" && msg["subtype"] == "init" {
-                    if let Some(claude_session_id) = msg["session_id"].as_str() {
-                        let mut session_id_guard = session_id_holder_clone.lock().unwrap();
-                        if session_id_guard.is_none() {
-                            *session_id_guard = Some(claude_session_id.to_string());
-                            log::info!("Extracted Claude session ID: {}", claude_session_id);
-
-                            // Now register with ProcessRegistry using Claude's session ID
-                            match registry_clone.register_claude_session(
-                                claude_session_id.to_string(),
-                                pid,
-                                project_path_clone.clone(),
-                                prompt_clone.clone(),
-                                model_clone.clone(),
-                            ) {
-                                Ok(run_id) => {
-                                    log::info!("Registered Claude session with run_id: {}", run_id);
-                                    let mut run_id_guard = run_id_holder_clone.lock().unwrap();
-                                    *run_id_guard = Some(run_id);
-                                }
-                                Err(e) => {
-                                    log::error!("Failed to register Claude session: {}", e);
-                                }
+            if let Ok((json, message)) = &parsed {
+                // Keep the live token and cost meter up to date
+                if usage.record(json) {
+                    let snapshot = usage.snapshot();
+                    if let Some(ref session_id) = *session_id_holder_clone.lock().unwrap() {
+                        let _ = app_handle.emit(&format!("claude-usage:{}", session_id), &snapshot);
+                    }
+                    let _ = app_handle.emit("claude-usage", &snapshot);
+                }
+
+                // Checkpoint before a destructive Bash command gets to run
+                let session_id = session_id_holder_clone.lock().unwrap().clone();
+                if let (Some(guard), Some(session_id)) = (&destructive_guard, session_id) {
+                    if let Some(command) = guard.destructive_command(message) {
+                        let run_id = *run_id_holder_clone.lock().unwrap();
+                        crate::commands::destructive_checkpoints::checkpoint_before_command(
+                            &app_handle,
+                            run_id,
+                            &session_id,
+                            &project_path_clone,
+                            &command,
+                        )
+                        .await;
+                    }
+                }
+
+                if let StreamMessage::Init(init) = message {
+                    let claude_session_id = init.session_id.as_str();
+                    let mut session_id_guard = session_id_holder_clone.lock().unwrap();
+                    if session_id_guard.is_none() {
+                        *session_id_guard = Some(claude_session_id.to_string());
+                        log::info!("Extracted Claude session ID: {}", claude_session_id);
+                        output_log_stdout.attach(&session_log_name(claude_session_id));
+                        batcher.serve_replays(&format!("claude-output:{}", claude_session_id));
+
+                        // Now register with ProcessRegistry using Claude's session ID
+                        match registry_clone.register_claude_session(
+                            claude_session_id.to_string(),
+                            pid,
+                            project_path_clone.clone(),
+                            prompt_clone.clone(),
+                            model_clone.clone(),
+                        ) {
+                            Ok(run_id) => {
+                                log::info!("Registered Claude session with run_id: {}", run_id);
+                                let mut run_id_guard = run_id_holder_clone.lock().unwrap();
+                                *run_id_guard = Some(run_id);
+                            }
+                            Err(e) => {
+                                log::error!("Failed to register Claude session: {}", e);
                             }
                         }
+
+                        // Bind the session to its tab so later prompts resume it
+                        if let Some(ref tab_id) = tab_id_clone {
+                            let run_id = *run_id_holder_clone.lock().unwrap();
+                            let _ = tabs_clone.bind_session(tab_id, claude_session_id, run_id);
+                            crate::commands::session_tabs::notify_tabs_changed(&app_handle);
+                        }
                     }
                 }
             }
 
-            // Store live output in registry if we have a run_id
-            if let Some(run_id) = *run_id_holder_clone.lock().unwrap() {
-                let _ = registry_clone.append_live_output(run_id, &line);
+            // Store live output in registry if we have a run_id, and emit the line to the
+            // frontend with session isolation if we have session ID. The session's
+            // stream filter decides what of it reaches the frontend.
+            let run_id = *run_id_holder_clone.lock().unwrap();
+            let session_id = session_id_holder_clone.lock().unwrap().clone();
+            let forwarded = match (&session_id, &parsed) {
+                (Some(session_id), Ok((json, _))) => {
+                    session_stream_filter(&app_handle, session_id).apply(json, &line)
+                }
+                _ => Some(Cow::Borrowed(line.as_str())),
+            };
+            if let Some(session_id) = session_id {
+                let emit = |_: &str| {
+                    if let Some(ref forwarded) = forwarded {
+                        batcher.push(&format!("claude-output:{}", session_id), forwarded);
+                    }
+                };
+                match run_id {
+                    Some(run_id) => {
+                        let _ = registry_clone.append_and_emit_live_output(run_id, &line, emit);
+                    }
+                    None => emit(&line),
+                }
             }
-
-            // Emit the line to the frontend with session isolation if we have session ID
-            if let Some(ref session_id) = *session_id_holder_clone.lock().unwrap() {
-                let _ = app_handle.emit(&format!("claude-output:{}", session_id), &line);
+            let Some(forwarded) = forwarded else {
+                continue;
+            };
+            // Tabs know their id before Claude reports a session ID
+            if let Some(ref tab_id) = tab_id_clone {
+                batcher.push(&format!("tab-output:{}", tab_id), &forwarded);
             }
             // Also emit to the generic event for backward compatibility
-            let _ = app_handle.emit("claude-output", &line);
+            batcher.push("claude-output", &forwarded);
         }
+        batcher.finish().await;
     });
 
     let app_handle_stderr = app.clone();
     let session_id_holder_clone2 = session_id_holder.clone();
+    let tab_id_clone2 = tab_id.clone();
     let stderr_task = tokio::spawn(async move {
         let mut lines = stderr_reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
-            log::error!("Claude stderr: {}", line);
+            let classified = classify_stderr(&line);
+            if classified.severity >= StderrSeverity::Error {
+                log::error!("Claude stderr: {}", line);
+            } else {
+                log::warn!("Claude stderr: {}", line);
+            }
+            output_log_stderr.write("stderr", &line);
             // Emit error lines to the frontend with session isolation if we have session ID
             if let Some(ref session_id) = *session_id_holder_clone2.lock().unwrap() {
                 let _ = app_handle_stderr.emit(&format!("claude-error:{}", session_id), &line);
+                let _ =
+                    app_handle_stderr.emit(&format!("claude-stderr:{}", session_id), &classified);
+            }
+            if let Some(ref tab_id) = tab_id_clone2 {
+                let _ = app_handle_stderr.emit(&format!("tab-error:{}", tab_id), &line);
+                let _ = app_handle_stderr.emit(&format!("tab-stderr:{}", tab_id), &classified);
             }
             // Also emit to the generic event for backward compatibility
             let _ = app_handle_stderr.emit("claude-error", &line);
@@ -1489,46 +1703,103 @@ async fn spawn_claude_process(
     let session_id_holder_clone3 = session_id_holder.clone();
     let run_id_holder_clone2 = run_id_holder.clone();
     let registry_clone2 = registry.0.clone();
+    let project_path_wait = project_path.clone();
     tokio::spawn(async move {
-        let _ = stdout_task.await;
-        let _ = stderr_task.await;
-
-        // Get the child from the state to wait on it
-        let mut current_process = claude_state_wait.lock().await;
-        if let Some(mut child) = current_process.take() {
-            match child.wait().await {
-                Ok(status) => {
-                    log::info!("Claude process exited with status: {}", status);
-                    // Add a small delay to ensure all messages are processed
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
-                        let _ = app_handle_wait
-                            .emit(&format!("claude-complete:{}", session_id), status.success());
+        let mut stopped = false;
+        let status = match tab_process {
+            Some((mut child, stop)) => {
+                // Closing the tab stops its process
+                let status = tokio::select! {
+                    status = child.wait() => status,
+                    _ = stop.notified() => {
+                        log::info!("Stopping Claude process of a closed tab");
+                        stopped = true;
+                        let _ = child.kill().await;
+                        child.wait().await
                     }
-                    // Also emit to the generic event for backward compatibility
-                    let _ = app_handle_wait.emit("claude-complete", status.success());
+                };
+                let _ = stdout_task.await;
+                let _ = stderr_task.await;
+                Some(status)
+            }
+            None => {
+                let _ = stdout_task.await;
+                let _ = stderr_task.await;
+
+                // Get the child from the state to wait on it
+                let mut current_process = claude_state_wait.lock().await;
+                let status = match current_process.take() {
+                    Some(mut child) => Some(child.wait().await),
+                    None => None,
+                };
+
+                // Clear the process from state
+                *current_process = None;
+                status
+            }
+        };
+        // A session that ended before reporting its ID is logged under its PID
+        output_log.attach(&format!("session-pid-{}", pid));
+
+        let success = match status {
+            Some(Ok(status)) => {
+                log::info!("Claude process exited with status: {}", status);
+                Some(status.success())
+            }
+            Some(Err(e)) => {
+                log::error!("Failed to wait for Claude process: {}", e);
+                Some(false)
+            }
+            None => None,
+        };
+        // A session the user stopped exits with an error, but hasn't failed
+        let run_id = *run_id_holder_clone2.lock().unwrap();
+        let stopped =
+            stopped || run_id.is_some_and(|run_id| registry_clone2.was_interrupted(run_id));
+        if let Some(success) = success {
+            // Add a small delay to ensure all messages are processed
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
+                let _ = app_handle_wait.emit(&format!("claude-complete:{}", session_id), success);
+                if !stopped {
+                    crate::commands::notification_actions::notify_session_finished(
+                        &app_handle_wait,
+                        session_id,
+                        &project_path_wait,
+                        success,
+                    );
                 }
-                Err(e) => {
-                    log::error!("Failed to wait for Claude process: {}", e);
-                    // Add a small delay to ensure all messages are processed
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
-                        let _ =
-                            app_handle_wait.emit(&format!("claude-complete:{}", session_id), false);
-                    }
-                    // Also emit to the generic event for backward compatibility
-                    let _ = app_handle_wait.emit("claude-complete", false);
+                if success {
+                    crate::commands::session_titles::spawn_session_titling(
+                        app_handle_wait.clone(),
+                        session_id.clone(),
+                        project_path_wait,
+                    );
                 }
             }
+            if let Some(ref tab_id) = tab_id {
+                let _ = app_handle_wait.emit(&format!("tab-complete:{}", tab_id), success);
+            }
+            // Also emit to the generic event for backward compatibility
+            let _ = app_handle_wait.emit("claude-complete", success);
         }
 
         // Unregister from ProcessRegistry if we have a run_id
-        if let Some(run_id) = *run_id_holder_clone2.lock().unwrap() {
+        if let Some(run_id) = run_id {
             let _ = registry_clone2.unregister_process(run_id);
         }
+        if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
+            crate::commands::stream_filters::forget_session_stream_filter(session_id);
+        }
 
-        // Clear the process from state
-        *current_process = None;
+        if let Some(ref tab_id) = tab_id {
+            let _ = tabs.finish_process(tab_id);
+            crate::commands::session_tabs::notify_tabs_changed(&app_handle_wait);
+            // Move on to the next prompt the user queued while this turn ran
+            if success == Some(true) {
+                crate::commands::session_tabs::dispatch_queued_prompt(&app_handle_wait, tab_id);
+            }
+        }
     });
 
     Ok(())
@@ -1728,13 +1999,14 @@ fn search_files_recursive(
     Ok(())
 }
 
-/// Creates a checkpoint for the current session state
-#[tauri::command]
-pub async fn create_checkpoint(
-    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
-    session_id: String,
-    project_id: String,
-    project_path: String,
+/// Creates a checkpoint from the session's JSONL file and runs the project's
+/// validation hook on it
+pub async fn checkpoint_session(
+    app_handle: &AppHandle,
+    state: &crate::checkpoint::state::CheckpointState,
+    session_id: &str,
+    project_id: &str,
+    project_path: &str,
     message_index: Option<usize>,
     description: Option<String>,
 ) -> Result<crate::checkpoint::CheckpointResult, String> {
@@ -1744,11 +2016,11 @@ pub async fn create_checkpoint(
         project_id
     );
 
-    let manager = app
+    let manager = state
         .get_or_create_manager(
-            session_id.clone(),
-            project_id.clone(),
-            PathBuf::from(&project_path),
+            session_id.to_string(),
+            project_id.to_string(),
+            PathBuf::from(project_path),
         )
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
@@ -1757,7 +2029,7 @@ pub async fn create_checkpoint(
     let session_path = get_claude_dir()
         .map_err(|e| e.to_string())?
         .join("projects")
-        .join(&project_id)
+        .join(project_id)
         .join(format!("{}.jsonl", session_id));
 
     if session_path.exists() {
@@ -1782,10 +2054,43 @@ pub async fn create_checkpoint(
         }
     }
 
-    manager
+    let result = manager
         .create_checkpoint(description, None)
         .await
-        .map_err(|e| format!("Failed to create checkpoint: {}", e))
+        .map_err(|e| format!("Failed to create checkpoint: {}", e))?;
+
+    crate::commands::checkpoint_validation::spawn_checkpoint_validation(
+        app_handle,
+        project_id,
+        session_id,
+        &result.checkpoint.id,
+        project_path,
+    );
+
+    Ok(result)
+}
+
+/// Creates a checkpoint for the current session state
+#[tauri::command]
+pub async fn create_checkpoint(
+    app_handle: AppHandle,
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    message_index: Option<usize>,
+    description: Option<String>,
+) -> Result<crate::checkpoint::CheckpointResult, String> {
+    checkpoint_session(
+        &app_handle,
+        &app,
+        &session_id,
+        &project_id,
+        &project_path,
+        message_index,
+        description,
+    )
+    .await
 }
 
 /// Restores a session to a specific checkpoint
@@ -1969,6 +2274,36 @@ pub async fn update_checkpoint_settings(
         .map_err(|e| format!("Failed to update settings: {}", e))
 }
 
+/// Chooses the checkpoint backend for a session: "files" keeps checkpoints in
+/// opcode's storage only, "git" also commits them to hidden refs in the project's
+/// repository
+#[tauri::command]
+pub async fn set_checkpoint_backend(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    backend: String,
+) -> Result<(), String> {
+    use crate::checkpoint::CheckpointBackend;
+
+    let backend = match backend.as_str() {
+        "files" => CheckpointBackend::Files,
+        "git" => CheckpointBackend::Git,
+        _ => return Err(format!("Invalid checkpoint backend: {}", backend)),
+    };
+
+    let manager = app
+        .get_or_create_manager(session_id, project_id, PathBuf::from(&project_path))
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    manager
+        .set_backend(backend)
+        .await
+        .map_err(|e| format!("Failed to set checkpoint backend: {}", e))
+}
+
 /// Gets diff between two checkpoints
 #[tauri::command]
 pub async fn get_checkpoint_diff(
@@ -2056,6 +2391,162 @@ pub async fn get_checkpoint_diff(
     })
 }
 
+/// Computes line-level diffs between a checkpoint and another checkpoint, or the
+/// current working tree when `to_checkpoint_id` is omitted
+#[tauri::command]
+pub async fn compute_checkpoint_diff(
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    from_checkpoint_id: String,
+    to_checkpoint_id: Option<String>,
+    context_lines: Option<usize>,
+) -> Result<crate::checkpoint::diff::CheckpointComparison, String> {
+    use crate::checkpoint::diff::{
+        checkpoint_contents, diff_contents, working_tree_contents, CheckpointComparison,
+    };
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    log::info!(
+        "Computing diff from checkpoint {} to {}",
+        from_checkpoint_id,
+        to_checkpoint_id.as_deref().unwrap_or("working tree")
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = CheckpointStorage::new(claude_dir);
+
+    let from = checkpoint_contents(&storage, &project_id, &session_id, &from_checkpoint_id)
+        .map_err(|e| format!("Failed to load source checkpoint: {}", e))?;
+    let to = match &to_checkpoint_id {
+        Some(id) => checkpoint_contents(&storage, &project_id, &session_id, id)
+            .map_err(|e| format!("Failed to load target checkpoint: {}", e))?,
+        None => {
+            let project_path = PathBuf::from(&project_path);
+            tokio::task::spawn_blocking(move || working_tree_contents(&project_path))
+                .await
+                .map_err(|e| format!("Failed to read working tree: {}", e))?
+        }
+    };
+
+    let files = diff_contents(&from, &to, context_lines.unwrap_or(3));
+    Ok(CheckpointComparison {
+        from_checkpoint_id,
+        to_checkpoint_id,
+        total_additions: files.iter().map(|f| f.additions).sum(),
+        total_deletions: files.iter().map(|f| f.deletions).sum(),
+        files,
+    })
+}
+
+/// Exports the changes a checkpoint made relative to its parent as a git-style
+/// patch, optionally writing it to `file_path`
+#[tauri::command]
+pub async fn export_checkpoint_patch(
+    session_id: String,
+    project_id: String,
+    checkpoint_id: String,
+    file_path: Option<String>,
+) -> Result<crate::checkpoint::diff::CheckpointPatch, String> {
+    use crate::checkpoint::diff::{checkpoint_contents, checkpoint_patch, FileContents};
+    use crate::checkpoint::git_backend::{commit_contents, root_base_commit};
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = CheckpointStorage::new(claude_dir.clone());
+
+    let (checkpoint, _, _) = storage
+        .load_checkpoint(&project_id, &session_id, &checkpoint_id)
+        .map_err(|e| format!("Failed to load checkpoint: {}", e))?;
+    let contents = checkpoint_contents(&storage, &project_id, &session_id, &checkpoint_id)
+        .map_err(|e| format!("Failed to load checkpoint: {}", e))?;
+    let mut warnings = Vec::new();
+    let parent_contents = match &checkpoint.parent_checkpoint_id {
+        Some(parent_id) => checkpoint_contents(&storage, &project_id, &session_id, parent_id)
+            .map_err(|e| format!("Failed to load parent checkpoint: {}", e))?,
+        // The first checkpoint is diffed against the commit the session started
+        // from, so the patch applies to a clean clone
+        None => {
+            let project_dir = claude_dir.join("projects").join(&project_id);
+            let (session_id, checkpoint_id) = (session_id.clone(), checkpoint_id.clone());
+            let base = tokio::task::spawn_blocking(move || {
+                let project_path = PathBuf::from(get_project_path_from_sessions(&project_dir)?);
+                match root_base_commit(&project_path, &session_id, &checkpoint_id) {
+                    Some(commit) => commit_contents(&project_path, &commit)
+                        .map(Some)
+                        .map_err(|e| format!("Failed to read commit {}: {}", commit, e)),
+                    None => Ok(None),
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+            match base {
+                Ok(Some(base)) => base,
+                Ok(None) => {
+                    warnings.push(
+                        "The project has no git commit to start from, so the patch adds every file"
+                            .to_string(),
+                    );
+                    FileContents::new()
+                }
+                Err(e) => {
+                    warnings.push(format!("{}, so the patch adds every file", e));
+                    FileContents::new()
+                }
+            }
+        }
+    };
+
+    let mut patch = checkpoint_patch(
+        checkpoint_id,
+        checkpoint.parent_checkpoint_id,
+        &parent_contents,
+        &contents,
+    );
+    warnings.append(&mut patch.warnings);
+    patch.warnings = warnings;
+    if let Some(file_path) = file_path {
+        std::fs::write(&file_path, &patch.patch)
+            .map_err(|e| format!("Failed to write patch: {}", e))?;
+        log::info!(
+            "Exported checkpoint {} as a patch of {} files to {}",
+            patch.checkpoint_id,
+            patch.files.len(),
+            file_path
+        );
+        for warning in &patch.warnings {
+            log::warn!("Patch of checkpoint {}: {}", patch.checkpoint_id, warning);
+        }
+    }
+    Ok(patch)
+}
+
+/// Gets checkpoints from every session of a project as one timeline, newest
+/// first. `since` is an RFC 3339 timestamp.
+#[tauri::command]
+pub async fn get_project_checkpoint_timeline(
+    project_id: String,
+    since: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<crate::checkpoint::project_timeline::ProjectTimelineEntry>, String> {
+    use crate::checkpoint::project_timeline::project_timeline;
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    let since = since
+        .map(|since| {
+            chrono::DateTime::parse_from_rfc3339(&since)
+                .map(|since| since.with_timezone(&chrono::Utc))
+                .map_err(|e| format!("Invalid timestamp '{}': {}", since, e))
+        })
+        .transpose()?;
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = CheckpointStorage::new(claude_dir);
+    tokio::task::spawn_blocking(move || project_timeline(&storage, &project_id, since, limit))
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Tracks a message for checkpointing
 #[tauri::command]
 pub async fn track_checkpoint_message(
@@ -2121,10 +2612,15 @@ pub async fn cleanup_old_checkpoints(
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
-    manager
+    let removed = manager
         .storage
         .cleanup_old_checkpoints(&project_id, &session_id, keep_count)
-        .map_err(|e| format!("Failed to cleanup checkpoints: {}", e))
+        .map_err(|e| format!("Failed to cleanup checkpoints: {}", e))?;
+    manager
+        .reload_timeline()
+        .await
+        .map_err(|e| format!("Failed to reload timeline: {}", e))?;
+    Ok(removed)
 }
 
 /// Gets checkpoint settings for a session
@@ -2149,6 +2645,7 @@ pub async fn get_checkpoint_settings(
         "checkpoint_strategy": timeline.checkpoint_strategy,
         "total_checkpoints": timeline.total_checkpoints,
         "current_checkpoint_id": timeline.current_checkpoint_id,
+        "backend": timeline.backend,
     }))
 }
 
@@ -0,0 +1,392 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::{
+    get_agent, get_agent_run, queue_agent_run, read_session_jsonl, AgentDb, AgentRun,
+};
+use crate::commands::budgets::RunUsageTracker;
+use crate::commands::run_diffs::{run_git, snapshot_worktree};
+use crate::process::ProcessRegistryState;
+
+/// How the two runs of a comparison are kept from interfering with each other
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonIsolation {
+    /// Each run works in its own git worktree seeded with the project's current state
+    #[default]
+    Worktree,
+    /// Both runs share the project but can't modify it
+    ReadOnly,
+}
+
+impl ComparisonIsolation {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Worktree => "worktree",
+            Self::ReadOnly => "read_only",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "read_only" => Self::ReadOnly,
+            _ => Self::Worktree,
+        }
+    }
+}
+
+/// The same agent and task run against two models
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunComparison {
+    pub id: i64,
+    pub agent_id: i64,
+    pub project_path: String,
+    pub task: String,
+    pub isolation: ComparisonIsolation,
+    pub run_a_id: i64,
+    pub run_b_id: i64,
+    pub worktree_a: Option<String>,
+    pub worktree_b: Option<String>,
+    pub created_at: String,
+}
+
+/// One side of a comparison with its outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonSide {
+    pub run: AgentRun,
+    /// Wall-clock time from process start to completion
+    pub duration_ms: Option<i64>,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    /// Session JSONL, once the run has produced one
+    pub transcript: Option<String>,
+}
+
+/// A comparison with both sides loaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunComparisonResult {
+    pub comparison: RunComparison,
+    pub a: ComparisonSide,
+    pub b: ComparisonSide,
+}
+
+/// Milliseconds between a run's RFC 3339 start and its SQLite completion timestamp
+pub fn run_duration_ms(started_at: Option<&str>, completed_at: Option<&str>) -> Option<i64> {
+    let started = DateTime::parse_from_rfc3339(started_at?)
+        .ok()?
+        .with_timezone(&Utc);
+    let completed = NaiveDateTime::parse_from_str(completed_at?, "%Y-%m-%d %H:%M:%S")
+        .ok()?
+        .and_utc();
+    Some((completed - started).num_milliseconds().max(0))
+}
+
+/// Create a detached worktree of the project's repository holding its current
+/// state, uncommitted and untracked files included.
+///
+/// Returns the path inside the worktree matching `project_path`.
+fn create_comparison_worktree(project_path: &Path, worktree: &Path) -> Result<PathBuf, String> {
+    let tree = snapshot_worktree(project_path).ok_or_else(|| {
+        "Project is not a git repository; use read-only isolation instead".to_string()
+    })?;
+    // The project may be a subdirectory of its repository
+    let prefix = run_git(project_path, &["rev-parse", "--show-prefix"], None)?;
+
+    let worktree_str = worktree.to_string_lossy().to_string();
+    run_git(
+        project_path,
+        &["worktree", "add", "--detach", &worktree_str, "HEAD"],
+        None,
+    )?;
+    if let Err(e) = run_git(worktree, &["read-tree", "-u", "--reset", &tree], None) {
+        remove_comparison_worktree(project_path, worktree);
+        return Err(e);
+    }
+
+    Ok(worktree.join(prefix.trim()))
+}
+
+/// Remove a comparison worktree and its git bookkeeping
+fn remove_comparison_worktree(project_path: &Path, worktree: &Path) {
+    let worktree_str = worktree.to_string_lossy().to_string();
+    if let Err(e) = run_git(
+        project_path,
+        &["worktree", "remove", "--force", &worktree_str],
+        None,
+    ) {
+        warn!("Failed to remove worktree {}: {}", worktree_str, e);
+        let _ = std::fs::remove_dir_all(worktree);
+        let _ = run_git(project_path, &["worktree", "prune"], None);
+    }
+}
+
+fn row_to_comparison(row: &rusqlite::Row) -> rusqlite::Result<RunComparison> {
+    Ok(RunComparison {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        project_path: row.get(2)?,
+        task: row.get(3)?,
+        isolation: ComparisonIsolation::parse(&row.get::<_, String>(4)?),
+        run_a_id: row.get(5)?,
+        run_b_id: row.get(6)?,
+        worktree_a: row.get(7)?,
+        worktree_b: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+fn load_comparison(conn: &Connection, comparison_id: i64) -> Result<RunComparison, String> {
+    conn.query_row(
+        "SELECT id, agent_id, project_path, task, isolation, run_a_id, run_b_id, worktree_a, worktree_b, created_at
+         FROM agent_run_comparisons WHERE id = ?1",
+        params![comparison_id],
+        row_to_comparison,
+    )
+    .map_err(|e| format!("Comparison {} not found: {}", comparison_id, e))
+}
+
+async fn load_side(db: State<'_, AgentDb>, run_id: i64) -> Result<ComparisonSide, String> {
+    let run = get_agent_run(db, run_id).await?;
+
+    let transcript = if run.session_id.is_empty() {
+        None
+    } else {
+        read_session_jsonl(&run.session_id, &run.project_path)
+            .await
+            .ok()
+    };
+
+    let usage = RunUsageTracker::from_jsonl(transcript.as_deref().unwrap_or_default());
+
+    Ok(ComparisonSide {
+        duration_ms: run_duration_ms(
+            run.process_started_at.as_deref(),
+            run.completed_at.as_deref(),
+        ),
+        total_tokens: usage.total_tokens(),
+        cost_usd: usage.cost_usd(),
+        transcript,
+        run,
+    })
+}
+
+/// Run an agent's task twice in parallel, once per model, so their output,
+/// duration and cost can be compared side by side
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_run_comparison(
+    app: AppHandle,
+    agent_id: i64,
+    project_path: String,
+    task: String,
+    model_a: String,
+    model_b: String,
+    isolation: Option<ComparisonIsolation>,
+    parameters: Option<HashMap<String, JsonValue>>,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<RunComparison, String> {
+    let isolation = isolation.unwrap_or_default();
+    let agent = get_agent(db.clone(), agent_id).await?;
+
+    // Give each side its own checkout so their edits don't collide
+    let mut worktrees: Vec<PathBuf> = Vec::new();
+    let mut run_paths = [project_path.clone(), project_path.clone()];
+    if isolation == ComparisonIsolation::Worktree {
+        let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        let base = app_data_dir
+            .join("comparison-worktrees")
+            .join(uuid::Uuid::new_v4().simple().to_string());
+        std::fs::create_dir_all(&base)
+            .map_err(|e| format!("Failed to create worktree directory: {}", e))?;
+
+        for (side, run_path) in ["a", "b"].iter().zip(run_paths.iter_mut()) {
+            let worktree = base.join(side);
+            match create_comparison_worktree(Path::new(&project_path), &worktree) {
+                Ok(path) => {
+                    *run_path = path.to_string_lossy().to_string();
+                    worktrees.push(worktree);
+                }
+                Err(e) => {
+                    for worktree in &worktrees {
+                        remove_comparison_worktree(Path::new(&project_path), worktree);
+                    }
+                    let _ = std::fs::remove_dir_all(&base);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    let queued = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (|| -> Result<RunComparison, String> {
+            let run_a_id = queue_agent_run(
+                &conn,
+                &agent,
+                &run_paths[0],
+                &task,
+                &model_a,
+                parameters.clone(),
+            )?;
+            let run_b_id =
+                queue_agent_run(&conn, &agent, &run_paths[1], &task, &model_b, parameters)?;
+
+            if isolation == ComparisonIsolation::ReadOnly {
+                conn.execute(
+                    "UPDATE agent_runs SET read_only = 1 WHERE id IN (?1, ?2)",
+                    params![run_a_id, run_b_id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+
+            let worktree = |i: usize| worktrees.get(i).map(|w| w.to_string_lossy().to_string());
+            conn.execute(
+                "INSERT INTO agent_run_comparisons (agent_id, project_path, task, isolation, run_a_id, run_b_id, worktree_a, worktree_b)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    agent_id,
+                    project_path,
+                    task,
+                    isolation.as_str(),
+                    run_a_id,
+                    run_b_id,
+                    worktree(0),
+                    worktree(1)
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            load_comparison(&conn, conn.last_insert_rowid())
+        })()
+    };
+
+    let comparison = match queued {
+        Ok(comparison) => comparison,
+        Err(e) => {
+            for worktree in &worktrees {
+                remove_comparison_worktree(Path::new(&project_path), worktree);
+            }
+            return Err(e);
+        }
+    };
+
+    info!(
+        "Started comparison {} of agent {}: {} vs {}",
+        comparison.id, agent.name, model_a, model_b
+    );
+
+    crate::commands::queue::dispatch_queued_runs(&app, db, registry).await?;
+    let _ = app.emit("agent-queue-updated", true);
+
+    Ok(comparison)
+}
+
+/// List model comparisons, newest first, optionally for a single agent
+#[tauri::command]
+pub async fn list_run_comparisons(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+) -> Result<Vec<RunComparison>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, agent_id, project_path, task, isolation, run_a_id, run_b_id, worktree_a, worktree_b, created_at
+             FROM agent_run_comparisons WHERE ?1 IS NULL OR agent_id = ?1 ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let comparisons = stmt
+        .query_map(params![agent_id], row_to_comparison)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(comparisons)
+}
+
+/// Get both sides of a comparison with their transcripts, durations and costs
+#[tauri::command]
+pub async fn get_run_comparison(
+    db: State<'_, AgentDb>,
+    comparison_id: i64,
+) -> Result<RunComparisonResult, String> {
+    let comparison = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_comparison(&conn, comparison_id)?
+    };
+
+    let a = load_side(db.clone(), comparison.run_a_id).await?;
+    let b = load_side(db, comparison.run_b_id).await?;
+
+    Ok(RunComparisonResult { comparison, a, b })
+}
+
+/// Delete a finished comparison and its worktrees; the runs themselves are kept
+#[tauri::command]
+pub async fn delete_run_comparison(
+    db: State<'_, AgentDb>,
+    comparison_id: i64,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let comparison = load_comparison(&conn, comparison_id)?;
+
+    let active: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM agent_runs WHERE id IN (?1, ?2) AND status IN ('queued', 'running')",
+            params![comparison.run_a_id, comparison.run_b_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if active > 0 {
+        return Err(format!("Comparison {} is still running", comparison_id));
+    }
+
+    let project_path = Path::new(&comparison.project_path);
+    for worktree in [&comparison.worktree_a, &comparison.worktree_b]
+        .into_iter()
+        .flatten()
+    {
+        let worktree = Path::new(worktree);
+        remove_comparison_worktree(project_path, worktree);
+        if let Some(parent) = worktree.parent() {
+            let _ = std::fs::remove_dir(parent);
+        }
+    }
+
+    conn.execute(
+        "DELETE FROM agent_run_comparisons WHERE id = ?1",
+        params![comparison_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_duration_ms() {
+        assert_eq!(
+            run_duration_ms(
+                Some("2025-01-01T10:00:00.500+00:00"),
+                Some("2025-01-01 10:01:00")
+            ),
+            Some(59_500)
+        );
+        assert_eq!(run_duration_ms(None, Some("2025-01-01 10:01:00")), None);
+        assert_eq!(
+            run_duration_ms(Some("2025-01-01T10:00:00+00:00"), None),
+            None
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::pricing::format_cost;
+use crate::commands::usage_index::refresh_usage_index;
+
+/// Interval between checks for anomalous costs
+const ANOMALY_CHECK_INTERVAL_SECS: u64 = 30 * 60;
+
+/// Days of a project's history a cost is compared against
+const HISTORY_DAYS: i64 = 30;
+
+/// Other sessions or runs a project needs before its costs are judged
+const MIN_HISTORY: usize = 5;
+
+/// Costs below this are never flagged, however unusual
+const MIN_ANOMALY_COST_USD: f64 = 0.5;
+
+/// Times the median a cost has to reach to be flagged
+const MIN_RATIO: f64 = 3.0;
+
+/// Modified z-score above which a cost is an outlier (Iglewicz and Hoaglin)
+const MAX_MODIFIED_Z: f64 = 3.5;
+
+/// A session or agent run that cost far more than usual for its project
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CostAnomaly {
+    pub id: i64,
+    /// "session" or "agent_run"
+    pub kind: String,
+    /// Session id, or agent run id
+    pub subject_id: String,
+    pub project_path: String,
+    pub cost_usd: f64,
+    /// Median cost of the project's other sessions or runs
+    pub median_cost_usd: f64,
+    /// `cost_usd` divided by `median_cost_usd`
+    pub ratio: f64,
+    pub reason: String,
+    pub detected_at: String,
+    pub dismissed_at: Option<String>,
+}
+
+fn median(sorted: &[f64]) -> f64 {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+/// The median of `history` and the ratio of `cost` to it, when `cost` is an
+/// outlier. Outliers are judged with the median absolute deviation, so a few
+/// earlier expensive sessions don't mask a new one.
+pub fn detect_outlier(cost: f64, history: &[f64]) -> Option<(f64, f64)> {
+    if history.len() < MIN_HISTORY || cost < MIN_ANOMALY_COST_USD {
+        return None;
+    }
+    let mut sorted = history.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median_cost = median(&sorted);
+    if median_cost <= 0.0 || cost < median_cost * MIN_RATIO {
+        return None;
+    }
+
+    let mut deviations: Vec<f64> = sorted.iter().map(|c| (c - median_cost).abs()).collect();
+    deviations.sort_by(f64::total_cmp);
+    let mad = median(&deviations);
+    // With most costs identical any clear jump past the ratio is an outlier
+    if mad > 0.0 && 0.6745 * (cost - median_cost) / mad <= MAX_MODIFIED_Z {
+        return None;
+    }
+    Some((median_cost, cost / median_cost))
+}
+
+/// (session id, project path, cost) of each session active in the last
+/// `HISTORY_DAYS` days. Sessions of agent runs are judged as runs instead.
+fn session_costs(conn: &Connection) -> rusqlite::Result<Vec<(String, String, f64)>> {
+    conn.prepare(
+        "SELECT session_id, project_path, SUM(cost) FROM usage_messages
+         WHERE date >= date('now', 'localtime', ?1)
+           AND session_id NOT IN (SELECT session_id FROM agent_runs)
+         GROUP BY project_path, session_id",
+    )?
+    .query_map(params![format!("-{} days", HISTORY_DAYS)], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })?
+    .collect()
+}
+
+/// (run id, project path, cost) of each agent run finished in the last `HISTORY_DAYS` days
+fn run_costs(conn: &Connection) -> rusqlite::Result<Vec<(String, String, f64)>> {
+    conn.prepare(
+        "SELECT CAST(id AS TEXT), project_path, cost_usd FROM agent_runs
+         WHERE cost_usd IS NOT NULL AND completed_at IS NOT NULL
+           AND date(created_at) >= date('now', ?1)",
+    )?
+    .query_map(params![format!("-{} days", HISTORY_DAYS)], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })?
+    .collect()
+}
+
+/// Flag the outliers among `costs`, each compared with the rest of its
+/// project. Returns the anomalies detected for the first time.
+fn flag_outliers(
+    conn: &Connection,
+    kind: &str,
+    costs: &[(String, String, f64)],
+) -> Result<Vec<CostAnomaly>, String> {
+    let mut by_project: HashMap<&str, Vec<(&str, f64)>> = HashMap::new();
+    for (subject_id, project_path, cost) in costs {
+        by_project
+            .entry(project_path.as_str())
+            .or_default()
+            .push((subject_id.as_str(), *cost));
+    }
+
+    let label = if kind == "session" { "session" } else { "run" };
+    let mut detected = Vec::new();
+    for (project_path, subjects) in &by_project {
+        for (index, (subject_id, cost)) in subjects.iter().enumerate() {
+            let history: Vec<f64> = subjects
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, (_, cost))| *cost)
+                .collect();
+            let Some((median_cost, ratio)) = detect_outlier(*cost, &history) else {
+                continue;
+            };
+            let reason = format!(
+                "This {} cost {}, {:.1}x the median of {} over {} other {}s in the last {} days",
+                label,
+                format_cost(*cost),
+                ratio,
+                format_cost(median_cost),
+                history.len(),
+                label,
+                HISTORY_DAYS
+            );
+
+            // Sessions keep growing; refresh the figures of known anomalies
+            let existing = conn
+                .execute(
+                    "UPDATE cost_anomalies SET cost_usd = ?3, median_cost_usd = ?4, ratio = ?5, reason = ?6
+                     WHERE kind = ?1 AND subject_id = ?2",
+                    params![kind, subject_id, cost, median_cost, ratio, reason],
+                )
+                .map_err(|e| e.to_string())?;
+            if existing > 0 {
+                continue;
+            }
+            conn.execute(
+                "INSERT INTO cost_anomalies (kind, subject_id, project_path, cost_usd, median_cost_usd, ratio, reason)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![kind, subject_id, project_path, cost, median_cost, ratio, reason],
+            )
+            .map_err(|e| e.to_string())?;
+            detected.push(get_anomaly(conn, conn.last_insert_rowid())?);
+        }
+    }
+    Ok(detected)
+}
+
+fn row_to_anomaly(row: &rusqlite::Row) -> rusqlite::Result<CostAnomaly> {
+    Ok(CostAnomaly {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        subject_id: row.get(2)?,
+        project_path: row.get(3)?,
+        cost_usd: row.get(4)?,
+        median_cost_usd: row.get(5)?,
+        ratio: row.get(6)?,
+        reason: row.get(7)?,
+        detected_at: row.get(8)?,
+        dismissed_at: row.get(9)?,
+    })
+}
+
+const ANOMALY_COLUMNS: &str =
+    "id, kind, subject_id, project_path, cost_usd, median_cost_usd, ratio, reason,
+    detected_at, dismissed_at";
+
+fn get_anomaly(conn: &Connection, id: i64) -> Result<CostAnomaly, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM cost_anomalies WHERE id = ?1",
+            ANOMALY_COLUMNS
+        ),
+        params![id],
+        row_to_anomaly,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Flag anomalous sessions and agent runs and announce new ones
+fn check_anomalies(app: &AppHandle, conn: &mut Connection) -> Result<(), String> {
+    refresh_usage_index(conn);
+    let sessions = session_costs(conn).map_err(|e| e.to_string())?;
+    let runs = run_costs(conn).map_err(|e| e.to_string())?;
+    let mut detected = flag_outliers(conn, "session", &sessions)?;
+    detected.extend(flag_outliers(conn, "agent_run", &runs)?);
+
+    for anomaly in detected {
+        info!(
+            "Cost anomaly in {}: {}",
+            anomaly.project_path, anomaly.reason
+        );
+        let _ = app.emit("cost-anomaly", anomaly);
+    }
+    Ok(())
+}
+
+/// Look for anomalous costs in the background
+pub fn start_anomaly_monitor(app: AppHandle, db_path: PathBuf) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let (app, path) = (app.clone(), db_path.clone());
+            let _ = tokio::task::spawn_blocking(move || {
+                let checked = Connection::open(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|mut conn| check_anomalies(&app, &mut conn));
+                if let Err(e) = checked {
+                    warn!("Failed to check for cost anomalies: {}", e);
+                }
+            })
+            .await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(
+                ANOMALY_CHECK_INTERVAL_SECS,
+            ))
+            .await;
+        }
+    });
+}
+
+/// Flagged sessions and agent runs, newest first
+#[tauri::command]
+pub async fn list_cost_anomalies(
+    db: State<'_, AgentDb>,
+    project_path: Option<String>,
+    include_dismissed: Option<bool>,
+) -> Result<Vec<CostAnomaly>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM cost_anomalies
+             WHERE (?1 IS NULL OR project_path = ?1) AND (?2 OR dismissed_at IS NULL)
+             ORDER BY detected_at DESC, id DESC",
+            ANOMALY_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let anomalies = stmt
+        .query_map(
+            params![project_path, include_dismissed.unwrap_or(false)],
+            row_to_anomaly,
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(anomalies)
+}
+
+/// Hide an anomaly from the dashboard; it isn't flagged again
+#[tauri::command]
+pub async fn dismiss_cost_anomaly(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE cost_anomalies SET dismissed_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Cost anomaly {} not found", id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::agents::open_database;
+
+    #[test]
+    fn test_detect_outlier() {
+        let history = [1.0, 1.2, 0.8, 1.1, 0.9, 1.0];
+        let (median_cost, ratio) = detect_outlier(10.0, &history).unwrap();
+        assert!((median_cost - 1.0).abs() < 1e-9);
+        assert!((ratio - 10.0).abs() < 1e-9);
+        assert_eq!(detect_outlier(1.5, &history), None);
+        // Too little history to judge
+        assert_eq!(detect_outlier(10.0, &history[..4]), None);
+        // Widely spread costs make a 4x session unremarkable
+        assert_eq!(detect_outlier(4.0, &[0.2, 0.5, 1.0, 3.0, 6.0, 1.0]), None);
+        // Cheap sessions are never flagged
+        assert_eq!(detect_outlier(0.3, &[0.01; 6]), None);
+        assert!(detect_outlier(3.0, &[1.0; 6]).is_some());
+
+        let dir = tempfile::tempdir().unwrap();
+        let conn = open_database(&dir.path().join("agents.db")).unwrap();
+        let mut costs: Vec<(String, String, f64)> = (0..6)
+            .map(|i| (format!("s{}", i), "/p".to_string(), 1.0))
+            .collect();
+        costs.push(("big".to_string(), "/p".to_string(), 10.0));
+        // Another project's costs don't count towards this one's history
+        costs.push(("other".to_string(), "/q".to_string(), 50.0));
+
+        let detected = flag_outliers(&conn, "session", &costs).unwrap();
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].subject_id, "big");
+        assert!(detected[0].reason.contains("10.0x the median"));
+
+        costs[6].2 = 20.0;
+        assert!(flag_outliers(&conn, "session", &costs).unwrap().is_empty());
+        let ratio: f64 = conn
+            .query_row(
+                "SELECT ratio FROM cost_anomalies WHERE subject_id = 'big'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!((ratio - 20.0).abs() < 1e-9);
+    }
+}
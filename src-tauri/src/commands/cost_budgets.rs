@@ -0,0 +1,307 @@
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::pricing::format_cost;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::commands::usage_index::refresh_usage_index;
+use crate::process::normalize_project_path;
+
+/// app_settings key holding the configured budgets
+const BUDGETS_KEY: &str = "cost_budgets";
+
+/// app_settings key holding the highest threshold already alerted per budget
+/// and period, so each alert fires once
+const ALERTS_KEY: &str = "cost_budget_alerts";
+
+/// Percentages of a budget that trigger an alert when spend crosses them
+const THRESHOLDS: [u8; 3] = [50, 80, 100];
+
+/// Interval between checks of spend against the budgets
+const BUDGET_CHECK_INTERVAL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    /// Monday through Sunday
+    Weekly,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Weekly => "weekly",
+            BudgetPeriod::Monthly => "monthly",
+        }
+    }
+}
+
+/// A spending limit on Claude usage, across all projects or for one
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CostBudget {
+    /// None for a budget on the spend of every project together
+    pub project_path: Option<String>,
+    pub period: BudgetPeriod,
+    pub limit_usd: f64,
+}
+
+/// Spend against a budget in its current period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CostBudgetStatus {
+    pub budget: CostBudget,
+    /// First day of the current period, `YYYY-MM-DD`
+    pub period_start: String,
+    pub spent_usd: f64,
+    pub percent: f64,
+    /// Highest threshold crossed this period, if any
+    pub threshold: Option<u8>,
+}
+
+/// Payload of `cost-budget-alert`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CostBudgetAlert {
+    pub status: CostBudgetStatus,
+    /// The threshold just crossed
+    pub threshold: u8,
+}
+
+/// First day of the period containing `today`
+pub fn period_start(period: BudgetPeriod, today: NaiveDate) -> NaiveDate {
+    match period {
+        BudgetPeriod::Weekly => {
+            today - Duration::days(today.weekday().num_days_from_monday() as i64)
+        }
+        BudgetPeriod::Monthly => today.with_day(1).unwrap_or(today),
+    }
+}
+
+/// Highest threshold that `percent` reaches
+fn reached_threshold(percent: f64) -> Option<u8> {
+    THRESHOLDS
+        .iter()
+        .rev()
+        .find(|threshold| percent >= **threshold as f64)
+        .copied()
+}
+
+/// The threshold to alert on, when spend reached one higher than the last
+/// alerted this period
+pub fn threshold_to_alert(percent: f64, last_alerted: Option<u8>) -> Option<u8> {
+    reached_threshold(percent).filter(|threshold| Some(*threshold) > last_alerted)
+}
+
+/// Key of a budget's alert state in one period
+fn alert_key(budget: &CostBudget, period_start: &str) -> String {
+    format!(
+        "{}:{}:{}",
+        budget.period.as_str(),
+        budget.project_path.as_deref().unwrap_or("*"),
+        period_start
+    )
+}
+
+/// Indexed spend since `since`, for one project or all of them
+fn spend_since(conn: &Connection, project_path: Option<&str>, since: &str) -> Result<f64, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(cost), 0) FROM usage_with_rollups
+         WHERE date >= ?1 AND (?2 IS NULL OR project_path = ?2)",
+        params![since, project_path],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Spend against every budget in its current period
+fn budget_statuses(conn: &Connection, today: NaiveDate) -> Result<Vec<CostBudgetStatus>, String> {
+    let budgets: Vec<CostBudget> = load_json_setting(conn, BUDGETS_KEY);
+    budgets
+        .into_iter()
+        .map(|budget| {
+            let start = period_start(budget.period, today)
+                .format("%Y-%m-%d")
+                .to_string();
+            let spent_usd = spend_since(conn, budget.project_path.as_deref(), &start)?;
+            let percent = if budget.limit_usd > 0.0 {
+                spent_usd / budget.limit_usd * 100.0
+            } else {
+                0.0
+            };
+            Ok(CostBudgetStatus {
+                budget,
+                period_start: start,
+                spent_usd,
+                percent,
+                threshold: reached_threshold(percent),
+            })
+        })
+        .collect()
+}
+
+/// Alert on every budget whose spend crossed a new threshold
+fn check_budgets(app: &AppHandle, conn: &mut Connection) -> Result<(), String> {
+    refresh_usage_index(conn);
+    let statuses = budget_statuses(conn, Local::now().date_naive())?;
+    let mut alerted: HashMap<String, u8> = load_json_setting(conn, ALERTS_KEY);
+    let mut current = Vec::new();
+    let mut changed = false;
+
+    for status in statuses {
+        let key = alert_key(&status.budget, &status.period_start);
+        current.push(key.clone());
+        let Some(threshold) = threshold_to_alert(status.percent, alerted.get(&key).copied()) else {
+            continue;
+        };
+        alerted.insert(key, threshold);
+        changed = true;
+
+        let scope = status
+            .budget
+            .project_path
+            .as_deref()
+            .map(|path| path.rsplit(['/', '\\']).next().unwrap_or(path).to_string())
+            .unwrap_or_else(|| "All projects".to_string());
+        let period = status.budget.period.as_str();
+        info!(
+            "{} {} budget at {:.0}% (${:.2} of ${:.2})",
+            scope, period, status.percent, status.spent_usd, status.budget.limit_usd
+        );
+        if let Err(e) = app
+            .notification()
+            .builder()
+            .title(format!(
+                "{} reached {}% of its {} budget",
+                scope, threshold, period
+            ))
+            .body(format!(
+                "{} spent of {} since {}",
+                format_cost(status.spent_usd),
+                format_cost(status.budget.limit_usd),
+                status.period_start
+            ))
+            .show()
+        {
+            warn!("Failed to show desktop notification: {}", e);
+        }
+        let _ = app.emit("cost-budget-alert", CostBudgetAlert { status, threshold });
+    }
+
+    // Forget the state of past periods and removed budgets
+    let before = alerted.len();
+    alerted.retain(|key, _| current.contains(key));
+    if changed || alerted.len() != before {
+        save_json_setting(conn, ALERTS_KEY, &alerted)?;
+    }
+    Ok(())
+}
+
+/// Check spend against the budgets in the background
+pub fn start_budget_monitor(app: AppHandle, db_path: PathBuf) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let (app, path) = (app.clone(), db_path.clone());
+            let _ = tokio::task::spawn_blocking(move || {
+                let checked = Connection::open(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|mut conn| check_budgets(&app, &mut conn));
+                if let Err(e) = checked {
+                    warn!("Failed to check cost budgets: {}", e);
+                }
+            })
+            .await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(BUDGET_CHECK_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+/// The configured cost budgets
+#[tauri::command]
+pub async fn get_cost_budgets(db: State<'_, AgentDb>) -> Result<Vec<CostBudget>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_json_setting(&conn, BUDGETS_KEY))
+}
+
+/// Replace the cost budgets; at most one per project and period
+#[tauri::command]
+pub async fn set_cost_budgets(
+    db: State<'_, AgentDb>,
+    budgets: Vec<CostBudget>,
+) -> Result<(), String> {
+    let mut seen = Vec::new();
+    let budgets: Vec<CostBudget> = budgets
+        .into_iter()
+        .map(|budget| CostBudget {
+            project_path: budget
+                .project_path
+                .as_deref()
+                .map(normalize_project_path)
+                .filter(|path| !path.is_empty()),
+            ..budget
+        })
+        .collect();
+    for budget in &budgets {
+        if budget.limit_usd.is_nan() || budget.limit_usd <= 0.0 {
+            return Err("Budget limits must be greater than zero".to_string());
+        }
+        let key = (budget.project_path.clone(), budget.period);
+        if seen.contains(&key) {
+            return Err(format!(
+                "More than one {} budget for {}",
+                budget.period.as_str(),
+                budget.project_path.as_deref().unwrap_or("all projects")
+            ));
+        }
+        seen.push(key);
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, BUDGETS_KEY, &budgets)
+}
+
+/// Spend against each budget in its current period
+#[tauri::command]
+pub async fn get_cost_budget_status(
+    db: State<'_, AgentDb>,
+) -> Result<Vec<CostBudgetStatus>, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    refresh_usage_index(&mut conn);
+    budget_statuses(&conn, Local::now().date_naive())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_periods_and_thresholds() {
+        let thursday = NaiveDate::from_ymd_opt(2025, 6, 12).unwrap();
+        assert_eq!(
+            period_start(BudgetPeriod::Weekly, thursday),
+            NaiveDate::from_ymd_opt(2025, 6, 9).unwrap()
+        );
+        assert_eq!(
+            period_start(BudgetPeriod::Monthly, thursday),
+            NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()
+        );
+
+        assert_eq!(threshold_to_alert(49.9, None), None);
+        assert_eq!(threshold_to_alert(55.0, None), Some(50));
+        assert_eq!(threshold_to_alert(79.0, Some(50)), None);
+        // Jumping past several thresholds alerts once, for the highest
+        assert_eq!(threshold_to_alert(120.0, Some(50)), Some(100));
+        assert_eq!(threshold_to_alert(150.0, Some(100)), None);
+
+        let budget = CostBudget {
+            project_path: None,
+            period: BudgetPeriod::Monthly,
+            limit_usd: 100.0,
+        };
+        assert_eq!(alert_key(&budget, "2025-06-01"), "monthly:*:2025-06-01");
+    }
+}
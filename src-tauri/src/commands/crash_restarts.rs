@@ -0,0 +1,215 @@
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Per-agent policy for relaunching runs whose Claude process crashed
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CrashRestartPolicy {
+    /// Times a run is relaunched with `--resume` after Claude exits without
+    /// finishing; 0 turns restarts off
+    pub max_restarts: u32,
+}
+
+/// Whether a run's process died instead of finishing: it ended without a
+/// result message and without exiting cleanly
+pub fn is_crash(exit_code: Option<i32>, saw_result: bool) -> bool {
+    !saw_result && exit_code != Some(0)
+}
+
+/// Load an agent's crash restart policy, defaulting to no restarts
+pub fn load_crash_restart_policy(
+    conn: &Connection,
+    agent_id: i64,
+) -> Result<CrashRestartPolicy, String> {
+    let max_restarts = conn
+        .query_row(
+            "SELECT max_restarts FROM agent_crash_restart_policies WHERE agent_id = ?1",
+            params![agent_id],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(CrashRestartPolicy {
+        max_restarts: max_restarts.unwrap_or(0).max(0) as u32,
+    })
+}
+
+/// Queue a crashed run to continue its Claude session if the agent's policy
+/// allows another restart.
+///
+/// The run keeps its id, and the output it wrote so far is held, followed by a
+/// `crash_restart` system message, until the relaunch picks it up with
+/// `load_crash_output`. Returns the restart number and that message.
+pub fn queue_crash_restart(
+    conn: &Connection,
+    run_id: i64,
+    exit_code: Option<i32>,
+    output: &str,
+) -> Result<Option<(u32, String)>, String> {
+    let (agent_id, restarts, session_id, status): (i64, i64, String, String) = conn
+        .query_row(
+            "SELECT agent_id, COALESCE(crash_restarts, 0), session_id, status FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    // Runs that were stopped in the meantime stay stopped, and there is nothing
+    // to resume until Claude has started a session
+    if status != "running" || session_id.is_empty() {
+        return Ok(None);
+    }
+    let policy = load_crash_restart_policy(conn, agent_id)?;
+    let restart = restarts.max(0) as u32 + 1;
+    if restart > policy.max_restarts {
+        return Ok(None);
+    }
+
+    let marker = serde_json::json!({
+        "type": "system",
+        "subtype": "crash_restart",
+        "restart": restart,
+        "max_restarts": policy.max_restarts,
+        "exit_code": exit_code,
+    })
+    .to_string();
+    let stitched = format!("{}{}\n", output, marker);
+
+    let rows = conn
+        .execute(
+            "UPDATE agent_runs SET status = 'queued', pid = NULL, resume_pending = 1,
+                 crash_restarts = ?2, crash_output = ?3,
+                 queue_position = (SELECT COALESCE(MAX(queue_position), 0) + 1 FROM agent_runs)
+             WHERE id = ?1 AND status = 'running'",
+            params![run_id, restart, stitched],
+        )
+        .map_err(|e| e.to_string())?;
+    if rows == 0 {
+        return Ok(None);
+    }
+
+    info!(
+        "Agent run {} crashed (exit code {:?}), restarting {}/{}",
+        run_id, exit_code, restart, policy.max_restarts
+    );
+    Ok(Some((restart, marker)))
+}
+
+/// The output a run wrote before it crashed, if there is any. It is kept
+/// until the relaunch has started, so a failed spawn doesn't lose it.
+pub fn load_crash_output(conn: &Connection, run_id: i64) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT crash_output FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Get the crash restart policy for an agent
+#[tauri::command]
+pub async fn get_agent_crash_restart_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<CrashRestartPolicy, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_crash_restart_policy(&conn, agent_id)
+}
+
+/// Store the crash restart policy for an agent; 0 restarts removes it
+pub fn save_crash_restart_policy(
+    conn: &Connection,
+    agent_id: i64,
+    policy: &CrashRestartPolicy,
+) -> Result<(), String> {
+    if policy.max_restarts == 0 {
+        conn.execute(
+            "DELETE FROM agent_crash_restart_policies WHERE agent_id = ?1",
+            params![agent_id],
+        )
+        .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_crash_restart_policies (agent_id, max_restarts) VALUES (?1, ?2)",
+        params![agent_id, policy.max_restarts],
+    )
+    .map_err(|e| format!("Failed to save crash restart policy: {}", e))?;
+
+    Ok(())
+}
+
+/// Set the crash restart policy for an agent; 0 restarts removes it
+#[tauri::command]
+pub async fn set_agent_crash_restart_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    policy: CrashRestartPolicy,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_crash_restart_policy(&conn, agent_id, &policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::agents::open_database;
+
+    #[test]
+    fn test_crash_restarts_stitch_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = open_database(&dir.path().join("agents.db")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO agents (id, name, icon, system_prompt) VALUES (1, 'Fixer', 'bot', ''), (2, 'Other', 'bot', '');
+             INSERT INTO agent_runs (id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid)
+             VALUES (1, 1, 'Fixer', 'bot', 'Fix it', 'sonnet', '/p', 'abc', 'running', 42),
+                    (2, 1, 'Fixer', 'bot', 'Fix it', 'sonnet', '/p', '', 'running', 43),
+                    (3, 2, 'Other', 'bot', 'Fix it', 'sonnet', '/p', 'def', 'running', 44);",
+        )
+        .unwrap();
+        save_crash_restart_policy(&conn, 1, &CrashRestartPolicy { max_restarts: 1 }).unwrap();
+
+        assert!(is_crash(None, false));
+        assert!(is_crash(Some(137), false));
+        assert!(!is_crash(Some(0), false));
+        assert!(!is_crash(Some(1), true));
+
+        let (restart, marker) = queue_crash_restart(&conn, 1, Some(137), "first\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(restart, 1);
+        assert!(marker.contains(r#""subtype":"crash_restart""#));
+        let (status, resume_pending): (String, bool) = conn
+            .query_row(
+                "SELECT status, resume_pending FROM agent_runs WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((status.as_str(), resume_pending), ("queued", true));
+
+        // The relaunch picks up the earlier output, then the policy runs out
+        let output = load_crash_output(&conn, 1).unwrap().unwrap();
+        assert_eq!(output, format!("first\n{}\n", marker));
+        conn.execute("UPDATE agent_runs SET status = 'running' WHERE id = 1", [])
+            .unwrap();
+        assert_eq!(queue_crash_restart(&conn, 1, None, &output).unwrap(), None);
+
+        // A run cancelled before its process exits isn't restarted
+        conn.execute(
+            "UPDATE agent_runs SET status = 'cancelled' WHERE id = 1",
+            [],
+        )
+        .unwrap();
+        assert_eq!(queue_crash_restart(&conn, 1, None, "").unwrap(), None);
+
+        // No session to resume, or no policy for the agent
+        assert_eq!(queue_crash_restart(&conn, 2, None, "").unwrap(), None);
+        assert_eq!(queue_crash_restart(&conn, 3, None, "").unwrap(), None);
+    }
+}
@@ -0,0 +1,281 @@
+use log::{info, warn};
+use regex::Regex;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::checkpoint::state::CheckpointState;
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::process::ProcessRegistryState;
+use crate::stream_json::StreamMessage;
+
+/// app_settings key holding the destructive-command settings as JSON
+const SETTINGS_KEY: &str = "destructive_checkpoints";
+
+/// Commands that get a checkpoint first unless the user changes the list
+const DEFAULT_PATTERNS: &[&str] = &[
+    r"\brm\s+(-\S+\s+)*-[a-zA-Z]*([rR][a-zA-Z]*f|f[a-zA-Z]*[rR])",
+    r"\brm\s+(-\S+\s+)*--recursive\b",
+    r"\bgit\s+reset\b.*--hard\b",
+    r"\bgit\s+clean\b",
+    r"\bgit\s+checkout\s+(--\s+)?\.(\s|$)",
+    r"(?i)\bdrop\s+(table|database|schema)\b",
+    r"(?i)\btruncate\s+table\b",
+];
+
+/// Bash commands that trigger a checkpoint before they run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DestructiveCheckpointSettings {
+    pub enabled: bool,
+    /// Regular expressions matched against the command line
+    pub patterns: Vec<String>,
+}
+
+impl Default for DestructiveCheckpointSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            patterns: DEFAULT_PATTERNS.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+fn load_settings(conn: &Connection) -> DestructiveCheckpointSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// Compiled trigger patterns for one Claude process
+pub struct DestructiveGuard {
+    patterns: Vec<Regex>,
+}
+
+impl DestructiveGuard {
+    pub fn new(settings: &DestructiveCheckpointSettings) -> Result<Self, String> {
+        let patterns = settings
+            .patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// The guard for the saved settings, or None when the feature is off
+    pub fn load(app: &AppHandle) -> Option<Self> {
+        let settings = {
+            let db = app.try_state::<AgentDb>()?;
+            let conn = db.0.lock().ok()?;
+            load_settings(&conn)
+        };
+        if !settings.enabled {
+            return None;
+        }
+        Self::new(&settings)
+            .map_err(|e| warn!("Destructive command detection is off: {}", e))
+            .ok()
+    }
+
+    /// The first Bash command in a stream message that matches a pattern
+    pub fn destructive_command(&self, message: &StreamMessage) -> Option<String> {
+        let StreamMessage::Assistant(turn) = message else {
+            return None;
+        };
+        turn.tool_uses()
+            .filter(|(_, name, _)| *name == "Bash")
+            .filter_map(|(_, _, input)| input["command"].as_str())
+            .find(|command| self.patterns.iter().any(|regex| regex.is_match(command)))
+            .map(str::to_string)
+    }
+}
+
+/// The project ID under ~/.claude/projects that holds a session, as the
+/// checkpoint commands are given it
+fn session_project_id(session_id: &str) -> Result<String, String> {
+    let session_file = crate::commands::session_diffs::find_session_file(session_id)?;
+    session_file
+        .parent()
+        .and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("No project holds session {}", session_id))
+}
+
+/// Pause a registered run and everything it spawned, so a tool command that
+/// already started stops with it; returns whether this call paused it. A run
+/// the user paused is left for them to unpause.
+async fn pause_run(app: &AppHandle, run_id: i64) -> bool {
+    let registry = app.state::<ProcessRegistryState>();
+    let info = match registry.0.get_process(run_id) {
+        Ok(Some(info)) if info.paused_at.is_none() => info,
+        _ => return false,
+    };
+    let tree = crate::commands::processes::process_tree(info.pid).await;
+    match registry.0.pause(run_id, &tree) {
+        Ok(paused) => paused,
+        Err(e) => {
+            warn!("Failed to pause run {} for a checkpoint: {}", run_id, e);
+            false
+        }
+    }
+}
+
+/// Checkpoint a session before a destructive command runs. The run's whole
+/// process tree is paused until the checkpoint is saved, so the command can't
+/// change files while they are being copied.
+pub async fn checkpoint_before_command(
+    app: &AppHandle,
+    run_id: Option<i64>,
+    session_id: &str,
+    project_path: &str,
+    command: &str,
+) {
+    let project_id = match session_project_id(session_id) {
+        Ok(project_id) => project_id,
+        Err(e) => {
+            warn!("Failed to checkpoint before '{}': {}", command, e);
+            return;
+        }
+    };
+    let description = format!(
+        "Before: {}",
+        crate::checkpoint::project_timeline::prompt_snippet(command)
+    );
+
+    let paused = match run_id {
+        Some(run_id) => pause_run(app, run_id).await,
+        None => false,
+    };
+    let result = crate::commands::claude::checkpoint_session(
+        app,
+        &app.state::<CheckpointState>(),
+        session_id,
+        &project_id,
+        project_path,
+        None,
+        Some(description),
+    )
+    .await;
+    if let (true, Some(run_id)) = (paused, run_id) {
+        if let Err(e) = app.state::<ProcessRegistryState>().0.unpause(run_id) {
+            warn!("Failed to unpause run {} after a checkpoint: {}", run_id, e);
+        }
+    }
+
+    match result {
+        Ok(result) => {
+            info!(
+                "Checkpoint {} taken before destructive command: {}",
+                result.checkpoint.id, command
+            );
+            let _ = app.emit(
+                "destructive-checkpoint",
+                serde_json::json!({
+                    "session_id": session_id,
+                    "checkpoint_id": result.checkpoint.id,
+                    "command": command,
+                }),
+            );
+        }
+        Err(e) => warn!("Failed to checkpoint before '{}': {}", command, e),
+    }
+}
+
+/// Get the destructive-command checkpoint settings
+#[tauri::command]
+pub async fn get_destructive_checkpoint_settings(
+    db: State<'_, AgentDb>,
+) -> Result<DestructiveCheckpointSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+/// Save the destructive-command checkpoint settings; every pattern must compile
+#[tauri::command]
+pub async fn save_destructive_checkpoint_settings(
+    db: State<'_, AgentDb>,
+    settings: DestructiveCheckpointSettings,
+) -> Result<(), String> {
+    DestructiveGuard::new(&settings)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream_json::parse_message;
+
+    fn bash(command: &str) -> StreamMessage {
+        parse_message(&serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [
+                {"type": "text", "text": "Cleaning up"},
+                {"type": "tool_use", "name": "Bash", "input": {"command": command}},
+            ]},
+        }))
+    }
+
+    #[test]
+    fn test_default_patterns() {
+        let guard = DestructiveGuard::new(&DestructiveCheckpointSettings::default()).unwrap();
+        for command in [
+            "rm -rf build",
+            "rm -fr /tmp/x",
+            "cd app && rm -v -Rf node_modules",
+            "rm --recursive --force dist",
+            "git reset --hard HEAD~1",
+            "git reset HEAD~2 --hard",
+            "git clean -fdx",
+            "git checkout -- .",
+            "sqlite3 app.db 'DROP TABLE users'",
+            "psql -c \"drop database prod\"",
+        ] {
+            assert_eq!(
+                guard.destructive_command(&bash(command)).as_deref(),
+                Some(command),
+                "{}",
+                command
+            );
+        }
+        for command in [
+            "rm file.txt",
+            "rm -f notes.txt",
+            "git reset HEAD file.rs",
+            "git checkout main",
+            "cargo clean",
+            "grep -r 'drop' src",
+        ] {
+            assert_eq!(
+                guard.destructive_command(&bash(command)),
+                None,
+                "{}",
+                command
+            );
+        }
+
+        let write = parse_message(&serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [
+                {"type": "tool_use", "name": "Write", "input": {"command": "rm -rf /"}},
+            ]},
+        }));
+        assert_eq!(guard.destructive_command(&write), None);
+
+        let custom = DestructiveCheckpointSettings {
+            enabled: true,
+            patterns: vec![r"\bterraform\s+destroy\b".to_string()],
+        };
+        let guard = DestructiveGuard::new(&custom).unwrap();
+        assert!(guard
+            .destructive_command(&bash("terraform destroy -auto-approve"))
+            .is_some());
+        assert!(guard.destructive_command(&bash("rm -rf build")).is_none());
+        assert!(DestructiveGuard::new(&DestructiveCheckpointSettings {
+            enabled: true,
+            patterns: vec!["(".to_string()],
+        })
+        .is_err());
+    }
+}
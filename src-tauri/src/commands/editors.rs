@@ -0,0 +1,279 @@
+use log::info;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tauri::State;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+use crate::commands::agent_parameters::render_prompt;
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::commands::shell::load_shell_config;
+use crate::shell_environment::{wsl_to_windows_path, ShellEnvironment};
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// app_settings key holding the editor settings as JSON
+const SETTINGS_KEY: &str = "editor";
+
+/// Editors files can be opened in, each with its own command line
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EditorPreset {
+    #[default]
+    Vscode,
+    Jetbrains,
+    Vim,
+    Custom,
+}
+
+impl EditorPreset {
+    fn template(&self) -> Option<&'static str> {
+        match self {
+            EditorPreset::Vscode => Some("code --goto {{path}}:{{line}}:{{column}}"),
+            EditorPreset::Jetbrains => Some("idea --line {{line}} --column {{column}} {{path}}"),
+            // vim needs a terminal of its own
+            EditorPreset::Vim if cfg!(windows) => Some("wt vim +{{line}} {{path}}"),
+            EditorPreset::Vim => Some("x-terminal-emulator -e vim +{{line}} {{path}}"),
+            EditorPreset::Custom => None,
+        }
+    }
+}
+
+/// Which editor files are opened in
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EditorSettings {
+    pub preset: EditorPreset,
+    /// Command line of the custom editor, with {{path}}, {{line}} and
+    /// {{column}} placeholders
+    pub command_template: Option<String>,
+}
+
+impl EditorSettings {
+    fn template(&self) -> Result<&str, String> {
+        match self.preset.template() {
+            Some(template) => Ok(template),
+            None => self
+                .command_template
+                .as_deref()
+                .filter(|t| !t.trim().is_empty())
+                .ok_or_else(|| "The custom editor has no command".to_string()),
+        }
+    }
+}
+
+fn load_settings(conn: &Connection) -> EditorSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// Split a command template into arguments on whitespace, keeping quoted
+/// parts together
+fn split_template(template: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+
+    for c in template.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(format!(
+            "Unterminated quote in editor command: {}",
+            template
+        ));
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// The program and arguments that open `path` at a line and column. The
+/// placeholders are filled in after splitting, so a path with spaces stays
+/// one argument.
+pub fn editor_command(
+    template: &str,
+    path: &str,
+    line: u32,
+    column: u32,
+) -> Result<(String, Vec<String>), String> {
+    let values = HashMap::from([
+        ("path".to_string(), path.to_string()),
+        ("line".to_string(), line.max(1).to_string()),
+        ("column".to_string(), column.max(1).to_string()),
+    ]);
+    let mut args = split_template(template)?
+        .into_iter()
+        .map(|arg| render_prompt(&arg, &values));
+    let program = args
+        .next()
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| "The editor command is empty".to_string())?;
+    Ok((program, args.collect()))
+}
+
+/// Translate a path from a WSL session to one a Windows editor can open:
+/// /mnt/<drive> paths map to the drive, anything else goes through the
+/// distribution's network share
+pub fn wsl_path_for_editor(path: &str, distro: Option<&str>) -> Result<String, String> {
+    if !path.starts_with('/') {
+        return Ok(path.to_string());
+    }
+    let translated = wsl_to_windows_path(path);
+    if translated != path {
+        return Ok(translated);
+    }
+    let distro = distro.ok_or_else(|| {
+        format!(
+            "Choose a WSL distribution in the shell settings to open {}",
+            path
+        )
+    })?;
+    Ok(format!(
+        r"\\wsl.localhost\{}{}",
+        distro,
+        path.replace('/', "\\")
+    ))
+}
+
+/// Open a file in the configured editor at a line, e.g. from a path in a
+/// tool result or a diff. Relative paths are taken from `project_path`;
+/// paths from WSL sessions are translated for the Windows editor.
+#[tauri::command]
+pub async fn open_in_editor(
+    db: State<'_, AgentDb>,
+    path: String,
+    line: Option<u32>,
+    column: Option<u32>,
+    project_path: Option<String>,
+) -> Result<(), String> {
+    let (settings, shell) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (load_settings(&conn), load_shell_config(&conn))
+    };
+
+    let path = match project_path {
+        Some(project) if Path::new(&path).is_relative() && !path.starts_with('/') => {
+            format!("{}/{}", project.trim_end_matches(['/', '\\']), path)
+        }
+        _ => path,
+    };
+    let path = if cfg!(windows) && shell.environment == ShellEnvironment::Wsl {
+        wsl_path_for_editor(&path, shell.wsl_distro.as_deref())?
+    } else {
+        path
+    };
+
+    let (program, args) = editor_command(
+        settings.template()?,
+        &path,
+        line.unwrap_or(1),
+        column.unwrap_or(1),
+    )?;
+    let resolved =
+        which::which(&program).map_err(|_| format!("{} was not found on PATH", program))?;
+
+    let mut cmd = Command::new(resolved);
+    cmd.args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    cmd.spawn()
+        .map_err(|e| format!("Failed to start {}: {}", program, e))?;
+
+    info!("Opened {} in {}", path, program);
+    Ok(())
+}
+
+/// Get the editor files are opened in
+#[tauri::command]
+pub async fn get_editor_settings(db: State<'_, AgentDb>) -> Result<EditorSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+/// Save the editor files are opened in; a custom command must split cleanly
+#[tauri::command]
+pub async fn save_editor_settings(
+    db: State<'_, AgentDb>,
+    settings: EditorSettings,
+) -> Result<(), String> {
+    editor_command(settings.template()?, "", 1, 1)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_command() {
+        let (program, args) = editor_command(
+            EditorPreset::Vscode.template().unwrap(),
+            "/home/me/my project/main.rs",
+            42,
+            0,
+        )
+        .unwrap();
+        assert_eq!(program, "code");
+        assert_eq!(args, ["--goto", "/home/me/my project/main.rs:42:1"]);
+
+        let (program, args) = editor_command(
+            r#""C:\Program Files\Sublime\subl" {{path}}:{{line}}"#,
+            "a.rs",
+            3,
+            1,
+        )
+        .unwrap();
+        assert_eq!(program, r"C:\Program Files\Sublime\subl");
+        assert_eq!(args, ["a.rs:3"]);
+
+        assert!(editor_command("'vim {{path}}", "a.rs", 1, 1).is_err());
+        assert!(editor_command("  ", "a.rs", 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_wsl_path_for_editor() {
+        assert_eq!(
+            wsl_path_for_editor("/mnt/c/work/app.rs", None).unwrap(),
+            r"C:\work\app.rs"
+        );
+        assert_eq!(
+            wsl_path_for_editor("/home/me/app.rs", Some("Ubuntu")).unwrap(),
+            r"\\wsl.localhost\Ubuntu\home\me\app.rs"
+        );
+        assert!(wsl_path_for_editor("/home/me/app.rs", None).is_err());
+        assert_eq!(
+            wsl_path_for_editor(r"C:\work\app.rs", None).unwrap(),
+            r"C:\work\app.rs"
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::{load_agent, AgentDb};
+use crate::commands::run_diffs::run_git;
+
+/// Lines enclosing what opcode adds to a hook, so it can be removed again
+/// without touching the rest of the script
+const BLOCK_START: &str = "# >>> opcode agent hook >>>";
+const BLOCK_END: &str = "# <<< opcode agent hook <<<";
+/// Comment in the block recording which agent it runs
+const AGENT_MARKER: &str = "# opcode-agent: ";
+
+/// Git hooks an agent can be installed as
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GitHookKind {
+    PreCommit,
+    PrePush,
+}
+
+impl GitHookKind {
+    const ALL: [GitHookKind; 2] = [GitHookKind::PreCommit, GitHookKind::PrePush];
+
+    fn file_name(&self) -> &'static str {
+        match self {
+            GitHookKind::PreCommit => "pre-commit",
+            GitHookKind::PrePush => "pre-push",
+        }
+    }
+
+    fn blocked(&self) -> &'static str {
+        match self {
+            GitHookKind::PreCommit => "commit",
+            GitHookKind::PrePush => "push",
+        }
+    }
+}
+
+/// An agent installed as a git hook in a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledGitHook {
+    pub hook: GitHookKind,
+    pub agent_id: i64,
+    pub path: String,
+}
+
+/// Quote a value for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// The lines a hook runs an agent with, failing the hook when the run fails
+pub fn hook_block(
+    hook: GitHookKind,
+    cli: &str,
+    db_path: &str,
+    agent_id: i64,
+    agent_name: &str,
+    task: Option<&str>,
+) -> String {
+    let mut command = format!(
+        "{} --db {} run {} --project \"$(git rev-parse --show-toplevel)\"",
+        shell_quote(cli),
+        shell_quote(db_path),
+        agent_id
+    );
+    if let Some(task) = task.filter(|t| !t.trim().is_empty()) {
+        command.push_str(&format!(" --task {}", shell_quote(task)));
+    }
+    format!(
+        "{start}\n{marker}{agent_id}\n{command} || {{\n  echo {message} >&2\n  exit 1\n}}\n{end}\n",
+        start = BLOCK_START,
+        marker = AGENT_MARKER,
+        agent_id = agent_id,
+        command = command,
+        message = shell_quote(&format!(
+            "opcode: the {} agent failed, so the {} was stopped (skip it with --no-verify)",
+            agent_name,
+            hook.blocked()
+        )),
+        end = BLOCK_END,
+    )
+}
+
+/// Split a hook script around opcode's block: what comes before, the block,
+/// and what comes after
+fn split_block(script: &str) -> Option<(&str, &str, &str)> {
+    let start = script.find(BLOCK_START)?;
+    let end = start + script[start..].find(BLOCK_END)? + BLOCK_END.len();
+    let end = if script[end..].starts_with('\n') {
+        end + 1
+    } else {
+        end
+    };
+    Some((&script[..start], &script[start..end], &script[end..]))
+}
+
+/// Add opcode's block to a hook script, replacing any block already there.
+/// It goes right after the shebang, so an existing script ending in `exit`
+/// can't skip it; a new script is created when there is none.
+pub fn add_hook_block(script: Option<&str>, block: &str) -> String {
+    let script = script.map(|s| remove_hook_block(s).unwrap_or_default());
+    match script.as_deref() {
+        Some(script) if script.starts_with("#!") => {
+            let (shebang, rest) = script.split_once('\n').unwrap_or((script, ""));
+            format!("{}\n{}{}", shebang, block, rest)
+        }
+        Some(script) if !script.trim().is_empty() => format!("#!/bin/sh\n{}{}", block, script),
+        _ => format!("#!/bin/sh\n{}", block),
+    }
+}
+
+/// Take opcode's block out of a hook script; None when nothing but the
+/// shebang would be left, so the hook can be deleted
+pub fn remove_hook_block(script: &str) -> Option<String> {
+    let remaining = match split_block(script) {
+        Some((before, _, after)) => format!("{}{}", before, after),
+        None => script.to_string(),
+    };
+    let has_commands = remaining
+        .lines()
+        .any(|line| !line.trim().is_empty() && !line.starts_with("#!"));
+    has_commands.then_some(remaining)
+}
+
+/// The agent a hook script's opcode block runs
+fn hook_agent(script: &str) -> Option<i64> {
+    let (_, block, _) = split_block(script)?;
+    block
+        .lines()
+        .find_map(|line| line.strip_prefix(AGENT_MARKER))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Directory git runs a project's hooks from, honouring core.hooksPath
+fn hooks_dir(project_path: &str) -> Result<PathBuf, String> {
+    let project = Path::new(project_path);
+    let dir = run_git(project, &["rev-parse", "--git-path", "hooks"], None)
+        .map_err(|e| format!("{} is not a git repository: {}", project_path, e))?;
+    let dir = PathBuf::from(dir.trim());
+    Ok(if dir.is_absolute() {
+        dir
+    } else {
+        project.join(dir)
+    })
+}
+
+/// The opcode CLI that hooks run agents with: the one shipped next to the
+/// app, or else whichever is on PATH
+fn cli_path() -> String {
+    let name = if cfg!(windows) {
+        "opcode-cli.exe"
+    } else {
+        "opcode-cli"
+    };
+    std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(name))
+        .filter(|cli| cli.exists())
+        .map(|cli| cli.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_else(|| "opcode-cli".to_string())
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("Failed to make {} executable: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+/// Install a pre-commit or pre-push hook in a project that runs an agent
+/// through the opcode CLI and stops the commit or push when the run fails.
+/// An existing hook script is kept and runs after the agent.
+#[tauri::command]
+pub async fn install_git_hook(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    project_path: String,
+    hook: GitHookKind,
+    agent_id: i64,
+    task: Option<String>,
+) -> Result<InstalledGitHook, String> {
+    let agent = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_agent(&conn, agent_id)?
+    };
+    let has_task = task.as_deref().is_some_and(|t| !t.trim().is_empty());
+    if !has_task && agent.default_task.is_none() {
+        return Err(format!(
+            "{} has no default task; give the hook a task to run",
+            agent.name
+        ));
+    }
+    let db_path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?
+        .join("agents.db");
+
+    let dir = hooks_dir(&project_path)?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(hook.file_name());
+    let existing = std::fs::read_to_string(&path).ok();
+
+    let block = hook_block(
+        hook,
+        &cli_path(),
+        &db_path.to_string_lossy().replace('\\', "/"),
+        agent_id,
+        &agent.name,
+        task.as_deref(),
+    );
+    std::fs::write(&path, add_hook_block(existing.as_deref(), &block))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    make_executable(&path)?;
+
+    info!(
+        "Installed {} hook running agent {} in {}",
+        hook.file_name(),
+        agent.name,
+        project_path
+    );
+    Ok(InstalledGitHook {
+        hook,
+        agent_id,
+        path: path.to_string_lossy().to_string(),
+    })
+}
+
+/// Remove opcode's agent from a project's hook, deleting the hook if nothing
+/// else is left in it
+#[tauri::command]
+pub async fn uninstall_git_hook(project_path: String, hook: GitHookKind) -> Result<(), String> {
+    let path = hooks_dir(&project_path)?.join(hook.file_name());
+    let Ok(script) = std::fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    if split_block(&script).is_none() {
+        return Ok(());
+    }
+
+    match remove_hook_block(&script) {
+        Some(remaining) => std::fs::write(&path, remaining),
+        None => std::fs::remove_file(&path),
+    }
+    .map_err(|e| format!("Failed to update {}: {}", path.display(), e))?;
+
+    info!(
+        "Removed opcode {} hook from {}",
+        hook.file_name(),
+        project_path
+    );
+    Ok(())
+}
+
+/// List the agents installed as git hooks in a project
+#[tauri::command]
+pub async fn list_git_hooks(project_path: String) -> Result<Vec<InstalledGitHook>, String> {
+    let dir = hooks_dir(&project_path)?;
+    Ok(GitHookKind::ALL
+        .into_iter()
+        .filter_map(|hook| {
+            let path = dir.join(hook.file_name());
+            let agent_id = hook_agent(&std::fs::read_to_string(&path).ok()?)?;
+            Some(InstalledGitHook {
+                hook,
+                agent_id,
+                path: path.to_string_lossy().to_string(),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_block_round_trip() {
+        let block = hook_block(
+            GitHookKind::PreCommit,
+            "/opt/opcode/opcode-cli",
+            "/data/agents.db",
+            7,
+            "Reviewer",
+            Some("Review what's staged"),
+        );
+        assert!(block.contains("--task 'Review what'\\''s staged'"));
+
+        let created = add_hook_block(None, &block);
+        assert!(created.starts_with("#!/bin/sh\n# >>> opcode agent hook >>>"));
+        assert_eq!(hook_agent(&created), Some(7));
+        assert_eq!(remove_hook_block(&created), None);
+
+        let existing = "#!/usr/bin/env bash\nnpm run lint\nexit 0\n";
+        let installed = add_hook_block(Some(existing), &block);
+        assert!(installed.starts_with("#!/usr/bin/env bash\n# >>> opcode agent hook >>>"));
+        assert!(installed.ends_with("# <<< opcode agent hook <<<\nnpm run lint\nexit 0\n"));
+        // Installing again replaces the block instead of adding another
+        let reinstalled = add_hook_block(Some(&installed), &block);
+        assert_eq!(reinstalled, installed);
+        assert_eq!(remove_hook_block(&installed).as_deref(), Some(existing));
+    }
+}
@@ -0,0 +1,812 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::run_diffs::ChangedFile;
+use crate::commands::session_diffs::{find_session_file, summarize_session};
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::keychain;
+
+/// app_settings key holding the issue tracker settings as JSON
+const SETTINGS_KEY: &str = "issue_trackers";
+
+/// Most changed files listed in a completion comment
+const MAX_LISTED_FILES: usize = 20;
+
+/// Issue trackers a session or run can be linked to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueTracker {
+    Jira,
+    Linear,
+    Github,
+}
+
+impl IssueTracker {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IssueTracker::Jira => "jira",
+            IssueTracker::Linear => "linear",
+            IssueTracker::Github => "github",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "jira" => Some(IssueTracker::Jira),
+            "linear" => Some(IssueTracker::Linear),
+            "github" => Some(IssueTracker::Github),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            IssueTracker::Jira => "Jira",
+            IssueTracker::Linear => "Linear",
+            IssueTracker::Github => "GitHub",
+        }
+    }
+
+    /// Keychain account of the tracker's API token; GitHub shares the token
+    /// used to open pull requests
+    fn keychain_account(&self) -> &'static str {
+        match self {
+            IssueTracker::Jira => "jira-token",
+            IssueTracker::Linear => "linear-token",
+            IssueTracker::Github => "github-token",
+        }
+    }
+}
+
+/// What an issue is linked to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkTarget {
+    Session,
+    Run,
+}
+
+impl LinkTarget {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LinkTarget::Session => "session",
+            LinkTarget::Run => "run",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "session" => Some(LinkTarget::Session),
+            "run" => Some(LinkTarget::Run),
+            _ => None,
+        }
+    }
+}
+
+/// Where bare issue keys point and how Jira is signed in to
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct IssueTrackerSettings {
+    /// Jira site that keys like PROJ-123 belong to, e.g. https://acme.atlassian.net
+    pub jira_base_url: Option<String>,
+    /// Account the Jira Cloud API token belongs to; without one the token is
+    /// sent as a Data Center personal access token
+    pub jira_email: Option<String>,
+    /// Linear workspace keys belong to when no Jira site is set
+    pub linear_workspace: Option<String>,
+}
+
+/// An issue linked to a session or agent run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IssueLink {
+    pub id: i64,
+    pub target: LinkTarget,
+    /// Session ID, or the run ID as text
+    pub target_id: String,
+    pub tracker: IssueTracker,
+    /// PROJ-123, ENG-42 or owner/repo#7
+    pub issue_key: String,
+    pub url: String,
+    /// Comment on the issue when the run finishes
+    pub post_comment: bool,
+    pub commented_at: Option<String>,
+    pub created_at: String,
+}
+
+fn load_settings(conn: &Connection) -> IssueTrackerSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// Whether `value` looks like a Jira or Linear key such as PROJ-123
+fn is_issue_key(value: &str) -> bool {
+    let Some((project, number)) = value.split_once('-') else {
+        return false;
+    };
+    project.starts_with(|c: char| c.is_ascii_uppercase())
+        && project
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Lowercased host of an http(s) URL
+fn url_host(url: &str) -> Option<String> {
+    let rest = url
+        .trim()
+        .strip_prefix("https://")
+        .or_else(|| url.trim().strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// The configured Jira site, without a trailing slash
+fn jira_site(settings: &IssueTrackerSettings) -> Option<&str> {
+    settings
+        .jira_base_url
+        .as_deref()
+        .map(|base| base.trim().trim_end_matches('/'))
+        .filter(|base| !base.is_empty())
+}
+
+/// Tracker, key and URL of an issue given as a URL, an owner/repo#123
+/// reference or a bare key. Jira URLs must be on the configured Jira site,
+/// as that is where comments on them are posted.
+pub fn parse_issue_reference(
+    input: &str,
+    settings: &IssueTrackerSettings,
+) -> Result<(IssueTracker, String, String), String> {
+    let input = input.trim().trim_end_matches('/');
+
+    if let Some(rest) = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))
+    {
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        match (host, segments.as_slice()) {
+            ("github.com", [owner, repo, "issues" | "pull", number, ..])
+                if number.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                return Ok((
+                    IssueTracker::Github,
+                    format!("{}/{}#{}", owner, repo, number),
+                    format!("https://github.com/{}/{}/issues/{}", owner, repo, number),
+                ));
+            }
+            ("linear.app", [workspace, "issue", key, ..]) if is_issue_key(key) => {
+                return Ok((
+                    IssueTracker::Linear,
+                    key.to_string(),
+                    format!("https://linear.app/{}/issue/{}", workspace, key),
+                ));
+            }
+            _ => {}
+        }
+        if let Some(index) = segments.iter().position(|s| *s == "browse") {
+            if let Some(key) = segments.get(index + 1).filter(|key| is_issue_key(key)) {
+                if let Some(site) = jira_site(settings) {
+                    if url_host(site).as_deref() != Some(host.to_ascii_lowercase().as_str()) {
+                        return Err(format!("{} is not on the Jira site {}", input, site));
+                    }
+                }
+                let base = &input[..input.find("/browse/").unwrap_or(input.len())];
+                return Ok((
+                    IssueTracker::Jira,
+                    key.to_string(),
+                    format!("{}/browse/{}", base, key),
+                ));
+            }
+        }
+        return Err(format!("{} is not a Jira, Linear or GitHub issue", input));
+    }
+
+    if let Some((repo, number)) = input.split_once('#') {
+        let valid_repo = repo.split('/').count() == 2 && !repo.contains(char::is_whitespace);
+        if valid_repo && !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+            return Ok((
+                IssueTracker::Github,
+                input.to_string(),
+                format!("https://github.com/{}/issues/{}", repo, number),
+            ));
+        }
+    }
+
+    if is_issue_key(input) {
+        if let Some(base) = jira_site(settings) {
+            let url = format!("{}/browse/{}", base, input);
+            return Ok((IssueTracker::Jira, input.to_string(), url));
+        }
+        if let Some(workspace) = settings.linear_workspace.as_deref() {
+            let url = format!("https://linear.app/{}/issue/{}", workspace.trim(), input);
+            return Ok((IssueTracker::Linear, input.to_string(), url));
+        }
+        return Err(format!(
+            "Set a Jira site or Linear workspace to link {}, or paste the issue URL",
+            input
+        ));
+    }
+
+    Err(format!(
+        "{} is not an issue key, owner/repo#number reference or issue URL",
+        input
+    ))
+}
+
+/// Lines added and removed by a unified diff
+pub fn diff_line_counts(diff: &str) -> (usize, usize) {
+    let (mut added, mut removed, mut in_hunk) = (0, 0, false);
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            in_hunk = false;
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+        } else if in_hunk && line.starts_with('+') {
+            added += 1;
+        } else if in_hunk && line.starts_with('-') {
+            removed += 1;
+        }
+    }
+    (added, removed)
+}
+
+/// Comment posted to a run's issues when it finishes
+pub fn run_comment(
+    run_id: i64,
+    agent_name: &str,
+    status: &str,
+    task: &str,
+    changed_files: &[ChangedFile],
+    diff: &str,
+    pr_url: Option<&str>,
+) -> String {
+    let mut body = format!("opcode: {} agent run #{} {}\n", agent_name, run_id, status);
+    if let Some(first_line) = task.lines().map(str::trim).find(|l| !l.is_empty()) {
+        body.push_str(&format!("\nTask: {}\n", first_line));
+    }
+    if changed_files.is_empty() {
+        body.push_str("\nNo files changed.\n");
+    } else {
+        let (added, removed) = diff_line_counts(diff);
+        body.push_str(&format!("\nChanged files (+{} -{}):\n", added, removed));
+        for file in changed_files.iter().take(MAX_LISTED_FILES) {
+            body.push_str(&format!("- {} ({})\n", file.path, file.status));
+        }
+        if changed_files.len() > MAX_LISTED_FILES {
+            body.push_str(&format!(
+                "- and {} more\n",
+                changed_files.len() - MAX_LISTED_FILES
+            ));
+        }
+    }
+    if let Some(url) = pr_url {
+        body.push_str(&format!("\nPull request: {}\n", url));
+    }
+    body
+}
+
+/// Comment posted to a session's issues
+fn session_comment(session_id: &str) -> Result<String, String> {
+    let path = find_session_file(session_id)?;
+    let jsonl = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read session {}: {}", session_id, e))?;
+    let summary = summarize_session(session_id, &jsonl);
+
+    let mut body = format!(
+        "opcode: Claude session {} ({} prompts, ${:.2})\n",
+        session_id,
+        summary.prompts.len(),
+        summary.cost_usd
+    );
+    if summary.files_modified.is_empty() {
+        body.push_str("\nNo files modified.\n");
+    } else {
+        body.push_str("\nModified files:\n");
+        for file in summary.files_modified.iter().take(MAX_LISTED_FILES) {
+            body.push_str(&format!("- {}\n", file));
+        }
+        if summary.files_modified.len() > MAX_LISTED_FILES {
+            body.push_str(&format!(
+                "- and {} more\n",
+                summary.files_modified.len() - MAX_LISTED_FILES
+            ));
+        }
+    }
+    Ok(body)
+}
+
+/// The comment for whatever a link points at
+fn link_comment(conn: &Connection, link: &IssueLink) -> Result<String, String> {
+    match link.target {
+        LinkTarget::Session => session_comment(&link.target_id),
+        LinkTarget::Run => {
+            let run_id: i64 = link
+                .target_id
+                .parse()
+                .map_err(|_| format!("Invalid run ID {}", link.target_id))?;
+            conn.query_row(
+                "SELECT agent_name, status, task, changed_files, git_diff, pr_url FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| {
+                    let changed_files: Vec<ChangedFile> = row
+                        .get::<_, Option<String>>(3)?
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default();
+                    Ok(run_comment(
+                        run_id,
+                        &row.get::<_, String>(0)?,
+                        &row.get::<_, String>(1)?,
+                        &row.get::<_, String>(2)?,
+                        &changed_files,
+                        &row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                        row.get::<_, Option<String>>(5)?.as_deref(),
+                    ))
+                },
+            )
+            .map_err(|e| format!("Failed to load agent run {}: {}", run_id, e))
+        }
+    }
+}
+
+fn tracker_token(tracker: IssueTracker) -> Result<Option<String>, String> {
+    keychain::get_secret(tracker.keychain_account())
+        .map_err(|e| format!("Failed to read {} token: {}", tracker.label(), e))
+}
+
+/// Post a comment to a linked issue through its tracker's API
+async fn post_comment(
+    link: &IssueLink,
+    settings: &IssueTrackerSettings,
+    body: &str,
+) -> Result<(), String> {
+    let token = tracker_token(link.tracker)?.ok_or_else(|| {
+        format!(
+            "Add a {} API token to comment on {}",
+            link.tracker.label(),
+            link.issue_key
+        )
+    })?;
+    let client = reqwest::Client::new();
+    let request = match link.tracker {
+        IssueTracker::Github => {
+            let (repo, number) = link
+                .issue_key
+                .split_once('#')
+                .ok_or_else(|| format!("Invalid GitHub issue {}", link.issue_key))?;
+            client
+                .post(format!(
+                    "https://api.github.com/repos/{}/issues/{}/comments",
+                    repo, number
+                ))
+                .header("Accept", "application/vnd.github+json")
+                .header("Authorization", format!("Bearer {}", token))
+                .header("User-Agent", "opcode-App")
+                .json(&serde_json::json!({ "body": body }))
+        }
+        IssueTracker::Linear => client
+            .post("https://api.linear.app/graphql")
+            .header("Authorization", token)
+            .json(&serde_json::json!({
+                "query": "mutation($issueId: String!, $body: String!) { commentCreate(input: { issueId: $issueId, body: $body }) { success } }",
+                "variables": { "issueId": link.issue_key, "body": body },
+            })),
+        IssueTracker::Jira => {
+            // Comments only go to the configured site, so the token isn't
+            // sent wherever a link happens to point
+            let base = jira_site(settings).ok_or_else(|| {
+                format!("Set the Jira site to comment on {}", link.issue_key)
+            })?;
+            if url_host(&link.url) != url_host(base) {
+                return Err(format!(
+                    "{} is not on the Jira site {}",
+                    link.issue_key, base
+                ));
+            }
+            let request = client
+                .post(format!("{}/rest/api/2/issue/{}/comment", base, link.issue_key))
+                .json(&serde_json::json!({ "body": body }));
+            match settings.jira_email.as_deref().filter(|e| !e.trim().is_empty()) {
+                Some(email) => request.basic_auth(email.trim(), Some(token)),
+                None => request.bearer_auth(token),
+            }
+        }
+    };
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to comment on {}: {}", link.issue_key, e))?;
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!(
+            "{} API error ({}): {}",
+            link.tracker.label(),
+            status,
+            text
+        ));
+    }
+    // Linear reports failures in the body of a successful response
+    if link.tracker == IssueTracker::Linear {
+        let json: JsonValue = serde_json::from_str(&text).unwrap_or_default();
+        if json["data"]["commentCreate"]["success"] != JsonValue::Bool(true) {
+            return Err(format!("Linear API error: {}", text));
+        }
+    }
+    Ok(())
+}
+
+fn row_to_link(row: &rusqlite::Row) -> rusqlite::Result<Option<IssueLink>> {
+    let target: String = row.get(1)?;
+    let tracker: String = row.get(3)?;
+    let (Some(target), Some(tracker)) = (LinkTarget::parse(&target), IssueTracker::parse(&tracker))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(IssueLink {
+        id: row.get(0)?,
+        target,
+        target_id: row.get(2)?,
+        tracker,
+        issue_key: row.get(4)?,
+        url: row.get(5)?,
+        post_comment: row.get(6)?,
+        commented_at: row.get(7)?,
+        created_at: row.get(8)?,
+    }))
+}
+
+const LINK_COLUMNS: &str =
+    "id, target_type, target_id, tracker, issue_key, url, post_comment, commented_at, created_at";
+
+/// Issues linked to a session or run
+pub fn load_issue_links(
+    conn: &Connection,
+    target: LinkTarget,
+    target_id: &str,
+) -> Result<Vec<IssueLink>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM issue_links WHERE target_type = ?1 AND target_id = ?2 ORDER BY id",
+            LINK_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let links = stmt
+        .query_map(params![target.as_str(), target_id], row_to_link)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(links.into_iter().flatten().collect())
+}
+
+fn load_issue_link(conn: &Connection, link_id: i64) -> Result<IssueLink, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM issue_links WHERE id = ?1", LINK_COLUMNS),
+        params![link_id],
+        row_to_link,
+    )
+    .map_err(|e| format!("Issue link {} not found: {}", link_id, e))?
+    .ok_or_else(|| format!("Issue link {} is invalid", link_id))
+}
+
+/// Save a link that came with imported data, unless it is already there.
+/// Its URL is checked the way a pasted one is; links that don't pass are
+/// skipped.
+pub fn import_issue_link(conn: &Connection, link: &IssueLink) -> Result<usize, String> {
+    let (tracker, issue_key, url) = match parse_issue_reference(&link.url, &load_settings(conn)) {
+        Ok(parsed) if parsed.0 == link.tracker => parsed,
+        Ok(_) => {
+            warn!(
+                "Skipping imported issue link {}: tracker mismatch",
+                link.url
+            );
+            return Ok(0);
+        }
+        Err(e) => {
+            warn!("Skipping imported issue link: {}", e);
+            return Ok(0);
+        }
+    };
+    conn.execute(
+        "INSERT OR IGNORE INTO issue_links (target_type, target_id, tracker, issue_key, url, post_comment, commented_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            link.target.as_str(),
+            link.target_id,
+            tracker.as_str(),
+            issue_key,
+            url,
+            link.post_comment,
+            link.commented_at
+        ],
+    )
+    .map_err(|e| format!("Failed to import issue link {}: {}", issue_key, e))
+}
+
+/// Link a retry of a run to the issues the run is linked to
+pub fn copy_run_issue_links(
+    conn: &Connection,
+    from_run_id: i64,
+    to_run_id: i64,
+) -> Result<usize, String> {
+    conn.execute(
+        "INSERT OR IGNORE INTO issue_links (target_type, target_id, tracker, issue_key, url, post_comment, commented_at)
+         SELECT target_type, ?2, tracker, issue_key, url, post_comment, commented_at
+         FROM issue_links WHERE target_type = 'run' AND target_id = ?1",
+        params![from_run_id.to_string(), to_run_id.to_string()],
+    )
+    .map_err(|e| format!("Failed to copy issue links of run {}: {}", from_run_id, e))
+}
+
+/// Comment on the issues of a finished run that asked for it and haven't
+/// been commented on yet. Runs that are retried leave it to their last
+/// attempt, which has the same links.
+pub fn comment_on_run_issues(db_path: &Path, run_id: i64) {
+    let conn = match Connection::open(db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to open database to comment on issues: {}", e);
+            return;
+        }
+    };
+    let links = match load_issue_links(&conn, LinkTarget::Run, &run_id.to_string()) {
+        Ok(links) => links,
+        Err(e) => {
+            warn!("Failed to load issue links of run {}: {}", run_id, e);
+            return;
+        }
+    };
+    let settings = load_settings(&conn);
+    for link in links
+        .into_iter()
+        .filter(|link| link.post_comment && link.commented_at.is_none())
+    {
+        let body = match link_comment(&conn, &link) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to write comment for {}: {}", link.issue_key, e);
+                continue;
+            }
+        };
+        let db_path = PathBuf::from(db_path);
+        let settings = settings.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = post_comment(&link, &settings, &body).await {
+                warn!("{}", e);
+                return;
+            }
+            if let Ok(conn) = Connection::open(&db_path) {
+                let _ = conn.execute(
+                    "UPDATE issue_links SET commented_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                    params![link.id],
+                );
+            }
+            info!("Commented on {} for agent run {}", link.issue_key, run_id);
+        });
+    }
+}
+
+/// Link an issue, given as a URL, an owner/repo#123 reference or a bare key,
+/// to a session or agent run. With `post_comment`, a run comments on the
+/// issue when it finishes.
+#[tauri::command]
+pub async fn link_issue(
+    db: State<'_, AgentDb>,
+    target: LinkTarget,
+    target_id: String,
+    issue: String,
+    post_comment: bool,
+) -> Result<IssueLink, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let (tracker, issue_key, url) = parse_issue_reference(&issue, &load_settings(&conn))?;
+    conn.execute(
+        "INSERT INTO issue_links (target_type, target_id, tracker, issue_key, url, post_comment)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(target_type, target_id, url) DO UPDATE SET post_comment = excluded.post_comment",
+        params![
+            target.as_str(),
+            target_id,
+            tracker.as_str(),
+            issue_key,
+            url,
+            post_comment
+        ],
+    )
+    .map_err(|e| format!("Failed to link issue: {}", e))?;
+    let id = conn
+        .query_row(
+            "SELECT id FROM issue_links WHERE target_type = ?1 AND target_id = ?2 AND url = ?3",
+            params![target.as_str(), target_id, url],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    load_issue_link(&conn, id)
+}
+
+/// List the issues linked to a session or agent run
+#[tauri::command]
+pub async fn list_issue_links(
+    db: State<'_, AgentDb>,
+    target: LinkTarget,
+    target_id: String,
+) -> Result<Vec<IssueLink>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_issue_links(&conn, target, &target_id)
+}
+
+/// Remove an issue link
+#[tauri::command]
+pub async fn unlink_issue(db: State<'_, AgentDb>, link_id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM issue_links WHERE id = ?1", params![link_id])
+        .map_err(|e| format!("Failed to remove issue link: {}", e))?;
+    Ok(())
+}
+
+/// Comment on a linked issue now with a summary of the session or run and
+/// the changes it made
+#[tauri::command]
+pub async fn post_issue_comment(db: State<'_, AgentDb>, link_id: i64) -> Result<IssueLink, String> {
+    let (link, settings, body) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let link = load_issue_link(&conn, link_id)?;
+        let body = link_comment(&conn, &link)?;
+        (link, load_settings(&conn), body)
+    };
+    post_comment(&link, &settings, &body).await?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE issue_links SET commented_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![link_id],
+    )
+    .map_err(|e| e.to_string())?;
+    info!("Commented on {}", link.issue_key);
+    load_issue_link(&conn, link_id)
+}
+
+/// Get where bare issue keys point and how Jira is signed in to
+#[tauri::command]
+pub async fn get_issue_tracker_settings(
+    db: State<'_, AgentDb>,
+) -> Result<IssueTrackerSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+/// Save where bare issue keys point and how Jira is signed in to
+#[tauri::command]
+pub async fn save_issue_tracker_settings(
+    db: State<'_, AgentDb>,
+    settings: IssueTrackerSettings,
+) -> Result<(), String> {
+    if let Some(base) = settings.jira_base_url.as_deref() {
+        if !base.starts_with("https://") && !base.starts_with("http://") {
+            return Err("The Jira site must start with http:// or https://".to_string());
+        }
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+/// Whether an API token for commenting on a tracker's issues is in the keychain
+#[tauri::command]
+pub async fn has_issue_tracker_token(tracker: IssueTracker) -> Result<bool, String> {
+    Ok(tracker_token(tracker)?.is_some())
+}
+
+/// Store the API token used to comment on a tracker's issues in the
+/// keychain; None removes it
+#[tauri::command]
+pub async fn set_issue_tracker_token(
+    tracker: IssueTracker,
+    token: Option<String>,
+) -> Result<(), String> {
+    let account = tracker.keychain_account();
+    match token.filter(|t| !t.trim().is_empty()) {
+        Some(token) => keychain::set_secret(account, token.trim()).map_err(|e| {
+            format!(
+                "Failed to store {} token in keychain: {}",
+                tracker.label(),
+                e
+            )
+        }),
+        None => keychain::delete_secret(account).map_err(|e| {
+            format!(
+                "Failed to remove {} token from keychain: {}",
+                tracker.label(),
+                e
+            )
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_issue_reference() {
+        let none = IssueTrackerSettings::default();
+        assert_eq!(
+            parse_issue_reference(
+                "https://github.com/acme/app/issues/42#issuecomment-1",
+                &none
+            )
+            .unwrap(),
+            (
+                IssueTracker::Github,
+                "acme/app#42".to_string(),
+                "https://github.com/acme/app/issues/42".to_string()
+            )
+        );
+        assert_eq!(
+            parse_issue_reference("acme/app#7", &none).unwrap().2,
+            "https://github.com/acme/app/issues/7"
+        );
+        assert_eq!(
+            parse_issue_reference("https://linear.app/acme/issue/ENG-12/fix-login", &none).unwrap(),
+            (
+                IssueTracker::Linear,
+                "ENG-12".to_string(),
+                "https://linear.app/acme/issue/ENG-12".to_string()
+            )
+        );
+        assert_eq!(
+            parse_issue_reference("https://acme.atlassian.net/browse/PROJ-123?focus=1", &none)
+                .unwrap(),
+            (
+                IssueTracker::Jira,
+                "PROJ-123".to_string(),
+                "https://acme.atlassian.net/browse/PROJ-123".to_string()
+            )
+        );
+
+        assert!(parse_issue_reference("PROJ-123", &none).is_err());
+        let jira = IssueTrackerSettings {
+            jira_base_url: Some("https://acme.atlassian.net/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_issue_reference(" PROJ-123 ", &jira).unwrap().2,
+            "https://acme.atlassian.net/browse/PROJ-123"
+        );
+        assert!(parse_issue_reference("proj-123", &jira).is_err());
+        // Jira links elsewhere than the configured site are refused
+        assert!(parse_issue_reference("https://ACME.atlassian.net/browse/PROJ-1", &jira).is_ok());
+        assert!(parse_issue_reference("https://evil.example/browse/PROJ-1", &jira).is_err());
+        assert!(parse_issue_reference("https://example.com/tickets/1", &jira).is_err());
+    }
+
+    #[test]
+    fn test_run_comment() {
+        let diff = "diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1,2 +1,2 @@\n-old\n--- not a header\n+new\n keep\n";
+        assert_eq!(diff_line_counts(diff), (1, 2));
+
+        let files = [ChangedFile {
+            status: "M".to_string(),
+            path: "src/a.rs".to_string(),
+        }];
+        let comment = run_comment(
+            9,
+            "Fixer",
+            "completed",
+            "Fix the login bug\nDetails",
+            &files,
+            diff,
+            Some("https://github.com/acme/app/pull/3"),
+        );
+        assert_eq!(
+            comment,
+            "opcode: Fixer agent run #9 completed\n\nTask: Fix the login bug\n\nChanged files (+1 -2):\n- src/a.rs (M)\n\nPull request: https://github.com/acme/app/pull/3\n"
+        );
+    }
+}
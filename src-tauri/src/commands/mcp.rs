@@ -2,11 +2,20 @@ use anyhow::{Context, Result};
 use dirs;
 use log::{error, info};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::mcp_secrets::{
+    config_has_templates, env_references, has_templates, server_secret_references,
+    store_server_secrets, take_config_secrets,
+};
+use crate::commands::mcp_snapshots;
+use crate::mcp_proxy;
+use crate::mcp_supervisor::{self, ServerState, SupervisorStatus};
 
 /// Helper function to create a std::process::Command with proper environment variables
 /// This ensures commands like Claude can find Node.js and other dependencies
@@ -44,7 +53,7 @@ pub struct MCPServer {
 }
 
 /// Server status information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServerStatus {
     /// Whether the server is running
     pub running: bool,
@@ -52,6 +61,26 @@ pub struct ServerStatus {
     pub error: Option<String>,
     /// Last checked timestamp
     pub last_checked: Option<u64>,
+    /// Times the server crashed since its session started
+    #[serde(default)]
+    pub crashes: u32,
+    /// Times the server was restarted after a crash
+    #[serde(default)]
+    pub restarts: u32,
+}
+
+impl From<SupervisorStatus> for ServerStatus {
+    fn from(status: SupervisorStatus) -> Self {
+        Self {
+            running: status.state == ServerState::Running,
+            error: status.last_error,
+            last_checked: chrono::DateTime::parse_from_rfc3339(&status.updated_at)
+                .ok()
+                .map(|time| time.timestamp() as u64),
+            crashes: status.crashes,
+            restarts: status.restarts,
+        }
+    }
 }
 
 /// MCP configuration for project scope (.mcp.json)
@@ -84,6 +113,9 @@ pub struct AddServerResult {
 pub struct ImportResult {
     pub imported_count: u32,
     pub failed_count: u32,
+    /// Servers left out because they were already configured
+    #[serde(default)]
+    pub skipped_count: u32,
     pub servers: Vec<ImportServerResult>,
 }
 
@@ -92,13 +124,39 @@ pub struct ImportResult {
 pub struct ImportServerResult {
     pub name: String,
     pub success: bool,
+    #[serde(default)]
+    pub skipped: bool,
     pub error: Option<String>,
 }
 
+/// Result of exporting servers to another tool's config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportResult {
+    /// The config file written
+    pub path: String,
+    pub exported: Vec<String>,
+    /// Servers left out, with the reason
+    pub skipped: Vec<ImportServerResult>,
+}
+
 /// Executes a claude mcp command
 fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<&str>) -> Result<String> {
     info!("Executing claude mcp command with args: {:?}", args);
 
+    if matches!(
+        args.first().copied(),
+        Some("add" | "add-json" | "remove" | "add-from-claude-desktop")
+    ) {
+        // Project-scoped servers live in the .mcp.json of the working directory
+        let cwd = std::env::current_dir().ok();
+        let cwd = cwd.as_ref().and_then(|dir| dir.to_str());
+        let projects: Vec<&str> = cwd
+            .filter(|dir| args.contains(&"project") || Path::new(dir).join(".mcp.json").exists())
+            .into_iter()
+            .collect();
+        mcp_snapshots::record_snapshot(&format!("claude mcp {}", args.join(" ")), &projects);
+    }
+
     let claude_path = find_claude_binary(app_handle)?;
     let mut cmd = create_command_with_env(&claude_path);
     cmd.arg("mcp");
@@ -116,6 +174,11 @@ fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<&str>) -> Result
     }
 }
 
+/// Servers shared through a project's .mcp.json can't use opcode's secrets:
+/// they are resolved by a launcher and keychain that only exist on this machine
+const SHARED_SECRETS_ERROR: &str =
+    "Servers added to the project's .mcp.json can't use secrets or variables; add it to local or user scope";
+
 /// Adds a new MCP server
 #[tauri::command]
 pub async fn mcp_add(
@@ -124,12 +187,45 @@ pub async fn mcp_add(
     transport: String,
     command: Option<String>,
     args: Vec<String>,
-    env: HashMap<String, String>,
+    mut env: HashMap<String, String>,
     url: Option<String>,
     scope: String,
+    secrets: Option<BTreeMap<String, String>>,
 ) -> Result<AddServerResult, String> {
     info!("Adding MCP server: {} with transport: {}", name, transport);
 
+    let has_secrets = secrets.as_ref().is_some_and(|secrets| !secrets.is_empty());
+    if scope == "project"
+        && (has_secrets || args.iter().chain(env.values()).any(|v| has_templates(v)))
+    {
+        return Err(SHARED_SECRETS_ERROR.to_string());
+    }
+
+    // Secret variables go to the keychain once the server is about to be
+    // added; the config only references them
+    let secrets = secrets.filter(|secrets| !secrets.is_empty());
+    if let Some(secrets) = &secrets {
+        if transport != "stdio" {
+            return Err("Secrets are only supported for stdio servers".to_string());
+        }
+        env.extend(server_secret_references(&name, secrets));
+    }
+
+    // Servers referencing secrets or variables launch through opcode, which resolves them
+    let (command, args) = match command {
+        Some(cmd)
+            if transport == "stdio"
+                && args.iter().chain(env.values()).any(|v| has_templates(v)) =>
+        {
+            let proxy = mcp_proxy::proxy_executable()?;
+            (
+                Some(proxy.to_string_lossy().into_owned()),
+                mcp_proxy::proxy_args(&name, &cmd, &args),
+            )
+        }
+        command => (command, args),
+    };
+
     // Prepare owned strings for environment variables
     let env_args: Vec<String> = env
         .iter()
@@ -188,6 +284,10 @@ pub async fn mcp_add(
         }
     }
 
+    let db = app.state::<AgentDb>();
+    let stored = secrets
+        .map(|secrets| store_server_secrets(&db, &name, &secrets))
+        .transpose()?;
     match execute_claude_mcp_command(&app, cmd_args) {
         Ok(output) => {
             info!("Successfully added MCP server: {}", name);
@@ -199,6 +299,9 @@ pub async fn mcp_add(
         }
         Err(e) => {
             error!("Failed to add MCP server: {}", e);
+            if let Some(stored) = stored {
+                stored.roll_back(&db);
+            }
             Ok(AddServerResult {
                 success: false,
                 message: e.to_string(),
@@ -299,6 +402,7 @@ pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
                                 running: false,
                                 error: None,
                                 last_checked: None,
+                                ..Default::default()
                             },
                         });
                         info!("Added server: {:?}", name);
@@ -389,6 +493,7 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
                     running: false,
                     error: None,
                     last_checked: None,
+                    ..Default::default()
                 },
             })
         }
@@ -429,6 +534,34 @@ pub async fn mcp_add_json(
         name, scope
     );
 
+    // Servers referencing secrets or variables launch through opcode, which resolves them
+    let mut secrets = None;
+    let json_config = match serde_json::from_str::<serde_json::Value>(&json_config) {
+        Ok(mut config) => {
+            let has_secrets = config
+                .get("secrets")
+                .and_then(|secrets| secrets.as_object())
+                .is_some_and(|secrets| !secrets.is_empty());
+            if scope == "project" && (has_secrets || config_has_templates(&config)) {
+                return Err(SHARED_SECRETS_ERROR.to_string());
+            }
+            secrets = take_config_secrets(&name, &mut config)?;
+            let wrapped = if config_has_templates(&config) {
+                mcp_proxy::wrap(&name, &config, &mcp_proxy::proxy_executable()?)
+            } else {
+                None
+            };
+            match wrapped {
+                Some(wrapped) => serde_json::to_string(&wrapped).map_err(|e| e.to_string())?,
+                None if secrets.is_some() => {
+                    serde_json::to_string(&config).map_err(|e| e.to_string())?
+                }
+                None => json_config,
+            }
+        }
+        Err(_) => json_config,
+    };
+
     // Build command args
     let mut cmd_args = vec!["add-json", &name, &json_config];
 
@@ -437,6 +570,11 @@ pub async fn mcp_add_json(
     cmd_args.push(scope_flag);
     cmd_args.push(&scope);
 
+    let db = app.state::<AgentDb>();
+    let stored = secrets
+        .filter(|secrets| !secrets.is_empty())
+        .map(|secrets| store_server_secrets(&db, &name, &secrets))
+        .transpose()?;
     match execute_claude_mcp_command(&app, cmd_args) {
         Ok(output) => {
             info!("Successfully added MCP server from JSON: {}", name);
@@ -448,6 +586,9 @@ pub async fn mcp_add_json(
         }
         Err(e) => {
             error!("Failed to add MCP server from JSON: {}", e);
+            if let Some(stored) = stored {
+                stored.roll_back(&db);
+            }
             Ok(AddServerResult {
                 success: false,
                 message: e.to_string(),
@@ -457,156 +598,522 @@ pub async fn mcp_add_json(
     }
 }
 
-/// Imports MCP servers from Claude Desktop
-#[tauri::command]
-pub async fn mcp_add_from_claude_desktop(
-    app: AppHandle,
-    scope: String,
-) -> Result<ImportResult, String> {
-    info!(
-        "Importing MCP servers from Claude Desktop with scope: {}",
-        scope
-    );
-
-    // Get Claude Desktop config path based on platform
-    let config_path = if cfg!(target_os = "macos") {
-        dirs::home_dir()
+/// Path of Claude Desktop's config file on this platform
+fn claude_desktop_config_path() -> Result<PathBuf, String> {
+    if cfg!(target_os = "macos") {
+        Ok(dirs::home_dir()
             .ok_or_else(|| "Could not find home directory".to_string())?
             .join("Library")
             .join("Application Support")
             .join("Claude")
-            .join("claude_desktop_config.json")
-    } else if cfg!(target_os = "linux") {
-        // For WSL/Linux, check common locations
-        dirs::config_dir()
+            .join("claude_desktop_config.json"))
+    } else {
+        // %APPDATA% on Windows, ~/.config on Linux/WSL
+        Ok(dirs::config_dir()
             .ok_or_else(|| "Could not find config directory".to_string())?
             .join("Claude")
-            .join("claude_desktop_config.json")
-    } else {
-        return Err(
-            "Import from Claude Desktop is only supported on macOS and Linux/WSL".to_string(),
-        );
-    };
-
-    // Check if config file exists
-    if !config_path.exists() {
-        return Err(
-            "Claude Desktop configuration not found. Make sure Claude Desktop is installed."
-                .to_string(),
-        );
+            .join("claude_desktop_config.json"))
     }
+}
 
-    // Read and parse the config file
-    let config_content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read Claude Desktop config: {}", e))?;
+/// Path of Claude Code's own config file, where `claude mcp add` stores servers
+pub fn claude_config_path() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
+        .ok_or_else(|| "Could not find home directory".to_string())?
+        .join(".claude.json"))
+}
+
+/// Reads a JSON config file, treating a missing file as an empty object
+pub fn read_json_config(path: &Path) -> Result<serde_json::Value, String> {
+    if !path.exists() {
+        return Ok(serde_json::json!({}));
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
 
-    let config: serde_json::Value = serde_json::from_str(&config_content)
-        .map_err(|e| format!("Failed to parse Claude Desktop config: {}", e))?;
+/// Write a config file through a temporary file renamed over it, so Claude
+/// never reads it half-written; the file keeps its permissions
+pub fn write_json_config(path: &Path, config: &serde_json::Value) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("{} has no parent directory", path.display()))?;
+    let mut temp = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    if let Ok(metadata) = fs::metadata(path) {
+        let _ = temp.as_file().set_permissions(metadata.permissions());
+    }
+    std::io::Write::write_all(&mut temp, content.as_bytes())
+        .and_then(|_| temp.as_file().sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    temp.persist(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e.error))?;
+    Ok(())
+}
 
-    // Extract MCP servers
-    let mcp_servers = config
+/// The `mcpServers` object of a parsed config file
+fn servers_in(config: &serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    config
         .get("mcpServers")
         .and_then(|v| v.as_object())
-        .ok_or_else(|| "No MCP servers found in Claude Desktop config".to_string())?;
+        .cloned()
+        .unwrap_or_default()
+}
 
-    let mut imported_count = 0;
-    let mut failed_count = 0;
-    let mut server_results = Vec::new();
+/// Servers stored by `claude mcp add`: user scope, plus a project's local scope
+fn claude_code_servers(
+    project_path: Option<&str>,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let claude_config = read_json_config(&claude_config_path()?)?;
+    let mut servers = servers_in(&claude_config);
+    if let Some(project_path) = project_path {
+        servers.extend(servers_in(&claude_config["projects"][project_path]));
+    }
+    Ok(servers)
+}
 
-    // Import each server using add-json
-    for (name, server_config) in mcp_servers {
-        info!("Importing server: {}", name);
+/// Server definitions configured for Claude Code. With a project, its
+/// `.mcp.json` servers override user scope and its local scope overrides both,
+/// as in Claude.
+pub fn configured_servers(
+    project_path: Option<&str>,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    resolve_servers(project_path, |_| true)
+}
 
-        // Convert Claude Desktop format to add-json format
-        let mut json_config = serde_json::Map::new();
+/// The servers Claude starts in a project: like [`configured_servers`], but
+/// `.mcp.json` servers only count once the user has approved them
+pub fn active_servers(
+    project_path: &str,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let claude_config = read_json_config(&claude_config_path()?)?;
+    let project = &claude_config["projects"][project_path];
+    let listed = |key: &str| -> Vec<String> {
+        project[key]
+            .as_array()
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|name| name.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let enable_all = project["enableAllProjectMcpServers"].as_bool() == Some(true);
+    let (enabled, disabled) = (
+        listed("enabledMcpjsonServers"),
+        listed("disabledMcpjsonServers"),
+    );
 
-        // All Claude Desktop servers are stdio type
-        json_config.insert(
-            "type".to_string(),
-            serde_json::Value::String("stdio".to_string()),
+    resolve_servers(Some(project_path), |name| {
+        !disabled.iter().any(|n| n == name) && (enable_all || enabled.iter().any(|n| n == name))
+    })
+}
+
+fn resolve_servers(
+    project_path: Option<&str>,
+    include_project_server: impl Fn(&str) -> bool,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let claude_config = read_json_config(&claude_config_path()?)?;
+    let mut servers = servers_in(&claude_config);
+    if let Some(project_path) = project_path {
+        let project_servers = servers_in(&read_json_config(
+            &PathBuf::from(project_path).join(".mcp.json"),
+        )?);
+        servers.extend(
+            project_servers
+                .into_iter()
+                .filter(|(name, _)| include_project_server(name)),
         );
+        servers.extend(servers_in(&claude_config["projects"][project_path]));
+    }
+    Ok(servers)
+}
 
-        // Add command
-        if let Some(command) = server_config.get("command").and_then(|v| v.as_str()) {
-            json_config.insert(
-                "command".to_string(),
-                serde_json::Value::String(command.to_string()),
-            );
-        } else {
-            failed_count += 1;
-            server_results.push(ImportServerResult {
-                name: name.clone(),
-                success: false,
-                error: Some("Missing command field".to_string()),
-            });
+/// Rewrites one server definition in whichever config defines it, looking at
+/// the project's local scope, then its `.mcp.json`, then user scope. `update`
+/// is given the file and the current definition and returns the new definition,
+/// or None to leave it unchanged. Returns the file that was written, if any.
+pub fn update_server_config(
+    name: &str,
+    project_path: Option<&str>,
+    update: impl FnOnce(&Path, &serde_json::Value) -> Option<serde_json::Value>,
+) -> Result<Option<PathBuf>, String> {
+    let claude_config = claude_config_path()?;
+    let mut scopes: Vec<(PathBuf, Option<&str>)> = Vec::new();
+    if let Some(project_path) = project_path {
+        scopes.push((claude_config.clone(), Some(project_path)));
+        scopes.push((PathBuf::from(project_path).join(".mcp.json"), None));
+    }
+    scopes.push((claude_config, None));
+
+    for (path, project) in scopes {
+        let mut config = read_json_config(&path)?;
+        let scope = match project {
+            Some(project) => config.get_mut("projects").and_then(|p| p.get_mut(project)),
+            None => Some(&mut config),
+        };
+        let Some(server) = scope
+            .and_then(|scope| scope.get_mut("mcpServers"))
+            .and_then(|servers| servers.get_mut(name))
+        else {
             continue;
+        };
+
+        let Some(updated) = update(&path, server) else {
+            return Ok(None);
+        };
+        mcp_snapshots::record_snapshot(&format!("update of {}", name), project_path.as_slice());
+        *server = updated;
+        write_json_config(&path, &config)?;
+        return Ok(Some(path));
+    }
+    Err(format!("MCP server '{}' is not configured", name))
+}
+
+/// What makes two server definitions the same server, whatever they're called
+pub fn server_identity(config: &serde_json::Value) -> Option<String> {
+    if let Some(original) = crate::mcp_proxy::unwrap(config) {
+        return server_identity(&original);
+    }
+    if let Some(url) = config.get("url").and_then(|v| v.as_str()) {
+        return Some(format!("url:{}", url.trim_end_matches('/')));
+    }
+    let command = config.get("command").and_then(|v| v.as_str())?;
+    let args: Vec<&str> = config
+        .get("args")
+        .and_then(|v| v.as_array())
+        .map(|args| args.iter().filter_map(|arg| arg.as_str()).collect())
+        .unwrap_or_default();
+    Some(format!("cmd:{}\0{}", command, args.join("\0")))
+}
+
+/// A server definition fit for a shared `.mcp.json`: launched without the
+/// proxy, with every environment variable, and every secret or variable in
+/// its arguments and headers, left for Claude to expand from the environment
+/// as `${NAME}`
+pub fn shared_server_config(config: &serde_json::Value) -> serde_json::Value {
+    let mut config = mcp_proxy::unwrap(config).unwrap_or_else(|| config.clone());
+    let reference = |value: &serde_json::Value| -> serde_json::Value {
+        match value.as_str() {
+            Some(value) => env_references(value).into(),
+            None => value.clone(),
+        }
+    };
+    if let Some(env) = config.get_mut("env").and_then(|env| env.as_object_mut()) {
+        for (key, value) in env.iter_mut() {
+            *value = format!("${{{}}}", key).into();
+        }
+    }
+    if let Some(args) = config.get_mut("args").and_then(|args| args.as_array_mut()) {
+        for arg in args.iter_mut() {
+            *arg = reference(arg);
         }
+    }
+    if let Some(headers) = config.get_mut("headers").and_then(|h| h.as_object_mut()) {
+        for value in headers.values_mut() {
+            *value = reference(value);
+        }
+    }
+    config
+}
 
-        // Add args if present
-        if let Some(args) = server_config.get("args").and_then(|v| v.as_array()) {
-            json_config.insert("args".to_string(), args.clone().into());
-        } else {
-            json_config.insert("args".to_string(), serde_json::Value::Array(vec![]));
+/// Normalizes a server definition from another tool to the add-json format
+pub fn to_add_json_config(config: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let mut json_config = serde_json::Map::new();
+    if let Some(url) = config.get("url").and_then(|v| v.as_str()) {
+        let transport = config
+            .get("type")
+            .and_then(|v| v.as_str())
+            .filter(|t| *t != "stdio")
+            .unwrap_or("sse");
+        json_config.insert("type".to_string(), transport.into());
+        json_config.insert("url".to_string(), url.into());
+        if let Some(headers) = config.get("headers").filter(|v| v.is_object()) {
+            json_config.insert("headers".to_string(), headers.clone());
         }
+    } else {
+        let command = config
+            .get("command")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| "Missing command field".to_string())?;
+        json_config.insert("type".to_string(), "stdio".into());
+        json_config.insert("command".to_string(), command.into());
+        json_config.insert(
+            "args".to_string(),
+            config
+                .get("args")
+                .filter(|v| v.is_array())
+                .cloned()
+                .unwrap_or_else(|| serde_json::json!([])),
+        );
+        json_config.insert(
+            "env".to_string(),
+            config
+                .get("env")
+                .filter(|v| v.is_object())
+                .cloned()
+                .unwrap_or_else(|| serde_json::json!({})),
+        );
+    }
+    Ok(serde_json::Value::Object(json_config))
+}
 
-        // Add env if present
-        if let Some(env) = server_config.get("env").and_then(|v| v.as_object()) {
-            json_config.insert("env".to_string(), env.clone().into());
+/// Adds servers found in another tool's config, skipping any that match an
+/// existing server by name or by command or URL
+async fn import_servers(
+    app: &AppHandle,
+    servers: serde_json::Map<String, serde_json::Value>,
+    existing: serde_json::Map<String, serde_json::Value>,
+    scope: &str,
+) -> Result<ImportResult, String> {
+    let mut known: HashMap<String, String> = existing
+        .iter()
+        .filter_map(|(name, config)| Some((server_identity(config)?, name.clone())))
+        .collect();
+
+    let mut result = ImportResult {
+        imported_count: 0,
+        failed_count: 0,
+        skipped_count: 0,
+        servers: Vec::new(),
+    };
+
+    for (name, server_config) in servers {
+        info!("Importing server: {}", name);
+
+        let identity = server_identity(&server_config);
+        let duplicate_of = if existing.contains_key(&name) {
+            Some(name.clone())
         } else {
-            json_config.insert(
-                "env".to_string(),
-                serde_json::Value::Object(serde_json::Map::new()),
+            identity.as_ref().and_then(|id| known.get(id).cloned())
+        };
+        if let Some(existing_name) = duplicate_of {
+            info!(
+                "Skipping server {}: already configured as {}",
+                name, existing_name
             );
+            result.skipped_count += 1;
+            result.servers.push(ImportServerResult {
+                name,
+                success: false,
+                skipped: true,
+                error: Some(format!("Already configured as '{}'", existing_name)),
+            });
+            continue;
         }
 
-        // Convert to JSON string
-        let json_str = serde_json::to_string(&json_config)
-            .map_err(|e| format!("Failed to serialize config for {}: {}", name, e))?;
-
-        // Call add-json command
-        match mcp_add_json(app.clone(), name.clone(), json_str, scope.clone()).await {
-            Ok(result) => {
-                if result.success {
-                    imported_count += 1;
-                    server_results.push(ImportServerResult {
-                        name: name.clone(),
-                        success: true,
-                        error: None,
-                    });
-                    info!("Successfully imported server: {}", name);
-                } else {
-                    failed_count += 1;
-                    let error_msg = result.message.clone();
-                    server_results.push(ImportServerResult {
-                        name: name.clone(),
-                        success: false,
-                        error: Some(result.message),
-                    });
-                    error!("Failed to import server {}: {}", name, error_msg);
-                }
-            }
+        let json_str = match to_add_json_config(&server_config)
+            .and_then(|config| serde_json::to_string(&config).map_err(|e| e.to_string()))
+        {
+            Ok(json_str) => json_str,
             Err(e) => {
-                failed_count += 1;
-                let error_msg = e.clone();
-                server_results.push(ImportServerResult {
-                    name: name.clone(),
+                result.failed_count += 1;
+                result.servers.push(ImportServerResult {
+                    name,
                     success: false,
+                    skipped: false,
                     error: Some(e),
                 });
-                error!("Error importing server {}: {}", name, error_msg);
+                continue;
             }
+        };
+
+        let add_result =
+            mcp_add_json(app.clone(), name.clone(), json_str, scope.to_string()).await?;
+        if add_result.success {
+            info!("Successfully imported server: {}", name);
+            if let Some(identity) = identity {
+                known.insert(identity, name.clone());
+            }
+            result.imported_count += 1;
+            result.servers.push(ImportServerResult {
+                name,
+                success: true,
+                skipped: false,
+                error: None,
+            });
+        } else {
+            error!("Failed to import server {}: {}", name, add_result.message);
+            result.failed_count += 1;
+            result.servers.push(ImportServerResult {
+                name,
+                success: false,
+                skipped: false,
+                error: Some(add_result.message),
+            });
         }
     }
 
     info!(
-        "Import complete: {} imported, {} failed",
-        imported_count, failed_count
+        "Import complete: {} imported, {} skipped, {} failed",
+        result.imported_count, result.skipped_count, result.failed_count
     );
+    Ok(result)
+}
 
-    Ok(ImportResult {
-        imported_count,
-        failed_count,
-        servers: server_results,
+/// Imports MCP servers from Claude Desktop
+#[tauri::command]
+pub async fn mcp_add_from_claude_desktop(
+    app: AppHandle,
+    scope: String,
+    project_path: Option<String>,
+) -> Result<ImportResult, String> {
+    info!(
+        "Importing MCP servers from Claude Desktop with scope: {}",
+        scope
+    );
+
+    let config_path = claude_desktop_config_path()?;
+
+    // Check if config file exists
+    if !config_path.exists() {
+        return Err(
+            "Claude Desktop configuration not found. Make sure Claude Desktop is installed."
+                .to_string(),
+        );
+    }
+
+    let servers = servers_in(&read_json_config(&config_path)?);
+    if servers.is_empty() {
+        return Err("No MCP servers found in Claude Desktop config".to_string());
+    }
+
+    let existing = configured_servers(project_path.as_deref())?;
+    import_servers(&app, servers, existing, &scope).await
+}
+
+/// Imports the servers of a project's `.mcp.json` into another scope
+#[tauri::command]
+pub async fn mcp_import_project_config(
+    app: AppHandle,
+    project_path: String,
+    scope: String,
+) -> Result<ImportResult, String> {
+    info!(
+        "Importing MCP servers from .mcp.json in {} with scope: {}",
+        project_path, scope
+    );
+
+    let mcp_json_path = PathBuf::from(&project_path).join(".mcp.json");
+    if !mcp_json_path.exists() {
+        return Err(format!("No .mcp.json found in {}", project_path));
+    }
+    let servers = servers_in(&read_json_config(&mcp_json_path)?);
+
+    // Compared without the .mcp.json itself, which would make everything a duplicate
+    let existing = claude_code_servers(Some(&project_path))?;
+    import_servers(&app, servers, existing, &scope).await
+}
+
+/// Exports configured servers to Claude Desktop's config or a project's
+/// `.mcp.json`, merging with what the file already holds. `names` limits the
+/// export to some servers.
+#[tauri::command]
+pub async fn mcp_export_servers(
+    target: String,
+    project_path: Option<String>,
+    names: Option<Vec<String>>,
+) -> Result<ExportResult, String> {
+    info!("Exporting MCP servers to {}", target);
+
+    let (path, servers) = match target.as_str() {
+        "claude_desktop" => (
+            claude_desktop_config_path()?,
+            configured_servers(project_path.as_deref())?,
+        ),
+        "project" => {
+            let project_path = project_path
+                .as_deref()
+                .ok_or("Project path required to export to .mcp.json")?;
+            (
+                PathBuf::from(project_path).join(".mcp.json"),
+                claude_code_servers(Some(project_path))?,
+            )
+        }
+        _ => return Err(format!("Unknown export target: {}", target)),
+    };
+
+    let mut config = read_json_config(&path)?;
+    if !config.is_object() {
+        return Err(format!("{} is not a JSON object", path.display()));
+    }
+    let mut target_servers = servers_in(&config);
+    let target_identities: HashMap<String, String> = target_servers
+        .iter()
+        .filter_map(|(name, config)| Some((server_identity(config)?, name.clone())))
+        .collect();
+
+    let mut exported = Vec::new();
+    let mut skipped = Vec::new();
+    let skip = |name: &str, reason: String| ImportServerResult {
+        name: name.to_string(),
+        success: false,
+        skipped: true,
+        error: Some(reason),
+    };
+    for (name, server_config) in servers {
+        if names.as_ref().is_some_and(|names| !names.contains(&name)) {
+            continue;
+        }
+        // .mcp.json is shared, so it gets the original command and no secrets
+        let server_config = match target.as_str() {
+            "project" => shared_server_config(&server_config),
+            _ => server_config,
+        };
+        let mut export_config = match to_add_json_config(&server_config) {
+            Ok(config) => config,
+            Err(e) => {
+                skipped.push(skip(&name, e));
+                continue;
+            }
+        };
+        if let Some(other) = server_identity(&server_config)
+            .and_then(|identity| target_identities.get(&identity))
+            .filter(|other| **other != name)
+        {
+            skipped.push(skip(&name, format!("Already present as '{}'", other)));
+            continue;
+        }
+        if target == "claude_desktop" {
+            // Claude Desktop only launches local stdio servers
+            if export_config.get("url").is_some() {
+                skipped.push(skip(
+                    &name,
+                    "Claude Desktop only supports stdio servers".to_string(),
+                ));
+                continue;
+            }
+            if let Some(fields) = export_config.as_object_mut() {
+                fields.remove("type");
+            }
+        }
+        target_servers.insert(name.clone(), export_config);
+        exported.push(name);
+    }
+
+    if let ("project", Some(project_path)) = (target.as_str(), project_path.as_deref()) {
+        mcp_snapshots::record_snapshot("export to .mcp.json", &[project_path]);
+    }
+    config["mcpServers"] = serde_json::Value::Object(target_servers);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    write_json_config(&path, &config)?;
+
+    info!(
+        "Exported {} MCP servers to {}",
+        exported.len(),
+        path.display()
+    );
+    Ok(ExportResult {
+        path: path.to_string_lossy().to_string(),
+        exported,
+        skipped,
     })
 }
 
@@ -673,9 +1180,14 @@ pub async fn mcp_reset_project_choices(app: AppHandle) -> Result<String, String>
 pub async fn mcp_get_server_status() -> Result<HashMap<String, ServerStatus>, String> {
     info!("Getting MCP server status");
 
-    // TODO: Implement actual status checking
-    // For now, return empty status
-    Ok(HashMap::new())
+    // Only servers launched through the proxy are supervised and report a status
+    let Some(root) = mcp_proxy::logs_root() else {
+        return Ok(HashMap::new());
+    };
+    Ok(mcp_supervisor::read_statuses(&root)
+        .into_iter()
+        .map(|status| (status.server.clone(), status.into()))
+        .collect())
 }
 
 /// Reads .mcp.json from the current project
@@ -719,8 +1231,86 @@ pub async fn mcp_save_project_config(
     let json_content = serde_json::to_string_pretty(&config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
+    mcp_snapshots::record_snapshot("save of .mcp.json", &[&project_path]);
     fs::write(&mcp_json_path, json_content)
         .map_err(|e| format!("Failed to write .mcp.json: {}", e))?;
 
     Ok("Project MCP configuration saved".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_identity_and_normalization() {
+        let desktop = serde_json::json!({
+            "command": "npx",
+            "args": ["-y", "@modelcontextprotocol/server-github"],
+            "env": {"GITHUB_TOKEN": "x"},
+        });
+        let renamed = serde_json::json!({
+            "type": "stdio",
+            "command": "npx",
+            "args": ["-y", "@modelcontextprotocol/server-github"],
+        });
+        assert_eq!(server_identity(&desktop), server_identity(&renamed));
+        assert_ne!(
+            server_identity(&desktop),
+            server_identity(&serde_json::json!({"command": "npx", "args": ["-y", "other"]}))
+        );
+        assert_eq!(
+            server_identity(
+                &serde_json::json!({"type": "http", "url": "https://mcp.example.com/"})
+            ),
+            server_identity(&serde_json::json!({"url": "https://mcp.example.com"}))
+        );
+        assert_eq!(server_identity(&serde_json::json!({"args": []})), None);
+
+        assert_eq!(
+            to_add_json_config(&desktop).unwrap(),
+            serde_json::json!({
+                "type": "stdio",
+                "command": "npx",
+                "args": ["-y", "@modelcontextprotocol/server-github"],
+                "env": {"GITHUB_TOKEN": "x"},
+            })
+        );
+        assert_eq!(
+            to_add_json_config(&serde_json::json!({"url": "https://mcp.example.com/sse"})).unwrap(),
+            serde_json::json!({"type": "sse", "url": "https://mcp.example.com/sse"})
+        );
+        assert!(to_add_json_config(&serde_json::json!({"args": []})).is_err());
+    }
+
+    #[test]
+    fn test_shared_server_config() {
+        let proxied = mcp_proxy::wrap(
+            "github",
+            &serde_json::json!({
+                "command": "npx",
+                "args": ["-y", "server-github", "--org=${var:ORG}"],
+                "env": {"GITHUB_TOKEN": "${secret:GITHUB_GITHUB_TOKEN}", "DEBUG": "1"},
+            }),
+            Path::new("/opt/opcode"),
+        )
+        .unwrap();
+        assert_eq!(
+            shared_server_config(&proxied),
+            serde_json::json!({
+                "command": "npx",
+                "args": ["-y", "server-github", "--org=${ORG}"],
+                "env": {"GITHUB_TOKEN": "${GITHUB_TOKEN}", "DEBUG": "${DEBUG}"},
+            })
+        );
+
+        let remote = serde_json::json!({
+            "url": "https://mcp.example.com",
+            "headers": {"Authorization": "Bearer ${secret:API_KEY}"},
+        });
+        assert_eq!(
+            shared_server_config(&remote)["headers"]["Authorization"],
+            "Bearer ${API_KEY}"
+        );
+    }
+}
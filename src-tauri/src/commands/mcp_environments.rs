@@ -0,0 +1,291 @@
+use log::{info, warn};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::shell_environment::{
+    windows_to_wsl_path, wsl_to_windows_path, ShellConfig, ShellEnvironment,
+};
+
+/// app_settings key holding the execution environment of each MCP server
+const SETTINGS_KEY: &str = "mcp_server_environments";
+
+const DEFAULT_GIT_BASH: &str = r"C:\Program Files\Git\bin\bash.exe";
+
+/// Where a stdio MCP server runs, when that differs from where Claude runs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpServerEnvironment {
+    pub environment: ShellEnvironment,
+    /// Distribution for servers run in WSL; the default distribution when unset
+    pub wsl_distro: Option<String>,
+}
+
+/// Execution environments keyed by server name; servers without one run
+/// wherever Claude runs
+pub fn load_server_environments(conn: &Connection) -> BTreeMap<String, McpServerEnvironment> {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+fn looks_like_windows_path(arg: &str) -> bool {
+    let bytes = arg.as_bytes();
+    arg.starts_with(r"\\")
+        || (bytes.len() >= 3
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && (bytes[2] == b'\\' || bytes[2] == b'/'))
+}
+
+/// Translate an argument that is a path, or a `--flag=path`, with `translate`
+fn translate_arg(
+    arg: &str,
+    is_path: impl Fn(&str) -> bool,
+    translate: fn(&str) -> String,
+) -> String {
+    if is_path(arg) {
+        return translate(arg);
+    }
+    match arg.split_once('=') {
+        Some((flag, value)) if flag.starts_with('-') && is_path(value) => {
+            format!("{}={}", flag, translate(value))
+        }
+        _ => arg.to_string(),
+    }
+}
+
+/// Quote words for a bash command line
+fn bash_script(words: &[String]) -> String {
+    let quoted: Vec<String> = words
+        .iter()
+        .map(|word| format!("'{}'", word.replace('\'', r"'\''")))
+        .collect();
+    format!("exec {}", quoted.join(" "))
+}
+
+/// A stdio server definition rewritten to run in `target` when Claude runs in
+/// the environment `shell` selects. None when nothing needs wrapping.
+pub fn wrap_for_environment(
+    config: &JsonValue,
+    target: &McpServerEnvironment,
+    shell: &ShellConfig,
+) -> Result<Option<JsonValue>, String> {
+    let Some(command) = config.get("command").and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+    let mut words = vec![command.to_string()];
+    words.extend(
+        config
+            .get("args")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|arg| arg.as_str().map(str::to_string)),
+    );
+    let claude_in_wsl = shell.environment == ShellEnvironment::Wsl;
+
+    // Environment variables only cross the WSL boundary when listed in WSLENV
+    let (program, args, wslenv_flag) = match (&target.environment, claude_in_wsl) {
+        (ShellEnvironment::Native, false) | (ShellEnvironment::Wsl, true) => return Ok(None),
+        (ShellEnvironment::Wsl, false) => {
+            let words: Vec<String> = words
+                .iter()
+                .map(|word| translate_arg(word, looks_like_windows_path, windows_to_wsl_path))
+                .collect();
+            let mut args = Vec::new();
+            if let Some(distro) = &target.wsl_distro {
+                args.extend(["-d".to_string(), distro.clone()]);
+            }
+            args.extend(["--", "bash", "-lc"].map(str::to_string));
+            args.push(bash_script(&words));
+            ("wsl.exe".to_string(), args, Some("/u"))
+        }
+        (ShellEnvironment::Native, true) => {
+            let mut args = ["/d", "/c"].map(str::to_string).to_vec();
+            args.extend(words.iter().map(|word| {
+                translate_arg(word, |arg| arg.starts_with("/mnt/"), wsl_to_windows_path)
+            }));
+            ("cmd.exe".to_string(), args, Some("/w"))
+        }
+        (ShellEnvironment::GitBash, false) => {
+            let bash = shell.git_bash_path.as_deref().unwrap_or(DEFAULT_GIT_BASH);
+            (
+                bash.to_string(),
+                vec!["-lc".to_string(), bash_script(&words)],
+                None,
+            )
+        }
+        (ShellEnvironment::GitBash, true) => {
+            return Err("Git Bash servers can't be started by Claude running in WSL".to_string())
+        }
+    };
+
+    let mut wrapped = config.clone();
+    wrapped["command"] = program.into();
+    wrapped["args"] = args.into();
+    let env = config.get("env").and_then(|v| v.as_object());
+    if let (Some(flag), Some(env)) = (wslenv_flag, env) {
+        let mut shared: Vec<String> = env
+            .get("WSLENV")
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(|v| vec![v.to_string()])
+            .unwrap_or_default();
+        shared.extend(
+            env.keys()
+                .filter(|name| *name != "WSLENV")
+                .map(|name| format!("{}{}", name, flag)),
+        );
+        if !shared.is_empty() {
+            wrapped["env"]["WSLENV"] = shared.join(":").into();
+        }
+    }
+    Ok(Some(wrapped))
+}
+
+/// Wrap every server with an execution environment; returns how many changed.
+/// A server that can't be wrapped is left as it is.
+pub fn apply_server_environments(
+    servers: &mut serde_json::Map<String, JsonValue>,
+    environments: &BTreeMap<String, McpServerEnvironment>,
+    shell: &ShellConfig,
+) -> usize {
+    // The environments only differ on Windows
+    if !cfg!(windows) {
+        return 0;
+    }
+    let mut wrapped = 0;
+    for (name, target) in environments {
+        let Some(config) = servers.get_mut(name) else {
+            continue;
+        };
+        match wrap_for_environment(config, target, shell) {
+            Ok(Some(rewritten)) => {
+                *config = rewritten;
+                wrapped += 1;
+            }
+            Ok(None) => {}
+            Err(e) => warn!("MCP server {} runs unwrapped: {}", name, e),
+        }
+    }
+    wrapped
+}
+
+/// Execution environments chosen for MCP servers
+#[tauri::command]
+pub async fn get_mcp_server_environments(
+    db: State<'_, AgentDb>,
+) -> Result<BTreeMap<String, McpServerEnvironment>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_server_environments(&conn))
+}
+
+/// Choose where a stdio MCP server runs; None runs it wherever Claude runs
+#[tauri::command]
+pub async fn set_mcp_server_environment(
+    db: State<'_, AgentDb>,
+    server: String,
+    environment: Option<McpServerEnvironment>,
+) -> Result<(), String> {
+    if !cfg!(windows)
+        && environment
+            .as_ref()
+            .is_some_and(|env| env.environment != ShellEnvironment::Native)
+    {
+        return Err("WSL and Git Bash are only available on Windows".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut environments = load_server_environments(&conn);
+    match &environment {
+        Some(environment) => {
+            environments.insert(server.clone(), environment.clone());
+        }
+        None => {
+            environments.remove(&server);
+        }
+    }
+    save_json_setting(&conn, SETTINGS_KEY, &environments)?;
+
+    info!(
+        "MCP server {} runs in {}",
+        server,
+        environment
+            .map(|env| env.environment.to_string())
+            .unwrap_or_else(|| "Claude's environment".to_string())
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_for_environment() {
+        let config = serde_json::json!({
+            "command": "npx",
+            "args": ["-y", "server-filesystem", "/mnt/c/Users/me/notes", "--root=/mnt/d/data"],
+            "env": {"API_KEY": "x"},
+        });
+        let in_wsl = ShellConfig {
+            environment: ShellEnvironment::Wsl,
+            ..Default::default()
+        };
+        let native = McpServerEnvironment {
+            environment: ShellEnvironment::Native,
+            wsl_distro: None,
+        };
+        let wrapped = wrap_for_environment(&config, &native, &in_wsl)
+            .unwrap()
+            .unwrap();
+        assert_eq!(wrapped["command"], "cmd.exe");
+        assert_eq!(
+            wrapped["args"],
+            serde_json::json!([
+                "/d",
+                "/c",
+                "npx",
+                "-y",
+                "server-filesystem",
+                r"C:\Users\me\notes",
+                r"--root=D:\data"
+            ])
+        );
+        assert_eq!(wrapped["env"]["WSLENV"], "API_KEY/w");
+        assert_eq!(wrapped["env"]["API_KEY"], "x");
+
+        let wsl = McpServerEnvironment {
+            environment: ShellEnvironment::Wsl,
+            wsl_distro: Some("Ubuntu".to_string()),
+        };
+        let script = serde_json::json!({"command": "it's", "args": ["a b"]});
+        let wrapped = wrap_for_environment(&script, &wsl, &ShellConfig::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(wrapped["command"], "wsl.exe");
+        assert_eq!(
+            wrapped["args"],
+            serde_json::json!(["-d", "Ubuntu", "--", "bash", "-lc", r"exec 'it'\''s' 'a b'"])
+        );
+        assert!(wrapped.get("env").is_none());
+
+        assert_eq!(wrap_for_environment(&config, &wsl, &in_wsl).unwrap(), None);
+        assert_eq!(
+            wrap_for_environment(&config, &native, &ShellConfig::default()).unwrap(),
+            None
+        );
+        assert_eq!(
+            wrap_for_environment(&serde_json::json!({"url": "https://x"}), &native, &in_wsl)
+                .unwrap(),
+            None
+        );
+        let git_bash = McpServerEnvironment {
+            environment: ShellEnvironment::GitBash,
+            wsl_distro: None,
+        };
+        assert!(wrap_for_environment(&config, &git_bash, &in_wsl).is_err());
+    }
+}
@@ -0,0 +1,575 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+
+use crate::commands::mcp::configured_servers;
+
+/// MCP protocol revision the inspector speaks
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Time allowed for the whole inspection, including server startup (npx/uvx
+/// may download the server first)
+const INSPECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Pages fetched per list before giving up on a server that never stops paginating
+const MAX_PAGES: usize = 50;
+
+/// Bytes of server stderr kept for error messages
+const STDERR_LIMIT: usize = 4000;
+
+/// What a server advertises after the handshake
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpCapabilities {
+    pub server: String,
+    pub protocol_version: String,
+    pub server_info: JsonValue,
+    pub instructions: Option<String>,
+    /// Tools with their input (and output, if any) JSON schemas
+    pub tools: Vec<JsonValue>,
+    pub resources: Vec<JsonValue>,
+    pub resource_templates: Vec<JsonValue>,
+    /// Prompts with their arguments
+    pub prompts: Vec<JsonValue>,
+}
+
+/// Incremental parser for `text/event-stream` bodies
+#[derive(Default)]
+struct SseParser {
+    buffer: String,
+}
+
+impl SseParser {
+    /// Feed a chunk and return the completed (event, data) pairs
+    fn push(&mut self, chunk: &str) -> Vec<(String, String)> {
+        self.buffer.push_str(&chunk.replace("\r\n", "\n"));
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.find("\n\n") {
+            let block: String = self.buffer.drain(..end + 2).collect();
+            let mut event = "message".to_string();
+            let mut data: Vec<&str> = Vec::new();
+            for line in block.lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    event = value.trim().to_string();
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data.push(value.strip_prefix(' ').unwrap_or(value));
+                }
+            }
+            if !data.is_empty() {
+                events.push((event, data.join("\n")));
+            }
+        }
+        events
+    }
+}
+
+/// The response to request `id` among JSON-RPC messages, if it's there
+fn response_for(message: &JsonValue, id: u64) -> Option<Result<JsonValue, String>> {
+    if message.get("id").and_then(|v| v.as_u64()) != Some(id) || message.get("method").is_some() {
+        return None;
+    }
+    Some(match message.get("error") {
+        Some(error) => Err(format!(
+            "Server error {}: {}",
+            error["code"],
+            error["message"].as_str().unwrap_or("unknown error")
+        )),
+        None => Ok(message.get("result").cloned().unwrap_or(JsonValue::Null)),
+    })
+}
+
+fn rpc_request(id: u64, method: &str, params: JsonValue) -> JsonValue {
+    serde_json::json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+}
+
+/// A connection to a server over one of the MCP transports
+enum McpConnection {
+    Stdio {
+        child: Child,
+        stdin: ChildStdin,
+        stdout: Lines<BufReader<ChildStdout>>,
+        /// The tail of the server's stderr, once it exits
+        stderr: tokio::task::JoinHandle<String>,
+    },
+    /// Streamable HTTP: every message is a POST, answered with JSON or an event stream
+    Http {
+        client: reqwest::Client,
+        url: String,
+        headers: reqwest::header::HeaderMap,
+        session_id: Option<String>,
+    },
+    /// Legacy HTTP+SSE: requests are POSTed to an endpoint announced on a
+    /// long-lived event stream, which also carries the responses
+    Sse {
+        client: reqwest::Client,
+        endpoint: String,
+        headers: reqwest::header::HeaderMap,
+        stream: reqwest::Response,
+        parser: SseParser,
+    },
+}
+
+impl McpConnection {
+    async fn connect(config: &JsonValue) -> Result<Self, String> {
+        if let Some(url) = config.get("url").and_then(|v| v.as_str()) {
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert("User-Agent", "opcode-App".parse().unwrap());
+            for (name, value) in config
+                .get("headers")
+                .and_then(|v| v.as_object())
+                .into_iter()
+                .flatten()
+            {
+                let name: reqwest::header::HeaderName = name
+                    .parse()
+                    .map_err(|_| format!("Invalid header name '{}'", name))?;
+                let value = value
+                    .as_str()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| format!("Invalid value for header '{}'", name))?;
+                headers.insert(name, value);
+            }
+            let client = reqwest::Client::new();
+
+            return if config.get("type").and_then(|v| v.as_str()) == Some("sse") {
+                Self::connect_sse(client, url, headers).await
+            } else {
+                Ok(Self::Http {
+                    client,
+                    url: url.to_string(),
+                    headers,
+                    session_id: None,
+                })
+            };
+        }
+
+        let command = config
+            .get("command")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| "Server has neither a command nor a URL".to_string())?;
+        let mut cmd =
+            tokio::process::Command::from(crate::claude_binary::create_command_with_env(command));
+        for arg in config
+            .get("args")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            cmd.arg(arg.as_str().unwrap_or_default());
+        }
+        for (key, value) in config
+            .get("env")
+            .and_then(|v| v.as_object())
+            .into_iter()
+            .flatten()
+        {
+            cmd.env(key, value.as_str().unwrap_or_default());
+        }
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start '{}': {}", command, e))?;
+        let stdin = child.stdin.take().ok_or("Server stdin unavailable")?;
+        let stdout = child.stdout.take().ok_or("Server stdout unavailable")?;
+
+        // Drained as it comes, so a chatty server can't block on a full pipe
+        let mut pipe = child.stderr.take().ok_or("Server stderr unavailable")?;
+        let stderr = tokio::spawn(async move {
+            let mut kept = Vec::new();
+            let mut chunk = [0u8; 4096];
+            while let Ok(n) = pipe.read(&mut chunk).await {
+                if n == 0 {
+                    break;
+                }
+                kept.extend_from_slice(&chunk[..n]);
+                kept.drain(..kept.len().saturating_sub(STDERR_LIMIT));
+            }
+            String::from_utf8_lossy(&kept).into_owned()
+        });
+        Ok(Self::Stdio {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            stderr,
+        })
+    }
+
+    async fn connect_sse(
+        client: reqwest::Client,
+        url: &str,
+        headers: reqwest::header::HeaderMap,
+    ) -> Result<Self, String> {
+        let mut stream = client
+            .get(url)
+            .headers(headers.clone())
+            .header("Accept", "text/event-stream")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to open event stream: {}", e))?;
+        let mut parser = SseParser::default();
+        loop {
+            let chunk = stream
+                .chunk()
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or("Event stream closed before announcing an endpoint")?;
+            let endpoint = parser
+                .push(&String::from_utf8_lossy(&chunk))
+                .into_iter()
+                .find(|(event, _)| event == "endpoint");
+            if let Some((_, endpoint)) = endpoint {
+                let endpoint = reqwest::Url::parse(url)
+                    .and_then(|base| base.join(endpoint.trim()))
+                    .map_err(|e| format!("Invalid endpoint '{}': {}", endpoint, e))?;
+                return Ok(Self::Sse {
+                    client,
+                    endpoint: endpoint.to_string(),
+                    headers,
+                    stream,
+                    parser,
+                });
+            }
+        }
+    }
+
+    /// Send a message; for requests, wait for the response with `id`
+    async fn send(&mut self, message: JsonValue, id: Option<u64>) -> Result<JsonValue, String> {
+        match self {
+            Self::Stdio { stdin, stdout, .. } => {
+                let mut line = message.to_string();
+                line.push('\n');
+                stdin
+                    .write_all(line.as_bytes())
+                    .await
+                    .map_err(|e| format!("Failed to write to server: {}", e))?;
+                stdin.flush().await.map_err(|e| e.to_string())?;
+                let Some(id) = id else {
+                    return Ok(JsonValue::Null);
+                };
+                while let Some(line) = stdout.next_line().await.map_err(|e| e.to_string())? {
+                    // Servers may log or send notifications between responses
+                    let Ok(message) = serde_json::from_str::<JsonValue>(&line) else {
+                        continue;
+                    };
+                    if let Some(response) = response_for(&message, id) {
+                        return response;
+                    }
+                }
+                Err("Server exited before responding".to_string())
+            }
+            Self::Http {
+                client,
+                url,
+                headers,
+                session_id,
+            } => {
+                let mut request = client
+                    .post(url.as_str())
+                    .headers(headers.clone())
+                    .header("Accept", "application/json, text/event-stream")
+                    .json(&message);
+                if let Some(session_id) = session_id.as_deref() {
+                    request = request
+                        .header("Mcp-Session-Id", session_id)
+                        .header("MCP-Protocol-Version", PROTOCOL_VERSION);
+                }
+                let response = request
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| format!("Request failed: {}", e))?;
+                if let Some(id) = response
+                    .headers()
+                    .get("Mcp-Session-Id")
+                    .and_then(|v| v.to_str().ok())
+                {
+                    *session_id = Some(id.to_string());
+                }
+                let Some(id) = id else {
+                    return Ok(JsonValue::Null);
+                };
+
+                let is_stream = response
+                    .headers()
+                    .get("Content-Type")
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|v| v.starts_with("text/event-stream"));
+                let body = response.text().await.map_err(|e| e.to_string())?;
+                let messages: Vec<JsonValue> = if is_stream {
+                    SseParser::default()
+                        .push(&format!("{}\n\n", body))
+                        .into_iter()
+                        .filter_map(|(_, data)| serde_json::from_str(&data).ok())
+                        .collect()
+                } else {
+                    let message: JsonValue = serde_json::from_str(&body)
+                        .map_err(|e| format!("Invalid response: {}", e))?;
+                    match message {
+                        JsonValue::Array(batch) => batch,
+                        message => vec![message],
+                    }
+                };
+                messages
+                    .iter()
+                    .find_map(|message| response_for(message, id))
+                    .unwrap_or_else(|| Err("Server sent no response".to_string()))
+            }
+            Self::Sse {
+                client,
+                endpoint,
+                headers,
+                stream,
+                parser,
+            } => {
+                client
+                    .post(endpoint.as_str())
+                    .headers(headers.clone())
+                    .json(&message)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| format!("Request failed: {}", e))?;
+                let Some(id) = id else {
+                    return Ok(JsonValue::Null);
+                };
+                while let Some(chunk) = stream.chunk().await.map_err(|e| e.to_string())? {
+                    for (_, data) in parser.push(&String::from_utf8_lossy(&chunk)) {
+                        let Ok(message) = serde_json::from_str::<JsonValue>(&data) else {
+                            continue;
+                        };
+                        if let Some(response) = response_for(&message, id) {
+                            return response;
+                        }
+                    }
+                }
+                Err("Event stream closed before the server responded".to_string())
+            }
+        }
+    }
+
+    /// Stop the server or end the HTTP session
+    async fn close(self) -> String {
+        match self {
+            Self::Stdio {
+                mut child,
+                stdin,
+                stderr,
+                ..
+            } => {
+                // Closing stdin lets a well-behaved server exit on its own
+                drop(stdin);
+                if tokio::time::timeout(Duration::from_secs(2), child.wait())
+                    .await
+                    .is_err()
+                {
+                    let _ = child.kill().await;
+                }
+                tokio::time::timeout(Duration::from_secs(2), stderr)
+                    .await
+                    .ok()
+                    .and_then(Result::ok)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string()
+            }
+            Self::Http {
+                client,
+                url,
+                headers,
+                session_id: Some(session_id),
+            } => {
+                let _ = client
+                    .delete(url)
+                    .headers(headers)
+                    .header("Mcp-Session-Id", session_id)
+                    .send()
+                    .await;
+                String::new()
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+/// Handshake with a server and collect everything it advertises
+async fn inspect(connection: &mut McpConnection, server: &str) -> Result<McpCapabilities, String> {
+    let mut next_id = 0;
+    let mut request = |method: &str, params: JsonValue| {
+        next_id += 1;
+        (rpc_request(next_id, method, params), Some(next_id))
+    };
+
+    let (message, id) = request(
+        "initialize",
+        serde_json::json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {"name": "opcode", "version": env!("CARGO_PKG_VERSION")},
+        }),
+    );
+    let init = connection.send(message, id).await?;
+    connection
+        .send(
+            serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+            None,
+        )
+        .await?;
+
+    let mut capabilities = McpCapabilities {
+        server: server.to_string(),
+        protocol_version: init["protocolVersion"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        server_info: init["serverInfo"].clone(),
+        instructions: init["instructions"].as_str().map(str::to_string),
+        tools: Vec::new(),
+        resources: Vec::new(),
+        resource_templates: Vec::new(),
+        prompts: Vec::new(),
+    };
+
+    let advertised = |capability: &str| init["capabilities"].get(capability).is_some();
+    let lists = [
+        ("tools", "tools/list", "tools"),
+        ("resources", "resources/list", "resources"),
+        ("resources", "resources/templates/list", "resourceTemplates"),
+        ("prompts", "prompts/list", "prompts"),
+    ];
+    for (capability, method, field) in lists {
+        if !advertised(capability) {
+            continue;
+        }
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_PAGES {
+            let params = match &cursor {
+                Some(cursor) => serde_json::json!({ "cursor": cursor }),
+                None => serde_json::json!({}),
+            };
+            let (message, id) = request(method, params);
+            let page = match connection.send(message, id).await {
+                Ok(page) => page,
+                // Resource templates are optional even for servers with resources
+                Err(_) if method == "resources/templates/list" => break,
+                Err(e) => return Err(format!("{} failed: {}", method, e)),
+            };
+            items.extend(page[field].as_array().cloned().unwrap_or_default());
+            cursor = page["nextCursor"].as_str().map(str::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        match field {
+            "tools" => capabilities.tools = items,
+            "resources" => capabilities.resources = items,
+            "resourceTemplates" => capabilities.resource_templates = items,
+            _ => capabilities.prompts = items,
+        }
+    }
+    Ok(capabilities)
+}
+
+/// Inspect a server definition directly
+pub async fn inspect_server_config(
+    server: &str,
+    config: &JsonValue,
+) -> Result<McpCapabilities, String> {
+    let mut connection = McpConnection::connect(config).await?;
+    let result = tokio::time::timeout(INSPECT_TIMEOUT, inspect(&mut connection, server))
+        .await
+        .unwrap_or_else(|_| {
+            Err(format!(
+                "Server did not respond within {}s",
+                INSPECT_TIMEOUT.as_secs()
+            ))
+        });
+    let stderr = connection.close().await;
+    result.map_err(|e| {
+        if stderr.is_empty() {
+            e
+        } else {
+            format!("{}\n\nServer stderr:\n{}", e, stderr)
+        }
+    })
+}
+
+/// Connect to a configured server, perform the handshake and return the
+/// tools, resources and prompts it advertises
+#[tauri::command]
+pub async fn mcp_inspect_server(
+    name: String,
+    project_path: Option<String>,
+) -> Result<McpCapabilities, String> {
+    info!("Inspecting MCP server: {}", name);
+    let config = configured_servers(project_path.as_deref())?
+        .remove(&name)
+        .ok_or_else(|| format!("MCP server '{}' is not configured", name))?;
+    inspect_server_config(&name, &config).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser() {
+        let mut parser = SseParser::default();
+        assert!(parser
+            .push("event: endpoint\r\ndata: /messages?s")
+            .is_empty());
+        assert_eq!(
+            parser.push("ession=1\r\n\r\ndata: {\"id\":1}\n\n: ping\n\n"),
+            vec![
+                ("endpoint".to_string(), "/messages?session=1".to_string()),
+                ("message".to_string(), "{\"id\":1}".to_string()),
+            ]
+        );
+
+        let ok = serde_json::json!({"jsonrpc": "2.0", "id": 2, "result": {"tools": []}});
+        assert_eq!(response_for(&ok, 1), None);
+        assert_eq!(
+            response_for(&ok, 2),
+            Some(Ok(serde_json::json!({"tools": []})))
+        );
+        let error = serde_json::json!({"id": 3, "error": {"code": -32601, "message": "nope"}});
+        assert!(response_for(&error, 3).unwrap().is_err());
+        let ping = serde_json::json!({"id": 4, "method": "ping"});
+        assert_eq!(response_for(&ping, 4), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_inspect_stdio_server() {
+        let script = r#"while IFS= read -r line; do
+  case "$line" in
+    *'"initialize"'*) echo '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{"tools":{},"prompts":{}},"serverInfo":{"name":"fake","version":"1.0"}}}' ;;
+    *'"tools/list"'*'"cursor"'*) echo '{"jsonrpc":"2.0","id":3,"result":{"tools":[{"name":"second","inputSchema":{"type":"object"}}]}}' ;;
+    *'"tools/list"'*) echo 'starting up'; echo '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"first","inputSchema":{"type":"object"}}],"nextCursor":"p2"}}' ;;
+    *'"prompts/list"'*) echo '{"jsonrpc":"2.0","id":4,"result":{"prompts":[{"name":"review","arguments":[]}]}}' ;;
+  esac
+done"#;
+        let config = serde_json::json!({"command": "sh", "args": ["-c", script]});
+        let capabilities = inspect_server_config("fake", &config).await.unwrap();
+        assert_eq!(capabilities.server_info["name"], "fake");
+        let tools: Vec<&str> = capabilities
+            .tools
+            .iter()
+            .filter_map(|tool| tool["name"].as_str())
+            .collect();
+        assert_eq!(tools, ["first", "second"]);
+        assert_eq!(capabilities.prompts[0]["name"], "review");
+        assert!(capabilities.resources.is_empty());
+
+        let missing = serde_json::json!({"command": "sh", "args": ["-c", "echo broken >&2"]});
+        let error = inspect_server_config("broken", &missing).await.unwrap_err();
+        assert!(error.contains("broken"), "{}", error);
+    }
+}
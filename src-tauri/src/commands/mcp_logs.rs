@@ -0,0 +1,176 @@
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::mcp::{configured_servers, update_server_config};
+use crate::commands::mcp_secrets::config_has_templates;
+use crate::mcp_proxy;
+
+/// How often a streamed log is checked for new lines
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Lines returned by `tail_mcp_logs` when the caller asks for more
+const MAX_TAIL_LINES: usize = 5000;
+
+/// Live log streams, keyed by server name, with the flag that stops each one
+#[derive(Default)]
+pub struct McpLogStreamState(pub Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+fn log_dir(server: &str) -> Result<PathBuf, String> {
+    let root = mcp_proxy::logs_root().ok_or_else(|| "Could not find data directory".to_string())?;
+    Ok(mcp_proxy::server_log_dir(&root, server))
+}
+
+/// Bytes of a file from `offset` on
+fn read_from(path: &Path, offset: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Whether a server is launched through the logging proxy
+#[tauri::command]
+pub async fn get_mcp_log_capture(
+    server: String,
+    project_path: Option<String>,
+) -> Result<bool, String> {
+    let servers = configured_servers(project_path.as_deref())?;
+    let config = servers
+        .get(&server)
+        .ok_or_else(|| format!("MCP server '{}' is not configured", server))?;
+    Ok(mcp_proxy::unwrap(config).is_some())
+}
+
+/// Turn log capture on or off for a stdio server by launching it through the
+/// logging proxy. Returns whether the server definition changed; running
+/// Claude sessions keep their servers until they restart.
+#[tauri::command]
+pub async fn set_mcp_log_capture(
+    server: String,
+    enabled: bool,
+    project_path: Option<String>,
+) -> Result<bool, String> {
+    let proxy = mcp_proxy::proxy_executable()?;
+    let mut needs_proxy = false;
+    let mut shared = false;
+    let written = update_server_config(&server, project_path.as_deref(), |path, config| {
+        if enabled {
+            shared = mcp_proxy::is_shared_config(path);
+            if shared {
+                return None;
+            }
+            mcp_proxy::wrap(&server, config, &proxy)
+        } else {
+            let original = mcp_proxy::unwrap(config)?;
+            needs_proxy = config_has_templates(&original);
+            (!needs_proxy).then_some(original)
+        }
+    })?;
+    if shared {
+        return Err(format!(
+            "MCP server '{}' is shared through the project's .mcp.json, where opcode can't capture its logs",
+            server
+        ));
+    }
+    if needs_proxy {
+        return Err(format!(
+            "MCP server '{}' references secrets or variables, which are resolved by the logging proxy",
+            server
+        ));
+    }
+
+    if let Some(path) = &written {
+        info!(
+            "Log capture for MCP server {} {} in {}",
+            server,
+            if enabled { "enabled" } else { "disabled" },
+            path.display()
+        );
+    }
+    Ok(written.is_some())
+}
+
+/// The last `lines` lines captured from a server, oldest first
+#[tauri::command]
+pub async fn tail_mcp_logs(server: String, lines: usize) -> Result<Vec<String>, String> {
+    let dir = log_dir(&server)?;
+    mcp_proxy::tail(&dir, lines.min(MAX_TAIL_LINES))
+        .map_err(|e| format!("Failed to read logs for '{}': {}", server, e))
+}
+
+/// Emit each line captured from a server from now on as an
+/// `mcp-log:<server>` event, until `stop_mcp_log_stream` is called
+#[tauri::command]
+pub async fn start_mcp_log_stream(
+    app: AppHandle,
+    streams: State<'_, McpLogStreamState>,
+    server: String,
+) -> Result<(), String> {
+    let dir = log_dir(&server)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let mut streams = streams.0.lock().map_err(|e| e.to_string())?;
+        if streams.contains_key(&server) {
+            return Ok(());
+        }
+        streams.insert(server.clone(), Arc::clone(&stop));
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let current = mcp_proxy::current_log(&dir);
+        let size = |path: &Path| fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let mut offset = size(&current);
+        let mut pending = String::new();
+        let event = format!("mcp-log:{}", server);
+
+        while !stop.load(Ordering::Relaxed) {
+            tokio::time::sleep(STREAM_POLL_INTERVAL).await;
+            let len = size(&current);
+            if len == offset {
+                continue;
+            }
+
+            let mut bytes = Vec::new();
+            if len < offset {
+                // Rotated: finish the old file, then start the new one from the top
+                bytes = read_from(&mcp_proxy::rotated_log(&dir, 1), offset).unwrap_or_default();
+                offset = 0;
+            }
+            match read_from(&current, offset) {
+                Ok(new) => {
+                    offset += new.len() as u64;
+                    bytes.extend(new);
+                }
+                Err(e) => warn!("Failed to read MCP log for {}: {}", server, e),
+            }
+
+            pending.push_str(&String::from_utf8_lossy(&bytes));
+            while let Some(end) = pending.find('\n') {
+                let line: String = pending.drain(..=end).collect();
+                let _ = app.emit(&event, line.trim_end());
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Stop a log stream started with `start_mcp_log_stream`
+#[tauri::command]
+pub async fn stop_mcp_log_stream(
+    streams: State<'_, McpLogStreamState>,
+    server: String,
+) -> Result<(), String> {
+    let mut streams = streams.0.lock().map_err(|e| e.to_string())?;
+    if let Some(stop) = streams.remove(&server) {
+        stop.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
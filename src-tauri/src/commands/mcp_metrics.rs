@@ -0,0 +1,332 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use tauri::command;
+
+use crate::mcp_proxy;
+use crate::mcp_supervisor;
+use crate::transcript::{self, ContentBlock, TranscriptEntry};
+
+/// Latency distribution of a set of timings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub total_ms: u64,
+    pub mean_ms: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyStats {
+    fn from_timings(mut timings: Vec<u64>) -> Option<Self> {
+        if timings.is_empty() {
+            return None;
+        }
+        timings.sort_unstable();
+        let count = timings.len();
+        // Nearest-rank percentile
+        let percentile = |p: usize| timings[(count * p).div_ceil(100).max(1) - 1];
+        let total_ms: u64 = timings.iter().sum();
+        Some(Self {
+            count: count as u64,
+            total_ms,
+            mean_ms: total_ms / count as u64,
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            max_ms: timings[count - 1],
+        })
+    }
+}
+
+/// Tool calls of one MCP tool
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpToolMetrics {
+    pub tool: String,
+    pub calls: LatencyStats,
+    pub errors: u64,
+}
+
+/// Startup and tool-call latency of one MCP server
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpServerMetrics {
+    pub server: String,
+    /// From launch to the answer to `initialize`; only measured for servers
+    /// launched through opcode's proxy
+    pub initialization: Option<LatencyStats>,
+    pub tool_calls: Option<LatencyStats>,
+    pub errors: u64,
+    /// Slowest tools first, by total time
+    pub tools: Vec<McpToolMetrics>,
+}
+
+/// One completed MCP tool call found in a transcript
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpToolCall {
+    pub server: String,
+    pub tool: String,
+    pub latency_ms: u64,
+    pub is_error: bool,
+}
+
+/// Server and tool of an MCP tool name, `mcp__<server>__<tool>`
+pub fn split_tool_name(name: &str) -> Option<(&str, &str)> {
+    let (server, tool) = name.strip_prefix("mcp__")?.split_once("__")?;
+    (!server.is_empty() && !tool.is_empty()).then_some((server, tool))
+}
+
+/// A server name as it appears in tool names, where Claude replaces anything
+/// but letters, digits, `_` and `-`
+fn tool_name_prefix(server: &str) -> String {
+    server
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn parse_time(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Pair every MCP tool use in a transcript with its result. The latency runs
+/// from the assistant message with the call to the user message with the
+/// result, so it includes any time spent waiting for permission.
+pub fn transcript_tool_calls(
+    reader: impl BufRead,
+    since: Option<DateTime<Utc>>,
+) -> Vec<McpToolCall> {
+    let mut started: HashMap<String, (String, String, DateTime<Utc>)> = HashMap::new();
+    let mut calls = Vec::new();
+    for line in reader.lines().map_while(Result::ok) {
+        let (message, is_result) = match transcript::parse_line(&line) {
+            Some(TranscriptEntry::Assistant(message)) => (message, false),
+            Some(TranscriptEntry::User(message)) => (message, true),
+            _ => continue,
+        };
+        let Some(time) = message.timestamp.as_deref().and_then(parse_time) else {
+            continue;
+        };
+        if since.is_some_and(|since| time < since) {
+            continue;
+        }
+        for block in message.content {
+            match block {
+                ContentBlock::ToolUse {
+                    id: Some(id), name, ..
+                } if !is_result => {
+                    if let Some((server, tool)) = split_tool_name(&name) {
+                        started.insert(id, (server.to_string(), tool.to_string(), time));
+                    }
+                }
+                ContentBlock::ToolResult {
+                    tool_use_id: Some(id),
+                    is_error,
+                    ..
+                } if is_result => {
+                    if let Some((server, tool, start)) = started.remove(&id) {
+                        calls.push(McpToolCall {
+                            server,
+                            tool,
+                            latency_ms: (time - start).num_milliseconds().max(0) as u64,
+                            is_error,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    calls
+}
+
+/// Aggregate tool calls and startup times per server, slowest servers first
+pub fn aggregate(
+    calls: Vec<McpToolCall>,
+    initializations: BTreeMap<String, Vec<u64>>,
+) -> Vec<McpServerMetrics> {
+    let mut by_server: BTreeMap<String, BTreeMap<String, (Vec<u64>, u64)>> = BTreeMap::new();
+    for call in calls {
+        let (timings, errors) = by_server
+            .entry(call.server)
+            .or_default()
+            .entry(call.tool)
+            .or_default();
+        timings.push(call.latency_ms);
+        *errors += call.is_error as u64;
+    }
+    for server in initializations.keys() {
+        by_server.entry(server.clone()).or_default();
+    }
+
+    let mut metrics: Vec<McpServerMetrics> = by_server
+        .into_iter()
+        .map(|(server, tools)| {
+            let all: Vec<u64> = tools
+                .values()
+                .flat_map(|(t, _)| t.iter().copied())
+                .collect();
+            let mut tools: Vec<McpToolMetrics> = tools
+                .into_iter()
+                .filter_map(|(tool, (timings, errors))| {
+                    Some(McpToolMetrics {
+                        tool,
+                        calls: LatencyStats::from_timings(timings)?,
+                        errors,
+                    })
+                })
+                .collect();
+            tools.sort_by_key(|tool| std::cmp::Reverse(tool.calls.total_ms));
+            McpServerMetrics {
+                initialization: initializations
+                    .get(&server)
+                    .and_then(|times| LatencyStats::from_timings(times.clone())),
+                tool_calls: LatencyStats::from_timings(all),
+                errors: tools.iter().map(|tool| tool.errors).sum(),
+                server,
+                tools,
+            }
+        })
+        .collect();
+    let cost = |m: &McpServerMetrics| {
+        m.tool_calls.as_ref().map_or(0, |s| s.total_ms)
+            + m.initialization.as_ref().map_or(0, |s| s.total_ms)
+    };
+    metrics.sort_by_key(|m| std::cmp::Reverse(cost(m)));
+    metrics
+}
+
+/// Startup times logged by the proxy, by server name
+fn initialization_times(since: Option<DateTime<Utc>>) -> BTreeMap<String, Vec<u64>> {
+    let mut times = BTreeMap::new();
+    let Some(entries) = mcp_proxy::logs_root().and_then(|root| fs::read_dir(root).ok()) else {
+        return times;
+    };
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let server = mcp_supervisor::read_status(&dir)
+            .map(|status| tool_name_prefix(&status.server))
+            .unwrap_or_else(|| entry.file_name().to_string_lossy().into_owned());
+        let logged: Vec<u64> = mcp_proxy::initialization_times(&dir)
+            .into_iter()
+            .filter(|(timestamp, _)| {
+                since.is_none_or(|since| parse_time(timestamp).is_some_and(|t| t >= since))
+            })
+            .map(|(_, ms)| ms)
+            .collect();
+        if !logged.is_empty() {
+            times.entry(server).or_insert_with(Vec::new).extend(logged);
+        }
+    }
+    times
+}
+
+fn modified_since(path: &Path, since: Option<DateTime<Utc>>) -> bool {
+    let Some(since) = since else {
+        return true;
+    };
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map(|modified| DateTime::<Utc>::from(modified) >= since)
+        .unwrap_or(true)
+}
+
+/// Startup and tool-call latency of every MCP server, slowest first, over the
+/// last `days` days and optionally one project's sessions
+#[command]
+pub fn get_mcp_latency_metrics(
+    days: Option<u32>,
+    project_id: Option<String>,
+) -> Result<Vec<McpServerMetrics>, String> {
+    let since = days.map(|days| Utc::now() - Duration::days(days as i64));
+    let projects_dir = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude")
+        .join("projects");
+
+    let mut calls = Vec::new();
+    let project_dirs: Vec<_> = match &project_id {
+        Some(id) => vec![projects_dir.join(id)],
+        None => fs::read_dir(&projects_dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default(),
+    };
+    for dir in project_dirs {
+        let Ok(files) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in files.flatten().map(|file| file.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl")
+                || !modified_since(&path, since)
+            {
+                continue;
+            }
+            if let Ok(file) = File::open(&path) {
+                calls.extend(transcript_tool_calls(BufReader::new(file), since));
+            }
+        }
+    }
+
+    // Startup isn't tied to a project; the proxy logs it per server
+    let initializations = initialization_times(since);
+    Ok(aggregate(calls, initializations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_call_latency() {
+        let transcript = [
+            r#"{"type":"assistant","timestamp":"2025-06-01T10:00:00.000Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"a","name":"mcp__github__search_issues","input":{}},{"type":"tool_use","id":"b","name":"Read","input":{}}]}}"#,
+            r#"{"type":"user","timestamp":"2025-06-01T10:00:02.500Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"a","content":"[]"},{"type":"tool_result","tool_use_id":"b","content":"x"}]}}"#,
+            r#"{"type":"assistant","timestamp":"2025-06-01T10:01:00.000Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"c","name":"mcp__github__search_issues","input":{}}]}}"#,
+            r#"{"type":"user","timestamp":"2025-06-01T10:01:00.500Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"c","content":"boom","is_error":true}]}}"#,
+            r#"{"type":"assistant","timestamp":"2025-06-01T10:02:00.000Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"d","name":"mcp__memory__read_graph","input":{}}]}}"#,
+            r#"{"type":"user","timestamp":"2025-06-01T10:02:00.100Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"d","content":"{}"}]}}"#,
+        ]
+        .join("\n");
+
+        let calls = transcript_tool_calls(transcript.as_bytes(), None);
+        assert_eq!(
+            calls
+                .iter()
+                .map(|c| (c.server.as_str(), c.latency_ms, c.is_error))
+                .collect::<Vec<_>>(),
+            [
+                ("github", 2500, false),
+                ("github", 500, true),
+                ("memory", 100, false)
+            ]
+        );
+        let since = parse_time("2025-06-01T10:01:30Z");
+        assert_eq!(transcript_tool_calls(transcript.as_bytes(), since).len(), 1);
+
+        let metrics = aggregate(
+            calls,
+            BTreeMap::from([("memory".to_string(), vec![4000, 6000])]),
+        );
+        assert_eq!(metrics[0].server, "memory");
+        assert_eq!(metrics[0].initialization.as_ref().unwrap().mean_ms, 5000);
+        let github = &metrics[1];
+        let calls = github.tool_calls.as_ref().unwrap();
+        assert_eq!((calls.count, calls.p50_ms, calls.max_ms), (2, 500, 2500));
+        assert_eq!(github.errors, 1);
+        assert_eq!(github.tools[0].tool, "search_issues");
+        assert!(github.initialization.is_none());
+
+        assert_eq!(split_tool_name("mcp__a__b__c"), Some(("a", "b__c")));
+        assert_eq!(split_tool_name("Read"), None);
+    }
+}
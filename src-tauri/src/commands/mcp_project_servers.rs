@@ -0,0 +1,310 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fs;
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::mcp::active_servers;
+use crate::commands::mcp_environments::{apply_server_environments, load_server_environments};
+use crate::commands::shell::load_shell_config;
+use crate::process::normalize_project_path;
+use crate::shell_environment::{windows_to_wsl_path, ShellConfig, ShellEnvironment};
+
+/// project_path of the flags that apply to every project without its own flag
+const ALL_PROJECTS: &str = "";
+
+/// Enabled flags keyed by server name
+type Flags = HashMap<String, bool>;
+
+/// Whether an MCP server runs in a project, and which flag decided it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProjectMcpServer {
+    pub name: String,
+    pub enabled: bool,
+    /// "project" for this project's own flag, "default" for the flag shared by
+    /// all projects, None when the server is unflagged and runs as configured
+    pub flag: Option<String>,
+}
+
+/// The flags set for a project, and those set for all projects
+fn load_flags(conn: &Connection, project_path: &str) -> Result<(Flags, Flags), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT project_path, server_name, enabled FROM mcp_project_servers
+             WHERE project_path IN (?1, ?2)",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![ALL_PROJECTS, normalize_project_path(project_path)],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, bool>(2)?,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+    let (mut project, mut defaults) = (HashMap::new(), HashMap::new());
+    for row in rows {
+        let (path, server, enabled) = row.map_err(|e| e.to_string())?;
+        if path == ALL_PROJECTS {
+            defaults.insert(server, enabled);
+        } else {
+            project.insert(server, enabled);
+        }
+    }
+    Ok((project, defaults))
+}
+
+/// Apply flags to the servers configured for a project: the project's own flag
+/// wins, then the all-projects flag, and unflagged servers stay enabled
+pub fn resolve_project_servers(
+    servers: &serde_json::Map<String, JsonValue>,
+    project: &Flags,
+    defaults: &Flags,
+) -> Vec<ProjectMcpServer> {
+    let mut resolved: Vec<ProjectMcpServer> = servers
+        .keys()
+        .map(|name| {
+            let (enabled, flag) = match (project.get(name), defaults.get(name)) {
+                (Some(enabled), _) => (*enabled, Some("project")),
+                (None, Some(enabled)) => (*enabled, Some("default")),
+                (None, None) => (true, None),
+            };
+            ProjectMcpServer {
+                name: name.clone(),
+                enabled,
+                flag: flag.map(str::to_string),
+            }
+        })
+        .collect();
+    resolved.sort_by(|a, b| a.name.cmp(&b.name));
+    resolved
+}
+
+/// The MCP config a Claude session in this project should run with when
+/// Claude runs in `claude_environment`, or None when no configured server is
+/// turned off or moved to another environment and Claude's own config applies
+pub fn effective_mcp_config(
+    conn: &Connection,
+    project_path: &str,
+    claude_environment: &ShellEnvironment,
+) -> Result<Option<JsonValue>, String> {
+    let (project, defaults) = load_flags(conn, project_path)?;
+    let environments = load_server_environments(conn);
+    if environments.is_empty()
+        && !project
+            .values()
+            .chain(defaults.values())
+            .any(|enabled| !enabled)
+    {
+        return Ok(None);
+    }
+
+    let mut servers = active_servers(&normalize_project_path(project_path))?;
+    let disabled: Vec<String> = resolve_project_servers(&servers, &project, &defaults)
+        .into_iter()
+        .filter(|server| !server.enabled)
+        .map(|server| server.name)
+        .collect();
+    for name in &disabled {
+        servers.remove(name);
+    }
+
+    let shell = ShellConfig {
+        environment: claude_environment.clone(),
+        ..load_shell_config(conn)
+    };
+    let wrapped = apply_server_environments(&mut servers, &environments, &shell);
+    if disabled.is_empty() && wrapped == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::json!({ "mcpServers": servers })))
+}
+
+/// Extra Claude arguments that limit a session to the servers enabled for its
+/// project, each run in its own environment. Empty when there's nothing to
+/// change, or when the config can't be written, in which case Claude starts
+/// every configured server as before.
+pub fn mcp_config_args(
+    app: &AppHandle,
+    project_path: &str,
+    claude_environment: &ShellEnvironment,
+) -> Vec<String> {
+    let config = {
+        let Some(db) = app.try_state::<AgentDb>() else {
+            return Vec::new();
+        };
+        let Ok(conn) = db.0.lock() else {
+            return Vec::new();
+        };
+        effective_mcp_config(&conn, project_path, claude_environment)
+    };
+    let config = match config {
+        Ok(Some(config)) => config,
+        Ok(None) => return Vec::new(),
+        Err(e) => {
+            warn!("Failed to resolve MCP servers for {}: {}", project_path, e);
+            return Vec::new();
+        }
+    };
+
+    let written = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|dir| {
+            let dir = dir.join("mcp-configs");
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            let path = dir.join(format!(
+                "{}.json",
+                normalize_project_path(project_path).replace(['/', '\\', ':'], "-")
+            ));
+            let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+            fs::write(&path, json).map_err(|e| e.to_string())?;
+            Ok(path)
+        });
+    match written {
+        Ok(path) => vec![
+            "--mcp-config".to_string(),
+            match claude_environment {
+                ShellEnvironment::Wsl => windows_to_wsl_path(&path.to_string_lossy()),
+                _ => path.to_string_lossy().into_owned(),
+            },
+            "--strict-mcp-config".to_string(),
+        ],
+        Err(e) => {
+            warn!("Failed to write MCP config for {}: {}", project_path, e);
+            Vec::new()
+        }
+    }
+}
+
+/// The servers configured for a project and whether each one runs there
+#[tauri::command]
+pub async fn get_project_mcp_servers(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<Vec<ProjectMcpServer>, String> {
+    let (project, defaults) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_flags(&conn, &project_path)?
+    };
+    let servers = active_servers(&normalize_project_path(&project_path))?;
+    Ok(resolve_project_servers(&servers, &project, &defaults))
+}
+
+/// Turn a server on or off for one project, or for every project when no
+/// project is given. `enabled: None` clears the flag again.
+#[tauri::command]
+pub async fn set_project_mcp_server(
+    db: State<'_, AgentDb>,
+    server: String,
+    project_path: Option<String>,
+    enabled: Option<bool>,
+) -> Result<(), String> {
+    let project_path = project_path
+        .as_deref()
+        .map(normalize_project_path)
+        .unwrap_or_else(|| ALL_PROJECTS.to_string());
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match enabled {
+        Some(enabled) => conn.execute(
+            "INSERT INTO mcp_project_servers (project_path, server_name, enabled)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(project_path, server_name) DO UPDATE SET
+                enabled = excluded.enabled,
+                updated_at = CURRENT_TIMESTAMP",
+            params![project_path, server, enabled],
+        ),
+        None => conn.execute(
+            "DELETE FROM mcp_project_servers WHERE project_path = ?1 AND server_name = ?2",
+            params![project_path, server],
+        ),
+    }
+    .map_err(|e| format!("Failed to save MCP server flag: {}", e))?;
+
+    info!(
+        "MCP server {} {} for {}",
+        server,
+        match enabled {
+            Some(true) => "enabled",
+            Some(false) => "disabled",
+            None => "reset",
+        },
+        if project_path == ALL_PROJECTS {
+            "all projects"
+        } else {
+            &project_path
+        }
+    );
+    Ok(())
+}
+
+/// The MCP config sessions in a project start with
+#[tauri::command]
+pub async fn get_effective_mcp_config(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<JsonValue, String> {
+    let config = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let environment = load_shell_config(&conn).environment;
+        effective_mcp_config(&conn, &project_path, &environment)?
+    };
+    match config {
+        Some(config) => Ok(config),
+        None => {
+            let servers = active_servers(&normalize_project_path(&project_path))?;
+            Ok(serde_json::json!({ "mcpServers": servers }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_project_servers() {
+        let servers = serde_json::json!({
+            "postgres": {"command": "npx"},
+            "github": {"url": "https://api.githubcopilot.com/mcp/"},
+            "memory": {"command": "npx"},
+        });
+        let servers = servers.as_object().unwrap();
+        let defaults = HashMap::from([
+            ("postgres".to_string(), false),
+            ("memory".to_string(), false),
+        ]);
+        let project = HashMap::from([("postgres".to_string(), true)]);
+
+        let flag = |name: &str, enabled: bool, flag: Option<&str>| ProjectMcpServer {
+            name: name.to_string(),
+            enabled,
+            flag: flag.map(str::to_string),
+        };
+        assert_eq!(
+            resolve_project_servers(servers, &project, &defaults),
+            vec![
+                flag("github", true, None),
+                flag("memory", false, Some("default")),
+                flag("postgres", true, Some("project")),
+            ]
+        );
+        assert_eq!(
+            resolve_project_servers(servers, &HashMap::new(), &defaults),
+            vec![
+                flag("github", true, None),
+                flag("memory", false, Some("default")),
+                flag("postgres", false, Some("default")),
+            ]
+        );
+    }
+}
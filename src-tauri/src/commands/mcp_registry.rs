@@ -0,0 +1,467 @@
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+
+use crate::commands::agent_env::is_valid_env_name;
+use crate::commands::agents::AgentDb;
+use crate::commands::mcp::{configured_servers, mcp_add_json, server_identity, AddServerResult};
+use crate::commands::settings::{load_json_setting, save_json_setting};
+
+/// Curated registry published alongside the bundled agents
+const DEFAULT_REGISTRY_URL: &str =
+    "https://raw.githubusercontent.com/getAsterisk/opcode/main/cc_mcp/registry.json";
+
+/// app_settings key holding the registry settings as JSON
+const SETTINGS_KEY: &str = "mcp_registry";
+
+/// app_settings key holding the last fetched registry
+const CACHE_KEY: &str = "mcp_registry_cache";
+
+/// How long a fetched registry is used before fetching it again
+const CACHE_TTL_HOURS: i64 = 24;
+
+/// Where the registry comes from and how it's verified
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpRegistrySettings {
+    pub url: String,
+    /// Minisign public key; when set, `<url>.minisig` must carry a valid signature
+    pub public_key: Option<String>,
+}
+
+impl Default for McpRegistrySettings {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_REGISTRY_URL.to_string(),
+            public_key: None,
+        }
+    }
+}
+
+/// An environment variable a registry server needs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegistryEnvVar {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub secret: bool,
+}
+
+/// A server listed in the registry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegistryServer {
+    pub name: String,
+    pub title: Option<String>,
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub homepage: Option<String>,
+    /// "stdio", "sse" or "http"
+    pub transport: String,
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: Vec<RegistryEnvVar>,
+    pub url: Option<String>,
+    /// Whether an equivalent server is already configured
+    #[serde(default)]
+    pub installed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Registry {
+    servers: Vec<RegistryServer>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedRegistry {
+    url: String,
+    /// Key the body was verified with, so adding a key drops unverified caches
+    #[serde(default)]
+    public_key: Option<String>,
+    fetched_at: DateTime<Utc>,
+    body: String,
+}
+
+/// Result of installing a server from the registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryInstallResult {
+    pub result: AddServerResult,
+    /// Variables written as `${NAME}` placeholders, to be set in the environment
+    pub env_placeholders: Vec<String>,
+}
+
+fn load_settings(conn: &Connection) -> McpRegistrySettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+fn load_cache(conn: &Connection) -> Option<CachedRegistry> {
+    load_json_setting(conn, CACHE_KEY)
+}
+
+fn is_https(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| url.scheme() == "https" && url.host().is_some())
+}
+
+/// Checks that a registry entry is safe to show and install
+pub fn validate_entry(server: &RegistryServer) -> Result<(), String> {
+    if server.name.is_empty()
+        || !server
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("invalid server name '{}'", server.name));
+    }
+    if let Some(homepage) = &server.homepage {
+        if !is_https(homepage) {
+            return Err(format!("homepage is not an https URL: {}", homepage));
+        }
+    }
+    match server.transport.as_str() {
+        "stdio" => {
+            // A bare program name resolved on PATH, never a path or a shell snippet
+            let command = server.command.as_deref().unwrap_or_default();
+            if command.is_empty()
+                || !command
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            {
+                return Err(format!("invalid command '{}'", command));
+            }
+        }
+        "sse" | "http" => {
+            let url = server.url.as_deref().unwrap_or_default();
+            if !is_https(url) {
+                return Err(format!("server URL is not an https URL: {}", url));
+            }
+        }
+        other => return Err(format!("unknown transport '{}'", other)),
+    }
+    if let Some(var) = server.env.iter().find(|var| !is_valid_env_name(&var.name)) {
+        return Err(format!("invalid environment variable name '{}'", var.name));
+    }
+    Ok(())
+}
+
+/// Parses a registry document, dropping entries that fail validation
+fn parse_registry(body: &str) -> Result<Vec<RegistryServer>, String> {
+    let registry: Registry =
+        serde_json::from_str(body).map_err(|e| format!("Failed to parse MCP registry: {}", e))?;
+    Ok(registry
+        .servers
+        .into_iter()
+        .filter(|server| match validate_entry(server) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Ignoring registry entry {}: {}", server.name, e);
+                false
+            }
+        })
+        .collect())
+}
+
+fn verify_signature(body: &[u8], signature: &str, public_key: &str) -> Result<(), String> {
+    let public_key = minisign_verify::PublicKey::from_base64(public_key.trim())
+        .map_err(|e| format!("Invalid registry public key: {}", e))?;
+    let signature = minisign_verify::Signature::decode(signature)
+        .map_err(|e| format!("Invalid registry signature: {}", e))?;
+    public_key
+        .verify(body, &signature, false)
+        .map_err(|e| format!("Registry signature does not verify: {}", e))
+}
+
+async fn fetch_text(client: &reqwest::Client, url: &str) -> Result<String, String> {
+    let response = client
+        .get(url)
+        .header("User-Agent", "opcode-App")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to fetch {}: HTTP {}",
+            url,
+            response.status()
+        ));
+    }
+    response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", url, e))
+}
+
+/// Downloads the registry, checking its signature when a key is configured
+async fn fetch_registry(settings: &McpRegistrySettings) -> Result<String, String> {
+    info!("Fetching MCP registry from {}", settings.url);
+    if !is_https(&settings.url) {
+        return Err(format!("Registry URL must use https: {}", settings.url));
+    }
+
+    let client = reqwest::Client::new();
+    let body = fetch_text(&client, &settings.url).await?;
+    if let Some(public_key) = &settings.public_key {
+        let signature = fetch_text(&client, &format!("{}.minisig", settings.url)).await?;
+        verify_signature(body.as_bytes(), &signature, public_key)?;
+    }
+    parse_registry(&body)?;
+    Ok(body)
+}
+
+/// Servers from the registry, served from the cache while it is fresh. A stale
+/// cache is still used when the registry can't be reached.
+async fn registry_servers(db: &AgentDb, refresh: bool) -> Result<Vec<RegistryServer>, String> {
+    let (settings, cache) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (load_settings(&conn), load_cache(&conn))
+    };
+    let cache =
+        cache.filter(|cache| cache.url == settings.url && cache.public_key == settings.public_key);
+    if let Some(cache) = &cache {
+        if !refresh && Utc::now() - cache.fetched_at < Duration::hours(CACHE_TTL_HOURS) {
+            return parse_registry(&cache.body);
+        }
+    }
+
+    match fetch_registry(&settings).await {
+        Ok(body) => {
+            let fresh = CachedRegistry {
+                url: settings.url,
+                public_key: settings.public_key,
+                fetched_at: Utc::now(),
+                body,
+            };
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            save_json_setting(&conn, CACHE_KEY, &fresh)?;
+            parse_registry(&fresh.body)
+        }
+        Err(e) => match cache {
+            Some(cache) => {
+                warn!("Using cached MCP registry: {}", e);
+                parse_registry(&cache.body)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// The definition written for a registry server, with `${NAME}` placeholders for
+/// variables the user didn't provide. Values of secret variables go in the
+/// `secrets` field, which [`mcp_add_json`] stores in the keychain and replaces
+/// with `${secret:NAME}` references.
+fn server_config(
+    server: &RegistryServer,
+    env: &HashMap<String, String>,
+) -> (serde_json::Value, Vec<String>) {
+    let mut placeholders = Vec::new();
+    let mut env_config = serde_json::Map::new();
+    let mut secrets = serde_json::Map::new();
+    for var in &server.env {
+        match env.get(&var.name).filter(|value| !value.is_empty()) {
+            Some(value) if var.secret => {
+                secrets.insert(var.name.clone(), value.clone().into());
+            }
+            Some(value) => {
+                env_config.insert(var.name.clone(), value.clone().into());
+            }
+            None if var.required => {
+                env_config.insert(var.name.clone(), format!("${{{}}}", var.name).into());
+                placeholders.push(var.name.clone());
+            }
+            None => {}
+        }
+    }
+
+    let config = match server.transport.as_str() {
+        "stdio" => {
+            let mut config = serde_json::json!({
+                "type": "stdio",
+                "command": server.command,
+                "args": server.args,
+                "env": env_config,
+            });
+            if !secrets.is_empty() {
+                config["secrets"] = secrets.into();
+            }
+            config
+        }
+        transport => serde_json::json!({
+            "type": transport,
+            "url": server.url,
+        }),
+    };
+    (config, placeholders)
+}
+
+/// Whether the registry entry is already configured, by name or definition
+fn mark_installed(servers: &mut [RegistryServer]) {
+    let Ok(configured) = configured_servers(None) else {
+        return;
+    };
+    let identities: Vec<String> = configured.values().filter_map(server_identity).collect();
+    for server in servers {
+        let (config, _) = server_config(server, &HashMap::new());
+        server.installed = configured.contains_key(&server.name)
+            || server_identity(&config).is_some_and(|id| identities.contains(&id));
+    }
+}
+
+/// Filters registry servers by a free-text query and a tag
+pub fn search_servers(
+    servers: Vec<RegistryServer>,
+    query: Option<&str>,
+    tag: Option<&str>,
+) -> Vec<RegistryServer> {
+    let query = query
+        .map(|q| q.trim().to_lowercase())
+        .filter(|q| !q.is_empty());
+    servers
+        .into_iter()
+        .filter(|server| tag.is_none_or(|tag| server.tags.iter().any(|t| t == tag)))
+        .filter(|server| {
+            query.as_ref().is_none_or(|query| {
+                [
+                    Some(&server.name),
+                    server.title.as_ref(),
+                    Some(&server.description),
+                ]
+                .into_iter()
+                .flatten()
+                .chain(&server.tags)
+                .any(|text| text.to_lowercase().contains(query.as_str()))
+            })
+        })
+        .collect()
+}
+
+/// Search the MCP server registry; with no query, lists every server
+#[tauri::command]
+pub async fn mcp_registry_search(
+    db: State<'_, AgentDb>,
+    query: Option<String>,
+    tag: Option<String>,
+    refresh: Option<bool>,
+) -> Result<Vec<RegistryServer>, String> {
+    let servers = registry_servers(&db, refresh.unwrap_or(false)).await?;
+    let mut servers = search_servers(servers, query.as_deref(), tag.as_deref());
+    mark_installed(&mut servers);
+    Ok(servers)
+}
+
+/// Install a registry server. Required variables missing from `env` are written
+/// as `${NAME}` placeholders that Claude fills in from the environment; secret
+/// ones that are provided are kept in the keychain.
+#[tauri::command]
+pub async fn mcp_registry_install(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    name: String,
+    scope: String,
+    env: Option<HashMap<String, String>>,
+) -> Result<RegistryInstallResult, String> {
+    info!("Installing MCP server {} from the registry", name);
+    let server = registry_servers(&db, false)
+        .await?
+        .into_iter()
+        .find(|server| server.name == name)
+        .ok_or_else(|| format!("Server '{}' is not in the registry", name))?;
+
+    let (config, env_placeholders) = server_config(&server, &env.unwrap_or_default());
+    let json_config = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+    let result = mcp_add_json(app, server.name, json_config, scope).await?;
+    Ok(RegistryInstallResult {
+        result,
+        env_placeholders,
+    })
+}
+
+/// Get the registry URL and signing key
+#[tauri::command]
+pub async fn get_mcp_registry_settings(
+    db: State<'_, AgentDb>,
+) -> Result<McpRegistrySettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+/// Point the registry browser at another registry
+#[tauri::command]
+pub async fn save_mcp_registry_settings(
+    db: State<'_, AgentDb>,
+    settings: McpRegistrySettings,
+) -> Result<(), String> {
+    if !is_https(&settings.url) {
+        return Err(format!("Registry URL must use https: {}", settings.url));
+    }
+    if let Some(public_key) = &settings.public_key {
+        minisign_verify::PublicKey::from_base64(public_key.trim())
+            .map_err(|e| format!("Invalid registry public key: {}", e))?;
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::mcp_secrets::take_config_secrets;
+
+    #[test]
+    fn test_registry_parsing_search_and_install_config() {
+        let body = include_str!("../../../cc_mcp/registry.json");
+        let servers = parse_registry(body).unwrap();
+        assert!(servers.len() >= 5);
+
+        let bad = serde_json::json!({"servers": [
+            {"name": "ok", "description": "", "transport": "stdio", "command": "npx"},
+            {"name": "shell", "description": "", "transport": "stdio", "command": "sh -c 'curl x | sh'"},
+            {"name": "path", "description": "", "transport": "stdio", "command": "/tmp/run"},
+            {"name": "plain", "description": "", "transport": "sse", "url": "http://example.com/sse"},
+            {"name": "../x", "description": "", "transport": "stdio", "command": "npx"},
+            {"name": "env", "description": "", "transport": "stdio", "command": "npx",
+             "env": [{"name": "BAD-NAME"}]},
+        ]});
+        let parsed = parse_registry(&bad.to_string()).unwrap();
+        assert_eq!(
+            parsed.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            vec!["ok"]
+        );
+
+        let found = search_servers(servers.clone(), Some("POSTGRES"), None);
+        assert_eq!(found.len(), 1);
+        assert!(search_servers(servers.clone(), None, Some("official"))
+            .iter()
+            .all(|s| s.tags.iter().any(|t| t == "official")));
+        assert!(search_servers(servers.clone(), Some("no such server"), None).is_empty());
+
+        let postgres = &found[0];
+        let (config, placeholders) = server_config(postgres, &HashMap::new());
+        assert_eq!(placeholders, vec!["DATABASE_URL".to_string()]);
+        assert_eq!(config["env"]["DATABASE_URL"], "${DATABASE_URL}");
+        let env = HashMap::from([("DATABASE_URL".to_string(), "postgres://db".to_string())]);
+        let (mut config, placeholders) = server_config(postgres, &env);
+        assert!(placeholders.is_empty());
+        assert_eq!(config["secrets"]["DATABASE_URL"], "postgres://db");
+        assert!(config["env"].get("DATABASE_URL").is_none());
+        let secrets = take_config_secrets(&postgres.name, &mut config)
+            .unwrap()
+            .unwrap();
+        assert_eq!(secrets["DATABASE_URL"], "postgres://db");
+        assert_eq!(
+            config["env"]["DATABASE_URL"],
+            "${secret:POSTGRES_DATABASE_URL}"
+        );
+        assert!(!config.to_string().contains("postgres://db"));
+
+        let remote = servers.iter().find(|s| s.transport == "http").unwrap();
+        let (config, _) = server_config(remote, &HashMap::new());
+        assert_eq!(config["type"], "http");
+        assert!(config.get("command").is_none());
+    }
+}
@@ -0,0 +1,626 @@
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use regex::{Captures, Regex};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::mcp::configured_servers;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::keychain;
+use crate::mcp_proxy;
+
+/// app_settings key holding the names of the secrets stored in the keychain
+const SECRET_NAMES_KEY: &str = "mcp_secret_names";
+
+/// app_settings key holding when each secret was stored and when it expires
+const SECRET_METADATA_KEY: &str = "mcp_secret_metadata";
+
+/// What the frontend sees in place of a secret's value
+pub const MASKED_VALUE: &str = "********";
+
+/// How long before its expiry a secret is due for rotation
+const EXPIRY_REMINDER: i64 = 14;
+
+/// Age after which a secret without an expiry is due for rotation
+const ROTATION_AGE: i64 = 180;
+
+/// app_settings key holding plain variables as a JSON object
+const VARIABLES_KEY: &str = "mcp_variables";
+
+/// Keychain account prefix of MCP secrets
+const KEYCHAIN_PREFIX: &str = "mcp-secret:";
+
+/// `${secret:NAME}` or `${var:NAME}`; plain `${NAME}` is left for Claude to expand
+fn template_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\$\{(secret|var):([A-Za-z_][A-Za-z0-9_]*)\}").unwrap())
+}
+
+/// When a secret was stored, and when it expires if the user knows
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SecretMetadata {
+    /// RFC 3339; None for secrets stored before this was recorded
+    pub updated_at: Option<String>,
+    /// RFC 3339 expiry hint, such as a token's expiration date
+    pub expires_at: Option<String>,
+}
+
+/// A secret as shown to the frontend, its value always masked
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpSecret {
+    pub name: String,
+    /// [`MASKED_VALUE`] when stored, empty when the secret is missing
+    pub value: String,
+    pub stored: bool,
+    pub updated_at: Option<String>,
+    pub expires_at: Option<String>,
+    /// Expires within two weeks, or hasn't been changed in half a year
+    pub rotation_due: bool,
+}
+
+/// A value of a server definition that references secrets
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpServerSecret {
+    /// `env.NAME`, `headers.NAME` or `args[N]`
+    pub field: String,
+    /// The value with every secret masked
+    pub value: String,
+    pub secrets: Vec<McpSecret>,
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid name '{}': use letters, digits and underscores",
+            name
+        ))
+    }
+}
+
+/// Whether a value references a secret or variable
+pub fn has_templates(value: &str) -> bool {
+    template_regex().is_match(value)
+}
+
+/// Whether a server definition references secrets or variables in its
+/// arguments or environment, so it must be launched through opcode
+pub fn config_has_templates(config: &JsonValue) -> bool {
+    let args = config.get("args").and_then(|v| v.as_array());
+    let env = config.get("env").and_then(|v| v.as_object());
+    args.into_iter()
+        .flatten()
+        .chain(env.into_iter().flat_map(|env| env.values()))
+        .filter_map(|value| value.as_str())
+        .any(has_templates)
+}
+
+/// A value with each secret reference replaced by [`MASKED_VALUE`]
+pub fn mask(value: &str) -> String {
+    template_regex()
+        .replace_all(value, |caps: &Captures| match &caps[1] {
+            "secret" => MASKED_VALUE.to_string(),
+            _ => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// A value with each secret or variable reference turned into a plain
+/// `${NAME}` environment reference, for configs opcode doesn't resolve
+pub fn env_references(value: &str) -> String {
+    template_regex()
+        .replace_all(value, |caps: &Captures| format!("${{{}}}", &caps[2]))
+        .into_owned()
+}
+
+/// Names of the secrets a value references
+fn referenced_secrets(value: &str) -> impl Iterator<Item = String> + '_ {
+    template_regex()
+        .captures_iter(value)
+        .filter(|caps| &caps[1] == "secret")
+        .map(|caps| caps[2].to_string())
+}
+
+/// Keychain name of a secret field of a server, e.g. `GITHUB_GITHUB_TOKEN`
+pub fn server_secret_name(server: &str, key: &str) -> String {
+    let name: String = format!("{}_{}", server, key)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Whether a secret should be rotated at `now`
+pub fn rotation_due(metadata: &SecretMetadata, now: DateTime<Utc>) -> bool {
+    let parse = |time: &Option<String>| {
+        time.as_deref()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&Utc))
+    };
+    match (parse(&metadata.expires_at), parse(&metadata.updated_at)) {
+        (Some(expires_at), _) => expires_at - now <= Duration::days(EXPIRY_REMINDER),
+        (None, Some(updated_at)) => now - updated_at >= Duration::days(ROTATION_AGE),
+        (None, None) => false,
+    }
+}
+
+/// Replace every reference in `value` using `lookup(kind, name)`; a reference
+/// that can't be resolved is an error rather than an empty string
+pub fn expand(
+    value: &str,
+    lookup: &mut impl FnMut(&str, &str) -> Result<Option<String>, String>,
+) -> Result<String, String> {
+    let mut error = None;
+    let expanded =
+        template_regex().replace_all(value, |caps: &Captures| match lookup(&caps[1], &caps[2]) {
+            Ok(Some(resolved)) => resolved,
+            Ok(None) => {
+                error.get_or_insert_with(|| format!("{} '{}' is not set", &caps[1], &caps[2]));
+                String::new()
+            }
+            Err(e) => {
+                error.get_or_insert(e);
+                String::new()
+            }
+        });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(expanded.into_owned()),
+    }
+}
+
+fn keychain_account(name: &str) -> String {
+    format!("{}{}", KEYCHAIN_PREFIX, name)
+}
+
+fn keychain_secret(name: &str) -> Result<Option<String>, String> {
+    keychain::get_secret(&keychain_account(name))
+        .map_err(|e| format!("Failed to read secret '{}': {}", name, e))
+}
+
+fn validate_secret(name: &str, expires_at: Option<&str>) -> Result<(), String> {
+    validate_name(name)?;
+    if let Some(expires_at) = expires_at {
+        DateTime::parse_from_rfc3339(expires_at)
+            .map_err(|e| format!("Invalid expiry '{}': {}", expires_at, e))?;
+    }
+    Ok(())
+}
+
+fn write_secret(name: &str, value: &str) -> Result<(), String> {
+    keychain::set_secret(&keychain_account(name), value)
+        .map_err(|e| format!("Failed to store secret '{}': {}", name, e))
+}
+
+fn remove_secret(name: &str) -> Result<(), String> {
+    keychain::delete_secret(&keychain_account(name))
+        .map_err(|e| format!("Failed to delete secret '{}': {}", name, e))
+}
+
+/// Record that a secret is stored in the keychain, and when
+fn record_secret(conn: &Connection, name: &str, expires_at: Option<String>) -> Result<(), String> {
+    let mut names: BTreeSet<String> = load_json_setting(conn, SECRET_NAMES_KEY);
+    names.insert(name.to_string());
+    save_json_setting(conn, SECRET_NAMES_KEY, &names)?;
+    let mut metadata: BTreeMap<String, SecretMetadata> =
+        load_json_setting(conn, SECRET_METADATA_KEY);
+    metadata.insert(
+        name.to_string(),
+        SecretMetadata {
+            updated_at: Some(Utc::now().to_rfc3339()),
+            expires_at,
+        },
+    );
+    save_json_setting(conn, SECRET_METADATA_KEY, &metadata)
+}
+
+/// Drop the record of a secret removed from the keychain
+fn forget_secret(conn: &Connection, name: &str) -> Result<(), String> {
+    let mut names: BTreeSet<String> = load_json_setting(conn, SECRET_NAMES_KEY);
+    names.remove(name);
+    save_json_setting(conn, SECRET_NAMES_KEY, &names)?;
+    let mut metadata: BTreeMap<String, SecretMetadata> =
+        load_json_setting(conn, SECRET_METADATA_KEY);
+    metadata.remove(name);
+    save_json_setting(conn, SECRET_METADATA_KEY, &metadata)
+}
+
+/// The environment entries referencing a server's secrets, configured in
+/// place of their values
+pub fn server_secret_references(
+    server: &str,
+    secrets: &BTreeMap<String, String>,
+) -> Vec<(String, String)> {
+    secrets
+        .keys()
+        .map(|key| {
+            (
+                key.clone(),
+                format!("${{secret:{}}}", server_secret_name(server, key)),
+            )
+        })
+        .collect()
+}
+
+/// Secrets of a server written to the keychain, with the values they
+/// replaced, so they can be put back when adding the server fails
+#[must_use]
+pub struct StoredSecrets {
+    replaced: Vec<(String, Option<String>)>,
+}
+
+impl StoredSecrets {
+    /// Restore the secrets that were replaced and remove the ones that are new
+    pub fn roll_back(self, db: &AgentDb) {
+        let mut removed = Vec::new();
+        for (name, previous) in self.replaced.into_iter().rev() {
+            let restored = match &previous {
+                Some(value) => write_secret(&name, value),
+                None => remove_secret(&name),
+            };
+            match restored {
+                Ok(()) if previous.is_none() => removed.push(name),
+                Ok(()) => {}
+                Err(e) => warn!("{}", e),
+            }
+        }
+        if let Ok(conn) = db.0.lock() {
+            for name in removed {
+                if let Err(e) = forget_secret(&conn, &name) {
+                    warn!("{}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Store the values of a server's secrets field in the keychain, under the
+/// names [`server_secret_references`] refers to. The keychain is written
+/// without holding the database lock.
+pub fn store_server_secrets(
+    db: &AgentDb,
+    server: &str,
+    secrets: &BTreeMap<String, String>,
+) -> Result<StoredSecrets, String> {
+    let mut stored = StoredSecrets {
+        replaced: Vec::new(),
+    };
+    for (key, value) in secrets {
+        let name = server_secret_name(server, key);
+        let written = validate_name(&name)
+            .and_then(|()| keychain_secret(&name))
+            .and_then(|previous| write_secret(&name, value).map(|()| previous));
+        match written {
+            Ok(previous) => stored.replaced.push((name, previous)),
+            Err(e) => {
+                stored.roll_back(db);
+                return Err(e);
+            }
+        }
+    }
+
+    let recorded = db.0.lock().map_err(|e| e.to_string()).and_then(|conn| {
+        stored
+            .replaced
+            .iter()
+            .try_for_each(|(name, _)| record_secret(&conn, name, None))
+    });
+    if let Err(e) = recorded {
+        stored.roll_back(db);
+        return Err(e);
+    }
+    info!("Stored {} secrets of MCP server {}", secrets.len(), server);
+    Ok(stored)
+}
+
+/// Take the `secrets` field out of a JSON server definition: environment
+/// variables whose values must not be written to Claude's config. They are
+/// replaced by environment entries referencing them; returns their values to
+/// store with [`store_server_secrets`], None when there is no such field.
+pub fn take_config_secrets(
+    server: &str,
+    config: &mut JsonValue,
+) -> Result<Option<BTreeMap<String, String>>, String> {
+    let Some(secrets) = config.as_object_mut().and_then(|c| c.remove("secrets")) else {
+        return Ok(None);
+    };
+    let secrets: BTreeMap<String, String> = serde_json::from_value(secrets)
+        .map_err(|_| "The secrets field must map variable names to strings".to_string())?;
+    if secrets.is_empty() {
+        return Ok(Some(secrets));
+    }
+    if config.get("command").is_none() {
+        return Err("Secrets are only supported for stdio servers".to_string());
+    }
+    if !config.get("env").is_some_and(|env| env.is_object()) {
+        config["env"] = serde_json::json!({});
+    }
+    for (key, reference) in server_secret_references(server, &secrets) {
+        config["env"][key] = reference.into();
+    }
+    Ok(Some(secrets))
+}
+
+fn secret_info(
+    name: &str,
+    names: &BTreeSet<String>,
+    metadata: &BTreeMap<String, SecretMetadata>,
+    now: DateTime<Utc>,
+) -> McpSecret {
+    let stored = names.contains(name);
+    let meta = metadata.get(name).cloned().unwrap_or_default();
+    McpSecret {
+        name: name.to_string(),
+        value: if stored { MASKED_VALUE } else { "" }.to_string(),
+        stored,
+        rotation_due: stored && rotation_due(&meta, now),
+        updated_at: meta.updated_at,
+        expires_at: meta.expires_at,
+    }
+}
+
+/// Variables from the desktop app's database, read from the proxy process
+fn stored_variables() -> Result<BTreeMap<String, String>, String> {
+    let path = dirs::data_dir()
+        .map(|dir| dir.join("opcode.asterisk.so").join("agents.db"))
+        .ok_or_else(|| "Could not determine the data directory".to_string())?;
+    let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    Ok(load_json_setting(&conn, VARIABLES_KEY))
+}
+
+/// Resolve references in each of `values` in place, reading the keychain and
+/// the stored variables only when something references them
+pub fn resolve_all(values: &mut [String]) -> Result<(), String> {
+    if !values.iter().any(|value| has_templates(value)) {
+        return Ok(());
+    }
+    let mut variables = None;
+    let mut lookup = |kind: &str, name: &str| match kind {
+        "secret" => keychain_secret(name),
+        _ => {
+            if variables.is_none() {
+                variables = Some(stored_variables()?);
+            }
+            Ok(variables.as_ref().and_then(|vars| vars.get(name).cloned()))
+        }
+    };
+    for value in values.iter_mut() {
+        *value = expand(value, &mut lookup)?;
+    }
+    Ok(())
+}
+
+/// Resolve references in a server's arguments and in the environment the
+/// proxy was started with. Arguments are rewritten in place; returns the
+/// environment variables to override for the server.
+pub fn resolve_for_spawn(args: &mut [String]) -> Result<Vec<(String, String)>, String> {
+    let (keys, mut values): (Vec<String>, Vec<String>) = std::env::vars()
+        .filter(|(_, value)| has_templates(value))
+        .unzip();
+    resolve_all(args)?;
+    resolve_all(&mut values)?;
+    Ok(keys.into_iter().zip(values).collect())
+}
+
+/// The secrets stored for MCP servers, masked; values never leave the
+/// keychain except to launch a server
+#[tauri::command]
+pub async fn list_mcp_secrets(db: State<'_, AgentDb>) -> Result<Vec<McpSecret>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let names: BTreeSet<String> = load_json_setting(&conn, SECRET_NAMES_KEY);
+    let metadata = load_json_setting(&conn, SECRET_METADATA_KEY);
+    let now = Utc::now();
+    Ok(names
+        .iter()
+        .map(|name| secret_info(name, &names, &metadata, now))
+        .collect())
+}
+
+/// Store a secret in the keychain for use as `${secret:NAME}`, optionally
+/// with the date it expires for rotation reminders
+#[tauri::command]
+pub async fn set_mcp_secret(
+    db: State<'_, AgentDb>,
+    name: String,
+    value: String,
+    expires_at: Option<String>,
+) -> Result<(), String> {
+    validate_secret(&name, expires_at.as_deref())?;
+    write_secret(&name, &value)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    record_secret(&conn, &name, expires_at)?;
+    info!("Stored MCP secret {}", name);
+    Ok(())
+}
+
+/// Remove a secret from the keychain
+#[tauri::command]
+pub async fn delete_mcp_secret(db: State<'_, AgentDb>, name: String) -> Result<(), String> {
+    remove_secret(&name)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    forget_secret(&conn, &name)
+}
+
+/// The values of a server's definition that reference secrets, masked, with
+/// each secret's rotation metadata
+#[tauri::command]
+pub async fn get_mcp_server_secrets(
+    db: State<'_, AgentDb>,
+    name: String,
+    project_path: Option<String>,
+) -> Result<Vec<McpServerSecret>, String> {
+    let servers = configured_servers(project_path.as_deref())?;
+    let config = servers
+        .get(&name)
+        .ok_or_else(|| format!("MCP server '{}' not found", name))?;
+    let config = mcp_proxy::unwrap(config).unwrap_or_else(|| config.clone());
+
+    let mut fields: Vec<(String, &str)> = Vec::new();
+    for (i, arg) in config["args"].as_array().into_iter().flatten().enumerate() {
+        fields.extend(arg.as_str().map(|arg| (format!("args[{}]", i), arg)));
+    }
+    for section in ["env", "headers"] {
+        for (key, value) in config[section].as_object().into_iter().flatten() {
+            fields.extend(
+                value
+                    .as_str()
+                    .map(|value| (format!("{}.{}", section, key), value)),
+            );
+        }
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let names: BTreeSet<String> = load_json_setting(&conn, SECRET_NAMES_KEY);
+    let metadata = load_json_setting(&conn, SECRET_METADATA_KEY);
+    let now = Utc::now();
+    Ok(fields
+        .into_iter()
+        .filter_map(|(field, value)| {
+            let secrets: Vec<McpSecret> = referenced_secrets(value)
+                .map(|secret| secret_info(&secret, &names, &metadata, now))
+                .collect();
+            (!secrets.is_empty()).then(|| McpServerSecret {
+                field,
+                value: mask(value),
+                secrets,
+            })
+        })
+        .collect())
+}
+
+/// Plain variables available to MCP servers as `${var:NAME}`
+#[tauri::command]
+pub async fn get_mcp_variables(db: State<'_, AgentDb>) -> Result<BTreeMap<String, String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_json_setting(&conn, VARIABLES_KEY))
+}
+
+/// Replace the plain variables available to MCP servers
+#[tauri::command]
+pub async fn save_mcp_variables(
+    db: State<'_, AgentDb>,
+    variables: BTreeMap<String, String>,
+) -> Result<(), String> {
+    for name in variables.keys() {
+        validate_name(name)?;
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, VARIABLES_KEY, &variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_expansion() {
+        let mut lookup = |kind: &str, name: &str| {
+            Ok(match (kind, name) {
+                ("secret", "GITHUB_TOKEN") => Some("ghp_123".to_string()),
+                ("var", "DB_HOST") => Some("localhost".to_string()),
+                _ => None,
+            })
+        };
+        assert_eq!(
+            expand("Bearer ${secret:GITHUB_TOKEN}", &mut lookup).unwrap(),
+            "Bearer ghp_123"
+        );
+        assert_eq!(
+            expand("postgres://${var:DB_HOST}/${DB_NAME}", &mut lookup).unwrap(),
+            "postgres://localhost/${DB_NAME}"
+        );
+        assert_eq!(
+            expand("${secret:MISSING}", &mut lookup).unwrap_err(),
+            "secret 'MISSING' is not set"
+        );
+
+        assert!(config_has_templates(&serde_json::json!({
+            "command": "npx",
+            "env": {"GITHUB_TOKEN": "${secret:GITHUB_TOKEN}"},
+        })));
+        assert!(config_has_templates(&serde_json::json!({
+            "command": "npx",
+            "args": ["server-postgres", "postgres://${var:DB_HOST}/app"],
+        })));
+        assert!(!config_has_templates(&serde_json::json!({
+            "command": "npx",
+            "env": {"TOKEN": "${TOKEN}"},
+        })));
+
+        assert!(validate_name("GITHUB_TOKEN").is_ok());
+        assert!(validate_name("1TOKEN").is_err());
+        assert!(validate_name("A-B").is_err());
+    }
+
+    #[test]
+    fn test_masking_and_rotation() {
+        assert_eq!(
+            mask("Bearer ${secret:TOKEN} for ${var:TEAM}"),
+            "Bearer ******** for ${var:TEAM}"
+        );
+        assert_eq!(
+            referenced_secrets("${secret:A}:${var:B}:${secret:C}").collect::<Vec<_>>(),
+            ["A", "C"]
+        );
+        assert_eq!(
+            server_secret_name("github", "GITHUB_TOKEN"),
+            "GITHUB_GITHUB_TOKEN"
+        );
+        assert_eq!(server_secret_name("2fa.io", "key"), "_2FA_IO_KEY");
+
+        let now = Utc::now();
+        let metadata = |updated_days_ago: i64, expires_in_days: Option<i64>| SecretMetadata {
+            updated_at: Some((now - Duration::days(updated_days_ago)).to_rfc3339()),
+            expires_at: expires_in_days.map(|days| (now + Duration::days(days)).to_rfc3339()),
+        };
+        assert!(!rotation_due(&metadata(10, None), now));
+        assert!(rotation_due(&metadata(200, None), now));
+        assert!(rotation_due(&metadata(10, Some(7)), now));
+        assert!(rotation_due(&metadata(10, Some(-1)), now));
+        assert!(!rotation_due(&metadata(200, Some(60)), now));
+        assert!(!rotation_due(&SecretMetadata::default(), now));
+
+        let mut config = serde_json::json!({
+            "command": "npx",
+            "env": {"DEBUG": "1"},
+            "secrets": {"API_KEY": "sk-123"},
+        });
+        let secrets = take_config_secrets("my-api", &mut config).unwrap().unwrap();
+        assert_eq!(secrets["API_KEY"], "sk-123");
+        assert_eq!(
+            config,
+            serde_json::json!({
+                "command": "npx",
+                "env": {"DEBUG": "1", "API_KEY": "${secret:MY_API_API_KEY}"},
+            })
+        );
+        assert!(take_config_secrets("my-api", &mut config)
+            .unwrap()
+            .is_none());
+
+        let names = BTreeSet::from(["TOKEN".to_string()]);
+        let stored = secret_info("TOKEN", &names, &BTreeMap::new(), now);
+        assert_eq!((stored.value.as_str(), stored.stored), (MASKED_VALUE, true));
+        let missing = secret_info("OTHER", &names, &BTreeMap::new(), now);
+        assert_eq!((missing.value.as_str(), missing.stored), ("", false));
+    }
+}
@@ -0,0 +1,348 @@
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::mcp::{claude_config_path, read_json_config};
+
+/// Snapshots kept; older ones are deleted as new ones are taken
+const KEPT_SNAPSHOTS: usize = 50;
+
+/// The MCP servers in Claude's config files at one point in time
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct McpConfigSnapshot {
+    /// Sortable id, derived from the time the snapshot was taken
+    pub id: String,
+    pub created_at: String,
+    /// The change the snapshot was taken before
+    pub reason: String,
+    /// User-scoped servers from `~/.claude.json`
+    pub user: Map<String, JsonValue>,
+    /// Local-scoped servers from `~/.claude.json`, by project path
+    pub local: BTreeMap<String, Map<String, JsonValue>>,
+    /// `.mcp.json` servers of the projects the change touched, by project
+    /// path; empty when the project had no `.mcp.json`
+    pub project: BTreeMap<String, Map<String, JsonValue>>,
+}
+
+/// One server that differs between two snapshots
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpServerChange {
+    /// "user", "local" or "project"
+    pub scope: String,
+    pub project_path: Option<String>,
+    pub name: String,
+    /// "added", "removed" or "changed"
+    pub change: String,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+fn snapshots_dir() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|dir| dir.join("opcode.asterisk.so").join("mcp-snapshots"))
+        .ok_or_else(|| "Could not determine the data directory".to_string())
+}
+
+fn project_config_path(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path).join(".mcp.json")
+}
+
+fn servers_of(config: &JsonValue) -> Map<String, JsonValue> {
+    config
+        .get("mcpServers")
+        .and_then(|v| v.as_object())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// The MCP servers configured right now, including the `.mcp.json` of each
+/// of `project_paths`
+fn current_config(project_paths: &[&str]) -> Result<McpConfigSnapshot, String> {
+    let claude_config = read_json_config(&claude_config_path()?)?;
+    let local = claude_config
+        .get("projects")
+        .and_then(|v| v.as_object())
+        .into_iter()
+        .flatten()
+        .map(|(path, project)| (path.clone(), servers_of(project)))
+        .filter(|(_, servers)| !servers.is_empty())
+        .collect();
+    let mut project = BTreeMap::new();
+    for path in project_paths {
+        let config = read_json_config(&project_config_path(path))?;
+        project.insert(path.to_string(), servers_of(&config));
+    }
+    Ok(McpConfigSnapshot {
+        user: servers_of(&claude_config),
+        local,
+        project,
+        ..Default::default()
+    })
+}
+
+fn save(mut snapshot: McpConfigSnapshot, dir: &Path) -> Result<McpConfigSnapshot, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let now = Utc::now();
+    let base = now.format("%Y%m%dT%H%M%S%.3fZ").to_string();
+    snapshot.id = base.clone();
+    let mut n = 1;
+    while dir.join(format!("{}.json", snapshot.id)).exists() {
+        n += 1;
+        snapshot.id = format!("{}-{:03}", base, n);
+    }
+    snapshot.created_at = now.to_rfc3339();
+
+    let json = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("{}.json", snapshot.id));
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    let ids = list_ids(dir);
+    for old in ids.iter().take(ids.len().saturating_sub(KEPT_SNAPSHOTS)) {
+        let _ = fs::remove_file(dir.join(format!("{}.json", old)));
+    }
+    Ok(snapshot)
+}
+
+/// Snapshot ids in a directory, oldest first
+fn list_ids(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut ids: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_suffix(".json").map(str::to_string)
+        })
+        .collect();
+    ids.sort();
+    ids
+}
+
+fn load(id: &str) -> Result<McpConfigSnapshot, String> {
+    if id.contains(['/', '\\']) || id.starts_with('.') {
+        return Err(format!("Invalid snapshot id '{}'", id));
+    }
+    let path = snapshots_dir()?.join(format!("{}.json", id));
+    let json = fs::read_to_string(&path).map_err(|_| format!("Snapshot '{}' not found", id))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse snapshot '{}': {}", id, e))
+}
+
+/// Record the MCP config before a change to it. A snapshot that can't be
+/// taken is logged and doesn't stop the change.
+pub fn record_snapshot(reason: &str, project_paths: &[&str]) {
+    let saved = current_config(project_paths).and_then(|snapshot| {
+        let snapshot = McpConfigSnapshot {
+            reason: reason.to_string(),
+            ..snapshot
+        };
+        save(snapshot, &snapshots_dir()?)
+    });
+    if let Err(e) = saved {
+        warn!("Failed to snapshot MCP config before {}: {}", reason, e);
+    }
+}
+
+/// Servers of a snapshot keyed by scope, project and name
+fn flatten(snapshot: &McpConfigSnapshot) -> BTreeMap<(String, Option<String>, String), &JsonValue> {
+    let mut servers = BTreeMap::new();
+    for (name, config) in &snapshot.user {
+        servers.insert(("user".to_string(), None, name.clone()), config);
+    }
+    for (scope, projects) in [("local", &snapshot.local), ("project", &snapshot.project)] {
+        for (path, project_servers) in projects {
+            for (name, config) in project_servers {
+                servers.insert(
+                    (scope.to_string(), Some(path.clone()), name.clone()),
+                    config,
+                );
+            }
+        }
+    }
+    servers
+}
+
+/// The servers added, removed or changed from `before` to `after`. A
+/// project's `.mcp.json` only counts when both snapshots recorded it.
+pub fn diff_snapshots(
+    before: &McpConfigSnapshot,
+    after: &McpConfigSnapshot,
+) -> Vec<McpServerChange> {
+    let shared_projects: BTreeSet<&String> = before
+        .project
+        .keys()
+        .filter(|path| after.project.contains_key(*path))
+        .collect();
+    let compared = |key: &(String, Option<String>, String)| {
+        key.0 != "project" || key.1.as_ref().is_some_and(|p| shared_projects.contains(p))
+    };
+    let (old, new) = (flatten(before), flatten(after));
+    let keys: BTreeSet<_> = old
+        .keys()
+        .chain(new.keys())
+        .filter(|k| compared(k))
+        .collect();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let (was, is) = (old.get(key), new.get(key));
+            let change = match (was, is) {
+                (None, Some(_)) => "added",
+                (Some(_), None) => "removed",
+                (Some(was), Some(is)) if was != is => "changed",
+                _ => return None,
+            };
+            Some(McpServerChange {
+                scope: key.0.clone(),
+                project_path: key.1.clone(),
+                name: key.2.clone(),
+                change: change.to_string(),
+                before: was.map(|config| (*config).clone()),
+                after: is.map(|config| (*config).clone()),
+            })
+        })
+        .collect()
+}
+
+/// Write a snapshot's servers back into Claude's config files, leaving every
+/// other setting in them alone
+fn restore(snapshot: &McpConfigSnapshot) -> Result<(), String> {
+    let write = |path: &Path, config: &JsonValue| {
+        let content = serde_json::to_string_pretty(config)
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    };
+
+    let claude_path = claude_config_path()?;
+    let mut claude_config = read_json_config(&claude_path)?;
+    if !claude_config.is_object() {
+        return Err(format!("{} is not a JSON object", claude_path.display()));
+    }
+    claude_config["mcpServers"] = JsonValue::Object(snapshot.user.clone());
+    if let Some(projects) = claude_config
+        .get_mut("projects")
+        .and_then(|v| v.as_object_mut())
+    {
+        for (path, project) in projects.iter_mut() {
+            let servers = snapshot.local.get(path).cloned().unwrap_or_default();
+            if project.get("mcpServers").is_some() || !servers.is_empty() {
+                project["mcpServers"] = JsonValue::Object(servers);
+            }
+        }
+    }
+    write(&claude_path, &claude_config)?;
+
+    for (project_path, servers) in &snapshot.project {
+        let path = project_config_path(project_path);
+        if !path.exists() && servers.is_empty() {
+            continue;
+        }
+        let mut config = read_json_config(&path)?;
+        if !config.is_object() {
+            return Err(format!("{} is not a JSON object", path.display()));
+        }
+        config["mcpServers"] = JsonValue::Object(servers.clone());
+        write(&path, &config)?;
+    }
+    Ok(())
+}
+
+/// Snapshots of the MCP config, newest first
+#[tauri::command]
+pub async fn list_mcp_snapshots() -> Result<Vec<McpConfigSnapshot>, String> {
+    let dir = snapshots_dir()?;
+    let mut snapshots = Vec::new();
+    for id in list_ids(&dir).into_iter().rev() {
+        match load(&id) {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => warn!("Skipping MCP snapshot: {}", e),
+        }
+    }
+    Ok(snapshots)
+}
+
+/// What changed from one snapshot to another, or to the current config when
+/// `to` is None
+#[tauri::command]
+pub async fn diff_mcp_snapshots(
+    from: String,
+    to: Option<String>,
+) -> Result<Vec<McpServerChange>, String> {
+    let before = load(&from)?;
+    let after = match to {
+        Some(to) => load(&to)?,
+        None => {
+            let projects: Vec<&str> = before.project.keys().map(String::as_str).collect();
+            current_config(&projects)?
+        }
+    };
+    Ok(diff_snapshots(&before, &after))
+}
+
+/// Put the MCP config back the way it was in a snapshot. The config is
+/// snapshotted first, so the rollback can itself be undone.
+#[tauri::command]
+pub async fn rollback_mcp_snapshot(id: String) -> Result<McpConfigSnapshot, String> {
+    let snapshot = load(&id)?;
+    let projects: Vec<&str> = snapshot.project.keys().map(String::as_str).collect();
+    let current = McpConfigSnapshot {
+        reason: format!("rollback to {}", id),
+        ..current_config(&projects)?
+    };
+    let saved = save(current, &snapshots_dir()?)?;
+    restore(&snapshot)?;
+    info!("Rolled MCP config back to snapshot {}", id);
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_diff_and_pruning() {
+        let servers = |entries: &[(&str, &str)]| -> Map<String, JsonValue> {
+            entries
+                .iter()
+                .map(|(name, command)| (name.to_string(), serde_json::json!({"command": command})))
+                .collect()
+        };
+        let before = McpConfigSnapshot {
+            user: servers(&[("memory", "npx"), ("github", "gh-mcp")]),
+            local: BTreeMap::from([("/repo".to_string(), servers(&[("db", "pg")]))]),
+            project: BTreeMap::from([("/repo".to_string(), servers(&[("lint", "eslint")]))]),
+            ..Default::default()
+        };
+        let after = McpConfigSnapshot {
+            user: servers(&[("memory", "uvx"), ("fetch", "fetch")]),
+            local: BTreeMap::new(),
+            project: BTreeMap::new(),
+            ..Default::default()
+        };
+
+        let changes: Vec<(String, String, String)> = diff_snapshots(&before, &after)
+            .into_iter()
+            .map(|c| (c.scope, c.name, c.change))
+            .collect();
+        let expected = [
+            ("local", "db", "removed"),
+            ("user", "fetch", "added"),
+            ("user", "github", "removed"),
+            ("user", "memory", "changed"),
+        ]
+        .map(|(scope, name, change)| (scope.to_string(), name.to_string(), change.to_string()));
+        assert_eq!(changes, expected);
+        assert!(diff_snapshots(&before, &before).is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        let ids: Vec<String> = (0..KEPT_SNAPSHOTS + 2)
+            .map(|_| save(before.clone(), dir.path()).unwrap().id)
+            .collect();
+        assert_eq!(list_ids(dir.path()), ids[2..].to_vec());
+        assert!(load("../agents").is_err());
+    }
+}
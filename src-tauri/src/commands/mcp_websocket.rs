@@ -0,0 +1,67 @@
+use log::info;
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+use crate::commands::mcp::{configured_servers, mcp_add_json, AddServerResult};
+use crate::mcp_proxy;
+use crate::mcp_ws_bridge::{self, WebSocketHealth};
+
+/// Add an MCP server reached over WebSocket. Claude launches it as a stdio
+/// server bridged by opcode, through the proxy so it gets logs and is
+/// reconnected when the connection drops. Header values may reference
+/// `${secret:NAME}` and `${var:NAME}`; they are resolved once here to catch
+/// missing secrets, and again each time the bridge connects.
+#[tauri::command]
+pub async fn mcp_add_websocket_server(
+    app: AppHandle,
+    name: String,
+    url: String,
+    headers: BTreeMap<String, String>,
+    scope: String,
+) -> Result<AddServerResult, String> {
+    info!("Adding WebSocket MCP server {} at {}", name, url);
+    if scope == "project" {
+        return Err(
+            "WebSocket servers are bridged by opcode, so they can't be added to the project's .mcp.json"
+                .to_string(),
+        );
+    }
+    mcp_ws_bridge::validate_url(&url)?;
+    for (header, value) in &headers {
+        mcp_ws_bridge::validate_header(header, value)?;
+    }
+    mcp_ws_bridge::resolve_headers(headers.clone().into_iter().collect())?;
+
+    let executable = mcp_proxy::proxy_executable()?;
+    let config = mcp_ws_bridge::server_config(&executable, &url, &headers);
+    let config = mcp_proxy::wrap(&name, &config, &executable).unwrap_or(config);
+    let json_config = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+    mcp_add_json(app, name, json_config, scope).await
+}
+
+/// Connect to a configured WebSocket server and initialize a session
+#[tauri::command]
+pub async fn check_mcp_websocket_health(
+    name: String,
+    project_path: Option<String>,
+) -> Result<WebSocketHealth, String> {
+    let servers = configured_servers(project_path.as_deref())?;
+    let config = servers
+        .get(&name)
+        .ok_or_else(|| format!("MCP server '{}' not found", name))?;
+    let (url, headers) = mcp_ws_bridge::endpoint(config)
+        .ok_or_else(|| format!("MCP server '{}' is not a WebSocket server", name))?;
+    let headers = mcp_ws_bridge::resolve_headers(headers)?;
+
+    let health = mcp_ws_bridge::check_health(&url, &headers).await;
+    info!(
+        "WebSocket MCP server {} is {}",
+        name,
+        if health.healthy {
+            "healthy"
+        } else {
+            "unhealthy"
+        }
+    );
+    Ok(health)
+}
@@ -0,0 +1,441 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Names of files Claude reads as memory
+const MEMORY_FILE_NAMES: &[&str] = &["CLAUDE.md", "CLAUDE.local.md"];
+
+/// How many directories below the project root are searched for memory files
+const MAX_SUBDIRECTORY_DEPTH: usize = 8;
+
+/// Where a memory file sits in Claude's memory hierarchy
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryScope {
+    /// Organization-wide policy file
+    Enterprise,
+    /// `~/.claude/CLAUDE.md`, applying to every project
+    User,
+    /// A CLAUDE.md in a directory above the project
+    Parent,
+    /// The project's own CLAUDE.md or .claude/CLAUDE.md
+    Project,
+    /// The project's CLAUDE.local.md, which isn't meant to be committed
+    ProjectLocal,
+    /// A CLAUDE.md below the project root, read when Claude works in that directory
+    Subdirectory,
+}
+
+/// A memory file that applies to a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryFile {
+    pub path: String,
+    pub scope: MemoryScope,
+    /// Files with a higher precedence are read later and win over earlier ones
+    pub precedence: usize,
+    pub exists: bool,
+    /// Whether Claude reads the file at startup rather than on demand
+    pub loaded_at_startup: bool,
+    /// Ids of the sections managed by opcode
+    pub sections: Vec<String>,
+}
+
+/// A block of a memory file managed by opcode, kept between anchor comments so it
+/// can be rewritten without touching anything edited by hand
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemorySection {
+    pub id: String,
+    pub content: String,
+}
+
+fn begin_anchor(id: &str) -> String {
+    format!("<!-- opcode:begin {} -->", id)
+}
+
+fn end_anchor(id: &str) -> String {
+    format!("<!-- opcode:end {} -->", id)
+}
+
+/// Id of the section a line opens, if it is a begin anchor
+fn parse_begin(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("<!-- opcode:begin ")?
+        .strip_suffix("-->")
+        .map(str::trim)
+}
+
+fn validate_section_id(id: &str) -> Result<(), String> {
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid section id '{}'", id))
+    }
+}
+
+/// Line range of each managed section as (id, begin line, end line)
+fn section_ranges(content: &str) -> Vec<(String, usize, usize)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some(id) = parse_begin(lines[i]) {
+            let end = end_anchor(id);
+            // A section without its end anchor isn't treated as managed
+            if let Some(offset) = lines[i + 1..].iter().position(|l| l.trim() == end) {
+                ranges.push((id.to_string(), i, i + 1 + offset));
+                i += offset + 2;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    ranges
+}
+
+/// The managed sections of a memory file
+pub fn parse_sections(content: &str) -> Vec<MemorySection> {
+    let lines: Vec<&str> = content.lines().collect();
+    section_ranges(content)
+        .into_iter()
+        .map(|(id, begin, end)| MemorySection {
+            id,
+            content: lines[begin + 1..end].join("\n"),
+        })
+        .collect()
+}
+
+/// Replace a section's content, or append the section if the file doesn't have it yet.
+/// Everything outside the section is left exactly as it was.
+pub fn upsert_section(content: &str, id: &str, body: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let body = body.trim_end_matches('\n');
+
+    match section_ranges(content)
+        .into_iter()
+        .find(|(s, _, _)| s == id)
+    {
+        Some((_, begin, end)) => {
+            let mut updated: Vec<&str> = lines[..=begin].to_vec();
+            updated.extend(body.lines());
+            updated.extend(&lines[end..]);
+            let mut updated = updated.join("\n");
+            if content.ends_with('\n') {
+                updated.push('\n');
+            }
+            updated
+        }
+        None => {
+            let mut updated = content.trim_end_matches('\n').to_string();
+            if !updated.is_empty() {
+                updated.push_str("\n\n");
+            }
+            updated.push_str(&begin_anchor(id));
+            updated.push('\n');
+            if !body.is_empty() {
+                updated.push_str(body);
+                updated.push('\n');
+            }
+            updated.push_str(&end_anchor(id));
+            updated.push('\n');
+            updated
+        }
+    }
+}
+
+/// Remove a section with its anchors, if the file has it
+pub fn remove_section(content: &str, id: &str) -> Option<String> {
+    let (_, begin, end) = section_ranges(content)
+        .into_iter()
+        .find(|(s, _, _)| s == id)?;
+    let lines: Vec<&str> = content.lines().collect();
+    let mut updated: Vec<&str> = lines[..begin].to_vec();
+    // Drop the blank line that separated the section from what came before
+    if updated.last().is_some_and(|l| l.trim().is_empty())
+        && lines.get(end + 1).is_none_or(|l| l.trim().is_empty())
+    {
+        updated.pop();
+    }
+    updated.extend(&lines[end + 1..]);
+    let mut updated = updated.join("\n");
+    if !updated.is_empty() && content.ends_with('\n') {
+        updated.push('\n');
+    }
+    Some(updated)
+}
+
+/// Only memory files may be edited through these commands
+fn memory_file_path(file_path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(file_path);
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if !MEMORY_FILE_NAMES
+        .iter()
+        .any(|m| m.eq_ignore_ascii_case(name))
+    {
+        return Err(format!("{} is not a CLAUDE.md memory file", file_path));
+    }
+    Ok(path)
+}
+
+fn read_memory_file(path: &Path) -> Result<String, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+fn write_memory_file(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create parent directory: {}", e))?;
+    }
+    std::fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Organization-wide memory file managed by IT
+fn enterprise_memory_path() -> PathBuf {
+    if cfg!(target_os = "macos") {
+        PathBuf::from("/Library/Application Support/ClaudeCode/CLAUDE.md")
+    } else if cfg!(windows) {
+        PathBuf::from(r"C:\ProgramData\ClaudeCode\CLAUDE.md")
+    } else {
+        PathBuf::from("/etc/claude-code/CLAUDE.md")
+    }
+}
+
+/// Memory files below the project root, skipping hidden and build directories.
+/// Symlinks aren't followed, so a link back up the tree can't loop.
+fn find_subdirectory_memory(root: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(root)
+        .follow_links(false)
+        .max_depth(MAX_SUBDIRECTORY_DEPTH)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !(name.starts_with('.')
+                    || matches!(name.as_ref(), "node_modules" | "target" | "dist"))
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.depth() > 1
+                && entry.file_type().is_file()
+                && entry
+                    .file_name()
+                    .to_string_lossy()
+                    .eq_ignore_ascii_case("CLAUDE.md")
+        })
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Memory files that apply to a project, lowest precedence first
+pub fn memory_files_for_project(project_path: &Path, home: Option<&Path>) -> Vec<MemoryFile> {
+    let mut candidates: Vec<(PathBuf, MemoryScope, bool)> =
+        vec![(enterprise_memory_path(), MemoryScope::Enterprise, true)];
+    if let Some(home) = home {
+        candidates.push((
+            home.join(".claude").join("CLAUDE.md"),
+            MemoryScope::User,
+            true,
+        ));
+    }
+
+    // Claude reads CLAUDE.md files from the filesystem root down to the project
+    let mut ancestors: Vec<&Path> = project_path.ancestors().skip(1).collect();
+    ancestors.reverse();
+    for dir in ancestors {
+        for name in MEMORY_FILE_NAMES {
+            let path = dir.join(name);
+            if path.is_file() {
+                candidates.push((path, MemoryScope::Parent, true));
+            }
+        }
+    }
+
+    candidates.push((project_path.join("CLAUDE.md"), MemoryScope::Project, true));
+    let dot_claude = project_path.join(".claude").join("CLAUDE.md");
+    if dot_claude.is_file() {
+        candidates.push((dot_claude, MemoryScope::Project, true));
+    }
+    let local = project_path.join("CLAUDE.local.md");
+    if local.is_file() {
+        candidates.push((local, MemoryScope::ProjectLocal, true));
+    }
+
+    let mut subdirectories = find_subdirectory_memory(project_path);
+    subdirectories.sort();
+    candidates.extend(
+        subdirectories
+            .into_iter()
+            .map(|path| (path, MemoryScope::Subdirectory, false)),
+    );
+
+    candidates
+        .into_iter()
+        // Missing files are only listed where the user may want to create them
+        .filter(|(path, scope, _)| {
+            path.is_file() || matches!(scope, MemoryScope::User | MemoryScope::Project)
+        })
+        .enumerate()
+        .map(|(precedence, (path, scope, loaded_at_startup))| {
+            let content = std::fs::read_to_string(&path).unwrap_or_default();
+            MemoryFile {
+                exists: path.is_file(),
+                path: path.to_string_lossy().to_string(),
+                scope,
+                precedence,
+                loaded_at_startup,
+                sections: parse_sections(&content).into_iter().map(|s| s.id).collect(),
+            }
+        })
+        .collect()
+}
+
+/// List the CLAUDE.md memory files that apply to a project, from the lowest
+/// precedence to the highest. The user and project files are listed even if they
+/// don't exist yet.
+#[tauri::command]
+pub async fn list_memory_files(project_path: String) -> Result<Vec<MemoryFile>, String> {
+    let project = PathBuf::from(&project_path);
+    if !project.is_dir() {
+        return Err(format!("Project directory not found: {}", project_path));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        memory_files_for_project(&project, dirs::home_dir().as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Get the sections opcode manages in a memory file
+#[tauri::command]
+pub async fn get_memory_sections(file_path: String) -> Result<Vec<MemorySection>, String> {
+    let path = memory_file_path(&file_path)?;
+    Ok(parse_sections(&read_memory_file(&path)?))
+}
+
+/// Write a managed section of a memory file, creating the file or section if needed.
+/// Returns the file's new content.
+#[tauri::command]
+pub async fn save_memory_section(
+    file_path: String,
+    section_id: String,
+    content: String,
+) -> Result<String, String> {
+    validate_section_id(&section_id)?;
+    let path = memory_file_path(&file_path)?;
+    // Re-read right before writing so edits made since the UI loaded the file survive
+    let updated = upsert_section(&read_memory_file(&path)?, &section_id, &content);
+    write_memory_file(&path, &updated)?;
+    log::info!("Saved section {} of {}", section_id, file_path);
+    Ok(updated)
+}
+
+/// Merge several managed sections into a memory file at once
+#[tauri::command]
+pub async fn merge_memory_sections(
+    file_path: String,
+    sections: Vec<MemorySection>,
+) -> Result<String, String> {
+    for section in &sections {
+        validate_section_id(&section.id)?;
+    }
+    let path = memory_file_path(&file_path)?;
+    let updated = sections
+        .iter()
+        .fold(read_memory_file(&path)?, |content, section| {
+            upsert_section(&content, &section.id, &section.content)
+        });
+    write_memory_file(&path, &updated)?;
+    Ok(updated)
+}
+
+/// Remove a managed section from a memory file. Returns the file's new content.
+#[tauri::command]
+pub async fn remove_memory_section(
+    file_path: String,
+    section_id: String,
+) -> Result<String, String> {
+    let path = memory_file_path(&file_path)?;
+    let content = read_memory_file(&path)?;
+    let updated = remove_section(&content, &section_id)
+        .ok_or_else(|| format!("Section {} not found in {}", section_id, file_path))?;
+    write_memory_file(&path, &updated)?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections_preserve_manual_edits() {
+        let manual = "# Project notes\n\nUse pnpm, not npm.\n";
+        let with_section = upsert_section(manual, "commands", "- Test: pnpm test");
+        assert_eq!(
+            with_section,
+            "# Project notes\n\nUse pnpm, not npm.\n\n<!-- opcode:begin commands -->\n- Test: pnpm test\n<!-- opcode:end commands -->\n"
+        );
+
+        // Hand edits around the section survive rewriting it
+        let edited = with_section.replace("Use pnpm", "Always use pnpm") + "\nKeep PRs small.\n";
+        let rewritten = upsert_section(&edited, "commands", "- Test: pnpm test\n- Lint: pnpm lint");
+        assert!(rewritten.contains("Always use pnpm"));
+        assert!(rewritten.ends_with("<!-- opcode:end commands -->\n\nKeep PRs small.\n"));
+        assert_eq!(
+            parse_sections(&rewritten),
+            vec![MemorySection {
+                id: "commands".to_string(),
+                content: "- Test: pnpm test\n- Lint: pnpm lint".to_string()
+            }]
+        );
+
+        let removed = remove_section(&rewritten, "commands").unwrap();
+        assert_eq!(
+            removed,
+            "# Project notes\n\nAlways use pnpm, not npm.\n\nKeep PRs small.\n"
+        );
+        assert_eq!(remove_section(&removed, "commands"), None);
+
+        // An unterminated anchor isn't a managed section
+        assert!(parse_sections("<!-- opcode:begin broken -->\ntext\n").is_empty());
+        assert!(validate_section_id("a b").is_err());
+    }
+
+    #[test]
+    fn test_memory_files_for_project() {
+        let home = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("app");
+        std::fs::create_dir_all(project.join("src/api")).unwrap();
+        std::fs::write(root.path().join("CLAUDE.md"), "parent").unwrap();
+        std::fs::write(project.join("CLAUDE.local.md"), "local").unwrap();
+        std::fs::write(project.join("src/api/CLAUDE.md"), "api").unwrap();
+        // Dependencies and links back up the tree aren't searched
+        std::fs::create_dir_all(project.join("node_modules/dep")).unwrap();
+        std::fs::write(project.join("node_modules/dep/CLAUDE.md"), "dep").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&project, project.join("src/loop")).unwrap();
+
+        let files: Vec<(MemoryScope, bool)> = memory_files_for_project(&project, Some(home.path()))
+            .into_iter()
+            .filter(|f| f.scope != MemoryScope::Enterprise)
+            .map(|f| (f.scope, f.exists))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (MemoryScope::User, false),
+                (MemoryScope::Parent, true),
+                (MemoryScope::Project, false),
+                (MemoryScope::ProjectLocal, true),
+                (MemoryScope::Subdirectory, true),
+            ]
+        );
+    }
+}
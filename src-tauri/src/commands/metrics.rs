@@ -0,0 +1,286 @@
+use log::{info, warn};
+use rusqlite::{params, Connection, ToSql};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::budgets::RunUsageTracker;
+use crate::commands::run_search::session_file;
+
+/// Number of weekly buckets returned when none is requested
+const DEFAULT_TREND_WEEKS: u32 = 12;
+
+/// Run duration in milliseconds, from the process start to completion.
+/// completed_at only has second precision, so sub-second runs are clamped to zero.
+const DURATION_MS_SQL: &str =
+    "MAX((julianday(completed_at) - julianday(process_started_at)) * 86400000.0, 0)";
+
+/// Statuses of runs that finished on their own (cancelled and interrupted runs are left out)
+const FINISHED_STATUSES_SQL: &str = "('completed', 'failed', 'budget_exceeded', 'limit_exceeded')";
+
+/// Aggregated run metrics for one agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentMetrics {
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub total_runs: i64,
+    pub completed_runs: i64,
+    pub failed_runs: i64,
+    /// Completed runs as a share of finished runs (0.0 - 1.0); None if none finished
+    pub success_rate: Option<f64>,
+    pub avg_duration_ms: Option<f64>,
+    pub total_cost_usd: f64,
+    pub total_tokens: i64,
+}
+
+/// Run metrics for one week
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsBucket {
+    /// Monday the week starts on (YYYY-MM-DD)
+    pub week_start: String,
+    pub total_runs: i64,
+    pub completed_runs: i64,
+    pub failed_runs: i64,
+    pub success_rate: Option<f64>,
+    pub avg_duration_ms: Option<f64>,
+    pub total_cost_usd: f64,
+    pub total_tokens: i64,
+}
+
+/// Store a finished run's token and cost totals so metrics can be aggregated in SQL
+pub fn store_run_usage(conn: &Connection, run_id: i64, usage: &RunUsageTracker) {
+    if let Err(e) = conn.execute(
+        "UPDATE agent_runs SET total_tokens = ?1, cost_usd = ?2 WHERE id = ?3",
+        params![usage.total_tokens() as i64, usage.cost_usd(), run_id],
+    ) {
+        warn!("Failed to store usage of agent run {}: {}", run_id, e);
+    }
+}
+
+/// Record a finished run's usage from its stream-json output.
+pub fn record_run_usage(db_path: &Path, run_id: i64, output: &str) {
+    match Connection::open(db_path) {
+        Ok(conn) => store_run_usage(&conn, run_id, &RunUsageTracker::from_jsonl(output)),
+        Err(e) => warn!("Failed to open database to record run usage: {}", e),
+    }
+}
+
+/// Record usage for finished runs from before run metrics existed, from their session files
+pub fn backfill_run_usage(db_path: &Path) {
+    let conn = match Connection::open(db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to open database to backfill run usage: {}", e);
+            return;
+        }
+    };
+
+    let runs: Vec<(i64, String, String)> = conn
+        .prepare(&format!(
+            "SELECT id, project_path, session_id FROM agent_runs
+             WHERE cost_usd IS NULL AND status IN {}",
+            FINISHED_STATUSES_SQL
+        ))
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if runs.is_empty() {
+        return;
+    }
+
+    for (run_id, project_path, session_id) in &runs {
+        let usage = session_file(project_path, session_id)
+            .filter(|_| !session_id.is_empty())
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|jsonl| RunUsageTracker::from_jsonl(&jsonl))
+            .unwrap_or_default();
+        store_run_usage(&conn, *run_id, &usage);
+    }
+    info!("Recorded usage of {} agent runs for metrics", runs.len());
+}
+
+/// Columns shared by the per-agent and per-week queries
+fn aggregate_columns() -> String {
+    format!(
+        "COUNT(*),
+         SUM(status = 'completed'),
+         SUM(status IN ('failed', 'budget_exceeded', 'limit_exceeded')),
+         AVG(CASE WHEN status IN {finished} AND process_started_at IS NOT NULL AND completed_at IS NOT NULL THEN {duration} END),
+         COALESCE(SUM(cost_usd), 0),
+         COALESCE(SUM(total_tokens), 0)",
+        finished = FINISHED_STATUSES_SQL,
+        duration = DURATION_MS_SQL,
+    )
+}
+
+/// Completed runs as a share of finished runs, None if none finished
+pub fn success_rate(completed: i64, failed: i64) -> Option<f64> {
+    let finished = completed + failed;
+    (finished > 0).then(|| completed as f64 / finished as f64)
+}
+
+/// Build the WHERE clause for the optional agent and date filters
+fn run_filters(
+    agent_id: Option<i64>,
+    from: Option<String>,
+    to: Option<String>,
+) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut sql = String::from("WHERE 1 = 1");
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+    if let Some(agent_id) = agent_id {
+        sql.push_str(" AND agent_id = ?");
+        values.push(Box::new(agent_id));
+    }
+    if let Some(from) = from {
+        sql.push_str(" AND date(created_at) >= date(?)");
+        values.push(Box::new(from));
+    }
+    if let Some(to) = to {
+        sql.push_str(" AND date(created_at) <= date(?)");
+        values.push(Box::new(to));
+    }
+    (sql, values)
+}
+
+/// Aggregate run metrics per agent, optionally for one agent and a date range
+#[tauri::command]
+pub async fn get_agent_metrics(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<AgentMetrics>, String> {
+    let (filters, values) = run_filters(agent_id, from, to);
+    let sql = format!(
+        "SELECT agent_id, MAX(agent_name), {} FROM agent_runs {} GROUP BY agent_id ORDER BY COUNT(*) DESC",
+        aggregate_columns(),
+        filters
+    );
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let metrics = stmt
+        .query_map(
+            rusqlite::params_from_iter(values.iter().map(|v| v.as_ref())),
+            |row| {
+                let completed_runs: i64 = row.get(3)?;
+                let failed_runs: i64 = row.get(4)?;
+                Ok(AgentMetrics {
+                    agent_id: row.get(0)?,
+                    agent_name: row.get(1)?,
+                    total_runs: row.get(2)?,
+                    completed_runs,
+                    failed_runs,
+                    success_rate: success_rate(completed_runs, failed_runs),
+                    avg_duration_ms: row.get(5)?,
+                    total_cost_usd: row.get(6)?,
+                    total_tokens: row.get(7)?,
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(metrics)
+}
+
+/// Weekly run metrics for the last `weeks` weeks, oldest first, optionally for one agent
+#[tauri::command]
+pub async fn get_agent_metrics_trend(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+    weeks: Option<u32>,
+) -> Result<Vec<MetricsBucket>, String> {
+    let weeks = weeks.unwrap_or(DEFAULT_TREND_WEEKS).max(1);
+    // Start from the Monday of the earliest week so it is counted in full
+    let from = format!("-{} days", (weeks - 1) * 7);
+    let (filters, mut values) = run_filters(agent_id, None, None);
+    values.push(Box::new(from));
+
+    let sql = format!(
+        "SELECT date(created_at, 'weekday 0', '-6 days') AS week_start, {}
+         FROM agent_runs {} AND date(created_at) >= date('now', 'weekday 0', '-6 days', ?)
+         GROUP BY week_start ORDER BY week_start",
+        aggregate_columns(),
+        filters
+    );
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let buckets = stmt
+        .query_map(
+            rusqlite::params_from_iter(values.iter().map(|v| v.as_ref())),
+            |row| {
+                let completed_runs: i64 = row.get(2)?;
+                let failed_runs: i64 = row.get(3)?;
+                Ok(MetricsBucket {
+                    week_start: row.get(0)?,
+                    total_runs: row.get(1)?,
+                    completed_runs,
+                    failed_runs,
+                    success_rate: success_rate(completed_runs, failed_runs),
+                    avg_duration_ms: row.get(4)?,
+                    total_cost_usd: row.get(5)?,
+                    total_tokens: row.get(6)?,
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(buckets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE agent_runs (agent_id INTEGER, status TEXT, process_started_at TEXT, completed_at TEXT, cost_usd REAL, total_tokens INTEGER)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO agent_runs VALUES
+                (1, 'completed', '2026-01-05T10:00:00.250+00:00', '2026-01-05 10:00:10', 0.5, 1000),
+                (1, 'failed', '2026-01-05T11:00:00+00:00', '2026-01-05 11:00:30', 0.25, 500),
+                (1, 'cancelled', '2026-01-05T12:00:00+00:00', '2026-01-05 12:00:01', NULL, NULL)",
+            [],
+        )
+        .unwrap();
+
+        let (total, completed, failed, avg_ms, cost, tokens): (i64, i64, i64, f64, f64, i64) = conn
+            .query_row(
+                &format!("SELECT {} FROM agent_runs", aggregate_columns()),
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                    ))
+                },
+            )
+            .unwrap();
+
+        assert_eq!((total, completed, failed), (3, 1, 1));
+        assert!((avg_ms - 19875.0).abs() < 1.0);
+        assert!((cost - 0.75).abs() < 1e-9);
+        assert_eq!(tokens, 1500);
+        assert_eq!(success_rate(completed, failed), Some(0.5));
+        assert_eq!(success_rate(0, 0), None);
+    }
+}
@@ -1,8 +1,83 @@
+pub mod agent_env;
+pub mod agent_parameters;
+pub mod agent_revisions;
+pub mod agent_variants;
 pub mod agents;
+pub mod artifacts;
+pub mod attachments;
+pub mod batches;
+pub mod budgets;
+pub mod burn_rate;
+pub mod cancellation;
+pub mod checkpoint_encryption;
+pub mod checkpoint_retention;
+pub mod checkpoint_validation;
 pub mod claude;
+pub mod comparisons;
+pub mod cost_anomalies;
+pub mod cost_budgets;
+pub mod crash_restarts;
+pub mod destructive_checkpoints;
+pub mod editors;
+pub mod git_hooks;
+pub mod issue_links;
 pub mod mcp;
+pub mod mcp_environments;
+pub mod mcp_inspector;
+pub mod mcp_logs;
+pub mod mcp_metrics;
+pub mod mcp_project_servers;
+pub mod mcp_registry;
+pub mod mcp_secrets;
+pub mod mcp_snapshots;
+pub mod mcp_websocket;
+pub mod memory_files;
+pub mod metrics;
+pub mod notification_actions;
+pub mod notification_channels;
+pub mod notifications;
+pub mod permissions;
+pub mod pricing;
+pub mod processes;
+pub mod project_bundles;
+pub mod project_discovery;
+pub mod project_stats;
+pub mod prompt_templates;
 pub mod proxy;
+pub mod pty_mode;
+pub mod queue;
+pub mod rate_limits;
+pub mod redaction;
+pub mod retries;
+pub mod run_diffs;
+pub mod run_limits;
+pub mod run_logs;
+pub mod run_pull_requests;
+pub mod run_recovery;
+pub mod run_search;
+pub mod schedules;
+pub mod session_archive;
+pub mod session_diffs;
+pub mod session_forks;
+pub mod session_reattach;
+pub mod session_search;
+pub mod session_sharing;
+pub mod session_tabs;
+pub mod session_titles;
+pub mod settings;
+pub mod shadow_runs;
 pub mod shell;
 pub mod slash_commands;
+pub mod stall_detection;
 pub mod storage;
+pub mod stream_batching;
+pub mod stream_filters;
+pub mod structured_output;
 pub mod usage;
+pub mod usage_imports;
+pub mod usage_index;
+pub mod usage_retention;
+pub mod watches;
+pub mod webhooks;
+pub mod workspaces;
+pub mod worktrees;
@@ -0,0 +1,586 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter, Manager, State};
+#[cfg(not(target_os = "linux"))]
+use tauri_plugin_notification::NotificationExt;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::process::ProcessRegistryState;
+
+/// app_settings key holding the desktop notification settings as JSON
+const SETTINGS_KEY: &str = "desktop_notifications";
+
+/// Most notifications kept around for their actions
+const MAX_PENDING_NOTIFICATIONS: usize = 50;
+
+/// Notifications whose actions haven't been taken or dismissed yet, oldest first
+static PENDING: OnceLock<Mutex<VecDeque<ActionableNotification>>> = OnceLock::new();
+
+/// What a desktop notification is about
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    SessionFinished,
+    SessionFailed,
+    RunSucceeded,
+    RunFailed,
+    /// A run was denied a permission it asked for, or stalled waiting for input
+    RunNeedsInput,
+}
+
+/// Which desktop notifications are shown. Runs with a desktop notification
+/// rule of their own always get it; the run settings cover the others.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DesktopNotificationSettings {
+    pub session_finished: bool,
+    pub session_failed: bool,
+    pub run_succeeded: bool,
+    pub run_failed: bool,
+    pub run_needs_input: bool,
+    /// Stay quiet while the app window has focus
+    pub only_when_unfocused: bool,
+}
+
+impl Default for DesktopNotificationSettings {
+    fn default() -> Self {
+        Self {
+            session_finished: true,
+            session_failed: true,
+            run_succeeded: false,
+            run_failed: false,
+            run_needs_input: false,
+            only_when_unfocused: true,
+        }
+    }
+}
+
+impl DesktopNotificationSettings {
+    pub fn enabled(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::SessionFinished => self.session_finished,
+            NotificationKind::SessionFailed => self.session_failed,
+            NotificationKind::RunSucceeded => self.run_succeeded,
+            NotificationKind::RunFailed => self.run_failed,
+            NotificationKind::RunNeedsInput => self.run_needs_input,
+        }
+    }
+}
+
+/// Something the user can do from a notification
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationAction {
+    OpenSession {
+        session_id: String,
+        project_path: String,
+    },
+    OpenRun {
+        run_id: i64,
+    },
+    ViewDiff {
+        run_id: i64,
+    },
+    /// Allow the tools a run was denied and continue its session
+    Approve {
+        run_id: i64,
+        tools: Vec<String>,
+    },
+}
+
+impl NotificationAction {
+    /// Button label for the action
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::OpenSession { .. } => "Open session",
+            Self::OpenRun { .. } => "Open run",
+            Self::ViewDiff { .. } => "View diff",
+            Self::Approve { .. } => "Approve",
+        }
+    }
+}
+
+/// A notification shown to the user, with the actions it offers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionableNotification {
+    pub id: String,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub actions: Vec<NotificationAction>,
+    pub created_at: String,
+}
+
+pub fn load_notification_settings(conn: &Connection) -> DesktopNotificationSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// Host of an http(s) URL
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+/// Permission rules granting what a run was denied, from the `permission_denials`
+/// of its result
+pub fn denied_tool_rules(output: &str) -> Vec<String> {
+    let mut rules: Vec<String> = Vec::new();
+    let denials = output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+        .filter(|json| json.get("type").and_then(|t| t.as_str()) == Some("result"))
+        .filter_map(|json| {
+            json.get("permission_denials")
+                .and_then(|d| d.as_array())
+                .cloned()
+        })
+        .flatten();
+    for denial in denials {
+        let Some(tool) = denial.get("tool_name").and_then(|t| t.as_str()) else {
+            continue;
+        };
+        let input = denial.get("tool_input");
+        let text = |field: &str| input.and_then(|i| i.get(field)).and_then(|v| v.as_str());
+        let rule = match (tool, text("command"), text("url")) {
+            ("Bash", Some(command), _) => format!("Bash({})", command.trim()),
+            ("WebFetch", _, Some(url)) => match url_host(url) {
+                Some(host) => format!("WebFetch(domain:{})", host),
+                None => tool.to_string(),
+            },
+            _ => tool.to_string(),
+        };
+        if !rules.contains(&rule) {
+            rules.push(rule);
+        }
+    }
+    rules
+}
+
+/// Split a comma-separated tool list into its rules, keeping commas inside a
+/// rule's parentheses such as `Bash(git commit -m "a, b")`
+fn split_tool_rules(list: &str) -> Vec<&str> {
+    let mut rules = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                rules.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    rules.push(list[start..].trim());
+    rules.retain(|rule| !rule.is_empty());
+    rules
+}
+
+/// Whether an approved rule is held back by a denied one: the same rule, or
+/// the whole tool it is scoped to
+fn denies(denied: &str, approved: &str) -> bool {
+    denied == approved || approved.split('(').next() == Some(denied)
+}
+
+/// Add the tools approved for a run to the rules its Claude process is allowed,
+/// and drop the denied rules that would still block them
+pub fn grant_approved_tools(args: &mut Vec<String>, tools: &[String]) {
+    if tools.is_empty() {
+        return;
+    }
+    // Without an allow list the run is unrestricted already
+    let Some(index) = args.iter().position(|arg| arg == "--allowedTools") else {
+        return;
+    };
+    let Some(allowed) = args.get_mut(index + 1) else {
+        return;
+    };
+    let mut rules: Vec<String> = split_tool_rules(allowed)
+        .into_iter()
+        .map(String::from)
+        .collect();
+    for tool in tools {
+        if !rules.contains(tool) {
+            rules.push(tool.clone());
+        }
+    }
+    *allowed = rules.join(",");
+
+    let Some(index) = args.iter().position(|arg| arg == "--disallowedTools") else {
+        return;
+    };
+    let Some(disallowed) = args.get(index + 1) else {
+        return;
+    };
+    let remaining: Vec<&str> = split_tool_rules(disallowed)
+        .into_iter()
+        .filter(|denied| !tools.iter().any(|tool| denies(denied, tool)))
+        .collect();
+    if remaining.is_empty() {
+        args.drain(index..index + 2);
+    } else {
+        args[index + 1] = remaining.join(",");
+    }
+}
+
+/// Tools approved for a run from a notification
+pub fn load_approved_tools(conn: &Connection, run_id: i64) -> Vec<String> {
+    conn.query_row(
+        "SELECT approved_tools FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .ok()
+    .flatten()
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn pending() -> &'static Mutex<VecDeque<ActionableNotification>> {
+    PENDING.get_or_init(Default::default)
+}
+
+fn main_window_focused(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+/// Show a notification with its actions as buttons. Clicking the notification
+/// itself takes its first action.
+#[cfg(target_os = "linux")]
+fn show_desktop_notification(app: &AppHandle, notification: &ActionableNotification) {
+    let mut desktop = notify_rust::Notification::new();
+    desktop
+        .appname(&app.package_info().name)
+        .summary(&notification.title)
+        .body(&notification.body);
+    for (index, action) in notification.actions.iter().enumerate() {
+        desktop.action(&index.to_string(), action.label());
+    }
+    let app = app.clone();
+    let notification_id = notification.id.clone();
+    // Waiting for the user blocks until the notification is acted on or closed
+    std::thread::spawn(move || match desktop.show() {
+        Ok(handle) => handle.wait_for_action(|action| {
+            let action_index = match action {
+                "default" => 0,
+                action => match action.parse::<usize>() {
+                    Ok(index) => index,
+                    Err(_) => return,
+                },
+            };
+            tauri::async_runtime::spawn(async move {
+                let db = app.state::<AgentDb>();
+                let registry = app.state::<ProcessRegistryState>();
+                if let Err(e) = perform_notification_action(
+                    app.clone(),
+                    db,
+                    registry,
+                    notification_id,
+                    action_index,
+                )
+                .await
+                {
+                    warn!("Failed to take notification action: {}", e);
+                }
+            });
+        }),
+        Err(e) => warn!("Failed to show desktop notification: {}", e),
+    });
+}
+
+/// Show a notification; its actions are offered in the app, as the
+/// notification plugin has no buttons on this platform
+#[cfg(not(target_os = "linux"))]
+fn show_desktop_notification(app: &AppHandle, notification: &ActionableNotification) {
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(&notification.title)
+        .body(&notification.body)
+        .show()
+    {
+        warn!("Failed to show desktop notification: {}", e);
+    }
+}
+
+/// Show a desktop notification and keep it with its actions so the app can
+/// offer them. The app is told through a `notification-posted` event.
+///
+/// With `settings` the notification is only shown if its kind is enabled;
+/// without them, as for a notification rule of the run's own, it always is.
+pub fn post_notification(
+    app: &AppHandle,
+    settings: Option<&DesktopNotificationSettings>,
+    kind: NotificationKind,
+    title: String,
+    body: String,
+    actions: Vec<NotificationAction>,
+) {
+    if let Some(settings) = settings {
+        if !settings.enabled(kind) || (settings.only_when_unfocused && main_window_focused(app)) {
+            return;
+        }
+    }
+
+    let notification = ActionableNotification {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        title,
+        body,
+        actions,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    show_desktop_notification(app, &notification);
+
+    let _ = app.emit("notification-posted", &notification);
+    if let Ok(mut pending) = pending().lock() {
+        if pending.len() >= MAX_PENDING_NOTIFICATIONS {
+            pending.pop_front();
+        }
+        pending.push_back(notification);
+    }
+}
+
+/// Notify that an interactive session's Claude process exited
+pub fn notify_session_finished(
+    app: &AppHandle,
+    session_id: &str,
+    project_path: &str,
+    success: bool,
+) {
+    let settings = match app.try_state::<AgentDb>() {
+        Some(db) => match db.0.lock() {
+            Ok(conn) => load_notification_settings(&conn),
+            Err(_) => return,
+        },
+        None => DesktopNotificationSettings::default(),
+    };
+    let project = std::path::Path::new(project_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| project_path.to_string());
+    let (kind, title) = if success {
+        (NotificationKind::SessionFinished, "Claude finished")
+    } else {
+        (
+            NotificationKind::SessionFailed,
+            "Claude stopped with an error",
+        )
+    };
+    post_notification(
+        app,
+        Some(&settings),
+        kind,
+        title.to_string(),
+        format!("Session in {}", project),
+        vec![NotificationAction::OpenSession {
+            session_id: session_id.to_string(),
+            project_path: project_path.to_string(),
+        }],
+    );
+}
+
+/// Allow a run the tools it was denied and continue its session with them
+async fn approve_run(
+    app: &AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+    tools: &[String],
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let (status, session_id): (String, String) = conn
+            .query_row(
+                "SELECT status, session_id FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| format!("Run {} not found: {}", run_id, e))?;
+        if matches!(status.as_str(), "running" | "queued") {
+            return Err(format!("Run {} is already going", run_id));
+        }
+        if session_id.is_empty() {
+            return Err(format!("Run {} has no Claude session to continue", run_id));
+        }
+
+        let mut approved = load_approved_tools(&conn, run_id);
+        for tool in tools {
+            if !approved.contains(tool) {
+                approved.push(tool.clone());
+            }
+        }
+        let approved = serde_json::to_string(&approved).map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE agent_runs SET approved_tools = ?1, status = 'queued', resume_pending = 1, completed_at = NULL,
+                 queue_position = (SELECT COALESCE(MAX(queue_position), 0) + 1 FROM agent_runs)
+             WHERE id = ?2",
+            params![approved, run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    info!("Approved {} for agent run {}", tools.join(", "), run_id);
+
+    let launched = crate::commands::queue::dispatch_queued_runs(app, db, registry).await?;
+    if let Some((_, Err(e))) = launched.into_iter().find(|(id, _)| *id == run_id) {
+        return Err(e);
+    }
+    let _ = app.emit("agent-queue-updated", true);
+    Ok(())
+}
+
+/// List notifications whose actions are still on offer, oldest first
+#[tauri::command]
+pub async fn list_pending_notifications() -> Result<Vec<ActionableNotification>, String> {
+    let pending = pending().lock().map_err(|e| e.to_string())?;
+    Ok(pending.iter().cloned().collect())
+}
+
+/// Forget a notification without taking any of its actions
+#[tauri::command]
+pub async fn dismiss_notification(notification_id: String) -> Result<(), String> {
+    let mut pending = pending().lock().map_err(|e| e.to_string())?;
+    pending.retain(|n| n.id != notification_id);
+    Ok(())
+}
+
+/// Take one of a notification's actions. Approving resumes the run here;
+/// anything else brings the app to the front and is handed to it as a
+/// `notification-action` event to navigate.
+#[tauri::command]
+pub async fn perform_notification_action(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    notification_id: String,
+    action_index: usize,
+) -> Result<(), String> {
+    let action = {
+        let mut pending = pending().lock().map_err(|e| e.to_string())?;
+        let index = pending
+            .iter()
+            .position(|n| n.id == notification_id)
+            .ok_or_else(|| format!("Notification {} not found", notification_id))?;
+        let action = pending[index]
+            .actions
+            .get(action_index)
+            .cloned()
+            .ok_or_else(|| format!("Notification has no action {}", action_index))?;
+        pending.remove(index);
+        action
+    };
+
+    if let NotificationAction::Approve { run_id, tools } = &action {
+        return approve_run(&app, db, registry, *run_id, tools).await;
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    app.emit("notification-action", &action)
+        .map_err(|e| e.to_string())
+}
+
+/// Get which desktop notifications are shown
+#[tauri::command]
+pub async fn get_notification_settings(
+    db: State<'_, AgentDb>,
+) -> Result<DesktopNotificationSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_notification_settings(&conn))
+}
+
+/// Set which desktop notifications are shown
+#[tauri::command]
+pub async fn save_notification_settings(
+    db: State<'_, AgentDb>,
+    settings: DesktopNotificationSettings,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denied_tools_are_granted() {
+        let result = serde_json::json!({
+            "type": "result",
+            "permission_denials": [
+                {"tool_name": "Bash", "tool_input": {"command": "npm publish"}},
+                {"tool_name": "WebFetch", "tool_input": {"url": "https://docs.rs/serde"}},
+                {"tool_name": "Write", "tool_input": {"file_path": "/tmp/a"}},
+                {"tool_name": "Bash", "tool_input": {"command": "npm publish"}},
+            ],
+        });
+        let output = format!(
+            "{}\n{}",
+            r#"{"type":"assistant","message":{"content":[]}}"#, result
+        );
+        let tools = denied_tool_rules(&output);
+        assert_eq!(
+            tools,
+            vec!["Bash(npm publish)", "WebFetch(domain:docs.rs)", "Write"]
+        );
+
+        let mut args = vec![
+            "-p".to_string(),
+            "task".to_string(),
+            "--allowedTools".to_string(),
+            "Task,Write".to_string(),
+        ];
+        grant_approved_tools(&mut args, &tools);
+        assert_eq!(
+            args[3],
+            "Task,Write,Bash(npm publish),WebFetch(domain:docs.rs)"
+        );
+
+        // Commas inside a rule don't split it, and denied rules that would
+        // still block an approved one are dropped
+        let mut args = vec![
+            "--allowedTools".to_string(),
+            "Task,Bash(git commit -m \"a, b\")".to_string(),
+            "--disallowedTools".to_string(),
+            "WebFetch,WebSearch".to_string(),
+        ];
+        grant_approved_tools(
+            &mut args,
+            &[
+                "Bash(git commit -m \"a, b\")".to_string(),
+                "WebFetch(domain:docs.rs)".to_string(),
+            ],
+        );
+        assert_eq!(
+            args,
+            vec![
+                "--allowedTools",
+                "Task,Bash(git commit -m \"a, b\"),WebFetch(domain:docs.rs)",
+                "--disallowedTools",
+                "WebSearch",
+            ]
+        );
+        grant_approved_tools(&mut args, &["WebSearch".to_string()]);
+        assert_eq!(args.len(), 2);
+
+        let mut unrestricted = vec!["--dangerously-skip-permissions".to_string()];
+        grant_approved_tools(&mut unrestricted, &tools);
+        assert_eq!(unrestricted.len(), 1);
+    }
+}
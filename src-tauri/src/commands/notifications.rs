@@ -0,0 +1,573 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+use crate::commands::agent_parameters::render_prompt;
+use crate::commands::agents::AgentDb;
+use crate::commands::budgets::RunUsageTracker;
+use crate::commands::comparisons::run_duration_ms;
+use crate::commands::notification_actions::{
+    denied_tool_rules, load_notification_settings, post_notification, NotificationAction,
+    NotificationKind,
+};
+use crate::commands::notification_channels::notify_channels;
+use crate::commands::permissions::load_permission_profile;
+use crate::commands::retries::RetryCondition;
+
+/// Longest final-message excerpt included in a notification
+const MAX_EXCERPT_CHARS: usize = 280;
+
+/// Timeout for notification webhook requests
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// Default desktop notification body
+const DEFAULT_MESSAGE_TEMPLATE: &str =
+    "Run #{{run_id}} {{status}} in {{duration_secs}}s (${{cost_usd}})\n{{excerpt}}";
+
+/// How a finished run ended, as far as notifications are concerned
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunEvent {
+    Success,
+    Failure,
+    /// The run stalled waiting for input or was denied a permission it asked for
+    NeedsInput,
+}
+
+impl RunEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunEvent::Success => "success",
+            RunEvent::Failure => "failure",
+            RunEvent::NeedsInput => "needs_input",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "success" => Some(RunEvent::Success),
+            "failure" => Some(RunEvent::Failure),
+            "needs_input" => Some(RunEvent::NeedsInput),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            RunEvent::Success => "finished",
+            RunEvent::Failure => "failed",
+            RunEvent::NeedsInput => "needs input",
+        }
+    }
+}
+
+/// Where a notification is delivered
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Desktop,
+    Webhook,
+    Both,
+}
+
+impl NotificationChannel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            NotificationChannel::Desktop => "desktop",
+            NotificationChannel::Webhook => "webhook",
+            NotificationChannel::Both => "both",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "desktop" => Some(NotificationChannel::Desktop),
+            "webhook" => Some(NotificationChannel::Webhook),
+            "both" => Some(NotificationChannel::Both),
+            _ => None,
+        }
+    }
+}
+
+/// What to send when an agent's run ends with a given event.
+///
+/// Templates use `{{run_id}}`, `{{agent}}`, `{{event}}`, `{{status}}`, `{{project}}`,
+/// `{{task}}`, `{{duration_secs}}`, `{{cost_usd}}`, `{{failure_reason}}` and `{{excerpt}}`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NotificationRule {
+    pub event: RunEvent,
+    pub channel: NotificationChannel,
+    pub webhook_url: Option<String>,
+    /// Desktop notification body
+    pub message_template: Option<String>,
+    /// Webhook body; values are JSON-escaped, so placeholders can sit inside JSON strings.
+    /// Without one a JSON object with every value is posted.
+    pub payload_template: Option<String>,
+}
+
+/// Classify a finished run; None for runs that were cancelled or are still going
+pub fn run_event(status: &str, failure_reason: Option<&str>, output: &str) -> Option<RunEvent> {
+    match status {
+        // The startup timeout fires when Claude sits waiting for input
+        "failed" if failure_reason == Some(RetryCondition::Timeout.as_str()) => {
+            Some(RunEvent::NeedsInput)
+        }
+        "failed" | "budget_exceeded" | "limit_exceeded" => Some(RunEvent::Failure),
+        "completed" if has_permission_denials(output) => Some(RunEvent::NeedsInput),
+        "completed" => Some(RunEvent::Success),
+        _ => None,
+    }
+}
+
+fn has_permission_denials(output: &str) -> bool {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+        .filter(|json| json.get("type").and_then(|t| t.as_str()) == Some("result"))
+        .any(|json| {
+            json.get("permission_denials")
+                .and_then(|d| d.as_array())
+                .is_some_and(|d| !d.is_empty())
+        })
+}
+
+/// Texts of the run's messages in order: each assistant reply and the final result
+pub fn message_texts(output: &str) -> Vec<String> {
+    let mut texts = Vec::new();
+    for json in output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+    {
+        match json.get("type").and_then(|t| t.as_str()) {
+            Some("result") => {
+                if let Some(result) = json.get("result").and_then(|r| r.as_str()) {
+                    texts.push(result.to_string());
+                }
+            }
+            Some("assistant") => {
+                let text: Vec<&str> = json
+                    .get("message")
+                    .and_then(|m| m.get("content"))
+                    .and_then(|c| c.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+                    .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                    .collect();
+                if !text.is_empty() {
+                    texts.push(text.join("\n"));
+                }
+            }
+            _ => {}
+        }
+    }
+    texts
+}
+
+/// The start of the run's final message: its result, or else the last assistant text
+pub fn final_message_excerpt(output: &str) -> String {
+    let text = message_texts(output).pop().unwrap_or_default();
+    let text = text.trim();
+    match text.char_indices().nth(MAX_EXCERPT_CHARS) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+/// Escape a value for use inside a JSON string literal
+fn json_escape(value: &str) -> String {
+    let quoted = JsonValue::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// Render a webhook payload template, JSON-escaping the substituted values
+pub fn render_payload(template: &str, values: &HashMap<String, String>) -> String {
+    let escaped = values
+        .iter()
+        .map(|(key, value)| (key.clone(), json_escape(value)))
+        .collect();
+    render_prompt(template, &escaped)
+}
+
+/// Load an agent's notification rules
+pub fn load_notification_rules(
+    conn: &Connection,
+    agent_id: i64,
+) -> Result<Vec<NotificationRule>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT event, channel, webhook_url, message_template, payload_template
+             FROM agent_notification_rules WHERE agent_id = ?1 ORDER BY event",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![agent_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(
+            |(event, channel, webhook_url, message_template, payload_template)| {
+                Some(NotificationRule {
+                    event: RunEvent::parse(&event)?,
+                    channel: NotificationChannel::parse(&channel)?,
+                    webhook_url,
+                    message_template,
+                    payload_template,
+                })
+            },
+        )
+        .collect())
+}
+
+/// Scheme and host of a URL, for logging a webhook without the token in its path
+pub fn url_origin(url: &str) -> String {
+    let url = url.trim();
+    let rest_start = url.find("://").map_or(0, |i| i + 3);
+    let end = url[rest_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |i| rest_start + i);
+    let origin = &url[..end];
+    // Credentials before the host are as secret as the path
+    match origin[rest_start..].rsplit_once('@') {
+        Some((_, host)) => format!("{}{}", &origin[..rest_start], host),
+        None => origin.to_string(),
+    }
+}
+
+/// Post a notification body to a URL in the background
+pub fn send_webhook(url: String, body: String) {
+    tauri::async_runtime::spawn(async move {
+        let is_json = serde_json::from_str::<JsonValue>(&body).is_ok();
+        let client = match reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("Failed to create notification webhook client: {}", e);
+                return;
+            }
+        };
+
+        let content_type = if is_json {
+            "application/json"
+        } else {
+            "text/plain; charset=utf-8"
+        };
+        match client
+            .post(&url)
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .await
+        {
+            Ok(response) if !response.status().is_success() => {
+                warn!(
+                    "Notification webhook {} returned {}",
+                    url_origin(&url),
+                    response.status()
+                );
+            }
+            Ok(_) => {}
+            Err(e) => warn!(
+                "Failed to call notification webhook {}: {}",
+                url_origin(&url),
+                e.without_url()
+            ),
+        }
+    });
+}
+
+/// Send the notifications configured for how a finished run ended.
+pub fn notify_run_finished(app: &AppHandle, db_path: &Path, run_id: i64, output: &str) {
+    let conn = match Connection::open(db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to open database to send run notifications: {}", e);
+            return;
+        }
+    };
+
+    let run = conn.query_row(
+        "SELECT agent_id, agent_name, task, project_path, status, failure_reason, process_started_at, completed_at,
+                COALESCE(changed_files, '[]') != '[]', session_id != ''
+         FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, Option<String>>(7)?,
+                row.get::<_, bool>(8)?,
+                row.get::<_, bool>(9)?,
+            ))
+        },
+    );
+    let (
+        agent_id,
+        agent_name,
+        task,
+        project,
+        status,
+        failure_reason,
+        started_at,
+        completed_at,
+        has_changes,
+        has_session,
+    ) = match run {
+        Ok(run) => run,
+        Err(e) => {
+            warn!("Failed to load run {} for notifications: {}", run_id, e);
+            return;
+        }
+    };
+
+    let Some(event) = run_event(&status, failure_reason.as_deref(), output) else {
+        return;
+    };
+    let rules = match load_notification_rules(&conn, agent_id) {
+        Ok(rules) => rules,
+        Err(e) => {
+            warn!("Failed to load notification rules: {}", e);
+            return;
+        }
+    };
+    // Without a rule of its own a run gets a desktop notification with the
+    // default message, if the desktop notification settings ask for one
+    let rule = rules.into_iter().find(|r| r.event == event);
+    let channel = rule
+        .as_ref()
+        .map_or(NotificationChannel::Desktop, |rule| rule.channel);
+
+    let duration_secs = run_duration_ms(started_at.as_deref(), completed_at.as_deref())
+        .map(|ms| ms as f64 / 1000.0)
+        .unwrap_or_default();
+    let cost_usd = RunUsageTracker::from_jsonl(output).cost_usd();
+    let excerpt = final_message_excerpt(output);
+
+    let values: HashMap<String, String> = [
+        ("run_id", run_id.to_string()),
+        ("agent", agent_name.clone()),
+        ("event", event.as_str().to_string()),
+        ("status", status.clone()),
+        ("project", project.clone()),
+        ("task", task.clone()),
+        ("duration_secs", format!("{:.1}", duration_secs)),
+        ("cost_usd", format!("{:.4}", cost_usd)),
+        ("failure_reason", failure_reason.clone().unwrap_or_default()),
+        ("excerpt", excerpt.clone()),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value))
+    .collect();
+
+    if matches!(
+        channel,
+        NotificationChannel::Desktop | NotificationChannel::Both
+    ) {
+        let template = rule
+            .as_ref()
+            .and_then(|rule| rule.message_template.as_deref())
+            .unwrap_or(DEFAULT_MESSAGE_TEMPLATE);
+        // Denials can only be granted to a run with an allow list to add them
+        // to, and a session to continue with them
+        let grantable =
+            has_session && matches!(load_permission_profile(&conn, agent_id), Ok(Some(_)));
+        let (kind, approved_tools) = match event {
+            RunEvent::Success => (NotificationKind::RunSucceeded, Vec::new()),
+            RunEvent::Failure => (NotificationKind::RunFailed, Vec::new()),
+            RunEvent::NeedsInput if grantable => {
+                (NotificationKind::RunNeedsInput, denied_tool_rules(output))
+            }
+            RunEvent::NeedsInput => (NotificationKind::RunNeedsInput, Vec::new()),
+        };
+        let mut actions = vec![NotificationAction::OpenRun { run_id }];
+        if has_changes {
+            actions.push(NotificationAction::ViewDiff { run_id });
+        }
+        if !approved_tools.is_empty() {
+            actions.push(NotificationAction::Approve {
+                run_id,
+                tools: approved_tools,
+            });
+        }
+        let settings = load_notification_settings(&conn);
+        post_notification(
+            app,
+            rule.is_none().then_some(&settings),
+            kind,
+            format!("{} {}", agent_name, event.label()),
+            render_prompt(template, &values).trim().to_string(),
+            actions,
+        );
+    }
+
+    // What a webhook gets without a payload template
+    let default_payload = serde_json::json!({
+        "run_id": run_id,
+        "agent": agent_name,
+        "event": event.as_str(),
+        "status": status,
+        "project": project,
+        "task": task,
+        "duration_secs": duration_secs,
+        "cost_usd": cost_usd,
+        "failure_reason": failure_reason,
+        "excerpt": excerpt,
+    });
+
+    if let (
+        NotificationChannel::Webhook | NotificationChannel::Both,
+        Some(NotificationRule {
+            webhook_url: Some(url),
+            payload_template,
+            ..
+        }),
+    ) = (channel, rule)
+    {
+        let body = match &payload_template {
+            Some(template) => render_payload(template, &values),
+            None => default_payload.to_string(),
+        };
+        send_webhook(url, body);
+    }
+
+    info!(
+        "Sent {} notification for run {} via {}",
+        event.as_str(),
+        run_id,
+        channel.as_str()
+    );
+
+    match notify_channels(&conn, agent_id, event, &values, &default_payload) {
+        Ok(0) => {}
+        Ok(sent) => info!("Reported run {} to {} notification channels", run_id, sent),
+        Err(e) => warn!("Failed to load notification channels: {}", e),
+    }
+}
+
+/// Get an agent's notification rules
+#[tauri::command]
+pub async fn get_agent_notification_rules(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<Vec<NotificationRule>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_notification_rules(&conn, agent_id)
+}
+
+/// Replace an agent's notification rules; at most one rule per event
+#[tauri::command]
+pub async fn set_agent_notification_rules(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    rules: Vec<NotificationRule>,
+) -> Result<(), String> {
+    for (index, rule) in rules.iter().enumerate() {
+        if rules[..index].iter().any(|r| r.event == rule.event) {
+            return Err(format!(
+                "Only one rule per event is allowed ({})",
+                rule.event.as_str()
+            ));
+        }
+        if rule.channel != NotificationChannel::Desktop {
+            let url = rule.webhook_url.as_deref().unwrap_or_default();
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(format!(
+                    "Webhook URL for {} notifications must start with http:// or https://",
+                    rule.event.as_str()
+                ));
+            }
+        }
+    }
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM agent_notification_rules WHERE agent_id = ?1",
+        params![agent_id],
+    )
+    .map_err(|e| e.to_string())?;
+    for rule in &rules {
+        tx.execute(
+            "INSERT INTO agent_notification_rules (agent_id, event, channel, webhook_url, message_template, payload_template)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                agent_id,
+                rule.event.as_str(),
+                rule.channel.as_str(),
+                rule.webhook_url,
+                rule.message_template,
+                rule.payload_template
+            ],
+        )
+        .map_err(|e| format!("Failed to save notification rule: {}", e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_event() {
+        let denied =
+            r#"{"type":"result","result":"Blocked","permission_denials":[{"tool_name":"Bash"}]}"#;
+        let clean = r#"{"type":"result","result":"Done","permission_denials":[]}"#;
+
+        assert_eq!(run_event("completed", None, clean), Some(RunEvent::Success));
+        assert_eq!(
+            run_event("completed", None, denied),
+            Some(RunEvent::NeedsInput)
+        );
+        assert_eq!(
+            run_event("failed", Some("timeout"), ""),
+            Some(RunEvent::NeedsInput)
+        );
+        assert_eq!(
+            run_event("failed", Some("nonzero_exit"), ""),
+            Some(RunEvent::Failure)
+        );
+        assert_eq!(
+            run_event("budget_exceeded", None, ""),
+            Some(RunEvent::Failure)
+        );
+        assert_eq!(run_event("cancelled", None, ""), None);
+    }
+
+    #[test]
+    fn test_render_payload_escapes_values() {
+        let values = HashMap::from([
+            ("run_id".to_string(), "42".to_string()),
+            ("excerpt".to_string(), "Said \"hi\"\nthen left".to_string()),
+        ]);
+        let body = render_payload(r#"{"text": "Run {{run_id}}: {{excerpt}}"}"#, &values);
+
+        let json: JsonValue = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["text"], "Run 42: Said \"hi\"\nthen left");
+    }
+}
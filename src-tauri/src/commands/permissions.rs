@@ -0,0 +1,225 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Tools that only touch Claude's own state and are always allowed
+const ALWAYS_ALLOWED_TOOLS: &[&str] = &["Task", "TodoWrite"];
+
+/// Which hosts an agent may reach
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkPolicy {
+    #[default]
+    Deny,
+    /// Only the listed domains
+    Domains,
+    Allow,
+}
+
+/// What an agent is allowed to do, enforced through Claude Code's permission rules.
+///
+/// Path scopes are gitignore-style globs relative to the project; `**` means everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PermissionProfile {
+    #[serde(default)]
+    pub read_paths: Vec<String>,
+    #[serde(default)]
+    pub write_paths: Vec<String>,
+    /// Command prefixes such as `npm test` or `git status`; `*` allows any command
+    #[serde(default)]
+    pub allowed_bash: Vec<String>,
+    #[serde(default)]
+    pub network: NetworkPolicy,
+    /// Domains reachable under [`NetworkPolicy::Domains`]
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+/// Claude Code permission rules granted and denied by a profile
+#[derive(Debug, Default, PartialEq)]
+pub struct PermissionRules {
+    pub allowed: Vec<String>,
+    pub disallowed: Vec<String>,
+}
+
+fn scoped_rules(tools: &[&str], scopes: &[String], rules: &mut Vec<String>) {
+    for scope in scopes.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        for tool in tools {
+            if scope == "**" {
+                rules.push(tool.to_string());
+            } else {
+                rules.push(format!("{}({})", tool, scope));
+            }
+        }
+    }
+}
+
+impl PermissionProfile {
+    /// Translate the profile into `--allowedTools` / `--disallowedTools` rules
+    pub fn to_rules(&self) -> PermissionRules {
+        let mut allowed: Vec<String> = ALWAYS_ALLOWED_TOOLS.iter().map(|t| t.to_string()).collect();
+        let mut disallowed = Vec::new();
+
+        // Read rules also govern Glob, Grep and LS
+        scoped_rules(&["Read"], &self.read_paths, &mut allowed);
+        if !self.read_paths.is_empty() {
+            allowed.extend(["Glob", "Grep", "LS"].map(String::from));
+        }
+        scoped_rules(
+            &["Edit", "MultiEdit", "Write", "NotebookEdit"],
+            &self.write_paths,
+            &mut allowed,
+        );
+
+        for command in self
+            .allowed_bash
+            .iter()
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+        {
+            if command == "*" {
+                allowed.push("Bash".to_string());
+            } else {
+                allowed.push(format!("Bash({}:*)", command));
+            }
+        }
+
+        match self.network {
+            NetworkPolicy::Allow => allowed.extend(["WebFetch", "WebSearch"].map(String::from)),
+            NetworkPolicy::Domains => {
+                for domain in self
+                    .allowed_domains
+                    .iter()
+                    .map(|d| d.trim())
+                    .filter(|d| !d.is_empty())
+                {
+                    allowed.push(format!("WebFetch(domain:{})", domain));
+                }
+                disallowed.push("WebSearch".to_string());
+            }
+            NetworkPolicy::Deny => disallowed.extend(["WebFetch", "WebSearch"].map(String::from)),
+        }
+
+        allowed.dedup();
+        PermissionRules {
+            allowed,
+            disallowed,
+        }
+    }
+}
+
+/// Load an agent's permission profile; None means the agent runs unrestricted
+pub fn load_permission_profile(
+    conn: &Connection,
+    agent_id: i64,
+) -> Result<Option<PermissionProfile>, String> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT permission_profile FROM agents WHERE id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to load agent {}: {}", agent_id, e))?;
+
+    json.map(|json| {
+        serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid permission profile for agent {}: {}", agent_id, e))
+    })
+    .transpose()
+}
+
+/// Store an agent's permission profile, or clear it with None
+pub fn save_permission_profile(
+    conn: &Connection,
+    agent_id: i64,
+    profile: Option<&PermissionProfile>,
+) -> Result<(), String> {
+    let json = profile
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE agents SET permission_profile = ?1 WHERE id = ?2",
+        params![json, agent_id],
+    )
+    .map_err(|e| format!("Failed to save permission profile: {}", e))?;
+
+    Ok(())
+}
+
+/// Get an agent's permission profile
+#[tauri::command]
+pub async fn get_agent_permissions(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<Option<PermissionProfile>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_permission_profile(&conn, agent_id)
+}
+
+/// Set an agent's permission profile; None lets it run without restrictions again
+#[tauri::command]
+pub async fn set_agent_permissions(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    profile: Option<PermissionProfile>,
+) -> Result<(), String> {
+    if let Some(profile) = &profile {
+        if profile.network == NetworkPolicy::Domains && profile.allowed_domains.is_empty() {
+            return Err("Add at least one domain or choose a different network policy".to_string());
+        }
+        let scopes = profile.read_paths.iter().chain(&profile.write_paths);
+        if let Some(scope) = scopes
+            .chain(&profile.allowed_bash)
+            .find(|s| s.contains([')', ',']))
+        {
+            return Err(format!("Invalid permission scope '{}'", scope));
+        }
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_permission_profile(&conn, agent_id, profile.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_to_rules() {
+        let profile = PermissionProfile {
+            read_paths: vec!["**".to_string()],
+            write_paths: vec!["src/**".to_string()],
+            allowed_bash: vec!["npm test".to_string()],
+            network: NetworkPolicy::Domains,
+            allowed_domains: vec!["registry.npmjs.org".to_string()],
+        };
+
+        let rules = profile.to_rules();
+        assert_eq!(
+            rules.allowed,
+            vec![
+                "Task",
+                "TodoWrite",
+                "Read",
+                "Glob",
+                "Grep",
+                "LS",
+                "Edit(src/**)",
+                "MultiEdit(src/**)",
+                "Write(src/**)",
+                "NotebookEdit(src/**)",
+                "Bash(npm test:*)",
+                "WebFetch(domain:registry.npmjs.org)",
+            ]
+        );
+        assert_eq!(rules.disallowed, vec!["WebSearch"]);
+
+        let locked_down = PermissionProfile::default().to_rules();
+        assert_eq!(locked_down.allowed, vec!["Task", "TodoWrite"]);
+        assert_eq!(locked_down.disallowed, vec!["WebFetch", "WebSearch"]);
+    }
+}
@@ -0,0 +1,368 @@
+use log::info;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::commands::usage::{set_custom_pricing, CustomPricing, ModelPricing, TokenUsage};
+
+/// app_settings key holding the pricing settings as JSON
+const SETTINGS_KEY: &str = "pricing";
+
+/// Currency costs are displayed in and its units per US dollar
+static DISPLAY_CURRENCY: RwLock<Option<(String, f64)>> = RwLock::new(None);
+
+/// A user-supplied price for the models whose id contains `model`, such as
+/// a Bedrock or Vertex model id or a negotiated rate. Prices are in USD per
+/// million tokens.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CustomModelPricing {
+    pub model: String,
+    pub input: f64,
+    pub output: f64,
+    /// Cache rates default to Anthropic's multiples of the input price
+    pub cache_write_5m: Option<f64>,
+    pub cache_write_1h: Option<f64>,
+    pub cache_read: Option<f64>,
+}
+
+impl CustomModelPricing {
+    pub fn pricing(&self) -> ModelPricing {
+        let derived = ModelPricing::from_input_output(self.input, self.output);
+        ModelPricing {
+            cache_write_5m: self.cache_write_5m.unwrap_or(derived.cache_write_5m),
+            cache_write_1h: self.cache_write_1h.unwrap_or(derived.cache_write_1h),
+            cache_read: self.cache_read.unwrap_or(derived.cache_read),
+            ..derived
+        }
+    }
+}
+
+/// Custom prices and the currency costs are shown in. Costs are computed and
+/// stored in USD, and converted only for display.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PricingSettings {
+    pub models: Vec<CustomModelPricing>,
+    /// ISO 4217 code, e.g. `EUR`
+    pub currency: String,
+    /// Units of `currency` per US dollar
+    pub usd_rate: f64,
+}
+
+impl PricingSettings {
+    pub fn custom_pricing(&self) -> CustomPricing {
+        CustomPricing::new(
+            self.models
+                .iter()
+                .map(|custom| (custom.model.clone(), custom.pricing()))
+                .collect(),
+        )
+    }
+}
+
+impl Default for PricingSettings {
+    fn default() -> Self {
+        Self {
+            models: Vec::new(),
+            currency: "USD".to_string(),
+            usd_rate: 1.0,
+        }
+    }
+}
+
+fn load_settings(conn: &Connection) -> PricingSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+fn apply_settings(settings: &PricingSettings) {
+    set_custom_pricing(settings.custom_pricing());
+    if let Ok(mut currency) = DISPLAY_CURRENCY.write() {
+        *currency = Some((settings.currency.clone(), settings.usd_rate));
+    }
+}
+
+/// Apply the saved pricing settings; called once at startup by the app and
+/// the CLI
+pub fn load_pricing_settings(conn: &Connection) {
+    let settings = load_settings(conn);
+    apply_settings(&settings);
+    if !settings.models.is_empty() {
+        info!("Using custom prices for {} models", settings.models.len());
+    }
+}
+
+/// A USD cost in the display currency, e.g. `$1.25` or `1.15 EUR`
+pub fn format_cost(usd: f64) -> String {
+    let currency = DISPLAY_CURRENCY
+        .read()
+        .ok()
+        .and_then(|currency| currency.clone());
+    format_cost_in(usd, currency.as_ref())
+}
+
+/// A USD cost in `currency`, given with its units per US dollar
+fn format_cost_in(usd: f64, currency: Option<&(String, f64)>) -> String {
+    match currency {
+        Some((currency, rate)) if currency != "USD" => format!("{:.2} {}", usd * rate, currency),
+        _ => format!("${:.2}", usd),
+    }
+}
+
+fn validate(settings: &PricingSettings) -> Result<(), String> {
+    let valid_price = |price: f64| price.is_finite() && price >= 0.0;
+    for custom in &settings.models {
+        if custom.model.trim().is_empty() {
+            return Err("Custom prices need a model id".to_string());
+        }
+        let prices = [
+            Some(custom.input),
+            Some(custom.output),
+            custom.cache_write_5m,
+            custom.cache_write_1h,
+            custom.cache_read,
+        ];
+        if !prices.into_iter().flatten().all(valid_price) {
+            return Err(format!("Invalid price for {}", custom.model));
+        }
+    }
+    if settings.currency.len() != 3 || !settings.currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(format!("Invalid currency code '{}'", settings.currency));
+    }
+    if !settings.usd_rate.is_finite() || settings.usd_rate <= 0.0 {
+        return Err("The conversion rate must be greater than zero".to_string());
+    }
+    Ok(())
+}
+
+/// A usage entry or rolled up month as (rowid, model, tokens, cost, cost
+/// without custom prices)
+type PricedUsage = (i64, String, TokenUsage, f64, f64);
+
+/// Recompute the cost of indexed usage and rolled up months with `pricing`;
+/// usage of models without a custom price goes back to its cost without one.
+/// Returns the number of entries and months whose cost changed.
+pub fn reprice_usage(conn: &mut Connection, pricing: &CustomPricing) -> Result<usize, String> {
+    let read = |sql: &str| -> rusqlite::Result<Vec<PricedUsage>> {
+        conn.prepare(sql)?
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    TokenUsage {
+                        input_tokens: row.get::<_, i64>(2)? as u64,
+                        output_tokens: row.get::<_, i64>(3)? as u64,
+                        cache_creation_tokens: row.get::<_, i64>(4)? as u64,
+                        cache_creation_1h_tokens: row.get::<_, i64>(5)? as u64,
+                        cache_read_tokens: row.get::<_, i64>(6)? as u64,
+                    },
+                    row.get(7)?,
+                    row.get(8)?,
+                ))
+            })?
+            .collect()
+    };
+    let columns =
+        "model, input_tokens, output_tokens, cache_creation_tokens, cache_creation_1h_tokens,
+        cache_read_tokens, cost, base_cost";
+    let entries = read(&format!("SELECT rowid, {} FROM usage_entries", columns))
+        .map_err(|e| e.to_string())?;
+    let months = read(&format!(
+        "SELECT rowid, {} FROM usage_monthly_rollups",
+        columns
+    ))
+    .map_err(|e| e.to_string())?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut repriced = 0;
+    for (table, rows) in [
+        ("usage_entries", entries),
+        ("usage_monthly_rollups", months),
+    ] {
+        for (rowid, model, tokens, cost, base_cost) in rows {
+            let new_cost = pricing.cost(&model, &tokens, base_cost);
+            if (new_cost - cost).abs() > 1e-12 {
+                tx.execute(
+                    &format!("UPDATE {} SET cost = ?1 WHERE rowid = ?2", table),
+                    params![new_cost, rowid],
+                )
+                .map_err(|e| e.to_string())?;
+                repriced += 1;
+            }
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(repriced)
+}
+
+/// The custom prices and display currency
+#[tauri::command]
+pub async fn get_pricing_settings(db: State<'_, AgentDb>) -> Result<PricingSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+/// Save the custom prices and display currency, and reprice the indexed usage
+/// to match; returns the number of usage entries whose cost changed
+#[tauri::command]
+pub async fn save_pricing_settings(
+    db: State<'_, AgentDb>,
+    settings: PricingSettings,
+) -> Result<usize, String> {
+    let settings = PricingSettings {
+        models: settings
+            .models
+            .into_iter()
+            .map(|custom| CustomModelPricing {
+                model: custom.model.trim().to_string(),
+                ..custom
+            })
+            .collect(),
+        currency: settings.currency.trim().to_uppercase(),
+        ..settings
+    };
+    validate(&settings)?;
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    apply_settings(&settings);
+
+    let repriced = reprice_usage(&mut conn, &settings.custom_pricing())?;
+    if repriced > 0 {
+        info!("Repriced {} usage entries", repriced);
+    }
+    Ok(repriced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usage::builtin_pricing;
+    use crate::commands::usage_index::{create_usage_tables, insert_test_usage};
+
+    fn costs(conn: &Connection, table: &str) -> Vec<f64> {
+        conn.prepare(&format!("SELECT cost FROM {} ORDER BY rowid", table))
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_custom_pricing_overrides_builtin_prices() {
+        let custom = CustomModelPricing {
+            model: "bedrock-test-model".to_string(),
+            input: 2.0,
+            output: 10.0,
+            cache_write_5m: None,
+            cache_write_1h: None,
+            cache_read: Some(0.1),
+        };
+        let pricing = custom.pricing();
+        assert_eq!(
+            (
+                pricing.cache_write_5m,
+                pricing.cache_write_1h,
+                pricing.cache_read
+            ),
+            (2.5, 4.0, 0.1)
+        );
+
+        let settings = PricingSettings {
+            models: vec![custom],
+            currency: "EUR".to_string(),
+            usd_rate: 0.5,
+        };
+        assert_eq!(validate(&settings), Ok(()));
+        let custom_pricing = settings.custom_pricing();
+        assert_eq!(
+            custom_pricing.get("us.bedrock-test-model-v1"),
+            Some(pricing)
+        );
+        assert_eq!(custom_pricing.get("claude-opus-4-1"), None);
+        assert_eq!(
+            builtin_pricing("claude-opus-4-1").map(|p| p.input),
+            Some(15.0)
+        );
+        assert_eq!(
+            format_cost_in(3.0, Some(&("EUR".to_string(), 0.5))),
+            "1.50 EUR"
+        );
+        assert_eq!(format_cost_in(3.0, None), "$3.00");
+        assert_eq!(
+            validate(&PricingSettings {
+                currency: "euro".to_string(),
+                ..PricingSettings::default()
+            }),
+            Err("Invalid currency code 'euro'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reprice_usage_and_revert() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_usage_tables(&conn).unwrap();
+        insert_test_usage(
+            &conn,
+            &[
+                (
+                    "a",
+                    "2025-06-01T10:00:00Z",
+                    "us.bedrock-test-model-v1",
+                    1000000,
+                    0,
+                    3.0,
+                    "s1",
+                    "/p",
+                ),
+                // Models without a custom price keep the cost Claude recorded
+                (
+                    "b",
+                    "2025-06-01T10:00:00Z",
+                    "unpriced-model",
+                    1000000,
+                    0,
+                    7.0,
+                    "s1",
+                    "/p",
+                ),
+            ],
+        );
+        conn.execute(
+            "INSERT INTO usage_monthly_rollups (month, project_path, model, input_tokens, output_tokens,
+                cache_creation_tokens, cache_read_tokens, cost, message_count, session_count, base_cost)
+             VALUES ('2025-01', '/p', 'us.bedrock-test-model-v1', 2000000, 0, 0, 0, 9.0, 2, 1, 9.0)",
+            [],
+        )
+        .unwrap();
+
+        let custom = PricingSettings {
+            models: vec![CustomModelPricing {
+                model: "bedrock-test-model".to_string(),
+                input: 2.0,
+                output: 10.0,
+                cache_write_5m: None,
+                cache_write_1h: None,
+                cache_read: None,
+            }],
+            ..PricingSettings::default()
+        };
+        assert_eq!(
+            reprice_usage(&mut conn, &custom.custom_pricing()).unwrap(),
+            2
+        );
+        assert_eq!(costs(&conn, "usage_entries"), vec![2.0, 7.0]);
+        assert_eq!(costs(&conn, "usage_monthly_rollups"), vec![4.0]);
+
+        // Removing the price brings back the cost without it
+        assert_eq!(
+            reprice_usage(&mut conn, &CustomPricing::default()).unwrap(),
+            2
+        );
+        assert_eq!(costs(&conn, "usage_entries"), vec![3.0, 7.0]);
+        assert_eq!(costs(&conn, "usage_monthly_rollups"), vec![9.0]);
+    }
+}
@@ -0,0 +1,354 @@
+use chrono::Utc;
+use futures::future::join_all;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use sysinfo::{Pid, ProcessRefreshKind, System};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::agents::{kill_agent_session, AgentDb};
+use crate::commands::cancellation::grace_period;
+use crate::process::{
+    normalize_project_path, CancelOutcome, ProcessInfo, ProcessRegistryState, ProcessType,
+};
+
+/// Lines of live output included when inspecting a process
+const INSPECT_OUTPUT_LINES: usize = 50;
+
+/// Process table shared between calls, so CPU usage is measured since the last one
+static SYSTEM: OnceLock<Mutex<System>> = OnceLock::new();
+
+/// A Claude session or agent run spawned by opcode. Memory and CPU cover the
+/// process and everything it spawned, such as MCP servers and tool commands.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManagedProcess {
+    #[serde(flatten)]
+    pub info: ProcessInfo,
+    pub uptime_secs: u64,
+    /// Whether the process still exists
+    pub alive: bool,
+    pub memory_bytes: u64,
+    /// Percent of one core, so it can exceed 100 on several
+    pub cpu_percent: f32,
+}
+
+/// A process started by a managed process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChildProcess {
+    pub pid: u32,
+    pub parent_pid: u32,
+    pub name: String,
+    pub command: Vec<String>,
+    pub memory_bytes: u64,
+    pub cpu_percent: f32,
+}
+
+/// A managed process in detail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessDetail {
+    #[serde(flatten)]
+    pub process: ManagedProcess,
+    pub command: Vec<String>,
+    pub cwd: Option<String>,
+    /// Run state reported by the OS, e.g. "Sleeping"
+    pub status: Option<String>,
+    pub children: Vec<ChildProcess>,
+    /// Last lines of the process's live output
+    pub recent_output: Vec<String>,
+}
+
+/// Refresh the process table and run `f` on it. CPU usage needs two
+/// refreshes apart, so the first call waits briefly.
+async fn with_system<T>(f: impl FnOnce(&System) -> T) -> T {
+    let mut first_refresh = false;
+    let system = SYSTEM.get_or_init(|| {
+        first_refresh = true;
+        Mutex::new(System::new())
+    });
+    if first_refresh {
+        if let Ok(mut system) = system.lock() {
+            system.refresh_processes_specifics(ProcessRefreshKind::everything());
+        }
+        tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+    }
+    let mut system = system.lock().unwrap_or_else(|e| e.into_inner());
+    system.refresh_processes_specifics(ProcessRefreshKind::everything());
+    f(&system)
+}
+
+/// All processes descended from `root`, parents before their children
+fn descendants(root: u32, parents: &HashMap<u32, u32>) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (&pid, &parent) in parents {
+        children.entry(parent).or_default().push(pid);
+    }
+    let mut found = Vec::new();
+    let mut pending = vec![root];
+    while let Some(pid) = pending.pop() {
+        for &child in children.get(&pid).into_iter().flatten() {
+            // Guard against pid reuse making a cycle
+            if child != root && !found.contains(&child) {
+                found.push(child);
+                pending.push(child);
+            }
+        }
+    }
+    found
+}
+
+/// Parent of each process in the table
+pub fn parent_pids(system: &System) -> HashMap<u32, u32> {
+    system
+        .processes()
+        .iter()
+        .filter_map(|(pid, process)| Some((pid.as_u32(), process.parent()?.as_u32())))
+        .collect()
+}
+
+/// A process followed by everything it spawned, parents before their children
+pub async fn process_tree(pid: u32) -> Vec<u32> {
+    with_system(|system| {
+        let mut tree = vec![pid];
+        tree.extend(descendants(pid, &parent_pids(system)));
+        tree
+    })
+    .await
+}
+
+/// Memory in bytes and CPU percent of a process and everything it spawned;
+/// None if it isn't running
+pub fn process_tree_usage(
+    system: &System,
+    parents: &HashMap<u32, u32>,
+    pid: u32,
+) -> Option<(u64, f32)> {
+    let process = system.process(Pid::from_u32(pid))?;
+    let tree = descendants(pid, parents)
+        .into_iter()
+        .filter_map(|pid| system.process(Pid::from_u32(pid)));
+    Some(tree.fold(
+        (process.memory(), process.cpu_usage()),
+        |(memory, cpu), child| (memory + child.memory(), cpu + child.cpu_usage()),
+    ))
+}
+
+fn managed_process(
+    system: &System,
+    parents: &HashMap<u32, u32>,
+    info: ProcessInfo,
+) -> ManagedProcess {
+    let uptime_secs = (Utc::now() - info.started_at).num_seconds().max(0) as u64;
+    let usage = process_tree_usage(system, parents, info.pid);
+    let (memory_bytes, cpu_percent) = usage.unwrap_or_default();
+    ManagedProcess {
+        info,
+        uptime_secs,
+        alive: usage.is_some(),
+        memory_bytes,
+        cpu_percent,
+    }
+}
+
+fn find_process(registry: &ProcessRegistryState, run_id: i64) -> Result<ProcessInfo, String> {
+    registry
+        .0
+        .get_process(run_id)?
+        .ok_or_else(|| format!("Process {} is not running", run_id))
+}
+
+/// Stop a managed process the way its own cancel command does
+async fn kill_managed_process_inner(
+    app: &AppHandle,
+    db: &State<'_, AgentDb>,
+    registry: &State<'_, ProcessRegistryState>,
+    info: &ProcessInfo,
+) -> Result<bool, String> {
+    match &info.process_type {
+        ProcessType::AgentRun { .. } => {
+            kill_agent_session(app.clone(), db.clone(), registry.clone(), info.run_id).await
+        }
+        ProcessType::ClaudeSession { session_id } => {
+            let grace = {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                grace_period(&conn)
+            };
+            let outcome = registry.0.cancel_process(info.run_id, grace).await?;
+            let _ = app.emit(&format!("claude-cancelled:{}", session_id), true);
+            let _ = app.emit(&format!("claude-cancellation:{}", session_id), outcome);
+            let _ = app.emit(&format!("claude-complete:{}", session_id), false);
+            Ok(outcome != CancelOutcome::NotRunning)
+        }
+    }
+}
+
+/// Every Claude session and agent run opcode has spawned, longest running first
+#[tauri::command]
+pub async fn list_managed_processes(
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<Vec<ManagedProcess>, String> {
+    let infos = registry.0.get_running_processes()?;
+    let mut processes = with_system(|system| {
+        let parents = parent_pids(system);
+        infos
+            .into_iter()
+            .map(|info| managed_process(system, &parents, info))
+            .collect::<Vec<_>>()
+    })
+    .await;
+    processes.sort_by_key(|process| process.info.started_at);
+    Ok(processes)
+}
+
+/// A managed process with its command line, child processes and recent output
+#[tauri::command]
+pub async fn inspect_managed_process(
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+) -> Result<ProcessDetail, String> {
+    let info = find_process(&registry, run_id)?;
+    let output = registry.0.get_live_output(run_id)?;
+    let lines: Vec<&str> = output.lines().collect();
+    let recent_output = lines[lines.len().saturating_sub(INSPECT_OUTPUT_LINES)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+    Ok(with_system(|system| {
+        let parents = parent_pids(system);
+        let process = system.process(Pid::from_u32(info.pid));
+        let children = descendants(info.pid, &parents)
+            .into_iter()
+            .filter_map(|pid| system.process(Pid::from_u32(pid)))
+            .map(|child| ChildProcess {
+                pid: child.pid().as_u32(),
+                parent_pid: child.parent().map(|p| p.as_u32()).unwrap_or_default(),
+                name: child.name().to_string(),
+                command: child.cmd().to_vec(),
+                memory_bytes: child.memory(),
+                cpu_percent: child.cpu_usage(),
+            })
+            .collect();
+        ProcessDetail {
+            command: process.map(|p| p.cmd().to_vec()).unwrap_or_default(),
+            cwd: process
+                .and_then(|p| p.cwd())
+                .map(|cwd| cwd.to_string_lossy().to_string()),
+            status: process.map(|p| p.status().to_string()),
+            children,
+            recent_output,
+            process: managed_process(system, &parents, info),
+        }
+    })
+    .await)
+}
+
+/// Interrupt a managed process as if Ctrl+C was pressed, leaving it to stop
+/// its current work on its own; returns whether it was signalled
+#[tauri::command]
+pub async fn interrupt_managed_process(
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+) -> Result<bool, String> {
+    find_process(&registry, run_id)?;
+    info!("Interrupting managed process {}", run_id);
+    registry.0.interrupt(run_id)
+}
+
+/// Pause a managed process and everything it spawned, e.g. to free the CPU
+/// for a while; returns whether it is paused
+#[tauri::command]
+pub async fn pause_managed_process(
+    app: AppHandle,
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+) -> Result<bool, String> {
+    let info = find_process(&registry, run_id)?;
+    let tree = process_tree(info.pid).await;
+    info!(
+        "Pausing managed process {} and {} processes it spawned",
+        run_id,
+        tree.len() - 1
+    );
+    let paused = registry.0.pause(run_id, &tree)?;
+    if paused {
+        let _ = app.emit("process-paused", run_id);
+    }
+    Ok(paused)
+}
+
+/// Let a paused managed process continue; returns whether it was paused
+#[tauri::command]
+pub async fn unpause_managed_process(
+    app: AppHandle,
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+) -> Result<bool, String> {
+    find_process(&registry, run_id)?;
+    info!("Unpausing managed process {}", run_id);
+    let unpaused = registry.0.unpause(run_id)?;
+    if unpaused {
+        let _ = app.emit("process-unpaused", run_id);
+    }
+    Ok(unpaused)
+}
+
+/// Stop a managed process
+#[tauri::command]
+pub async fn kill_managed_process(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+) -> Result<bool, String> {
+    let info = find_process(&registry, run_id)?;
+    kill_managed_process_inner(&app, &db, &registry, &info).await
+}
+
+/// Stop every managed process working in a project; returns how many were stopped
+#[tauri::command]
+pub async fn kill_project_processes(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    project_path: String,
+) -> Result<usize, String> {
+    let project_path = normalize_project_path(&project_path);
+    let infos: Vec<ProcessInfo> = registry
+        .0
+        .get_running_processes()?
+        .into_iter()
+        .filter(|info| normalize_project_path(&info.project_path) == project_path)
+        .collect();
+
+    // Cancelled together, so they share one grace period rather than one each
+    let results = join_all(
+        infos
+            .iter()
+            .map(|info| kill_managed_process_inner(&app, &db, &registry, info)),
+    )
+    .await;
+    let mut killed = 0;
+    for stopped in results {
+        if stopped? {
+            killed += 1;
+        }
+    }
+    info!("Stopped {} processes in {}", killed, project_path);
+    Ok(killed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descendants() {
+        // 10 -> 11 -> 13, 10 -> 12, 20 -> 21 and a cycle from pid reuse
+        let parents = HashMap::from([(11, 10), (12, 10), (13, 11), (21, 20), (10, 13)]);
+        let mut tree = descendants(10, &parents);
+        tree.sort();
+        assert_eq!(tree, vec![11, 12, 13]);
+        assert_eq!(descendants(21, &parents), Vec::<u32>::new());
+    }
+}
@@ -0,0 +1,489 @@
+use base64::Engine;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use tauri::State;
+use zstd::stream::{decode_all, encode_all};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::claude::get_project_path_from_sessions;
+use crate::commands::issue_links::{import_issue_link, load_issue_links, IssueLink, LinkTarget};
+use crate::commands::prompt_templates::{list_templates, PromptTemplate};
+use crate::process::normalize_project_path;
+
+/// Version of the bundle format, bumped on incompatible changes
+const BUNDLE_VERSION: u32 = 1;
+
+/// zstd level used for bundles
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// A file from the project's directory under ~/.claude/projects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleFile {
+    /// Path relative to the project directory, `/`-separated
+    pub path: String,
+    /// Base64 encoded content
+    pub content: String,
+}
+
+/// A session title carried over with its session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledSessionTitle {
+    pub session_id: String,
+    pub title: String,
+    pub user_set: bool,
+}
+
+/// opcode's own data about the project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleMetadata {
+    pub session_titles: Vec<BundledSessionTitle>,
+    /// Templates scoped to the project; global ones stay on the machine
+    pub prompt_templates: Vec<PromptTemplate>,
+    /// Issues linked to the sessions
+    #[serde(default)]
+    pub issue_links: Vec<IssueLink>,
+}
+
+/// A project's sessions, checkpoints and metadata in one archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectBundle {
+    pub version: u32,
+    pub exported_at: String,
+    pub project_id: String,
+    /// Where the project lived on the exporting machine
+    pub project_path: String,
+    pub files: Vec<BundleFile>,
+    pub metadata: BundleMetadata,
+}
+
+/// Outcome of exporting a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleExportSummary {
+    pub project_path: String,
+    pub sessions: usize,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Outcome of importing a bundle
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleImportSummary {
+    pub project_id: String,
+    pub project_path: String,
+    pub sessions: usize,
+    pub files_written: usize,
+    /// Files left alone because they already exist on this machine
+    pub files_skipped: usize,
+    pub session_titles: usize,
+    pub prompt_templates: usize,
+    pub issue_links: usize,
+}
+
+fn claude_projects_dir() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("projects"))
+}
+
+/// Reject project ids that could point outside the projects directory
+fn validate_project_id(project_id: &str) -> Result<(), String> {
+    if project_id.is_empty()
+        || project_id == "."
+        || project_id.contains("..")
+        || project_id.contains(['/', '\\', ':'])
+        || Path::new(project_id).is_absolute()
+    {
+        return Err(format!("Invalid project id '{}'", project_id));
+    }
+    Ok(())
+}
+
+/// Directory under `projects_dir` that sessions for `project_path` are written to
+fn import_project_dir(
+    projects_dir: &Path,
+    project_path: &str,
+) -> Result<(String, PathBuf), String> {
+    let project_id = project_path.replace('/', "-");
+    validate_project_id(&project_id)?;
+    let project_dir = projects_dir.join(&project_id);
+    if project_dir.parent() != Some(projects_dir) {
+        return Err(format!("Invalid project id '{}'", project_id));
+    }
+    Ok((project_id, project_dir))
+}
+
+fn is_session_file(path: &str) -> bool {
+    !path.contains('/') && path.ends_with(".jsonl")
+}
+
+/// Replace a project path with another wherever it appears as a whole path or a
+/// prefix of one, in both plain and JSON-escaped form. `/work/api` is rewritten in
+/// `/work/api/src` but not in `/work/api-v2`.
+pub fn remap_paths(content: &str, from: &str, to: &str) -> String {
+    let escape = |path: &str| {
+        let quoted = serde_json::to_string(path).unwrap_or_default();
+        quoted[1..quoted.len() - 1].to_string()
+    };
+
+    let mut remapped = content.to_string();
+    let mut pairs = vec![(from.to_string(), to.to_string())];
+    if escape(from) != from {
+        pairs.push((escape(from), escape(to)));
+    }
+
+    for (from, to) in pairs {
+        if from.is_empty() {
+            continue;
+        }
+        let mut out = String::with_capacity(remapped.len());
+        let mut rest = remapped.as_str();
+        while let Some(index) = rest.find(&from) {
+            let after = &rest[index + from.len()..];
+            let whole = !after
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+            out.push_str(&rest[..index]);
+            out.push_str(if whole { &to } else { &from });
+            rest = after;
+        }
+        out.push_str(rest);
+        remapped = out;
+    }
+    remapped
+}
+
+/// Every file under a project directory: session transcripts, subagent logs and
+/// checkpoint timelines
+pub fn collect_bundle_files(project_dir: &Path) -> Result<Vec<BundleFile>, String> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(project_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let Ok(relative) = entry.path().strip_prefix(project_dir) else {
+            continue;
+        };
+        let content = std::fs::read(entry.path())
+            .map_err(|e| format!("Failed to read {}: {}", entry.path().display(), e))?;
+        files.push(BundleFile {
+            path: relative.to_string_lossy().replace('\\', "/"),
+            content: engine.encode(content),
+        });
+    }
+    Ok(files)
+}
+
+/// Resolve a bundled file path inside `target_dir`, refusing anything that would
+/// escape it
+fn bundle_target(target_dir: &Path, relative: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative);
+    if relative.as_os_str().is_empty()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("Invalid path in bundle: {}", relative.display()));
+    }
+    Ok(target_dir.join(relative))
+}
+
+/// Write a bundle's files into `target_dir`, rewriting the old project path in
+/// transcripts and checkpoint metadata. Compressed files (archived sessions and
+/// checkpoint content) are copied as they are. Existing files are never
+/// overwritten. Returns how many files were written and skipped.
+pub fn write_bundle_files(
+    files: &[BundleFile],
+    target_dir: &Path,
+    from: &str,
+    to: &str,
+) -> Result<(usize, usize), String> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let (mut written, mut skipped) = (0, 0);
+    for file in files {
+        let target = bundle_target(target_dir, &file.path)?;
+        if target.exists() {
+            skipped += 1;
+            continue;
+        }
+
+        let mut content = engine
+            .decode(&file.content)
+            .map_err(|e| format!("Corrupt file {} in bundle: {}", file.path, e))?;
+        if from != to && (file.path.ends_with(".jsonl") || file.path.ends_with(".json")) {
+            if let Ok(text) = String::from_utf8(content.clone()) {
+                content = remap_paths(&text, from, to).into_bytes();
+            }
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&target, content)
+            .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+        written += 1;
+    }
+    Ok((written, skipped))
+}
+
+/// Package a project's sessions, checkpoints, session titles, issue links and
+/// prompt templates into a single compressed archive at `file_path`
+#[tauri::command]
+pub async fn export_project_bundle(
+    db: State<'_, AgentDb>,
+    project_id: String,
+    file_path: String,
+) -> Result<BundleExportSummary, String> {
+    validate_project_id(&project_id)?;
+    let project_dir = claude_projects_dir()?.join(&project_id);
+    if !project_dir.is_dir() {
+        return Err(format!("Project directory not found: {}", project_id));
+    }
+    let project_path = get_project_path_from_sessions(&project_dir)?;
+    let files = collect_bundle_files(&project_dir)?;
+    let session_ids: Vec<String> = files
+        .iter()
+        .filter(|file| is_session_file(&file.path))
+        .map(|file| file.path.trim_end_matches(".jsonl").to_string())
+        .collect();
+
+    let metadata = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut session_titles = Vec::new();
+        let mut issue_links = Vec::new();
+        for session_id in &session_ids {
+            issue_links.extend(load_issue_links(&conn, LinkTarget::Session, session_id)?);
+            if let Ok((title, user_set)) = conn.query_row(
+                "SELECT title, user_set FROM session_titles WHERE session_id = ?1",
+                params![session_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ) {
+                session_titles.push(BundledSessionTitle {
+                    session_id: session_id.clone(),
+                    title,
+                    user_set,
+                });
+            }
+        }
+        let prompt_templates = list_templates(&conn, Some(&project_path))?
+            .into_iter()
+            .filter(|template| template.project_path.is_some())
+            .collect();
+        BundleMetadata {
+            session_titles,
+            prompt_templates,
+            issue_links,
+        }
+    };
+
+    let bundle = ProjectBundle {
+        version: BUNDLE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        project_id,
+        project_path: project_path.clone(),
+        files,
+        metadata,
+    };
+    let json = serde_json::to_vec(&bundle).map_err(|e| e.to_string())?;
+    let compressed = encode_all(&json[..], COMPRESSION_LEVEL)
+        .map_err(|e| format!("Failed to compress bundle: {}", e))?;
+    std::fs::write(&file_path, &compressed).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    log::info!(
+        "Exported {} sessions of {} to {}",
+        session_ids.len(),
+        project_path,
+        file_path
+    );
+    Ok(BundleExportSummary {
+        project_path,
+        sessions: session_ids.len(),
+        files: bundle.files.len(),
+        bytes: compressed.len() as u64,
+    })
+}
+
+/// Unpack a project bundle on this machine. Paths in the sessions are remapped
+/// from the exporting machine's project path to `project_path`, which defaults to
+/// the original one. Sessions that already exist here are kept as they are.
+#[tauri::command]
+pub async fn import_project_bundle(
+    db: State<'_, AgentDb>,
+    file_path: String,
+    project_path: Option<String>,
+) -> Result<BundleImportSummary, String> {
+    let compressed =
+        std::fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let json = decode_all(&compressed[..]).map_err(|e| format!("Not a project bundle: {}", e))?;
+    let bundle: ProjectBundle =
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid project bundle: {}", e))?;
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Bundle version {} is newer than supported version {}",
+            bundle.version, BUNDLE_VERSION
+        ));
+    }
+
+    let project_path = project_path
+        .map(|path| normalize_project_path(&path))
+        .unwrap_or_else(|| bundle.project_path.clone());
+    let (project_id, project_dir) = import_project_dir(&claude_projects_dir()?, &project_path)?;
+    let (files_written, files_skipped) = write_bundle_files(
+        &bundle.files,
+        &project_dir,
+        &bundle.project_path,
+        &project_path,
+    )?;
+
+    let mut summary = BundleImportSummary {
+        sessions: bundle
+            .files
+            .iter()
+            .filter(|file| is_session_file(&file.path))
+            .count(),
+        project_id,
+        project_path: project_path.clone(),
+        files_written,
+        files_skipped,
+        ..Default::default()
+    };
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    for title in &bundle.metadata.session_titles {
+        summary.session_titles += conn
+            .execute(
+                "INSERT OR IGNORE INTO session_titles (session_id, title, user_set) VALUES (?1, ?2, ?3)",
+                params![title.session_id, title.title, title.user_set],
+            )
+            .map_err(|e| format!("Failed to import session title: {}", e))?;
+    }
+
+    for link in &bundle.metadata.issue_links {
+        summary.issue_links += import_issue_link(&conn, link)?;
+    }
+
+    let existing: Vec<String> = list_templates(&conn, Some(&project_path))?
+        .into_iter()
+        .filter(|template| template.project_path.is_some())
+        .map(|template| template.name)
+        .collect();
+    for template in &bundle.metadata.prompt_templates {
+        if existing.contains(&template.name) {
+            continue;
+        }
+        let variables = serde_json::to_string(&template.variables).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO prompt_templates (name, description, content, variables, project_path)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                template.name,
+                template.description,
+                template.content,
+                variables,
+                project_path
+            ],
+        )
+        .map_err(|e| {
+            format!(
+                "Failed to import prompt template '{}': {}",
+                template.name, e
+            )
+        })?;
+        summary.prompt_templates += 1;
+    }
+
+    log::info!(
+        "Imported {} sessions from {} into {} ({} files skipped)",
+        summary.sessions,
+        file_path,
+        project_path,
+        files_skipped
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_files_round_trip_with_remapping() {
+        assert_eq!(
+            remap_paths(
+                r#"{"cwd":"/work/api","file":"/work/api/src/main.rs","other":"/work/api-v2"}"#,
+                "/work/api",
+                "/home/me/api"
+            ),
+            r#"{"cwd":"/home/me/api","file":"/home/me/api/src/main.rs","other":"/work/api-v2"}"#
+        );
+        assert_eq!(
+            remap_paths(r#"{"cwd":"C:\\work\\api"}"#, r"C:\work\api", "/home/me/api"),
+            r#"{"cwd":"/home/me/api"}"#
+        );
+
+        let source = tempfile::tempdir().unwrap();
+        std::fs::write(source.path().join("s1.jsonl"), "{\"cwd\":\"/work/api\"}\n").unwrap();
+        let timeline = source.path().join(".timelines/s1");
+        std::fs::create_dir_all(timeline.join("files/content_pool")).unwrap();
+        std::fs::write(timeline.join("timeline.json"), "{\"root\":\"/work/api\"}").unwrap();
+        std::fs::write(timeline.join("files/content_pool/abc"), b"/work/api\0").unwrap();
+
+        let files = collect_bundle_files(source.path()).unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files.iter().filter(|f| is_session_file(&f.path)).count(), 1);
+
+        let target = tempfile::tempdir().unwrap();
+        std::fs::write(target.path().join("s1.jsonl"), "existing").unwrap();
+        let (written, skipped) =
+            write_bundle_files(&files, target.path(), "/work/api", "/home/me/api").unwrap();
+        assert_eq!((written, skipped), (2, 1));
+        assert_eq!(
+            std::fs::read_to_string(target.path().join("s1.jsonl")).unwrap(),
+            "existing"
+        );
+        assert_eq!(
+            std::fs::read_to_string(target.path().join(".timelines/s1/timeline.json")).unwrap(),
+            "{\"root\":\"/home/me/api\"}"
+        );
+        assert_eq!(
+            std::fs::read(target.path().join(".timelines/s1/files/content_pool/abc")).unwrap(),
+            b"/work/api\0"
+        );
+
+        let escaping = [BundleFile {
+            path: "../outside.jsonl".to_string(),
+            content: String::new(),
+        }];
+        assert!(write_bundle_files(&escaping, target.path(), "/a", "/b").is_err());
+    }
+
+    #[test]
+    fn test_import_project_dir_stays_inside_projects_dir() {
+        let projects = Path::new("/home/me/.claude/projects");
+        let (project_id, project_dir) = import_project_dir(projects, "/work/api").unwrap();
+        assert_eq!(project_id, "-work-api");
+        assert_eq!(project_dir, projects.join("-work-api"));
+
+        for project_path in [
+            "",
+            ".",
+            "..",
+            "a/../..",
+            r"C:\work\api",
+            "C:",
+            r"\\server\share",
+        ] {
+            assert!(
+                import_project_dir(projects, project_path).is_err(),
+                "accepted {:?}",
+                project_path
+            );
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::claude::list_projects;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::process::normalize_project_path;
+
+/// app_settings key holding the discovery settings as JSON
+const DISCOVERY_SETTINGS_KEY: &str = "project_discovery";
+
+/// Directories never worth descending into while looking for repositories
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", "dist", "build"];
+
+/// Where to look for git repositories that have no Claude project yet
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProjectDiscoverySettings {
+    /// Directories to scan; `~` expands to the home directory
+    pub roots: Vec<String>,
+    /// How many levels below each root to look
+    pub max_depth: usize,
+}
+
+impl Default for ProjectDiscoverySettings {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            max_depth: 3,
+        }
+    }
+}
+
+/// A git repository found under a discovery root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredProject {
+    pub path: String,
+    pub name: String,
+    /// Unix timestamp of the repository's last git activity
+    pub last_activity: Option<u64>,
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~") {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => match dirs::home_dir() {
+            Some(home) => home.join(rest.trim_start_matches(['/', '\\'])),
+            None => PathBuf::from(path),
+        },
+        _ => PathBuf::from(path),
+    }
+}
+
+fn load_discovery_settings(conn: &Connection) -> ProjectDiscoverySettings {
+    load_json_setting(conn, DISCOVERY_SETTINGS_KEY)
+}
+
+/// Git repositories under `root`, up to `max_depth` levels down.
+///
+/// Hidden directories and build output are skipped, and a repository's own
+/// subdirectories aren't searched for nested ones.
+pub fn find_git_repos(root: &Path, max_depth: usize) -> Vec<PathBuf> {
+    let mut repos = Vec::new();
+    let mut walker = walkdir::WalkDir::new(root)
+        .max_depth(max_depth)
+        .follow_links(false)
+        .into_iter();
+
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        if entry.depth() > 0 && (name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref())) {
+            walker.skip_current_dir();
+            continue;
+        }
+        // .git is a file in worktrees and submodules
+        if entry.path().join(".git").exists() {
+            repos.push(entry.path().to_path_buf());
+            walker.skip_current_dir();
+        }
+    }
+    repos
+}
+
+fn last_activity(repo: &Path) -> Option<u64> {
+    std::fs::metadata(repo.join(".git"))
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+}
+
+/// Get the directories scanned for new projects
+#[tauri::command]
+pub async fn get_project_discovery_settings(
+    db: State<'_, AgentDb>,
+) -> Result<ProjectDiscoverySettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_discovery_settings(&conn))
+}
+
+/// Set the directories scanned for new projects
+#[tauri::command]
+pub async fn save_project_discovery_settings(
+    db: State<'_, AgentDb>,
+    settings: ProjectDiscoverySettings,
+) -> Result<(), String> {
+    if settings.max_depth == 0 {
+        return Err("Discovery depth must be at least 1".to_string());
+    }
+    for root in &settings.roots {
+        if !expand_home(root).is_dir() {
+            return Err(format!("Discovery root '{}' is not a directory", root));
+        }
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, DISCOVERY_SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+/// Scan the discovery roots for git repositories that have no Claude project yet,
+/// most recently active first
+#[tauri::command]
+pub async fn discover_projects(db: State<'_, AgentDb>) -> Result<Vec<DiscoveredProject>, String> {
+    let settings = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_discovery_settings(&conn)
+    };
+    let known: HashSet<String> = list_projects()
+        .await?
+        .into_iter()
+        .map(|project| normalize_project_path(&project.path))
+        .collect();
+
+    let mut projects = tauri::async_runtime::spawn_blocking(move || {
+        let mut seen = HashSet::new();
+        let mut projects = Vec::new();
+        for root in &settings.roots {
+            for repo in find_git_repos(&expand_home(root), settings.max_depth) {
+                let path = normalize_project_path(&repo.to_string_lossy());
+                if known.contains(&path) || !seen.insert(path.clone()) {
+                    continue;
+                }
+                projects.push(DiscoveredProject {
+                    name: repo
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_else(|| path.clone()),
+                    last_activity: last_activity(&repo),
+                    path,
+                });
+            }
+        }
+        projects
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    projects.sort_by_key(|project| std::cmp::Reverse(project.last_activity));
+    Ok(projects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_git_repos() {
+        let root = tempfile::tempdir().unwrap();
+        for dir in [
+            "api/.git",
+            "api/packages/nested/.git",
+            "clients/web/.git",
+            "clients/web/node_modules/dep/.git",
+            ".cache/tool/.git",
+            "deep/a/b/c/.git",
+            "notes",
+        ] {
+            std::fs::create_dir_all(root.path().join(dir)).unwrap();
+        }
+        // Worktrees have a .git file instead of a directory
+        std::fs::create_dir_all(root.path().join("api-feature")).unwrap();
+        std::fs::write(root.path().join("api-feature/.git"), "gitdir: ../api/.git").unwrap();
+
+        let mut repos: Vec<String> = find_git_repos(root.path(), 3)
+            .into_iter()
+            .map(|p| {
+                p.strip_prefix(root.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        repos.sort();
+        assert_eq!(repos, vec!["api", "api-feature", "clients/web"]);
+    }
+}
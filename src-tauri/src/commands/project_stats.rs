@@ -0,0 +1,348 @@
+use chrono::DateTime;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::budgets::RunUsageTracker;
+
+/// How often a tool was used across a project's sessions
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolUseCount {
+    pub tool: String,
+    pub count: i64,
+}
+
+/// Aggregate statistics of a project's sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectStats {
+    /// Encoded directory name under ~/.claude/projects
+    pub project_id: String,
+    pub session_count: i64,
+    /// User and assistant messages
+    pub message_count: i64,
+    /// Most used tools first
+    pub tool_uses: Vec<ToolUseCount>,
+    /// Mean time from a session's first message to its last
+    pub average_session_secs: f64,
+    pub average_messages_per_session: f64,
+    pub total_tokens: i64,
+    pub total_cost_usd: f64,
+}
+
+/// Figures from one pass over a session file
+#[derive(Debug, Default, PartialEq)]
+struct SessionFileStats {
+    messages: i64,
+    tool_uses: BTreeMap<String, i64>,
+    duration_secs: i64,
+    total_tokens: i64,
+    cost_usd: f64,
+}
+
+/// Stream through a session transcript, keeping only the counts
+fn scan_session(reader: impl BufRead) -> SessionFileStats {
+    let mut stats = SessionFileStats::default();
+    let mut usage = RunUsageTracker::default();
+    let (mut first, mut last) = (None, None);
+
+    for line in reader.lines().map_while(Result::ok) {
+        let Ok(json) = serde_json::from_str::<JsonValue>(&line) else {
+            continue;
+        };
+        usage.record(&json);
+
+        let role = json.get("type").and_then(|t| t.as_str());
+        if !matches!(role, Some("user" | "assistant")) || json.get("message").is_none() {
+            continue;
+        }
+        stats.messages += 1;
+
+        if let Some(timestamp) = json
+            .get("timestamp")
+            .and_then(|t| t.as_str())
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        {
+            first.get_or_insert(timestamp);
+            last = Some(timestamp);
+        }
+
+        if let Some(JsonValue::Array(blocks)) = json.get("message").and_then(|m| m.get("content")) {
+            for block in blocks {
+                if block.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
+                    let tool = block
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or("unknown");
+                    *stats.tool_uses.entry(tool.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    if let (Some(first), Some(last)) = (first, last) {
+        stats.duration_secs = (last - first).num_seconds().max(0);
+    }
+    stats.total_tokens = usage.total_tokens() as i64;
+    stats.cost_usd = usage.cost_usd();
+    stats
+}
+
+fn project_dir(project_id: &str) -> Result<PathBuf, String> {
+    if project_id.is_empty() || project_id.contains(['/', '\\']) || project_id.contains("..") {
+        return Err(format!("Invalid project id '{}'", project_id));
+    }
+    Ok(dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("projects")
+        .join(project_id))
+}
+
+/// Session files of a project with their size and modification time
+fn session_files(dir: &Path) -> Vec<(PathBuf, i64, i64)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            let modified = metadata
+                .modified()
+                .ok()?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_secs() as i64;
+            Some((path, metadata.len() as i64, modified))
+        })
+        .collect()
+}
+
+/// Bring the cached per-session figures of a project up to date, scanning only the
+/// session files that changed since the last refresh
+fn refresh_project(db: &AgentDb, project_id: &str, dir: &Path) -> Result<(), String> {
+    let files = session_files(dir);
+    let known: HashMap<String, (i64, i64)> = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT path, file_size, modified FROM session_stats WHERE project_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![project_id], |row| {
+                Ok((row.get(0)?, (row.get(1)?, row.get(2)?)))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+
+    // Parse without holding the database lock
+    let mut scanned = Vec::new();
+    for (path, size, modified) in &files {
+        let key = path.to_string_lossy().to_string();
+        if known.get(&key) == Some(&(*size, *modified)) {
+            continue;
+        }
+        let Ok(file) = std::fs::File::open(path) else {
+            continue;
+        };
+        let session_id = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        scanned.push((
+            key,
+            session_id,
+            *size,
+            *modified,
+            scan_session(BufReader::new(file)),
+        ));
+    }
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let current: Vec<String> = files
+        .iter()
+        .map(|(path, _, _)| path.to_string_lossy().to_string())
+        .collect();
+    for path in known.keys().filter(|path| !current.contains(path)) {
+        delete_session_stats(&tx, path)?;
+    }
+    for (path, session_id, size, modified, stats) in &scanned {
+        delete_session_stats(&tx, path)?;
+        tx.execute(
+            "INSERT INTO session_stats (path, project_id, session_id, file_size, modified, messages, duration_secs, total_tokens, cost_usd)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                path,
+                project_id,
+                session_id,
+                size,
+                modified,
+                stats.messages,
+                stats.duration_secs,
+                stats.total_tokens,
+                stats.cost_usd
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        for (tool, count) in &stats.tool_uses {
+            tx.execute(
+                "INSERT INTO session_tool_uses (path, project_id, tool, count) VALUES (?1, ?2, ?3, ?4)",
+                params![path, project_id, tool, count],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    if !scanned.is_empty() {
+        log::info!(
+            "Refreshed stats of {} sessions in {}",
+            scanned.len(),
+            project_id
+        );
+    }
+    Ok(())
+}
+
+fn delete_session_stats(conn: &Connection, path: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM session_stats WHERE path = ?1", params![path])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM session_tool_uses WHERE path = ?1",
+        params![path],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Aggregate the cached figures of a project
+fn load_project_stats(conn: &Connection, project_id: &str) -> Result<ProjectStats, String> {
+    let mut stats = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(messages), 0), COALESCE(AVG(duration_secs), 0),
+                    COALESCE(AVG(messages), 0), COALESCE(SUM(total_tokens), 0), COALESCE(SUM(cost_usd), 0)
+             FROM session_stats WHERE project_id = ?1",
+            params![project_id],
+            |row| {
+                Ok(ProjectStats {
+                    project_id: project_id.to_string(),
+                    session_count: row.get(0)?,
+                    message_count: row.get(1)?,
+                    tool_uses: Vec::new(),
+                    average_session_secs: row.get(2)?,
+                    average_messages_per_session: row.get(3)?,
+                    total_tokens: row.get(4)?,
+                    total_cost_usd: row.get(5)?,
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT tool, SUM(count) AS total FROM session_tool_uses WHERE project_id = ?1
+             GROUP BY tool ORDER BY total DESC, tool",
+        )
+        .map_err(|e| e.to_string())?;
+    stats.tool_uses = stmt
+        .query_map(params![project_id], |row| {
+            Ok(ToolUseCount {
+                tool: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(stats)
+}
+
+/// Get session counts, message counts, tool usage, average session length and cost
+/// for a project. Only sessions that changed since the last call are re-read.
+#[tauri::command]
+pub async fn get_project_stats(
+    db: State<'_, AgentDb>,
+    project_id: String,
+) -> Result<ProjectStats, String> {
+    let dir = project_dir(&project_id)?;
+    if !dir.is_dir() {
+        return Err(format!("Project directory not found: {}", project_id));
+    }
+    refresh_project(&db, &project_id, &dir)?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_project_stats(&conn, &project_id)
+}
+
+/// Get the statistics of every project, most expensive first
+#[tauri::command]
+pub async fn get_all_project_stats(db: State<'_, AgentDb>) -> Result<Vec<ProjectStats>, String> {
+    let projects_dir = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("projects");
+    let project_ids: Vec<String> = std::fs::read_dir(&projects_dir)
+        .map_err(|e| format!("Failed to read projects directory: {}", e))?
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+
+    let mut all = Vec::new();
+    for project_id in project_ids {
+        refresh_project(&db, &project_id, &projects_dir.join(&project_id))?;
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        all.push(load_project_stats(&conn, &project_id)?);
+    }
+    all.sort_by(|a, b| b.total_cost_usd.total_cmp(&a.total_cost_usd));
+    Ok(all)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_scan_session() {
+        let jsonl = [
+            json!({"type": "summary", "summary": "Fix build"}),
+            json!({"type": "user", "timestamp": "2025-01-01T10:00:00Z", "message": {"role": "user", "content": "Fix the build"}}),
+            json!({"type": "assistant", "timestamp": "2025-01-01T10:00:30Z", "message": {"id": "m1", "model": "claude-sonnet-4", "content": [
+                {"type": "text", "text": "Checking"},
+                {"type": "tool_use", "name": "Bash"},
+                {"type": "tool_use", "name": "Edit"}
+            ], "usage": {"input_tokens": 100, "output_tokens": 50}}}),
+            json!({"type": "user", "timestamp": "2025-01-01T10:01:00Z", "message": {"content": [{"type": "tool_result", "content": "ok"}]}}),
+            json!({"type": "assistant", "timestamp": "2025-01-01T10:05:00Z", "message": {"id": "m2", "content": [
+                {"type": "tool_use", "name": "Bash"}
+            ], "usage": {"input_tokens": 200, "output_tokens": 20}}}),
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join("\nnot json\n");
+
+        let stats = scan_session(BufReader::new(jsonl.as_bytes()));
+        assert_eq!(stats.messages, 4);
+        assert_eq!(stats.duration_secs, 300);
+        assert_eq!(stats.total_tokens, 370);
+        assert_eq!(
+            stats.tool_uses,
+            BTreeMap::from([("Bash".to_string(), 2), ("Edit".to_string(), 1)])
+        );
+        assert!(stats.cost_usd > 0.0);
+    }
+}
@@ -0,0 +1,264 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::agent_parameters::{render_prompt, resolve_parameters, AgentParameter};
+use crate::commands::agents::AgentDb;
+use crate::process::{normalize_project_path, SessionTabsState};
+
+/// A reusable prompt with `{{variables}}`, available everywhere or in one project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub content: String,
+    /// Declared variables, type-checked and defaulted like agent parameters
+    pub variables: Vec<AgentParameter>,
+    /// Project the template belongs to; global templates have none
+    pub project_path: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn row_to_template(row: &rusqlite::Row) -> rusqlite::Result<PromptTemplate> {
+    let variables: String = row.get(4)?;
+    Ok(PromptTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        content: row.get(3)?,
+        variables: serde_json::from_str(&variables).unwrap_or_default(),
+        project_path: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+fn load_template(conn: &Connection, template_id: i64) -> Result<PromptTemplate, String> {
+    conn.query_row(
+        "SELECT id, name, description, content, variables, project_path, created_at, updated_at
+         FROM prompt_templates WHERE id = ?1",
+        params![template_id],
+        row_to_template,
+    )
+    .map_err(|e| format!("Prompt template {} not found: {}", template_id, e))
+}
+
+/// Global templates plus those of `project_path`, project ones first
+pub fn list_templates(
+    conn: &Connection,
+    project_path: Option<&str>,
+) -> Result<Vec<PromptTemplate>, String> {
+    let project_path = project_path.map(normalize_project_path);
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, name, description, content, variables, project_path, created_at, updated_at
+             FROM prompt_templates WHERE project_path IS NULL OR project_path = ?1
+             ORDER BY project_path IS NULL, name COLLATE NOCASE",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let templates = stmt
+        .query_map(params![project_path], row_to_template)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(templates)
+}
+
+fn validate_template(name: &str, variables: &[AgentParameter]) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Prompt template name cannot be empty".to_string());
+    }
+    for variable in variables {
+        if variable.name.trim().is_empty() || variable.name.contains(['{', '}']) {
+            return Err(format!("Invalid variable name '{}'", variable.name));
+        }
+    }
+    Ok(name.to_string())
+}
+
+/// Fill in a template's variables; undeclared values are substituted as-is
+pub fn render_template(
+    template: &PromptTemplate,
+    values: &HashMap<String, JsonValue>,
+) -> Result<String, String> {
+    let values = resolve_parameters(&template.variables, values)?;
+    Ok(render_prompt(&template.content, &values))
+}
+
+/// List the prompt templates available in a project: its own and the global ones.
+/// Without a project only the global templates are listed.
+#[tauri::command]
+pub async fn list_prompt_templates(
+    db: State<'_, AgentDb>,
+    project_path: Option<String>,
+) -> Result<Vec<PromptTemplate>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_templates(&conn, project_path.as_deref())
+}
+
+/// Create a prompt template, scoped to a project when `project_path` is given
+#[tauri::command]
+pub async fn create_prompt_template(
+    db: State<'_, AgentDb>,
+    name: String,
+    content: String,
+    description: Option<String>,
+    variables: Option<Vec<AgentParameter>>,
+    project_path: Option<String>,
+) -> Result<PromptTemplate, String> {
+    let variables = variables.unwrap_or_default();
+    let name = validate_template(&name, &variables)?;
+    let variables_json = serde_json::to_string(&variables).map_err(|e| e.to_string())?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO prompt_templates (name, description, content, variables, project_path)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            name,
+            description,
+            content,
+            variables_json,
+            project_path.as_deref().map(normalize_project_path)
+        ],
+    )
+    .map_err(|e| format!("Failed to create prompt template '{}': {}", name, e))?;
+
+    load_template(&conn, conn.last_insert_rowid())
+}
+
+/// Update a prompt template; fields that aren't given are left unchanged
+#[tauri::command]
+pub async fn update_prompt_template(
+    db: State<'_, AgentDb>,
+    template_id: i64,
+    name: Option<String>,
+    content: Option<String>,
+    description: Option<String>,
+    variables: Option<Vec<AgentParameter>>,
+) -> Result<PromptTemplate, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let existing = load_template(&conn, template_id)?;
+
+    let variables = variables.unwrap_or(existing.variables);
+    let name = validate_template(name.as_deref().unwrap_or(&existing.name), &variables)?;
+    let variables_json = serde_json::to_string(&variables).map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE prompt_templates
+         SET name = ?1, description = ?2, content = ?3, variables = ?4, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?5",
+        params![
+            name,
+            description.or(existing.description),
+            content.unwrap_or(existing.content),
+            variables_json,
+            template_id
+        ],
+    )
+    .map_err(|e| format!("Failed to update prompt template {}: {}", template_id, e))?;
+
+    load_template(&conn, template_id)
+}
+
+/// Delete a prompt template
+#[tauri::command]
+pub async fn delete_prompt_template(
+    db: State<'_, AgentDb>,
+    template_id: i64,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let deleted = conn
+        .execute(
+            "DELETE FROM prompt_templates WHERE id = ?1",
+            params![template_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if deleted == 0 {
+        return Err(format!("Prompt template {} not found", template_id));
+    }
+    Ok(())
+}
+
+/// Render a template with the given variable values. With `tab_id`, the result is
+/// also sent to that session tab's input on `session-input:{tab_id}`.
+#[tauri::command]
+pub async fn render_prompt_template(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    tabs: State<'_, SessionTabsState>,
+    template_id: i64,
+    values: Option<HashMap<String, JsonValue>>,
+    tab_id: Option<String>,
+) -> Result<String, String> {
+    let template = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_template(&conn, template_id)?
+    };
+    let rendered = render_template(&template, &values.unwrap_or_default())?;
+
+    if let Some(tab_id) = tab_id {
+        tabs.0.get(&tab_id)?;
+        let _ = app.emit(&format!("session-input:{}", tab_id), &rendered);
+    }
+
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::agent_parameters::ParameterType;
+    use crate::commands::agents::open_database;
+    use serde_json::json;
+
+    #[test]
+    fn test_list_and_render_templates() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = open_database(&dir.path().join("agents.db")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO prompt_templates (id, name, content) VALUES (1, 'Review', 'Review {{file}} for {{focus}}');
+             INSERT INTO prompt_templates (id, name, content, project_path) VALUES (2, 'Deploy', 'Deploy the api', '/work/api');
+             INSERT INTO prompt_templates (id, name, content, project_path) VALUES (3, 'Lint', 'Lint the web app', '/work/web');",
+        )
+        .unwrap();
+
+        let names = |project: Option<&str>| -> Vec<String> {
+            list_templates(&conn, project)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.name)
+                .collect()
+        };
+        assert_eq!(names(Some("/work/api/")), vec!["Deploy", "Review"]);
+        assert_eq!(names(None), vec!["Review"]);
+
+        let mut template = load_template(&conn, 1).unwrap();
+        template.variables = vec![
+            AgentParameter {
+                name: "file".to_string(),
+                param_type: ParameterType::String,
+                default: None,
+                description: None,
+            },
+            AgentParameter {
+                name: "focus".to_string(),
+                param_type: ParameterType::String,
+                default: Some(json!("bugs")),
+                description: None,
+            },
+        ];
+        let values = HashMap::from([("file".to_string(), json!("src/main.rs"))]);
+        assert_eq!(
+            render_template(&template, &values).unwrap(),
+            "Review src/main.rs for bugs"
+        );
+        assert!(render_template(&template, &HashMap::new()).is_err());
+    }
+}
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use tauri::State;
 
 use crate::commands::agents::AgentDb;
+use crate::commands::settings::save_setting;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProxySettings {
@@ -85,11 +86,7 @@ pub async fn save_proxy_settings(
     ];
 
     for (key, value) in values {
-        conn.execute(
-            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
-            params![key, value],
-        )
-        .map_err(|e| format!("Failed to save {}: {}", key, e))?;
+        save_setting(&conn, key, &value)?;
     }
 
     // Apply the proxy settings immediately to the current process
@@ -0,0 +1,301 @@
+//! Running agent runs on a pseudo-terminal
+//!
+//! Claude behaves differently without a TTY: spinners, the interactive login
+//! and hooks that check `isatty` only work on a terminal. In PTY mode an agent
+//! run is spawned on a pseudo-terminal instead of pipes. Its stdout and stderr
+//! arrive merged on the terminal, so every line has its escape sequences and
+//! carriage-return redraws removed before it is parsed as stream output.
+
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader};
+use std::sync::{Mutex, OnceLock};
+use tauri::State;
+use tokio::io::{BufReader as TokioBufReader, Lines};
+use tokio::process::ChildStdout;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+
+/// app_settings key holding the PTY mode settings as JSON
+const SETTINGS_KEY: &str = "pty_mode";
+
+/// Lines held between the terminal reader thread and the run's output task
+const LINE_BUFFER: usize = 1024;
+
+/// Terminals of running agent runs, by run ID, so they can be resized
+static TERMINALS: OnceLock<Mutex<HashMap<i64, Box<dyn MasterPty + Send>>>> = OnceLock::new();
+
+/// Whether agent runs are spawned on a pseudo-terminal
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PtyModeSettings {
+    pub enabled: bool,
+    /// Terminal size a run starts with, until the UI reports its own
+    pub cols: u16,
+    pub rows: u16,
+}
+
+impl Default for PtyModeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cols: 120,
+            rows: 40,
+        }
+    }
+}
+
+fn load_settings(conn: &Connection) -> PtyModeSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// The PTY mode settings of the app database at `db`
+pub fn load_pty_mode(db: &AgentDb) -> PtyModeSettings {
+    db.0.lock()
+        .map(|conn| load_settings(&conn))
+        .unwrap_or_default()
+}
+
+fn terminals() -> &'static Mutex<HashMap<i64, Box<dyn MasterPty + Send>>> {
+    TERMINALS.get_or_init(Default::default)
+}
+
+fn pty_size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Remove escape sequences (colours, cursor movement, window titles) and
+/// control characters other than tabs
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            if !c.is_control() || c == '\t' {
+                stripped.push(c);
+            }
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters up to a final byte
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: up to BEL or ESC \
+            Some(']') => {
+                for c in chars.by_ref() {
+                    if c == '\x07' || c == '\\' {
+                        break;
+                    }
+                }
+            }
+            // Character set selection takes one more byte
+            Some('(' | ')') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// A line read from the terminal as plain text: what a carriage return drew
+/// over is dropped along with escape sequences
+pub fn clean_line(line: &str) -> String {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let redrawn = line.rsplit('\r').next().unwrap_or(line);
+    strip_ansi(redrawn)
+}
+
+/// The same command for a terminal: program, arguments, environment and
+/// working directory
+fn command_builder(cmd: &std::process::Command) -> CommandBuilder {
+    let mut builder = CommandBuilder::new(cmd.get_program());
+    builder.args(cmd.get_args());
+    for (key, value) in cmd.get_envs() {
+        match value {
+            Some(value) => builder.env(key, value),
+            None => builder.env_remove(key),
+        }
+    }
+    if let Some(dir) = cmd.get_current_dir() {
+        builder.cwd(dir);
+    }
+    if builder.get_env("TERM").is_none() {
+        builder.env("TERM", "xterm-256color");
+    }
+    builder
+}
+
+/// Cleaned lines of a run on a terminal
+pub struct PtyLines {
+    receiver: mpsc::Receiver<String>,
+}
+
+/// A process spawned on a pseudo-terminal
+pub struct PtyProcess {
+    pub pid: u32,
+    pub output: PtyLines,
+    /// Sent the exit code once the process has exited
+    pub exit: oneshot::Receiver<Option<i32>>,
+}
+
+/// Where a run's output is read from
+pub enum RunOutput {
+    Pipe(Lines<TokioBufReader<ChildStdout>>),
+    Pty(PtyLines),
+}
+
+impl RunOutput {
+    /// The next line of output, None once it has ended
+    pub async fn next_line(&mut self) -> io::Result<Option<String>> {
+        match self {
+            RunOutput::Pipe(lines) => lines.next_line().await,
+            RunOutput::Pty(lines) => Ok(lines.receiver.recv().await),
+        }
+    }
+}
+
+/// Spawn `cmd` on a new pseudo-terminal, kept under `run_id` until it exits so
+/// `resize_run_terminal` can reach it
+pub fn spawn_on_pty(
+    cmd: &std::process::Command,
+    run_id: i64,
+    settings: &PtyModeSettings,
+) -> Result<PtyProcess, String> {
+    let pair = native_pty_system()
+        .openpty(pty_size(settings.cols, settings.rows))
+        .map_err(|e| format!("Failed to open a terminal: {}", e))?;
+    let mut child = pair
+        .slave
+        .spawn_command(command_builder(cmd))
+        .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
+    // Only the child holds the terminal open now, so reading ends when it exits
+    drop(pair.slave);
+    let pid = child.process_id().unwrap_or(0);
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to read the terminal: {}", e))?;
+    terminals()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(run_id, pair.master);
+
+    let (line_tx, line_rx) = mpsc::channel(LINE_BUFFER);
+    let (exit_tx, exit_rx) = oneshot::channel();
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            // The terminal reports an error rather than end of file once the
+            // child is gone, possibly after part of a last line
+            let read = reader.read_until(b'\n', &mut buf);
+            let line = clean_line(&String::from_utf8_lossy(&buf));
+            buf.clear();
+            if !line.is_empty() && line_tx.blocking_send(line).is_err() {
+                break;
+            }
+            if !matches!(read, Ok(n) if n > 0) {
+                break;
+            }
+        }
+        drop(line_tx);
+
+        let exit_code = child.wait().ok().map(|status| status.exit_code() as i32);
+        if let Ok(mut terminals) = terminals().lock() {
+            terminals.remove(&run_id);
+        }
+        let _ = exit_tx.send(exit_code);
+    });
+
+    Ok(PtyProcess {
+        pid,
+        output: PtyLines { receiver: line_rx },
+        exit: exit_rx,
+    })
+}
+
+/// Pass the UI's terminal size on to an agent run on a pseudo-terminal
+#[tauri::command]
+pub async fn resize_run_terminal(run_id: i64, cols: u16, rows: u16) -> Result<(), String> {
+    if cols == 0 || rows == 0 {
+        return Err("The terminal size must be greater than zero".to_string());
+    }
+    let terminals = terminals().lock().map_err(|e| e.to_string())?;
+    let master = terminals
+        .get(&run_id)
+        .ok_or_else(|| format!("Run {} isn't running on a terminal", run_id))?;
+    master
+        .resize(pty_size(cols, rows))
+        .map_err(|e| format!("Failed to resize the terminal: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_pty_mode_settings(db: State<'_, AgentDb>) -> Result<PtyModeSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_pty_mode_settings(
+    db: State<'_, AgentDb>,
+    settings: PtyModeSettings,
+) -> Result<(), String> {
+    if settings.cols == 0 || settings.rows == 0 {
+        return Err("The terminal size must be greater than zero".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_line() {
+        assert_eq!(
+            clean_line("{\"type\":\"result\",\"result\":\"a\\tb\"}\r\n"),
+            "{\"type\":\"result\",\"result\":\"a\\tb\"}"
+        );
+        assert_eq!(
+            clean_line("\x1b[?25l\x1b[32m⠋ Thinking\x1b[0m\r\x1b[2K{\"type\":\"system\"}\r"),
+            "{\"type\":\"system\"}"
+        );
+        assert_eq!(
+            clean_line("\x1b]0;claude\x07\x1b(Bcol\tumn\x07"),
+            "col\tumn"
+        );
+        assert_eq!(clean_line("\x1b[1A\x1b[2K\r"), "");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_on_pty() {
+        let mut cmd = std::process::Command::new("sh");
+        cmd.args(["-c", "test -t 1 && printf '\\033[1mtty\\033[0m\\n'; exit 3"]);
+        let mut pty = spawn_on_pty(&cmd, 1, &PtyModeSettings::default()).unwrap();
+        assert!(pty.pid > 0);
+
+        let mut output = RunOutput::Pty(pty.output);
+        assert_eq!(output.next_line().await.unwrap().as_deref(), Some("tty"));
+        assert_eq!(output.next_line().await.unwrap(), None);
+        assert_eq!((&mut pty.exit).await.unwrap(), Some(3));
+        assert!(resize_run_terminal(1, 80, 24).await.is_err());
+    }
+}
@@ -0,0 +1,444 @@
+use log::{error, info};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::{launch_agent_run, AgentDb};
+use crate::commands::settings::{load_json_setting, save_json_setting, save_setting};
+use crate::process::{
+    cap_claude_processes, plan_queue, ProcessRegistryState, QueueVerdict, RunQueueSettings,
+    RunQueueState, RunScheduler, SchedulerSettings,
+};
+
+/// How often the dispatcher re-checks the queue even without a wakeup
+const DISPATCH_INTERVAL_SECS: u64 = 5;
+
+/// app_settings key holding the scheduler settings as JSON
+const SCHEDULER_SETTINGS_KEY: &str = "scheduler";
+
+/// A run waiting in the queue
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueuedRun {
+    pub run_id: i64,
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub agent_icon: String,
+    pub task: String,
+    pub project_path: String,
+    /// 1-based position in the queue
+    pub position: usize,
+    pub verdict: QueueVerdict,
+    pub created_at: String,
+}
+
+/// Load the run queue limits from app_settings
+pub fn load_queue_settings(conn: &Connection) -> RunQueueSettings {
+    let mut settings = RunQueueSettings::default();
+
+    let read = |key: &str| -> Option<u32> {
+        conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .and_then(|v| v.parse().ok())
+    };
+
+    if let Some(value) = read("queue_max_concurrent_runs") {
+        settings.max_concurrent_runs = value;
+    }
+    if let Some(value) = read("queue_max_concurrent_per_project") {
+        settings.max_concurrent_per_project = value;
+    }
+
+    settings
+}
+
+/// Load the limits shared by all Claude processes from app_settings
+pub fn load_scheduler_settings(conn: &Connection) -> SchedulerSettings {
+    load_json_setting(conn, SCHEDULER_SETTINGS_KEY)
+}
+
+/// Refuse to start a Claude session once the app runs as many Claude
+/// processes as the scheduler allows
+pub fn check_session_capacity(app: &AppHandle) -> Result<(), String> {
+    let settings = app
+        .try_state::<AgentDb>()
+        .and_then(|db| db.0.lock().ok().map(|conn| load_scheduler_settings(&conn)))
+        .unwrap_or_default();
+    let running = app
+        .state::<ProcessRegistryState>()
+        .0
+        .get_running_processes()?
+        .len();
+    crate::process::check_claude_capacity(running, &settings)
+}
+
+/// Project a run counts against for the per-project limit: its repository
+/// when `scheduler` is given, otherwise its project directory.
+///
+/// Read-only runs can't clobber each other's edits, so each one gets a slot of its own.
+fn queue_project_key(
+    run_id: i64,
+    project_path: String,
+    read_only: bool,
+    scheduler: Option<&RunScheduler>,
+) -> String {
+    let project_path = match scheduler {
+        Some(scheduler) => scheduler.repository_key(&project_path),
+        None => project_path,
+    };
+    if read_only {
+        format!("{}#read-only-{}", project_path, run_id)
+    } else {
+        project_path
+    }
+}
+
+/// Queued runs in queue order as (run_id, project key)
+fn queued_runs(
+    conn: &Connection,
+    scheduler: Option<&RunScheduler>,
+) -> Result<Vec<(i64, String)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, project_path, COALESCE(read_only, 0) FROM agent_runs WHERE status = 'queued'
+             ORDER BY queue_position ASC, id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let runs = stmt
+        .query_map([], |row| {
+            let run_id: i64 = row.get(0)?;
+            Ok((
+                run_id,
+                queue_project_key(run_id, row.get(1)?, row.get(2)?, scheduler),
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(runs)
+}
+
+/// Project paths of runs that are actually running (cross-checked with the registry)
+fn active_run_projects(
+    conn: &Connection,
+    registry: &ProcessRegistryState,
+    scheduler: Option<&RunScheduler>,
+) -> Result<Vec<String>, String> {
+    let registry_run_ids: std::collections::HashSet<i64> = registry
+        .0
+        .get_running_agent_processes()?
+        .iter()
+        .map(|p| p.run_id)
+        .collect();
+
+    let mut stmt = conn
+        .prepare("SELECT id, project_path, COALESCE(read_only, 0) FROM agent_runs WHERE status = 'running'")
+        .map_err(|e| e.to_string())?;
+
+    let projects = stmt
+        .query_map([], |row| {
+            let run_id: i64 = row.get(0)?;
+            Ok((
+                run_id,
+                queue_project_key(run_id, row.get(1)?, row.get(2)?, scheduler),
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        // Runs left 'running' by a crash aren't in the registry and shouldn't hold a slot
+        .filter(|(id, _)| registry_run_ids.contains(id))
+        .map(|(_, project)| project)
+        .collect();
+
+    Ok(projects)
+}
+
+/// Resolve the repositories of queued and running runs ahead of planning, so
+/// git never runs while the database lock is held
+async fn resolve_run_repositories(db: &AgentDb, scheduler: &RunScheduler) -> Result<(), String> {
+    let project_paths = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if !load_scheduler_settings(&conn).serialize_repositories {
+            return Ok(());
+        }
+        let mut stmt = conn
+            .prepare(
+                "SELECT DISTINCT project_path FROM agent_runs WHERE status IN ('queued', 'running')",
+            )
+            .map_err(|e| e.to_string())?;
+        let paths = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        paths
+    };
+    scheduler.resolve_repositories(project_paths).await;
+    Ok(())
+}
+
+/// The queue plan under both the queue's limits and the scheduler's
+fn plan_runs(
+    conn: &Connection,
+    registry: &ProcessRegistryState,
+    scheduler: &RunScheduler,
+) -> Result<Vec<(i64, QueueVerdict)>, String> {
+    let settings = load_queue_settings(conn);
+    let scheduler_settings = load_scheduler_settings(conn);
+    let scheduler = scheduler_settings
+        .serialize_repositories
+        .then_some(scheduler);
+    let queued = queued_runs(conn, scheduler)?;
+    if queued.is_empty() {
+        return Ok(Vec::new());
+    }
+    let active = active_run_projects(conn, registry, scheduler)?;
+    let running = registry.0.get_running_processes()?.len();
+
+    Ok(cap_claude_processes(
+        plan_queue(&queued, &active, &settings),
+        running,
+        &scheduler_settings,
+    ))
+}
+
+/// Launch as many queued runs as the concurrency limits allow.
+///
+/// Returns the launch result for each run that was started.
+pub async fn dispatch_queued_runs(
+    app: &AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<Vec<(i64, Result<(), String>)>, String> {
+    let queue = app.state::<RunQueueState>();
+    let _guard = queue.dispatch_lock.lock().await;
+
+    let scheduler = app.state::<RunScheduler>();
+    resolve_run_repositories(&db, &scheduler).await?;
+    let runnable: Vec<i64> = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        plan_runs(&conn, &registry, &scheduler)?
+            .into_iter()
+            .filter(|(_, verdict)| *verdict == QueueVerdict::Runnable)
+            .map(|(run_id, _)| run_id)
+            .collect()
+    };
+
+    let mut results = Vec::new();
+    for run_id in runnable {
+        info!("Dispatching queued agent run {}", run_id);
+        let result = launch_agent_run(app.clone(), run_id, db.clone(), registry.clone()).await;
+
+        if let Err(e) = &result {
+            error!("Failed to launch queued agent run {}: {}", run_id, e);
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            let _ = conn.execute(
+                "UPDATE agent_runs SET status = 'failed', queue_position = NULL, completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                params![run_id],
+            );
+            let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+        }
+
+        results.push((run_id, result));
+    }
+
+    if !results.is_empty() {
+        let _ = app.emit("agent-queue-updated", true);
+    }
+
+    Ok(results)
+}
+
+/// Start the background task that launches queued runs as slots free up
+pub fn start_queue_dispatcher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            {
+                let queue = app.state::<RunQueueState>();
+                let _ = tokio::time::timeout(
+                    tokio::time::Duration::from_secs(DISPATCH_INTERVAL_SECS),
+                    queue.wakeup.notified(),
+                )
+                .await;
+            }
+
+            if let Err(e) = dispatch_queued_runs(
+                &app,
+                app.state::<AgentDb>(),
+                app.state::<ProcessRegistryState>(),
+            )
+            .await
+            {
+                error!("Run queue dispatch failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Get the current run queue with each run's position and blocking reason
+#[tauri::command]
+pub async fn get_run_queue(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    scheduler: State<'_, RunScheduler>,
+) -> Result<Vec<QueuedRun>, String> {
+    resolve_run_repositories(&db, &scheduler).await?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let plan = plan_runs(&conn, &registry, &scheduler)?;
+
+    let mut runs = Vec::new();
+    for (index, (run_id, verdict)) in plan.into_iter().enumerate() {
+        let run = conn
+            .query_row(
+                "SELECT agent_id, agent_name, agent_icon, task, project_path, created_at FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| {
+                    Ok(QueuedRun {
+                        run_id,
+                        agent_id: row.get(0)?,
+                        agent_name: row.get(1)?,
+                        agent_icon: row.get(2)?,
+                        task: row.get(3)?,
+                        project_path: row.get(4)?,
+                        position: index + 1,
+                        verdict,
+                        created_at: row.get(5)?,
+                    })
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        runs.push(run);
+    }
+
+    Ok(runs)
+}
+
+/// Move a queued run to a new 1-based position in the queue
+#[tauri::command]
+pub async fn move_queued_run(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    run_id: i64,
+    position: usize,
+) -> Result<(), String> {
+    {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut order: Vec<i64> = queued_runs(&conn, None)?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+
+        let current = order
+            .iter()
+            .position(|id| *id == run_id)
+            .ok_or_else(|| format!("Run {} is not queued", run_id))?;
+        order.remove(current);
+        let target = position.saturating_sub(1).min(order.len());
+        order.insert(target, run_id);
+
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for (index, id) in order.iter().enumerate() {
+            tx.execute(
+                "UPDATE agent_runs SET queue_position = ?1 WHERE id = ?2",
+                params![index as i64 + 1, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+
+    app.state::<RunQueueState>().notify();
+    let _ = app.emit("agent-queue-updated", true);
+    Ok(())
+}
+
+/// Cancel a run that is still waiting in the queue
+#[tauri::command]
+pub async fn cancel_queued_run(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let updated = conn
+            .execute(
+                "UPDATE agent_runs SET status = 'cancelled', queue_position = NULL, completed_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1 AND status = 'queued'",
+                params![run_id],
+            )
+            .map_err(|e| e.to_string())?;
+
+        if updated == 0 {
+            return Err(format!("Run {} is not queued", run_id));
+        }
+    }
+
+    let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+    let _ = app.emit("agent-queue-updated", true);
+    Ok(())
+}
+
+/// Get the run queue concurrency limits
+#[tauri::command]
+pub async fn get_run_queue_settings(db: State<'_, AgentDb>) -> Result<RunQueueSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_queue_settings(&conn))
+}
+
+/// Save the run queue concurrency limits
+#[tauri::command]
+pub async fn save_run_queue_settings(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    settings: RunQueueSettings,
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let values = [
+            ("queue_max_concurrent_runs", settings.max_concurrent_runs),
+            (
+                "queue_max_concurrent_per_project",
+                settings.max_concurrent_per_project,
+            ),
+        ];
+
+        for (key, value) in values {
+            save_setting(&conn, key, &value.to_string())?;
+        }
+    }
+
+    // Raised limits may let queued runs start immediately
+    app.state::<RunQueueState>().notify();
+    Ok(())
+}
+
+/// Get the limits shared by all Claude processes
+#[tauri::command]
+pub async fn get_scheduler_settings(db: State<'_, AgentDb>) -> Result<SchedulerSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_scheduler_settings(&conn))
+}
+
+/// Save the limits shared by all Claude processes
+#[tauri::command]
+pub async fn save_scheduler_settings(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    settings: SchedulerSettings,
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        save_json_setting(&conn, SCHEDULER_SETTINGS_KEY, &settings)?;
+    }
+
+    app.state::<RunQueueState>().notify();
+    Ok(())
+}
@@ -0,0 +1,269 @@
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use log::warn;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::commands::usage_index::{local_date, refresh_usage_index};
+
+/// app_settings key holding the rate limit settings as JSON
+const SETTINGS_KEY: &str = "rate_limit_window";
+
+/// Length of a subscription usage window, counted from its first message
+const WINDOW_HOURS: i64 = 5;
+
+/// How far back past windows are looked at to estimate the limit
+const HISTORY_DAYS: i64 = 30;
+
+/// Interval between `rate-limit-window` events while a window is active
+const WINDOW_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// How the token limit of a usage window is known
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RateLimitSettings {
+    /// Tokens allowed per window; None to estimate it from the busiest past window
+    pub token_limit: Option<u64>,
+}
+
+/// A block of usage starting at the hour of its first message and lasting
+/// `WINDOW_HOURS`, the way subscription limits are reset
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageWindow {
+    pub start: DateTime<Utc>,
+    /// When the window's usage stops counting towards the limit
+    pub resets_at: DateTime<Utc>,
+    /// Input, output and cache creation tokens; cache reads barely count
+    /// towards subscription limits and are left out
+    pub tokens: u64,
+    pub cost_usd: f64,
+    pub message_count: u64,
+}
+
+/// Estimated state of the current usage window
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RateLimitStatus {
+    /// None when nothing was sent in the last `WINDOW_HOURS` hours
+    pub window: Option<UsageWindow>,
+    pub token_limit: Option<u64>,
+    /// Whether `token_limit` is estimated rather than configured
+    pub limit_estimated: bool,
+    pub remaining_tokens: Option<u64>,
+    pub percent: Option<f64>,
+}
+
+fn load_settings(conn: &Connection) -> RateLimitSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// Group messages, oldest first, into windows. A window starts at the hour of
+/// the first message sent after the previous one reset.
+pub fn usage_windows(messages: &[(DateTime<Utc>, u64, f64)]) -> Vec<UsageWindow> {
+    let mut windows: Vec<UsageWindow> = Vec::new();
+    for (timestamp, tokens, cost_usd) in messages {
+        match windows.last_mut() {
+            Some(window) if *timestamp < window.resets_at => {
+                window.tokens += tokens;
+                window.cost_usd += cost_usd;
+                window.message_count += 1;
+            }
+            _ => {
+                let start = timestamp
+                    .duration_trunc(Duration::hours(1))
+                    .unwrap_or(*timestamp);
+                windows.push(UsageWindow {
+                    start,
+                    resets_at: start + Duration::hours(WINDOW_HOURS),
+                    tokens: *tokens,
+                    cost_usd: *cost_usd,
+                    message_count: 1,
+                });
+            }
+        }
+    }
+    windows
+}
+
+/// Indexed messages sent since `since`, oldest first
+fn messages_since(
+    conn: &Connection,
+    since: DateTime<Utc>,
+) -> Result<Vec<(DateTime<Utc>, u64, f64)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, input_tokens + output_tokens + cache_creation_tokens, cost
+             FROM usage_messages WHERE date >= ?1 ORDER BY timestamp",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![local_date(&since.to_rfc3339())], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, f64>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(timestamp, tokens, cost)| {
+            let timestamp = DateTime::parse_from_rfc3339(&timestamp).ok()?.to_utc();
+            (timestamp >= since).then_some((timestamp, tokens, cost))
+        })
+        .collect())
+}
+
+/// The current window and how much of its limit is left. Without a configured
+/// limit, the busiest finished window of the last `HISTORY_DAYS` days is taken
+/// as the limit, as that much was evidently allowed.
+pub fn rate_limit_status(
+    windows: &[UsageWindow],
+    settings: &RateLimitSettings,
+    now: DateTime<Utc>,
+) -> RateLimitStatus {
+    let window = windows
+        .last()
+        .filter(|window| window.start <= now && now < window.resets_at)
+        .cloned();
+    let estimated_limit = windows
+        .iter()
+        .filter(|past| past.resets_at <= now)
+        .map(|past| past.tokens)
+        .max();
+    let (token_limit, limit_estimated) = match settings.token_limit {
+        Some(limit) => (Some(limit), false),
+        None => (estimated_limit, true),
+    };
+    let used = window.as_ref().map_or(0, |window| window.tokens);
+
+    RateLimitStatus {
+        remaining_tokens: token_limit.map(|limit| limit.saturating_sub(used)),
+        percent: token_limit
+            .filter(|limit| *limit > 0)
+            .map(|limit| used as f64 / limit as f64 * 100.0),
+        window,
+        token_limit,
+        limit_estimated,
+    }
+}
+
+fn current_status(conn: &mut Connection) -> Result<RateLimitStatus, String> {
+    refresh_usage_index(conn);
+    let now = Utc::now();
+    let windows = usage_windows(&messages_since(conn, now - Duration::days(HISTORY_DAYS))?);
+    Ok(rate_limit_status(&windows, &load_settings(conn), now))
+}
+
+/// Emit `rate-limit-window` with the current window's status while one is active
+pub fn start_rate_limit_monitor(app: AppHandle, db_path: PathBuf) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let (app, path) = (app.clone(), db_path.clone());
+            let _ = tokio::task::spawn_blocking(move || {
+                let status = Connection::open(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|mut conn| current_status(&mut conn));
+                match status {
+                    Ok(status) if status.window.is_some() => {
+                        let _ = app.emit("rate-limit-window", status);
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to check the rate limit window: {}", e),
+                }
+            })
+            .await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(WINDOW_CHECK_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+/// Estimated remaining capacity and reset time of the current usage window
+#[tauri::command]
+pub async fn get_rate_limit_status(db: State<'_, AgentDb>) -> Result<RateLimitStatus, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    current_status(&mut conn)
+}
+
+#[tauri::command]
+pub async fn get_rate_limit_settings(db: State<'_, AgentDb>) -> Result<RateLimitSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_rate_limit_settings(
+    db: State<'_, AgentDb>,
+    settings: RateLimitSettings,
+) -> Result<(), String> {
+    if settings.token_limit == Some(0) {
+        return Err("The token limit must be greater than zero".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_usage_windows_and_status() {
+        let windows = usage_windows(&[
+            (at("2025-06-01T09:40:00Z"), 1000, 1.0),
+            (at("2025-06-01T13:59:00Z"), 500, 0.5),
+            // After the first window reset at 14:00
+            (at("2025-06-01T14:10:00Z"), 200, 0.25),
+            (at("2025-06-01T16:30:00Z"), 100, 0.25),
+        ]);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(
+            (windows[0].start, windows[0].resets_at, windows[0].tokens),
+            (at("2025-06-01T09:00:00Z"), at("2025-06-01T14:00:00Z"), 1500)
+        );
+        assert_eq!(
+            (
+                windows[1].start,
+                windows[1].tokens,
+                windows[1].message_count
+            ),
+            (at("2025-06-01T14:00:00Z"), 300, 2)
+        );
+
+        let status = rate_limit_status(
+            &windows,
+            &RateLimitSettings::default(),
+            at("2025-06-01T17:00:00Z"),
+        );
+        assert_eq!(status.window.as_ref(), Some(&windows[1]));
+        assert_eq!(
+            (
+                status.token_limit,
+                status.limit_estimated,
+                status.remaining_tokens
+            ),
+            (Some(1500), true, Some(1200))
+        );
+        assert!((status.percent.unwrap() - 20.0).abs() < 1e-9);
+
+        let settings = RateLimitSettings {
+            token_limit: Some(200),
+        };
+        let status = rate_limit_status(&windows, &settings, at("2025-06-01T17:00:00Z"));
+        assert_eq!(status.remaining_tokens, Some(0));
+        assert!(!status.limit_estimated);
+
+        // Once the window resets there is nothing in use
+        let status = rate_limit_status(&windows, &settings, at("2025-06-01T19:00:00Z"));
+        assert_eq!((status.window, status.remaining_tokens), (None, Some(200)));
+    }
+}
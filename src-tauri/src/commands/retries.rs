@@ -0,0 +1,324 @@
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::issue_links::copy_run_issue_links;
+
+/// Longest delay between two attempts, regardless of the backoff multiplier
+const MAX_RETRY_DELAY_SECS: u64 = 60 * 60;
+
+/// Why an agent run attempt failed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryCondition {
+    /// No output was produced before the startup timeout
+    Timeout,
+    /// The Claude process exited with a non-zero status
+    NonZeroExit,
+    /// The stream ended with an error result message
+    ErrorResult,
+}
+
+impl RetryCondition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetryCondition::Timeout => "timeout",
+            RetryCondition::NonZeroExit => "nonzero_exit",
+            RetryCondition::ErrorResult => "error_result",
+        }
+    }
+}
+
+/// Per-agent retry policy
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one (1 disables retries)
+    pub max_attempts: u32,
+    pub backoff_seconds: u64,
+    pub backoff_multiplier: f64,
+    pub retry_on: Vec<RetryCondition>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_seconds: 30,
+            backoff_multiplier: 2.0,
+            retry_on: vec![
+                RetryCondition::Timeout,
+                RetryCondition::NonZeroExit,
+                RetryCondition::ErrorResult,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the attempt that follows `failed_attempt` (1-based)
+    pub fn delay_after(&self, failed_attempt: u32) -> u64 {
+        let exponent = failed_attempt.saturating_sub(1) as i32;
+        let delay = self.backoff_seconds as f64 * self.backoff_multiplier.max(1.0).powi(exponent);
+        if delay.is_finite() {
+            (delay as u64).min(MAX_RETRY_DELAY_SECS)
+        } else {
+            MAX_RETRY_DELAY_SECS
+        }
+    }
+}
+
+/// One attempt of a (possibly retried) agent run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunAttempt {
+    pub run_id: i64,
+    pub attempt: u32,
+    pub status: String,
+    pub failure_reason: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+/// Load an agent's retry policy, falling back to the default (no retries)
+pub fn load_retry_policy(conn: &Connection, agent_id: i64) -> Result<RetryPolicy, String> {
+    let policy = conn
+        .query_row(
+            "SELECT max_attempts, backoff_seconds, backoff_multiplier, retry_on FROM agent_retry_policies WHERE agent_id = ?1",
+            params![agent_id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, f64>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(match policy {
+        Some((max_attempts, backoff_seconds, backoff_multiplier, retry_on)) => RetryPolicy {
+            max_attempts: max_attempts.max(1) as u32,
+            backoff_seconds: backoff_seconds.max(0) as u64,
+            backoff_multiplier,
+            retry_on: serde_json::from_str(&retry_on).unwrap_or_default(),
+        },
+        None => RetryPolicy::default(),
+    })
+}
+
+/// Get the retry policy for an agent
+#[tauri::command]
+pub async fn get_agent_retry_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<RetryPolicy, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_retry_policy(&conn, agent_id)
+}
+
+/// Set the retry policy for an agent
+#[tauri::command]
+pub async fn set_agent_retry_policy(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    policy: RetryPolicy,
+) -> Result<(), String> {
+    if policy.max_attempts == 0 {
+        return Err("max_attempts must be at least 1".to_string());
+    }
+    if !policy.backoff_multiplier.is_finite() || policy.backoff_multiplier < 1.0 {
+        return Err("backoff_multiplier must be at least 1.0".to_string());
+    }
+
+    let retry_on = serde_json::to_string(&policy.retry_on).map_err(|e| e.to_string())?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_retry_policies (agent_id, max_attempts, backoff_seconds, backoff_multiplier, retry_on) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            agent_id,
+            policy.max_attempts,
+            policy.backoff_seconds as i64,
+            policy.backoff_multiplier,
+            retry_on
+        ],
+    )
+    .map_err(|e| format!("Failed to save retry policy: {}", e))?;
+
+    Ok(())
+}
+
+/// List every attempt belonging to the same original run, oldest first
+#[tauri::command]
+pub async fn list_run_attempts(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Vec<RunAttempt>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    // Attempts all point at the first run, so resolve that first
+    let root_id: i64 = conn
+        .query_row(
+            "SELECT COALESCE(retry_of_run_id, id) FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, COALESCE(attempt, 1), status, failure_reason, created_at, completed_at
+             FROM agent_runs WHERE id = ?1 OR retry_of_run_id = ?1
+             ORDER BY attempt ASC, id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let attempts = stmt
+        .query_map(params![root_id], |row| {
+            Ok(RunAttempt {
+                run_id: row.get(0)?,
+                attempt: row.get::<_, i64>(1)? as u32,
+                status: row.get(2)?,
+                failure_reason: row.get(3)?,
+                created_at: row.get(4)?,
+                completed_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(attempts)
+}
+
+/// Queue another attempt of a failed run if the agent's retry policy allows
+/// it; returns whether one was scheduled.
+pub fn schedule_retry(
+    app: &AppHandle,
+    db_path: &Path,
+    run_id: i64,
+    condition: RetryCondition,
+) -> bool {
+    let conn = match Connection::open(db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to open database to schedule retry: {}", e);
+            return false;
+        }
+    };
+
+    let run = conn.query_row(
+        "SELECT agent_id, COALESCE(attempt, 1), COALESCE(retry_of_run_id, id) FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)? as u32,
+                row.get::<_, i64>(2)?,
+            ))
+        },
+    );
+    let (agent_id, attempt, root_id) = match run {
+        Ok(run) => run,
+        Err(e) => {
+            warn!("Failed to load run {} for retry: {}", run_id, e);
+            return false;
+        }
+    };
+
+    let policy = match load_retry_policy(&conn, agent_id) {
+        Ok(policy) => policy,
+        Err(e) => {
+            warn!("Failed to load retry policy for agent {}: {}", agent_id, e);
+            return false;
+        }
+    };
+
+    if attempt >= policy.max_attempts || !policy.retry_on.contains(&condition) {
+        return false;
+    }
+
+    let delay_secs = policy.delay_after(attempt);
+    info!(
+        "Retrying agent run {} ({}) as attempt {} in {}s",
+        run_id,
+        condition.as_str(),
+        attempt + 1,
+        delay_secs
+    );
+
+    let _ = app.emit(
+        "agent-retry-scheduled",
+        serde_json::json!({
+            "run_id": run_id,
+            "original_run_id": root_id,
+            "next_attempt": attempt + 1,
+            "delay_secs": delay_secs,
+            "reason": condition.as_str(),
+        }),
+    );
+
+    let app = app.clone();
+    let db_path: PathBuf = db_path.to_path_buf();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(tokio::time::Duration::from_secs(delay_secs)).await;
+
+        let conn = match Connection::open(&db_path) {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Failed to open database to queue retry: {}", e);
+                return;
+            }
+        };
+
+        // Copy the failed attempt into a new queued run linked to the original
+        let inserted = conn.execute(
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, queue_position, attempt, retry_of_run_id, parameters, background)
+             SELECT agent_id, agent_name, agent_icon, task, model, project_path, '', 'queued',
+                    (SELECT COALESCE(MAX(queue_position), 0) + 1 FROM agent_runs), ?2, ?3, parameters, background
+             FROM agent_runs WHERE id = ?1",
+            params![run_id, attempt + 1, root_id],
+        );
+
+        match inserted {
+            Ok(_) => {
+                let new_run_id = conn.last_insert_rowid();
+                if let Err(e) = copy_run_issue_links(&conn, run_id, new_run_id) {
+                    warn!("{}", e);
+                }
+                let _ = app.emit(
+                    "agent-retry-queued",
+                    serde_json::json!({ "run_id": new_run_id, "original_run_id": root_id }),
+                );
+                if let Some(queue) = app.try_state::<crate::process::RunQueueState>() {
+                    queue.notify();
+                }
+            }
+            Err(e) => warn!("Failed to queue retry of run {}: {}", run_id, e),
+        }
+    });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            backoff_seconds: 10,
+            backoff_multiplier: 3.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay_after(1), 10);
+        assert_eq!(policy.delay_after(2), 30);
+        assert_eq!(policy.delay_after(3), 90);
+        assert_eq!(policy.delay_after(20), MAX_RETRY_DELAY_SECS);
+    }
+}
@@ -0,0 +1,310 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// A file touched by an agent run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChangedFile {
+    /// Single-letter git status (A, M, D, R, C, T)
+    pub status: String,
+    pub path: String,
+}
+
+/// The changes an agent run made to its project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRunDiff {
+    pub run_id: i64,
+    pub base_tree: Option<String>,
+    pub end_tree: Option<String>,
+    pub changed_files: Vec<ChangedFile>,
+    pub diff: String,
+    pub reverted_at: Option<String>,
+    /// Pull request opened from the changes, if any
+    pub pr_url: Option<String>,
+}
+
+/// Run git in `dir`, optionally against an alternate index file
+pub fn run_git(dir: &Path, args: &[&str], index_file: Option<&Path>) -> Result<String, String> {
+    let mut cmd = Command::new("git");
+    // Fail instead of waiting on a credential prompt nobody can answer
+    cmd.args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(index_file) = index_file {
+        cmd.env("GIT_INDEX_FILE", index_file);
+    }
+
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Record the full working tree (including untracked files) as a git tree object.
+///
+/// Uses a throwaway index so the user's staging area is left untouched. Returns
+/// None when the project isn't inside a git repository.
+pub fn snapshot_worktree(project_path: &Path) -> Option<String> {
+    let git_dir = run_git(project_path, &["rev-parse", "--absolute-git-dir"], None).ok()?;
+    let git_dir = PathBuf::from(git_dir.trim());
+
+    let temp_index =
+        std::env::temp_dir().join(format!("opcode-index-{}", uuid::Uuid::new_v4().simple()));
+    // Starting from the real index lets git reuse its stat cache
+    let real_index = git_dir.join("index");
+    if real_index.exists() {
+        if let Err(e) = std::fs::copy(&real_index, &temp_index) {
+            warn!("Failed to copy git index for snapshot: {}", e);
+            return None;
+        }
+    }
+
+    let tree = run_git(project_path, &["add", "-A"], Some(&temp_index))
+        .and_then(|_| run_git(project_path, &["write-tree"], Some(&temp_index)));
+    let _ = std::fs::remove_file(&temp_index);
+
+    match tree {
+        Ok(tree) => Some(tree.trim().to_string()),
+        Err(e) => {
+            warn!("Failed to snapshot {}: {}", project_path.display(), e);
+            None
+        }
+    }
+}
+
+/// Parse `git diff --name-status` output
+pub fn parse_name_status(output: &str) -> Vec<ChangedFile> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let status = parts.next()?.chars().next()?.to_string();
+            // Renames and copies list the old path first; report the new one
+            let path = parts.next_back()?.to_string();
+            Some(ChangedFile { status, path })
+        })
+        .collect()
+}
+
+/// Store the snapshot of the project taken with [`snapshot_worktree`] before
+/// a run started, so its changes can be isolated later
+pub fn record_run_base(conn: &Connection, run_id: i64, base_tree: &str) {
+    let _ = conn.execute(
+        "UPDATE agent_runs SET git_base_tree = ?1 WHERE id = ?2",
+        params![base_tree, run_id],
+    );
+}
+
+/// Compute and store the diff a finished run made, relative to its base snapshot.
+pub fn capture_run_diff(db_path: &Path, run_id: i64) {
+    let conn = match Connection::open(db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to open database to capture run diff: {}", e);
+            return;
+        }
+    };
+
+    let run = conn.query_row(
+        "SELECT project_path, git_base_tree FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+    );
+    let (project_path, base_tree) = match run {
+        Ok((project_path, Some(base_tree))) => (project_path, base_tree),
+        // Not a git project, or the base snapshot failed
+        Ok((_, None)) => return,
+        Err(e) => {
+            warn!("Failed to load run {} for diff capture: {}", run_id, e);
+            return;
+        }
+    };
+
+    let project_path = Path::new(&project_path);
+    let Some(end_tree) = snapshot_worktree(project_path) else {
+        return;
+    };
+
+    let diff = run_git(
+        project_path,
+        &["diff", "--binary", &base_tree, &end_tree],
+        None,
+    );
+    let name_status = run_git(
+        project_path,
+        &["diff", "--name-status", &base_tree, &end_tree],
+        None,
+    );
+
+    match (diff, name_status) {
+        (Ok(diff), Ok(name_status)) => {
+            let changed_files = parse_name_status(&name_status);
+            let changed_json = serde_json::to_string(&changed_files).unwrap_or_default();
+            let _ = conn.execute(
+                "UPDATE agent_runs SET git_end_tree = ?1, git_diff = ?2, changed_files = ?3 WHERE id = ?4",
+                params![end_tree, diff, changed_json, run_id],
+            );
+            info!(
+                "Captured diff for agent run {} ({} files changed)",
+                run_id,
+                changed_files.len()
+            );
+        }
+        (Err(e), _) | (_, Err(e)) => warn!("Failed to capture diff for run {}: {}", run_id, e),
+    }
+}
+
+/// Get the git diff and changed files recorded for an agent run
+#[tauri::command]
+pub async fn get_agent_run_diff(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<AgentRunDiff, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.query_row(
+        "SELECT git_base_tree, git_end_tree, changed_files, git_diff, changes_reverted_at, pr_url FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| {
+            let changed_files: Option<String> = row.get(2)?;
+            Ok(AgentRunDiff {
+                run_id,
+                base_tree: row.get(0)?,
+                end_tree: row.get(1)?,
+                changed_files: changed_files
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                diff: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                reverted_at: row.get(4)?,
+                pr_url: row.get(5)?,
+            })
+        },
+    )
+    .map_err(|e| format!("Failed to load agent run {}: {}", run_id, e))
+}
+
+/// Revert every change a specific agent run made to its project.
+///
+/// Fails without touching anything if later edits conflict with the run's diff.
+#[tauri::command]
+pub async fn revert_agent_run_changes(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Vec<ChangedFile>, String> {
+    let (project_path, status, changed_files, diff, reverted_at) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT project_path, status, changed_files, git_diff, changes_reverted_at FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            },
+        )
+        .map_err(|e| format!("Failed to load agent run {}: {}", run_id, e))?
+    };
+
+    if status == "running" || status == "queued" {
+        return Err(format!("Run {} has not finished yet", run_id));
+    }
+    if reverted_at.is_some() {
+        return Err(format!("Changes from run {} were already reverted", run_id));
+    }
+    let diff = match diff {
+        Some(diff) if !diff.is_empty() => diff,
+        _ => return Err(format!("Run {} has no recorded changes to revert", run_id)),
+    };
+
+    // git runs without the database locked
+    tauri::async_runtime::spawn_blocking(move || {
+        revert_diff(run_id, Path::new(&project_path), &diff)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE agent_runs SET changes_reverted_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![run_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    info!("Reverted changes from agent run {}", run_id);
+    Ok(changed_files
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+/// Apply a run's diff in reverse to its project, or nothing if any of it conflicts
+fn revert_diff(run_id: i64, project_path: &Path, diff: &str) -> Result<(), String> {
+    // Diff paths are relative to the repository root
+    let toplevel = run_git(project_path, &["rev-parse", "--show-toplevel"], None)?;
+    let toplevel = PathBuf::from(toplevel.trim());
+
+    let patch_file = std::env::temp_dir().join(format!(
+        "opcode-run-{}-{}.patch",
+        run_id,
+        uuid::Uuid::new_v4().simple()
+    ));
+    std::fs::write(&patch_file, diff).map_err(|e| format!("Failed to write patch: {}", e))?;
+    let patch = patch_file.to_string_lossy().to_string();
+
+    let result = run_git(
+        &toplevel,
+        &["apply", "-R", "--check", "--binary", &patch],
+        None,
+    )
+    .map_err(|e| format!("Run {} changes can't be reverted cleanly: {}", run_id, e))
+    .and_then(|_| run_git(&toplevel, &["apply", "-R", "--binary", &patch], None));
+    let _ = std::fs::remove_file(&patch_file);
+    result.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_name_status() {
+        let output = "M\tsrc/main.rs\nA\tnew.txt\nR087\told/name.rs\tnew/name.rs\nD\tgone.md\n";
+        assert_eq!(
+            parse_name_status(output),
+            vec![
+                ChangedFile {
+                    status: "M".to_string(),
+                    path: "src/main.rs".to_string()
+                },
+                ChangedFile {
+                    status: "A".to_string(),
+                    path: "new.txt".to_string()
+                },
+                ChangedFile {
+                    status: "R".to_string(),
+                    path: "new/name.rs".to_string()
+                },
+                ChangedFile {
+                    status: "D".to_string(),
+                    path: "gone.md".to_string()
+                },
+            ]
+        );
+    }
+}
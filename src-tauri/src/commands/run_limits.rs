@@ -0,0 +1,275 @@
+use log::warn;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessRefreshKind, System};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::watch;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::budgets::UsageSnapshot;
+use crate::commands::cancellation::grace_period;
+use crate::commands::processes::{parent_pids, process_tree_usage};
+use crate::process::ProcessRegistry;
+
+/// Interval between the watchdog's checks of a run
+const WATCHDOG_INTERVAL_SECS: u64 = 2;
+
+/// Per-run resource limits for an agent (None means no limit)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RunLimits {
+    /// Wall-clock time since the process was spawned
+    pub max_runtime_secs: Option<u64>,
+    /// Memory of Claude and every process it spawned
+    pub max_memory_mb: Option<u64>,
+    /// Bytes written to stdout and stderr
+    pub max_output_bytes: Option<u64>,
+}
+
+/// What a run has used so far, as checked against its limits
+#[derive(Debug, Clone, Default)]
+pub struct ResourceUsage {
+    pub runtime: Duration,
+    pub memory_bytes: u64,
+    pub output_bytes: u64,
+}
+
+impl RunLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_runtime_secs.is_none()
+            && self.max_memory_mb.is_none()
+            && self.max_output_bytes.is_none()
+    }
+
+    /// Describe the limit that has been breached, if any
+    pub fn breached(&self, usage: &ResourceUsage) -> Option<String> {
+        if let Some(max_secs) = self.max_runtime_secs {
+            if usage.runtime.as_secs() >= max_secs {
+                return Some(format!(
+                    "ran for {}s, over the limit of {}s",
+                    usage.runtime.as_secs(),
+                    max_secs
+                ));
+            }
+        }
+        if let Some(max_mb) = self.max_memory_mb {
+            let memory_mb = usage.memory_bytes / (1024 * 1024);
+            if memory_mb > max_mb {
+                return Some(format!(
+                    "used {} MB of memory, over the limit of {} MB",
+                    memory_mb, max_mb
+                ));
+            }
+        }
+        if let Some(max_bytes) = self.max_output_bytes {
+            if usage.output_bytes > max_bytes {
+                return Some(format!(
+                    "wrote {} bytes of output, over the limit of {}",
+                    usage.output_bytes, max_bytes
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Load an agent's run limits, defaulting to no limits
+pub fn load_run_limits(conn: &Connection, agent_id: i64) -> Result<RunLimits, String> {
+    let as_u64 = |value: Option<i64>| value.map(|v| v.max(0) as u64);
+    let limits = conn
+        .query_row(
+            "SELECT max_runtime_secs, max_memory_mb, max_output_bytes FROM agent_run_limits WHERE agent_id = ?1",
+            params![agent_id],
+            |row| {
+                Ok(RunLimits {
+                    max_runtime_secs: as_u64(row.get(0)?),
+                    max_memory_mb: as_u64(row.get(1)?),
+                    max_output_bytes: as_u64(row.get(2)?),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(limits.unwrap_or_default())
+}
+
+/// Watch a run until its output ends, stopping it once it breaches a limit.
+///
+/// The run is marked `limit_exceeded` with the breached limit as its failure
+/// reason before it is stopped, so the monitor doesn't record it as completed.
+/// Returns a flag set once the run has been stopped.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_limit_watchdog(
+    app: AppHandle,
+    registry: Arc<ProcessRegistry>,
+    run_id: i64,
+    pid: u32,
+    limits: RunLimits,
+    output_bytes: Arc<AtomicU64>,
+    usage: watch::Receiver<UsageSnapshot>,
+) -> Arc<AtomicBool> {
+    let exceeded = Arc::new(AtomicBool::new(false));
+    if limits.is_unlimited() {
+        return exceeded;
+    }
+    let exceeded_clone = exceeded.clone();
+    let started = Instant::now();
+
+    tokio::spawn(async move {
+        let mut system = System::new();
+        let mut interval = tokio::time::interval(Duration::from_secs(WATCHDOG_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            // The stdout reader drops the sender once the run's output ends
+            if usage.has_changed().is_err() {
+                return;
+            }
+
+            let memory_bytes = if limits.max_memory_mb.is_some() {
+                system.refresh_processes_specifics(ProcessRefreshKind::new().with_memory());
+                let parents = parent_pids(&system);
+                process_tree_usage(&system, &parents, pid)
+                    .map(|(memory, _)| memory)
+                    .unwrap_or_default()
+            } else {
+                0
+            };
+            let current = ResourceUsage {
+                runtime: started.elapsed(),
+                memory_bytes,
+                output_bytes: output_bytes.load(Ordering::Relaxed),
+            };
+            let Some(reason) = limits.breached(&current) else {
+                continue;
+            };
+
+            warn!("Stopping agent run {}: {}", run_id, reason);
+            exceeded_clone.store(true, Ordering::Relaxed);
+            let grace = match app.state::<AgentDb>().0.lock() {
+                Ok(conn) => {
+                    if let Err(e) = conn.execute(
+                        "UPDATE agent_runs SET status = 'limit_exceeded', failure_reason = ?2, completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
+                        params![run_id, format!("limit_exceeded: {}", reason)],
+                    ) {
+                        warn!("Failed to mark run {} over its limits: {}", run_id, e);
+                    }
+                    grace_period(&conn)
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to lock database to mark run {} over its limits: {}",
+                        run_id, e
+                    );
+                    Duration::ZERO
+                }
+            };
+            let _ = app.emit(
+                "agent-limit-exceeded",
+                serde_json::json!({ "run_id": run_id, "reason": reason }),
+            );
+            if let Err(e) = registry.cancel_process(run_id, grace).await {
+                warn!("Failed to stop agent run {}: {}", run_id, e);
+            }
+            return;
+        }
+    });
+    exceeded
+}
+
+/// Get the per-run resource limits for an agent
+#[tauri::command]
+pub async fn get_agent_run_limits(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<RunLimits, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_run_limits(&conn, agent_id)
+}
+
+/// Store the per-run resource limits for an agent; clearing every limit removes them
+pub fn save_run_limits(conn: &Connection, agent_id: i64, limits: &RunLimits) -> Result<(), String> {
+    let limit_values = [
+        limits.max_runtime_secs,
+        limits.max_memory_mb,
+        limits.max_output_bytes,
+    ];
+    if limit_values.contains(&Some(0)) {
+        return Err("Limits must be greater than 0".to_string());
+    }
+
+    if limits.is_unlimited() {
+        conn.execute(
+            "DELETE FROM agent_run_limits WHERE agent_id = ?1",
+            params![agent_id],
+        )
+        .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_run_limits (agent_id, max_runtime_secs, max_memory_mb, max_output_bytes)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            agent_id,
+            limits.max_runtime_secs.map(|v| v as i64),
+            limits.max_memory_mb.map(|v| v as i64),
+            limits.max_output_bytes.map(|v| v as i64)
+        ],
+    )
+    .map_err(|e| format!("Failed to save run limits: {}", e))?;
+
+    Ok(())
+}
+
+/// Set the per-run resource limits for an agent; clearing every limit removes them
+#[tauri::command]
+pub async fn set_agent_run_limits(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    limits: RunLimits,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_run_limits(&conn, agent_id, &limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breached_limits() {
+        let limits = RunLimits {
+            max_runtime_secs: Some(60),
+            max_memory_mb: Some(512),
+            max_output_bytes: None,
+        };
+        let usage = ResourceUsage {
+            runtime: Duration::from_secs(30),
+            memory_bytes: 256 * 1024 * 1024,
+            output_bytes: 10_000_000,
+        };
+        assert_eq!(limits.breached(&usage), None);
+
+        let usage = ResourceUsage {
+            memory_bytes: 600 * 1024 * 1024,
+            ..usage
+        };
+        assert_eq!(
+            limits.breached(&usage),
+            Some("used 600 MB of memory, over the limit of 512 MB".to_string())
+        );
+
+        let usage = ResourceUsage {
+            runtime: Duration::from_secs(61),
+            ..usage
+        };
+        assert_eq!(
+            limits.breached(&usage),
+            Some("ran for 61s, over the limit of 60s".to_string())
+        );
+        assert!(RunLimits::default().is_unlimited());
+    }
+}
@@ -0,0 +1,258 @@
+//! Raw output logs of agent runs and Claude sessions
+//!
+//! Every line a run or session writes to stdout or stderr is copied into a
+//! rotating log under the app data directory, so its output can still be read
+//! after the run has failed or its process has been cleaned up. Each log is
+//! capped by rotation, and the oldest logs are removed once all of them
+//! together grow past a size limit.
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+use crate::mcp_proxy::{self, RotatingLog};
+
+/// Size at which a run's current log file is rotated
+const RUN_LOG_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Size of all run logs together past which the oldest are removed
+const MAX_TOTAL_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Lines held for a log that hasn't been named yet
+const MAX_PENDING_LINES: usize = 1000;
+
+/// Lines returned by `read_run_output_log` when the caller doesn't ask for a number
+const DEFAULT_READ_LINES: usize = 1000;
+
+/// Lines returned by `read_run_output_log` when the caller asks for more
+const MAX_READ_LINES: usize = 50_000;
+
+/// A stored output log
+#[derive(Debug, Clone, Serialize)]
+pub struct RunOutputLogInfo {
+    /// `agent-run-{run_id}`, `session-{session_id}`, or `session-pid-{pid}`
+    /// for a session that ended before Claude reported its ID
+    pub name: String,
+    /// Directory holding the log's current and rotated files
+    pub path: String,
+    pub size_bytes: u64,
+    pub modified_at: String,
+}
+
+/// Directory holding every run's output log
+pub fn logs_root(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("run-logs")
+}
+
+pub fn agent_run_log_name(run_id: i64) -> String {
+    format!("agent-run-{}", run_id)
+}
+
+pub fn session_log_name(session_id: &str) -> String {
+    format!("session-{}", session_id)
+}
+
+enum LogState {
+    /// Lines written before the log was named
+    Pending(Vec<(&'static str, String)>),
+    Open(RotatingLog),
+    /// The log couldn't be opened, so lines are dropped
+    Closed,
+}
+
+/// The output log of one run, shared by its stdout and stderr readers
+pub struct RunOutputLog {
+    root: PathBuf,
+    state: Mutex<LogState>,
+}
+
+impl RunOutputLog {
+    /// A log whose name is known up front, such as an agent run's
+    pub fn open(root: &Path, name: &str) -> Self {
+        let log = Self::pending(root);
+        log.attach(name);
+        log
+    }
+
+    /// A log that holds its lines until `attach` names it, for sessions whose
+    /// ID only arrives with Claude's first message
+    pub fn pending(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            state: Mutex::new(LogState::Pending(Vec::new())),
+        }
+    }
+
+    /// Name the log and write the lines held so far; a log that is already
+    /// named keeps its name
+    pub fn attach(&self, name: &str) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let LogState::Pending(held) = &mut *state else {
+            return;
+        };
+        let held = std::mem::take(held);
+
+        prune_logs(&self.root, MAX_TOTAL_BYTES);
+        let dir = mcp_proxy::server_log_dir(&self.root, name);
+        *state = match RotatingLog::with_limit(&dir, RUN_LOG_BYTES) {
+            Ok(mut log) => {
+                for (stream, line) in held {
+                    let _ = log.write_line(stream, &line);
+                }
+                LogState::Open(log)
+            }
+            Err(e) => {
+                warn!("Failed to open output log {}: {}", dir.display(), e);
+                LogState::Closed
+            }
+        };
+    }
+
+    /// Record one line from `stream` (stdout or stderr)
+    pub fn write(&self, stream: &'static str, line: &str) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        match &mut *state {
+            LogState::Pending(held) => {
+                if held.len() < MAX_PENDING_LINES {
+                    held.push((stream, line.to_string()));
+                }
+            }
+            LogState::Open(log) => {
+                if let Err(e) = log.write_line(stream, line) {
+                    warn!("Failed to write output log: {}", e);
+                }
+            }
+            LogState::Closed => {}
+        }
+    }
+}
+
+/// Total size and last modification of the files in a log directory
+fn log_dir_usage(dir: &Path) -> (u64, SystemTime) {
+    let mut size = 0;
+    let mut modified = SystemTime::UNIX_EPOCH;
+    for path in mcp_proxy::log_files(dir) {
+        if let Ok(metadata) = fs::metadata(&path) {
+            size += metadata.len();
+            if let Ok(time) = metadata.modified() {
+                modified = modified.max(time);
+            }
+        }
+    }
+    (size, modified)
+}
+
+/// Every log under `root` with its size and last modification, newest first
+fn stored_logs(root: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .map(|dir| {
+            let (size, modified) = log_dir_usage(&dir);
+            (dir, size, modified)
+        })
+        .collect();
+    logs.sort_by_key(|(_, _, modified)| std::cmp::Reverse(*modified));
+    logs
+}
+
+/// Remove the least recently written logs until the rest fit in `max_bytes`
+fn prune_logs(root: &Path, max_bytes: u64) {
+    let mut total = 0;
+    for (dir, size, _) in stored_logs(root) {
+        total += size;
+        if total > max_bytes {
+            if let Err(e) = fs::remove_dir_all(&dir) {
+                warn!("Failed to remove output log {}: {}", dir.display(), e);
+            }
+        }
+    }
+}
+
+/// List the stored output logs of agent runs and sessions, newest first
+#[tauri::command]
+pub async fn list_run_output_logs(app: AppHandle) -> Result<Vec<RunOutputLogInfo>, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let root = logs_root(&app_data_dir);
+    Ok(stored_logs(&root)
+        .into_iter()
+        .filter_map(|(dir, size_bytes, modified)| {
+            let name = dir.file_name()?.to_string_lossy().into_owned();
+            Some(RunOutputLogInfo {
+                name,
+                path: dir.to_string_lossy().into_owned(),
+                size_bytes,
+                modified_at: DateTime::<Utc>::from(modified).to_rfc3339(),
+            })
+        })
+        .collect())
+}
+
+/// Read the last lines of a stored output log, each prefixed with its time
+/// and stream
+#[tauri::command]
+pub async fn read_run_output_log(
+    app: AppHandle,
+    name: String,
+    lines: Option<usize>,
+) -> Result<Vec<String>, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let dir = mcp_proxy::server_log_dir(&logs_root(&app_data_dir), &name);
+    if !dir.is_dir() {
+        return Err(format!("No output log named '{}'", name));
+    }
+    let lines = lines.unwrap_or(DEFAULT_READ_LINES).min(MAX_READ_LINES);
+    mcp_proxy::tail(&dir, lines).map_err(|e| format!("Failed to read output log: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_log_and_pruning() {
+        let root = tempfile::tempdir().unwrap();
+
+        let log = RunOutputLog::pending(root.path());
+        log.write("stderr", "starting");
+        log.write("stdout", r#"{"type":"system","subtype":"init"}"#);
+        assert!(stored_logs(root.path()).is_empty());
+        log.attach(&session_log_name("abc"));
+        log.attach("session-pid-42");
+        log.write("stdout", r#"{"type":"result"}"#);
+
+        let dir = root.path().join("session-abc");
+        let lines = mcp_proxy::tail(&dir, 10).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("[stderr] starting"), "{}", lines[0]);
+        assert!(lines[2].ends_with(r#"[stdout] {"type":"result"}"#));
+        assert!(!root.path().join("session-pid-42").exists());
+
+        let old = RunOutputLog::open(root.path(), &agent_run_log_name(1));
+        old.write("stdout", &"x".repeat(200));
+        let old_file = mcp_proxy::current_log(&root.path().join("agent-run-1"));
+        fs::File::options()
+            .write(true)
+            .open(&old_file)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        prune_logs(root.path(), 200);
+        assert!(!root.path().join("agent-run-1").exists());
+        assert!(dir.exists());
+    }
+}
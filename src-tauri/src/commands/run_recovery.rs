@@ -0,0 +1,206 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::agents::{get_agent_run, is_process_alive, AgentDb, AgentRun};
+use crate::process::ProcessRegistryState;
+
+/// Prompt sent when a run continues its interrupted Claude session
+pub const RESUME_PROMPT: &str =
+    "Your previous run was interrupted before it finished. Continue the task from where you left off.";
+
+/// A run that was cut off by a crash and can be resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterruptedRun {
+    pub run: AgentRun,
+    /// When the run last produced output
+    pub last_output_at: Option<String>,
+    /// Whether a Claude session was started that `--resume` can pick up
+    pub resumable: bool,
+}
+
+/// Mark runs left 'running' by a crash as interrupted.
+///
+/// Called at startup, before any run has been launched; runs whose process is still
+/// alive (for example started by opcode-cli) are left alone.
+pub fn mark_interrupted_runs(conn: &Connection) -> usize {
+    let runs: Vec<(i64, Option<i64>)> = conn
+        .prepare("SELECT id, pid FROM agent_runs WHERE status = 'running'")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut interrupted = 0;
+    for (run_id, pid) in runs {
+        if pid.is_some_and(|pid| is_process_alive(pid as u32)) {
+            continue;
+        }
+        match conn.execute(
+            "UPDATE agent_runs SET status = 'interrupted', pid = NULL WHERE id = ?1 AND status = 'running'",
+            params![run_id],
+        ) {
+            Ok(rows) => interrupted += rows,
+            Err(e) => warn!("Failed to mark run {} as interrupted: {}", run_id, e),
+        }
+    }
+
+    if interrupted > 0 {
+        info!("Found {} agent runs interrupted by a crash", interrupted);
+    }
+    interrupted
+}
+
+/// Clear a run's pending resume, returning the Claude session to continue if there is one
+pub fn take_resume_session(conn: &Connection, run_id: i64) -> Result<Option<String>, String> {
+    let session_id: Option<String> = conn
+        .query_row(
+            "SELECT CASE WHEN COALESCE(resume_pending, 0) = 1 AND session_id != '' THEN session_id END
+             FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    if session_id.is_some() {
+        conn.execute(
+            "UPDATE agent_runs SET resume_pending = 0 WHERE id = ?1",
+            params![run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(session_id)
+}
+
+/// List runs that were interrupted by a crash, newest first
+#[tauri::command]
+pub async fn list_interrupted_runs(db: State<'_, AgentDb>) -> Result<Vec<InterruptedRun>, String> {
+    let rows: Vec<(i64, Option<String>)> = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, last_output_at FROM agent_runs WHERE status = 'interrupted' ORDER BY id DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+
+    let mut runs = Vec::new();
+    for (run_id, last_output_at) in rows {
+        let run = get_agent_run(db.clone(), run_id).await?;
+        runs.push(InterruptedRun {
+            resumable: !run.session_id.is_empty(),
+            run,
+            last_output_at,
+        });
+    }
+    Ok(runs)
+}
+
+/// Resume an interrupted run by continuing its Claude session with `--resume`.
+///
+/// The run goes back through the queue and keeps its id, so its output streams
+/// to the same `agent-output:{run_id}` events as before.
+#[tauri::command]
+pub async fn resume_agent_run(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let (status, session_id): (String, String) = conn
+            .query_row(
+                "SELECT status, session_id FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| format!("Run {} not found: {}", run_id, e))?;
+
+        if status != "interrupted" {
+            return Err(format!("Run {} was not interrupted", run_id));
+        }
+        if session_id.is_empty() {
+            return Err(
+                "The run was interrupted before Claude started a session; re-run it instead"
+                    .to_string(),
+            );
+        }
+
+        conn.execute(
+            "UPDATE agent_runs SET status = 'queued', resume_pending = 1, completed_at = NULL,
+                 queue_position = (SELECT COALESCE(MAX(queue_position), 0) + 1 FROM agent_runs)
+             WHERE id = ?1",
+            params![run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    info!("Resuming interrupted agent run {}", run_id);
+
+    let launched = crate::commands::queue::dispatch_queued_runs(&app, db, registry).await?;
+    if let Some((_, Err(e))) = launched.into_iter().find(|(id, _)| *id == run_id) {
+        return Err(e);
+    }
+
+    let _ = app.emit("agent-queue-updated", true);
+    Ok(())
+}
+
+/// Give up on an interrupted run
+#[tauri::command]
+pub async fn dismiss_interrupted_run(db: State<'_, AgentDb>, run_id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'interrupted'",
+        params![run_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::agents::open_database;
+
+    #[test]
+    fn test_interrupted_run_resume_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = open_database(&dir.path().join("agents.db")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO agents (id, name, icon, system_prompt) VALUES (1, 'Fixer', 'bot', '');
+             INSERT INTO agent_runs (id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status)
+             VALUES (1, 1, 'Fixer', 'bot', 'Fix it', 'sonnet', '/p', 'abc', 'running'),
+                    (2, 1, 'Fixer', 'bot', 'Fix it', 'sonnet', '/p', '', 'running'),
+                    (3, 1, 'Fixer', 'bot', 'Fix it', 'sonnet', '/p', 'def', 'completed');",
+        )
+        .unwrap();
+
+        assert_eq!(mark_interrupted_runs(&conn), 2);
+        let status: String = conn
+            .query_row("SELECT status FROM agent_runs WHERE id = 3", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(status, "completed");
+
+        // Only a pending resume hands out the session, and only once
+        assert_eq!(take_resume_session(&conn, 1).unwrap(), None);
+        conn.execute("UPDATE agent_runs SET resume_pending = 1", [])
+            .unwrap();
+        assert_eq!(
+            take_resume_session(&conn, 1).unwrap().as_deref(),
+            Some("abc")
+        );
+        assert_eq!(take_resume_session(&conn, 1).unwrap(), None);
+        assert_eq!(take_resume_session(&conn, 2).unwrap(), None);
+    }
+}
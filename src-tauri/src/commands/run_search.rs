@@ -0,0 +1,360 @@
+use log::{info, warn};
+use rusqlite::{params, Connection, ToSql};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::commands::agents::{AgentDb, AgentRun};
+
+/// Tool results can be huge (whole files); only their beginning is indexed
+const MAX_TOOL_RESULT_CHARS: usize = 2000;
+
+/// Default number of search results
+const DEFAULT_SEARCH_LIMIT: u32 = 50;
+
+/// Optional filters narrowing a run search
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunSearchFilters {
+    pub agent_id: Option<i64>,
+    pub project_path: Option<String>,
+    pub status: Option<String>,
+    /// Inclusive start date (YYYY-MM-DD or RFC 3339)
+    pub from: Option<String>,
+    /// Inclusive end date (YYYY-MM-DD or RFC 3339)
+    pub to: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// A run matching a search, with the best matching excerpt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSearchResult {
+    pub run: AgentRun,
+    /// Excerpt with matches wrapped in `**`
+    pub snippet: String,
+}
+
+fn truncate_chars(text: &str, max: usize) -> &str {
+    match text.char_indices().nth(max) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
+
+fn push_content_text(content: &JsonValue, text: &mut String) {
+    match content {
+        JsonValue::String(s) => {
+            text.push_str(truncate_chars(s, MAX_TOOL_RESULT_CHARS));
+            text.push('\n');
+        }
+        JsonValue::Array(blocks) => {
+            for block in blocks {
+                push_block_text(block, text);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn push_block_text(block: &JsonValue, text: &mut String) {
+    match block.get("type").and_then(|t| t.as_str()) {
+        Some("text") => {
+            if let Some(s) = block.get("text").and_then(|t| t.as_str()) {
+                text.push_str(s);
+                text.push('\n');
+            }
+        }
+        // Tool names and inputs carry the file paths and commands a run touched
+        Some("tool_use") => {
+            if let Some(name) = block.get("name").and_then(|n| n.as_str()) {
+                text.push_str(name);
+                text.push(' ');
+            }
+            if let Some(input) = block.get("input") {
+                text.push_str(&input.to_string());
+            }
+            text.push('\n');
+        }
+        Some("tool_result") => {
+            if let Some(content) = block.get("content") {
+                push_content_text(content, text);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Searchable text of one stream-json or session JSONL entry, if it is a message
+pub fn message_text(json: &JsonValue) -> Option<String> {
+    let mut text = String::new();
+    match json.get("type").and_then(|t| t.as_str()) {
+        Some("assistant") | Some("user") => {
+            push_content_text(json.get("message")?.get("content")?, &mut text);
+        }
+        Some("result") => {
+            text.push_str(json.get("result")?.as_str()?);
+            text.push('\n');
+        }
+        _ => return None,
+    }
+    Some(text)
+}
+
+/// Extract the searchable text from a run's stream-json or session JSONL output
+pub fn extract_searchable_text(output: &str) -> String {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+        .filter_map(|json| message_text(&json))
+        .collect()
+}
+
+/// Turn free text into an FTS5 query that matches all terms, the last one as a prefix.
+///
+/// Each term is quoted so punctuation like `-` or `:` isn't read as query syntax.
+pub fn build_fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+
+    let (last, rest) = terms.split_last()?;
+    let mut fts_query = rest.join(" ");
+    if !fts_query.is_empty() {
+        fts_query.push(' ');
+    }
+    fts_query.push_str(last);
+    fts_query.push('*');
+    Some(fts_query)
+}
+
+/// Add or refresh a run in the search index
+pub fn index_run_text(conn: &Connection, run_id: i64, task: &str, output: &str) {
+    let result = conn
+        .execute(
+            "DELETE FROM agent_runs_fts WHERE rowid = ?1",
+            params![run_id],
+        )
+        .and_then(|_| {
+            conn.execute(
+                "INSERT INTO agent_runs_fts (rowid, task, output) VALUES (?1, ?2, ?3)",
+                params![run_id, task, output],
+            )
+        });
+    if let Err(e) = result {
+        warn!("Failed to index agent run {} for search: {}", run_id, e);
+    }
+}
+
+/// Index a finished run's prompt and output.
+pub fn index_finished_run(db_path: &Path, run_id: i64, output: &str) {
+    let conn = match Connection::open(db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to open database to index run {}: {}", run_id, e);
+            return;
+        }
+    };
+
+    match conn.query_row(
+        "SELECT task FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(task) => index_run_text(&conn, run_id, &task, &extract_searchable_text(output)),
+        Err(e) => warn!("Failed to load run {} for indexing: {}", run_id, e),
+    }
+}
+
+/// Path of a Claude session's JSONL file
+pub fn session_file(project_path: &str, session_id: &str) -> Option<PathBuf> {
+    let encoded_project = project_path.replace('/', "-");
+    Some(
+        dirs::home_dir()?
+            .join(".claude")
+            .join("projects")
+            .join(encoded_project)
+            .join(format!("{}.jsonl", session_id)),
+    )
+}
+
+/// Index runs that predate the search index and drop entries for deleted runs
+pub fn backfill_run_search_index(db_path: &Path) {
+    let conn = match Connection::open(db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to open database to backfill run search: {}", e);
+            return;
+        }
+    };
+
+    let _ = conn.execute(
+        "DELETE FROM agent_runs_fts WHERE rowid NOT IN (SELECT id FROM agent_runs)",
+        [],
+    );
+
+    let runs: Vec<(i64, String, String, String)> = conn
+        .prepare(
+            "SELECT id, task, project_path, session_id FROM agent_runs
+             WHERE id NOT IN (SELECT rowid FROM agent_runs_fts)",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect()
+        })
+        .unwrap_or_default();
+
+    if runs.is_empty() {
+        return;
+    }
+
+    for (run_id, task, project_path, session_id) in &runs {
+        let output = session_file(project_path, session_id)
+            .filter(|_| !session_id.is_empty())
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|jsonl| extract_searchable_text(&jsonl))
+            .unwrap_or_default();
+        index_run_text(&conn, *run_id, task, &output);
+    }
+    info!("Indexed {} agent runs for search", runs.len());
+}
+
+/// Search agent runs by prompt and output, best matches first
+#[tauri::command]
+pub async fn search_agent_runs(
+    db: State<'_, AgentDb>,
+    query: String,
+    filters: Option<RunSearchFilters>,
+) -> Result<Vec<RunSearchResult>, String> {
+    let Some(fts_query) = build_fts_query(&query) else {
+        return Ok(Vec::new());
+    };
+    let filters = filters.unwrap_or_default();
+
+    let mut sql = String::from(
+        "SELECT r.id, r.agent_id, r.agent_name, r.agent_icon, r.task, r.model, r.project_path, r.session_id,
+                r.status, r.pid, r.process_started_at, r.created_at, r.completed_at,
+                snippet(agent_runs_fts, -1, '**', '**', '…', 24)
+         FROM agent_runs_fts JOIN agent_runs r ON r.id = agent_runs_fts.rowid
+         WHERE agent_runs_fts MATCH ?",
+    );
+    let mut values: Vec<Box<dyn ToSql>> = vec![Box::new(fts_query)];
+
+    if let Some(agent_id) = filters.agent_id {
+        sql.push_str(" AND r.agent_id = ?");
+        values.push(Box::new(agent_id));
+    }
+    if let Some(project_path) = filters.project_path {
+        sql.push_str(" AND rtrim(r.project_path, '/') = rtrim(?, '/')");
+        values.push(Box::new(project_path));
+    }
+    if let Some(status) = filters.status {
+        sql.push_str(" AND r.status = ?");
+        values.push(Box::new(status));
+    }
+    if let Some(from) = filters.from {
+        sql.push_str(" AND date(r.created_at) >= date(?)");
+        values.push(Box::new(from));
+    }
+    if let Some(to) = filters.to {
+        sql.push_str(" AND date(r.created_at) <= date(?)");
+        values.push(Box::new(to));
+    }
+    sql.push_str(" ORDER BY bm25(agent_runs_fts), r.id DESC LIMIT ?");
+    values.push(Box::new(filters.limit.unwrap_or(DEFAULT_SEARCH_LIMIT)));
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+
+    let results = stmt
+        .query_map(
+            rusqlite::params_from_iter(values.iter().map(|v| v.as_ref())),
+            |row| {
+                Ok(RunSearchResult {
+                    run: AgentRun {
+                        id: Some(row.get(0)?),
+                        agent_id: row.get(1)?,
+                        agent_name: row.get(2)?,
+                        agent_icon: row.get(3)?,
+                        task: row.get(4)?,
+                        model: row.get(5)?,
+                        project_path: row.get(6)?,
+                        session_id: row.get(7)?,
+                        status: row
+                            .get::<_, String>(8)
+                            .unwrap_or_else(|_| "pending".to_string()),
+                        pid: row
+                            .get::<_, Option<i64>>(9)
+                            .ok()
+                            .flatten()
+                            .map(|p| p as u32),
+                        process_started_at: row.get(10)?,
+                        created_at: row.get(11)?,
+                        completed_at: row.get(12)?,
+                    },
+                    snippet: row.get(13)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Search failed: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_fts_query_quotes_terms() {
+        assert_eq!(build_fts_query("   "), None);
+        assert_eq!(build_fts_query("migra").as_deref(), Some("\"migra\"*"));
+        assert_eq!(
+            build_fts_query("db:migrate \"users\"").as_deref(),
+            Some("\"db:migrate\" \"\"\"users\"\"\"*")
+        );
+    }
+
+    #[test]
+    fn test_index_and_match() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE VIRTUAL TABLE agent_runs_fts USING fts5(task, output)",
+            [],
+        )
+        .unwrap();
+
+        let output = [
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Edit","input":{"file_path":"db/migrations/001_users.sql"}}]}}"#,
+            r#"{"type":"result","result":"Added the users migration"}"#,
+        ]
+        .join("\n");
+        index_run_text(
+            &conn,
+            7,
+            "Add a users table",
+            &extract_searchable_text(&output),
+        );
+        // Re-indexing replaces the previous entry
+        index_run_text(
+            &conn,
+            7,
+            "Add a users table",
+            &extract_searchable_text(&output),
+        );
+
+        let query = build_fts_query("migrations").unwrap();
+        let hits: Vec<i64> = conn
+            .prepare("SELECT rowid FROM agent_runs_fts WHERE agent_runs_fts MATCH ?1")
+            .unwrap()
+            .query_map(params![query], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(hits, vec![7]);
+    }
+}
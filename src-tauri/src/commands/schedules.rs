@@ -0,0 +1,197 @@
+use chrono::Local;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::scheduler::{local_to_rfc3339, CronSchedule};
+
+/// A cron schedule attached to an agent
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentSchedule {
+    pub id: i64,
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub cron_expression: String,
+    pub project_path: String,
+    pub task: Option<String>,
+    pub model: Option<String>,
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    pub next_run_at: Option<String>,
+    pub created_at: String,
+}
+
+/// An upcoming scheduled run
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpcomingScheduledRun {
+    pub schedule_id: i64,
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub project_path: String,
+    pub run_at: String,
+}
+
+/// Compute the next run time for a cron expression, if it is valid
+fn next_run_at(cron_expression: &str) -> Option<String> {
+    CronSchedule::parse(cron_expression)
+        .ok()?
+        .next_after(&Local::now().naive_local())
+        .and_then(|t| local_to_rfc3339(&t))
+}
+
+fn query_schedules(conn: &Connection, id: Option<i64>) -> Result<Vec<AgentSchedule>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT s.id, s.agent_id, a.name, s.cron_expression, s.project_path, s.task, s.model,
+                    s.enabled, s.last_run_at, s.created_at
+             FROM agent_schedules s JOIN agents a ON a.id = s.agent_id
+             WHERE ?1 IS NULL OR s.id = ?1
+             ORDER BY s.created_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let schedules = stmt
+        .query_map(params![id], |row| {
+            let cron_expression: String = row.get(3)?;
+            Ok(AgentSchedule {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                agent_name: row.get(2)?,
+                next_run_at: next_run_at(&cron_expression),
+                cron_expression,
+                project_path: row.get(4)?,
+                task: row.get(5)?,
+                model: row.get(6)?,
+                enabled: row.get(7)?,
+                last_run_at: row.get(8)?,
+                created_at: row.get(9)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(schedules)
+}
+
+/// Create a cron schedule for an agent
+#[tauri::command]
+pub async fn create_agent_schedule(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    cron_expression: String,
+    project_path: String,
+    task: Option<String>,
+    model: Option<String>,
+) -> Result<AgentSchedule, String> {
+    CronSchedule::parse(&cron_expression)?;
+
+    if !std::path::Path::new(&project_path).is_dir() {
+        return Err(format!("Project path does not exist: {}", project_path));
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO agent_schedules (agent_id, cron_expression, project_path, task, model) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![agent_id, cron_expression.trim(), project_path, task, model],
+    )
+    .map_err(|e| format!("Failed to create schedule: {}", e))?;
+
+    let id = conn.last_insert_rowid();
+    query_schedules(&conn, Some(id))?
+        .pop()
+        .ok_or_else(|| "Failed to load created schedule".to_string())
+}
+
+/// List all agent schedules
+#[tauri::command]
+pub async fn list_agent_schedules(db: State<'_, AgentDb>) -> Result<Vec<AgentSchedule>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_schedules(&conn, None)
+}
+
+/// Enable or disable an agent schedule
+#[tauri::command]
+pub async fn set_agent_schedule_enabled(
+    db: State<'_, AgentDb>,
+    id: i64,
+    enabled: bool,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let updated = conn
+        .execute(
+            "UPDATE agent_schedules SET enabled = ?1 WHERE id = ?2",
+            params![enabled, id],
+        )
+        .map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err(format!("Schedule {} not found", id));
+    }
+
+    Ok(())
+}
+
+/// Delete an agent schedule
+#[tauri::command]
+pub async fn delete_agent_schedule(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute("DELETE FROM agent_schedules WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// List the next upcoming runs across all enabled schedules, soonest first
+#[tauri::command]
+pub async fn get_upcoming_scheduled_runs(
+    db: State<'_, AgentDb>,
+    limit: Option<usize>,
+) -> Result<Vec<UpcomingScheduledRun>, String> {
+    let limit = limit.unwrap_or(20);
+    let schedules = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        query_schedules(&conn, None)?
+    };
+
+    let now = Local::now().naive_local();
+    let mut upcoming = Vec::new();
+
+    for schedule in schedules.into_iter().filter(|s| s.enabled) {
+        let Ok(cron) = CronSchedule::parse(&schedule.cron_expression) else {
+            continue;
+        };
+
+        // Collect up to `limit` occurrences per schedule; the merged list is trimmed below
+        let mut cursor = now;
+        for _ in 0..limit {
+            let Some(next) = cron.next_after(&cursor) else {
+                break;
+            };
+            cursor = next;
+            if let Some(run_at) = local_to_rfc3339(&next) {
+                upcoming.push((
+                    next,
+                    UpcomingScheduledRun {
+                        schedule_id: schedule.id,
+                        agent_id: schedule.agent_id,
+                        agent_name: schedule.agent_name.clone(),
+                        project_path: schedule.project_path.clone(),
+                        run_at,
+                    },
+                ));
+            }
+        }
+    }
+
+    upcoming.sort_by_key(|(time, _)| *time);
+    Ok(upcoming
+        .into_iter()
+        .take(limit)
+        .map(|(_, run)| run)
+        .collect())
+}
@@ -0,0 +1,358 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::State;
+use zstd::stream::{copy_decode, copy_encode};
+
+use crate::process::ProcessRegistryState;
+
+/// Suffix of a compressed session transcript; Claude Code only reads `.jsonl`, so
+/// archived sessions drop out of every listing until restored
+const ARCHIVE_EXTENSION: &str = "jsonl.zst";
+
+/// Disk space taken by one session transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDiskUsage {
+    pub session_id: String,
+    pub bytes: u64,
+    pub archived: bool,
+    /// Unix timestamp of the session's last activity
+    pub modified_at: u64,
+}
+
+/// Disk space taken by one project's sessions, largest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectDiskUsage {
+    pub project_id: String,
+    pub total_bytes: u64,
+    pub archived_bytes: u64,
+    pub sessions: Vec<SessionDiskUsage>,
+}
+
+/// What to do with old sessions
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionCleanupAction {
+    Archive,
+    Delete,
+}
+
+/// Outcome of cleaning up old sessions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionCleanupResult {
+    /// Sessions archived or deleted, as `project_id/session_id`
+    pub sessions: Vec<String>,
+    pub bytes_freed: u64,
+    /// Sessions skipped because they are running or failed to process
+    pub skipped: Vec<String>,
+}
+
+fn claude_projects_dir() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("projects"))
+}
+
+fn validate_id(value: &str, what: &str) -> Result<(), String> {
+    if value.is_empty() || value.contains(['/', '\\']) || value.contains("..") {
+        return Err(format!("Invalid {} '{}'", what, value));
+    }
+    Ok(())
+}
+
+/// Session id and whether it's archived, for a file in a project directory
+fn session_of(path: &Path) -> Option<(String, bool)> {
+    let name = path.file_name()?.to_str()?;
+    if let Some(id) = name.strip_suffix(&format!(".{}", ARCHIVE_EXTENSION)) {
+        return Some((id.to_string(), true));
+    }
+    name.strip_suffix(".jsonl")
+        .map(|id| (id.to_string(), false))
+}
+
+fn modified_secs(meta: &std::fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Sessions stored in a project directory, archived or not
+fn project_sessions(project_dir: &Path) -> Vec<(SessionDiskUsage, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(project_dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let (session_id, archived) = session_of(&path)?;
+            let meta = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some((
+                SessionDiskUsage {
+                    session_id,
+                    bytes: meta.len(),
+                    archived,
+                    modified_at: modified_secs(&meta),
+                },
+                path,
+            ))
+        })
+        .collect()
+}
+
+/// Compress a session transcript next to itself and remove the original.
+///
+/// The archive keeps the transcript's modification time so its age still reflects
+/// the session's last activity. Returns the archive's path.
+pub fn archive_session_file(path: &Path) -> Result<PathBuf, String> {
+    let modified = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map_err(|e| e.to_string())?;
+    let archive = path.with_extension(ARCHIVE_EXTENSION);
+    let partial = path.with_extension(format!("{}.partial", ARCHIVE_EXTENSION));
+
+    let result = File::open(path)
+        .and_then(|source| {
+            let mut target = File::create(&partial)?;
+            copy_encode(source, &mut target, 3)?;
+            target.set_modified(modified)?;
+            target.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&partial, &archive));
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(format!("Failed to archive {}: {}", path.display(), e));
+    }
+    std::fs::remove_file(path).map_err(|e| e.to_string())?;
+    Ok(archive)
+}
+
+/// Decompress an archived session back into a transcript Claude Code can resume
+pub fn restore_session_file(archive: &Path) -> Result<PathBuf, String> {
+    let Some((session_id, true)) = session_of(archive) else {
+        return Err(format!("{} is not an archived session", archive.display()));
+    };
+    let path = archive.with_file_name(format!("{}.jsonl", session_id));
+    if path.exists() {
+        return Err(format!("Session {} already exists", session_id));
+    }
+    let modified = std::fs::metadata(archive)
+        .and_then(|meta| meta.modified())
+        .map_err(|e| e.to_string())?;
+    let partial = archive.with_file_name(format!("{}.jsonl.partial", session_id));
+
+    let result = File::open(archive)
+        .and_then(|source| {
+            let mut target = File::create(&partial)?;
+            copy_decode(source, &mut target)?;
+            target.set_modified(modified)?;
+            target.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&partial, &path));
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(format!("Failed to restore {}: {}", archive.display(), e));
+    }
+    std::fs::remove_file(archive).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Disk usage of every project's sessions under ~/.claude/projects, largest project first
+#[tauri::command]
+pub async fn get_session_disk_usage() -> Result<Vec<ProjectDiskUsage>, String> {
+    let projects_dir = claude_projects_dir()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let entries = std::fs::read_dir(&projects_dir)
+            .map_err(|e| format!("Failed to read {}: {}", projects_dir.display(), e))?;
+        let mut projects: Vec<ProjectDiskUsage> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| {
+                let mut sessions: Vec<SessionDiskUsage> = project_sessions(&entry.path())
+                    .into_iter()
+                    .map(|(usage, _)| usage)
+                    .collect();
+                sessions.sort_by_key(|s| std::cmp::Reverse(s.bytes));
+                ProjectDiskUsage {
+                    project_id: entry.file_name().to_string_lossy().to_string(),
+                    total_bytes: sessions.iter().map(|s| s.bytes).sum(),
+                    archived_bytes: sessions
+                        .iter()
+                        .filter(|s| s.archived)
+                        .map(|s| s.bytes)
+                        .sum(),
+                    sessions,
+                }
+            })
+            .collect();
+        projects.sort_by_key(|p| std::cmp::Reverse(p.total_bytes));
+        Ok(projects)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Archive or delete sessions with no activity in the last `older_than_days` days,
+/// optionally only in one project. Running sessions are never touched; deleting
+/// also removes old archives.
+#[tauri::command]
+pub async fn cleanup_sessions(
+    registry: State<'_, ProcessRegistryState>,
+    older_than_days: u32,
+    action: SessionCleanupAction,
+    project_id: Option<String>,
+) -> Result<SessionCleanupResult, String> {
+    if let Some(project_id) = &project_id {
+        validate_id(project_id, "project id")?;
+    }
+    let projects_dir = claude_projects_dir()?;
+    let cutoff = SystemTime::now()
+        .checked_sub(Duration::from_secs(older_than_days as u64 * 24 * 60 * 60))
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let running: Vec<String> = registry
+        .0
+        .get_running_claude_sessions()?
+        .into_iter()
+        .filter_map(|process| match process.process_type {
+            crate::process::ProcessType::ClaudeSession { session_id } => Some(session_id),
+            _ => None,
+        })
+        .collect();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let project_dirs: Vec<PathBuf> = match project_id {
+            Some(project_id) => vec![projects_dir.join(project_id)],
+            None => std::fs::read_dir(&projects_dir)
+                .map_err(|e| format!("Failed to read {}: {}", projects_dir.display(), e))?
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect(),
+        };
+
+        let mut result = SessionCleanupResult::default();
+        for project_dir in project_dirs {
+            let project_id = project_dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            for (session, path) in project_sessions(&project_dir) {
+                if session.modified_at >= cutoff
+                    || (action == SessionCleanupAction::Archive && session.archived)
+                {
+                    continue;
+                }
+                let name = format!("{}/{}", project_id, session.session_id);
+                if running.contains(&session.session_id) {
+                    result.skipped.push(name);
+                    continue;
+                }
+
+                let outcome = match action {
+                    SessionCleanupAction::Archive => archive_session_file(&path).map(|archive| {
+                        let archived = std::fs::metadata(archive).map(|m| m.len()).unwrap_or(0);
+                        session.bytes.saturating_sub(archived)
+                    }),
+                    SessionCleanupAction::Delete => std::fs::remove_file(&path)
+                        .map(|_| session.bytes)
+                        .map_err(|e| e.to_string()),
+                };
+                match outcome {
+                    Ok(freed) => {
+                        result.bytes_freed += freed;
+                        result.sessions.push(name);
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to clean up session {}: {}", name, e);
+                        result.skipped.push(name);
+                    }
+                }
+            }
+        }
+        log::info!(
+            "Cleaned up {} sessions ({:?}), freed {} bytes",
+            result.sessions.len(),
+            action,
+            result.bytes_freed
+        );
+        Ok(result)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// List a project's archived sessions, most recently active first
+#[tauri::command]
+pub async fn list_archived_sessions(project_id: String) -> Result<Vec<SessionDiskUsage>, String> {
+    validate_id(&project_id, "project id")?;
+    let mut sessions: Vec<SessionDiskUsage> =
+        project_sessions(&claude_projects_dir()?.join(project_id))
+            .into_iter()
+            .map(|(usage, _)| usage)
+            .filter(|usage| usage.archived)
+            .collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.modified_at));
+    Ok(sessions)
+}
+
+/// Restore an archived session so it shows up and can be resumed again
+#[tauri::command]
+pub async fn restore_archived_session(
+    project_id: String,
+    session_id: String,
+) -> Result<(), String> {
+    validate_id(&project_id, "project id")?;
+    validate_id(&session_id, "session id")?;
+    let archive = claude_projects_dir()?
+        .join(&project_id)
+        .join(format!("{}.{}", session_id, ARCHIVE_EXTENSION));
+    tauri::async_runtime::spawn_blocking(move || restore_session_file(&archive))
+        .await
+        .map_err(|e| e.to_string())??;
+    log::info!("Restored archived session {}/{}", project_id, session_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_and_restore_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc.jsonl");
+        let transcript = "{\"type\":\"user\"}\n".repeat(1000);
+        std::fs::write(&path, &transcript).unwrap();
+        let old = SystemTime::now() - Duration::from_secs(90 * 24 * 60 * 60);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let archive = archive_session_file(&path).unwrap();
+        assert!(!path.exists());
+        let sessions = project_sessions(dir.path());
+        assert_eq!(sessions.len(), 1);
+        let (usage, _) = &sessions[0];
+        assert_eq!(usage.session_id, "abc");
+        assert!(usage.archived);
+        assert!(usage.bytes < transcript.len() as u64);
+        assert_eq!(
+            usage.modified_at,
+            old.duration_since(UNIX_EPOCH).unwrap().as_secs()
+        );
+
+        assert_eq!(restore_session_file(&archive).unwrap(), path);
+        assert!(!archive.exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), transcript);
+        assert!(restore_session_file(&path).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use crate::commands::agent_revisions::{diff_items, DiffLine};
+use crate::commands::budgets::RunUsageTracker;
+
+/// Tools whose `file_path` (or `notebook_path`) input is a file the session modified
+const FILE_EDIT_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// What one session did, as far as a comparison is concerned
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    /// Working directory the session ran in, if it recorded one
+    pub project_path: Option<String>,
+    /// Prompts the user sent, in order
+    pub prompts: Vec<String>,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    /// Files the session edited or wrote, sorted
+    pub files_modified: Vec<String>,
+}
+
+/// Structured comparison of two sessions; deltas are `b - a`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDiff {
+    pub a: SessionSummary,
+    pub b: SessionSummary,
+    /// Prompt-by-prompt diff from `a` to `b`
+    pub prompts: Vec<DiffLine>,
+    pub token_delta: i64,
+    pub cost_delta_usd: f64,
+    pub files_only_in_a: Vec<String>,
+    pub files_only_in_b: Vec<String>,
+    pub files_in_both: Vec<String>,
+}
+
+/// Find a session's JSONL file in whichever project under ~/.claude/projects holds it
+pub fn find_session_file(session_id: &str) -> Result<PathBuf, String> {
+    if session_id.is_empty() || session_id.contains(['/', '\\']) || session_id.contains("..") {
+        return Err(format!("Invalid session id '{}'", session_id));
+    }
+    let projects_dir = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("projects");
+
+    std::fs::read_dir(&projects_dir)
+        .map_err(|e| format!("Failed to read {}: {}", projects_dir.display(), e))?
+        .flatten()
+        .map(|project| project.path().join(format!("{}.jsonl", session_id)))
+        .find(|path| path.is_file())
+        .ok_or_else(|| format!("Session file not found: {}", session_id))
+}
+
+/// Text the user typed in a message; None for tool results and other generated content
+fn prompt_text(json: &JsonValue) -> Option<String> {
+    let content = json.get("message")?.get("content")?;
+    let text = match content {
+        JsonValue::String(s) => s.clone(),
+        JsonValue::Array(blocks) => blocks
+            .iter()
+            .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    let text = text.trim();
+    // Local command output is recorded as user messages but wasn't typed as a prompt
+    if text.is_empty()
+        || text.starts_with("<command-name>")
+        || text.starts_with("<local-command-stdout>")
+        || text.starts_with("Caveat: The messages below were generated by the user")
+    {
+        return None;
+    }
+    Some(text.to_string())
+}
+
+/// Summarize a session from its JSONL transcript
+pub fn summarize_session(session_id: &str, jsonl: &str) -> SessionSummary {
+    let mut summary = SessionSummary {
+        session_id: session_id.to_string(),
+        ..Default::default()
+    };
+    let mut files = BTreeSet::new();
+
+    for json in jsonl
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+    {
+        if summary.project_path.is_none() {
+            summary.project_path = json
+                .get("cwd")
+                .and_then(|c| c.as_str())
+                .filter(|c| !c.is_empty())
+                .map(str::to_string);
+        }
+        match json.get("type").and_then(|t| t.as_str()) {
+            Some("user") => summary.prompts.extend(prompt_text(&json)),
+            Some("assistant") => {
+                let blocks = json
+                    .get("message")
+                    .and_then(|m| m.get("content"))
+                    .and_then(|c| c.as_array())
+                    .into_iter()
+                    .flatten();
+                for block in blocks {
+                    let is_edit = block
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .is_some_and(|name| FILE_EDIT_TOOLS.contains(&name));
+                    if !is_edit {
+                        continue;
+                    }
+                    let input = block.get("input");
+                    let path = input
+                        .and_then(|i| i.get("file_path"))
+                        .or_else(|| input.and_then(|i| i.get("notebook_path")))
+                        .and_then(|p| p.as_str());
+                    if let Some(path) = path {
+                        files.insert(path.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let usage = RunUsageTracker::from_jsonl(jsonl);
+    summary.total_tokens = usage.total_tokens();
+    summary.cost_usd = usage.cost_usd();
+    summary.files_modified = files.into_iter().collect();
+    summary
+}
+
+/// Compare two summarized sessions
+pub fn diff_sessions(a: SessionSummary, b: SessionSummary) -> SessionDiff {
+    let prompts_a: Vec<&str> = a.prompts.iter().map(String::as_str).collect();
+    let prompts_b: Vec<&str> = b.prompts.iter().map(String::as_str).collect();
+    let files_a: BTreeSet<&String> = a.files_modified.iter().collect();
+    let files_b: BTreeSet<&String> = b.files_modified.iter().collect();
+
+    SessionDiff {
+        prompts: diff_items(&prompts_a, &prompts_b),
+        token_delta: b.total_tokens as i64 - a.total_tokens as i64,
+        cost_delta_usd: b.cost_usd - a.cost_usd,
+        files_only_in_a: files_a
+            .difference(&files_b)
+            .map(|f| f.to_string())
+            .collect(),
+        files_only_in_b: files_b
+            .difference(&files_a)
+            .map(|f| f.to_string())
+            .collect(),
+        files_in_both: files_a
+            .intersection(&files_b)
+            .map(|f| f.to_string())
+            .collect(),
+        a,
+        b,
+    }
+}
+
+/// Compare two Claude sessions: their prompts, token and cost usage, and the files they modified
+#[tauri::command]
+pub async fn compare_sessions(
+    session_id_a: String,
+    session_id_b: String,
+) -> Result<SessionDiff, String> {
+    let load = |session_id: &str| -> Result<SessionSummary, String> {
+        let path = find_session_file(session_id)?;
+        let jsonl = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read session {}: {}", session_id, e))?;
+        Ok(summarize_session(session_id, &jsonl))
+    };
+
+    Ok(diff_sessions(load(&session_id_a)?, load(&session_id_b)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn transcript(lines: &[JsonValue]) -> String {
+        lines
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_diff_sessions() {
+        let a = transcript(&[
+            json!({"type": "user", "cwd": "/work/app", "message": {"role": "user", "content": "Fix the login bug"}}),
+            json!({"type": "assistant", "message": {"id": "m1", "model": "claude-sonnet-4", "content": [
+                {"type": "tool_use", "name": "Edit", "input": {"file_path": "/work/app/login.rs"}},
+                {"type": "tool_use", "name": "Read", "input": {"file_path": "/work/app/lib.rs"}}
+            ], "usage": {"input_tokens": 100, "output_tokens": 50}}}),
+            json!({"type": "user", "message": {"role": "user", "content": [{"type": "tool_result", "content": "ok"}]}}),
+        ]);
+        let b = transcript(&[
+            json!({"type": "user", "message": {"role": "user", "content": "Fix the login bug"}}),
+            json!({"type": "user", "message": {"role": "user", "content": [{"type": "text", "text": "Add a test too"}]}}),
+            json!({"type": "assistant", "message": {"id": "m1", "model": "claude-sonnet-4", "content": [
+                {"type": "tool_use", "name": "Edit", "input": {"file_path": "/work/app/login.rs"}},
+                {"type": "tool_use", "name": "Write", "input": {"file_path": "/work/app/login_test.rs"}}
+            ], "usage": {"input_tokens": 300, "output_tokens": 100}}}),
+        ]);
+
+        let diff = diff_sessions(summarize_session("a", &a), summarize_session("b", &b));
+        assert_eq!(diff.a.project_path.as_deref(), Some("/work/app"));
+        assert_eq!(
+            diff.prompts,
+            vec![
+                DiffLine::Unchanged("Fix the login bug".to_string()),
+                DiffLine::Added("Add a test too".to_string()),
+            ]
+        );
+        assert_eq!(diff.token_delta, 250);
+        assert!(diff.cost_delta_usd > 0.0);
+        assert!(diff.files_only_in_a.is_empty());
+        assert_eq!(diff.files_only_in_b, vec!["/work/app/login_test.rs"]);
+        assert_eq!(diff.files_in_both, vec!["/work/app/login.rs"]);
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::checkpoint::state::CheckpointState;
+use crate::checkpoint::Checkpoint;
+
+/// A new session branched off an earlier one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFork {
+    /// Id of the new session; continue it with `resume_claude_code`
+    pub session_id: String,
+    pub project_id: String,
+    /// Transcript lines copied from the original session
+    pub lines_copied: usize,
+    /// Checkpoint whose files were restored into the project, if any
+    pub restored_checkpoint: Option<Checkpoint>,
+    pub warnings: Vec<String>,
+}
+
+fn is_tool_result_message(json: &JsonValue) -> bool {
+    json.get("type").and_then(|t| t.as_str()) == Some("user")
+        && json
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_array())
+            .is_some_and(|blocks| {
+                !blocks.is_empty()
+                    && blocks.iter().all(|block| {
+                        block.get("type").and_then(|t| t.as_str()) == Some("tool_result")
+                    })
+            })
+}
+
+/// Copy a session transcript up to and including a line, under a new session id.
+///
+/// Tool results that answer the last copied message come along with it, since a
+/// session can't be resumed with tool calls left unanswered. Returns the new
+/// transcript and the number of lines in it.
+pub fn fork_transcript(
+    jsonl: &str,
+    message_index: usize,
+    new_session_id: &str,
+) -> Result<(String, usize), String> {
+    let lines: Vec<&str> = jsonl.lines().collect();
+    if message_index >= lines.len() {
+        return Err(format!(
+            "Message {} is past the end of the session ({} lines)",
+            message_index,
+            lines.len()
+        ));
+    }
+
+    let mut end = message_index + 1;
+    while let Some(json) = lines
+        .get(end)
+        .and_then(|line| serde_json::from_str::<JsonValue>(line).ok())
+    {
+        if !is_tool_result_message(&json) {
+            break;
+        }
+        end += 1;
+    }
+
+    let mut forked = String::new();
+    for line in &lines[..end] {
+        match serde_json::from_str::<JsonValue>(line) {
+            Ok(mut json) => {
+                if let Some(session_id) = json.get_mut("sessionId") {
+                    *session_id = JsonValue::String(new_session_id.to_string());
+                }
+                forked.push_str(&json.to_string());
+            }
+            Err(_) => forked.push_str(line),
+        }
+        forked.push('\n');
+    }
+    Ok((forked, end))
+}
+
+fn validate_path_component(value: &str, what: &str) -> Result<(), String> {
+    if value.is_empty() || value.contains(['/', '\\']) || value.contains("..") {
+        return Err(format!("Invalid {} '{}'", what, value));
+    }
+    Ok(())
+}
+
+/// Branch a past session at a message: the new session gets the transcript up to that
+/// line of the original's JSONL, and the original is left untouched.
+///
+/// With `restore_files` the project's files are also put back to the latest checkpoint
+/// taken at or before that message, so the branch starts from matching code.
+#[tauri::command]
+pub async fn fork_session_at_message(
+    checkpoints: State<'_, CheckpointState>,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    message_index: usize,
+    restore_files: Option<bool>,
+) -> Result<SessionFork, String> {
+    validate_path_component(&session_id, "session id")?;
+    validate_path_component(&project_id, "project id")?;
+
+    let project_dir: PathBuf = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("projects")
+        .join(&project_id);
+    let source = project_dir.join(format!("{}.jsonl", session_id));
+    let jsonl = std::fs::read_to_string(&source)
+        .map_err(|e| format!("Failed to read session {}: {}", session_id, e))?;
+
+    let new_session_id = uuid::Uuid::new_v4().to_string();
+    let (forked, lines_copied) = fork_transcript(&jsonl, message_index, &new_session_id)?;
+    std::fs::write(
+        project_dir.join(format!("{}.jsonl", new_session_id)),
+        forked,
+    )
+    .map_err(|e| format!("Failed to write forked session: {}", e))?;
+    log::info!(
+        "Forked session {} at message {} into {}",
+        session_id,
+        message_index,
+        new_session_id
+    );
+
+    let mut fork = SessionFork {
+        session_id: new_session_id,
+        project_id: project_id.clone(),
+        lines_copied,
+        restored_checkpoint: None,
+        warnings: Vec::new(),
+    };
+
+    if restore_files.unwrap_or(false) {
+        let manager = checkpoints
+            .get_or_create_manager(session_id, project_id, PathBuf::from(&project_path))
+            .await
+            .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+        let checkpoint = manager
+            .list_checkpoints()
+            .await
+            .into_iter()
+            .filter(|c| c.message_index <= message_index)
+            .max_by_key(|c| (c.message_index, c.timestamp));
+
+        match checkpoint {
+            Some(checkpoint) => {
+                let result = manager
+                    .restore_checkpoint(&checkpoint.id)
+                    .await
+                    .map_err(|e| format!("Failed to restore checkpoint: {}", e))?;
+                fork.warnings = result.warnings;
+                fork.restored_checkpoint = Some(result.checkpoint);
+            }
+            None => fork.warnings.push(format!(
+                "No checkpoint at or before message {}; project files were left as they are",
+                message_index
+            )),
+        }
+    }
+
+    Ok(fork)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fork_transcript_keeps_tool_results() {
+        let jsonl = [
+            json!({"type": "user", "sessionId": "old", "message": {"role": "user", "content": "List files"}}),
+            json!({"type": "assistant", "sessionId": "old", "message": {"content": [{"type": "tool_use", "id": "t1", "name": "Bash"}]}}),
+            json!({"type": "user", "sessionId": "old", "message": {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t1", "content": "a.rs"}]}}),
+            json!({"type": "assistant", "sessionId": "old", "message": {"content": [{"type": "text", "text": "One file"}]}}),
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        let (forked, lines) = fork_transcript(&jsonl, 1, "new").unwrap();
+        assert_eq!(lines, 3);
+        let parsed: Vec<JsonValue> = forked
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(parsed.len(), 3);
+        assert!(parsed.iter().all(|json| json["sessionId"] == "new"));
+
+        assert_eq!(fork_transcript(&jsonl, 0, "new").unwrap().1, 1);
+        assert!(fork_transcript(&jsonl, 4, "new").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use tauri::{AppHandle, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::stream_batching::{load_stream_batching, replay_stream};
+use crate::commands::stream_filters::{session_stream_filter, StreamFilter};
+use crate::process::{ProcessInfo, ProcessRegistryState, ProcessType};
+
+/// A running session or agent run whose output stream the UI picked up again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReattachedSession {
+    pub process: ProcessInfo,
+    /// Event the buffered output was replayed on; live output continues on it
+    pub output_event: String,
+    pub replayed_lines: usize,
+}
+
+/// Event a process's output lines are emitted on
+fn output_event(process: &ProcessInfo) -> String {
+    match &process.process_type {
+        ProcessType::ClaudeSession { session_id } => format!("claude-output:{}", session_id),
+        ProcessType::AgentRun { .. } => format!("agent-output:{}", process.run_id),
+    }
+}
+
+/// A buffered line as the filter forwards it; lines that aren't stream
+/// messages are forwarded as they are
+fn filter_line(filter: &StreamFilter, line: &str) -> Option<String> {
+    if !filter.is_active() {
+        return Some(line.to_string());
+    }
+    match serde_json::from_str::<serde_json::Value>(line) {
+        Ok(json) => filter.apply(&json, line).map(Cow::into_owned),
+        Err(_) => Some(line.to_string()),
+    }
+}
+
+/// Pick up a Claude session or agent run that kept running while the UI reloaded.
+///
+/// Everything the process has written so far is replayed on its output event, then
+/// live output follows on the same event without gaps or repeats. Listen to
+/// `output_event` before calling this.
+#[tauri::command]
+pub async fn reattach_session(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+) -> Result<ReattachedSession, String> {
+    let process = registry
+        .0
+        .get_process(run_id)?
+        .ok_or_else(|| format!("Run {} is no longer running", run_id))?;
+    let event = output_event(&process);
+
+    let max_message_bytes = load_stream_batching(&db).max_message_bytes;
+    // Sessions replay what their stream filter lets through, like live output
+    let filter = match &process.process_type {
+        ProcessType::ClaudeSession { session_id } => session_stream_filter(&app, session_id),
+        ProcessType::AgentRun { .. } => StreamFilter::default(),
+    };
+
+    let replayed_lines = registry
+        .0
+        .replay_live_output(run_id, |lines| {
+            let lines = lines
+                .into_iter()
+                .filter_map(|line| filter_line(&filter, line))
+                .collect();
+            replay_stream(&app, &event, lines, max_message_bytes);
+        })?
+        .ok_or_else(|| format!("Run {} is no longer running", run_id))?;
+    log::info!(
+        "Reattached to run {}, replayed {} lines on {}",
+        run_id,
+        replayed_lines,
+        event
+    );
+
+    Ok(ReattachedSession {
+        process,
+        output_event: event,
+        replayed_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_line() {
+        let filter = StreamFilter {
+            max_tool_result_bytes: 0,
+            only_text_and_tool_names: true,
+        };
+        let tool_result =
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","content":"output"}]}}"#;
+        assert_eq!(filter_line(&filter, tool_result), None);
+        assert_eq!(
+            filter_line(&StreamFilter::default(), tool_result).as_deref(),
+            Some(tool_result)
+        );
+        assert_eq!(
+            filter_line(&filter, "not json").as_deref(),
+            Some("not json")
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, ToSql};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::run_search::{build_fts_query, message_text};
+
+/// How often the background indexer looks for new session output
+const SESSION_INDEX_INTERVAL_SECS: u64 = 60;
+
+/// Default number of sessions returned by a search
+const DEFAULT_SEARCH_LIMIT: u32 = 50;
+
+/// Matching messages shown per session
+const MAX_MATCHES_PER_SESSION: usize = 5;
+
+/// A message in a session that matches a search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSearchMatch {
+    /// Zero-based line of the message in the session's JSONL file
+    pub line: i64,
+    pub uuid: Option<String>,
+    pub role: String,
+    pub timestamp: Option<String>,
+    /// Excerpt with matches wrapped in `**`
+    pub snippet: String,
+}
+
+/// A session matching a search, with its best matching messages first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSearchResult {
+    pub session_id: String,
+    /// Encoded directory name under ~/.claude/projects
+    pub project_id: String,
+    /// Working directory the session ran in, if it recorded one
+    pub project_path: Option<String>,
+    pub matches: Vec<SessionSearchMatch>,
+}
+
+/// How far a session file has been indexed
+struct IndexedFile {
+    indexed_bytes: u64,
+    indexed_lines: i64,
+    project_path: Option<String>,
+}
+
+fn claude_projects_dir() -> Option<PathBuf> {
+    Some(dirs::home_dir()?.join(".claude").join("projects"))
+}
+
+/// Session JSONL files under ~/.claude/projects as (project id, session id, path)
+fn session_files(projects_dir: &Path) -> Vec<(String, String, PathBuf)> {
+    let mut files = Vec::new();
+    let Ok(projects) = std::fs::read_dir(projects_dir) else {
+        return files;
+    };
+
+    for project in projects.flatten().filter(|e| e.path().is_dir()) {
+        let project_id = project.file_name().to_string_lossy().to_string();
+        let Ok(sessions) = std::fs::read_dir(project.path()) else {
+            continue;
+        };
+        for session in sessions.flatten() {
+            let path = session.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            if let Some(session_id) = path.file_stem().and_then(|s| s.to_str()) {
+                files.push((project_id.clone(), session_id.to_string(), path.clone()));
+            }
+        }
+    }
+    files
+}
+
+/// Index the complete lines appended to a session file since it was last indexed.
+///
+/// Session files are append-only; one that shrank was rewritten and is indexed again
+/// from the start. Returns the number of messages added.
+fn index_session_file(
+    conn: &mut Connection,
+    project_id: &str,
+    session_id: &str,
+    path: &Path,
+) -> Result<usize, String> {
+    let path_str = path.to_string_lossy().to_string();
+    let size = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+
+    let previous = conn
+        .query_row(
+            "SELECT indexed_bytes, indexed_lines, project_path FROM session_index_files WHERE path = ?1",
+            params![path_str],
+            |row| {
+                Ok(IndexedFile {
+                    indexed_bytes: row.get::<_, i64>(0)? as u64,
+                    indexed_lines: row.get(1)?,
+                    project_path: row.get(2)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let mut state = match previous {
+        Some(previous) if previous.indexed_bytes == size => return Ok(0),
+        Some(previous) if previous.indexed_bytes < size => previous,
+        _ => IndexedFile {
+            indexed_bytes: 0,
+            indexed_lines: 0,
+            project_path: None,
+        },
+    };
+
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(state.indexed_bytes))
+        .map_err(|e| e.to_string())?;
+    let mut appended = Vec::new();
+    file.read_to_end(&mut appended).map_err(|e| e.to_string())?;
+    // A line still being written is picked up on the next pass
+    let Some(complete) = appended.iter().rposition(|b| *b == b'\n').map(|i| i + 1) else {
+        return Ok(0);
+    };
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    if state.indexed_bytes == 0 {
+        tx.execute(
+            "DELETE FROM session_messages_fts WHERE path = ?1",
+            params![path_str],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    let mut added = 0;
+    for line in String::from_utf8_lossy(&appended[..complete]).lines() {
+        let line_number = state.indexed_lines;
+        state.indexed_lines += 1;
+        let Ok(json) = serde_json::from_str::<JsonValue>(line) else {
+            continue;
+        };
+        if state.project_path.is_none() {
+            state.project_path = json
+                .get("cwd")
+                .and_then(|c| c.as_str())
+                .filter(|c| !c.is_empty())
+                .map(str::to_string);
+        }
+        let Some(text) = message_text(&json).filter(|t| !t.trim().is_empty()) else {
+            continue;
+        };
+
+        tx.execute(
+            "INSERT INTO session_messages_fts (text, path, line, uuid, role, timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                text,
+                path_str,
+                line_number,
+                json.get("uuid").and_then(|u| u.as_str()),
+                json.get("type").and_then(|t| t.as_str()),
+                json.get("timestamp").and_then(|t| t.as_str()),
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        added += 1;
+    }
+
+    tx.execute(
+        "INSERT OR REPLACE INTO session_index_files (path, project_id, session_id, project_path, indexed_bytes, indexed_lines)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            path_str,
+            project_id,
+            session_id,
+            state.project_path,
+            (state.indexed_bytes + complete as u64) as i64,
+            state.indexed_lines,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(added)
+}
+
+/// Bring the session search index up to date with ~/.claude/projects
+pub fn index_sessions(db_path: &Path) {
+    let Some(projects_dir) = claude_projects_dir() else {
+        return;
+    };
+    let mut conn = match Connection::open(db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to open database to index sessions: {}", e);
+            return;
+        }
+    };
+
+    let files = session_files(&projects_dir);
+    let mut added = 0;
+    for (project_id, session_id, path) in &files {
+        match index_session_file(&mut conn, project_id, session_id, path) {
+            Ok(count) => added += count,
+            Err(e) => warn!("Failed to index session {}: {}", path.display(), e),
+        }
+    }
+
+    // Drop sessions whose files were deleted
+    let present: HashSet<String> = files
+        .iter()
+        .map(|(_, _, path)| path.to_string_lossy().to_string())
+        .collect();
+    let indexed: Vec<String> = conn
+        .prepare("SELECT path FROM session_index_files")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .unwrap_or_default();
+    for path in indexed.iter().filter(|p| !present.contains(*p)) {
+        let _ = conn.execute(
+            "DELETE FROM session_messages_fts WHERE path = ?1",
+            params![path],
+        );
+        let _ = conn.execute(
+            "DELETE FROM session_index_files WHERE path = ?1",
+            params![path],
+        );
+    }
+
+    if added > 0 {
+        info!("Indexed {} session messages for search", added);
+    }
+}
+
+/// Keep the session search index up to date in the background
+pub fn start_session_indexer(db_path: PathBuf) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let path = db_path.clone();
+            let _ = tokio::task::spawn_blocking(move || index_sessions(&path)).await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(
+                SESSION_INDEX_INTERVAL_SECS,
+            ))
+            .await;
+        }
+    });
+}
+
+/// Search the messages of every Claude session, best matching sessions first
+#[tauri::command]
+pub async fn search_sessions(
+    db: State<'_, AgentDb>,
+    query: String,
+    project_id: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<SessionSearchResult>, String> {
+    let Some(fts_query) = build_fts_query(&query) else {
+        return Ok(Vec::new());
+    };
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT) as usize;
+
+    let mut sql = String::from(
+        "SELECT f.session_id, f.project_id, f.project_path, m.line, m.uuid, m.role, m.timestamp,
+                snippet(session_messages_fts, 0, '**', '**', '…', 24)
+         FROM session_messages_fts m JOIN session_index_files f ON f.path = m.path
+         WHERE session_messages_fts MATCH ?",
+    );
+    let mut values: Vec<Box<dyn ToSql>> = vec![Box::new(fts_query)];
+    if let Some(project_id) = project_id {
+        sql.push_str(" AND f.project_id = ?");
+        values.push(Box::new(project_id));
+    }
+    // Enough messages to fill the requested number of sessions in most cases
+    sql.push_str(" ORDER BY bm25(session_messages_fts) LIMIT ?");
+    values.push(Box::new((limit * MAX_MATCHES_PER_SESSION * 4) as i64));
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            rusqlite::params_from_iter(values.iter().map(|v| v.as_ref())),
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    SessionSearchMatch {
+                        line: row.get(3)?,
+                        uuid: row.get(4)?,
+                        role: row.get(5)?,
+                        timestamp: row.get(6)?,
+                        snippet: row.get(7)?,
+                    },
+                ))
+            },
+        )
+        .map_err(|e| format!("Search failed: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // Group messages by session, keeping sessions in order of their best match
+    let mut results: Vec<SessionSearchResult> = Vec::new();
+    let mut positions: HashMap<(String, String), usize> = HashMap::new();
+    for (session_id, project_id, project_path, message) in rows {
+        let key = (project_id.clone(), session_id.clone());
+        let position = match positions.get(&key) {
+            Some(position) => *position,
+            None if results.len() < limit => {
+                positions.insert(key, results.len());
+                results.push(SessionSearchResult {
+                    session_id,
+                    project_id,
+                    project_path,
+                    matches: Vec::new(),
+                });
+                results.len() - 1
+            }
+            None => continue,
+        };
+        let matches = &mut results[position].matches;
+        if matches.len() < MAX_MATCHES_PER_SESSION {
+            matches.push(message);
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_index_session_file_incrementally() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE session_index_files (path TEXT PRIMARY KEY, project_id TEXT NOT NULL, session_id TEXT NOT NULL,
+                 project_path TEXT, indexed_bytes INTEGER NOT NULL DEFAULT 0, indexed_lines INTEGER NOT NULL DEFAULT 0);
+             CREATE VIRTUAL TABLE session_messages_fts USING fts5(text, path UNINDEXED, line UNINDEXED, uuid UNINDEXED, role UNINDEXED, timestamp UNINDEXED);",
+        )
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc.jsonl");
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(
+            file,
+            r#"{{"type":"summary","cwd":"/work/app"}}
+{{"type":"user","uuid":"u1","message":{{"role":"user","content":"Fix the login redirect"}}}}"#
+        )
+        .unwrap();
+        // The second line isn't finished yet
+        write!(
+            file,
+            r#"{{"type":"assistant","uuid":"a1","message":{{"content":[{{"type":"text","text":"Patched redirect"#
+        )
+        .unwrap();
+
+        assert_eq!(
+            index_session_file(&mut conn, "-work-app", "abc", &path).unwrap(),
+            1
+        );
+        writeln!(file, r#" handling"}}]}}}}"#).unwrap();
+        assert_eq!(
+            index_session_file(&mut conn, "-work-app", "abc", &path).unwrap(),
+            1
+        );
+        assert_eq!(
+            index_session_file(&mut conn, "-work-app", "abc", &path).unwrap(),
+            0
+        );
+
+        let hits: Vec<(i64, String)> = conn
+            .prepare("SELECT line, role FROM session_messages_fts WHERE session_messages_fts MATCH ?1 ORDER BY line")
+            .unwrap()
+            .query_map(params![build_fts_query("redirect").unwrap()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            hits,
+            vec![(1, "user".to_string()), (2, "assistant".to_string())]
+        );
+
+        let project_path: Option<String> = conn
+            .query_row("SELECT project_path FROM session_index_files", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(project_path.as_deref(), Some("/work/app"));
+    }
+}
@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::redaction::{redact_stored_session, RedactionReport};
+
+/// Where a shared session is published
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareTarget {
+    /// A standalone HTML file in the app's data directory
+    File,
+    /// A secret GitHub gist
+    Gist,
+}
+
+/// A published session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedSession {
+    /// Path of the HTML file, or URL of the gist
+    pub location: String,
+    /// What was scrubbed from the transcript before publishing
+    pub redactions: RedactionReport,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Text of a tool result, whose content is either a string or a list of blocks
+fn tool_result_text(block: &JsonValue) -> String {
+    match block.get("content") {
+        Some(JsonValue::String(text)) => text.clone(),
+        Some(JsonValue::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn render_block(block: &JsonValue, html: &mut String) {
+    match block.get("type").and_then(|t| t.as_str()) {
+        Some("text") => {
+            let text = block.get("text").and_then(|t| t.as_str()).unwrap_or("");
+            html.push_str(&format!(
+                "<div class=\"text\">{}</div>\n",
+                escape_html(text)
+            ));
+        }
+        Some("thinking") => {
+            let text = block.get("thinking").and_then(|t| t.as_str()).unwrap_or("");
+            html.push_str(&format!(
+                "<details><summary>Thinking</summary><pre>{}</pre></details>\n",
+                escape_html(text)
+            ));
+        }
+        Some("tool_use") => {
+            let name = block.get("name").and_then(|n| n.as_str()).unwrap_or("tool");
+            let input = block
+                .get("input")
+                .map(|i| serde_json::to_string_pretty(i).unwrap_or_default())
+                .unwrap_or_default();
+            html.push_str(&format!(
+                "<details class=\"tool\"><summary>Tool: {}</summary><pre>{}</pre></details>\n",
+                escape_html(name),
+                escape_html(&input)
+            ));
+        }
+        Some("tool_result") => {
+            html.push_str(&format!(
+                "<details class=\"tool\"><summary>Tool result</summary><pre>{}</pre></details>\n",
+                escape_html(&tool_result_text(block))
+            ));
+        }
+        _ => {}
+    }
+}
+
+/// Render a session transcript as a self-contained HTML page
+pub fn render_session_html(session_id: &str, jsonl: &str) -> String {
+    let mut title = None;
+    let mut body = String::new();
+
+    for json in jsonl
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+    {
+        let role = match json.get("type").and_then(|t| t.as_str()) {
+            Some("summary") => {
+                if title.is_none() {
+                    title = json
+                        .get("summary")
+                        .and_then(|s| s.as_str())
+                        .map(str::to_string);
+                }
+                continue;
+            }
+            Some(role @ ("user" | "assistant")) => role,
+            _ => continue,
+        };
+
+        let mut content = String::new();
+        match json.get("message").and_then(|m| m.get("content")) {
+            Some(JsonValue::String(text)) => content.push_str(&format!(
+                "<div class=\"text\">{}</div>\n",
+                escape_html(text)
+            )),
+            Some(JsonValue::Array(blocks)) => {
+                for block in blocks {
+                    render_block(block, &mut content);
+                }
+            }
+            _ => {}
+        }
+        if content.is_empty() {
+            continue;
+        }
+
+        let timestamp = json
+            .get("timestamp")
+            .and_then(|t| t.as_str())
+            .map(|t| format!(" <time>{}</time>", escape_html(t)))
+            .unwrap_or_default();
+        body.push_str(&format!(
+            "<section class=\"{role}\"><h2>{role}{timestamp}</h2>\n{content}</section>\n"
+        ));
+    }
+
+    let title = escape_html(&title.unwrap_or_else(|| format!("Session {}", session_id)));
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>{title}</title>
+<style>
+body {{ font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; max-width: 52rem; margin: 2rem auto; padding: 0 1rem; color: #1f2328; background: #fff; }}
+section {{ border: 1px solid #d0d7de; border-radius: 6px; padding: 0.75rem 1rem; margin: 1rem 0; }}
+section.user {{ background: #f6f8fa; }}
+h2 {{ font-size: 0.8rem; text-transform: uppercase; color: #656d76; margin: 0 0 0.5rem; }}
+time {{ font-weight: normal; text-transform: none; margin-left: 0.5rem; }}
+.text {{ white-space: pre-wrap; line-height: 1.5; }}
+pre {{ white-space: pre-wrap; word-break: break-word; background: #f6f8fa; padding: 0.5rem; border-radius: 4px; font-size: 0.85rem; }}
+details {{ margin: 0.5rem 0; }}
+summary {{ cursor: pointer; color: #656d76; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+{body}</body>
+</html>
+"#
+    )
+}
+
+/// Upload a page as a secret gist and return its URL
+async fn create_gist(token: &str, file_name: &str, content: String) -> Result<String, String> {
+    let payload = serde_json::json!({
+        "description": "Shared Claude Code session",
+        "public": false,
+        "files": { file_name: { "content": content } },
+    });
+    let response = reqwest::Client::new()
+        .post("https://api.github.com/gists")
+        .header("Accept", "application/vnd.github+json")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("User-Agent", "opcode-App")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to create gist: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("GitHub API error ({}): {}", status, error_text));
+    }
+    let gist: JsonValue = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse GitHub response: {}", e))?;
+    gist.get("html_url")
+        .and_then(|u| u.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "GitHub response had no gist URL".to_string())
+}
+
+/// Publish a redacted copy of a session as a standalone HTML page, either as a file
+/// in the app's `shared` directory or as a secret gist (which needs a GitHub token
+/// with the gist scope). The page gets a random name so it can't be guessed.
+#[tauri::command]
+pub async fn share_session(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    project_id: String,
+    session_id: String,
+    target: ShareTarget,
+    github_token: Option<String>,
+) -> Result<SharedSession, String> {
+    let redacted = redact_stored_session(&db, &project_id, &session_id)?;
+    let html = render_session_html(&session_id, &redacted.content);
+    let file_name = format!("session-{}.html", uuid::Uuid::new_v4().simple());
+
+    let location = match target {
+        ShareTarget::File => {
+            let dir = app
+                .path()
+                .app_data_dir()
+                .map_err(|e| format!("Failed to get app data dir: {}", e))?
+                .join("shared");
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            let path = dir.join(&file_name);
+            std::fs::write(&path, html).map_err(|e| format!("Failed to write file: {}", e))?;
+            path.to_string_lossy().to_string()
+        }
+        ShareTarget::Gist => {
+            let token = github_token
+                .filter(|t| !t.trim().is_empty())
+                .ok_or("A GitHub token is needed to share as a gist")?;
+            create_gist(token.trim(), &file_name, html).await?
+        }
+    };
+    log::info!(
+        "Shared session {} with {} redactions at {}",
+        session_id,
+        redacted.report.findings.len(),
+        location
+    );
+
+    Ok(SharedSession {
+        location,
+        redactions: redacted.report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_session_html() {
+        let jsonl = [
+            json!({"type": "summary", "summary": "Fix <login> bug"}),
+            json!({"type": "user", "timestamp": "2025-01-01T10:00:00Z", "message": {"role": "user", "content": "Why does </script> break?"}}),
+            json!({"type": "assistant", "message": {"content": [
+                {"type": "text", "text": "Let me check."},
+                {"type": "tool_use", "name": "Read", "input": {"file_path": "src/app.ts"}}
+            ]}}),
+            json!({"type": "user", "message": {"content": [{"type": "tool_result", "content": [{"type": "text", "text": "a & b"}]}]}}),
+            json!({"type": "system", "subtype": "init"}),
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        let html = render_session_html("abc", &jsonl);
+        assert!(html.contains("<title>Fix &lt;login&gt; bug</title>"));
+        assert!(html.contains("Why does &lt;/script&gt; break?"));
+        assert!(!html.contains("</script>"));
+        assert!(html.contains("<summary>Tool: Read</summary>"));
+        assert!(html.contains("a &amp; b"));
+        assert_eq!(html.matches("<section").count(), 3);
+    }
+}
@@ -0,0 +1,250 @@
+use rusqlite::{params, Connection};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::claude::{execute_claude_code, resume_claude_code};
+use crate::commands::settings::save_setting;
+use crate::process::{PromptQueueState, QueuedPrompt, SessionTab, SessionTabsState};
+
+/// app_settings key for allowing only one running session per project
+const PROJECT_LOCK_KEY: &str = "session_project_lock";
+
+fn load_project_lock(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![PROJECT_LOCK_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .map(|value| value == "true")
+    .unwrap_or(false)
+}
+
+/// Whether sessions in the same project must wait for each other
+pub fn project_lock_enabled(app: &AppHandle) -> bool {
+    app.try_state::<AgentDb>()
+        .and_then(|db| db.0.lock().ok().map(|conn| load_project_lock(&conn)))
+        .unwrap_or(false)
+}
+
+/// Let the frontend know the set of tabs or their state changed
+pub fn notify_tabs_changed(app: &AppHandle) {
+    let _ = app.emit("session-tabs-changed", true);
+}
+
+/// Let the frontend know a tab's prompt queue changed
+fn notify_prompt_queue_changed(app: &AppHandle, tab_id: &str) {
+    let _ = app.emit(&format!("prompt-queue-changed:{}", tab_id), true);
+}
+
+/// Send the next queued prompt of a tab, if it has one. Called when a turn in the
+/// tab completes successfully; a failed or stopped turn leaves the queue waiting.
+pub fn dispatch_queued_prompt(app: &AppHandle, tab_id: &str) {
+    let Some(queue) = app.try_state::<PromptQueueState>() else {
+        return;
+    };
+    let queue = queue.0.clone();
+    let next = match queue.pop(tab_id) {
+        Ok(Some(next)) => next,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("Failed to take queued prompt of tab {}: {}", tab_id, e);
+            return;
+        }
+    };
+    notify_prompt_queue_changed(app, tab_id);
+
+    let app = app.clone();
+    let tab_id = tab_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        log::info!("Sending queued prompt {} in tab {}", next.id, tab_id);
+        let result = send_session_input(
+            app.clone(),
+            app.state::<SessionTabsState>(),
+            tab_id.clone(),
+            next.prompt.clone(),
+            next.model.clone(),
+            next.attachments.clone(),
+        )
+        .await;
+        if let Err(e) = result {
+            log::warn!("Failed to send queued prompt in tab {}: {}", tab_id, e);
+            if let Err(e) = queue.requeue(&tab_id, next) {
+                log::warn!("Failed to requeue prompt in tab {}: {}", tab_id, e);
+            }
+            notify_prompt_queue_changed(&app, &tab_id);
+        }
+    });
+}
+
+/// Open a tab for a project, optionally showing an existing session
+#[tauri::command]
+pub async fn open_session_tab(
+    app: AppHandle,
+    tabs: State<'_, SessionTabsState>,
+    project_path: String,
+    session_id: Option<String>,
+) -> Result<SessionTab, String> {
+    let tab = tabs.0.open(project_path, session_id)?;
+    notify_tabs_changed(&app);
+    Ok(tab)
+}
+
+/// List open session tabs in the order they were opened
+#[tauri::command]
+pub async fn list_session_tabs(
+    tabs: State<'_, SessionTabsState>,
+) -> Result<Vec<SessionTab>, String> {
+    tabs.0.list()
+}
+
+/// Mark a tab as the one the user is looking at
+#[tauri::command]
+pub async fn focus_session_tab(
+    app: AppHandle,
+    tabs: State<'_, SessionTabsState>,
+    tab_id: String,
+) -> Result<(), String> {
+    tabs.0.focus(&tab_id)?;
+    notify_tabs_changed(&app);
+    Ok(())
+}
+
+/// Close a tab, stopping its Claude process if one is running
+#[tauri::command]
+pub async fn close_session_tab(
+    app: AppHandle,
+    tabs: State<'_, SessionTabsState>,
+    tab_id: String,
+) -> Result<(), String> {
+    let tab = tabs.0.close(&tab_id)?;
+    if let Some(queue) = app.try_state::<PromptQueueState>() {
+        queue.0.clear(&tab_id)?;
+    }
+    if tab.running {
+        log::info!("Stopping Claude process of closed tab {}", tab_id);
+    }
+    notify_tabs_changed(&app);
+    Ok(())
+}
+
+/// Send a prompt to a tab: it resumes the tab's session, or starts a new one if the
+/// tab has none yet. Output arrives on `tab-output:{tab_id}`.
+#[tauri::command]
+pub async fn send_session_input(
+    app: AppHandle,
+    tabs: State<'_, SessionTabsState>,
+    tab_id: String,
+    prompt: String,
+    model: String,
+    attachments: Option<Vec<String>>,
+) -> Result<(), String> {
+    let tab = tabs.0.get(&tab_id)?;
+    match tab.session_id {
+        Some(session_id) => {
+            resume_claude_code(
+                app,
+                tab.project_path,
+                session_id,
+                prompt,
+                model,
+                Some(tab_id),
+                attachments,
+            )
+            .await
+        }
+        None => {
+            execute_claude_code(
+                app,
+                tab.project_path,
+                prompt,
+                model,
+                Some(tab_id),
+                attachments,
+            )
+            .await
+        }
+    }
+}
+
+/// Queue a prompt for a tab. It is sent once the turn in progress completes, or right
+/// away when the tab is idle.
+#[tauri::command]
+pub async fn queue_session_prompt(
+    app: AppHandle,
+    tabs: State<'_, SessionTabsState>,
+    queue: State<'_, PromptQueueState>,
+    tab_id: String,
+    prompt: String,
+    model: String,
+    attachments: Option<Vec<String>>,
+) -> Result<QueuedPrompt, String> {
+    let tab = tabs.0.get(&tab_id)?;
+    let queued = queue.0.push(&tab_id, prompt, model, attachments)?;
+    notify_prompt_queue_changed(&app, &tab_id);
+    if !tab.running {
+        dispatch_queued_prompt(&app, &tab_id);
+    }
+    Ok(queued)
+}
+
+/// List the prompts queued in a tab, next one first
+#[tauri::command]
+pub async fn list_queued_prompts(
+    queue: State<'_, PromptQueueState>,
+    tab_id: String,
+) -> Result<Vec<QueuedPrompt>, String> {
+    queue.0.list(&tab_id)
+}
+
+/// Change the order of a tab's queued prompts; every queued prompt must be listed
+#[tauri::command]
+pub async fn reorder_queued_prompts(
+    app: AppHandle,
+    queue: State<'_, PromptQueueState>,
+    tab_id: String,
+    prompt_ids: Vec<String>,
+) -> Result<Vec<QueuedPrompt>, String> {
+    queue.0.reorder(&tab_id, &prompt_ids)?;
+    notify_prompt_queue_changed(&app, &tab_id);
+    queue.0.list(&tab_id)
+}
+
+/// Remove a prompt from a tab's queue before it is sent
+#[tauri::command]
+pub async fn cancel_queued_prompt(
+    app: AppHandle,
+    queue: State<'_, PromptQueueState>,
+    tab_id: String,
+    prompt_id: String,
+) -> Result<(), String> {
+    queue.0.cancel(&tab_id, &prompt_id)?;
+    notify_prompt_queue_changed(&app, &tab_id);
+    Ok(())
+}
+
+/// Drop every prompt queued in a tab
+#[tauri::command]
+pub async fn clear_prompt_queue(
+    app: AppHandle,
+    queue: State<'_, PromptQueueState>,
+    tab_id: String,
+) -> Result<usize, String> {
+    let cleared = queue.0.clear(&tab_id)?;
+    notify_prompt_queue_changed(&app, &tab_id);
+    Ok(cleared)
+}
+
+/// Whether only one session may run per project at a time
+#[tauri::command]
+pub async fn get_session_project_lock(db: State<'_, AgentDb>) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_project_lock(&conn))
+}
+
+/// Allow only one running session per project, or lift that limit
+#[tauri::command]
+pub async fn set_session_project_lock(db: State<'_, AgentDb>, enabled: bool) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_setting(&conn, PROJECT_LOCK_KEY, &enabled.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,309 @@
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::{read_session_jsonl, AgentDb};
+use crate::commands::claude::Session;
+use crate::commands::settings::save_setting;
+
+/// app_settings key for generating session titles automatically
+const AUTO_TITLE_KEY: &str = "session_auto_titles";
+
+/// Model used for titling; a title isn't worth more than the cheapest one
+const TITLE_MODEL: &str = "haiku";
+
+/// How long a titling call may take before it is given up
+const TITLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How much of each side of the first exchange is sent for titling
+const EXCERPT_CHARS: usize = 2000;
+
+const MAX_TITLE_CHARS: usize = 60;
+
+fn load_auto_titles(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![AUTO_TITLE_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .map(|value| value != "false")
+    .unwrap_or(true)
+}
+
+/// Stored title of a session, if it has one
+fn load_title(conn: &Connection, session_id: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT title FROM session_titles WHERE session_id = ?1",
+        params![session_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Fill in the stored titles of listed sessions
+pub fn apply_session_titles(conn: &Connection, sessions: &mut [Session]) {
+    let titles: HashMap<String, String> = conn
+        .prepare("SELECT session_id, title FROM session_titles")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect()
+        })
+        .unwrap_or_default();
+    for session in sessions {
+        session.title = titles.get(&session.id).cloned();
+    }
+}
+
+/// Text of a message, whose content is either a string or a list of blocks
+fn message_text(json: &JsonValue) -> Option<String> {
+    let text = match json.get("message")?.get("content")? {
+        JsonValue::String(text) => text.clone(),
+        JsonValue::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    let text = text.trim();
+    // Command output and other injected context isn't what the session is about
+    if text.is_empty() || text.starts_with('<') || text.starts_with("Caveat:") {
+        return None;
+    }
+    Some(text.chars().take(EXCERPT_CHARS).collect())
+}
+
+/// The first user message and the first assistant reply after it
+pub fn first_exchange(jsonl: &str) -> Option<(String, String)> {
+    let mut user = None;
+    for json in jsonl
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+    {
+        match (json.get("type").and_then(|t| t.as_str()), &user) {
+            (Some("user"), None) => user = message_text(&json),
+            (Some("assistant"), Some(user)) => {
+                if let Some(reply) = message_text(&json) {
+                    return Some((user.clone(), reply));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Tidy up what the model answered into a single short title
+pub fn clean_title(output: &str) -> Option<String> {
+    let line = output.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let line = line
+        .trim_start_matches('#')
+        .trim_start()
+        .trim_start_matches("Title:")
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'' || c == '`' || c == '*')
+        .trim_end_matches('.')
+        .trim();
+    if line.is_empty() {
+        return None;
+    }
+    let mut title: String = line.chars().take(MAX_TITLE_CHARS).collect();
+    if line.chars().count() > MAX_TITLE_CHARS {
+        title = title.trim_end().to_string();
+        title.push('…');
+    }
+    Some(title)
+}
+
+/// Ask Claude for a title summarizing a session's first exchange
+async fn summarize_exchange(app: &AppHandle, user: &str, reply: &str) -> Result<String, String> {
+    let claude_path = crate::claude_binary::find_claude_binary(app)?;
+    let prompt = format!(
+        "Write a title of at most six words for this conversation. Reply with the title only.\n\n<user>\n{}\n</user>\n\n<assistant>\n{}\n</assistant>",
+        user, reply
+    );
+
+    let mut cmd =
+        tokio::process::Command::from(crate::claude_binary::create_command_with_env(&claude_path));
+    // Run outside the project so the titling call doesn't show up among its sessions
+    cmd.args([
+        "-p",
+        &prompt,
+        "--model",
+        TITLE_MODEL,
+        "--output-format",
+        "text",
+    ])
+    .current_dir(std::env::temp_dir())
+    .stdin(Stdio::null())
+    .kill_on_drop(true);
+
+    let output = tokio::time::timeout(TITLE_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| "Timed out generating a session title".to_string())?
+        .map_err(|e| format!("Failed to run claude: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "claude exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    clean_title(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| "Claude returned an empty title".to_string())
+}
+
+/// Generate and store a title for a session from its first exchange
+async fn generate_title(
+    app: &AppHandle,
+    session_id: &str,
+    project_path: &str,
+) -> Result<Option<String>, String> {
+    let jsonl = read_session_jsonl(session_id, project_path).await?;
+    let Some((user, reply)) = first_exchange(&jsonl) else {
+        return Ok(None);
+    };
+    let title = summarize_exchange(app, &user, &reply).await?;
+
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    // A title the user set in the meantime wins
+    conn.execute(
+        "INSERT INTO session_titles (session_id, title, user_set) VALUES (?1, ?2, 0)
+         ON CONFLICT(session_id) DO UPDATE SET title = excluded.title, updated_at = CURRENT_TIMESTAMP
+         WHERE user_set = 0",
+        params![session_id, title],
+    )
+    .map_err(|e| format!("Failed to save session title: {}", e))?;
+    load_title(&conn, session_id)
+}
+
+/// Title a session in the background once its first exchange is done, unless it
+/// already has a title or automatic titles are turned off
+pub fn spawn_session_titling(app: AppHandle, session_id: String, project_path: String) {
+    let wanted = app
+        .try_state::<AgentDb>()
+        .and_then(|db| {
+            let conn = db.0.lock().ok()?;
+            Some(load_auto_titles(&conn) && matches!(load_title(&conn, &session_id), Ok(None)))
+        })
+        .unwrap_or(false);
+    if !wanted {
+        return;
+    }
+
+    tokio::spawn(async move {
+        match generate_title(&app, &session_id, &project_path).await {
+            Ok(Some(title)) => {
+                info!("Titled session {}: {}", session_id, title);
+                let _ = app.emit(
+                    "session-titled",
+                    serde_json::json!({ "session_id": session_id, "title": title }),
+                );
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to title session {}: {}", session_id, e),
+        }
+    });
+}
+
+/// Give a session a title of the user's choosing; an empty title removes it so a
+/// new one can be generated
+#[tauri::command]
+pub async fn rename_session(
+    db: State<'_, AgentDb>,
+    session_id: String,
+    title: String,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let title = title.trim();
+    if title.is_empty() {
+        conn.execute(
+            "DELETE FROM session_titles WHERE session_id = ?1",
+            params![session_id],
+        )
+        .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    conn.execute(
+        "INSERT INTO session_titles (session_id, title, user_set) VALUES (?1, ?2, 1)
+         ON CONFLICT(session_id) DO UPDATE SET title = excluded.title, user_set = 1, updated_at = CURRENT_TIMESTAMP",
+        params![session_id, title],
+    )
+    .map_err(|e| format!("Failed to rename session: {}", e))?;
+    Ok(())
+}
+
+/// Generate a title for a session now, e.g. one from before titling was turned on.
+/// Titles set by the user are kept.
+#[tauri::command]
+pub async fn generate_session_title(
+    app: AppHandle,
+    session_id: String,
+    project_path: String,
+) -> Result<Option<String>, String> {
+    generate_title(&app, &session_id, &project_path).await
+}
+
+/// Whether sessions are titled automatically
+#[tauri::command]
+pub async fn get_session_auto_titles(db: State<'_, AgentDb>) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_auto_titles(&conn))
+}
+
+/// Turn automatic session titles on or off
+#[tauri::command]
+pub async fn set_session_auto_titles(db: State<'_, AgentDb>, enabled: bool) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_setting(&conn, AUTO_TITLE_KEY, &enabled.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_first_exchange_and_clean_title() {
+        let jsonl = [
+            json!({"type": "summary", "summary": "ignored"}),
+            json!({"type": "user", "message": {"content": "<command-name>/clear</command-name>"}}),
+            json!({"type": "user", "message": {"content": "Why does the login form reload?"}}),
+            json!({"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Read"}]}}),
+            json!({"type": "assistant", "message": {"content": [{"type": "text", "text": "The submit handler is missing preventDefault."}]}}),
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        assert_eq!(
+            first_exchange(&jsonl),
+            Some((
+                "Why does the login form reload?".to_string(),
+                "The submit handler is missing preventDefault.".to_string()
+            ))
+        );
+        assert_eq!(first_exchange(""), None);
+
+        assert_eq!(
+            clean_title("\n\"Fix login form reload.\"\nextra"),
+            Some("Fix login form reload".to_string())
+        );
+        assert_eq!(
+            clean_title("# Title: Debugging flaky CI"),
+            Some("Debugging flaky CI".to_string())
+        );
+        assert_eq!(clean_title("  \n"), None);
+        assert!(clean_title(&"word ".repeat(30)).unwrap().ends_with('…'));
+    }
+}
@@ -0,0 +1,209 @@
+use rusqlite::{params, Connection};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::proxy::{apply_proxy_settings, ProxySettings};
+use crate::webhook::{delete_webhook_token, webhook_token};
+
+/// A single recorded change to the app_settings table
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SettingsAuditEntry {
+    pub id: i64,
+    pub key: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub changed_at: String,
+}
+
+const SHELL_SETTINGS_KEYS: &[&str] = &[
+    "shell_environment",
+    "wsl_distro",
+    "wsl_claude_path",
+    "git_bash_path",
+];
+
+const BINARY_SETTINGS_KEYS: &[&str] = &["claude_binary_path", "claude_installation_preference"];
+
+const QUEUE_SETTINGS_KEYS: &[&str] = &[
+    "queue_max_concurrent_runs",
+    "queue_max_concurrent_per_project",
+];
+
+const WEBHOOK_SETTINGS_KEYS: &[&str] = &["webhook_enabled", "webhook_port"];
+
+const PROXY_SETTINGS_KEYS: &[&str] = &[
+    "proxy_enabled",
+    "proxy_http",
+    "proxy_https",
+    "proxy_no",
+    "proxy_all",
+];
+
+/// Read a setting stored as JSON, or its default when it's missing or unreadable
+pub fn load_json_setting<T: DeserializeOwned + Default>(conn: &Connection, key: &str) -> T {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+/// Store a setting's raw value
+pub fn save_setting(conn: &Connection, key: &str, value: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    )
+    .map_err(|e| format!("Failed to save {}: {}", key, e))?;
+    Ok(())
+}
+
+/// Store a setting as JSON
+pub fn save_json_setting(
+    conn: &Connection,
+    key: &str,
+    value: &impl Serialize,
+) -> Result<(), String> {
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    save_setting(conn, key, &json)
+}
+
+/// Returns the settings keys that belong to a reset scope
+fn settings_keys_for_scope(scope: &str) -> Option<Vec<&'static str>> {
+    match scope {
+        "shell" => Some(SHELL_SETTINGS_KEYS.to_vec()),
+        "binary" => Some(BINARY_SETTINGS_KEYS.to_vec()),
+        "proxy" => Some(PROXY_SETTINGS_KEYS.to_vec()),
+        "queue" => Some(QUEUE_SETTINGS_KEYS.to_vec()),
+        "webhook" => Some(WEBHOOK_SETTINGS_KEYS.to_vec()),
+        "all" => Some(
+            SHELL_SETTINGS_KEYS
+                .iter()
+                .chain(BINARY_SETTINGS_KEYS)
+                .chain(PROXY_SETTINGS_KEYS)
+                .chain(QUEUE_SETTINGS_KEYS)
+                .chain(WEBHOOK_SETTINGS_KEYS)
+                .copied()
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Get the settings audit trail, newest first
+#[tauri::command]
+pub async fn get_settings_audit(
+    db: State<'_, AgentDb>,
+    key: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<SettingsAuditEntry>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, key, old_value, new_value, changed_at FROM settings_audit
+             WHERE ?1 IS NULL OR key = ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let entries = stmt
+        .query_map(params![key, limit.unwrap_or(200)], |row| {
+            Ok(SettingsAuditEntry {
+                id: row.get(0)?,
+                key: row.get(1)?,
+                old_value: row.get(2)?,
+                new_value: row.get(3)?,
+                changed_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(entries)
+}
+
+/// Reset a group of settings (shell, binary, proxy, queue, webhook or all) to their defaults
+#[tauri::command]
+pub async fn reset_settings(db: State<'_, AgentDb>, scope: String) -> Result<Vec<String>, String> {
+    let keys = settings_keys_for_scope(&scope).ok_or_else(|| {
+        format!(
+            "Unknown settings scope '{}'. Expected one of: shell, binary, proxy, queue, webhook, all",
+            scope
+        )
+    })?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    // Defaults are represented by the absence of a key, so resetting is a delete
+    let mut reset_keys = Vec::new();
+    for key in keys {
+        let removed = conn
+            .execute("DELETE FROM app_settings WHERE key = ?1", params![key])
+            .map_err(|e| format!("Failed to reset {}: {}", key, e))?;
+        if removed > 0 {
+            reset_keys.push(key.to_string());
+        }
+    }
+    drop(conn);
+
+    // The webhook token is kept in the keychain rather than app_settings
+    if (scope == "webhook" || scope == "all") && webhook_token()?.is_some() {
+        delete_webhook_token()?;
+        reset_keys.push("webhook_token".to_string());
+    }
+
+    // Proxy settings live in the process environment too, so clear them there as well
+    if scope == "proxy" || scope == "all" {
+        apply_proxy_settings(&ProxySettings::default());
+    }
+
+    log::info!("Reset {} settings in scope '{}'", reset_keys.len(), scope);
+    Ok(reset_keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::agents::open_database;
+
+    #[test]
+    fn test_audit_masks_secret_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = open_database(&dir.path().join("agents.db")).unwrap();
+        for (key, value) in [
+            ("webhook_token", "first"),
+            ("webhook_token", "second"),
+            ("webhook_port", "8787"),
+        ] {
+            conn.execute(
+                "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+                rusqlite::params![key, value],
+            )
+            .unwrap();
+        }
+
+        let audit: Vec<(String, Option<String>, Option<String>)> = conn
+            .prepare("SELECT key, old_value, new_value FROM settings_audit ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let masked = Some("********".to_string());
+        assert_eq!(
+            audit,
+            vec![
+                ("webhook_token".to_string(), None, masked.clone()),
+                ("webhook_token".to_string(), masked.clone(), masked),
+                ("webhook_port".to_string(), None, Some("8787".to_string())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::commands::agents::{read_session_jsonl, AgentDb, READ_ONLY_DISALLOWED_TOOLS};
+use crate::commands::budgets::RunUsageTracker;
+use crate::process::ProcessRegistryState;
+use crate::stream_json::{parse_line, parse_message, StreamMessage};
+
+/// A prompt sent to a session and, on the side, to a second model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowRun {
+    pub id: i64,
+    pub project_path: String,
+    /// The session the prompt was sent to
+    pub session_id: String,
+    pub prompt: String,
+    pub primary_model: String,
+    pub shadow_model: String,
+    /// The session's reply, filled in from its transcript once available
+    pub primary_response: Option<String>,
+    /// The forked session the shadow model answered in
+    pub shadow_session_id: Option<String>,
+    pub shadow_response: Option<String>,
+    /// 'running', 'completed' or 'failed'
+    pub status: String,
+    pub error: Option<String>,
+    pub shadow_total_tokens: i64,
+    pub shadow_cost_usd: f64,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+fn row_to_shadow_run(row: &rusqlite::Row) -> rusqlite::Result<ShadowRun> {
+    Ok(ShadowRun {
+        id: row.get(0)?,
+        project_path: row.get(1)?,
+        session_id: row.get(2)?,
+        prompt: row.get(3)?,
+        primary_model: row.get(4)?,
+        shadow_model: row.get(5)?,
+        primary_response: row.get(6)?,
+        shadow_session_id: row.get(7)?,
+        shadow_response: row.get(8)?,
+        status: row.get(9)?,
+        error: row.get(10)?,
+        shadow_total_tokens: row.get(11)?,
+        shadow_cost_usd: row.get(12)?,
+        created_at: row.get(13)?,
+        completed_at: row.get(14)?,
+    })
+}
+
+const SHADOW_RUN_COLUMNS: &str =
+    "id, project_path, session_id, prompt, primary_model, shadow_model,
+     primary_response, shadow_session_id, shadow_response, status, error, shadow_total_tokens,
+     shadow_cost_usd, created_at, completed_at";
+
+fn load_shadow_run(conn: &Connection, shadow_run_id: i64) -> Result<ShadowRun, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM shadow_runs WHERE id = ?1",
+            SHADOW_RUN_COLUMNS
+        ),
+        params![shadow_run_id],
+        row_to_shadow_run,
+    )
+    .map_err(|e| format!("Shadow run {} not found: {}", shadow_run_id, e))
+}
+
+/// Arguments for the shadow process: a read-only fork of the session, so the
+/// shadow model sees the same conversation without adding to it or touching files
+pub fn shadow_args(session_id: &str, prompt: &str, model: &str, extra: Vec<String>) -> Vec<String> {
+    let mut args = vec![
+        "--resume".to_string(),
+        session_id.to_string(),
+        "--fork-session".to_string(),
+        "-p".to_string(),
+        prompt.to_string(),
+        "--model".to_string(),
+        model.to_string(),
+        "--output-format".to_string(),
+        "stream-json".to_string(),
+        "--verbose".to_string(),
+        "--dangerously-skip-permissions".to_string(),
+        "--disallowedTools".to_string(),
+        READ_ONLY_DISALLOWED_TOOLS.join(","),
+    ];
+    args.extend(extra);
+    args
+}
+
+/// What the shadow process reported on its stream
+#[derive(Debug, Default)]
+pub struct ShadowOutput {
+    pub session_id: Option<String>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub usage: RunUsageTracker,
+}
+
+impl ShadowOutput {
+    pub fn record(&mut self, json: &JsonValue) {
+        self.usage.record(json);
+        match parse_message(json) {
+            StreamMessage::Init(init) => {
+                self.session_id = Some(init.session_id);
+            }
+            StreamMessage::Result(result) => {
+                if result.is_error {
+                    self.error = result
+                        .result
+                        .or_else(|| Some("Shadow model failed".to_string()));
+                } else {
+                    self.response = result.result;
+                }
+            }
+            StreamMessage::Error { message, .. } => {
+                self.error.get_or_insert(message);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Text of an assistant message's text blocks
+fn assistant_text(json: &JsonValue) -> Option<String> {
+    let text = match json.get("message")?.get("content")? {
+        JsonValue::String(text) => text.clone(),
+        JsonValue::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// Whether a user entry is a prompt rather than tool results fed back to Claude
+fn is_user_prompt(json: &JsonValue) -> bool {
+    match json.get("message").and_then(|m| m.get("content")) {
+        Some(JsonValue::String(_)) => true,
+        Some(JsonValue::Array(blocks)) => blocks
+            .iter()
+            .any(|b| b.get("type").and_then(|t| t.as_str()) != Some("tool_result")),
+        _ => false,
+    }
+}
+
+/// The session's reply to the prompt written after line `offset` of its transcript,
+/// and whether the reply is finished because another prompt followed it
+pub fn response_after(jsonl: &str, offset: usize) -> (Option<String>, bool) {
+    let mut prompted = false;
+    let mut texts = Vec::new();
+    for json in jsonl
+        .lines()
+        .skip(offset)
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+    {
+        match json.get("type").and_then(|t| t.as_str()) {
+            Some("user") if is_user_prompt(&json) => {
+                if prompted {
+                    return (Some(texts.join("\n\n")), true);
+                }
+                prompted = true;
+            }
+            Some("assistant") if prompted => texts.extend(assistant_text(&json)),
+            _ => {}
+        }
+    }
+    ((!texts.is_empty()).then(|| texts.join("\n\n")), false)
+}
+
+/// Run the shadow process to completion and record what it answered
+async fn run_shadow(app: AppHandle, shadow_run_id: i64, args: Vec<String>, project_path: String) {
+    let output = match stream_shadow(&app, args, &project_path).await {
+        Ok(output) => output,
+        Err(e) => ShadowOutput {
+            error: Some(e),
+            ..Default::default()
+        },
+    };
+    let status = if output.error.is_none() && output.response.is_some() {
+        "completed"
+    } else {
+        "failed"
+    };
+    let error = match (&output.error, &output.response) {
+        (Some(e), _) => Some(e.clone()),
+        (None, None) => Some("Shadow model returned no response".to_string()),
+        (None, Some(_)) => None,
+    };
+
+    {
+        let db = app.state::<AgentDb>();
+        let Ok(conn) = db.0.lock() else {
+            return;
+        };
+        if let Err(e) = conn.execute(
+            "UPDATE shadow_runs SET shadow_session_id = ?1, shadow_response = ?2, status = ?3, error = ?4,
+                 shadow_total_tokens = ?5, shadow_cost_usd = ?6, completed_at = CURRENT_TIMESTAMP
+             WHERE id = ?7",
+            params![
+                output.session_id,
+                output.response,
+                status,
+                error,
+                output.usage.total_tokens() as i64,
+                output.usage.cost_usd(),
+                shadow_run_id
+            ],
+        ) {
+            warn!("Failed to record shadow run {}: {}", shadow_run_id, e);
+        }
+    }
+
+    info!("Shadow run {} {}", shadow_run_id, status);
+    let _ = app.emit(
+        "shadow-run-complete",
+        serde_json::json!({ "shadow_run_id": shadow_run_id, "status": status }),
+    );
+}
+
+async fn stream_shadow(
+    app: &AppHandle,
+    args: Vec<String>,
+    project_path: &str,
+) -> Result<ShadowOutput, String> {
+    let claude_path = crate::claude_binary::find_claude_binary(app)?;
+    let mut cmd =
+        tokio::process::Command::from(crate::claude_binary::create_command_with_env(&claude_path));
+    cmd.args(args)
+        .current_dir(project_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
+    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
+
+    let mut output = ShadowOutput::default();
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        match parse_line(&line) {
+            Ok((json, _)) => output.record(&json),
+            Err(e) => warn!(
+                "Shadow model wrote a line that isn't a stream message: {}",
+                e
+            ),
+        }
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for Claude: {}", e))?;
+    if !status.success() && output.error.is_none() {
+        output.error = Some(format!("claude exited with {}", status));
+    }
+    Ok(output)
+}
+
+/// Send a prompt to a session as usual and, at the same time, to `shadow_model`
+/// in a read-only fork of that session. Both replies are recorded on the returned
+/// shadow run; the session itself only ever sees the primary model's reply.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_shadow_run(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    project_path: String,
+    session_id: String,
+    prompt: String,
+    model: String,
+    shadow_model: String,
+    tab_id: Option<String>,
+    attachments: Option<Vec<String>>,
+) -> Result<ShadowRun, String> {
+    if shadow_model == model {
+        return Err("The shadow model must differ from the session's model".to_string());
+    }
+    // Everything after this point in the transcript belongs to this prompt
+    let primary_offset = read_session_jsonl(&session_id, &project_path)
+        .await?
+        .lines()
+        .count();
+
+    let shadow_run_id = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO shadow_runs (project_path, session_id, prompt, primary_model, shadow_model, primary_offset)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                project_path,
+                session_id,
+                prompt,
+                model,
+                shadow_model,
+                primary_offset as i64
+            ],
+        )
+        .map_err(|e| format!("Failed to create shadow run: {}", e))?;
+        conn.last_insert_rowid()
+    };
+
+    let (shadow_prompt, attachment_args) = crate::commands::attachments::attach_to_prompt(
+        &project_path,
+        prompt.clone(),
+        attachments.clone(),
+    )?;
+    let args = shadow_args(&session_id, &shadow_prompt, &shadow_model, attachment_args);
+
+    crate::commands::claude::resume_claude_code(
+        app.clone(),
+        project_path.clone(),
+        session_id,
+        prompt,
+        model,
+        tab_id,
+        attachments,
+    )
+    .await?;
+
+    info!("Shadowing session prompt with {}", shadow_model);
+    tokio::spawn(run_shadow(app, shadow_run_id, args, project_path));
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_shadow_run(&conn, shadow_run_id)
+}
+
+/// Get a shadow run, filling in the session's reply from its transcript
+#[tauri::command]
+pub async fn get_shadow_run(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    shadow_run_id: i64,
+) -> Result<ShadowRun, String> {
+    let (mut run, offset) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let offset: i64 = conn
+            .query_row(
+                "SELECT primary_offset FROM shadow_runs WHERE id = ?1",
+                params![shadow_run_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Shadow run {} not found: {}", shadow_run_id, e))?;
+        (load_shadow_run(&conn, shadow_run_id)?, offset)
+    };
+    if run.primary_response.is_some() {
+        return Ok(run);
+    }
+
+    let Ok(jsonl) = read_session_jsonl(&run.session_id, &run.project_path).await else {
+        return Ok(run);
+    };
+    let (response, followed) = response_after(&jsonl, offset.max(0) as usize);
+    run.primary_response = response;
+
+    // Only keep the reply once the session has moved on or stopped
+    let running = registry
+        .0
+        .get_claude_session_by_id(&run.session_id)?
+        .is_some();
+    if run.primary_response.is_some() && (followed || !running) {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE shadow_runs SET primary_response = ?1 WHERE id = ?2",
+            params![run.primary_response, shadow_run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(run)
+}
+
+/// List the shadow runs of a session, newest first
+#[tauri::command]
+pub async fn list_shadow_runs(
+    db: State<'_, AgentDb>,
+    session_id: String,
+) -> Result<Vec<ShadowRun>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM shadow_runs WHERE session_id = ?1 ORDER BY id DESC",
+            SHADOW_RUN_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let runs = stmt
+        .query_map(params![session_id], row_to_shadow_run)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(runs)
+}
+
+/// Delete a shadow run's record; the forked shadow session is left in place
+#[tauri::command]
+pub async fn delete_shadow_run(db: State<'_, AgentDb>, shadow_run_id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let deleted = conn
+        .execute(
+            "DELETE FROM shadow_runs WHERE id = ?1",
+            params![shadow_run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if deleted == 0 {
+        return Err(format!("Shadow run {} not found", shadow_run_id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_response_after_and_shadow_output() {
+        let jsonl = [
+            json!({"type": "user", "message": {"content": "Earlier prompt"}}),
+            json!({"type": "assistant", "message": {"content": [{"type": "text", "text": "Earlier reply"}]}}),
+            json!({"type": "user", "message": {"content": "Why is the build slow?"}}),
+            json!({"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Read"}]}}),
+            json!({"type": "user", "message": {"content": [{"type": "tool_result", "content": "..."}]}}),
+            json!({"type": "assistant", "message": {"content": [{"type": "text", "text": "LTO is on in dev."}]}}),
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        assert_eq!(
+            response_after(&jsonl, 2),
+            (Some("LTO is on in dev.".to_string()), false)
+        );
+        assert_eq!(
+            response_after(&jsonl, 0),
+            (Some("Earlier reply".to_string()), true)
+        );
+        assert_eq!(response_after(&jsonl, 6), (None, false));
+
+        let mut output = ShadowOutput::default();
+        output.record(&json!({"type": "system", "subtype": "init", "session_id": "fork-1"}));
+        output.record(&json!({"type": "result", "is_error": false, "result": "Turn off LTO."}));
+        assert_eq!(output.session_id.as_deref(), Some("fork-1"));
+        assert_eq!(output.response.as_deref(), Some("Turn off LTO."));
+        assert!(output.error.is_none());
+
+        let args = shadow_args("s1", "hi", "opus", vec![]);
+        assert!(args.windows(2).any(|w| w == ["--resume", "s1"]));
+        assert!(args.contains(&"--fork-session".to_string()));
+        assert!(args.last().unwrap().contains("Write"));
+    }
+}
@@ -5,12 +5,33 @@
 //! - Get/set the preferred shell environment
 //! - Check if Claude is available in WSL
 
+use crate::commands::settings::save_setting;
 use crate::shell_environment::{
     check_claude_in_wsl, detect_available_shells, AvailableShells, ShellConfig, ShellEnvironment,
 };
 use log::{info, warn};
 use tauri::Manager;
 
+/// Read the shell configuration from an open database
+pub fn load_shell_config(conn: &rusqlite::Connection) -> ShellConfig {
+    let setting = |key: &str| {
+        conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            [key],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+    };
+    ShellConfig {
+        environment: setting("shell_environment")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default(),
+        wsl_distro: setting("wsl_distro"),
+        wsl_claude_path: setting("wsl_claude_path"),
+        git_bash_path: setting("git_bash_path"),
+    }
+}
+
 /// Get available shell environments on the current system
 #[tauri::command]
 pub async fn get_available_shells() -> Result<AvailableShells, String> {
@@ -104,19 +125,11 @@ pub async fn save_shell_config(app: tauri::AppHandle, config: ShellConfig) -> Re
     .map_err(|e| format!("Failed to create settings table: {}", e))?;
 
     // Save shell environment
-    conn.execute(
-        "INSERT OR REPLACE INTO app_settings (key, value) VALUES ('shell_environment', ?)",
-        [config.environment.to_string()],
-    )
-    .map_err(|e| format!("Failed to save shell_environment: {}", e))?;
+    save_setting(&conn, "shell_environment", &config.environment.to_string())?;
 
     // Save WSL distribution (if set)
     if let Some(ref distro) = config.wsl_distro {
-        conn.execute(
-            "INSERT OR REPLACE INTO app_settings (key, value) VALUES ('wsl_distro', ?)",
-            [distro],
-        )
-        .map_err(|e| format!("Failed to save wsl_distro: {}", e))?;
+        save_setting(&conn, "wsl_distro", distro)?;
     } else {
         conn.execute("DELETE FROM app_settings WHERE key = 'wsl_distro'", [])
             .ok();
@@ -124,11 +137,7 @@ pub async fn save_shell_config(app: tauri::AppHandle, config: ShellConfig) -> Re
 
     // Save WSL Claude path (if set)
     if let Some(ref path) = config.wsl_claude_path {
-        conn.execute(
-            "INSERT OR REPLACE INTO app_settings (key, value) VALUES ('wsl_claude_path', ?)",
-            [path],
-        )
-        .map_err(|e| format!("Failed to save wsl_claude_path: {}", e))?;
+        save_setting(&conn, "wsl_claude_path", path)?;
     } else {
         conn.execute("DELETE FROM app_settings WHERE key = 'wsl_claude_path'", [])
             .ok();
@@ -136,11 +145,7 @@ pub async fn save_shell_config(app: tauri::AppHandle, config: ShellConfig) -> Re
 
     // Save Git Bash path (if set)
     if let Some(ref path) = config.git_bash_path {
-        conn.execute(
-            "INSERT OR REPLACE INTO app_settings (key, value) VALUES ('git_bash_path', ?)",
-            [path],
-        )
-        .map_err(|e| format!("Failed to save git_bash_path: {}", e))?;
+        save_setting(&conn, "git_bash_path", path)?;
     } else {
         conn.execute("DELETE FROM app_settings WHERE key = 'git_bash_path'", [])
             .ok();
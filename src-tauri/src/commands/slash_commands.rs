@@ -26,6 +26,13 @@ pub struct SlashCommand {
     pub description: Option<String>,
     /// Allowed tools from frontmatter
     pub allowed_tools: Vec<String>,
+    /// Hint for the arguments, shown when completing the command
+    pub argument_hint: Option<String>,
+    /// Model the command runs with instead of the session's
+    pub model: Option<String>,
+    /// What is wrong with the command's frontmatter; Claude Code ignores
+    /// metadata it can't read
+    pub problems: Vec<String>,
     /// Whether the command has bash commands (!)
     pub has_bash_commands: bool,
     /// Whether the command has file references (@)
@@ -34,22 +41,134 @@ pub struct SlashCommand {
     pub accepts_arguments: bool,
 }
 
+/// `allowed-tools` as a YAML list or a comma-separated string
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ToolList {
+    List(Vec<String>),
+    Text(String),
+}
+
+impl ToolList {
+    /// The tools, splitting a string on commas outside parentheses so
+    /// `Bash(git add:*), Read` is two tools
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            ToolList::List(tools) => tools,
+            ToolList::Text(text) => {
+                let mut tools = Vec::new();
+                let (mut depth, mut start) = (0i32, 0);
+                for (i, c) in text.char_indices() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        ',' if depth == 0 => {
+                            tools.push(text[start..i].trim().to_string());
+                            start = i + 1;
+                        }
+                        _ => {}
+                    }
+                }
+                tools.push(text[start..].trim().to_string());
+                tools.retain(|tool| !tool.is_empty());
+                tools
+            }
+        }
+    }
+}
+
 /// YAML frontmatter structure
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct CommandFrontmatter {
-    #[serde(rename = "allowed-tools")]
-    allowed_tools: Option<Vec<String>>,
+    #[serde(rename = "allowed-tools", skip_serializing_if = "Option::is_none")]
+    allowed_tools: Option<ToolList>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
+    #[serde(
+        rename = "argument-hint",
+        default,
+        deserialize_with = "deserialize_argument_hint",
+        skip_serializing_if = "Option::is_none"
+    )]
+    argument_hint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+}
+
+/// Read `argument-hint` as written, whether quoted or left as `[file]`,
+/// which YAML takes for a list
+fn deserialize_argument_hint<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(
+        Option::<ToolList>::deserialize(deserializer)?.map(|hint| match hint {
+            ToolList::List(items) => format!("[{}]", items.join(", ")),
+            ToolList::Text(text) => text,
+        }),
+    )
+}
+
+impl CommandFrontmatter {
+    /// What Claude Code would reject or misread in the frontmatter
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(tools) = self.allowed_tools.clone() {
+            for tool in tools.into_vec() {
+                problems.extend(tool_problem(&tool));
+            }
+        }
+        if self
+            .description
+            .as_deref()
+            .is_some_and(|d| d.trim().is_empty())
+        {
+            problems.push("description is empty".to_string());
+        }
+        if let Some(model) = self.model.as_deref() {
+            if model.trim().is_empty() || model.contains(char::is_whitespace) {
+                problems.push(format!("model '{}' is not a model name", model));
+            }
+        }
+        problems
+    }
+}
+
+/// Why an `allowed-tools` entry is invalid; tools are a name optionally
+/// followed by a rule in parentheses, like `Bash(git status:*)`
+fn tool_problem(tool: &str) -> Option<String> {
+    let tool = tool.trim();
+    let (name, rule) = match tool.split_once('(') {
+        Some((name, rest)) => match rest.strip_suffix(')') {
+            Some(rule) => (name, Some(rule)),
+            None => return Some(format!("allowed tool '{}' has no closing )", tool)),
+        },
+        None => (tool, None),
+    };
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Some(format!("allowed tool '{}' has no valid tool name", tool));
+    }
+    if rule.is_some_and(|rule| rule.trim().is_empty()) {
+        return Some(format!("allowed tool '{}' has an empty rule", tool));
+    }
+    None
 }
 
-/// Parse a markdown file with optional YAML frontmatter
-fn parse_markdown_with_frontmatter(content: &str) -> Result<(Option<CommandFrontmatter>, String)> {
+/// Parse a markdown file with optional YAML frontmatter. Frontmatter that
+/// can't be read stays in the body and is reported as a problem.
+fn parse_markdown_with_frontmatter(
+    content: &str,
+) -> (Option<CommandFrontmatter>, String, Vec<String>) {
     let lines: Vec<&str> = content.lines().collect();
 
     // Check if the file starts with YAML frontmatter
     if lines.is_empty() || lines[0] != "---" {
         // No frontmatter
-        return Ok((None, content.to_string()));
+        return (None, content.to_string(), Vec::new());
     }
 
     // Find the end of frontmatter
@@ -66,18 +185,32 @@ fn parse_markdown_with_frontmatter(content: &str) -> Result<(Option<CommandFront
         let frontmatter_content = lines[1..end].join("\n");
         let body_content = lines[(end + 1)..].join("\n");
 
-        // Parse YAML
+        // Parse YAML; empty frontmatter is no frontmatter
+        if frontmatter_content.trim().is_empty() {
+            return (None, body_content, Vec::new());
+        }
         match serde_yaml::from_str::<CommandFrontmatter>(&frontmatter_content) {
-            Ok(frontmatter) => Ok((Some(frontmatter), body_content)),
+            Ok(frontmatter) => {
+                let problems = frontmatter.problems();
+                (Some(frontmatter), body_content, problems)
+            }
             Err(e) => {
                 debug!("Failed to parse frontmatter: {}", e);
                 // Return full content if frontmatter parsing fails
-                Ok((None, content.to_string()))
+                (
+                    None,
+                    content.to_string(),
+                    vec![format!("frontmatter is not valid YAML: {}", e)],
+                )
             }
         }
     } else {
         // Malformed frontmatter, treat as regular content
-        Ok((None, content.to_string()))
+        (
+            None,
+            content.to_string(),
+            vec!["frontmatter has no closing ---".to_string()],
+        )
     }
 }
 
@@ -119,7 +252,7 @@ fn load_command_from_file(file_path: &Path, base_path: &Path, scope: &str) -> Re
     let content = fs::read_to_string(file_path).context("Failed to read command file")?;
 
     // Parse frontmatter
-    let (frontmatter, body) = parse_markdown_with_frontmatter(&content)?;
+    let (frontmatter, body, problems) = parse_markdown_with_frontmatter(&content);
 
     // Extract command info
     let (name, namespace) = extract_command_info(file_path, base_path)?;
@@ -143,11 +276,8 @@ fn load_command_from_file(file_path: &Path, base_path: &Path, scope: &str) -> Re
     let accepts_arguments = body.contains("$ARGUMENTS");
 
     // Extract metadata from frontmatter
-    let (description, allowed_tools) = if let Some(fm) = frontmatter {
-        (fm.description, fm.allowed_tools.unwrap_or_default())
-    } else {
-        (None, Vec::new())
-    };
+    let fm = frontmatter.unwrap_or_default();
+    let allowed_tools = fm.allowed_tools.map(ToolList::into_vec).unwrap_or_default();
 
     Ok(SlashCommand {
         id,
@@ -157,8 +287,11 @@ fn load_command_from_file(file_path: &Path, base_path: &Path, scope: &str) -> Re
         namespace,
         file_path: file_path.to_string_lossy().to_string(),
         content: body,
-        description,
+        description: fm.description,
         allowed_tools,
+        argument_hint: fm.argument_hint,
+        model: fm.model,
+        problems,
         has_bash_commands,
         has_file_references,
         accepts_arguments,
@@ -209,6 +342,9 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Add additional working directories".to_string(),
             description: Some("Add additional working directories".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
+            problems: vec![],
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -223,6 +359,9 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Initialize project with CLAUDE.md guide".to_string(),
             description: Some("Initialize project with CLAUDE.md guide".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
+            problems: vec![],
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -237,6 +376,9 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Request code review".to_string(),
             description: Some("Request code review".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
+            problems: vec![],
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -311,7 +453,10 @@ pub async fn slash_commands_list(
 
 /// Get a single slash command by ID
 #[tauri::command]
-pub async fn slash_command_get(command_id: String) -> Result<SlashCommand, String> {
+pub async fn slash_command_get(
+    command_id: String,
+    project_path: Option<String>,
+) -> Result<SlashCommand, String> {
     debug!("Getting slash command: {}", command_id);
 
     // Parse the ID to determine scope and reconstruct file path
@@ -322,7 +467,7 @@ pub async fn slash_command_get(command_id: String) -> Result<SlashCommand, Strin
 
     // The actual implementation would need to reconstruct the path and reload the command
     // For now, we'll list all commands and find the matching one
-    let commands = slash_commands_list(None).await?;
+    let commands = slash_commands_list(project_path).await?;
 
     commands
         .into_iter()
@@ -330,8 +475,99 @@ pub async fn slash_command_get(command_id: String) -> Result<SlashCommand, Strin
         .ok_or_else(|| format!("Command not found: {}", command_id))
 }
 
+/// A command as Claude Code resolves it in a project, with the commands of
+/// the same name it hides
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectiveSlashCommand {
+    #[serde(flatten)]
+    pub command: SlashCommand,
+    /// IDs of the lower-precedence commands with the same name
+    pub shadows: Vec<String>,
+}
+
+/// How a scope ranks when commands share a name: project commands win over
+/// user commands, which win over built-in ones
+fn scope_rank(scope: &str) -> u8 {
+    match scope {
+        "project" => 0,
+        "user" => 1,
+        _ => 2,
+    }
+}
+
+/// Keep one command per name, the one from the highest-precedence scope,
+/// sorted by name
+fn effective_commands(mut commands: Vec<SlashCommand>) -> Vec<EffectiveSlashCommand> {
+    commands.sort_by(|a, b| {
+        a.full_command
+            .cmp(&b.full_command)
+            .then_with(|| scope_rank(&a.scope).cmp(&scope_rank(&b.scope)))
+    });
+
+    let mut effective: Vec<EffectiveSlashCommand> = Vec::new();
+    for command in commands {
+        match effective.last_mut() {
+            Some(last) if last.command.full_command == command.full_command => {
+                last.shadows.push(command.id);
+            }
+            _ => effective.push(EffectiveSlashCommand {
+                command,
+                shadows: Vec::new(),
+            }),
+        }
+    }
+    effective
+}
+
+/// List the commands available in a project, with project commands taking
+/// precedence over user commands of the same name
+#[tauri::command]
+pub async fn slash_commands_effective(
+    project_path: String,
+) -> Result<Vec<EffectiveSlashCommand>, String> {
+    let commands = slash_commands_list(Some(project_path)).await?;
+    Ok(effective_commands(commands))
+}
+
+/// Check a command name or namespace part, which becomes a file or directory name
+fn validate_name_component(component: &str) -> Result<(), String> {
+    let valid = !component.is_empty()
+        && !component.starts_with('.')
+        && component
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' is not a valid command name; use letters, digits, '-', '_' and '.'",
+            component
+        ))
+    }
+}
+
+/// The markdown file of a command: YAML frontmatter when there is metadata,
+/// then the body
+fn command_file_content(frontmatter: &CommandFrontmatter, body: &str) -> Result<String, String> {
+    let has_metadata = frontmatter.allowed_tools.is_some()
+        || frontmatter.description.is_some()
+        || frontmatter.argument_hint.is_some()
+        || frontmatter.model.is_some();
+    if !has_metadata {
+        // A body starting with --- would be read back as frontmatter
+        if body.lines().next() == Some("---") {
+            return Err("Command content can't start with a --- line".to_string());
+        }
+        return Ok(body.to_string());
+    }
+    let yaml = serde_yaml::to_string(frontmatter)
+        .map_err(|e| format!("Failed to write frontmatter: {}", e))?;
+    Ok(format!("---\n{}---\n\n{}", yaml, body))
+}
+
 /// Create or update a slash command
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn slash_command_save(
     scope: String,
     name: String,
@@ -340,6 +576,8 @@ pub async fn slash_command_save(
     description: Option<String>,
     allowed_tools: Vec<String>,
     project_path: Option<String>,
+    argument_hint: Option<String>,
+    model: Option<String>,
 ) -> Result<SlashCommand, String> {
     info!("Saving slash command: {} in scope: {}", name, scope);
 
@@ -347,11 +585,29 @@ pub async fn slash_command_save(
     if name.is_empty() {
         return Err("Command name cannot be empty".to_string());
     }
+    validate_name_component(&name)?;
+    if let Some(ns) = &namespace {
+        ns.split(':').try_for_each(validate_name_component)?;
+    }
 
     if !["project", "user"].contains(&scope.as_str()) {
         return Err("Invalid scope. Must be 'project' or 'user'".to_string());
     }
 
+    // Build content with frontmatter, refusing metadata Claude Code would reject
+    let non_empty = |value: Option<String>| value.filter(|v| !v.trim().is_empty());
+    let frontmatter = CommandFrontmatter {
+        allowed_tools: (!allowed_tools.is_empty()).then_some(ToolList::List(allowed_tools)),
+        description: non_empty(description),
+        argument_hint: non_empty(argument_hint),
+        model: non_empty(model),
+    };
+    let problems = frontmatter.problems();
+    if !problems.is_empty() {
+        return Err(format!("Invalid frontmatter: {}", problems.join("; ")));
+    }
+    let full_content = command_file_content(&frontmatter, &content)?;
+
     // Determine base directory
     let base_dir = if scope == "project" {
         if let Some(proj_path) = project_path {
@@ -380,29 +636,6 @@ pub async fn slash_command_save(
     // Add filename
     file_path = file_path.join(format!("{}.md", name));
 
-    // Build content with frontmatter
-    let mut full_content = String::new();
-
-    // Add frontmatter if we have metadata
-    if description.is_some() || !allowed_tools.is_empty() {
-        full_content.push_str("---\n");
-
-        if let Some(desc) = &description {
-            full_content.push_str(&format!("description: {}\n", desc));
-        }
-
-        if !allowed_tools.is_empty() {
-            full_content.push_str("allowed-tools:\n");
-            for tool in &allowed_tools {
-                full_content.push_str(&format!("  - {}\n", tool));
-            }
-        }
-
-        full_content.push_str("---\n\n");
-    }
-
-    full_content.push_str(&content);
-
     // Write file
     fs::write(&file_path, &full_content)
         .map_err(|e| format!("Failed to write command file: {}", e))?;
@@ -469,3 +702,86 @@ fn remove_empty_dirs(dir: &Path) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(scope: &str, full_command: &str) -> SlashCommand {
+        SlashCommand {
+            id: format!("{}-{}", scope, full_command),
+            name: full_command.trim_start_matches('/').to_string(),
+            full_command: full_command.to_string(),
+            scope: scope.to_string(),
+            namespace: None,
+            file_path: String::new(),
+            content: String::new(),
+            description: None,
+            allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
+            problems: vec![],
+            has_bash_commands: false,
+            has_file_references: false,
+            accepts_arguments: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_frontmatter() {
+        let (fm, body, problems) = parse_markdown_with_frontmatter(
+            "---\nallowed-tools: Bash(git add:*, git status:*), Read\nargument-hint: [file]\n---\nReview $ARGUMENTS",
+        );
+        let fm = fm.unwrap();
+        assert_eq!(body, "Review $ARGUMENTS");
+        assert!(problems.is_empty());
+        assert_eq!(fm.argument_hint.as_deref(), Some("[file]"));
+        assert_eq!(
+            fm.allowed_tools.unwrap().into_vec(),
+            ["Bash(git add:*, git status:*)", "Read"]
+        );
+
+        let (_, _, problems) =
+            parse_markdown_with_frontmatter("---\nallowed-tools: [Bash(git, \"\"]\n---\nbody");
+        assert_eq!(problems.len(), 2);
+
+        let (fm, body, problems) = parse_markdown_with_frontmatter("---\ndescription: x\nbody");
+        assert!(fm.is_none());
+        assert_eq!(body, "---\ndescription: x\nbody");
+        assert_eq!(problems, ["frontmatter has no closing ---"]);
+    }
+
+    #[test]
+    fn test_command_file_content() {
+        let frontmatter = CommandFrontmatter {
+            description: Some("Review a file".to_string()),
+            ..Default::default()
+        };
+        let content = command_file_content(&frontmatter, "Review $ARGUMENTS").unwrap();
+        let (fm, body, problems) = parse_markdown_with_frontmatter(&content);
+        assert_eq!(fm.unwrap().description.as_deref(), Some("Review a file"));
+        assert_eq!(body.trim(), "Review $ARGUMENTS");
+        assert!(problems.is_empty());
+
+        assert!(command_file_content(&CommandFrontmatter::default(), "---\nbody").is_err());
+        assert!(validate_name_component("review-pr").is_ok());
+        assert!(validate_name_component("../review").is_err());
+        assert!(validate_name_component(".hidden").is_err());
+    }
+
+    #[test]
+    fn test_effective_commands() {
+        let effective = effective_commands(vec![
+            command("default", "/review"),
+            command("user", "/review"),
+            command("user", "/deploy"),
+            command("project", "/review"),
+        ]);
+        let names: Vec<_> = effective
+            .iter()
+            .map(|e| (e.command.full_command.as_str(), e.command.scope.as_str()))
+            .collect();
+        assert_eq!(names, [("/deploy", "user"), ("/review", "project")]);
+        assert_eq!(effective[1].shadows, ["user-/review", "default-/review"]);
+    }
+}
@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::process::{ProcessInfo, ProcessRegistryState, ProcessType};
+
+/// app_settings key holding the stall detection settings as JSON
+const SETTINGS_KEY: &str = "stall_detection";
+
+/// Interval between checks for stalled runs
+const STALL_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// When a running session or agent run is reported as possibly stalled
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StallDetectionSettings {
+    /// Minutes without output after which a run is reported; 0 turns
+    /// detection off
+    pub stall_after_minutes: u64,
+}
+
+impl Default for StallDetectionSettings {
+    fn default() -> Self {
+        Self {
+            stall_after_minutes: 10,
+        }
+    }
+}
+
+/// A running session or agent run that hasn't written output for a while,
+/// sent as `run-stalled`
+#[derive(Debug, Clone, Serialize)]
+pub struct StalledRun {
+    pub run_id: i64,
+    pub process_type: ProcessType,
+    pub pid: u32,
+    pub project_path: String,
+    /// When the run last wrote output, or when it started if it never has
+    pub last_output_at: DateTime<Utc>,
+    pub idle_secs: u64,
+}
+
+fn load_settings(conn: &Connection) -> StallDetectionSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// The runs that have been quiet for at least `stall_after_minutes` at `now`,
+/// quietest first
+pub fn find_stalled(
+    activity: Vec<(ProcessInfo, DateTime<Utc>)>,
+    stall_after_minutes: u64,
+    now: DateTime<Utc>,
+) -> Vec<StalledRun> {
+    if stall_after_minutes == 0 {
+        return Vec::new();
+    }
+    let mut stalled: Vec<StalledRun> = activity
+        .into_iter()
+        .filter_map(|(info, last_output_at)| {
+            let idle_secs = (now - last_output_at).num_seconds().max(0) as u64;
+            (idle_secs >= stall_after_minutes * 60).then_some(StalledRun {
+                run_id: info.run_id,
+                process_type: info.process_type,
+                pid: info.pid,
+                project_path: info.project_path,
+                last_output_at,
+                idle_secs,
+            })
+        })
+        .collect();
+    stalled.sort_by_key(|run| run.last_output_at);
+    stalled
+}
+
+/// Check running sessions and agent runs for ones that have gone quiet.
+///
+/// Each stall is reported once as `run-stalled`; when the run writes output
+/// again `run-stall-cleared` is sent with its run ID.
+pub fn start_stall_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        // Stalled runs already reported, with the output time they were reported for
+        let mut reported: HashMap<i64, DateTime<Utc>> = HashMap::new();
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(STALL_CHECK_INTERVAL_SECS)).await;
+
+            let settings = app
+                .state::<AgentDb>()
+                .0
+                .lock()
+                .map(|conn| load_settings(&conn))
+                .unwrap_or_default();
+
+            let registry = app.state::<ProcessRegistryState>().0.clone();
+            let activity = match registry.output_activity() {
+                Ok(activity) => activity,
+                Err(e) => {
+                    warn!("Failed to check for stalled runs: {}", e);
+                    continue;
+                }
+            };
+            let stalled = find_stalled(activity, settings.stall_after_minutes, Utc::now());
+
+            for run_id in reported.keys().copied().collect::<Vec<_>>() {
+                let still_stalled = stalled.iter().any(|run| {
+                    run.run_id == run_id && reported.get(&run_id) == Some(&run.last_output_at)
+                });
+                if !still_stalled {
+                    reported.remove(&run_id);
+                    let _ = app.emit("run-stall-cleared", run_id);
+                }
+            }
+            for run in stalled {
+                if reported.contains_key(&run.run_id) {
+                    continue;
+                }
+                info!(
+                    "Run {} has written no output for {}s",
+                    run.run_id, run.idle_secs
+                );
+                reported.insert(run.run_id, run.last_output_at);
+                let _ = app.emit("run-stalled", &run);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_stall_detection_settings(
+    db: State<'_, AgentDb>,
+) -> Result<StallDetectionSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_stall_detection_settings(
+    db: State<'_, AgentDb>,
+    settings: StallDetectionSettings,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+/// Running sessions and agent runs that are possibly stalled, quietest first
+#[tauri::command]
+pub async fn list_stalled_runs(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<Vec<StalledRun>, String> {
+    let settings = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_settings(&conn)
+    };
+    Ok(find_stalled(
+        registry.0.output_activity()?,
+        settings.stall_after_minutes,
+        Utc::now(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn session(run_id: i64, started_at: DateTime<Utc>) -> ProcessInfo {
+        ProcessInfo {
+            run_id,
+            process_type: ProcessType::ClaudeSession {
+                session_id: format!("s{}", run_id),
+            },
+            pid: 100 + run_id as u32,
+            started_at,
+            project_path: "/tmp/project".to_string(),
+            task: "task".to_string(),
+            model: "sonnet".to_string(),
+            paused_at: None,
+        }
+    }
+
+    #[test]
+    fn test_find_stalled() {
+        let now = Utc::now();
+        let activity: Vec<_> = [(1, 2), (2, 15), (3, 40)]
+            .into_iter()
+            .map(|(run_id, idle_minutes)| {
+                (
+                    session(run_id, now - Duration::hours(1)),
+                    now - Duration::minutes(idle_minutes),
+                )
+            })
+            .collect();
+
+        let stalled = find_stalled(activity.clone(), 10, now);
+        assert_eq!(
+            stalled.iter().map(|run| run.run_id).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+        assert_eq!(stalled[1].idle_secs, 15 * 60);
+        assert!(find_stalled(activity, 0, now).is_empty());
+    }
+}
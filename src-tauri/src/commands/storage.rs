@@ -462,10 +462,86 @@ pub async fn storage_reset_database(app: AppHandle) -> Result<(), String> {
         // Drop tables - order doesn't matter with foreign keys disabled
         conn.execute("DROP TABLE IF EXISTS agent_runs", [])
             .map_err(|e| format!("Failed to drop agent_runs table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_run_artifacts", [])
+            .map_err(|e| format!("Failed to drop agent_run_artifacts table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_runs_fts", [])
+            .map_err(|e| format!("Failed to drop agent_runs_fts table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_index_files", [])
+            .map_err(|e| format!("Failed to drop session_index_files table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_messages_fts", [])
+            .map_err(|e| format!("Failed to drop session_messages_fts table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_revisions", [])
+            .map_err(|e| format!("Failed to drop agent_revisions table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_notification_rules", [])
+            .map_err(|e| format!("Failed to drop agent_notification_rules table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_notification_channels", [])
+            .map_err(|e| format!("Failed to drop agent_notification_channels table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS notification_channels", [])
+            .map_err(|e| format!("Failed to drop notification_channels table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS issue_links", [])
+            .map_err(|e| format!("Failed to drop issue_links table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_output_schemas", [])
+            .map_err(|e| format!("Failed to drop agent_output_schemas table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_run_outputs", [])
+            .map_err(|e| format!("Failed to drop agent_run_outputs table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_env_vars", [])
+            .map_err(|e| format!("Failed to drop agent_env_vars table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_run_batches", [])
+            .map_err(|e| format!("Failed to drop agent_run_batches table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_run_comparisons", [])
+            .map_err(|e| format!("Failed to drop agent_run_comparisons table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_retry_policies", [])
+            .map_err(|e| format!("Failed to drop agent_retry_policies table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_parameter_schemas", [])
+            .map_err(|e| format!("Failed to drop agent_parameter_schemas table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_budgets", [])
+            .map_err(|e| format!("Failed to drop agent_budgets table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_run_limits", [])
+            .map_err(|e| format!("Failed to drop agent_run_limits table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_crash_restart_policies", [])
+            .map_err(|e| format!("Failed to drop agent_crash_restart_policies table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS task_worktrees", [])
+            .map_err(|e| format!("Failed to drop task_worktrees table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS prompt_templates", [])
+            .map_err(|e| format!("Failed to drop prompt_templates table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_titles", [])
+            .map_err(|e| format!("Failed to drop session_titles table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_stream_filters", [])
+            .map_err(|e| format!("Failed to drop session_stream_filters table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_tool_uses", [])
+            .map_err(|e| format!("Failed to drop session_tool_uses table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_stats", [])
+            .map_err(|e| format!("Failed to drop session_stats table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS usage_index_files", [])
+            .map_err(|e| format!("Failed to drop usage_index_files table: {}", e))?;
+        conn.execute("DROP VIEW IF EXISTS usage_messages", [])
+            .map_err(|e| format!("Failed to drop usage_messages view: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS usage_entries", [])
+            .map_err(|e| format!("Failed to drop usage_entries table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS usage_monthly_rollups", [])
+            .map_err(|e| format!("Failed to drop usage_monthly_rollups table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS cost_anomalies", [])
+            .map_err(|e| format!("Failed to drop cost_anomalies table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS shadow_runs", [])
+            .map_err(|e| format!("Failed to drop shadow_runs table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS checkpoint_validation_hooks", [])
+            .map_err(|e| format!("Failed to drop checkpoint_validation_hooks table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS mcp_project_servers", [])
+            .map_err(|e| format!("Failed to drop mcp_project_servers table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_schedules", [])
+            .map_err(|e| format!("Failed to drop agent_schedules table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_file_watches", [])
+            .map_err(|e| format!("Failed to drop agent_file_watches table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS workspace_projects", [])
+            .map_err(|e| format!("Failed to drop workspace_projects table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS workspaces", [])
+            .map_err(|e| format!("Failed to drop workspaces table: {}", e))?;
         conn.execute("DROP TABLE IF EXISTS agents", [])
             .map_err(|e| format!("Failed to drop agents table: {}", e))?;
         conn.execute("DROP TABLE IF EXISTS app_settings", [])
             .map_err(|e| format!("Failed to drop app_settings table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS settings_audit", [])
+            .map_err(|e| format!("Failed to drop settings_audit table: {}", e))?;
 
         // Re-enable foreign key constraints
         conn.execute("PRAGMA foreign_keys = ON", [])
@@ -0,0 +1,334 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+
+/// app_settings key holding the stream batching settings as JSON
+const SETTINGS_KEY: &str = "stream_batching";
+
+/// Longest a single string inside an oversized message is kept
+const TRUNCATED_FIELD_BYTES: usize = 8 * 1024;
+
+/// Running batchers by the output event a reattached UI replays, so the replay
+/// goes out in order with the lines they still hold
+static REPLAY_TARGETS: OnceLock<Mutex<HashMap<String, mpsc::UnboundedSender<Queued>>>> =
+    OnceLock::new();
+
+/// How stream output is coalesced before it is sent to the UI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StreamBatchingSettings {
+    /// Milliseconds lines are held to be sent together; 0 sends each line
+    /// as its own event
+    pub flush_interval_ms: u64,
+    /// Lines sent in one event at most
+    pub max_batch_size: usize,
+    /// Messages larger than this have their long strings truncated
+    pub max_message_bytes: usize,
+}
+
+impl Default for StreamBatchingSettings {
+    fn default() -> Self {
+        Self {
+            flush_interval_ms: 50,
+            max_batch_size: 200,
+            max_message_bytes: 256 * 1024,
+        }
+    }
+}
+
+fn load_settings(conn: &Connection) -> StreamBatchingSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// The stream batching settings of the app database at `db`
+pub fn load_stream_batching(db: &AgentDb) -> StreamBatchingSettings {
+    db.0.lock()
+        .map(|conn| load_settings(&conn))
+        .unwrap_or_default()
+}
+
+fn truncate_str(text: &str, max_bytes: usize) -> String {
+    let mut end = max_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n… [truncated {} bytes]", &text[..end], text.len() - end)
+}
+
+fn truncate_strings(value: &mut JsonValue) {
+    match value {
+        JsonValue::String(text) if text.len() > TRUNCATED_FIELD_BYTES => {
+            *text = truncate_str(text, TRUNCATED_FIELD_BYTES);
+        }
+        JsonValue::Array(items) => items.iter_mut().for_each(truncate_strings),
+        JsonValue::Object(fields) => fields.values_mut().for_each(truncate_strings),
+        _ => {}
+    }
+}
+
+/// A message small enough to send to the UI. Long strings in an oversized
+/// message, such as a huge tool result, are cut with a truncation marker; a
+/// message that is still too large is replaced by a `truncated` system message.
+pub fn truncate_message(line: &str, max_bytes: usize) -> Cow<'_, str> {
+    if line.len() <= max_bytes {
+        return Cow::Borrowed(line);
+    }
+    let Ok(mut message) = serde_json::from_str::<JsonValue>(line) else {
+        return Cow::Owned(truncate_str(line, max_bytes));
+    };
+    truncate_strings(&mut message);
+    match serde_json::to_string(&message) {
+        Ok(truncated) if truncated.len() <= max_bytes => Cow::Owned(truncated),
+        _ => Cow::Owned(
+            serde_json::json!({
+                "type": "system",
+                "subtype": "truncated",
+                "original_type": message.get("type"),
+                "original_bytes": line.len(),
+            })
+            .to_string(),
+        ),
+    }
+}
+
+/// Group queued lines by event, keeping the order of the lines within each
+fn group_by_event(batch: Vec<(String, String)>) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    for (event, line) in batch {
+        match groups.iter_mut().find(|(name, _)| *name == event) {
+            Some((_, lines)) => lines.push(line),
+            None => groups.push((event, vec![line])),
+        }
+    }
+    groups
+}
+
+/// What a batcher's task is sent
+enum Queued {
+    Line(String, String),
+    /// Everything written on an event so far, for a reattached UI. Lines of the
+    /// event that are still queued are part of it, so they are dropped.
+    Replay(String, Vec<String>),
+}
+
+fn replay_targets() -> &'static Mutex<HashMap<String, mpsc::UnboundedSender<Queued>>> {
+    REPLAY_TARGETS.get_or_init(Default::default)
+}
+
+fn emit_batch(app: &AppHandle, batch: &mut Vec<(String, String)>) {
+    for (event, lines) in group_by_event(std::mem::take(batch)) {
+        let _ = app.emit(&event, lines);
+    }
+}
+
+/// Coalesces stream output so a flood of lines reaches the UI as a few events.
+///
+/// Lines are held for up to the flush interval, or until a batch is full,
+/// and each event then gets an array of its lines. With batching off every
+/// line is still sent as a string of its own.
+pub struct StreamBatcher {
+    app: AppHandle,
+    max_message_bytes: usize,
+    sender: Option<mpsc::UnboundedSender<Queued>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl StreamBatcher {
+    pub fn new(app: AppHandle, settings: &StreamBatchingSettings) -> Self {
+        let max_message_bytes = settings.max_message_bytes;
+        if settings.flush_interval_ms == 0 {
+            return Self {
+                app,
+                max_message_bytes,
+                sender: None,
+                task: None,
+            };
+        }
+
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Queued>();
+        let flush_interval = Duration::from_millis(settings.flush_interval_ms);
+        let max_batch_size = settings.max_batch_size.max(1);
+        let task_app = app.clone();
+        let task = tokio::spawn(async move {
+            let mut batch = Vec::new();
+            let mut deadline = Instant::now();
+            loop {
+                let received = if batch.is_empty() {
+                    receiver.recv().await
+                } else {
+                    match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                        Ok(received) => received,
+                        Err(_) => {
+                            emit_batch(&task_app, &mut batch);
+                            continue;
+                        }
+                    }
+                };
+                let item = match received {
+                    Some(Queued::Line(event, line)) => (event, line),
+                    Some(Queued::Replay(event, lines)) => {
+                        batch.retain(|(queued_event, _)| *queued_event != event);
+                        let _ = task_app.emit(&event, lines);
+                        continue;
+                    }
+                    None => {
+                        emit_batch(&task_app, &mut batch);
+                        return;
+                    }
+                };
+                if batch.is_empty() {
+                    deadline = Instant::now() + flush_interval;
+                }
+                batch.push(item);
+                if batch.len() >= max_batch_size {
+                    emit_batch(&task_app, &mut batch);
+                }
+            }
+        });
+
+        Self {
+            app,
+            max_message_bytes,
+            sender: Some(sender),
+            task: Some(task),
+        }
+    }
+
+    /// Queue a line for `event`
+    pub fn push(&self, event: &str, line: &str) {
+        let line = truncate_message(line, self.max_message_bytes);
+        match &self.sender {
+            Some(sender) => {
+                let _ = sender.send(Queued::Line(event.to_string(), line.into_owned()));
+            }
+            None => {
+                let _ = self.app.emit(event, line.as_ref());
+            }
+        }
+    }
+
+    /// Let `replay_stream` replays of `event` go through this batcher
+    pub fn serve_replays(&self, event: &str) {
+        if let (Some(sender), Ok(mut targets)) = (&self.sender, replay_targets().lock()) {
+            targets.insert(event.to_string(), sender.clone());
+        }
+    }
+
+    /// Send whatever is still queued; call before announcing the stream has ended
+    pub async fn finish(mut self) {
+        if let (Some(sender), Ok(mut targets)) = (&self.sender, replay_targets().lock()) {
+            targets.retain(|_, target| !target.same_channel(sender));
+        }
+        self.sender.take();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+/// Replay a stream's output so far on `event` for a reattached UI, truncated
+/// like live output. A batcher serving the event sends it in order with the
+/// lines it still holds and drops those, as the replay already has them. Call
+/// while the output can't grow, so nothing is missed or repeated.
+pub fn replay_stream(app: &AppHandle, event: &str, lines: Vec<String>, max_message_bytes: usize) {
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| truncate_message(line, max_message_bytes).into_owned())
+        .collect();
+    let target = replay_targets()
+        .lock()
+        .ok()
+        .and_then(|targets| targets.get(event).cloned());
+    let lines = match target {
+        Some(sender) => match sender.send(Queued::Replay(event.to_string(), lines)) {
+            Ok(()) => return,
+            Err(mpsc::error::SendError(Queued::Replay(_, lines))) => lines,
+            Err(_) => return,
+        },
+        None => lines,
+    };
+    for line in lines {
+        let _ = app.emit(event, line);
+    }
+}
+
+#[tauri::command]
+pub async fn get_stream_batching_settings(
+    db: State<'_, AgentDb>,
+) -> Result<StreamBatchingSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_stream_batching_settings(
+    db: State<'_, AgentDb>,
+    settings: StreamBatchingSettings,
+) -> Result<(), String> {
+    if settings.max_batch_size == 0 {
+        return Err("The batch size must be greater than zero".to_string());
+    }
+    if settings.max_message_bytes < TRUNCATED_FIELD_BYTES {
+        return Err(format!(
+            "Messages must be allowed at least {} bytes",
+            TRUNCATED_FIELD_BYTES
+        ));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_message_and_group_batches() {
+        let small = r#"{"type":"assistant"}"#;
+        assert!(matches!(truncate_message(small, 1024), Cow::Borrowed(_)));
+
+        let huge_result = "x".repeat(100_000);
+        let line = serde_json::json!({
+            "type": "user",
+            "message": { "content": [{ "type": "tool_result", "content": huge_result }] }
+        })
+        .to_string();
+        let truncated = truncate_message(&line, 16 * 1024);
+        let message: JsonValue = serde_json::from_str(&truncated).unwrap();
+        let content = message["message"]["content"][0]["content"]
+            .as_str()
+            .unwrap();
+        assert!(content.ends_with("… [truncated 91808 bytes]"));
+        assert_eq!(message["type"], "user");
+
+        // Too many strings to fit even once each is cut down
+        let many = serde_json::json!({ "type": "user", "parts": vec![huge_result; 4] }).to_string();
+        let marker: JsonValue = serde_json::from_str(&truncate_message(&many, 16 * 1024)).unwrap();
+        assert_eq!(marker["subtype"], "truncated");
+        assert_eq!(marker["original_type"], "user");
+
+        let batch = vec![
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+            ("a".to_string(), "3".to_string()),
+        ];
+        assert_eq!(
+            group_by_event(batch),
+            vec![
+                ("a".to_string(), vec!["1".to_string(), "3".to_string()]),
+                ("b".to_string(), vec!["2".to_string()]),
+            ]
+        );
+    }
+}
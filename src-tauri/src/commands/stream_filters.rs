@@ -0,0 +1,302 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+
+/// app_settings key holding the filter for sessions without one of their own
+const DEFAULT_FILTER_KEY: &str = "stream_filter";
+
+/// Filters of sessions looked up so far, by session ID, so a session's output
+/// doesn't hit the database for every line
+static FILTERS: OnceLock<Mutex<HashMap<String, StreamFilter>>> = OnceLock::new();
+
+/// What of a session's stream output is forwarded to the UI. The output log
+/// and the registry's live output always keep every line in full.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StreamFilter {
+    /// Tool results larger than this are replaced by a note of their size;
+    /// 0 forwards them whole
+    pub max_tool_result_bytes: usize,
+    /// Forward only the text of assistant messages and the names of the tools
+    /// they call, dropping thinking, tool inputs and tool results
+    pub only_text_and_tool_names: bool,
+}
+
+impl StreamFilter {
+    pub fn is_active(&self) -> bool {
+        self.max_tool_result_bytes > 0 || self.only_text_and_tool_names
+    }
+
+    /// The line to forward for a stream message, None to drop it
+    pub fn apply<'a>(&self, json: &JsonValue, line: &'a str) -> Option<Cow<'a, str>> {
+        if !self.is_active() {
+            return Some(Cow::Borrowed(line));
+        }
+        let kind = json.get("type").and_then(|t| t.as_str());
+        if self.only_text_and_tool_names {
+            match kind {
+                Some("user") => return None,
+                Some("assistant") => return Some(Cow::Owned(outline_assistant(json))),
+                _ => {}
+            }
+        }
+        // A line no longer than the limit can't hold a tool result over it
+        if self.max_tool_result_bytes > 0
+            && kind == Some("user")
+            && line.len() > self.max_tool_result_bytes
+        {
+            let mut message = json.clone();
+            if suppress_tool_results(&mut message, self.max_tool_result_bytes) {
+                return Some(Cow::Owned(message.to_string()));
+            }
+        }
+        Some(Cow::Borrowed(line))
+    }
+}
+
+fn content_blocks(message: &mut JsonValue) -> Option<&mut Vec<JsonValue>> {
+    message
+        .get_mut("message")?
+        .get_mut("content")?
+        .as_array_mut()
+}
+
+/// An assistant message with only its text and the names of the tools it calls
+fn outline_assistant(json: &JsonValue) -> String {
+    let mut message = json.clone();
+    if let Some(blocks) = content_blocks(&mut message) {
+        blocks.retain(|block| {
+            matches!(
+                block.get("type").and_then(|t| t.as_str()),
+                Some("text" | "tool_use")
+            )
+        });
+        for block in blocks.iter_mut() {
+            if let Some(input) = block.get_mut("input") {
+                *input = JsonValue::Object(Default::default());
+            }
+        }
+    }
+    message.to_string()
+}
+
+/// Replace the content of tool results over `max_bytes` with a note of their
+/// size; returns whether any was
+fn suppress_tool_results(message: &mut JsonValue, max_bytes: usize) -> bool {
+    let mut suppressed = false;
+    if let Some(blocks) = content_blocks(message) {
+        for block in blocks.iter_mut() {
+            if block.get("type").and_then(|t| t.as_str()) != Some("tool_result") {
+                continue;
+            }
+            let bytes = match block.get("content") {
+                Some(JsonValue::String(text)) => text.len(),
+                Some(content) => content.to_string().len(),
+                None => 0,
+            };
+            if bytes > max_bytes {
+                block["content"] =
+                    JsonValue::String(format!("[{} bytes of tool output not shown]", bytes));
+                block["suppressed_bytes"] = JsonValue::from(bytes);
+                suppressed = true;
+            }
+        }
+    }
+    // The same output again, in full, next to the message
+    if suppressed {
+        if let Some(fields) = message.as_object_mut() {
+            fields.remove("tool_use_result");
+        }
+    }
+    suppressed
+}
+
+fn load_default_filter(conn: &Connection) -> StreamFilter {
+    load_json_setting(conn, DEFAULT_FILTER_KEY)
+}
+
+/// A session's own filter, if it has one
+fn load_session_filter(
+    conn: &Connection,
+    session_id: &str,
+) -> Result<Option<StreamFilter>, String> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT filter FROM session_stream_filters WHERE session_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+fn filters() -> &'static Mutex<HashMap<String, StreamFilter>> {
+    FILTERS.get_or_init(Default::default)
+}
+
+/// The filter a session's output goes through: its own, or the default one
+pub fn session_stream_filter(app: &AppHandle, session_id: &str) -> StreamFilter {
+    if let Some(filter) = filters()
+        .lock()
+        .ok()
+        .and_then(|filters| filters.get(session_id).copied())
+    {
+        return filter;
+    }
+    let filter = app
+        .try_state::<AgentDb>()
+        .and_then(|db| {
+            let conn = db.0.lock().ok()?;
+            Some(
+                load_session_filter(&conn, session_id)
+                    .ok()
+                    .flatten()
+                    .unwrap_or_else(|| load_default_filter(&conn)),
+            )
+        })
+        .unwrap_or_default();
+    if let Ok(mut filters) = filters().lock() {
+        filters.insert(session_id.to_string(), filter);
+    }
+    filter
+}
+
+/// Drop a finished session's filter from the cache; it is looked up again if
+/// the session is resumed
+pub fn forget_session_stream_filter(session_id: &str) {
+    if let Ok(mut filters) = filters().lock() {
+        filters.remove(session_id);
+    }
+}
+
+/// Get the stream filter of a session, falling back to the default one
+#[tauri::command]
+pub async fn get_session_stream_filter(
+    db: State<'_, AgentDb>,
+    session_id: String,
+) -> Result<StreamFilter, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_session_filter(&conn, &session_id)?.unwrap_or_else(|| load_default_filter(&conn)))
+}
+
+/// Set the stream filter of a session, taking effect on its next line of
+/// output; None goes back to the default filter
+#[tauri::command]
+pub async fn set_session_stream_filter(
+    db: State<'_, AgentDb>,
+    session_id: String,
+    filter: Option<StreamFilter>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match filter {
+        Some(filter) => {
+            let json = serde_json::to_string(&filter).map_err(|e| e.to_string())?;
+            conn.execute(
+                "INSERT OR REPLACE INTO session_stream_filters (session_id, filter, updated_at)
+                 VALUES (?1, ?2, CURRENT_TIMESTAMP)",
+                params![session_id, json],
+            )
+            .map_err(|e| format!("Failed to save stream filter: {}", e))?;
+        }
+        None => {
+            conn.execute(
+                "DELETE FROM session_stream_filters WHERE session_id = ?1",
+                params![session_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    if let Ok(mut filters) = filters().lock() {
+        filters.remove(&session_id);
+    }
+    Ok(())
+}
+
+/// Get the stream filter of sessions without one of their own
+#[tauri::command]
+pub async fn get_default_stream_filter(db: State<'_, AgentDb>) -> Result<StreamFilter, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_default_filter(&conn))
+}
+
+/// Set the stream filter of sessions without one of their own
+#[tauri::command]
+pub async fn set_default_stream_filter(
+    db: State<'_, AgentDb>,
+    filter: StreamFilter,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, DEFAULT_FILTER_KEY, &filter)?;
+    // Sessions following the default pick up the new one
+    if let Ok(mut filters) = filters().lock() {
+        filters.clear();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(filter: &StreamFilter, line: &str) -> Option<String> {
+        let json: JsonValue = serde_json::from_str(line).unwrap();
+        filter.apply(&json, line).map(Cow::into_owned)
+    }
+
+    #[test]
+    fn test_stream_filter() {
+        let big = "x".repeat(100);
+        let tool_result = serde_json::json!({
+            "type": "user",
+            "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": big},
+                {"type": "tool_result", "tool_use_id": "t2", "content": "ok"},
+            ]},
+            "tool_use_result": big,
+        })
+        .to_string();
+        let assistant = r#"{"type":"assistant","message":{"content":[{"type":"thinking","thinking":"hmm"},{"type":"text","text":"Editing"},{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"a.rs"}}]}}"#;
+        let result = r#"{"type":"result","subtype":"success"}"#;
+
+        assert_eq!(
+            apply(&StreamFilter::default(), &tool_result).as_deref(),
+            Some(tool_result.as_str())
+        );
+
+        let limited = StreamFilter {
+            max_tool_result_bytes: 50,
+            ..Default::default()
+        };
+        let filtered: JsonValue =
+            serde_json::from_str(&apply(&limited, &tool_result).unwrap()).unwrap();
+        let blocks = &filtered["message"]["content"];
+        assert_eq!(blocks[0]["suppressed_bytes"], 100);
+        assert_eq!(blocks[1]["content"], "ok");
+        assert!(filtered.get("tool_use_result").is_none());
+        assert_eq!(apply(&limited, assistant).as_deref(), Some(assistant));
+
+        let outline = StreamFilter {
+            only_text_and_tool_names: true,
+            ..Default::default()
+        };
+        assert_eq!(apply(&outline, &tool_result), None);
+        let outlined: JsonValue =
+            serde_json::from_str(&apply(&outline, assistant).unwrap()).unwrap();
+        assert_eq!(
+            outlined["message"]["content"],
+            serde_json::json!([
+                {"type": "text", "text": "Editing"},
+                {"type": "tool_use", "id": "t1", "name": "Edit", "input": {}},
+            ])
+        );
+        assert_eq!(apply(&outline, result).as_deref(), Some(result));
+    }
+}
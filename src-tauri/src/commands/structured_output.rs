@@ -0,0 +1,273 @@
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::path::Path;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::notifications::message_texts;
+
+/// The parsed JSON result of a run, checked against its agent's output schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredOutput {
+    pub run_id: i64,
+    /// The last JSON block in the run's messages; None if it ended without one
+    pub data: Option<JsonValue>,
+    pub valid: bool,
+    /// Schema violations as "/path: message", or why nothing could be extracted
+    pub errors: Vec<String>,
+    pub created_at: String,
+}
+
+/// Load the output schema declared for an agent, if any
+pub fn load_output_schema(conn: &Connection, agent_id: i64) -> Result<Option<JsonValue>, String> {
+    let schema: Option<String> = conn
+        .query_row(
+            "SELECT schema FROM agent_output_schemas WHERE agent_id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    schema
+        .map(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid output schema for agent {}: {}", agent_id, e))
+        })
+        .transpose()
+}
+
+/// Instructions appended to the system prompt so the run ends with a block we can extract
+pub fn output_instructions(schema: &JsonValue) -> String {
+    format!(
+        "\n\nWhen you are done, end your final message with a single ```json fenced code block \
+         containing your result. It must be valid JSON matching this JSON Schema:\n```json\n{}\n```",
+        serde_json::to_string_pretty(schema).unwrap_or_else(|_| schema.to_string())
+    )
+}
+
+/// Contents of the ``` fenced code blocks in a text, in order
+fn fenced_blocks(text: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("```") {
+        // Skip the info string (e.g. "json") on the opening fence's line
+        let after = &rest[start + 3..];
+        let Some(newline) = after.find('\n') else {
+            break;
+        };
+        let body = &after[newline + 1..];
+        let Some(end) = body.find("```") else {
+            break;
+        };
+        blocks.push(&body[..end]);
+        rest = &body[end + 3..];
+    }
+    blocks
+}
+
+/// The last JSON block in a message: a fenced block that parses, or else the whole message
+fn extract_json_block(text: &str) -> Option<JsonValue> {
+    fenced_blocks(text)
+        .into_iter()
+        .rev()
+        .find_map(|block| serde_json::from_str::<JsonValue>(block.trim()).ok())
+        .or_else(|| serde_json::from_str::<JsonValue>(text.trim()).ok())
+        .filter(|value| value.is_object() || value.is_array())
+}
+
+/// The final JSON block in a run's stream-json output
+pub fn extract_structured_output(output: &str) -> Option<JsonValue> {
+    message_texts(output)
+        .iter()
+        .rev()
+        .find_map(|text| extract_json_block(text))
+}
+
+/// Validate a value against a JSON Schema, returning the violations
+pub fn validate_output(schema: &JsonValue, value: &JsonValue) -> Result<Vec<String>, String> {
+    let validator =
+        jsonschema::validator_for(schema).map_err(|e| format!("Invalid output schema: {}", e))?;
+    Ok(validator
+        .iter_errors(value)
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            format!("{}: {}", if path.is_empty() { "/" } else { &path }, e)
+        })
+        .collect())
+}
+
+/// Extract and validate a completed run's structured result, if its agent declares a schema
+pub fn store_structured_output(conn: &Connection, run_id: i64, output: &str) {
+    let agent_id: Option<i64> = match conn
+        .query_row(
+            "SELECT agent_id FROM agent_runs WHERE id = ?1 AND status = 'completed'",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .optional()
+    {
+        Ok(agent_id) => agent_id,
+        Err(e) => {
+            warn!("Failed to look up agent run {}: {}", run_id, e);
+            return;
+        }
+    };
+    let schema = match agent_id.map(|id| load_output_schema(conn, id)).transpose() {
+        Ok(Some(Some(schema))) => schema,
+        Ok(_) => return,
+        Err(e) => {
+            warn!("{}", e);
+            return;
+        }
+    };
+
+    let data = extract_structured_output(output);
+    let errors = match &data {
+        Some(value) => validate_output(&schema, value).unwrap_or_else(|e| vec![e]),
+        None => vec!["The run did not end with a JSON block".to_string()],
+    };
+    if !errors.is_empty() {
+        info!(
+            "Structured output of run {} is invalid: {}",
+            run_id,
+            errors.join("; ")
+        );
+    }
+
+    if let Err(e) = conn.execute(
+        "INSERT OR REPLACE INTO agent_run_outputs (run_id, data, valid, errors) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            run_id,
+            data.map(|value| value.to_string()),
+            errors.is_empty(),
+            serde_json::to_string(&errors).unwrap_or_else(|_| "[]".to_string()),
+        ],
+    ) {
+        warn!("Failed to store structured output of run {}: {}", run_id, e);
+    }
+}
+
+/// Record a finished run's structured result from its stream-json output.
+pub fn record_structured_output(db_path: &Path, run_id: i64, output: &str) {
+    match Connection::open(db_path) {
+        Ok(conn) => store_structured_output(&conn, run_id, output),
+        Err(e) => warn!("Failed to open database to record structured output: {}", e),
+    }
+}
+
+/// Get the JSON Schema an agent's runs must answer with
+#[tauri::command]
+pub async fn get_agent_output_schema(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<Option<JsonValue>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_output_schema(&conn, agent_id)
+}
+
+/// Declare the JSON Schema an agent's runs must answer with; None removes it
+#[tauri::command]
+pub async fn set_agent_output_schema(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    schema: Option<JsonValue>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    match schema {
+        Some(schema) => {
+            jsonschema::validator_for(&schema)
+                .map_err(|e| format!("Invalid output schema: {}", e))?;
+            conn.execute(
+                "INSERT OR REPLACE INTO agent_output_schemas (agent_id, schema) VALUES (?1, ?2)",
+                params![agent_id, schema.to_string()],
+            )
+            .map_err(|e| format!("Failed to save output schema: {}", e))?;
+        }
+        None => {
+            conn.execute(
+                "DELETE FROM agent_output_schemas WHERE agent_id = ?1",
+                params![agent_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Get the structured result extracted from a run, if its agent declares an output schema
+#[tauri::command]
+pub async fn get_run_structured_output(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Option<StructuredOutput>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let row: Option<(Option<String>, bool, String, String)> = conn
+        .query_row(
+            "SELECT data, valid, errors, created_at FROM agent_run_outputs WHERE run_id = ?1",
+            params![run_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(
+        row.map(|(data, valid, errors, created_at)| StructuredOutput {
+            run_id,
+            data: data.and_then(|json| serde_json::from_str(&json).ok()),
+            valid,
+            errors: serde_json::from_str(&errors).unwrap_or_default(),
+            created_at,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_structured_output_takes_last_json_block() {
+        let output = [
+            json!({"type": "assistant", "message": {"content": [{"type": "text", "text": "Plan:\n```json\n{\"step\": 1}\n```"}]}}),
+            json!({"type": "assistant", "message": {"content": [{"type": "text", "text": "Done.\n```json\n{\"status\": \"ok\", \"count\": 2}\n```\n```\nnot json\n```"}]}}),
+            json!({"type": "result", "result": "All finished"}),
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        assert_eq!(
+            extract_structured_output(&output),
+            Some(json!({"status": "ok", "count": 2}))
+        );
+        assert_eq!(extract_json_block("  [1, 2]  "), Some(json!([1, 2])));
+        assert_eq!(extract_json_block("42"), None);
+        assert_eq!(extract_structured_output("not stream json"), None);
+    }
+
+    #[test]
+    fn test_validate_output_reports_paths() {
+        let schema = json!({
+            "type": "object",
+            "required": ["status"],
+            "properties": {"count": {"type": "integer"}}
+        });
+
+        assert!(
+            validate_output(&schema, &json!({"status": "ok", "count": 2}))
+                .unwrap()
+                .is_empty()
+        );
+        let errors = validate_output(&schema, &json!({"count": "two"})).unwrap();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| e.starts_with("/count:")));
+        assert!(validate_output(&json!({"type": 5}), &json!({})).is_err());
+    }
+}
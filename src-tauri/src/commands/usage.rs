@@ -1,10 +1,14 @@
 use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::{params, Connection, Row};
 use serde::{Deserialize, Serialize};
-use serde_json;
-use std::collections::{HashMap, HashSet};
-use std::fs;
-use std::path::PathBuf;
-use tauri::command;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tauri::{command, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::metrics::success_rate;
+use crate::commands::usage_index::indexed_db;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UsageEntry {
@@ -13,6 +17,8 @@ pub struct UsageEntry {
     input_tokens: u64,
     output_tokens: u64,
     cache_creation_tokens: u64,
+    /// The part of `cache_creation_tokens` written with the 1 hour TTL
+    cache_creation_1h_tokens: u64,
     cache_read_tokens: u64,
     cost: f64,
     session_id: String,
@@ -50,393 +56,364 @@ pub struct DailyUsage {
     date: String,
     total_cost: f64,
     total_tokens: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
     models_used: Vec<String>,
 }
 
+/// Spend of an agent's runs, attributed through their session ids
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgentCost {
+    agent_id: i64,
+    agent_name: String,
+    agent_icon: String,
+    run_count: u64,
+    total_cost: f64,
+    /// Total cost divided by the number of runs
+    average_cost: f64,
+    total_tokens: u64,
+}
+
+/// How a model performed over a date range, for comparing models
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModelComparison {
+    model: String,
+    total_cost: f64,
+    total_tokens: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    session_count: u64,
+    /// Assistant messages per session
+    average_turns: f64,
+    /// Total cost divided by the number of sessions
+    average_session_cost: f64,
+    /// Agent runs whose session mostly used this model
+    run_count: u64,
+    completed_runs: u64,
+    failed_runs: u64,
+    /// Completed runs as a share of finished runs (0.0 - 1.0); None if none finished
+    success_rate: Option<f64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProjectUsage {
     project_path: String,
     project_name: String,
     total_cost: f64,
     total_tokens: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
     session_count: u64,
     last_used: String,
 }
 
-// Claude 4 pricing constants (per million tokens)
-const OPUS_4_INPUT_PRICE: f64 = 15.0;
-const OPUS_4_OUTPUT_PRICE: f64 = 75.0;
-const OPUS_4_CACHE_WRITE_PRICE: f64 = 18.75;
-const OPUS_4_CACHE_READ_PRICE: f64 = 1.50;
+/// Activity of one day, for a contributions-style heatmap
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ActivityDay {
+    date: String,
+    /// Sessions whose first message was on this day
+    sessions_started: u64,
+    agent_runs: u64,
+    cost: f64,
+}
 
-const SONNET_4_INPUT_PRICE: f64 = 3.0;
-const SONNET_4_OUTPUT_PRICE: f64 = 15.0;
-const SONNET_4_CACHE_WRITE_PRICE: f64 = 3.75;
-const SONNET_4_CACHE_READ_PRICE: f64 = 0.30;
+/// Prices of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    /// Cache writes with the default 5 minute TTL
+    pub cache_write_5m: f64,
+    pub cache_write_1h: f64,
+    pub cache_read: f64,
+}
 
-#[derive(Debug, Deserialize)]
-struct JsonlEntry {
-    timestamp: String,
-    message: Option<MessageData>,
-    #[serde(rename = "sessionId")]
-    session_id: Option<String>,
-    #[serde(rename = "requestId")]
-    request_id: Option<String>,
-    #[serde(rename = "costUSD")]
-    cost_usd: Option<f64>,
-}
-
-#[derive(Debug, Deserialize)]
-struct MessageData {
-    id: Option<String>,
-    model: Option<String>,
-    usage: Option<UsageData>,
-}
-
-#[derive(Debug, Deserialize)]
-struct UsageData {
-    input_tokens: Option<u64>,
-    output_tokens: Option<u64>,
-    cache_creation_input_tokens: Option<u64>,
-    cache_read_input_tokens: Option<u64>,
-}
-
-fn calculate_cost(model: &str, usage: &UsageData) -> f64 {
-    let input_tokens = usage.input_tokens.unwrap_or(0) as f64;
-    let output_tokens = usage.output_tokens.unwrap_or(0) as f64;
-    let cache_creation_tokens = usage.cache_creation_input_tokens.unwrap_or(0) as f64;
-    let cache_read_tokens = usage.cache_read_input_tokens.unwrap_or(0) as f64;
-
-    // Calculate cost based on model
-    let (input_price, output_price, cache_write_price, cache_read_price) =
-        if model.contains("opus-4") || model.contains("claude-opus-4") {
-            (
-                OPUS_4_INPUT_PRICE,
-                OPUS_4_OUTPUT_PRICE,
-                OPUS_4_CACHE_WRITE_PRICE,
-                OPUS_4_CACHE_READ_PRICE,
-            )
-        } else if model.contains("sonnet-4") || model.contains("claude-sonnet-4") {
-            (
-                SONNET_4_INPUT_PRICE,
-                SONNET_4_OUTPUT_PRICE,
-                SONNET_4_CACHE_WRITE_PRICE,
-                SONNET_4_CACHE_READ_PRICE,
-            )
-        } else {
-            // Return 0 for unknown models to avoid incorrect cost estimations.
-            (0.0, 0.0, 0.0, 0.0)
-        };
-
-    // Calculate cost (prices are per million tokens)
-    let cost = (input_tokens * input_price / 1_000_000.0)
-        + (output_tokens * output_price / 1_000_000.0)
-        + (cache_creation_tokens * cache_write_price / 1_000_000.0)
-        + (cache_read_tokens * cache_read_price / 1_000_000.0);
-
-    cost
-}
-
-fn parse_jsonl_file(
-    path: &PathBuf,
-    encoded_project_name: &str,
-    processed_hashes: &mut HashSet<String>,
-) -> Vec<UsageEntry> {
-    let mut entries = Vec::new();
-    let mut actual_project_path: Option<String> = None;
-
-    if let Ok(content) = fs::read_to_string(path) {
-        // Extract session ID from the file path
-        let session_id = path
-            .parent()
-            .and_then(|p| p.file_name())
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-
-        for line in content.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(line) {
-                // Extract the actual project path from cwd if we haven't already
-                if actual_project_path.is_none() {
-                    if let Some(cwd) = json_value.get("cwd").and_then(|v| v.as_str()) {
-                        actual_project_path = Some(cwd.to_string());
-                    }
-                }
-
-                // Try to parse as JsonlEntry for usage data
-                if let Ok(entry) = serde_json::from_value::<JsonlEntry>(json_value) {
-                    if let Some(message) = &entry.message {
-                        // Deduplication based on message ID and request ID
-                        if let (Some(msg_id), Some(req_id)) = (&message.id, &entry.request_id) {
-                            let unique_hash = format!("{}:{}", msg_id, req_id);
-                            if processed_hashes.contains(&unique_hash) {
-                                continue; // Skip duplicate entry
-                            }
-                            processed_hashes.insert(unique_hash);
-                        }
-
-                        if let Some(usage) = &message.usage {
-                            // Skip entries without meaningful token usage
-                            if usage.input_tokens.unwrap_or(0) == 0
-                                && usage.output_tokens.unwrap_or(0) == 0
-                                && usage.cache_creation_input_tokens.unwrap_or(0) == 0
-                                && usage.cache_read_input_tokens.unwrap_or(0) == 0
-                            {
-                                continue;
-                            }
-
-                            let cost = entry.cost_usd.unwrap_or_else(|| {
-                                if let Some(model_str) = &message.model {
-                                    calculate_cost(model_str, usage)
-                                } else {
-                                    0.0
-                                }
-                            });
-
-                            // Use actual project path if found, otherwise use encoded name
-                            let project_path = actual_project_path
-                                .clone()
-                                .unwrap_or_else(|| encoded_project_name.to_string());
-
-                            entries.push(UsageEntry {
-                                timestamp: entry.timestamp,
-                                model: message
-                                    .model
-                                    .clone()
-                                    .unwrap_or_else(|| "unknown".to_string()),
-                                input_tokens: usage.input_tokens.unwrap_or(0),
-                                output_tokens: usage.output_tokens.unwrap_or(0),
-                                cache_creation_tokens: usage
-                                    .cache_creation_input_tokens
-                                    .unwrap_or(0),
-                                cache_read_tokens: usage.cache_read_input_tokens.unwrap_or(0),
-                                cost,
-                                session_id: entry.session_id.unwrap_or_else(|| session_id.clone()),
-                                project_path,
-                            });
-                        }
-                    }
-                }
-            }
+impl ModelPricing {
+    /// Pricing with the cache rates Anthropic derives from the input price
+    pub const fn from_input_output(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_write_5m: input * 1.25,
+            cache_write_1h: input * 2.0,
+            cache_read: input * 0.1,
         }
     }
 
-    entries
-}
-
-fn get_earliest_timestamp(path: &PathBuf) -> Option<String> {
-    if let Ok(content) = fs::read_to_string(path) {
-        let mut earliest_timestamp: Option<String> = None;
-        for line in content.lines() {
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(line) {
-                if let Some(timestamp_str) = json_value.get("timestamp").and_then(|v| v.as_str()) {
-                    if let Some(current_earliest) = &earliest_timestamp {
-                        if timestamp_str < current_earliest.as_str() {
-                            earliest_timestamp = Some(timestamp_str.to_string());
-                        }
-                    } else {
-                        earliest_timestamp = Some(timestamp_str.to_string());
-                    }
-                }
-            }
-        }
-        return earliest_timestamp;
+    /// Cost in USD of the given tokens at these prices
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        let cache_creation_5m_tokens = usage
+            .cache_creation_tokens
+            .saturating_sub(usage.cache_creation_1h_tokens);
+
+        // Prices are per million tokens
+        (usage.input_tokens as f64 * self.input
+            + usage.output_tokens as f64 * self.output
+            + cache_creation_5m_tokens as f64 * self.cache_write_5m
+            + usage.cache_creation_1h_tokens as f64 * self.cache_write_1h
+            + usage.cache_read_tokens as f64 * self.cache_read)
+            / 1_000_000.0
     }
-    None
-}
-
-fn get_all_usage_entries(claude_path: &PathBuf) -> Vec<UsageEntry> {
-    let mut all_entries = Vec::new();
-    let mut processed_hashes = HashSet::new();
-    let projects_dir = claude_path.join("projects");
-
-    let mut files_to_process: Vec<(PathBuf, String)> = Vec::new();
-
-    if let Ok(projects) = fs::read_dir(&projects_dir) {
-        for project in projects.flatten() {
-            if project.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                let project_name = project.file_name().to_string_lossy().to_string();
-                let project_path = project.path();
-
-                walkdir::WalkDir::new(&project_path)
-                    .into_iter()
-                    .filter_map(Result::ok)
-                    .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("jsonl"))
-                    .for_each(|entry| {
-                        files_to_process.push((entry.path().to_path_buf(), project_name.clone()));
-                    });
-            }
-        }
+}
+
+/// Pricing by model id fragment, most specific first
+const MODEL_PRICING: &[(&str, ModelPricing)] = &[
+    ("opus-4-5", ModelPricing::from_input_output(5.0, 25.0)),
+    ("opus-4", ModelPricing::from_input_output(15.0, 75.0)),
+    ("3-opus", ModelPricing::from_input_output(15.0, 75.0)),
+    ("sonnet-4", ModelPricing::from_input_output(3.0, 15.0)),
+    ("3-7-sonnet", ModelPricing::from_input_output(3.0, 15.0)),
+    ("3-5-sonnet", ModelPricing::from_input_output(3.0, 15.0)),
+    ("haiku-4-5", ModelPricing::from_input_output(1.0, 5.0)),
+    ("3-5-haiku", ModelPricing::from_input_output(0.8, 4.0)),
+    ("3-haiku", ModelPricing::from_input_output(0.25, 1.25)),
+];
+
+/// Prices configured by the user by model id fragment, longest first; they
+/// take precedence over `MODEL_PRICING`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CustomPricing(Vec<(String, ModelPricing)>);
+
+impl CustomPricing {
+    pub fn new(mut pricing: Vec<(String, ModelPricing)>) -> Self {
+        pricing.sort_by_key(|(fragment, _)| std::cmp::Reverse(fragment.len()));
+        Self(pricing)
     }
 
-    // Sort files by their earliest timestamp to ensure chronological processing
-    // and deterministic deduplication.
-    files_to_process.sort_by_cached_key(|(path, _)| get_earliest_timestamp(path));
+    /// The user's price for a model, if they set one
+    pub fn get(&self, model: &str) -> Option<ModelPricing> {
+        self.0
+            .iter()
+            .find(|(fragment, _)| model.contains(fragment.as_str()))
+            .map(|(_, pricing)| *pricing)
+    }
 
-    for (path, project_name) in files_to_process {
-        let entries = parse_jsonl_file(&path, &project_name, &mut processed_hashes);
-        all_entries.extend(entries);
+    /// Cost of usage at the user's price for its model, or `base_cost`, what
+    /// it costs without custom prices, for models the user hasn't priced
+    pub fn cost(&self, model: &str, usage: &TokenUsage, base_cost: f64) -> f64 {
+        self.get(model)
+            .map_or(base_cost, |pricing| pricing.cost(usage))
     }
+}
 
-    // Sort by timestamp
-    all_entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+static CUSTOM_PRICING: RwLock<CustomPricing> = RwLock::new(CustomPricing(Vec::new()));
 
-    all_entries
+/// Replace the user's prices
+pub fn set_custom_pricing(pricing: CustomPricing) {
+    if let Ok(mut custom) = CUSTOM_PRICING.write() {
+        *custom = pricing;
+    }
 }
 
-#[command]
-pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
-
-    let all_entries = get_all_usage_entries(&claude_path);
-
-    if all_entries.is_empty() {
-        return Ok(UsageStats {
-            total_cost: 0.0,
-            total_tokens: 0,
-            total_input_tokens: 0,
-            total_output_tokens: 0,
-            total_cache_creation_tokens: 0,
-            total_cache_read_tokens: 0,
-            total_sessions: 0,
-            by_model: vec![],
-            by_date: vec![],
-            by_project: vec![],
-        });
-    }
+/// The user's price for a model, if they set one
+pub fn custom_pricing(model: &str) -> Option<ModelPricing> {
+    CUSTOM_PRICING.read().ok()?.get(model)
+}
 
-    // Filter by days if specified
-    let filtered_entries = if let Some(days) = days {
-        let cutoff = Local::now().naive_local().date() - chrono::Duration::days(days as i64);
-        all_entries
-            .into_iter()
-            .filter(|e| {
-                if let Ok(dt) = DateTime::parse_from_rfc3339(&e.timestamp) {
-                    dt.naive_local().date() >= cutoff
-                } else {
-                    false
-                }
-            })
-            .collect()
-    } else {
-        all_entries
-    };
+/// Anthropic's price for a model, None for models without known prices
+pub fn builtin_pricing(model: &str) -> Option<ModelPricing> {
+    MODEL_PRICING
+        .iter()
+        .find(|(fragment, _)| model.contains(fragment))
+        .map(|(_, pricing)| *pricing)
+}
 
-    // Calculate aggregated stats
-    let mut total_cost = 0.0;
-    let mut total_input_tokens = 0u64;
-    let mut total_output_tokens = 0u64;
-    let mut total_cache_creation_tokens = 0u64;
-    let mut total_cache_read_tokens = 0u64;
-
-    let mut model_stats: HashMap<String, ModelUsage> = HashMap::new();
-    let mut daily_stats: HashMap<String, DailyUsage> = HashMap::new();
-    let mut project_stats: HashMap<String, ProjectUsage> = HashMap::new();
-
-    for entry in &filtered_entries {
-        // Update totals
-        total_cost += entry.cost;
-        total_input_tokens += entry.input_tokens;
-        total_output_tokens += entry.output_tokens;
-        total_cache_creation_tokens += entry.cache_creation_tokens;
-        total_cache_read_tokens += entry.cache_read_tokens;
-
-        // Update model stats
-        let model_stat = model_stats
-            .entry(entry.model.clone())
-            .or_insert(ModelUsage {
-                model: entry.model.clone(),
-                total_cost: 0.0,
-                total_tokens: 0,
-                input_tokens: 0,
-                output_tokens: 0,
-                cache_creation_tokens: 0,
-                cache_read_tokens: 0,
-                session_count: 0,
-            });
-        model_stat.total_cost += entry.cost;
-        model_stat.input_tokens += entry.input_tokens;
-        model_stat.output_tokens += entry.output_tokens;
-        model_stat.cache_creation_tokens += entry.cache_creation_tokens;
-        model_stat.cache_read_tokens += entry.cache_read_tokens;
-        model_stat.total_tokens = model_stat.input_tokens + model_stat.output_tokens;
-        model_stat.session_count += 1;
-
-        // Update daily stats
-        let date = entry
-            .timestamp
-            .split('T')
-            .next()
-            .unwrap_or(&entry.timestamp)
-            .to_string();
-        let daily_stat = daily_stats.entry(date.clone()).or_insert(DailyUsage {
-            date,
-            total_cost: 0.0,
-            total_tokens: 0,
-            models_used: vec![],
-        });
-        daily_stat.total_cost += entry.cost;
-        daily_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        if !daily_stat.models_used.contains(&entry.model) {
-            daily_stat.models_used.push(entry.model.clone());
-        }
+/// Pricing of a model, None for models without known prices
+pub fn model_pricing(model: &str) -> Option<ModelPricing> {
+    custom_pricing(model).or_else(|| builtin_pricing(model))
+}
 
-        // Update project stats
-        let project_stat =
-            project_stats
-                .entry(entry.project_path.clone())
-                .or_insert(ProjectUsage {
-                    project_path: entry.project_path.clone(),
-                    project_name: entry
-                        .project_path
-                        .split('/')
-                        .last()
-                        .unwrap_or(&entry.project_path)
-                        .to_string(),
-                    total_cost: 0.0,
-                    total_tokens: 0,
-                    session_count: 0,
-                    last_used: entry.timestamp.clone(),
-                });
-        project_stat.total_cost += entry.cost;
-        project_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        project_stat.session_count += 1;
-        if entry.timestamp > project_stat.last_used {
-            project_stat.last_used = entry.timestamp.clone();
+/// Tokens of one or more messages, split by how they are billed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Tokens written to the cache, at either TTL
+    pub cache_creation_tokens: u64,
+    /// The part of `cache_creation_tokens` written with the 1 hour TTL
+    pub cache_creation_1h_tokens: u64,
+    pub cache_read_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Token counts of the `usage` object of an assistant message
+    pub fn from_json(usage: &JsonValue) -> Self {
+        let tokens = |value: &JsonValue| value.as_u64().unwrap_or(0);
+        let cache_creation_tokens = tokens(&usage["cache_creation_input_tokens"]);
+        Self {
+            input_tokens: tokens(&usage["input_tokens"]),
+            output_tokens: tokens(&usage["output_tokens"]),
+            cache_creation_tokens,
+            cache_creation_1h_tokens: tokens(&usage["cache_creation"]["ephemeral_1h_input_tokens"])
+                .min(cache_creation_tokens),
+            cache_read_tokens: tokens(&usage["cache_read_input_tokens"]),
         }
     }
 
-    let total_tokens = total_input_tokens
-        + total_output_tokens
-        + total_cache_creation_tokens
-        + total_cache_read_tokens;
-    let total_sessions = filtered_entries.len() as u64;
+    pub fn is_empty(&self) -> bool {
+        self.input_tokens + self.output_tokens + self.cache_creation_tokens + self.cache_read_tokens
+            == 0
+    }
 
-    // Convert hashmaps to sorted vectors
-    let mut by_model: Vec<ModelUsage> = model_stats.into_values().collect();
-    by_model.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_creation_1h_tokens += other.cache_creation_1h_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+    }
+}
 
-    let mut by_date: Vec<DailyUsage> = daily_stats.into_values().collect();
-    by_date.sort_by(|a, b| b.date.cmp(&a.date));
+/// Estimate the cost in USD of the given tokens for a model. Cache writes and
+/// reads are priced at their own rates; unknown models cost nothing rather
+/// than an incorrect estimate.
+pub fn token_cost(model: &str, usage: &TokenUsage) -> f64 {
+    model_pricing(model).map_or(0.0, |pricing| pricing.cost(usage))
+}
 
-    let mut by_project: Vec<ProjectUsage> = project_stats.into_values().collect();
-    by_project.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
+/// Entries within an optional inclusive range of `YYYY-MM-DD` dates
+const DATE_RANGE_SQL: &str = "(?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2)";
+
+/// Entries of the sessions of an optional agent's runs
+const AGENT_FILTER_SQL: &str =
+    "(?3 IS NULL OR session_id IN (SELECT session_id FROM agent_runs WHERE agent_id = ?3))";
+
+/// Sums of each kind of token of the entries in a group, read by `token_sums`
+const TOKEN_SUMS_SQL: &str =
+    "SUM(input_tokens), SUM(output_tokens), SUM(cache_creation_tokens), SUM(cache_read_tokens)";
+
+/// Input, output, cache creation and cache read token sums starting at column `first`
+fn token_sums(row: &Row, first: usize) -> rusqlite::Result<[u64; 4]> {
+    Ok([
+        row.get::<_, i64>(first)? as u64,
+        row.get::<_, i64>(first + 1)? as u64,
+        row.get::<_, i64>(first + 2)? as u64,
+        row.get::<_, i64>(first + 3)? as u64,
+    ])
+}
+
+fn project_name(project_path: &str) -> String {
+    project_path
+        .split('/')
+        .next_back()
+        .unwrap_or(project_path)
+        .to_string()
+}
+
+/// Aggregate the indexed usage between two dates, either open-ended, of all
+/// sessions or only those of one agent's runs. Rolled up months count towards
+/// the totals of all sessions, dated to their first day, but have no daily
+/// breakdown.
+fn usage_stats(
+    conn: &Connection,
+    start: Option<String>,
+    end: Option<String>,
+    agent_id: Option<i64>,
+) -> Result<UsageStats, rusqlite::Error> {
+    let (
+        total_cost,
+        total_input_tokens,
+        total_output_tokens,
+        total_cache_creation_tokens,
+        total_cache_read_tokens,
+        total_sessions,
+    ) = conn.query_row(
+        &format!(
+            "SELECT COALESCE(SUM(cost), 0), COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0),
+                COALESCE(SUM(cache_creation_tokens), 0), COALESCE(SUM(cache_read_tokens), 0),
+                COALESCE(SUM(message_count), 0)
+             FROM usage_with_rollups WHERE {} AND {}",
+            DATE_RANGE_SQL, AGENT_FILTER_SQL
+        ),
+        params![start, end, agent_id],
+        |row| {
+            Ok((
+                row.get::<_, f64>(0)?,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, i64>(2)? as u64,
+                row.get::<_, i64>(3)? as u64,
+                row.get::<_, i64>(4)? as u64,
+                row.get::<_, i64>(5)? as u64,
+            ))
+        },
+    )?;
+
+    let by_model = conn
+        .prepare(&format!(
+            "SELECT model, SUM(cost), SUM(input_tokens), SUM(output_tokens), SUM(cache_creation_tokens),
+                SUM(cache_read_tokens), SUM(message_count)
+             FROM usage_with_rollups WHERE {} AND {} GROUP BY model ORDER BY SUM(cost) DESC",
+            DATE_RANGE_SQL, AGENT_FILTER_SQL
+        ))?
+        .query_map(params![start, end, agent_id], |row| {
+            let input_tokens = row.get::<_, i64>(2)? as u64;
+            let output_tokens = row.get::<_, i64>(3)? as u64;
+            Ok(ModelUsage {
+                model: row.get(0)?,
+                total_cost: row.get(1)?,
+                total_tokens: input_tokens + output_tokens,
+                input_tokens,
+                output_tokens,
+                cache_creation_tokens: row.get::<_, i64>(4)? as u64,
+                cache_read_tokens: row.get::<_, i64>(5)? as u64,
+                session_count: row.get::<_, i64>(6)? as u64,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let by_date = conn
+        .prepare(&format!(
+            "SELECT date, SUM(cost), {}, GROUP_CONCAT(DISTINCT model)
+             FROM usage_messages WHERE {} AND {} GROUP BY date ORDER BY date DESC",
+            TOKEN_SUMS_SQL, DATE_RANGE_SQL, AGENT_FILTER_SQL
+        ))?
+        .query_map(params![start, end, agent_id], |row| {
+            let [input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens] =
+                token_sums(row, 2)?;
+            Ok(DailyUsage {
+                date: row.get(0)?,
+                total_cost: row.get(1)?,
+                total_tokens: input_tokens
+                    + output_tokens
+                    + cache_creation_tokens
+                    + cache_read_tokens,
+                input_tokens,
+                output_tokens,
+                cache_creation_tokens,
+                cache_read_tokens,
+                models_used: row
+                    .get::<_, String>(6)?
+                    .split(',')
+                    .map(str::to_string)
+                    .collect(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let by_project = conn
+        .prepare(&format!(
+            "SELECT project_path, SUM(cost), {}, SUM(message_count), MAX(timestamp)
+             FROM usage_with_rollups WHERE {} AND {} GROUP BY project_path ORDER BY SUM(cost) DESC",
+            TOKEN_SUMS_SQL, DATE_RANGE_SQL, AGENT_FILTER_SQL
+        ))?
+        .query_map(params![start, end, agent_id], |row| {
+            let project_path: String = row.get(0)?;
+            project_usage(project_name(&project_path), project_path, row, 1)
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
 
     Ok(UsageStats {
         total_cost,
-        total_tokens,
+        total_tokens: total_input_tokens
+            + total_output_tokens
+            + total_cache_creation_tokens
+            + total_cache_read_tokens,
         total_input_tokens,
         total_output_tokens,
         total_cache_creation_tokens,
@@ -448,267 +425,623 @@ pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
     })
 }
 
-#[command]
-pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<UsageStats, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+/// A project or session group read from `SUM(cost)`, the token sums, the
+/// message count and `MAX(timestamp)` starting at column `first`
+fn project_usage(
+    project_name: String,
+    project_path: String,
+    row: &Row,
+    first: usize,
+) -> rusqlite::Result<ProjectUsage> {
+    let [input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens] =
+        token_sums(row, first + 1)?;
+    Ok(ProjectUsage {
+        project_path,
+        project_name,
+        total_cost: row.get(first)?,
+        total_tokens: input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens,
+        input_tokens,
+        output_tokens,
+        cache_creation_tokens,
+        cache_read_tokens,
+        session_count: row.get::<_, i64>(first + 5)? as u64,
+        last_used: row.get(first + 6)?,
+    })
+}
 
-    let all_entries = get_all_usage_entries(&claude_path);
+/// Cost of every agent's runs. Runs are costed from the indexed usage of
+/// their sessions, falling back to the cost recorded on the run for sessions
+/// whose transcript is gone or whose usage was rolled up.
+fn agent_costs(conn: &Connection) -> Result<Vec<AgentCost>, rusqlite::Error> {
+    conn.prepare(
+        "SELECT r.agent_id, COALESCE(a.name, MAX(r.agent_name)), COALESCE(a.icon, MAX(r.agent_icon)),
+            COUNT(*), SUM(COALESCE(u.cost, r.cost_usd, 0)), SUM(COALESCE(u.tokens, r.total_tokens, 0))
+         FROM agent_runs r
+         LEFT JOIN agents a ON a.id = r.agent_id
+         LEFT JOIN (
+             SELECT session_id, SUM(cost) AS cost,
+                 SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens) AS tokens
+             FROM usage_messages GROUP BY session_id
+         ) u ON u.session_id = r.session_id
+         WHERE r.session_id != ''
+         GROUP BY r.agent_id
+         ORDER BY SUM(COALESCE(u.cost, r.cost_usd, 0)) DESC",
+    )?
+    .query_map([], |row| {
+        let run_count = row.get::<_, i64>(3)? as u64;
+        let total_cost: f64 = row.get(4)?;
+        Ok(AgentCost {
+            agent_id: row.get(0)?,
+            agent_name: row.get(1)?,
+            agent_icon: row.get(2)?,
+            run_count,
+            total_cost,
+            average_cost: total_cost / run_count.max(1) as f64,
+            total_tokens: row.get::<_, i64>(5)? as u64,
+        })
+    })?
+    .collect()
+}
 
-    // Parse dates
-    let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d").or_else(|_| {
-        // Try parsing ISO datetime format
-        DateTime::parse_from_rfc3339(&start_date)
-            .map(|dt| dt.naive_local().date())
-            .map_err(|e| format!("Invalid start date: {}", e))
-    })?;
-    let end = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d").or_else(|_| {
-        // Try parsing ISO datetime format
-        DateTime::parse_from_rfc3339(&end_date)
-            .map(|dt| dt.naive_local().date())
-            .map_err(|e| format!("Invalid end date: {}", e))
-    })?;
-
-    // Filter entries by date range
-    let filtered_entries: Vec<_> = all_entries
-        .into_iter()
-        .filter(|e| {
-            if let Ok(dt) = DateTime::parse_from_rfc3339(&e.timestamp) {
-                let date = dt.naive_local().date();
-                date >= start && date <= end
-            } else {
-                false
-            }
+/// Compare the models used between two dates, either open-ended. Agent runs
+/// created in the range count towards the model that wrote most of their
+/// session's output, as a run's own model is an alias like `sonnet`.
+fn model_comparison(
+    conn: &Connection,
+    start: Option<String>,
+    end: Option<String>,
+) -> Result<Vec<ModelComparison>, rusqlite::Error> {
+    let mut runs: HashMap<String, [u64; 3]> = conn
+        .prepare(
+            "WITH session_models AS (
+                 SELECT session_id, model FROM (
+                     SELECT session_id, model,
+                         ROW_NUMBER() OVER (PARTITION BY session_id ORDER BY SUM(output_tokens) DESC) AS rank
+                     FROM usage_messages GROUP BY session_id, model
+                 ) WHERE rank = 1
+             )
+             SELECT m.model, COUNT(*), SUM(r.status = 'completed'), SUM(r.status IN ('failed', 'budget_exceeded', 'limit_exceeded'))
+             FROM agent_runs r JOIN session_models m ON m.session_id = r.session_id
+             WHERE (?1 IS NULL OR date(r.created_at) >= ?1) AND (?2 IS NULL OR date(r.created_at) <= ?2)
+             GROUP BY m.model",
+        )?
+        .query_map(params![start, end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                [
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, i64>(2)? as u64,
+                    row.get::<_, i64>(3)? as u64,
+                ],
+            ))
+        })?
+        .collect::<Result<_, _>>()?;
+
+    conn.prepare(&format!(
+        "SELECT model, SUM(cost), {}, COUNT(DISTINCT session_id) + SUM(rolled_up_sessions), SUM(message_count)
+         FROM usage_with_rollups WHERE {} GROUP BY model ORDER BY SUM(cost) DESC",
+        TOKEN_SUMS_SQL, DATE_RANGE_SQL
+    ))?
+    .query_map(params![start, end], |row| {
+        let model: String = row.get(0)?;
+        let total_cost: f64 = row.get(1)?;
+        let [input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens] =
+            token_sums(row, 2)?;
+        let session_count = row.get::<_, i64>(6)? as u64;
+        let message_count = row.get::<_, i64>(7)? as u64;
+        let [run_count, completed_runs, failed_runs] = runs.remove(&model).unwrap_or_default();
+        Ok(ModelComparison {
+            model,
+            total_cost,
+            total_tokens: input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens,
+            input_tokens,
+            output_tokens,
+            cache_creation_tokens,
+            cache_read_tokens,
+            session_count,
+            average_turns: message_count as f64 / session_count.max(1) as f64,
+            average_session_cost: total_cost / session_count.max(1) as f64,
+            run_count,
+            completed_runs,
+            failed_runs,
+            success_rate: success_rate(completed_runs as i64, failed_runs as i64),
+        })
+    })?
+    .collect()
+}
+
+/// Daily activity from `start` to `end`, with every day in between present
+fn activity_heatmap(
+    conn: &Connection,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Vec<ActivityDay>, rusqlite::Error> {
+    let (first, last) = (
+        start.format("%Y-%m-%d").to_string(),
+        end.format("%Y-%m-%d").to_string(),
+    );
+    let range = params![first, last];
+    let mut days: Vec<ActivityDay> = start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .map(|date| ActivityDay {
+            date: date.format("%Y-%m-%d").to_string(),
+            ..Default::default()
         })
         .collect();
+    let index: HashMap<String, usize> = days
+        .iter()
+        .enumerate()
+        .map(|(i, day)| (day.date.clone(), i))
+        .collect();
 
-    if filtered_entries.is_empty() {
-        return Ok(UsageStats {
-            total_cost: 0.0,
-            total_tokens: 0,
-            total_input_tokens: 0,
-            total_output_tokens: 0,
-            total_cache_creation_tokens: 0,
-            total_cache_read_tokens: 0,
-            total_sessions: 0,
-            by_model: vec![],
-            by_date: vec![],
-            by_project: vec![],
-        });
+    for row in conn
+        .prepare(
+            "SELECT started, COUNT(*) FROM (
+                 SELECT MIN(date) AS started FROM usage_messages GROUP BY session_id
+             ) WHERE started BETWEEN ?1 AND ?2 GROUP BY started",
+        )?
+        .query_map(range, |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?
+    {
+        let (date, count) = row?;
+        if let Some(&i) = index.get(&date) {
+            days[i].sessions_started = count as u64;
+        }
     }
-
-    // Calculate aggregated stats (same logic as get_usage_stats)
-    let mut total_cost = 0.0;
-    let mut total_input_tokens = 0u64;
-    let mut total_output_tokens = 0u64;
-    let mut total_cache_creation_tokens = 0u64;
-    let mut total_cache_read_tokens = 0u64;
-
-    let mut model_stats: HashMap<String, ModelUsage> = HashMap::new();
-    let mut daily_stats: HashMap<String, DailyUsage> = HashMap::new();
-    let mut project_stats: HashMap<String, ProjectUsage> = HashMap::new();
-
-    for entry in &filtered_entries {
-        // Update totals
-        total_cost += entry.cost;
-        total_input_tokens += entry.input_tokens;
-        total_output_tokens += entry.output_tokens;
-        total_cache_creation_tokens += entry.cache_creation_tokens;
-        total_cache_read_tokens += entry.cache_read_tokens;
-
-        // Update model stats
-        let model_stat = model_stats
-            .entry(entry.model.clone())
-            .or_insert(ModelUsage {
-                model: entry.model.clone(),
-                total_cost: 0.0,
-                total_tokens: 0,
-                input_tokens: 0,
-                output_tokens: 0,
-                cache_creation_tokens: 0,
-                cache_read_tokens: 0,
-                session_count: 0,
-            });
-        model_stat.total_cost += entry.cost;
-        model_stat.input_tokens += entry.input_tokens;
-        model_stat.output_tokens += entry.output_tokens;
-        model_stat.cache_creation_tokens += entry.cache_creation_tokens;
-        model_stat.cache_read_tokens += entry.cache_read_tokens;
-        model_stat.total_tokens = model_stat.input_tokens + model_stat.output_tokens;
-        model_stat.session_count += 1;
-
-        // Update daily stats
-        let date = entry
-            .timestamp
-            .split('T')
-            .next()
-            .unwrap_or(&entry.timestamp)
-            .to_string();
-        let daily_stat = daily_stats.entry(date.clone()).or_insert(DailyUsage {
-            date,
-            total_cost: 0.0,
-            total_tokens: 0,
-            models_used: vec![],
-        });
-        daily_stat.total_cost += entry.cost;
-        daily_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        if !daily_stat.models_used.contains(&entry.model) {
-            daily_stat.models_used.push(entry.model.clone());
+    for row in conn
+        .prepare(
+            "SELECT substr(created_at, 1, 10) AS date, COUNT(*) FROM agent_runs
+             WHERE date BETWEEN ?1 AND ?2 GROUP BY date",
+        )?
+        .query_map(range, |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?
+    {
+        let (date, count) = row?;
+        if let Some(&i) = index.get(&date) {
+            days[i].agent_runs = count as u64;
         }
-
-        // Update project stats
-        let project_stat =
-            project_stats
-                .entry(entry.project_path.clone())
-                .or_insert(ProjectUsage {
-                    project_path: entry.project_path.clone(),
-                    project_name: entry
-                        .project_path
-                        .split('/')
-                        .last()
-                        .unwrap_or(&entry.project_path)
-                        .to_string(),
-                    total_cost: 0.0,
-                    total_tokens: 0,
-                    session_count: 0,
-                    last_used: entry.timestamp.clone(),
-                });
-        project_stat.total_cost += entry.cost;
-        project_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        project_stat.session_count += 1;
-        if entry.timestamp > project_stat.last_used {
-            project_stat.last_used = entry.timestamp.clone();
+    }
+    for row in conn
+        .prepare(
+            "SELECT date, SUM(cost) FROM usage_messages WHERE date BETWEEN ?1 AND ?2 GROUP BY date",
+        )?
+        .query_map(range, |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+        })?
+    {
+        let (date, cost) = row?;
+        if let Some(&i) = index.get(&date) {
+            days[i].cost = cost;
         }
     }
 
-    let total_tokens = total_input_tokens
-        + total_output_tokens
-        + total_cache_creation_tokens
-        + total_cache_read_tokens;
-    let total_sessions = filtered_entries.len() as u64;
+    Ok(days)
+}
 
-    // Convert hashmaps to sorted vectors
-    let mut by_model: Vec<ModelUsage> = model_stats.into_values().collect();
-    by_model.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
+fn usage_entry(row: &Row) -> rusqlite::Result<UsageEntry> {
+    Ok(UsageEntry {
+        timestamp: row.get(0)?,
+        model: row.get(1)?,
+        input_tokens: row.get::<_, i64>(2)? as u64,
+        output_tokens: row.get::<_, i64>(3)? as u64,
+        cache_creation_tokens: row.get::<_, i64>(4)? as u64,
+        cache_creation_1h_tokens: row.get::<_, i64>(5)? as u64,
+        cache_read_tokens: row.get::<_, i64>(6)? as u64,
+        cost: row.get(7)?,
+        session_id: row.get(8)?,
+        project_path: row.get(9)?,
+    })
+}
 
-    let mut by_date: Vec<DailyUsage> = daily_stats.into_values().collect();
-    by_date.sort_by(|a, b| b.date.cmp(&a.date));
+fn parse_date(date: &str, label: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").or_else(|_| {
+        // Try parsing ISO datetime format
+        DateTime::parse_from_rfc3339(date)
+            .map(|dt| dt.naive_local().date())
+            .map_err(|e| format!("Invalid {} date: {}", label, e))
+    })
+}
 
-    let mut by_project: Vec<ProjectUsage> = project_stats.into_values().collect();
-    by_project.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
+#[command]
+pub async fn get_usage_stats(
+    db: State<'_, AgentDb>,
+    days: Option<u32>,
+    agent_id: Option<i64>,
+) -> Result<UsageStats, String> {
+    let conn = indexed_db(&db).await?;
 
-    Ok(UsageStats {
-        total_cost,
-        total_tokens,
-        total_input_tokens,
-        total_output_tokens,
-        total_cache_creation_tokens,
-        total_cache_read_tokens,
-        total_sessions,
-        by_model,
-        by_date,
-        by_project,
-    })
+    // Filter by days if specified
+    let start = days.map(|days| {
+        let cutoff = Local::now().naive_local().date() - chrono::Duration::days(days as i64);
+        cutoff.format("%Y-%m-%d").to_string()
+    });
+    usage_stats(&conn, start, None, agent_id).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_usage_by_date_range(
+    db: State<'_, AgentDb>,
+    start_date: String,
+    end_date: String,
+    agent_id: Option<i64>,
+) -> Result<UsageStats, String> {
+    let start = parse_date(&start_date, "start")?;
+    let end = parse_date(&end_date, "end")?;
+
+    let conn = indexed_db(&db).await?;
+    usage_stats(
+        &conn,
+        Some(start.format("%Y-%m-%d").to_string()),
+        Some(end.format("%Y-%m-%d").to_string()),
+        agent_id,
+    )
+    .map_err(|e| e.to_string())
 }
 
 #[command]
-pub fn get_usage_details(
+pub async fn get_usage_details(
+    db: State<'_, AgentDb>,
     project_path: Option<String>,
     date: Option<String>,
 ) -> Result<Vec<UsageEntry>, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
-
-    let mut all_entries = get_all_usage_entries(&claude_path);
-
-    // Filter by project if specified
-    if let Some(project) = project_path {
-        all_entries.retain(|e| e.project_path == project);
-    }
-
-    // Filter by date if specified
-    if let Some(date) = date {
-        all_entries.retain(|e| e.timestamp.starts_with(&date));
-    }
-
-    Ok(all_entries)
+    let conn = indexed_db(&db).await?;
+
+    // Filter by project and by date prefix if specified
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, model, input_tokens, output_tokens, cache_creation_tokens, cache_creation_1h_tokens,
+                cache_read_tokens, cost, session_id, project_path
+             FROM usage_messages
+             WHERE (?1 IS NULL OR project_path = ?1)
+               AND (?2 IS NULL OR substr(date, 1, length(?2)) = ?2)
+             ORDER BY timestamp",
+        )
+        .map_err(|e| e.to_string())?;
+    let entries = stmt
+        .query_map(params![project_path, date], usage_entry)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(entries)
 }
 
 #[command]
-pub fn get_session_stats(
+pub async fn get_session_stats(
+    db: State<'_, AgentDb>,
     since: Option<String>,
     until: Option<String>,
     order: Option<String>,
 ) -> Result<Vec<ProjectUsage>, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
-
-    let all_entries = get_all_usage_entries(&claude_path);
-
-    let since_date = since.and_then(|s| NaiveDate::parse_from_str(&s, "%Y%m%d").ok());
-    let until_date = until.and_then(|s| NaiveDate::parse_from_str(&s, "%Y%m%d").ok());
-
-    let filtered_entries: Vec<_> = all_entries
-        .into_iter()
-        .filter(|e| {
-            if let Ok(dt) = DateTime::parse_from_rfc3339(&e.timestamp) {
-                let date = dt.date_naive();
-                let is_after_since = since_date.map_or(true, |s| date >= s);
-                let is_before_until = until_date.map_or(true, |u| date <= u);
-                is_after_since && is_before_until
-            } else {
-                false
-            }
+    let date = |value: Option<String>| {
+        value
+            .and_then(|s| NaiveDate::parse_from_str(&s, "%Y%m%d").ok())
+            .map(|date| date.format("%Y-%m-%d").to_string())
+    };
+    let (since, until) = (date(since), date(until));
+    // Default to descending
+    let direction = match order.as_deref() {
+        Some("asc") => "ASC",
+        _ => "DESC",
+    };
+
+    let conn = indexed_db(&db).await?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT project_path, session_id, SUM(cost), {}, COUNT(*), MAX(timestamp)
+             FROM usage_messages WHERE {}
+             GROUP BY project_path, session_id ORDER BY MAX(timestamp) {}",
+            TOKEN_SUMS_SQL, DATE_RANGE_SQL, direction
+        ))
+        .map_err(|e| e.to_string())?;
+    let by_session = stmt
+        .query_map(params![since, until], |row| {
+            // Using session_id as project_name for session view; session_count
+            // then counts entries per session
+            project_usage(row.get(1)?, row.get(0)?, row, 2)
         })
-        .collect();
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(by_session)
+}
 
-    let mut session_stats: HashMap<String, ProjectUsage> = HashMap::new();
-    for entry in &filtered_entries {
-        let session_key = format!("{}/{}", entry.project_path, entry.session_id);
-        let project_stat = session_stats
-            .entry(session_key)
-            .or_insert_with(|| ProjectUsage {
-                project_path: entry.project_path.clone(),
-                project_name: entry.session_id.clone(), // Using session_id as project_name for session view
-                total_cost: 0.0,
-                total_tokens: 0,
-                session_count: 0, // In this context, this will count entries per session
-                last_used: " ".to_string(),
-            });
-
-        project_stat.total_cost += entry.cost;
-        project_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        project_stat.session_count += 1;
-        if entry.timestamp > project_stat.last_used {
-            project_stat.last_used = entry.timestamp.clone();
-        }
+#[command]
+pub async fn get_agent_costs(db: State<'_, AgentDb>) -> Result<Vec<AgentCost>, String> {
+    let conn = indexed_db(&db).await?;
+    agent_costs(&conn).map_err(|e| e.to_string())
+}
+
+/// Compare cost, tokens, turns and agent run success rates per model
+#[command]
+pub async fn get_model_comparison(
+    db: State<'_, AgentDb>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<Vec<ModelComparison>, String> {
+    let start = start_date
+        .map(|date| parse_date(&date, "start"))
+        .transpose()?
+        .map(|date| date.format("%Y-%m-%d").to_string());
+    let end = end_date
+        .map(|date| parse_date(&date, "end"))
+        .transpose()?
+        .map(|date| date.format("%Y-%m-%d").to_string());
+
+    let conn = indexed_db(&db).await?;
+    model_comparison(&conn, start, end).map_err(|e| e.to_string())
+}
+
+/// Sessions started, agent runs and cost for each day of the past year, for
+/// the dashboard's activity heatmap
+#[command]
+pub async fn get_activity_heatmap(db: State<'_, AgentDb>) -> Result<Vec<ActivityDay>, String> {
+    let end = Local::now().naive_local().date();
+    let start = end - chrono::Duration::days(364);
+    let conn = indexed_db(&db).await?;
+    activity_heatmap(&conn, start, end).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usage_index::{create_usage_tables, insert_test_usage};
+
+    #[test]
+    fn test_cache_tokens_are_priced_separately() {
+        let usage = TokenUsage::from_json(&serde_json::json!({
+            "input_tokens": 1_000_000,
+            "output_tokens": 1_000_000,
+            "cache_creation_input_tokens": 3_000_000,
+            "cache_creation": {
+                "ephemeral_5m_input_tokens": 2_000_000,
+                "ephemeral_1h_input_tokens": 1_000_000
+            },
+            "cache_read_input_tokens": 10_000_000
+        }));
+        assert_eq!(usage.cache_creation_tokens, 3_000_000);
+        assert_eq!(usage.cache_creation_1h_tokens, 1_000_000);
+
+        // 3 input + 15 output + 2 * 3.75 + 1 * 6 cache writes + 10 * 0.3 cache reads
+        let cost = token_cost("claude-sonnet-4-20250514", &usage);
+        assert!((cost - 34.5).abs() < 1e-9);
+        let cost = token_cost("claude-opus-4-1-20250805", &usage);
+        assert!((cost - 172.5).abs() < 1e-9);
+        assert_eq!(
+            model_pricing("claude-opus-4-5-20251101").map(|p| p.input),
+            Some(5.0)
+        );
+        assert_eq!(
+            model_pricing("claude-3-5-haiku-20241022").map(|p| p.output),
+            Some(4.0)
+        );
+        assert_eq!(token_cost("gpt-4o", &usage), 0.0);
     }
 
-    let mut by_session: Vec<ProjectUsage> = session_stats.into_values().collect();
+    #[test]
+    fn test_agent_cost_attribution() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agents (id INTEGER PRIMARY KEY, name TEXT NOT NULL, icon TEXT NOT NULL);
+             CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, agent_id INTEGER NOT NULL, agent_name TEXT NOT NULL,
+                 agent_icon TEXT NOT NULL, session_id TEXT NOT NULL, total_tokens INTEGER, cost_usd REAL);
+             INSERT INTO agents VALUES (1, 'Reviewer', 'bot');
+             INSERT INTO agent_runs VALUES (1, 1, 'Reviewer', 'bot', 's1', NULL, NULL);
+             -- The transcript of this run is gone; its recorded cost is used
+             INSERT INTO agent_runs VALUES (2, 1, 'Reviewer', 'bot', 's2', 500, 1.0);
+             INSERT INTO agent_runs VALUES (3, 2, 'Deleted', 'code', 's3', NULL, NULL);",
+        )
+        .unwrap();
+        create_usage_tables(&conn).unwrap();
+        insert_test_usage(
+            &conn,
+            &[
+                ("a", "2025-06-01T10:00:00Z", "m", 100, 50, 2.0, "s1", "/p"),
+                ("b", "2025-06-01T10:01:00Z", "m", 100, 50, 3.0, "s1", "/p"),
+                ("c", "2025-06-01T10:02:00Z", "m", 10, 5, 0.5, "s3", "/p"),
+                ("d", "2025-06-01T10:03:00Z", "m", 10, 5, 7.0, "manual", "/p"),
+            ],
+        );
+
+        let costs = agent_costs(&conn).unwrap();
+        assert_eq!(costs.len(), 2);
+        assert_eq!(
+            (
+                costs[0].agent_name.as_str(),
+                costs[0].run_count,
+                costs[0].total_tokens
+            ),
+            ("Reviewer", 2, 800)
+        );
+        assert!((costs[0].total_cost - 6.0).abs() < 1e-9);
+        assert!((costs[0].average_cost - 3.0).abs() < 1e-9);
+        assert_eq!(costs[1].agent_name, "Deleted");
+
+        let stats = usage_stats(&conn, None, None, Some(1)).unwrap();
+        assert!((stats.total_cost - 5.0).abs() < 1e-9);
+        let stats = usage_stats(&conn, None, None, None).unwrap();
+        assert!((stats.total_cost - 12.5).abs() < 1e-9);
+    }
 
-    // Sort by last_used date
-    if let Some(order_str) = order {
-        if order_str == "asc" {
-            by_session.sort_by(|a, b| a.last_used.cmp(&b.last_used));
-        } else {
-            by_session.sort_by(|a, b| b.last_used.cmp(&a.last_used));
-        }
-    } else {
-        // Default to descending
-        by_session.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+    #[test]
+    fn test_usage_stats_include_rollups() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, agent_id INTEGER NOT NULL, session_id TEXT NOT NULL);
+             INSERT INTO agent_runs VALUES (1, 1, 's1');",
+        )
+        .unwrap();
+        create_usage_tables(&conn).unwrap();
+        insert_test_usage(
+            &conn,
+            &[("a", "2025-06-01T10:00:00Z", "opus", 10, 20, 1.0, "s1", "/p")],
+        );
+        conn.execute(
+            "INSERT INTO usage_monthly_rollups (month, project_path, model, input_tokens, output_tokens,
+                cache_creation_tokens, cache_read_tokens, cost, message_count, session_count)
+             VALUES ('2025-01', '/p', 'opus', 100, 200, 0, 0, 5.0, 4, 2)",
+            [],
+        )
+        .unwrap();
+
+        let stats = usage_stats(&conn, None, None, None).unwrap();
+        assert!((stats.total_cost - 6.0).abs() < 1e-9);
+        assert_eq!((stats.total_tokens, stats.total_sessions), (330, 5));
+        assert_eq!(stats.by_model[0].session_count, 5);
+        assert_eq!(stats.by_date.len(), 1);
+        // Rolled up months are dated to their first day
+        let stats = usage_stats(&conn, Some("2025-01-02".to_string()), None, None).unwrap();
+        assert!((stats.total_cost - 1.0).abs() < 1e-9);
+        // and have no sessions to attribute to agents
+        let stats = usage_stats(&conn, None, None, Some(1)).unwrap();
+        assert!((stats.total_cost - 1.0).abs() < 1e-9);
+
+        let models = model_comparison(&conn, None, None).unwrap();
+        assert_eq!(models[0].session_count, 3);
     }
 
-    Ok(by_session)
+    #[test]
+    fn test_model_comparison() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, session_id TEXT NOT NULL, status TEXT NOT NULL,
+                 created_at TEXT NOT NULL);
+             INSERT INTO agent_runs VALUES (1, 's1', 'completed', '2025-06-01 10:00:00');
+             INSERT INTO agent_runs VALUES (2, 's2', 'failed', '2025-06-01 11:00:00');
+             INSERT INTO agent_runs VALUES (3, 's3', 'completed', '2025-06-02 10:00:00');
+             INSERT INTO agent_runs VALUES (4, 's4', 'completed', '2025-05-01 10:00:00');",
+        )
+        .unwrap();
+        create_usage_tables(&conn).unwrap();
+        insert_test_usage(
+            &conn,
+            &[
+                (
+                    "a",
+                    "2025-06-01T10:00:00Z",
+                    "opus",
+                    10,
+                    100,
+                    4.0,
+                    "s1",
+                    "/p",
+                ),
+                (
+                    "b",
+                    "2025-06-01T10:01:00Z",
+                    "opus",
+                    10,
+                    100,
+                    4.0,
+                    "s1",
+                    "/p",
+                ),
+                // A session that mostly used opus counts towards it
+                ("c", "2025-06-01T10:02:00Z", "haiku", 10, 5, 0.1, "s1", "/p"),
+                (
+                    "d",
+                    "2025-06-01T11:00:00Z",
+                    "opus",
+                    10,
+                    100,
+                    4.0,
+                    "s2",
+                    "/p",
+                ),
+                (
+                    "e",
+                    "2025-06-02T10:00:00Z",
+                    "sonnet",
+                    10,
+                    100,
+                    1.0,
+                    "s3",
+                    "/p",
+                ),
+                (
+                    "f",
+                    "2025-05-01T10:00:00Z",
+                    "sonnet",
+                    10,
+                    100,
+                    1.0,
+                    "s4",
+                    "/p",
+                ),
+            ],
+        );
+
+        let models = model_comparison(&conn, Some("2025-06-01".to_string()), None).unwrap();
+        assert_eq!(models.len(), 3);
+        let opus = &models[0];
+        assert_eq!(opus.model, "opus");
+        assert_eq!((opus.session_count, opus.total_tokens), (2, 330));
+        assert!((opus.average_turns - 1.5).abs() < 1e-9);
+        assert!((opus.average_session_cost - 6.0).abs() < 1e-9);
+        assert_eq!(
+            (opus.run_count, opus.completed_runs, opus.failed_runs),
+            (2, 1, 1)
+        );
+        assert_eq!(opus.success_rate, Some(0.5));
+        assert_eq!(
+            (models[1].model.as_str(), models[1].run_count),
+            ("sonnet", 1)
+        );
+        assert_eq!(models[1].success_rate, Some(1.0));
+        assert_eq!(
+            (models[2].model.as_str(), models[2].run_count),
+            ("haiku", 0)
+        );
+        assert_eq!(models[2].success_rate, None);
+    }
+
+    #[test]
+    fn test_activity_heatmap() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, created_at TEXT NOT NULL);
+             INSERT INTO agent_runs VALUES (1, '2025-06-01 10:00:00');
+             INSERT INTO agent_runs VALUES (2, '2025-06-03 09:00:00');
+             INSERT INTO agent_runs VALUES (3, '2025-06-09 09:00:00');",
+        )
+        .unwrap();
+        create_usage_tables(&conn).unwrap();
+        insert_test_usage(
+            &conn,
+            &[
+                // Started before the range, so only its cost counts
+                ("a", "2025-05-31T12:00:00Z", "m", 0, 0, 1.0, "s1", "/p"),
+                ("b", "2025-06-01T12:00:00Z", "m", 0, 0, 2.0, "s1", "/p"),
+                ("c", "2025-06-01T12:00:00Z", "m", 0, 0, 0.5, "s2", "/p"),
+                ("d", "2025-06-03T12:00:00Z", "m", 0, 0, 0.25, "s2", "/p"),
+                ("e", "2025-06-03T12:00:00Z", "m", 0, 0, 1.0, "s3", "/p"),
+            ],
+        );
+
+        let day = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        let days = activity_heatmap(&conn, day("2025-06-01"), day("2025-06-03")).unwrap();
+        assert_eq!(
+            days,
+            [
+                ActivityDay {
+                    date: "2025-06-01".to_string(),
+                    sessions_started: 1,
+                    agent_runs: 1,
+                    cost: 2.5,
+                },
+                ActivityDay {
+                    date: "2025-06-02".to_string(),
+                    ..Default::default()
+                },
+                ActivityDay {
+                    date: "2025-06-03".to_string(),
+                    sessions_started: 1,
+                    agent_runs: 1,
+                    cost: 1.25,
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,370 @@
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::usage_index::{indexed_db, local_date};
+use crate::commands::usage_retention::rolled_up_before;
+
+/// Version of the usage export format, bumped on incompatible changes
+const EXPORT_VERSION: u32 = 1;
+
+/// Prefix of the `path` of imported usage entries, followed by the machine
+/// they came from. No transcript has such a path, so the indexer never drops
+/// them, and rows are keyed by id and path, so a message indexed locally
+/// after it was imported keeps a row of its own when the import is removed.
+pub const IMPORT_PATH_PREFIX: &str = "import:";
+
+/// Longest machine name accepted
+const MAX_MACHINE_NAME_LEN: usize = 64;
+
+/// The token usage of one Claude message, keyed by the id it is indexed under
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExportedUsageEntry {
+    /// `message id:request id`, so copies of a message on several machines are counted once
+    pub id: String,
+    pub timestamp: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_1h_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cost: f64,
+    pub session_id: String,
+    pub project_path: String,
+}
+
+/// The usage indexed on one machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageExport {
+    pub version: u32,
+    pub exported_at: String,
+    pub machine: String,
+    pub entries: Vec<ExportedUsageEntry>,
+}
+
+/// Outcome of importing a usage export
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageImportSummary {
+    pub machine: String,
+    pub imported: usize,
+    /// Entries already indexed locally or imported before, or rolled up
+    pub duplicates: usize,
+}
+
+/// Usage imported from another machine
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageImport {
+    pub machine: String,
+    pub entry_count: u64,
+    pub total_cost: f64,
+    pub first_date: String,
+    pub last_date: String,
+}
+
+/// Name of this machine, used to label its exports
+fn machine_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| {
+            std::fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|name| name.trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Machine names label imports and are part of their rows' paths
+fn validate_machine_name(machine: &str) -> Result<(), String> {
+    let valid = !machine.is_empty()
+        && machine.len() <= MAX_MACHINE_NAME_LEN
+        && machine
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid machine name '{}': use up to {} letters, digits, dots, dashes and underscores",
+            machine, MAX_MACHINE_NAME_LEN
+        ))
+    }
+}
+
+/// The usage indexed from this machine's transcripts; imported usage is left
+/// out so it isn't passed around between machines
+fn local_entries(conn: &Connection) -> Result<Vec<ExportedUsageEntry>, rusqlite::Error> {
+    conn.prepare(
+        "SELECT id, timestamp, model, input_tokens, output_tokens, cache_creation_tokens,
+            cache_creation_1h_tokens, cache_read_tokens, cost, session_id, project_path
+         FROM usage_entries WHERE substr(path, 1, length(?1)) != ?1 GROUP BY id ORDER BY timestamp",
+    )?
+    .query_map(params![IMPORT_PATH_PREFIX], |row| {
+        Ok(ExportedUsageEntry {
+            id: row.get(0)?,
+            timestamp: row.get(1)?,
+            model: row.get(2)?,
+            input_tokens: row.get::<_, i64>(3)? as u64,
+            output_tokens: row.get::<_, i64>(4)? as u64,
+            cache_creation_tokens: row.get::<_, i64>(5)? as u64,
+            cache_creation_1h_tokens: row.get::<_, i64>(6)? as u64,
+            cache_read_tokens: row.get::<_, i64>(7)? as u64,
+            cost: row.get(8)?,
+            session_id: row.get(9)?,
+            project_path: row.get(10)?,
+        })
+    })?
+    .collect()
+}
+
+/// Add the entries of an export to the index. Entries whose id is already
+/// indexed, from a local transcript or an earlier import, are skipped, as are
+/// entries older than the rolled up usage.
+pub fn import_entries(
+    conn: &mut Connection,
+    export: &UsageExport,
+) -> Result<UsageImportSummary, String> {
+    validate_machine_name(&export.machine)?;
+    let path = format!("{}{}", IMPORT_PATH_PREFIX, export.machine);
+    let rolled_up_before = rolled_up_before(conn);
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut imported = 0;
+    for entry in &export.entries {
+        let date = local_date(&entry.timestamp);
+        // Detail past the retention period isn't kept
+        if rolled_up_before
+            .as_deref()
+            .is_some_and(|before| date.as_str() < before)
+        {
+            continue;
+        }
+        imported += tx
+            .execute(
+                "INSERT INTO usage_entries (id, path, timestamp, date, model, input_tokens, output_tokens,
+                    cache_creation_tokens, cache_creation_1h_tokens, cache_read_tokens, cost, session_id, project_path,
+                    base_cost)
+                 SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?11
+                 WHERE NOT EXISTS (SELECT 1 FROM usage_entries WHERE id = ?1)",
+                params![
+                    entry.id,
+                    path,
+                    entry.timestamp,
+                    date,
+                    entry.model,
+                    entry.input_tokens as i64,
+                    entry.output_tokens as i64,
+                    entry.cache_creation_tokens as i64,
+                    entry.cache_creation_1h_tokens as i64,
+                    entry.cache_read_tokens as i64,
+                    entry.cost,
+                    entry.session_id,
+                    entry.project_path,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(UsageImportSummary {
+        machine: export.machine.clone(),
+        imported,
+        duplicates: export.entries.len() - imported,
+    })
+}
+
+/// Write the usage indexed on this machine to a file for import elsewhere
+#[tauri::command]
+pub async fn export_usage(
+    db: State<'_, AgentDb>,
+    file_path: String,
+    machine: Option<String>,
+) -> Result<usize, String> {
+    let machine = machine
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(machine_name);
+    validate_machine_name(&machine)?;
+    let entries = {
+        let conn = indexed_db(&db).await?;
+        local_entries(&conn).map_err(|e| e.to_string())?
+    };
+    let export = UsageExport {
+        version: EXPORT_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        machine,
+        entries,
+    };
+    let json = serde_json::to_string(&export).map_err(|e| e.to_string())?;
+    std::fs::write(&file_path, json).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(export.entries.len())
+}
+
+/// Merge a usage export from another machine into the dashboard
+#[tauri::command]
+pub async fn import_usage(
+    db: State<'_, AgentDb>,
+    file_path: String,
+) -> Result<UsageImportSummary, String> {
+    let json =
+        std::fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let export: UsageExport =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid usage export: {}", e))?;
+    if export.version > EXPORT_VERSION {
+        return Err(format!(
+            "Usage export version {} is newer than supported version {}",
+            export.version, EXPORT_VERSION
+        ));
+    }
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let summary = import_entries(&mut conn, &export)?;
+    info!(
+        "Imported {} usage entries from {} ({} already known)",
+        summary.imported, summary.machine, summary.duplicates
+    );
+    Ok(summary)
+}
+
+/// Machines whose usage has been imported
+#[tauri::command]
+pub async fn list_usage_imports(db: State<'_, AgentDb>) -> Result<Vec<UsageImport>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT substr(path, length(?1) + 1), COUNT(*), SUM(cost), MIN(date), MAX(date)
+             FROM usage_entries WHERE substr(path, 1, length(?1)) = ?1
+             GROUP BY path ORDER BY path",
+        )
+        .map_err(|e| e.to_string())?;
+    let imports = stmt
+        .query_map(params![IMPORT_PATH_PREFIX], |row| {
+            Ok(UsageImport {
+                machine: row.get(0)?,
+                entry_count: row.get::<_, i64>(1)? as u64,
+                total_cost: row.get(2)?,
+                first_date: row.get(3)?,
+                last_date: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(imports)
+}
+
+/// Drop the usage imported from a machine. Returns the number of entries removed.
+fn remove_import(conn: &Connection, machine: &str) -> Result<usize, String> {
+    validate_machine_name(machine)?;
+    conn.execute(
+        "DELETE FROM usage_entries WHERE path = ?1",
+        params![format!("{}{}", IMPORT_PATH_PREFIX, machine)],
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Drop the usage imported from a machine
+#[tauri::command]
+pub async fn remove_usage_import(db: State<'_, AgentDb>, machine: String) -> Result<usize, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    remove_import(&conn, &machine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usage_index::{create_usage_tables, insert_test_usage};
+
+    fn entry(id: &str, cost: f64) -> ExportedUsageEntry {
+        ExportedUsageEntry {
+            id: id.to_string(),
+            timestamp: "2025-06-01T10:00:00Z".to_string(),
+            model: "claude-sonnet-4".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_creation_tokens: 0,
+            cache_creation_1h_tokens: 0,
+            cache_read_tokens: 0,
+            cost,
+            session_id: "s1".to_string(),
+            project_path: "/work/app".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_import_deduplicates_entries() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_usage_tables(&conn).unwrap();
+        insert_test_usage(
+            &conn,
+            &[(
+                "m1:r1",
+                "2025-06-01T09:00:00Z",
+                "claude-sonnet-4",
+                100,
+                50,
+                1.0,
+                "s1",
+                "/work/app",
+            )],
+        );
+
+        let export = UsageExport {
+            version: EXPORT_VERSION,
+            exported_at: "2025-06-02T00:00:00Z".to_string(),
+            machine: "laptop".to_string(),
+            // A session synced between machines repeats m1:r1
+            entries: vec![
+                entry("m1:r1", 1.0),
+                entry("m2:r2", 2.0),
+                entry("m3:r3", 3.0),
+            ],
+        };
+        let summary = import_entries(&mut conn, &export).unwrap();
+        assert_eq!((summary.imported, summary.duplicates), (2, 1));
+        let summary = import_entries(&mut conn, &export).unwrap();
+        assert_eq!((summary.imported, summary.duplicates), (0, 3));
+
+        let total: f64 = conn
+            .query_row("SELECT SUM(cost) FROM usage_entries", [], |row| row.get(0))
+            .unwrap();
+        assert!((total - 6.0).abs() < 1e-9);
+        // Imported usage isn't exported again
+        let local = local_entries(&conn).unwrap();
+        assert_eq!(local.len(), 1);
+        assert_eq!(local[0].id, "m1:r1");
+
+        // A message of the import that turns up locally is counted once, and
+        // still counted once the import is removed
+        insert_test_usage(
+            &conn,
+            &[(
+                "m2:r2",
+                "2025-06-01T10:00:00Z",
+                "claude-sonnet-4",
+                100,
+                50,
+                2.0,
+                "s1",
+                "/work/app",
+            )],
+        );
+        let messages = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM usage_messages", [], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(messages(&conn), 3);
+        assert_eq!(remove_import(&conn, "laptop").unwrap(), 2);
+        assert_eq!(messages(&conn), 2);
+        assert!(remove_import(&conn, "").is_err());
+
+        let mut export = export;
+        export.machine = "../laptop".to_string();
+        assert!(import_entries(&mut conn, &export).is_err());
+    }
+}
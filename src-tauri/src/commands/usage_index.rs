@@ -0,0 +1,609 @@
+use chrono::{DateTime, Local};
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::MutexGuard;
+use std::time::Duration;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::usage::{builtin_pricing, custom_pricing, TokenUsage};
+use crate::commands::usage_imports::IMPORT_PATH_PREFIX;
+use crate::commands::usage_retention::{apply_usage_retention, rolled_up_before};
+
+/// Interval between background passes of the usage indexer
+const USAGE_INDEX_INTERVAL_SECS: u64 = 60;
+
+/// How long work on its own connection waits for other writers to the database
+const USAGE_BUSY_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Deserialize)]
+struct JsonlEntry {
+    timestamp: String,
+    message: Option<MessageData>,
+    #[serde(rename = "sessionId")]
+    session_id: Option<String>,
+    #[serde(rename = "requestId")]
+    request_id: Option<String>,
+    #[serde(rename = "costUSD")]
+    cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageData {
+    id: Option<String>,
+    model: Option<String>,
+    usage: Option<serde_json::Value>,
+}
+
+/// Token usage recorded by one transcript line
+#[derive(Debug, Clone, PartialEq)]
+struct LineUsage {
+    /// `message id:request id`, shared by the copies of a message that
+    /// resumed sessions repeat
+    message_key: Option<String>,
+    timestamp: String,
+    model: String,
+    tokens: TokenUsage,
+    cost: f64,
+    /// What the usage costs without the user's prices
+    base_cost: f64,
+    session_id: Option<String>,
+}
+
+/// How far a transcript has been indexed
+struct IndexedFile {
+    indexed_bytes: u64,
+    project_path: Option<String>,
+}
+
+fn parse_usage_line(json: serde_json::Value) -> Option<LineUsage> {
+    let entry = serde_json::from_value::<JsonlEntry>(json).ok()?;
+    let message = entry.message?;
+    let tokens = TokenUsage::from_json(&message.usage?);
+    // Skip entries without meaningful token usage
+    if tokens.is_empty() {
+        return None;
+    }
+
+    // The cost Claude recorded, or an estimate at Anthropic's prices when it
+    // recorded none; the user's prices take precedence over both
+    let base_cost = entry.cost_usd.unwrap_or_else(|| {
+        message
+            .model
+            .as_deref()
+            .and_then(builtin_pricing)
+            .map_or(0.0, |pricing| pricing.cost(&tokens))
+    });
+    let cost = message
+        .model
+        .as_deref()
+        .and_then(custom_pricing)
+        .map_or(base_cost, |pricing| pricing.cost(&tokens));
+    Some(LineUsage {
+        message_key: message
+            .id
+            .zip(entry.request_id)
+            .map(|(message_id, request_id)| format!("{}:{}", message_id, request_id)),
+        timestamp: entry.timestamp,
+        model: message.model.unwrap_or_else(|| "unknown".to_string()),
+        tokens,
+        cost,
+        base_cost,
+        session_id: entry.session_id,
+    })
+}
+
+/// The local day of a transcript timestamp, which usage is grouped by
+pub fn local_date(timestamp: &str) -> String {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|_| timestamp.split('T').next().unwrap_or(timestamp).to_string())
+}
+
+/// Create the tables of the usage index and its monthly rollups. Shared by
+/// the app's database and the tests of everything that queries usage.
+pub fn create_usage_tables(conn: &Connection) -> rusqlite::Result<()> {
+    // Create usage_index_files table tracking how far each transcript's usage is indexed
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_index_files (
+            path TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            project_path TEXT,
+            indexed_bytes INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Usage indexed before cache writes were split by TTL was priced at the
+    // 5 minute rate; index it again from scratch
+    if conn
+        .execute(
+            "ALTER TABLE usage_entries ADD COLUMN cache_creation_1h_tokens INTEGER NOT NULL DEFAULT 0",
+            [],
+        )
+        .is_ok()
+    {
+        conn.execute("DELETE FROM usage_entries", [])?;
+        conn.execute("DELETE FROM usage_index_files", [])?;
+    }
+    // Usage indexed when rows were keyed by message id alone kept a single
+    // copy of each message and was dated in UTC
+    conn.execute("DROP VIEW IF EXISTS usage_with_rollups", [])?;
+    conn.execute("DROP VIEW IF EXISTS usage_messages", [])?;
+    let keyed_by_id = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('usage_entries') WHERE pk > 0",
+        [],
+        |row| row.get::<_, i64>(0),
+    )? == 1;
+    if keyed_by_id {
+        conn.execute_batch(
+            "ALTER TABLE usage_entries RENAME TO usage_entries_by_id;
+             DROP INDEX IF EXISTS idx_usage_entries_date;
+             DROP INDEX IF EXISTS idx_usage_entries_path;",
+        )?;
+    }
+
+    // Create usage_entries table with the token usage of every Claude message
+    // in every transcript. A message repeated by resumed sessions has a row
+    // per transcript, so its usage survives the original being deleted.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_entries (
+            id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            date TEXT NOT NULL,
+            model TEXT NOT NULL,
+            input_tokens INTEGER NOT NULL,
+            output_tokens INTEGER NOT NULL,
+            cache_creation_tokens INTEGER NOT NULL,
+            cache_creation_1h_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_tokens INTEGER NOT NULL,
+            cost REAL NOT NULL,
+            session_id TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            base_cost REAL NOT NULL DEFAULT 0,
+            PRIMARY KEY (id, path)
+        )",
+        [],
+    )?;
+    if keyed_by_id {
+        // Keep imported usage, redated to the local day, and index the
+        // transcripts again
+        conn.execute(
+            "INSERT INTO usage_entries (id, path, timestamp, date, model, input_tokens, output_tokens,
+                 cache_creation_tokens, cache_creation_1h_tokens, cache_read_tokens, cost, session_id, project_path,
+                 base_cost)
+             SELECT id, path, timestamp, date(timestamp, 'localtime'), model, input_tokens, output_tokens,
+                 cache_creation_tokens, cache_creation_1h_tokens, cache_read_tokens, cost, session_id, project_path,
+                 cost
+             FROM usage_entries_by_id WHERE substr(path, 1, length(?1)) = ?1",
+            params![IMPORT_PATH_PREFIX],
+        )?;
+        conn.execute("DROP TABLE usage_entries_by_id", [])?;
+        conn.execute("DELETE FROM usage_index_files", [])?;
+    }
+    // Usage indexed before its cost without custom prices was kept can't
+    // revert to it when a price is removed; index the transcripts again and
+    // take imported costs as they are
+    if conn
+        .execute(
+            "ALTER TABLE usage_entries ADD COLUMN base_cost REAL NOT NULL DEFAULT 0",
+            [],
+        )
+        .is_ok()
+    {
+        conn.execute(
+            "DELETE FROM usage_entries WHERE substr(path, 1, length(?1)) != ?1",
+            params![IMPORT_PATH_PREFIX],
+        )?;
+        conn.execute("UPDATE usage_entries SET base_cost = cost", [])?;
+        conn.execute("DELETE FROM usage_index_files", [])?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_entries_date ON usage_entries(date)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_entries_path ON usage_entries(path)",
+        [],
+    )?;
+    // Each message counted once, from whichever transcript indexed it first;
+    // everything that sums usage reads this instead of usage_entries
+    conn.execute(
+        "CREATE VIEW usage_messages AS
+         SELECT * FROM usage_entries
+         WHERE rowid IN (SELECT MIN(rowid) FROM usage_entries GROUP BY id)",
+        [],
+    )?;
+
+    // Create usage_monthly_rollups table with the totals of usage past the retention period
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_monthly_rollups (
+            month TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            model TEXT NOT NULL,
+            input_tokens INTEGER NOT NULL,
+            output_tokens INTEGER NOT NULL,
+            cache_creation_tokens INTEGER NOT NULL,
+            cache_read_tokens INTEGER NOT NULL,
+            cost REAL NOT NULL,
+            message_count INTEGER NOT NULL,
+            session_count INTEGER NOT NULL,
+            cache_creation_1h_tokens INTEGER NOT NULL DEFAULT 0,
+            base_cost REAL NOT NULL DEFAULT 0,
+            PRIMARY KEY (month, project_path, model)
+        )",
+        [],
+    )?;
+    // Months rolled up before the cost without custom prices was kept take
+    // their cost as it is
+    if conn
+        .execute(
+            "ALTER TABLE usage_monthly_rollups ADD COLUMN base_cost REAL NOT NULL DEFAULT 0",
+            [],
+        )
+        .is_ok()
+    {
+        conn.execute("UPDATE usage_monthly_rollups SET base_cost = cost", [])?;
+    }
+    let _ = conn.execute(
+        "ALTER TABLE usage_monthly_rollups ADD COLUMN cache_creation_1h_tokens INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    // Each message and each rolled up month, dated to its first day, for
+    // totals that span the retention period. Rolled up usage has no session.
+    conn.execute(
+        "CREATE VIEW usage_with_rollups AS
+         SELECT date, timestamp, model, project_path, session_id, input_tokens, output_tokens,
+             cache_creation_tokens, cache_read_tokens, cost, 1 AS message_count, 0 AS rolled_up_sessions
+         FROM usage_messages
+         UNION ALL
+         SELECT month || '-01', month || '-01', model, project_path, NULL, input_tokens, output_tokens,
+             cache_creation_tokens, cache_read_tokens, cost, message_count, session_count
+         FROM usage_monthly_rollups",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Add usage entries to a test database, given as (id, timestamp, model,
+/// input tokens, output tokens, cost, session id, project path)
+#[cfg(test)]
+pub fn insert_test_usage(
+    conn: &Connection,
+    entries: &[(&str, &str, &str, i64, i64, f64, &str, &str)],
+) {
+    for (id, timestamp, model, input, output, cost, session_id, project_path) in entries {
+        conn.execute(
+            "INSERT INTO usage_entries (id, path, timestamp, date, model, input_tokens, output_tokens,
+                cache_creation_tokens, cache_read_tokens, cost, session_id, project_path, base_cost)
+             VALUES (?1, ?2, ?3, substr(?3, 1, 10), ?4, ?5, ?6, 0, 0, ?7, ?8, ?9, ?7)",
+            params![
+                id,
+                format!("/test/{}.jsonl", session_id),
+                timestamp,
+                model,
+                input,
+                output,
+                cost,
+                session_id,
+                project_path
+            ],
+        )
+        .unwrap();
+    }
+}
+
+/// Transcripts under ~/.claude/projects as (project id, path), least
+/// recently modified first so the original of a repeated message is usually
+/// indexed before its copies
+fn usage_files(projects_dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut files = Vec::new();
+    let Ok(projects) = std::fs::read_dir(projects_dir) else {
+        return files;
+    };
+    for project in projects.flatten().filter(|e| e.path().is_dir()) {
+        let project_id = project.file_name().to_string_lossy().to_string();
+        walkdir::WalkDir::new(project.path())
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("jsonl"))
+            .for_each(|e| files.push((project_id.clone(), e.path().to_path_buf())));
+    }
+    files.sort_by_cached_key(|(_, path)| {
+        std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    });
+    files
+}
+
+/// Index the usage in the complete lines appended to a transcript since it
+/// was last indexed. A transcript that shrank was rewritten and is indexed
+/// again from the start. Usage dated before `rolled_up_before` is already
+/// counted in the monthly rollups and skipped. Returns the number of usage
+/// entries added.
+fn index_usage_file(
+    conn: &mut Connection,
+    project_id: &str,
+    path: &Path,
+    rolled_up_before: Option<&str>,
+) -> Result<usize, String> {
+    let path_str = path.to_string_lossy().to_string();
+    let size = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+
+    let previous = conn
+        .query_row(
+            "SELECT indexed_bytes, project_path FROM usage_index_files WHERE path = ?1",
+            params![path_str],
+            |row| {
+                Ok(IndexedFile {
+                    indexed_bytes: row.get::<_, i64>(0)? as u64,
+                    project_path: row.get(1)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let mut state = match previous {
+        Some(previous) if previous.indexed_bytes == size => return Ok(0),
+        Some(previous) if previous.indexed_bytes < size => previous,
+        _ => IndexedFile {
+            indexed_bytes: 0,
+            project_path: None,
+        },
+    };
+
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(state.indexed_bytes))
+        .map_err(|e| e.to_string())?;
+    let mut appended = Vec::new();
+    file.read_to_end(&mut appended).map_err(|e| e.to_string())?;
+    // A line still being written is picked up on the next pass
+    let Some(complete) = appended.iter().rposition(|b| *b == b'\n').map(|i| i + 1) else {
+        return Ok(0);
+    };
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    if state.indexed_bytes == 0 {
+        tx.execute(
+            "DELETE FROM usage_entries WHERE path = ?1",
+            params![path_str],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    let fallback_session_id = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut added = 0;
+    let mut offset = state.indexed_bytes;
+    for line in appended[..complete].split_inclusive(|b| *b == b'\n') {
+        let line_offset = offset;
+        offset += line.len() as u64;
+        let Ok(json) = serde_json::from_slice::<serde_json::Value>(line) else {
+            continue;
+        };
+        if state.project_path.is_none() {
+            state.project_path = json
+                .get("cwd")
+                .and_then(|c| c.as_str())
+                .filter(|c| !c.is_empty())
+                .map(str::to_string);
+        }
+        let Some(usage) = parse_usage_line(json) else {
+            continue;
+        };
+
+        // Entries without ids can't be repeated elsewhere; key them by position
+        let id = usage
+            .message_key
+            .clone()
+            .unwrap_or_else(|| format!("{}@{}", path_str, line_offset));
+        let date = local_date(&usage.timestamp);
+        if rolled_up_before.is_some_and(|before| date.as_str() < before) {
+            continue;
+        }
+        added += tx
+            .execute(
+                "INSERT OR IGNORE INTO usage_entries (id, path, timestamp, date, model, input_tokens, output_tokens,
+                    cache_creation_tokens, cache_creation_1h_tokens, cache_read_tokens, cost, session_id, project_path,
+                    base_cost)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![
+                    id,
+                    path_str,
+                    usage.timestamp,
+                    date,
+                    usage.model,
+                    usage.tokens.input_tokens as i64,
+                    usage.tokens.output_tokens as i64,
+                    usage.tokens.cache_creation_tokens as i64,
+                    usage.tokens.cache_creation_1h_tokens as i64,
+                    usage.tokens.cache_read_tokens as i64,
+                    usage.cost,
+                    usage.session_id.as_deref().unwrap_or(&fallback_session_id),
+                    state.project_path.as_deref().unwrap_or(project_id),
+                    usage.base_cost,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+    }
+
+    tx.execute(
+        "INSERT OR REPLACE INTO usage_index_files (path, project_id, project_path, indexed_bytes)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![path_str, project_id, state.project_path, offset as i64],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(added)
+}
+
+/// Bring the usage index up to date with the transcripts in `projects_dir`,
+/// parsing only what was appended since the last pass
+pub fn index_usage(conn: &mut Connection, projects_dir: &Path) -> usize {
+    let files = usage_files(projects_dir);
+    let rolled_up_before = rolled_up_before(conn);
+    let mut added = 0;
+    for (project_id, path) in &files {
+        match index_usage_file(conn, project_id, path, rolled_up_before.as_deref()) {
+            Ok(count) => added += count,
+            Err(e) => warn!("Failed to index usage of {}: {}", path.display(), e),
+        }
+    }
+
+    // Drop the usage of transcripts that were deleted
+    let present: HashSet<String> = files
+        .iter()
+        .map(|(_, path)| path.to_string_lossy().to_string())
+        .collect();
+    let indexed: Vec<String> = conn
+        .prepare("SELECT path FROM usage_index_files")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .unwrap_or_default();
+    for path in indexed.iter().filter(|p| !present.contains(*p)) {
+        let _ = conn.execute("DELETE FROM usage_entries WHERE path = ?1", params![path]);
+        let _ = conn.execute(
+            "DELETE FROM usage_index_files WHERE path = ?1",
+            params![path],
+        );
+    }
+
+    if added > 0 {
+        info!("Indexed {} usage entries", added);
+    }
+    added
+}
+
+/// Index the usage in ~/.claude/projects
+pub fn refresh_usage_index(conn: &mut Connection) {
+    if let Some(home) = dirs::home_dir() {
+        index_usage(conn, &home.join(".claude").join("projects"));
+    }
+}
+
+/// Open a connection of its own to the app's database, so indexing and other
+/// long work on usage doesn't hold the lock on the shared connection
+pub fn open_usage_connection(db_path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    conn.busy_timeout(Duration::from_secs(USAGE_BUSY_TIMEOUT_SECS))?;
+    Ok(conn)
+}
+
+/// Bring the usage index up to date off the async runtime, then lock the
+/// database; only transcript data written since the last query is parsed
+pub async fn indexed_db<'a>(
+    db: &'a State<'_, AgentDb>,
+) -> Result<MutexGuard<'a, Connection>, String> {
+    let db_path =
+        db.0.lock()
+            .map_err(|e| e.to_string())?
+            .path()
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
+    if let Some(db_path) = db_path {
+        tauri::async_runtime::spawn_blocking(move || {
+            let mut conn = open_usage_connection(&db_path).map_err(|e| e.to_string())?;
+            refresh_usage_index(&mut conn);
+            Ok::<_, String>(())
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+    }
+    db.0.lock().map_err(|e| e.to_string())
+}
+
+/// Keep the usage index up to date in the background, so the dashboard
+/// rarely has anything left to parse when it opens, and roll up usage past
+/// the retention period
+pub fn start_usage_indexer(db_path: PathBuf) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let path = db_path.clone();
+            let _ = tokio::task::spawn_blocking(move || match open_usage_connection(&path) {
+                Ok(mut conn) => {
+                    refresh_usage_index(&mut conn);
+                    if let Err(e) = apply_usage_retention(&mut conn) {
+                        warn!("Failed to apply usage retention: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to open database to index usage: {}", e),
+            })
+            .await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(USAGE_INDEX_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_index_usage_incrementally() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_usage_tables(&conn).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-work-app");
+        std::fs::create_dir(&project).unwrap();
+        let path = project.join("abc.jsonl");
+        let usage_line = |id: &str, output: u64| {
+            format!(
+                r#"{{"type":"assistant","timestamp":"2025-06-01T10:00:00Z","sessionId":"abc","requestId":"r{id}","message":{{"id":"m{id}","model":"claude-sonnet-4","usage":{{"input_tokens":1000000,"output_tokens":{output}}}}}}}"#
+            )
+        };
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, r#"{{"type":"user","cwd":"/work/app"}}"#).unwrap();
+        writeln!(file, "{}", usage_line("1", 0)).unwrap();
+        // Resumed sessions repeat earlier messages
+        writeln!(file, "{}", usage_line("1", 0)).unwrap();
+        // The last line isn't finished yet
+        write!(file, "{}", &usage_line("2", 1000000)[..40]).unwrap();
+
+        assert_eq!(index_usage(&mut conn, dir.path()), 1);
+        writeln!(file, "{}", &usage_line("2", 1000000)[40..]).unwrap();
+        assert_eq!(index_usage(&mut conn, dir.path()), 1);
+        assert_eq!(index_usage(&mut conn, dir.path()), 0);
+
+        let totals = |conn: &Connection| -> (i64, f64, String) {
+            conn.query_row(
+                "SELECT COUNT(*), COALESCE(SUM(cost), 0), COALESCE(MAX(project_path), '') FROM usage_messages",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap()
+        };
+        let (entries, cost, project_path) = totals(&conn);
+        assert_eq!((entries, project_path.as_str()), (2, "/work/app"));
+        assert!((cost - 21.0).abs() < 1e-9);
+
+        // A resumed session's transcript repeats the first message; it's
+        // counted once, and still counted once the original is deleted
+        let resumed = project.join("def.jsonl");
+        let mut file = std::fs::File::create(&resumed).unwrap();
+        writeln!(file, "{}", usage_line("1", 0)).unwrap();
+        assert_eq!(index_usage(&mut conn, dir.path()), 1);
+        assert_eq!(totals(&conn).0, 2);
+        std::fs::remove_file(&path).unwrap();
+        index_usage(&mut conn, dir.path());
+        let (entries, cost, _) = totals(&conn);
+        assert_eq!(entries, 1);
+        assert!((cost - 3.0).abs() < 1e-9);
+
+        std::fs::remove_file(&resumed).unwrap();
+        index_usage(&mut conn, dir.path());
+        let entries: i64 = conn
+            .query_row("SELECT COUNT(*) FROM usage_entries", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(entries, 0);
+    }
+}
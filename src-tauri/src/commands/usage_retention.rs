@@ -0,0 +1,356 @@
+use chrono::{Datelike, Local, Months, NaiveDate};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::settings::{load_json_setting, save_json_setting};
+use crate::commands::usage_index::open_usage_connection;
+
+/// app_settings key holding the usage retention settings as JSON
+const SETTINGS_KEY: &str = "usage_retention";
+
+/// app_settings key holding the date before which usage has been rolled up
+const ROLLED_UP_BEFORE_KEY: &str = "usage_rolled_up_before";
+
+/// How long per-message usage is kept
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UsageRetentionSettings {
+    /// Whole months of per-message usage to keep besides the current one;
+    /// older usage is rolled up into monthly totals. None keeps everything.
+    pub detail_months: Option<u32>,
+}
+
+/// Totals of a month's usage of one model in one project
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MonthlyUsage {
+    /// `YYYY-MM`
+    pub month: String,
+    pub project_path: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cost: f64,
+    pub message_count: u64,
+    /// Sessions spanning several roll-ups are counted in each
+    pub session_count: u64,
+}
+
+/// Outcome of rolling up usage
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsagePurgeReport {
+    /// Usage dated before this day was rolled up
+    pub cutoff: String,
+    pub rolled_up_entries: usize,
+    pub bytes_freed: u64,
+}
+
+fn load_settings(conn: &Connection) -> UsageRetentionSettings {
+    load_json_setting(conn, SETTINGS_KEY)
+}
+
+/// The date before which usage has been rolled up, `YYYY-MM-DD`. The indexer
+/// skips usage older than this so it isn't counted twice.
+pub fn rolled_up_before(conn: &Connection) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![ROLLED_UP_BEFORE_KEY],
+        |row| row.get(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+/// First day of the oldest month whose detail is kept, `detail_months` before
+/// the month of `today`
+pub fn retention_cutoff(detail_months: u32, today: NaiveDate) -> NaiveDate {
+    let month_start = today.with_day(1).unwrap_or(today);
+    month_start
+        .checked_sub_months(Months::new(detail_months))
+        .unwrap_or(month_start)
+}
+
+/// Fold the usage dated before `cutoff` into the monthly rollups and delete it.
+/// Returns the number of entries rolled up.
+pub fn roll_up_usage(conn: &mut Connection, cutoff: NaiveDate) -> Result<usize, String> {
+    // Roll up whole months so none is split between detail and totals
+    let cutoff = cutoff
+        .with_day(1)
+        .unwrap_or(cutoff)
+        .format("%Y-%m-%d")
+        .to_string();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO usage_monthly_rollups (month, project_path, model, input_tokens, output_tokens,
+            cache_creation_tokens, cache_read_tokens, cost, message_count, session_count,
+            cache_creation_1h_tokens, base_cost)
+         SELECT substr(date, 1, 7), project_path, model, SUM(input_tokens), SUM(output_tokens),
+             SUM(cache_creation_tokens), SUM(cache_read_tokens), SUM(cost), COUNT(*), COUNT(DISTINCT session_id),
+             SUM(cache_creation_1h_tokens), SUM(base_cost)
+         FROM usage_messages WHERE date < ?1
+         GROUP BY substr(date, 1, 7), project_path, model
+         ON CONFLICT (month, project_path, model) DO UPDATE SET
+             input_tokens = input_tokens + excluded.input_tokens,
+             output_tokens = output_tokens + excluded.output_tokens,
+             cache_creation_tokens = cache_creation_tokens + excluded.cache_creation_tokens,
+             cache_read_tokens = cache_read_tokens + excluded.cache_read_tokens,
+             cost = cost + excluded.cost,
+             message_count = message_count + excluded.message_count,
+             session_count = session_count + excluded.session_count,
+             cache_creation_1h_tokens = cache_creation_1h_tokens + excluded.cache_creation_1h_tokens,
+             base_cost = base_cost + excluded.base_cost",
+        params![cutoff],
+    )
+    .map_err(|e| format!("Failed to roll up usage: {}", e))?;
+    // Rollups have no sessions; keep what the runs of the rolled up sessions cost
+    tx.execute(
+        "UPDATE agent_runs SET
+             cost_usd = (SELECT SUM(cost) FROM usage_messages u WHERE u.session_id = agent_runs.session_id),
+             total_tokens = (
+                 SELECT SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens)
+                 FROM usage_messages u WHERE u.session_id = agent_runs.session_id
+             )
+         WHERE session_id IN (SELECT session_id FROM usage_messages WHERE date < ?1)",
+        params![cutoff],
+    )
+    .map_err(|e| e.to_string())?;
+    let rolled_up = tx
+        .execute("DELETE FROM usage_entries WHERE date < ?1", params![cutoff])
+        .map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT (key) DO UPDATE SET value = MAX(value, excluded.value)",
+        params![ROLLED_UP_BEFORE_KEY, cutoff],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(rolled_up)
+}
+
+/// Roll up the usage past the configured retention period, if there is one
+pub fn apply_usage_retention(conn: &mut Connection) -> Result<usize, String> {
+    let Some(detail_months) = load_settings(conn).detail_months else {
+        return Ok(0);
+    };
+    let rolled_up = roll_up_usage(
+        conn,
+        retention_cutoff(detail_months, Local::now().date_naive()),
+    )?;
+    if rolled_up > 0 {
+        info!("Rolled up {} usage entries into monthly totals", rolled_up);
+    }
+    Ok(rolled_up)
+}
+
+fn database_size(conn: &Connection) -> u64 {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|size| size as u64)
+    .unwrap_or(0)
+}
+
+#[tauri::command]
+pub async fn get_usage_retention(db: State<'_, AgentDb>) -> Result<UsageRetentionSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_usage_retention(
+    db: State<'_, AgentDb>,
+    settings: UsageRetentionSettings,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_json_setting(&conn, SETTINGS_KEY, &settings)?;
+    Ok(())
+}
+
+/// Roll up the usage before `before` (`YYYY-MM-DD`, rounded down to the start
+/// of its month), or past the configured retention period, and compact the
+/// database
+#[tauri::command]
+pub async fn purge_usage(
+    db: State<'_, AgentDb>,
+    before: Option<String>,
+) -> Result<UsagePurgeReport, String> {
+    let (cutoff, size_before, rolled_up_entries, db_path) = {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let cutoff = match before {
+            Some(before) => NaiveDate::parse_from_str(&before, "%Y-%m-%d")
+                .map_err(|e| format!("Invalid date: {}", e))?,
+            None => {
+                let detail_months = load_settings(&conn)
+                    .detail_months
+                    .ok_or("No usage retention period is configured")?;
+                retention_cutoff(detail_months, Local::now().date_naive())
+            }
+        }
+        .with_day(1)
+        .ok_or("Invalid date")?;
+
+        let size_before = database_size(&conn);
+        let rolled_up_entries = roll_up_usage(&mut conn, cutoff)?;
+        (
+            cutoff,
+            size_before,
+            rolled_up_entries,
+            conn.path().map(PathBuf::from),
+        )
+    };
+
+    // Compacting rewrites the whole file; do it on a connection of its own
+    // rather than holding the lock on the shared one meanwhile
+    let size_after = match db_path {
+        Some(db_path) => tauri::async_runtime::spawn_blocking(move || {
+            let conn = open_usage_connection(&db_path).map_err(|e| e.to_string())?;
+            conn.execute_batch("VACUUM")
+                .map_err(|e| format!("Failed to compact database: {}", e))?;
+            Ok::<_, String>(database_size(&conn))
+        })
+        .await
+        .map_err(|e| e.to_string())??,
+        None => size_before,
+    };
+    let report = UsagePurgeReport {
+        cutoff: cutoff.format("%Y-%m-%d").to_string(),
+        rolled_up_entries,
+        bytes_freed: size_before.saturating_sub(size_after),
+    };
+    info!(
+        "Rolled up {} usage entries before {}, freeing {} bytes",
+        report.rolled_up_entries, report.cutoff, report.bytes_freed
+    );
+    Ok(report)
+}
+
+/// Monthly totals of rolled up usage, newest first
+#[tauri::command]
+pub async fn get_usage_rollups(db: State<'_, AgentDb>) -> Result<Vec<MonthlyUsage>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT month, project_path, model, input_tokens, output_tokens, cache_creation_tokens,
+                cache_read_tokens, cost, message_count, session_count
+             FROM usage_monthly_rollups ORDER BY month DESC, cost DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rollups = stmt
+        .query_map([], |row| {
+            Ok(MonthlyUsage {
+                month: row.get(0)?,
+                project_path: row.get(1)?,
+                model: row.get(2)?,
+                input_tokens: row.get::<_, i64>(3)? as u64,
+                output_tokens: row.get::<_, i64>(4)? as u64,
+                cache_creation_tokens: row.get::<_, i64>(5)? as u64,
+                cache_read_tokens: row.get::<_, i64>(6)? as u64,
+                cost: row.get(7)?,
+                message_count: row.get::<_, i64>(8)? as u64,
+                session_count: row.get::<_, i64>(9)? as u64,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rollups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usage_index::{create_usage_tables, insert_test_usage};
+
+    #[test]
+    fn test_roll_up_usage() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 15).unwrap();
+        assert_eq!(
+            retention_cutoff(12, today),
+            NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()
+        );
+        assert_eq!(
+            retention_cutoff(0, today),
+            NaiveDate::from_ymd_opt(2026, 3, 1).unwrap()
+        );
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, session_id TEXT NOT NULL, total_tokens INTEGER, cost_usd REAL);
+             INSERT INTO agent_runs VALUES (1, 's2', NULL, NULL);",
+        )
+        .unwrap();
+        create_usage_tables(&conn).unwrap();
+        insert_test_usage(
+            &conn,
+            &[
+                ("a", "2025-01-05T12:00:00Z", "m", 10, 20, 1.0, "s1", "/p"),
+                ("b", "2025-01-20T12:00:00Z", "m", 10, 20, 2.0, "s2", "/p"),
+                ("c", "2025-02-03T12:00:00Z", "m", 10, 20, 4.0, "s3", "/p"),
+                ("d", "2025-03-01T12:00:00Z", "m", 10, 20, 8.0, "s4", "/p"),
+            ],
+        );
+
+        // A cutoff mid-month keeps the whole month in detail
+        let cutoff = NaiveDate::from_ymd_opt(2025, 2, 10).unwrap();
+        assert_eq!(roll_up_usage(&mut conn, cutoff).unwrap(), 2);
+        assert_eq!(rolled_up_before(&conn).as_deref(), Some("2025-02-01"));
+        let run_cost: (i64, f64) = conn
+            .query_row(
+                "SELECT total_tokens, cost_usd FROM agent_runs WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(run_cost, (30, 2.0));
+
+        // Usage of a rolled up month that turns up again adds to its totals
+        insert_test_usage(
+            &conn,
+            &[("e", "2025-01-30T12:00:00Z", "m", 1, 2, 0.5, "s5", "/p")],
+        );
+        let cutoff = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        assert_eq!(roll_up_usage(&mut conn, cutoff).unwrap(), 2);
+        assert_eq!(rolled_up_before(&conn).as_deref(), Some("2025-03-01"));
+        // Rolling up less doesn't move the cutoff back
+        roll_up_usage(&mut conn, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+        assert_eq!(rolled_up_before(&conn).as_deref(), Some("2025-03-01"));
+
+        let rollups: Vec<(String, i64, f64, i64, i64)> = conn
+            .prepare(
+                "SELECT month, input_tokens, cost, message_count, session_count
+                 FROM usage_monthly_rollups ORDER BY month",
+            )
+            .unwrap()
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            rollups,
+            vec![
+                ("2025-01".to_string(), 21, 3.5, 3, 3),
+                ("2025-02".to_string(), 10, 4.0, 1, 1),
+            ]
+        );
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM usage_entries", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 1);
+    }
+}
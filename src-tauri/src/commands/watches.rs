@@ -0,0 +1,159 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::commands::agents::AgentDb;
+use crate::watcher::{start_file_watch_by_id, stop_file_watch};
+
+/// Debounce used when a watch doesn't specify one
+const DEFAULT_DEBOUNCE_MS: u64 = 2000;
+
+/// A file watch that launches an agent when matching files change
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentFileWatch {
+    pub id: i64,
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub project_path: String,
+    pub glob_pattern: String,
+    pub task: Option<String>,
+    pub model: Option<String>,
+    pub debounce_ms: u64,
+    pub enabled: bool,
+    pub last_triggered_at: Option<String>,
+    pub created_at: String,
+}
+
+fn query_watches(conn: &Connection, id: Option<i64>) -> Result<Vec<AgentFileWatch>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT w.id, w.agent_id, a.name, w.project_path, w.glob_pattern, w.task, w.model,
+                    w.debounce_ms, w.enabled, w.last_triggered_at, w.created_at
+             FROM agent_file_watches w JOIN agents a ON a.id = w.agent_id
+             WHERE ?1 IS NULL OR w.id = ?1
+             ORDER BY w.created_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let watches = stmt
+        .query_map(params![id], |row| {
+            Ok(AgentFileWatch {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                agent_name: row.get(2)?,
+                project_path: row.get(3)?,
+                glob_pattern: row.get(4)?,
+                task: row.get(5)?,
+                model: row.get(6)?,
+                debounce_ms: row.get::<_, i64>(7)?.max(0) as u64,
+                enabled: row.get(8)?,
+                last_triggered_at: row.get(9)?,
+                created_at: row.get(10)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(watches)
+}
+
+/// Attach an agent to a project with a glob pattern and start watching it
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_agent_file_watch(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    project_path: String,
+    glob_pattern: String,
+    task: Option<String>,
+    model: Option<String>,
+    debounce_ms: Option<u64>,
+) -> Result<AgentFileWatch, String> {
+    glob::Pattern::new(&glob_pattern)
+        .map_err(|e| format!("Invalid glob pattern '{}': {}", glob_pattern, e))?;
+
+    if !std::path::Path::new(&project_path).is_dir() {
+        return Err(format!("Project path does not exist: {}", project_path));
+    }
+
+    let id = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO agent_file_watches (agent_id, project_path, glob_pattern, task, model, debounce_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                agent_id,
+                project_path,
+                glob_pattern,
+                task,
+                model,
+                debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS) as i64
+            ],
+        )
+        .map_err(|e| format!("Failed to create file watch: {}", e))?;
+        conn.last_insert_rowid()
+    };
+
+    start_file_watch_by_id(&app, id)?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_watches(&conn, Some(id))?
+        .pop()
+        .ok_or_else(|| "Failed to load created file watch".to_string())
+}
+
+/// List all agent file watches
+#[tauri::command]
+pub async fn list_agent_file_watches(
+    db: State<'_, AgentDb>,
+) -> Result<Vec<AgentFileWatch>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_watches(&conn, None)
+}
+
+/// Enable or disable a file watch, starting or stopping its watcher
+#[tauri::command]
+pub async fn set_agent_file_watch_enabled(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    id: i64,
+    enabled: bool,
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let updated = conn
+            .execute(
+                "UPDATE agent_file_watches SET enabled = ?1 WHERE id = ?2",
+                params![enabled, id],
+            )
+            .map_err(|e| e.to_string())?;
+
+        if updated == 0 {
+            return Err(format!("File watch {} not found", id));
+        }
+    }
+
+    if enabled {
+        start_file_watch_by_id(&app, id)
+    } else {
+        stop_file_watch(&app, id);
+        Ok(())
+    }
+}
+
+/// Delete a file watch and stop its watcher
+#[tauri::command]
+pub async fn delete_agent_file_watch(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    id: i64,
+) -> Result<(), String> {
+    stop_file_watch(&app, id);
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM agent_file_watches WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use log::warn;
+use tauri::{AppHandle, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::mcp_secrets::MASKED_VALUE;
+use crate::commands::settings::save_setting;
+use crate::webhook::{
+    load_webhook_settings, restart_webhook_listener, set_webhook_token, webhook_token,
+    WebhookSettings,
+};
+
+fn generate_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// The webhook settings with the token masked
+fn masked_settings(db: &AgentDb) -> Result<WebhookSettings, String> {
+    let mut settings = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_webhook_settings(&conn)
+    };
+    settings.token = webhook_token()?.map(|_| MASKED_VALUE.to_string());
+    Ok(settings)
+}
+
+fn store_settings(db: &AgentDb, enabled: bool, port: u16) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_setting(&conn, "webhook_enabled", &enabled.to_string())?;
+    save_setting(&conn, "webhook_port", &port.to_string())
+}
+
+/// Get the webhook listener settings. The token is masked; it's only shown
+/// when generated with [`regenerate_webhook_token`].
+#[tauri::command]
+pub async fn get_webhook_settings(db: State<'_, AgentDb>) -> Result<WebhookSettings, String> {
+    masked_settings(&db)
+}
+
+/// Enable or disable the webhook listener and set its port.
+///
+/// A token is generated the first time webhooks are enabled. When the listener
+/// can't start, the previous settings and listener are restored.
+#[tauri::command]
+pub async fn save_webhook_settings(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    enabled: bool,
+    port: u16,
+) -> Result<WebhookSettings, String> {
+    if port == 0 {
+        return Err("Webhook port must be between 1 and 65535".to_string());
+    }
+
+    if enabled && webhook_token()?.is_none() {
+        set_webhook_token(&generate_token())?;
+    }
+    let previous = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_webhook_settings(&conn)
+    };
+    store_settings(&db, enabled, port)?;
+
+    if let Err(e) = restart_webhook_listener(&app).await {
+        store_settings(&db, previous.enabled, previous.port)?;
+        if let Err(e) = restart_webhook_listener(&app).await {
+            warn!("Previous webhook listener not restarted: {}", e);
+        }
+        return Err(e);
+    }
+
+    masked_settings(&db)
+}
+
+/// Replace the webhook token, invalidating the previous one. This is the only
+/// command that returns the token unmasked.
+#[tauri::command]
+pub async fn regenerate_webhook_token() -> Result<String, String> {
+    let token = generate_token();
+    set_webhook_token(&token)?;
+    Ok(token)
+}
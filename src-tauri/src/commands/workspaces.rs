@@ -0,0 +1,217 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::commands::claude::{get_project_sessions, list_projects, Project, Session};
+use crate::process::normalize_project_path;
+
+/// A named group of project paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+    pub project_paths: Vec<String>,
+    pub created_at: String,
+}
+
+fn load_workspace(conn: &Connection, workspace_id: i64) -> Result<Workspace, String> {
+    let (name, created_at): (String, String) = conn
+        .query_row(
+            "SELECT name, created_at FROM workspaces WHERE id = ?1",
+            params![workspace_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Workspace {} not found: {}", workspace_id, e))?;
+    let project_paths = conn
+        .prepare(
+            "SELECT project_path FROM workspace_projects WHERE workspace_id = ?1 ORDER BY project_path",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map(params![workspace_id], |row| row.get(0))?
+                .collect()
+        })
+        .map_err(|e| e.to_string())?;
+
+    Ok(Workspace {
+        id: workspace_id,
+        name,
+        project_paths,
+        created_at,
+    })
+}
+
+/// Replace a workspace's projects, dropping duplicates and trailing slashes
+fn save_workspace_projects(
+    conn: &Connection,
+    workspace_id: i64,
+    project_paths: &[String],
+) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM workspace_projects WHERE workspace_id = ?1",
+        params![workspace_id],
+    )
+    .map_err(|e| e.to_string())?;
+    for path in project_paths.iter().filter(|p| !p.trim().is_empty()) {
+        conn.execute(
+            "INSERT OR IGNORE INTO workspace_projects (workspace_id, project_path) VALUES (?1, ?2)",
+            params![workspace_id, normalize_project_path(path.trim())],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Workspace name cannot be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// List all workspaces by name
+#[tauri::command]
+pub async fn list_workspaces(db: State<'_, AgentDb>) -> Result<Vec<Workspace>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = conn
+        .prepare("SELECT id FROM workspaces ORDER BY name COLLATE NOCASE")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .map_err(|e| e.to_string())?;
+
+    ids.into_iter()
+        .map(|id| load_workspace(&conn, id))
+        .collect()
+}
+
+/// Create a workspace grouping the given project paths
+#[tauri::command]
+pub async fn create_workspace(
+    db: State<'_, AgentDb>,
+    name: String,
+    project_paths: Vec<String>,
+) -> Result<Workspace, String> {
+    let name = validate_name(&name)?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("INSERT INTO workspaces (name) VALUES (?1)", params![name])
+        .map_err(|e| format!("Failed to create workspace '{}': {}", name, e))?;
+    let workspace_id = tx.last_insert_rowid();
+    save_workspace_projects(&tx, workspace_id, &project_paths)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    load_workspace(&conn, workspace_id)
+}
+
+/// Rename a workspace and/or replace its projects
+#[tauri::command]
+pub async fn update_workspace(
+    db: State<'_, AgentDb>,
+    workspace_id: i64,
+    name: Option<String>,
+    project_paths: Option<Vec<String>>,
+) -> Result<Workspace, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_workspace(&conn, workspace_id)?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    if let Some(name) = name {
+        let name = validate_name(&name)?;
+        tx.execute(
+            "UPDATE workspaces SET name = ?1 WHERE id = ?2",
+            params![name, workspace_id],
+        )
+        .map_err(|e| format!("Failed to rename workspace to '{}': {}", name, e))?;
+    }
+    if let Some(project_paths) = project_paths {
+        save_workspace_projects(&tx, workspace_id, &project_paths)?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    load_workspace(&conn, workspace_id)
+}
+
+/// Delete a workspace; its projects are left alone
+#[tauri::command]
+pub async fn delete_workspace(db: State<'_, AgentDb>, workspace_id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM workspace_projects WHERE workspace_id = ?1",
+        params![workspace_id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM workspaces WHERE id = ?1",
+        params![workspace_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// List the Claude projects that belong to a workspace
+#[tauri::command]
+pub async fn list_workspace_projects(
+    db: State<'_, AgentDb>,
+    workspace_id: i64,
+) -> Result<Vec<Project>, String> {
+    let paths: HashSet<String> = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_workspace(&conn, workspace_id)?
+            .project_paths
+            .into_iter()
+            .collect()
+    };
+
+    Ok(list_projects()
+        .await?
+        .into_iter()
+        .filter(|project| paths.contains(&normalize_project_path(&project.path)))
+        .collect())
+}
+
+/// List the sessions of every project in a workspace, newest first
+#[tauri::command]
+pub async fn list_workspace_sessions(
+    db: State<'_, AgentDb>,
+    workspace_id: i64,
+) -> Result<Vec<Session>, String> {
+    let mut sessions = Vec::new();
+    for project in list_workspace_projects(db.clone(), workspace_id).await? {
+        sessions.extend(get_project_sessions(db.clone(), project.id).await?);
+    }
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::agents::open_database;
+
+    #[test]
+    fn test_save_workspace_projects_normalizes_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = open_database(&dir.path().join("agents.db")).unwrap();
+        conn.execute(
+            "INSERT INTO workspaces (id, name) VALUES (1, 'Client work')",
+            [],
+        )
+        .unwrap();
+
+        let paths = ["/work/api/", "/work/api", " /work/web ", ""].map(String::from);
+        save_workspace_projects(&conn, 1, &paths).unwrap();
+        assert_eq!(
+            load_workspace(&conn, 1).unwrap().project_paths,
+            vec!["/work/api", "/work/web"]
+        );
+
+        save_workspace_projects(&conn, 1, &["/work/docs".to_string()]).unwrap();
+        assert_eq!(
+            load_workspace(&conn, 1).unwrap().project_paths,
+            vec!["/work/docs"]
+        );
+        assert!(load_workspace(&conn, 2).is_err());
+    }
+}
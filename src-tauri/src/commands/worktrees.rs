@@ -0,0 +1,569 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::run_diffs::run_git;
+use crate::process::ProcessRegistryState;
+
+/// Prefix of the branches created for task worktrees
+const BRANCH_PREFIX: &str = "opcode/";
+
+/// A git worktree on its own branch, created so a session or agent run can work
+/// without touching the project's checkout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskWorktree {
+    pub id: i64,
+    /// Project the worktree was created from
+    pub project_path: String,
+    /// Root of the project's repository, where the branch is merged back
+    pub repo_root: String,
+    pub worktree_path: String,
+    /// Path inside the worktree matching `project_path`; sessions and runs work here
+    pub working_dir: String,
+    pub branch: String,
+    /// Branch that was checked out when the worktree was created, if any
+    pub base_branch: Option<String>,
+    pub task: String,
+    /// "active", "merged" or "discarded"
+    pub status: String,
+    /// Agent run working in the worktree, if it was created for one
+    pub run_id: Option<i64>,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+}
+
+/// Turn a task into something usable as a branch and directory name
+pub fn slugify_task(task: &str) -> String {
+    let mut slug = String::new();
+    for c in task.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= 40 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "task".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+fn branch_exists(repo_root: &Path, branch: &str) -> bool {
+    let reference = format!("refs/heads/{}", branch);
+    run_git(
+        repo_root,
+        &["rev-parse", "--verify", "--quiet", &reference],
+        None,
+    )
+    .is_ok()
+}
+
+fn row_to_worktree(row: &rusqlite::Row) -> rusqlite::Result<TaskWorktree> {
+    Ok(TaskWorktree {
+        id: row.get(0)?,
+        project_path: row.get(1)?,
+        repo_root: row.get(2)?,
+        worktree_path: row.get(3)?,
+        working_dir: row.get(4)?,
+        branch: row.get(5)?,
+        base_branch: row.get(6)?,
+        task: row.get(7)?,
+        status: row.get(8)?,
+        run_id: row.get(9)?,
+        created_at: row.get(10)?,
+        finished_at: row.get(11)?,
+    })
+}
+
+const WORKTREE_COLUMNS: &str = "w.id, w.project_path, w.repo_root, w.worktree_path, w.working_dir, w.branch, w.base_branch, w.task, w.status,
+     (SELECT MAX(r.id) FROM agent_runs r WHERE r.worktree_id = w.id), w.created_at, w.finished_at";
+
+pub fn load_worktree(conn: &Connection, worktree_id: i64) -> Result<TaskWorktree, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM task_worktrees w WHERE w.id = ?1",
+            WORKTREE_COLUMNS
+        ),
+        params![worktree_id],
+        row_to_worktree,
+    )
+    .map_err(|e| format!("Worktree {} not found: {}", worktree_id, e))
+}
+
+/// A worktree checkout that was added but not recorded yet
+struct WorktreeCheckout {
+    repo_root: PathBuf,
+    worktree_path: PathBuf,
+    working_dir: String,
+    branch: String,
+    base_branch: Option<String>,
+}
+
+/// Add a worktree of the project's repository on a new branch named after the task,
+/// starting from the current HEAD
+fn add_worktree_checkout(
+    worktrees_dir: &Path,
+    project_path: &str,
+    task: &str,
+) -> Result<WorktreeCheckout, String> {
+    let project = Path::new(project_path);
+    let repo_root = run_git(project, &["rev-parse", "--show-toplevel"], None)
+        .map_err(|_| format!("{} is not in a git repository", project_path))?;
+    let repo_root = PathBuf::from(repo_root.trim());
+    // The project may be a subdirectory of its repository
+    let prefix = run_git(project, &["rev-parse", "--show-prefix"], None)?;
+    let base_branch = run_git(
+        &repo_root,
+        &["symbolic-ref", "--quiet", "--short", "HEAD"],
+        None,
+    )
+    .ok()
+    .map(|b| b.trim().to_string());
+
+    std::fs::create_dir_all(worktrees_dir)
+        .map_err(|e| format!("Failed to create worktree directory: {}", e))?;
+
+    // Pick the first name that is free both as a branch and as a directory
+    let slug = slugify_task(task);
+    let (branch, worktree_path) = (1..)
+        .map(|n| {
+            let name = if n == 1 {
+                slug.clone()
+            } else {
+                format!("{}-{}", slug, n)
+            };
+            (
+                format!("{}{}", BRANCH_PREFIX, name),
+                worktrees_dir.join(name),
+            )
+        })
+        .find(|(branch, path)| !path.exists() && !branch_exists(&repo_root, branch))
+        .expect("unbounded range always yields a free name");
+
+    let worktree_str = worktree_path.to_string_lossy().to_string();
+    run_git(
+        &repo_root,
+        &["worktree", "add", "-b", &branch, &worktree_str, "HEAD"],
+        None,
+    )?;
+    let working_dir = worktree_path
+        .join(prefix.trim())
+        .to_string_lossy()
+        .to_string();
+
+    Ok(WorktreeCheckout {
+        repo_root,
+        worktree_path,
+        working_dir,
+        branch,
+        base_branch,
+    })
+}
+
+fn record_worktree(
+    db: &AgentDb,
+    checkout: &WorktreeCheckout,
+    project_path: &str,
+    task: &str,
+) -> Result<TaskWorktree, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO task_worktrees (project_path, repo_root, worktree_path, working_dir, branch, base_branch, task, status)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'active')",
+        params![
+            project_path,
+            checkout.repo_root.to_string_lossy(),
+            checkout.worktree_path.to_string_lossy(),
+            checkout.working_dir,
+            checkout.branch,
+            checkout.base_branch,
+            task
+        ],
+    )
+    .map_err(|e| format!("Failed to record worktree: {}", e))?;
+    load_worktree(&conn, conn.last_insert_rowid())
+}
+
+/// Create a worktree of the project's repository on a new branch named after the
+/// task, starting from the current HEAD. Uncommitted changes in the project are not
+/// carried over, so the branch merges back cleanly.
+///
+/// Git runs off the database lock; the checkout is removed again if it can't be
+/// recorded.
+pub async fn create_task_worktree(
+    db: &AgentDb,
+    worktrees_dir: PathBuf,
+    project_path: &str,
+    task: &str,
+) -> Result<TaskWorktree, String> {
+    let (project, task_text) = (project_path.to_string(), task.to_string());
+    let checkout = tauri::async_runtime::spawn_blocking(move || {
+        add_worktree_checkout(&worktrees_dir, &project, &task_text)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    match record_worktree(db, &checkout, project_path, task) {
+        Ok(worktree) => {
+            info!(
+                "Created worktree {} on branch {}",
+                worktree.worktree_path, worktree.branch
+            );
+            Ok(worktree)
+        }
+        Err(e) => {
+            let _ = tauri::async_runtime::spawn_blocking(move || {
+                remove_worktree_checkout(
+                    &checkout.repo_root,
+                    &checkout.worktree_path,
+                    &checkout.branch,
+                )
+            })
+            .await;
+            Err(e)
+        }
+    }
+}
+
+/// Throw away a worktree whose run could not be queued, so it doesn't linger as
+/// an active worktree nothing will ever work in
+pub async fn abandon_task_worktree(db: &AgentDb, worktree: TaskWorktree) {
+    let (repo_root, worktree_path, branch) = (
+        PathBuf::from(&worktree.repo_root),
+        PathBuf::from(&worktree.worktree_path),
+        worktree.branch.clone(),
+    );
+    let _ = tauri::async_runtime::spawn_blocking(move || {
+        remove_worktree_checkout(&repo_root, &worktree_path, &branch)
+    })
+    .await;
+    let finished =
+        db.0.lock()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| finish_worktree(&conn, worktree.id, "discarded"));
+    if let Err(e) = finished {
+        warn!("Failed to discard worktree {}: {}", worktree.id, e);
+    }
+}
+
+/// Remove a worktree's checkout and delete its branch
+fn remove_worktree_checkout(repo_root: &Path, worktree_path: &Path, branch: &str) {
+    let worktree_str = worktree_path.to_string_lossy().to_string();
+    if let Err(e) = run_git(
+        repo_root,
+        &["worktree", "remove", "--force", &worktree_str],
+        None,
+    ) {
+        warn!("Failed to remove worktree {}: {}", worktree_str, e);
+        let _ = std::fs::remove_dir_all(worktree_path);
+        let _ = run_git(repo_root, &["worktree", "prune"], None);
+    }
+    if let Err(e) = run_git(repo_root, &["branch", "-D", branch], None) {
+        warn!("Failed to delete branch {}: {}", branch, e);
+    }
+}
+
+/// Load an active worktree that nothing is working in anymore
+fn load_idle_worktree(
+    conn: &Connection,
+    registry: &ProcessRegistryState,
+    worktree_id: i64,
+) -> Result<TaskWorktree, String> {
+    let worktree = load_worktree(conn, worktree_id)?;
+    if worktree.status != "active" {
+        return Err(format!(
+            "Worktree {} was already {}",
+            worktree_id, worktree.status
+        ));
+    }
+
+    let pending_runs: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM agent_runs WHERE worktree_id = ?1 AND status IN ('queued', 'running')",
+            params![worktree_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let busy = registry
+        .0
+        .get_running_processes()?
+        .iter()
+        .any(|p| Path::new(&p.project_path).starts_with(&worktree.worktree_path));
+    if pending_runs > 0 || busy {
+        return Err(format!(
+            "Claude is still working in worktree {}",
+            worktree.worktree_path
+        ));
+    }
+
+    Ok(worktree)
+}
+
+fn finish_worktree(conn: &Connection, worktree_id: i64, status: &str) -> Result<(), String> {
+    conn.execute(
+        "UPDATE task_worktrees SET status = ?1, finished_at = CURRENT_TIMESTAMP WHERE id = ?2",
+        params![status, worktree_id],
+    )
+    .map_err(|e| format!("Failed to update worktree {}: {}", worktree_id, e))?;
+    Ok(())
+}
+
+/// Create a worktree on a new branch named after the task, for an interactive
+/// session to work in. Start the session in the returned `working_dir`.
+#[tauri::command]
+pub async fn create_session_worktree(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    project_path: String,
+    task: String,
+) -> Result<TaskWorktree, String> {
+    let worktrees_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?
+        .join("worktrees");
+    create_task_worktree(&db, worktrees_dir, &project_path, &task).await
+}
+
+/// List task worktrees, newest first, optionally for a single project
+#[tauri::command]
+pub async fn list_task_worktrees(
+    db: State<'_, AgentDb>,
+    project_path: Option<String>,
+) -> Result<Vec<TaskWorktree>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM task_worktrees w WHERE ?1 IS NULL OR w.project_path = ?1 ORDER BY w.id DESC",
+            WORKTREE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let worktrees = stmt
+        .query_map(params![project_path], row_to_worktree)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(worktrees)
+}
+
+/// Commit what was left uncommitted in a worktree, merge its branch into the branch
+/// it was created from, and remove the worktree. On a conflict the merge is aborted
+/// and the worktree kept.
+fn merge_worktree_branch(
+    worktree: &TaskWorktree,
+    commit_message: Option<String>,
+) -> Result<(), String> {
+    let repo_root = Path::new(&worktree.repo_root);
+    let worktree_path = Path::new(&worktree.worktree_path);
+
+    if let Some(base_branch) = &worktree.base_branch {
+        let current = run_git(
+            repo_root,
+            &["symbolic-ref", "--quiet", "--short", "HEAD"],
+            None,
+        )
+        .unwrap_or_default();
+        if current.trim() != base_branch {
+            return Err(format!(
+                "Check out {} in {} before merging {}",
+                base_branch, worktree.repo_root, worktree.branch
+            ));
+        }
+    }
+
+    let status = run_git(worktree_path, &["status", "--porcelain"], None)?;
+    if !status.trim().is_empty() {
+        let message = commit_message
+            .filter(|m| !m.trim().is_empty())
+            .unwrap_or_else(|| worktree.task.clone());
+        run_git(worktree_path, &["add", "-A"], None)?;
+        run_git(worktree_path, &["commit", "-m", &message], None)?;
+    }
+
+    if let Err(e) = run_git(
+        repo_root,
+        &["merge", "--no-ff", "--no-edit", &worktree.branch],
+        None,
+    ) {
+        let _ = run_git(repo_root, &["merge", "--abort"], None);
+        return Err(e);
+    }
+
+    remove_worktree_checkout(repo_root, worktree_path, &worktree.branch);
+    Ok(())
+}
+
+/// Commit what was left uncommitted in a worktree, merge its branch into the branch
+/// it was created from, and remove the worktree. The base branch must be checked
+/// out in the project; on a conflict the merge is aborted and the worktree kept.
+#[tauri::command]
+pub async fn merge_task_worktree(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    worktree_id: i64,
+    commit_message: Option<String>,
+) -> Result<TaskWorktree, String> {
+    let worktree = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_idle_worktree(&conn, &registry, worktree_id)?
+    };
+
+    let merged = worktree.clone();
+    tauri::async_runtime::spawn_blocking(move || merge_worktree_branch(&merged, commit_message))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    finish_worktree(&conn, worktree_id, "merged")?;
+    info!("Merged {} into {}", worktree.branch, worktree.repo_root);
+    load_worktree(&conn, worktree_id)
+}
+
+/// Throw away a worktree and its branch, including any commits made on it
+#[tauri::command]
+pub async fn discard_task_worktree(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    worktree_id: i64,
+) -> Result<TaskWorktree, String> {
+    let worktree = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_idle_worktree(&conn, &registry, worktree_id)?
+    };
+
+    let (repo_root, worktree_path, branch) = (
+        PathBuf::from(&worktree.repo_root),
+        PathBuf::from(&worktree.worktree_path),
+        worktree.branch.clone(),
+    );
+    tauri::async_runtime::spawn_blocking(move || {
+        remove_worktree_checkout(&repo_root, &worktree_path, &branch)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    finish_worktree(&conn, worktree_id, "discarded")?;
+    info!("Discarded worktree {}", worktree.worktree_path);
+    load_worktree(&conn, worktree_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify_task() {
+        assert_eq!(
+            slugify_task("Fix the login bug (#42)!"),
+            "fix-the-login-bug-42"
+        );
+        assert_eq!(slugify_task("  Ünïcode -- only  "), "n-code-only");
+        assert_eq!(slugify_task("???"), "task");
+        let long = slugify_task(&"refactor the session parser ".repeat(5));
+        assert!(long.len() <= 40 && !long.ends_with('-'));
+    }
+
+    fn init_repo(repo: &Path) {
+        let git = |args: &[&str]| run_git(repo, args, None).unwrap();
+        git(&["init", "--quiet", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test"]);
+        std::fs::write(repo.join("a.txt"), "one\n").unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "--quiet", "-m", "init"]);
+    }
+
+    fn add_worktree(repo: &Path, worktrees_dir: &Path, task: &str) -> TaskWorktree {
+        let checkout = add_worktree_checkout(worktrees_dir, &repo.to_string_lossy(), task).unwrap();
+        TaskWorktree {
+            id: 1,
+            project_path: repo.to_string_lossy().to_string(),
+            repo_root: checkout.repo_root.to_string_lossy().to_string(),
+            worktree_path: checkout.worktree_path.to_string_lossy().to_string(),
+            working_dir: checkout.working_dir,
+            branch: checkout.branch,
+            base_branch: checkout.base_branch,
+            task: task.to_string(),
+            status: "active".to_string(),
+            run_id: None,
+            created_at: String::new(),
+            finished_at: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_worktree_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        init_repo(&repo);
+        let worktree = add_worktree(&repo, &dir.path().join("worktrees"), "Change a");
+        assert_eq!(worktree.branch, "opcode/change-a");
+        assert_eq!(worktree.base_branch.as_deref(), Some("main"));
+
+        // Uncommitted work in the worktree is committed with the given message
+        std::fs::write(Path::new(&worktree.working_dir).join("a.txt"), "two\n").unwrap();
+        merge_worktree_branch(&worktree, Some("Update a".to_string())).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(repo.join("a.txt")).unwrap(),
+            "two\n"
+        );
+        let log = run_git(&repo, &["log", "--format=%s", "-3"], None).unwrap();
+        assert!(log.contains("Update a"));
+        assert!(!Path::new(&worktree.worktree_path).exists());
+        assert!(!branch_exists(&repo, &worktree.branch));
+    }
+
+    #[test]
+    fn test_merge_requires_base_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        init_repo(&repo);
+        let worktree = add_worktree(&repo, &dir.path().join("worktrees"), "Change a");
+        run_git(&repo, &["checkout", "--quiet", "-b", "other"], None).unwrap();
+
+        let err = merge_worktree_branch(&worktree, None).unwrap_err();
+        assert!(err.contains("Check out main"));
+        // Nothing is removed when the merge doesn't happen
+        assert!(Path::new(&worktree.worktree_path).exists());
+        assert!(branch_exists(&repo, &worktree.branch));
+    }
+
+    #[test]
+    fn test_discard_worktree_checkout() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        init_repo(&repo);
+        let worktrees_dir = dir.path().join("worktrees");
+        let worktree = add_worktree(&repo, &worktrees_dir, "Change a");
+        let worktree_path = Path::new(&worktree.worktree_path);
+        std::fs::write(worktree_path.join("a.txt"), "two\n").unwrap();
+        run_git(worktree_path, &["commit", "--quiet", "-am", "wip"], None).unwrap();
+
+        remove_worktree_checkout(&repo, worktree_path, &worktree.branch);
+        assert!(!worktree_path.exists());
+        assert!(!branch_exists(&repo, &worktree.branch));
+        assert_eq!(
+            std::fs::read_to_string(repo.join("a.txt")).unwrap(),
+            "one\n"
+        );
+
+        // The name is free again for the next worktree
+        let again = add_worktree(&repo, &worktrees_dir, "Change a");
+        assert_eq!(again.branch, "opcode/change-a");
+    }
+}
@@ -0,0 +1,34 @@
+//! Secrets kept in the OS keychain. Every secret opcode stores lives under
+//! one keychain service, told apart by its account name.
+
+use keyring::{Entry, Error, Result};
+
+/// Keychain service every secret is stored under
+const SERVICE: &str = "opcode";
+
+/// The keychain entry of one secret
+pub fn entry(account: &str) -> Result<Entry> {
+    Entry::new(SERVICE, account)
+}
+
+/// Read a secret; None when it isn't stored
+pub fn get_secret(account: &str) -> Result<Option<String>> {
+    match entry(account)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::NoEntry) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Store a secret, replacing any earlier value
+pub fn set_secret(account: &str, value: &str) -> Result<()> {
+    entry(account)?.set_password(value)
+}
+
+/// Remove a secret; removing one that isn't stored succeeds
+pub fn delete_secret(account: &str) -> Result<()> {
+    match entry(account)?.delete_credential() {
+        Ok(()) | Err(Error::NoEntry) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
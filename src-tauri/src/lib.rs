@@ -4,9 +4,20 @@
 pub mod checkpoint;
 pub mod claude_binary;
 pub mod commands;
+pub mod keychain;
+pub mod mcp_proxy;
+pub mod mcp_supervisor;
+pub mod mcp_ws_bridge;
 pub mod process;
+pub mod projects_watcher;
+pub mod scheduler;
 pub mod shell_environment;
+pub mod stderr_rules;
+pub mod stream_json;
+pub mod transcript;
+pub mod watcher;
 pub mod web_server;
+pub mod webhook;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
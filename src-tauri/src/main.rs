@@ -4,52 +4,222 @@
 mod checkpoint;
 mod claude_binary;
 mod commands;
+mod keychain;
+mod mcp_proxy;
+mod mcp_supervisor;
+mod mcp_ws_bridge;
 mod process;
+mod projects_watcher;
+mod scheduler;
 mod shell_environment;
+mod stderr_rules;
+mod stream_json;
+mod transcript;
+mod watcher;
+mod webhook;
 
 use checkpoint::state::CheckpointState;
+use commands::agent_env::{delete_agent_env_var, get_agent_env, set_agent_env_var};
+use commands::agent_parameters::{get_agent_parameters, set_agent_parameters};
+use commands::agent_revisions::{
+    diff_agent_revisions, get_run_agent_revision, list_agent_revisions, rollback_agent,
+};
+use commands::agent_variants::{duplicate_agent, get_agent_variants};
 use commands::agents::{
     cleanup_finished_processes, create_agent, delete_agent, execute_agent, export_agent,
     export_agent_to_file, fetch_github_agent_content, fetch_github_agents, get_agent,
     get_agent_run, get_agent_run_with_real_time_metrics, get_claude_binary_path,
     get_live_session_output, get_session_output, get_session_status, import_agent,
-    import_agent_from_file, import_agent_from_github, init_database, kill_agent_session,
-    list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
-    list_running_sessions, load_agent_session_history, set_claude_binary_path,
-    stream_session_output, update_agent, AgentDb,
+    import_agent_from_file, import_agent_from_github, import_agents_from_repo, init_database,
+    kill_agent_session, list_agent_runs, list_agent_runs_with_metrics, list_agents,
+    list_claude_installations, list_running_sessions, load_agent_session_history,
+    set_claude_binary_path, stream_session_output, update_agent, AgentDb,
+};
+use commands::artifacts::{export_run_artifact, list_run_artifacts, read_run_artifact};
+use commands::attachments::prepare_prompt_attachments;
+use commands::batches::{cancel_batch_run, get_batch_run, list_batch_runs, start_batch_run};
+use commands::budgets::{get_agent_budget, set_agent_budget};
+use commands::cancellation::{get_cancellation_settings, set_cancellation_settings};
+use commands::checkpoint_encryption::{get_checkpoint_encryption, set_checkpoint_encryption};
+use commands::checkpoint_retention::{
+    get_checkpoint_disk_usage, get_checkpoint_retention_policy, run_checkpoint_gc,
+    set_checkpoint_retention_policy,
+};
+use commands::checkpoint_validation::{
+    delete_checkpoint_validation_hook, get_checkpoint_validation, get_checkpoint_validation_hook,
+    set_checkpoint_validation_hook,
 };
 use commands::claude::{
     cancel_claude_execution, check_auto_checkpoint, check_claude_version, cleanup_old_checkpoints,
-    clear_checkpoint_manager, continue_claude_code, create_checkpoint, create_project,
-    execute_claude_code, find_claude_md_files, fork_from_checkpoint, get_checkpoint_diff,
-    get_checkpoint_settings, get_checkpoint_state_stats, get_claude_session_output,
-    get_claude_settings, get_home_directory, get_hooks_config, get_project_sessions,
-    get_recently_modified_files, get_session_timeline, get_system_prompt, list_checkpoints,
-    list_directory_contents, list_projects, list_running_claude_sessions, load_session_history,
+    clear_checkpoint_manager, compute_checkpoint_diff, continue_claude_code, create_checkpoint,
+    create_project, execute_claude_code, export_checkpoint_patch, find_claude_md_files,
+    fork_from_checkpoint, get_checkpoint_diff, get_checkpoint_settings, get_checkpoint_state_stats,
+    get_claude_session_output, get_claude_settings, get_home_directory, get_hooks_config,
+    get_project_checkpoint_timeline, get_project_sessions, get_recently_modified_files,
+    get_session_timeline, get_system_prompt, list_checkpoints, list_directory_contents,
+    list_projects, list_running_claude_sessions, load_session_history, load_session_transcript,
     open_new_session, read_claude_md_file, restore_checkpoint, resume_claude_code,
     save_claude_md_file, save_claude_settings, save_system_prompt, search_files,
-    track_checkpoint_message, track_session_messages, update_checkpoint_settings,
-    update_hooks_config, validate_hook_command, ClaudeProcessState,
+    set_checkpoint_backend, track_checkpoint_message, track_session_messages,
+    update_checkpoint_settings, update_hooks_config, validate_hook_command, ClaudeProcessState,
+};
+use commands::comparisons::{
+    delete_run_comparison, get_run_comparison, list_run_comparisons, start_run_comparison,
+};
+use commands::cost_anomalies::{dismiss_cost_anomaly, list_cost_anomalies};
+use commands::cost_budgets::{get_cost_budget_status, get_cost_budgets, set_cost_budgets};
+use commands::crash_restarts::{get_agent_crash_restart_policy, set_agent_crash_restart_policy};
+use commands::destructive_checkpoints::{
+    get_destructive_checkpoint_settings, save_destructive_checkpoint_settings,
+};
+use commands::editors::{get_editor_settings, open_in_editor, save_editor_settings};
+use commands::git_hooks::{install_git_hook, list_git_hooks, uninstall_git_hook};
+use commands::issue_links::{
+    get_issue_tracker_settings, has_issue_tracker_token, link_issue, list_issue_links,
+    post_issue_comment, save_issue_tracker_settings, set_issue_tracker_token, unlink_issue,
 };
 use commands::mcp::{
-    mcp_add, mcp_add_from_claude_desktop, mcp_add_json, mcp_get, mcp_get_server_status, mcp_list,
-    mcp_read_project_config, mcp_remove, mcp_reset_project_choices, mcp_save_project_config,
-    mcp_serve, mcp_test_connection,
+    mcp_add, mcp_add_from_claude_desktop, mcp_add_json, mcp_export_servers, mcp_get,
+    mcp_get_server_status, mcp_import_project_config, mcp_list, mcp_read_project_config,
+    mcp_remove, mcp_reset_project_choices, mcp_save_project_config, mcp_serve, mcp_test_connection,
+};
+use commands::mcp_environments::{get_mcp_server_environments, set_mcp_server_environment};
+use commands::mcp_inspector::mcp_inspect_server;
+use commands::mcp_logs::{
+    get_mcp_log_capture, set_mcp_log_capture, start_mcp_log_stream, stop_mcp_log_stream,
+    tail_mcp_logs, McpLogStreamState,
+};
+use commands::mcp_metrics::get_mcp_latency_metrics;
+use commands::mcp_project_servers::{
+    get_effective_mcp_config, get_project_mcp_servers, set_project_mcp_server,
+};
+use commands::mcp_registry::{
+    get_mcp_registry_settings, mcp_registry_install, mcp_registry_search,
+    save_mcp_registry_settings,
 };
+use commands::mcp_secrets::{
+    delete_mcp_secret, get_mcp_server_secrets, get_mcp_variables, list_mcp_secrets,
+    save_mcp_variables, set_mcp_secret,
+};
+use commands::mcp_snapshots::{diff_mcp_snapshots, list_mcp_snapshots, rollback_mcp_snapshot};
+use commands::mcp_websocket::{check_mcp_websocket_health, mcp_add_websocket_server};
 
+use commands::memory_files::{
+    get_memory_sections, list_memory_files, merge_memory_sections, remove_memory_section,
+    save_memory_section,
+};
+use commands::metrics::{get_agent_metrics, get_agent_metrics_trend};
+use commands::notification_actions::{
+    dismiss_notification, get_notification_settings, list_pending_notifications,
+    perform_notification_action, save_notification_settings,
+};
+use commands::notification_channels::{
+    delete_notification_channel, get_agent_notification_channels, list_notification_channels,
+    save_notification_channel, set_agent_notification_channels, test_notification_channel,
+};
+use commands::notifications::{get_agent_notification_rules, set_agent_notification_rules};
+use commands::permissions::{get_agent_permissions, set_agent_permissions};
+use commands::pricing::{get_pricing_settings, save_pricing_settings};
+use commands::processes::{
+    inspect_managed_process, interrupt_managed_process, kill_managed_process,
+    kill_project_processes, list_managed_processes, pause_managed_process, unpause_managed_process,
+};
+use commands::project_bundles::{export_project_bundle, import_project_bundle};
+use commands::project_discovery::{
+    discover_projects, get_project_discovery_settings, save_project_discovery_settings,
+};
+use commands::project_stats::{get_all_project_stats, get_project_stats};
+use commands::prompt_templates::{
+    create_prompt_template, delete_prompt_template, list_prompt_templates, render_prompt_template,
+    update_prompt_template,
+};
 use commands::proxy::{apply_proxy_settings, get_proxy_settings, save_proxy_settings};
+use commands::pty_mode::{get_pty_mode_settings, resize_run_terminal, set_pty_mode_settings};
+use commands::queue::{
+    cancel_queued_run, get_run_queue, get_run_queue_settings, get_scheduler_settings,
+    move_queued_run, save_run_queue_settings, save_scheduler_settings,
+};
+use commands::rate_limits::{
+    get_rate_limit_settings, get_rate_limit_status, set_rate_limit_settings,
+};
+use commands::redaction::{
+    export_redacted_session, get_redaction_settings, redact_session, save_redaction_settings,
+};
+use commands::retries::{get_agent_retry_policy, list_run_attempts, set_agent_retry_policy};
+use commands::run_diffs::{get_agent_run_diff, revert_agent_run_changes};
+use commands::run_limits::{get_agent_run_limits, set_agent_run_limits};
+use commands::run_logs::{list_run_output_logs, read_run_output_log};
+use commands::run_pull_requests::{create_run_pull_request, has_github_token, set_github_token};
+use commands::run_recovery::{dismiss_interrupted_run, list_interrupted_runs, resume_agent_run};
+use commands::run_search::search_agent_runs;
+use commands::schedules::{
+    create_agent_schedule, delete_agent_schedule, get_upcoming_scheduled_runs,
+    list_agent_schedules, set_agent_schedule_enabled,
+};
+use commands::session_archive::{
+    cleanup_sessions, get_session_disk_usage, list_archived_sessions, restore_archived_session,
+};
+use commands::session_diffs::compare_sessions;
+use commands::session_forks::fork_session_at_message;
+use commands::session_reattach::reattach_session;
+use commands::session_search::search_sessions;
+use commands::session_sharing::share_session;
+use commands::session_tabs::{
+    cancel_queued_prompt, clear_prompt_queue, close_session_tab, focus_session_tab,
+    get_session_project_lock, list_queued_prompts, list_session_tabs, open_session_tab,
+    queue_session_prompt, reorder_queued_prompts, send_session_input, set_session_project_lock,
+};
+use commands::session_titles::{
+    generate_session_title, get_session_auto_titles, rename_session, set_session_auto_titles,
+};
+use commands::settings::{get_settings_audit, reset_settings};
+use commands::shadow_runs::{
+    delete_shadow_run, get_shadow_run, list_shadow_runs, start_shadow_run,
+};
 use commands::shell::{
     auto_detect_wsl_claude, check_wsl_claude, get_available_shells, get_shell_config,
     save_shell_config,
 };
+use commands::stall_detection::{
+    get_stall_detection_settings, list_stalled_runs, set_stall_detection_settings,
+};
 use commands::storage::{
     storage_delete_row, storage_execute_sql, storage_insert_row, storage_list_tables,
     storage_read_table, storage_reset_database, storage_update_row,
 };
+use commands::stream_batching::{get_stream_batching_settings, set_stream_batching_settings};
+use commands::stream_filters::{
+    get_default_stream_filter, get_session_stream_filter, set_default_stream_filter,
+    set_session_stream_filter,
+};
+use commands::structured_output::{
+    get_agent_output_schema, get_run_structured_output, set_agent_output_schema,
+};
 use commands::usage::{
-    get_session_stats, get_usage_by_date_range, get_usage_details, get_usage_stats,
+    get_activity_heatmap, get_agent_costs, get_model_comparison, get_session_stats,
+    get_usage_by_date_range, get_usage_details, get_usage_stats,
+};
+use commands::usage_imports::{
+    export_usage, import_usage, list_usage_imports, remove_usage_import,
+};
+use commands::usage_retention::{
+    get_usage_retention, get_usage_rollups, purge_usage, set_usage_retention,
+};
+use commands::watches::{
+    create_agent_file_watch, delete_agent_file_watch, list_agent_file_watches,
+    set_agent_file_watch_enabled,
+};
+use commands::webhooks::{get_webhook_settings, regenerate_webhook_token, save_webhook_settings};
+use commands::workspaces::{
+    create_workspace, delete_workspace, list_workspace_projects, list_workspace_sessions,
+    list_workspaces, update_workspace,
+};
+use commands::worktrees::{
+    create_session_worktree, discard_task_worktree, list_task_worktrees, merge_task_worktree,
+};
+use process::{
+    ProcessRegistryState, PromptQueueState, RunQueueState, RunScheduler, SessionTabsState,
 };
-use process::ProcessRegistryState;
 use std::sync::Mutex;
 use tauri::Manager;
 
@@ -57,12 +227,22 @@ use tauri::Manager;
 use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
 
 fn main() {
+    // Claude launches servers with log capture, and WebSocket servers, through this binary
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some(mcp_proxy::PROXY_SUBCOMMAND) {
+        std::process::exit(mcp_proxy::run(&args[2..]));
+    }
+    if args.get(1).map(String::as_str) == Some(mcp_ws_bridge::WS_SUBCOMMAND) {
+        std::process::exit(mcp_ws_bridge::run(&args[2..]));
+    }
+
     // Initialize logger
     env_logger::init();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
             // Initialize agents database
             let conn = init_database(&app.handle()).expect("Failed to initialize agents database");
@@ -123,6 +303,11 @@ fn main() {
 
             // Re-open the connection for the app to manage
             let conn = init_database(&app.handle()).expect("Failed to initialize agents database");
+
+            // Runs still marked running were cut off by a crash; offer to resume them
+            commands::run_recovery::mark_interrupted_runs(&conn);
+            commands::checkpoint_encryption::load_checkpoint_encryption(&conn);
+            commands::pricing::load_pricing_settings(&conn);
             app.manage(AgentDb(Mutex::new(conn)));
 
             // Initialize checkpoint state
@@ -149,9 +334,102 @@ fn main() {
             // Initialize process registry
             app.manage(ProcessRegistryState::default());
 
+            // Initialize the agent run queue, the Claude process scheduler and the dispatcher
+            app.manage(RunQueueState::default());
+            app.manage(RunScheduler::default());
+            commands::queue::start_queue_dispatcher(app.handle().clone());
+
             // Initialize Claude process state
             app.manage(ClaudeProcessState::default());
 
+            // Initialize session tabs
+            app.manage(SessionTabsState::default());
+
+            // Initialize prompts queued behind running session turns
+            app.manage(PromptQueueState::default());
+
+            // Initialize live MCP server log streams
+            app.manage(McpLogStreamState::default());
+
+            // Report crashes and restarts of supervised MCP servers
+            mcp_supervisor::start_status_watcher(app.handle().clone());
+
+            // Keep the launcher MCP server definitions point at on this executable
+            if let Err(e) = mcp_proxy::install_launcher() {
+                log::warn!("Failed to install the MCP server launcher: {}", e);
+            }
+
+            // Start the background scheduler for cron-style agent runs
+            scheduler::start_scheduler(app.handle().clone());
+
+            // Start watchers for file-change triggered agents
+            app.manage(watcher::FileWatchState::default());
+            watcher::start_file_watches(app.handle());
+
+            // Watch Claude's projects directory for sessions started elsewhere
+            app.manage(projects_watcher::ProjectsWatchState::default());
+            projects_watcher::start_projects_watcher(app.handle());
+
+            // Enforce the checkpoint retention policy in the background
+            commands::checkpoint_retention::start_checkpoint_gc(app.handle().clone());
+
+            // Start the opt-in webhook listener for externally triggered runs
+            app.manage(webhook::WebhookState::default());
+            webhook::start_webhook_listener(app.handle().clone());
+
+            // Index agent runs from before run search existed
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                let db_path = app_data_dir.join("agents.db");
+                tauri::async_runtime::spawn_blocking(move || {
+                    commands::run_search::backfill_run_search_index(&db_path)
+                });
+            }
+
+            // Record usage of runs from before run metrics existed
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                let db_path = app_data_dir.join("agents.db");
+                tauri::async_runtime::spawn_blocking(move || {
+                    commands::metrics::backfill_run_usage(&db_path)
+                });
+            }
+
+            // Keep the full-text index of Claude sessions up to date
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                commands::session_search::start_session_indexer(app_data_dir.join("agents.db"));
+            }
+
+            // Keep the token usage index up to date for the usage dashboard
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                commands::usage_index::start_usage_indexer(app_data_dir.join("agents.db"));
+            }
+
+            // Alert when spend crosses a cost budget threshold
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                commands::cost_budgets::start_budget_monitor(
+                    app.handle().clone(),
+                    app_data_dir.join("agents.db"),
+                );
+            }
+
+            // Flag sessions and runs that cost far more than usual
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                commands::cost_anomalies::start_anomaly_monitor(
+                    app.handle().clone(),
+                    app_data_dir.join("agents.db"),
+                );
+            }
+
+            // Report sessions and runs that have gone quiet for too long
+            commands::stall_detection::start_stall_monitor(app.handle().clone());
+
+            // Report the estimated state of the subscription usage window
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                commands::rate_limits::start_rate_limit_monitor(
+                    app.handle().clone(),
+                    app_data_dir.join("agents.db"),
+                );
+            }
+
             // Apply window vibrancy with rounded corners on macOS
             #[cfg(target_os = "macos")]
             {
@@ -204,6 +482,7 @@ fn main() {
             read_claude_md_file,
             save_claude_md_file,
             load_session_history,
+            load_session_transcript,
             execute_claude_code,
             continue_claude_code,
             resume_claude_code,
@@ -223,7 +502,11 @@ fn main() {
             fork_from_checkpoint,
             get_session_timeline,
             update_checkpoint_settings,
+            set_checkpoint_backend,
             get_checkpoint_diff,
+            compute_checkpoint_diff,
+            export_checkpoint_patch,
+            get_project_checkpoint_timeline,
             track_checkpoint_message,
             track_session_messages,
             check_auto_checkpoint,
@@ -231,6 +514,18 @@ fn main() {
             get_checkpoint_settings,
             clear_checkpoint_manager,
             get_checkpoint_state_stats,
+            get_checkpoint_retention_policy,
+            set_checkpoint_retention_policy,
+            get_checkpoint_disk_usage,
+            run_checkpoint_gc,
+            get_checkpoint_validation_hook,
+            set_checkpoint_validation_hook,
+            delete_checkpoint_validation_hook,
+            get_checkpoint_validation,
+            get_checkpoint_encryption,
+            set_checkpoint_encryption,
+            get_destructive_checkpoint_settings,
+            save_destructive_checkpoint_settings,
             // Agent Management
             list_agents,
             create_agent,
@@ -244,8 +539,29 @@ fn main() {
             get_agent_run_with_real_time_metrics,
             list_running_sessions,
             kill_agent_session,
+            get_cancellation_settings,
+            set_cancellation_settings,
+            get_stream_batching_settings,
+            set_stream_batching_settings,
+            get_session_stream_filter,
+            set_session_stream_filter,
+            get_default_stream_filter,
+            set_default_stream_filter,
+            get_pty_mode_settings,
+            set_pty_mode_settings,
+            resize_run_terminal,
             get_session_status,
             cleanup_finished_processes,
+            list_managed_processes,
+            inspect_managed_process,
+            interrupt_managed_process,
+            pause_managed_process,
+            unpause_managed_process,
+            kill_managed_process,
+            kill_project_processes,
+            get_stall_detection_settings,
+            set_stall_detection_settings,
+            list_stalled_runs,
             get_session_output,
             get_live_session_output,
             stream_session_output,
@@ -260,11 +576,234 @@ fn main() {
             fetch_github_agents,
             fetch_github_agent_content,
             import_agent_from_github,
+            import_agents_from_repo,
+            // Agent Run Queue
+            get_run_queue,
+            move_queued_run,
+            cancel_queued_run,
+            get_run_queue_settings,
+            save_run_queue_settings,
+            get_scheduler_settings,
+            save_scheduler_settings,
+            // Agent Parameters
+            get_agent_parameters,
+            set_agent_parameters,
+            // Agent Revisions
+            list_agent_revisions,
+            diff_agent_revisions,
+            get_run_agent_revision,
+            rollback_agent,
+            // Agent Variants
+            duplicate_agent,
+            get_agent_variants,
+            // Agent Budgets
+            get_agent_budget,
+            set_agent_budget,
+            get_agent_run_limits,
+            set_agent_run_limits,
+            // Run Output Logs
+            list_run_output_logs,
+            read_run_output_log,
+            // Agent Retries
+            get_agent_retry_policy,
+            set_agent_retry_policy,
+            list_run_attempts,
+            // Agent Crash Restarts
+            get_agent_crash_restart_policy,
+            set_agent_crash_restart_policy,
+            // Agent Metrics
+            get_agent_metrics,
+            get_agent_metrics_trend,
+            // Agent Notifications
+            get_agent_notification_rules,
+            set_agent_notification_rules,
+            get_notification_settings,
+            save_notification_settings,
+            list_pending_notifications,
+            perform_notification_action,
+            dismiss_notification,
+            // Notification Channels
+            list_notification_channels,
+            save_notification_channel,
+            delete_notification_channel,
+            test_notification_channel,
+            get_agent_notification_channels,
+            set_agent_notification_channels,
+            // Agent Structured Output
+            get_agent_output_schema,
+            set_agent_output_schema,
+            get_run_structured_output,
+            // Agent Permissions
+            get_agent_permissions,
+            set_agent_permissions,
+            // Agent Environment
+            get_agent_env,
+            set_agent_env_var,
+            delete_agent_env_var,
+            // Agent Run Batches
+            start_batch_run,
+            list_batch_runs,
+            get_batch_run,
+            cancel_batch_run,
+            // Agent Run Comparisons
+            start_run_comparison,
+            list_run_comparisons,
+            get_run_comparison,
+            delete_run_comparison,
+            // Agent Run Search
+            search_agent_runs,
+            // Session Search
+            search_sessions,
+            // Session Comparison
+            compare_sessions,
+            // Session Forks
+            fork_session_at_message,
+            // Session Reattach
+            reattach_session,
+            // Session Archive
+            get_session_disk_usage,
+            cleanup_sessions,
+            list_archived_sessions,
+            restore_archived_session,
+            // Session Tabs
+            open_session_tab,
+            list_session_tabs,
+            focus_session_tab,
+            close_session_tab,
+            send_session_input,
+            get_session_project_lock,
+            set_session_project_lock,
+            queue_session_prompt,
+            list_queued_prompts,
+            reorder_queued_prompts,
+            cancel_queued_prompt,
+            clear_prompt_queue,
+            // Session Titles
+            rename_session,
+            generate_session_title,
+            get_session_auto_titles,
+            set_session_auto_titles,
+            // Shadow Runs
+            start_shadow_run,
+            get_shadow_run,
+            list_shadow_runs,
+            delete_shadow_run,
+            // Transcript Redaction
+            get_redaction_settings,
+            save_redaction_settings,
+            redact_session,
+            export_redacted_session,
+            // Session Sharing
+            share_session,
+            // Project Statistics
+            get_project_stats,
+            get_all_project_stats,
+            // Project Bundles
+            export_project_bundle,
+            import_project_bundle,
+            // Memory Files
+            list_memory_files,
+            get_memory_sections,
+            save_memory_section,
+            merge_memory_sections,
+            remove_memory_section,
+            // Prompt Attachments
+            prepare_prompt_attachments,
+            // Prompt Templates
+            list_prompt_templates,
+            create_prompt_template,
+            update_prompt_template,
+            delete_prompt_template,
+            render_prompt_template,
+            // Project Discovery
+            get_project_discovery_settings,
+            save_project_discovery_settings,
+            discover_projects,
+            // Workspaces
+            list_workspaces,
+            create_workspace,
+            update_workspace,
+            delete_workspace,
+            list_workspace_projects,
+            list_workspace_sessions,
+            // Task Worktrees
+            create_session_worktree,
+            list_task_worktrees,
+            merge_task_worktree,
+            discard_task_worktree,
+            // Agent Run Artifacts
+            list_run_artifacts,
+            read_run_artifact,
+            export_run_artifact,
+            // Agent Run Diffs
+            get_agent_run_diff,
+            revert_agent_run_changes,
+            // Agent Run Pull Requests
+            create_run_pull_request,
+            has_github_token,
+            set_github_token,
+            // Agent Git Hooks
+            install_git_hook,
+            uninstall_git_hook,
+            list_git_hooks,
+            // Issue Links
+            link_issue,
+            list_issue_links,
+            unlink_issue,
+            post_issue_comment,
+            get_issue_tracker_settings,
+            save_issue_tracker_settings,
+            has_issue_tracker_token,
+            set_issue_tracker_token,
+            // External Editor
+            open_in_editor,
+            get_editor_settings,
+            save_editor_settings,
+            // Interrupted Agent Runs
+            list_interrupted_runs,
+            resume_agent_run,
+            dismiss_interrupted_run,
+            // Agent Scheduling
+            create_agent_schedule,
+            list_agent_schedules,
+            set_agent_schedule_enabled,
+            delete_agent_schedule,
+            get_upcoming_scheduled_runs,
+            // Agent File Watches
+            create_agent_file_watch,
+            list_agent_file_watches,
+            set_agent_file_watch_enabled,
+            delete_agent_file_watch,
+            // Agent Webhooks
+            get_webhook_settings,
+            save_webhook_settings,
+            regenerate_webhook_token,
             // Usage & Analytics
             get_usage_stats,
             get_usage_by_date_range,
             get_usage_details,
             get_session_stats,
+            get_agent_costs,
+            get_model_comparison,
+            get_activity_heatmap,
+            get_pricing_settings,
+            save_pricing_settings,
+            export_usage,
+            import_usage,
+            list_usage_imports,
+            remove_usage_import,
+            get_usage_retention,
+            set_usage_retention,
+            purge_usage,
+            get_usage_rollups,
+            get_cost_budgets,
+            set_cost_budgets,
+            get_cost_budget_status,
+            list_cost_anomalies,
+            dismiss_cost_anomaly,
+            get_rate_limit_status,
+            get_rate_limit_settings,
+            set_rate_limit_settings,
             // MCP (Model Context Protocol)
             mcp_add,
             mcp_list,
@@ -272,8 +811,37 @@ fn main() {
             mcp_remove,
             mcp_add_json,
             mcp_add_from_claude_desktop,
+            mcp_import_project_config,
+            mcp_export_servers,
+            mcp_add_websocket_server,
+            check_mcp_websocket_health,
+            mcp_registry_search,
+            mcp_registry_install,
+            get_mcp_registry_settings,
+            save_mcp_registry_settings,
+            get_mcp_log_capture,
+            set_mcp_log_capture,
+            tail_mcp_logs,
+            start_mcp_log_stream,
+            stop_mcp_log_stream,
+            get_mcp_latency_metrics,
+            get_project_mcp_servers,
+            set_project_mcp_server,
+            get_effective_mcp_config,
+            get_mcp_server_environments,
+            set_mcp_server_environment,
+            list_mcp_secrets,
+            set_mcp_secret,
+            delete_mcp_secret,
+            get_mcp_server_secrets,
+            get_mcp_variables,
+            save_mcp_variables,
+            list_mcp_snapshots,
+            diff_mcp_snapshots,
+            rollback_mcp_snapshot,
             mcp_serve,
             mcp_test_connection,
+            mcp_inspect_server,
             mcp_reset_project_choices,
             mcp_get_server_status,
             mcp_read_project_config,
@@ -291,9 +859,13 @@ fn main() {
             commands::slash_commands::slash_command_get,
             commands::slash_commands::slash_command_save,
             commands::slash_commands::slash_command_delete,
+            commands::slash_commands::slash_commands_effective,
             // Proxy Settings
             get_proxy_settings,
             save_proxy_settings,
+            // Settings Audit
+            get_settings_audit,
+            reset_settings,
             // Shell Environment
             get_available_shells,
             get_shell_config,
@@ -301,6 +873,13 @@ fn main() {
             check_wsl_claude,
             auto_detect_wsl_claude,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Runs still going would otherwise outlive the app, along with
+            // everything they started
+            if let tauri::RunEvent::Exit = event {
+                app.state::<ProcessRegistryState>().0.kill_all_trees();
+            }
+        });
 }
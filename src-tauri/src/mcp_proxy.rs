@@ -0,0 +1,745 @@
+//! Logging proxy for stdio MCP servers
+//!
+//! Claude launches stdio servers itself and discards their stderr, so a server
+//! that crashes or rejects its arguments fails silently. A server with log
+//! capture turned on is launched through opcode instead:
+//!
+//! `opcode mcp-proxy --server <name> -- <command> <args...>`
+//!
+//! The proxy starts the real server, relays stdin and stdout untouched and
+//! copies every line of stdin, stdout and stderr into a rotating log file per
+//! server under the app data directory. `${secret:NAME}` and `${var:NAME}`
+//! references in the server's arguments and environment are resolved just
+//! before it starts, so servers using them always launch through the proxy.
+//! The proxy also restarts a server that crashes; see `mcp_supervisor`.
+
+use chrono::Utc;
+use serde_json::Value as JsonValue;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::mcp_supervisor::{
+    self, CrashHistory, ServerLine, ServerState, Session, SupervisorStatus,
+};
+
+/// First argument that switches the opcode binary into proxy mode
+pub const PROXY_SUBCOMMAND: &str = "mcp-proxy";
+
+/// Size at which the current log file is rotated
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Rotated log files kept per server, besides the current one
+const KEPT_LOGS: usize = 3;
+
+const CURRENT_LOG: &str = "current.log";
+
+/// Start of the note logged when a server answers `initialize`, followed by
+/// the milliseconds since it was started
+const INITIALIZED_NOTE: &str = "Initialized in ";
+
+/// How long a restarted server gets to answer the replayed handshake
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Root directory of the per-server log directories
+pub fn logs_root() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("opcode.asterisk.so").join("mcp-logs"))
+}
+
+/// Log directory of one server; the name is reduced to filename-safe characters
+pub fn server_log_dir(root: &Path, server: &str) -> PathBuf {
+    let name: String = server
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match name.trim_start_matches('.') {
+        "" => root.join("_"),
+        name => root.join(name),
+    }
+}
+
+/// The file currently being written in a server's log directory
+pub fn current_log(dir: &Path) -> PathBuf {
+    dir.join(CURRENT_LOG)
+}
+
+/// The `n`th most recently rotated log file in a server's log directory
+pub fn rotated_log(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("{}.{}", CURRENT_LOG, n))
+}
+
+/// A server's log files, oldest first
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    (1..=KEPT_LOGS)
+        .rev()
+        .map(|n| rotated_log(dir, n))
+        .chain(std::iter::once(current_log(dir)))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// The last `lines` lines logged for a server, across rotated files
+pub fn tail(dir: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let mut tail = Vec::new();
+    for path in log_files(dir).into_iter().rev() {
+        let content = fs::read(&path)?;
+        let mut chunk: Vec<String> = String::from_utf8_lossy(&content)
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let keep = chunk.len().min(lines - tail.len());
+        chunk.drain(..chunk.len() - keep);
+        chunk.append(&mut tail);
+        tail = chunk;
+        if tail.len() >= lines {
+            break;
+        }
+    }
+    Ok(tail)
+}
+
+/// The time and startup duration of every `initialize` answered by a
+/// server, oldest first, from its logs
+pub fn initialization_times(dir: &Path) -> Vec<(String, u64)> {
+    let marker = format!("[proxy] {}", INITIALIZED_NOTE);
+    let mut times = Vec::new();
+    for path in log_files(dir) {
+        let Ok(content) = fs::read(&path) else {
+            continue;
+        };
+        for line in String::from_utf8_lossy(&content).lines() {
+            let Some((timestamp, note)) = line.split_once(' ') else {
+                continue;
+            };
+            if let Some(ms) = note
+                .strip_prefix(&marker)
+                .and_then(|rest| rest.strip_suffix(" ms"))
+                .and_then(|ms| ms.parse().ok())
+            {
+                times.push((timestamp.to_string(), ms));
+            }
+        }
+    }
+    times
+}
+
+/// Append-only log file that rotates once it grows past a size limit
+pub struct RotatingLog {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl RotatingLog {
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        Self::with_limit(dir, MAX_LOG_BYTES)
+    }
+
+    pub fn with_limit(dir: &Path, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(current_log(dir))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+        })
+    }
+
+    /// Record one line from `stream` (stdin, stdout, stderr or proxy)
+    pub fn write_line(&mut self, stream: &str, line: &str) -> io::Result<()> {
+        let entry = format!(
+            "{} [{}] {}\n",
+            Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            stream,
+            line.trim_end_matches(['\r', '\n'])
+        );
+        if self.size > 0 && self.size + entry.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(entry.as_bytes())?;
+        self.size += entry.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let _ = fs::remove_file(rotated_log(&self.dir, KEPT_LOGS));
+        for n in (1..KEPT_LOGS).rev() {
+            let _ = fs::rename(rotated_log(&self.dir, n), rotated_log(&self.dir, n + 1));
+        }
+        fs::rename(current_log(&self.dir), rotated_log(&self.dir, 1))?;
+        let reopened = Self::with_limit(&self.dir, self.max_bytes)?;
+        *self = reopened;
+        Ok(())
+    }
+}
+
+/// Link in the app data directory that server definitions launch opcode
+/// through. Unlike the running executable's path, it survives the app being
+/// updated or moved, as long as opcode is started again.
+#[cfg(unix)]
+fn launcher_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("opcode.asterisk.so").join("bin").join("opcode"))
+}
+
+/// Point the launcher at the running executable; called at startup so server
+/// definitions written by an earlier install keep working
+#[cfg(unix)]
+pub fn install_launcher() -> Result<PathBuf, String> {
+    let executable = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the opcode executable: {}", e))?;
+    let launcher = launcher_path().ok_or_else(|| "Could not find data directory".to_string())?;
+    if fs::read_link(&launcher).ok().as_ref() == Some(&executable) {
+        return Ok(launcher);
+    }
+    if let Some(parent) = launcher.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    // Swap the link in with a rename so running servers never see it missing
+    let temp = launcher.with_extension(format!("tmp-{}", std::process::id()));
+    let _ = fs::remove_file(&temp);
+    std::os::unix::fs::symlink(&executable, &temp)
+        .and_then(|_| fs::rename(&temp, &launcher))
+        .map_err(|e| format!("Failed to link {}: {}", launcher.display(), e))?;
+    Ok(launcher)
+}
+
+/// Windows can't link without elevation, so definitions use the executable,
+/// whose install location doesn't change between updates
+#[cfg(not(unix))]
+pub fn install_launcher() -> Result<PathBuf, String> {
+    std::env::current_exe().map_err(|e| format!("Failed to locate the opcode executable: {}", e))
+}
+
+/// The opcode executable servers are launched through
+pub fn proxy_executable() -> Result<PathBuf, String> {
+    install_launcher()
+}
+
+/// Whether a config file is shared with others, like a project's `.mcp.json`.
+/// Its servers can't launch through opcode, whose launcher only exists here.
+pub fn is_shared_config(path: &Path) -> bool {
+    path.file_name().and_then(|name| name.to_str()) == Some(".mcp.json")
+}
+
+/// What is logged of a JSON-RPC line: the params of requests and
+/// notifications carry tool arguments, which may be secret, so only their
+/// method and id are kept
+fn redact_params(line: &[u8]) -> String {
+    let text = String::from_utf8_lossy(line);
+    match serde_json::from_str::<JsonValue>(&text) {
+        Ok(JsonValue::Object(mut message)) if message.contains_key("params") => {
+            message.insert("params".to_string(), JsonValue::from("[redacted]"));
+            JsonValue::Object(message).to_string()
+        }
+        _ => text.into_owned(),
+    }
+}
+
+/// Arguments that make the proxy launch `command` with `args` for `server`
+pub fn proxy_args(server: &str, command: &str, args: &[String]) -> Vec<String> {
+    [PROXY_SUBCOMMAND, "--server", server, "--", command]
+        .into_iter()
+        .map(str::to_string)
+        .chain(args.iter().cloned())
+        .collect()
+}
+
+/// A stdio server definition rewritten to launch through the proxy, or None
+/// for remote servers and definitions that are already wrapped
+pub fn wrap(server: &str, config: &JsonValue, proxy: &Path) -> Option<JsonValue> {
+    let command = config.get("command").and_then(|v| v.as_str())?;
+    if config.get("url").is_some() || unwrap(config).is_some() {
+        return None;
+    }
+    let mut args: Vec<JsonValue> = proxy_args(server, command, &[])
+        .into_iter()
+        .map(JsonValue::from)
+        .collect();
+    if let Some(original) = config.get("args").and_then(|v| v.as_array()) {
+        args.extend(original.iter().cloned());
+    }
+
+    let mut wrapped = config.clone();
+    wrapped["command"] = proxy.to_string_lossy().into_owned().into();
+    wrapped["args"] = args.into();
+    Some(wrapped)
+}
+
+/// The original definition of a server launched through the proxy, or None
+/// when it isn't wrapped
+pub fn unwrap(config: &JsonValue) -> Option<JsonValue> {
+    let args = config.get("args")?.as_array()?;
+    if args.first()?.as_str()? != PROXY_SUBCOMMAND {
+        return None;
+    }
+    let separator = args.iter().position(|arg| arg == "--")?;
+    let command = args.get(separator + 1)?.clone();
+
+    let mut original = config.clone();
+    original["command"] = command;
+    original["args"] = args[separator + 2..].to_vec().into();
+    Some(original)
+}
+
+/// Copy `reader` to `writer` line by line, logging each line under `stream`.
+/// Lines for which `forward` returns false are logged but not copied.
+fn relay(
+    reader: impl Read,
+    mut writer: impl Write,
+    log: Option<Arc<Mutex<RotatingLog>>>,
+    stream: &str,
+    mut forward: impl FnMut(&[u8]) -> bool,
+) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if forward(&line)
+            && writer
+                .write_all(&line)
+                .and_then(|_| writer.flush())
+                .is_err()
+        {
+            break;
+        }
+        if let Some(log) = &log {
+            let logged = match stream {
+                "stderr" => String::from_utf8_lossy(&line).into_owned(),
+                _ => redact_params(&line),
+            };
+            if let Ok(mut log) = log.lock() {
+                let _ = log.write_line(stream, &logged);
+            }
+        }
+    }
+}
+
+/// The client's side of the server's stdin, which outlives server restarts
+#[derive(Default)]
+struct ClientInput {
+    /// stdin of the running server; None while it is being restarted
+    server: Option<ChildStdin>,
+    /// Lines that arrived while no server was running, with their request ids
+    queued: Vec<(Vec<u8>, Option<JsonValue>)>,
+    /// Whether the client closed its end
+    closed: bool,
+}
+
+impl ClientInput {
+    fn send(&mut self, line: Vec<u8>, id: Option<JsonValue>, session: &Mutex<Session>) {
+        if let Some(server) = &mut self.server {
+            let track = |f: fn(&mut Session, &JsonValue)| {
+                if let (Some(id), Ok(mut session)) = (&id, session.lock()) {
+                    f(&mut session, id);
+                }
+            };
+            track(Session::sent);
+            if server.write_all(&line).and_then(|_| server.flush()).is_ok() {
+                return;
+            }
+            // The server is gone; the line goes to its replacement
+            track(Session::unsent);
+            self.server = None;
+        }
+        self.queued.push((line, id));
+    }
+
+    fn attach(&mut self, server: ChildStdin, session: &Mutex<Session>) {
+        self.server = Some(server);
+        for (line, id) in std::mem::take(&mut self.queued) {
+            self.send(line, id, session);
+        }
+    }
+}
+
+/// Relay the client's stdin to whichever server is running
+fn forward_client(
+    input: Arc<Mutex<ClientInput>>,
+    session: Arc<Mutex<Session>>,
+    log: Option<Arc<Mutex<RotatingLog>>>,
+) {
+    let mut reader = BufReader::new(io::stdin());
+    loop {
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if let Some(log) = &log {
+            if let Ok(mut log) = log.lock() {
+                let _ = log.write_line("stdin", &redact_params(&line));
+            }
+        }
+        let id = session
+            .lock()
+            .ok()
+            .and_then(|mut session| session.client_line(&line));
+        if let Ok(mut input) = input.lock() {
+            input.send(line, id, &session);
+        }
+    }
+    // Closing the server's stdin tells it to shut down
+    if let Ok(mut input) = input.lock() {
+        input.closed = true;
+        input.server = None;
+    }
+}
+
+/// Relay one server process until it exits. A restarted server is handed the
+/// client's handshake before the client's queued lines.
+fn run_server(
+    mut child: Child,
+    input: &Mutex<ClientInput>,
+    session: &Arc<Mutex<Session>>,
+    log: &Option<Arc<Mutex<RotatingLog>>>,
+    last_stderr: &Arc<Mutex<Option<String>>>,
+    replay: bool,
+) -> io::Result<ExitStatus> {
+    let started = Instant::now();
+    let (replayed, replay_answered) = mpsc::channel();
+    let relays: Vec<_> = [
+        child.stdout.take().map(|stdout| {
+            let (log, session) = (log.clone(), Arc::clone(session));
+            thread::spawn(move || {
+                let note_log = log.clone();
+                relay(stdout, io::stdout(), log, "stdout", |line| {
+                    let kind = session
+                        .lock()
+                        .map_or(ServerLine::Relay, |mut session| session.server_line(line));
+                    let ServerLine::Initialized {
+                        replayed: was_replayed,
+                    } = kind
+                    else {
+                        return true;
+                    };
+                    if let Some(Ok(mut log)) = note_log.as_ref().map(|log| log.lock()) {
+                        let message =
+                            format!("{}{} ms", INITIALIZED_NOTE, started.elapsed().as_millis());
+                        let _ = log.write_line("proxy", &message);
+                    }
+                    if was_replayed {
+                        let _ = replayed.send(());
+                    }
+                    !was_replayed
+                })
+            })
+        }),
+        child.stderr.take().map(|stderr| {
+            let (log, last_stderr) = (log.clone(), Arc::clone(last_stderr));
+            thread::spawn(move || {
+                relay(stderr, io::stderr(), log, "stderr", |line| {
+                    let line = String::from_utf8_lossy(line);
+                    if !line.trim().is_empty() {
+                        if let Ok(mut last) = last_stderr.lock() {
+                            *last = Some(line.trim().to_string());
+                        }
+                    }
+                    true
+                })
+            })
+        }),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let handshake = session.lock().ok().and_then(|session| session.replay());
+        if let (true, Some((initialize, initialized))) = (replay, handshake) {
+            if stdin.write_all(initialize.as_bytes()).is_ok() {
+                let _ = replay_answered.recv_timeout(REPLAY_TIMEOUT);
+                if let Some(initialized) = initialized {
+                    let _ = stdin.write_all(initialized.as_bytes());
+                }
+            }
+        }
+        if let Ok(mut input) = input.lock() {
+            if !input.closed {
+                input.attach(stdin, session);
+            }
+        }
+    }
+
+    let status = child.wait();
+    if let Ok(mut input) = input.lock() {
+        input.server = None;
+    }
+    for relay in relays {
+        let _ = relay.join();
+    }
+    status
+}
+
+/// Run the proxy with the arguments after the subcommand; returns the exit code
+pub fn run(args: &[String]) -> i32 {
+    let (server, command) = match args {
+        [flag, server, separator, command @ ..]
+            if flag == "--server" && separator == "--" && !command.is_empty() =>
+        {
+            (server, command)
+        }
+        _ => {
+            eprintln!(
+                "usage: opcode {} --server <name> -- <command> [args...]",
+                PROXY_SUBCOMMAND
+            );
+            return 2;
+        }
+    };
+
+    // A server without a log is still better than no server at all
+    let dir = logs_root().map(|root| server_log_dir(&root, server));
+    let log = dir
+        .as_deref()
+        .and_then(|dir| RotatingLog::open(dir).ok())
+        .map(|log| Arc::new(Mutex::new(log)));
+    let note = |message: String| {
+        if let Some(log) = &log {
+            if let Ok(mut log) = log.lock() {
+                let _ = log.write_line("proxy", &message);
+            }
+        }
+    };
+    let mut status = SupervisorStatus {
+        server: server.clone(),
+        ..Default::default()
+    };
+    let report = |status: &mut SupervisorStatus| {
+        if let Some(dir) = &dir {
+            let _ = mcp_supervisor::write_status(dir, status);
+        }
+    };
+
+    // Secrets are resolved here, so they only ever exist in the server's process
+    let mut resolved = command.to_vec();
+    let env = match crate::commands::mcp_secrets::resolve_for_spawn(&mut resolved) {
+        Ok(env) => env,
+        Err(e) => {
+            note(format!("Failed to resolve references: {}", e));
+            eprintln!("Failed to start MCP server '{}': {}", server, e);
+            return 1;
+        }
+    };
+
+    // stdin is read for the proxy's whole life, across server restarts
+    let input = Arc::new(Mutex::new(ClientInput::default()));
+    let session = Arc::new(Mutex::new(Session::default()));
+    {
+        let (input, session, log) = (Arc::clone(&input), Arc::clone(&session), log.clone());
+        thread::spawn(move || forward_client(input, session, log));
+    }
+
+    let mut crashes = CrashHistory::default();
+    loop {
+        let spawned = Command::new(&resolved[0])
+            .args(&resolved[1..])
+            .envs(env.iter().cloned())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let last_stderr = Arc::new(Mutex::new(None));
+        let exit = match spawned {
+            Ok(child) => {
+                note(format!(
+                    "Started '{}' (pid {})",
+                    command.join(" "),
+                    child.id()
+                ));
+                status.state = ServerState::Running;
+                status.pid = Some(child.id());
+                status.next_restart_at = None;
+                report(&mut status);
+                let replay = status.restarts > 0;
+                run_server(child, &input, &session, &log, &last_stderr, replay)
+                    .map_err(|e| format!("Failed to wait for server: {}", e))
+            }
+            Err(e) if status.restarts == 0 => {
+                note(format!("Failed to start '{}': {}", command.join(" "), e));
+                eprintln!("Failed to start MCP server '{}': {}", server, e);
+                return 127;
+            }
+            Err(e) => Err(format!("Failed to start '{}': {}", command.join(" "), e)),
+        };
+        let code = exit.as_ref().map_or(1, |status| status.code().unwrap_or(1));
+        status.pid = None;
+
+        let client_closed = input.lock().map_or(true, |input| input.closed);
+        let crashed = match &exit {
+            Ok(exit_status) => !exit_status.success() && !client_closed,
+            Err(_) => !client_closed,
+        };
+        let description = match &exit {
+            Ok(exit_status) => format!("Exited with {}", exit_status),
+            Err(e) => e.clone(),
+        };
+        if !crashed {
+            note(description);
+            status.state = ServerState::Stopped;
+            report(&mut status);
+            return code;
+        }
+
+        note(format!("Crashed: {}", description));
+        status.crashes += 1;
+        status.last_error = last_stderr
+            .lock()
+            .ok()
+            .and_then(|last| last.clone())
+            .or(Some(description));
+
+        // Requests in flight are lost with the process; answer them so the
+        // client isn't left waiting
+        let delay = crashes.record(Instant::now());
+        let reason = format!(
+            "MCP server '{}' crashed{}",
+            server,
+            if delay.is_some() {
+                " and is being restarted"
+            } else {
+                ""
+            }
+        );
+        let failed = session
+            .lock()
+            .map(|mut session| session.fail_pending(&reason))
+            .unwrap_or_default();
+        let mut stdout = io::stdout().lock();
+        for response in failed {
+            let _ = stdout.write_all(response.as_bytes());
+        }
+        let _ = stdout.flush();
+        drop(stdout);
+
+        let Some(delay) = delay else {
+            note(format!(
+                "Giving up after {} crashes; last error: {}",
+                status.crashes,
+                status.last_error.as_deref().unwrap_or("unknown")
+            ));
+            status.state = ServerState::Failed;
+            report(&mut status);
+            return code;
+        };
+        note(format!("Restarting in {} ms", delay.as_millis()));
+        status.state = ServerState::Restarting;
+        status.next_restart_at = chrono::Duration::from_std(delay)
+            .ok()
+            .map(|delay| (Utc::now() + delay).to_rfc3339());
+        report(&mut status);
+        thread::sleep(delay);
+
+        if input.lock().map_or(true, |input| input.closed) {
+            note("Client disconnected before the restart".to_string());
+            status.state = ServerState::Stopped;
+            status.next_restart_at = None;
+            report(&mut status);
+            return code;
+        }
+        status.restarts += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_tail_and_wrapping() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = server_log_dir(root.path(), "../my server");
+        assert_eq!(dir, root.path().join("_my_server"));
+
+        let mut log = RotatingLog::with_limit(&dir, 200).unwrap();
+        for i in 0..40 {
+            log.write_line("stderr", &format!("line {}\n", i)).unwrap();
+        }
+        let files = log_files(&dir);
+        assert_eq!(files.len(), KEPT_LOGS + 1);
+        assert_eq!(files.last().unwrap(), &current_log(&dir));
+        assert!(files
+            .iter()
+            .all(|path| fs::metadata(path).unwrap().len() <= 200));
+
+        let lines = tail(&dir, 6).unwrap();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].ends_with("[stderr] line 34"), "{}", lines[0]);
+        assert!(lines[5].ends_with("[stderr] line 39"), "{}", lines[5]);
+        assert!(tail(&dir, 1000).unwrap().len() < 40);
+        assert!(tail(&root.path().join("missing"), 5).unwrap().is_empty());
+
+        let started = root.path().join("started");
+        let mut log = RotatingLog::open(&started).unwrap();
+        log.write_line("proxy", "Started 'npx' (pid 7)").unwrap();
+        log.write_line("proxy", "Initialized in 1250 ms").unwrap();
+        log.write_line("stdout", "Initialized in 3 ms").unwrap();
+        let times = initialization_times(&started);
+        assert_eq!(times.len(), 1);
+        assert_eq!(times[0].1, 1250);
+
+        let config = serde_json::json!({
+            "type": "stdio",
+            "command": "npx",
+            "args": ["-y", "server-memory"],
+            "env": {"DEBUG": "1"},
+        });
+        let wrapped = wrap("memory", &config, Path::new("/opt/opcode")).unwrap();
+        assert_eq!(wrapped["command"], "/opt/opcode");
+        assert_eq!(
+            wrapped["args"],
+            serde_json::json!([
+                "mcp-proxy",
+                "--server",
+                "memory",
+                "--",
+                "npx",
+                "-y",
+                "server-memory"
+            ])
+        );
+        assert_eq!(wrapped["env"], config["env"]);
+        assert!(wrap("memory", &wrapped, Path::new("/opt/opcode")).is_none());
+        assert_eq!(unwrap(&wrapped).unwrap(), config);
+        assert!(unwrap(&config).is_none());
+        assert!(wrap(
+            "remote",
+            &serde_json::json!({"url": "https://x"}),
+            Path::new("/o")
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_redact_params() {
+        let call = br#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"arguments":{"token":"abc"}}}"#;
+        let logged: JsonValue = serde_json::from_str(&redact_params(call)).unwrap();
+        assert_eq!(logged["method"], "tools/call");
+        assert_eq!(logged["id"], 3);
+        assert_eq!(logged["params"], "[redacted]");
+
+        let result = br#"{"jsonrpc":"2.0","id":3,"result":{}}"#;
+        assert_eq!(redact_params(result), String::from_utf8_lossy(result));
+        assert_eq!(redact_params(b"not json\n"), "not json\n");
+    }
+}
@@ -0,0 +1,320 @@
+//! Supervision of stdio MCP servers launched through the proxy
+//!
+//! A server that crashes mid-session takes its tools with it until Claude
+//! restarts. The proxy keeps the client's end of the connection open instead:
+//! when the server exits unexpectedly it answers the requests the server never
+//! did with an error, restarts the server after an exponentially growing
+//! delay, replays the client's `initialize` handshake to the new process and
+//! carries on relaying. A server that keeps crashing is given up on.
+//!
+//! Each proxy records its server's state in `status.json` in the server's log
+//! directory. The desktop app polls those files and emits
+//! `mcp-server-status` with every status that changes.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::mcp_proxy;
+
+const STATUS_FILE: &str = "status.json";
+
+/// Delay before the first restart; each further crash in the window doubles it
+const BASE_RESTART_DELAY: Duration = Duration::from_millis(500);
+
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// Crashes within `RESTART_WINDOW` after which the server is given up on
+const MAX_CRASHES: usize = 5;
+
+const RESTART_WINDOW: Duration = Duration::from_secs(300);
+
+/// Request id of the `initialize` replayed to a restarted server, whose
+/// response is swallowed rather than relayed to the client
+pub const REPLAY_ID: &str = "opcode-proxy-replay";
+
+/// JSON-RPC error code of requests lost in a crash
+const SERVER_CRASHED: i64 = -32000;
+
+/// How often the app checks the status files for changes
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Lifecycle of a supervised server
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerState {
+    #[default]
+    Running,
+    /// Crashed and waiting to be restarted
+    Restarting,
+    /// Crashed too often and given up on
+    Failed,
+    /// Exited normally, or the client went away
+    Stopped,
+}
+
+/// Payload of `mcp-server-status`, as written by the proxy
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SupervisorStatus {
+    pub server: String,
+    pub state: ServerState,
+    pub pid: Option<u32>,
+    /// Unexpected exits since the client connected
+    pub crashes: u32,
+    pub restarts: u32,
+    /// The last line the server wrote to stderr before its latest crash, or
+    /// how it exited when it wrote nothing
+    pub last_error: Option<String>,
+    /// When a crashed server is restarted (RFC 3339)
+    pub next_restart_at: Option<String>,
+    pub updated_at: String,
+}
+
+pub fn status_path(dir: &Path) -> PathBuf {
+    dir.join(STATUS_FILE)
+}
+
+/// Replace a server's status file, stamping the status with the current time
+pub fn write_status(dir: &Path, status: &mut SupervisorStatus) -> io::Result<()> {
+    status.updated_at = Utc::now().to_rfc3339();
+    fs::create_dir_all(dir)?;
+    let json = serde_json::to_vec_pretty(status)?;
+    let temp = dir.join(format!("{}.tmp", STATUS_FILE));
+    fs::write(&temp, json)?;
+    fs::rename(&temp, status_path(dir))
+}
+
+pub fn read_status(dir: &Path) -> Option<SupervisorStatus> {
+    let json = fs::read(status_path(dir)).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+/// The status of every server that ran through the proxy
+pub fn read_statuses(root: &Path) -> Vec<SupervisorStatus> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| read_status(&entry.path()))
+        .collect()
+}
+
+/// Recent crashes of a server, which decide when it is restarted
+#[derive(Debug, Default)]
+pub struct CrashHistory(VecDeque<Instant>);
+
+impl CrashHistory {
+    /// Record a crash at `now`; returns how long to wait before restarting,
+    /// or None when the server crashed too often within the window
+    pub fn record(&mut self, now: Instant) -> Option<Duration> {
+        while self
+            .0
+            .front()
+            .is_some_and(|crash| now.duration_since(*crash) > RESTART_WINDOW)
+        {
+            self.0.pop_front();
+        }
+        self.0.push_back(now);
+        if self.0.len() > MAX_CRASHES {
+            return None;
+        }
+        let delay = BASE_RESTART_DELAY.saturating_mul(2u32.pow(self.0.len() as u32 - 1));
+        Some(delay.min(MAX_RESTART_DELAY))
+    }
+}
+
+/// A line written by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerLine {
+    /// Anything for the client
+    Relay,
+    /// The response to `initialize`; a replayed one must not reach the client
+    Initialized { replayed: bool },
+}
+
+/// What the proxy knows of the client's session, to carry it over to a
+/// restarted server
+#[derive(Debug, Default)]
+pub struct Session {
+    initialize: Option<JsonValue>,
+    initialized: Option<JsonValue>,
+    /// Requests sent to the server and not answered yet, keyed by their id
+    pending: HashMap<String, JsonValue>,
+}
+
+impl Session {
+    /// Note a line from the client; returns its id when it is a request
+    pub fn client_line(&mut self, line: &[u8]) -> Option<JsonValue> {
+        let message: JsonValue = serde_json::from_slice(line).ok()?;
+        match message.get("method")?.as_str()? {
+            "initialize" => self.initialize = Some(message.clone()),
+            "notifications/initialized" => self.initialized = Some(message.clone()),
+            _ => {}
+        }
+        message.get("id").cloned()
+    }
+
+    /// Note that a request reached the server
+    pub fn sent(&mut self, id: &JsonValue) {
+        self.pending.insert(id.to_string(), id.clone());
+    }
+
+    /// Forget a request that never reached the server
+    pub fn unsent(&mut self, id: &JsonValue) {
+        self.pending.remove(&id.to_string());
+    }
+
+    /// Note a line from the server and work out what it is
+    pub fn server_line(&mut self, line: &[u8]) -> ServerLine {
+        let Ok(message) = serde_json::from_slice::<JsonValue>(line) else {
+            return ServerLine::Relay;
+        };
+        if message.get("method").is_some() {
+            return ServerLine::Relay;
+        }
+        let Some(id) = message.get("id") else {
+            return ServerLine::Relay;
+        };
+        if id == REPLAY_ID {
+            return ServerLine::Initialized { replayed: true };
+        }
+        self.pending.remove(&id.to_string());
+        let initialize_id = self.initialize.as_ref().and_then(|init| init.get("id"));
+        if initialize_id == Some(id) {
+            ServerLine::Initialized { replayed: false }
+        } else {
+            ServerLine::Relay
+        }
+    }
+
+    /// The lines that redo the client's handshake with a new server process:
+    /// `initialize` under `REPLAY_ID`, and the `initialized` notification to
+    /// send once it is answered. None before the client initialized.
+    pub fn replay(&self) -> Option<(String, Option<String>)> {
+        let mut initialize = self.initialize.clone()?;
+        initialize["id"] = REPLAY_ID.into();
+        Some((
+            format!("{}\n", initialize),
+            self.initialized
+                .as_ref()
+                .map(|notification| format!("{}\n", notification)),
+        ))
+    }
+
+    /// Error responses for the requests the crashed server never answered
+    pub fn fail_pending(&mut self, reason: &str) -> Vec<String> {
+        self.pending
+            .drain()
+            .map(|(_, id)| {
+                let response = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": SERVER_CRASHED, "message": reason},
+                });
+                format!("{}\n", response)
+            })
+            .collect()
+    }
+}
+
+/// Poll the status files written by the proxies and emit
+/// `mcp-server-status` with each status that changed
+pub fn start_status_watcher(app: AppHandle) {
+    let Some(root) = mcp_proxy::logs_root() else {
+        return;
+    };
+    tauri::async_runtime::spawn(async move {
+        let mut seen: HashMap<String, SupervisorStatus> = read_statuses(&root)
+            .into_iter()
+            .map(|status| (status.server.clone(), status))
+            .collect();
+        loop {
+            tokio::time::sleep(STATUS_POLL_INTERVAL).await;
+            for status in read_statuses(&root) {
+                if seen.get(&status.server) != Some(&status) {
+                    let _ = app.emit("mcp-server-status", &status);
+                    seen.insert(status.server.clone(), status);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_backoff_and_cap() {
+        let mut history = CrashHistory::default();
+        let start = Instant::now();
+        let delays: Vec<_> = (0..MAX_CRASHES as u64)
+            .map(|i| history.record(start + Duration::from_secs(i)))
+            .collect();
+        assert_eq!(
+            delays,
+            [500, 1000, 2000, 4000, 8000].map(|ms| Some(Duration::from_millis(ms)))
+        );
+        assert_eq!(history.record(start + Duration::from_secs(10)), None);
+
+        // Crashes outside the window no longer count
+        let later = start + RESTART_WINDOW + Duration::from_secs(20);
+        assert_eq!(history.record(later), Some(BASE_RESTART_DELAY));
+    }
+
+    #[test]
+    fn test_session_replay_and_pending() {
+        let mut session = Session::default();
+        assert!(session.replay().is_none());
+
+        let initialize = br#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#;
+        let id = session.client_line(initialize).unwrap();
+        session.sent(&id);
+        assert_eq!(
+            session.server_line(br#"{"jsonrpc":"2.0","id":0,"result":{}}"#),
+            ServerLine::Initialized { replayed: false }
+        );
+        assert_eq!(
+            session.client_line(br#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#),
+            None
+        );
+        for id in [1, 2] {
+            let request = format!(r#"{{"jsonrpc":"2.0","id":{},"method":"tools/call"}}"#, id);
+            let id = session.client_line(request.as_bytes()).unwrap();
+            session.sent(&id);
+        }
+        // A response to a server-initiated request isn't tracked
+        assert_eq!(
+            session.client_line(br#"{"jsonrpc":"2.0","id":9,"result":{}}"#),
+            None
+        );
+        assert_eq!(
+            session.server_line(br#"{"jsonrpc":"2.0","id":1,"result":{}}"#),
+            ServerLine::Relay
+        );
+
+        let failed = session.fail_pending("crashed");
+        assert_eq!(failed.len(), 1);
+        let failed: JsonValue = serde_json::from_str(&failed[0]).unwrap();
+        assert_eq!(failed["id"], 2);
+        assert_eq!(failed["error"]["code"], SERVER_CRASHED);
+        assert!(session.fail_pending("crashed").is_empty());
+
+        let (initialize, initialized) = session.replay().unwrap();
+        let initialize: JsonValue = serde_json::from_str(&initialize).unwrap();
+        assert_eq!(initialize["id"], REPLAY_ID);
+        assert_eq!(initialize["method"], "initialize");
+        assert!(initialized.unwrap().contains("notifications/initialized"));
+        assert_eq!(
+            session.server_line(br#"{"jsonrpc":"2.0","id":"opcode-proxy-replay","result":{}}"#),
+            ServerLine::Initialized { replayed: true }
+        );
+    }
+}
@@ -1,3 +1,17 @@
+pub mod priority;
+pub mod prompt_queue;
+pub mod queue;
 pub mod registry;
+pub mod scheduler;
+pub mod tabs;
+pub mod tree;
+#[cfg(windows)]
+mod win32;
 
+pub use priority::*;
+pub use prompt_queue::*;
+pub use queue::*;
 pub use registry::*;
+pub use scheduler::*;
+pub use tabs::*;
+pub use tree::*;
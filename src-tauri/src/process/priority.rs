@@ -0,0 +1,95 @@
+/// Nice value background runs get on Unix
+#[cfg(unix)]
+const BACKGROUND_NICE: libc::c_int = 10;
+
+/// Lower a process to background priority, so its CPU and disk use gives way
+/// to the rest of the machine. Processes it starts afterwards inherit it.
+/// Returns whether it worked.
+#[cfg(target_os = "linux")]
+pub fn set_background_priority(pid: u32) -> bool {
+    // Niceness and I/O priority belong to each thread on Linux, and the
+    // process may have started threads already
+    let mut tids: Vec<u32> = std::fs::read_dir(format!("/proc/{}/task", pid))
+        .map(|tasks| {
+            tasks
+                .filter_map(|task| task.ok()?.file_name().to_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    if tids.is_empty() {
+        tids.push(pid);
+    }
+
+    // Lowest best-effort I/O priority, as `ionice -c2 -n7`
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_BACKGROUND: libc::c_int = (2 << 13) | 7;
+    tids.into_iter().fold(false, |lowered, tid| {
+        // SAFETY: both calls only change scheduling priorities; a stale tid
+        // makes them fail harmlessly
+        let niced = unsafe {
+            libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, BACKGROUND_NICE) == 0
+        };
+        unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                tid as libc::c_int,
+                IOPRIO_BACKGROUND,
+            );
+        }
+        lowered || niced
+    })
+}
+
+/// Lower a process to background priority, so its CPU use gives way to the
+/// rest of the machine. Processes it starts afterwards inherit it. Returns
+/// whether it worked.
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn set_background_priority(pid: u32) -> bool {
+    // SAFETY: setpriority only changes the scheduling priority; a stale pid
+    // makes it fail harmlessly
+    unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, BACKGROUND_NICE) == 0 }
+}
+
+/// Lower a process to the below-normal priority class, so its CPU use gives
+/// way to the rest of the machine. Processes it starts afterwards inherit it.
+/// Returns whether it worked.
+#[cfg(windows)]
+pub fn set_background_priority(pid: u32) -> bool {
+    use super::win32::*;
+    // SAFETY: the handle is only used after it was opened and is closed afterwards
+    unsafe {
+        let handle = OpenProcess(PROCESS_SET_INFORMATION, 0, pid);
+        if handle == 0 {
+            return false;
+        }
+        let lowered = SetPriorityClass(handle, BELOW_NORMAL_PRIORITY_CLASS) != 0;
+        CloseHandle(handle);
+        lowered
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn set_background_priority(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_set_background_priority() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        assert!(set_background_priority(child.id()));
+        // SAFETY: getpriority only reads the scheduling priority
+        let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, child.id() as libc::id_t) };
+        assert!(nice >= BACKGROUND_NICE);
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
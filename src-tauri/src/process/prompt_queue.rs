@@ -0,0 +1,176 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// A prompt waiting for its session to finish the current turn
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueuedPrompt {
+    pub id: String,
+    pub prompt: String,
+    pub model: String,
+    pub attachments: Option<Vec<String>>,
+    pub queued_at: DateTime<Utc>,
+}
+
+/// Prompts queued per session tab, in the order they will be sent. Queues are keyed
+/// by tab rather than session since a new session has no id until Claude reports it.
+#[derive(Default)]
+pub struct PromptQueue {
+    queues: Mutex<HashMap<String, VecDeque<QueuedPrompt>>>,
+}
+
+impl PromptQueue {
+    /// Add a prompt to the back of a tab's queue
+    pub fn push(
+        &self,
+        tab_id: &str,
+        prompt: String,
+        model: String,
+        attachments: Option<Vec<String>>,
+    ) -> Result<QueuedPrompt, String> {
+        let queued = QueuedPrompt {
+            id: uuid::Uuid::new_v4().to_string(),
+            prompt,
+            model,
+            attachments,
+            queued_at: Utc::now(),
+        };
+        let mut queues = self.queues.lock().map_err(|e| e.to_string())?;
+        queues
+            .entry(tab_id.to_string())
+            .or_default()
+            .push_back(queued.clone());
+        Ok(queued)
+    }
+
+    /// Take the next prompt to send
+    pub fn pop(&self, tab_id: &str) -> Result<Option<QueuedPrompt>, String> {
+        let mut queues = self.queues.lock().map_err(|e| e.to_string())?;
+        let Some(queue) = queues.get_mut(tab_id) else {
+            return Ok(None);
+        };
+        let next = queue.pop_front();
+        if queue.is_empty() {
+            queues.remove(tab_id);
+        }
+        Ok(next)
+    }
+
+    /// Put back a prompt that couldn't be sent, ahead of the others
+    pub fn requeue(&self, tab_id: &str, prompt: QueuedPrompt) -> Result<(), String> {
+        let mut queues = self.queues.lock().map_err(|e| e.to_string())?;
+        queues
+            .entry(tab_id.to_string())
+            .or_default()
+            .push_front(prompt);
+        Ok(())
+    }
+
+    pub fn list(&self, tab_id: &str) -> Result<Vec<QueuedPrompt>, String> {
+        let queues = self.queues.lock().map_err(|e| e.to_string())?;
+        Ok(queues
+            .get(tab_id)
+            .map(|queue| queue.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// Reorder a tab's queue. `prompt_ids` must name every queued prompt exactly once.
+    pub fn reorder(&self, tab_id: &str, prompt_ids: &[String]) -> Result<(), String> {
+        let mut queues = self.queues.lock().map_err(|e| e.to_string())?;
+        let queue = queues.get_mut(tab_id).ok_or("No prompts are queued")?;
+
+        let complete = prompt_ids.len() == queue.len()
+            && queue.iter().all(|prompt| prompt_ids.contains(&prompt.id));
+        if !complete {
+            return Err("The new order must list every queued prompt once".to_string());
+        }
+
+        let mut reordered = VecDeque::with_capacity(queue.len());
+        for id in prompt_ids {
+            if let Some(index) = queue.iter().position(|prompt| &prompt.id == id) {
+                reordered.extend(queue.remove(index));
+            }
+        }
+        *queue = reordered;
+        Ok(())
+    }
+
+    /// Remove one queued prompt
+    pub fn cancel(&self, tab_id: &str, prompt_id: &str) -> Result<QueuedPrompt, String> {
+        let mut queues = self.queues.lock().map_err(|e| e.to_string())?;
+        let queue = queues
+            .get_mut(tab_id)
+            .ok_or_else(|| format!("Queued prompt {} not found", prompt_id))?;
+        let cancelled = queue
+            .iter()
+            .position(|p| p.id == prompt_id)
+            .and_then(|index| queue.remove(index))
+            .ok_or_else(|| format!("Queued prompt {} not found", prompt_id))?;
+        if queue.is_empty() {
+            queues.remove(tab_id);
+        }
+        Ok(cancelled)
+    }
+
+    /// Drop a tab's whole queue, returning how many prompts it held
+    pub fn clear(&self, tab_id: &str) -> Result<usize, String> {
+        let mut queues = self.queues.lock().map_err(|e| e.to_string())?;
+        Ok(queues.remove(tab_id).map(|queue| queue.len()).unwrap_or(0))
+    }
+}
+
+/// Global prompt queue state
+#[derive(Default)]
+pub struct PromptQueueState(pub Arc<PromptQueue>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_queue_fifo_reorder_and_cancel() {
+        let queue = PromptQueue::default();
+        let push = |tab: &str, prompt: &str, model: &str| {
+            queue
+                .push(tab, prompt.to_string(), model.to_string(), None)
+                .unwrap()
+        };
+        let a = push("tab", "first", "sonnet");
+        let b = push("tab", "second", "sonnet");
+        let c = push("tab", "third", "opus");
+        push("other", "elsewhere", "sonnet");
+
+        queue
+            .reorder("tab", &[c.id.clone(), a.id.clone(), b.id.clone()])
+            .unwrap();
+        let ids = |queue: &PromptQueue| -> Vec<String> {
+            queue
+                .list("tab")
+                .unwrap()
+                .into_iter()
+                .map(|p| p.id)
+                .collect()
+        };
+        assert_eq!(ids(&queue), vec![c.id.clone(), a.id.clone(), b.id.clone()]);
+
+        // A bad order changes nothing
+        assert!(queue
+            .reorder("tab", &[a.id.clone(), "missing".to_string()])
+            .is_err());
+        assert_eq!(ids(&queue), vec![c.id.clone(), a.id.clone(), b.id.clone()]);
+
+        assert_eq!(queue.cancel("tab", &a.id).unwrap().prompt, "first");
+        assert!(queue.cancel("tab", &a.id).is_err());
+
+        let next = queue.pop("tab").unwrap().unwrap();
+        assert_eq!(next.prompt, "third");
+        queue.requeue("tab", next).unwrap();
+        assert_eq!(queue.pop("tab").unwrap().unwrap().prompt, "third");
+        assert_eq!(queue.pop("tab").unwrap().unwrap().prompt, "second");
+        assert!(queue.pop("tab").unwrap().is_none());
+
+        assert_eq!(queue.clear("other").unwrap(), 1);
+        assert_eq!(queue.clear("other").unwrap(), 0);
+    }
+}
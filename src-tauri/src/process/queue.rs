@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::{Mutex, Notify};
+
+/// Concurrency limits for agent runs (0 means unlimited)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunQueueSettings {
+    pub max_concurrent_runs: u32,
+    pub max_concurrent_per_project: u32,
+}
+
+impl Default for RunQueueSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrent_runs: 4,
+            // Runs on the same repository clobber each other's edits, so serialize them by default
+            max_concurrent_per_project: 1,
+        }
+    }
+}
+
+/// Why a queued run can or can't start right now
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueVerdict {
+    Runnable,
+    GlobalLimit,
+    ProjectLimit,
+}
+
+/// Normalize a project path so the same directory is counted once
+pub fn normalize_project_path(path: &str) -> String {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() {
+        path.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Decide which queued runs may start, given the projects of runs already active.
+///
+/// `queued` must be in queue order. A run blocked by its project's limit does not
+/// hold up later runs for other projects.
+pub fn plan_queue(
+    queued: &[(i64, String)],
+    active_projects: &[String],
+    settings: &RunQueueSettings,
+) -> Vec<(i64, QueueVerdict)> {
+    let mut global = active_projects.len() as u32;
+    let mut per_project: HashMap<String, u32> = HashMap::new();
+    for project in active_projects {
+        *per_project
+            .entry(normalize_project_path(project))
+            .or_insert(0) += 1;
+    }
+
+    queued
+        .iter()
+        .map(|(run_id, project)| {
+            let project = normalize_project_path(project);
+            let project_count = per_project.get(&project).copied().unwrap_or(0);
+
+            let verdict =
+                if settings.max_concurrent_runs > 0 && global >= settings.max_concurrent_runs {
+                    QueueVerdict::GlobalLimit
+                } else if settings.max_concurrent_per_project > 0
+                    && project_count >= settings.max_concurrent_per_project
+                {
+                    QueueVerdict::ProjectLimit
+                } else {
+                    global += 1;
+                    per_project.insert(project, project_count + 1);
+                    QueueVerdict::Runnable
+                };
+
+            (*run_id, verdict)
+        })
+        .collect()
+}
+
+/// Shared state for the agent run queue
+#[derive(Default)]
+pub struct RunQueueState {
+    /// Serializes dispatch so two callers can't launch the same queued run
+    pub dispatch_lock: Mutex<()>,
+    /// Wakes the dispatcher when a run finishes or the queue changes
+    pub wakeup: Notify,
+}
+
+impl RunQueueState {
+    /// Ask the dispatcher to re-evaluate the queue
+    pub fn notify(&self) {
+        self.wakeup.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(items: &[(i64, &str)]) -> Vec<(i64, String)> {
+        items.iter().map(|(id, p)| (*id, p.to_string())).collect()
+    }
+
+    #[test]
+    fn test_per_project_limit_lets_other_projects_through() {
+        let settings = RunQueueSettings::default();
+        let plan = plan_queue(
+            &queued(&[(1, "/repo/a"), (2, "/repo/a/"), (3, "/repo/b")]),
+            &[],
+            &settings,
+        );
+        assert_eq!(
+            plan,
+            vec![
+                (1, QueueVerdict::Runnable),
+                (2, QueueVerdict::ProjectLimit),
+                (3, QueueVerdict::Runnable),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_global_limit_counts_active_runs() {
+        let settings = RunQueueSettings {
+            max_concurrent_runs: 2,
+            max_concurrent_per_project: 0,
+        };
+        let plan = plan_queue(
+            &queued(&[(1, "/repo/a"), (2, "/repo/b")]),
+            &["/repo/c".to_string()],
+            &settings,
+        );
+        assert_eq!(
+            plan,
+            vec![(1, QueueVerdict::Runnable), (2, QueueVerdict::GlobalLimit)]
+        );
+    }
+}
@@ -1,9 +1,13 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use sysinfo::{Pid, ProcessStatus, System};
 use tokio::process::Child;
 
+use super::tree::ProcessTree;
+
 /// Type of process being tracked
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ProcessType {
@@ -21,6 +25,97 @@ pub struct ProcessInfo {
     pub project_path: String,
     pub task: String,
     pub model: String,
+    /// When the process was paused, None while it runs
+    pub paused_at: Option<DateTime<Utc>>,
+}
+
+/// How a cancelled process was stopped
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelOutcome {
+    /// Exited on its own within the grace period after being interrupted
+    Interrupted,
+    /// Still running after the grace period, or couldn't be interrupted
+    ForceKilled,
+    /// Not in the registry, so nothing was signalled
+    NotRunning,
+}
+
+/// Ask a process to stop as if Ctrl+C was pressed; returns whether it was signalled
+#[cfg(unix)]
+fn interrupt_process(pid: u32) -> bool {
+    // SAFETY: kill only sends a signal; a stale pid makes it fail harmlessly
+    unsafe { libc::kill(pid as libc::pid_t, libc::SIGINT) == 0 }
+}
+
+/// Send Ctrl+Break, the console's interrupt, to the process group a process
+/// started with `own_process_group` leads; returns whether it was signalled
+#[cfg(windows)]
+fn interrupt_process(pid: u32) -> bool {
+    super::win32::send_ctrl_break(pid)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn interrupt_process(_pid: u32) -> bool {
+    false
+}
+
+/// Interrupt a registered process along with everything it started when it
+/// has a tree, so its tool commands stop too; returns whether it was signalled
+fn interrupt_handle(handle: &ProcessHandle) -> bool {
+    match &handle.tree {
+        Some(tree) => tree.interrupt(),
+        None => interrupt_process(handle.info.pid),
+    }
+}
+
+/// Stop a process from being scheduled, or let it continue; returns whether
+/// it was signalled
+#[cfg(unix)]
+fn suspend_process(pid: u32, suspend: bool) -> bool {
+    let signal = if suspend {
+        libc::SIGSTOP
+    } else {
+        libc::SIGCONT
+    };
+    // SAFETY: kill only sends a signal; a stale pid makes it fail harmlessly
+    unsafe { libc::kill(pid as libc::pid_t, signal) == 0 }
+}
+
+/// Freeze every thread of a process, or thaw them; returns whether it worked
+#[cfg(windows)]
+fn suspend_process(pid: u32, suspend: bool) -> bool {
+    use super::win32::*;
+    // SAFETY: the handle is only used after it was opened and is closed afterwards
+    unsafe {
+        let handle = OpenProcess(PROCESS_SUSPEND_RESUME, 0, pid);
+        if handle == 0 {
+            return false;
+        }
+        let status = if suspend {
+            NtSuspendProcess(handle)
+        } else {
+            NtResumeProcess(handle)
+        };
+        CloseHandle(handle);
+        status >= 0
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn suspend_process(_pid: u32, _suspend: bool) -> bool {
+    false
+}
+
+/// Whether a pid belongs to a live process; one that exited but hasn't been
+/// waited for yet counts as gone
+fn pid_running(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_process(pid)
+        && system
+            .process(pid)
+            .is_some_and(|process| process.status() != ProcessStatus::Zombie)
 }
 
 /// Information about a running process with handle
@@ -29,12 +124,23 @@ pub struct ProcessHandle {
     pub info: ProcessInfo,
     pub child: Arc<Mutex<Option<Child>>>,
     pub live_output: Arc<Mutex<String>>,
+    /// When the process last wrote output, None until it has
+    pub last_output_at: Option<DateTime<Utc>>,
+    /// The process and those it spawned that were stopped by `pause`
+    pub paused_pids: Vec<u32>,
+    /// When the process was last unpaused
+    pub unpaused_at: Option<DateTime<Utc>>,
+    /// Everything the process started, killed when the handle is dropped
+    pub tree: Option<ProcessTree>,
 }
 
 /// Registry for tracking active agent processes
 pub struct ProcessRegistry {
     processes: Arc<Mutex<HashMap<i64, ProcessHandle>>>, // run_id -> ProcessHandle
     next_id: Arc<Mutex<i64>>, // Auto-incrementing ID for non-agent processes
+    /// Runs stopped on purpose, so their exit isn't a crash. Kept after the
+    /// process is unregistered, as its monitor only checks once it has exited.
+    interrupted: Arc<Mutex<HashSet<i64>>>,
 }
 
 impl ProcessRegistry {
@@ -42,6 +148,7 @@ impl ProcessRegistry {
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
             next_id: Arc::new(Mutex::new(1000000)), // Start at high number to avoid conflicts
+            interrupted: Arc::new(Mutex::new(HashSet::new())),
         }
     }
 
@@ -76,6 +183,7 @@ impl ProcessRegistry {
             project_path,
             task,
             model,
+            paused_at: None,
         };
 
         self.register_process_internal(run_id, process_info, child)
@@ -103,15 +211,21 @@ impl ProcessRegistry {
             project_path,
             task,
             model,
+            paused_at: None,
         };
 
         // For sidecar processes, we register without the child handle since it's managed differently
+        self.clear_interrupted(run_id)?;
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
         let process_handle = ProcessHandle {
             info: process_info,
             child: Arc::new(Mutex::new(None)), // No tokio::process::Child handle for sidecar
             live_output: Arc::new(Mutex::new(String::new())),
+            last_output_at: None,
+            paused_pids: Vec::new(),
+            unpaused_at: None,
+            tree: ProcessTree::attach(pid),
         };
 
         processes.insert(run_id, process_handle);
@@ -137,6 +251,7 @@ impl ProcessRegistry {
             project_path,
             task,
             model,
+            paused_at: None,
         };
 
         // Register without child - Claude sessions use ClaudeProcessState for process management
@@ -146,6 +261,10 @@ impl ProcessRegistry {
             info: process_info,
             child: Arc::new(Mutex::new(None)), // No child handle for Claude sessions
             live_output: Arc::new(Mutex::new(String::new())),
+            last_output_at: None,
+            paused_pids: Vec::new(),
+            unpaused_at: None,
+            tree: ProcessTree::attach(pid),
         };
 
         processes.insert(run_id, process_handle);
@@ -159,12 +278,18 @@ impl ProcessRegistry {
         process_info: ProcessInfo,
         child: Child,
     ) -> Result<(), String> {
+        // A run relaunched after a crash reuses its id
+        self.clear_interrupted(run_id)?;
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
         let process_handle = ProcessHandle {
+            tree: ProcessTree::attach(process_info.pid),
             info: process_info,
             child: Arc::new(Mutex::new(Some(child))),
             live_output: Arc::new(Mutex::new(String::new())),
+            last_output_at: None,
+            paused_pids: Vec::new(),
+            unpaused_at: None,
         };
 
         processes.insert(run_id, process_handle);
@@ -207,7 +332,6 @@ impl ProcessRegistry {
     }
 
     /// Get all running processes
-    #[allow(dead_code)]
     pub fn get_running_processes(&self) -> Result<Vec<ProcessInfo>, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
         Ok(processes
@@ -229,7 +353,6 @@ impl ProcessRegistry {
     }
 
     /// Get a specific running process
-    #[allow(dead_code)]
     pub fn get_process(&self, run_id: i64) -> Result<Option<ProcessInfo>, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
         Ok(processes.get(&run_id).map(|handle| handle.info.clone()))
@@ -254,6 +377,11 @@ impl ProcessRegistry {
             "Attempting graceful shutdown of process {} (PID: {})",
             run_id, pid
         );
+        self.mark_interrupted(run_id)?;
+        // Stopped processes it spawned would otherwise be left behind frozen
+        self.unpause(run_id)?;
+        // Take down everything the process started along with it
+        self.kill_tree(run_id)?;
 
         // Send kill signal to the process
         let kill_sent = {
@@ -359,6 +487,83 @@ impl ProcessRegistry {
         Ok(true)
     }
 
+    /// Stop a process gracefully: interrupt it, give it `grace_period` to
+    /// finish its current tool call and exit, then kill it if it hasn't
+    pub async fn cancel_process(
+        &self,
+        run_id: i64,
+        grace_period: Duration,
+    ) -> Result<CancelOutcome, String> {
+        use log::{info, warn};
+
+        let (pid, child_arc) = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            match processes.get(&run_id) {
+                Some(handle) => (handle.info.pid, handle.child.clone()),
+                None => return Ok(CancelOutcome::NotRunning),
+            }
+        };
+
+        // A paused process can't act on the interrupt until it continues
+        self.mark_interrupted(run_id)?;
+        self.unpause(run_id)?;
+        if !grace_period.is_zero() && self.interrupt_tree(run_id)? {
+            info!(
+                "Interrupted process {} (PID: {}), waiting up to {:?} for it to exit",
+                run_id, pid, grace_period
+            );
+            let exited = tokio::time::timeout(grace_period, async {
+                loop {
+                    let running = {
+                        let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
+                        match child_guard.as_mut() {
+                            Some(child) => matches!(child.try_wait(), Ok(None)),
+                            None => pid_running(pid),
+                        }
+                    };
+                    if !running {
+                        return Ok::<(), String>(());
+                    }
+                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                }
+            })
+            .await;
+            if let Ok(result) = exited {
+                result?;
+                info!("Process {} exited after being interrupted", run_id);
+                self.unregister_process(run_id)?;
+                return Ok(CancelOutcome::Interrupted);
+            }
+            warn!(
+                "Process {} still running after {:?}, force-killing it",
+                run_id, grace_period
+            );
+        }
+
+        self.kill_process(run_id).await?;
+        Ok(CancelOutcome::ForceKilled)
+    }
+
+    /// Kill every process in a registered process's tree; returns whether it
+    /// was signalled
+    pub fn kill_tree(&self, run_id: i64) -> Result<bool, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        Ok(processes
+            .get(&run_id)
+            .and_then(|handle| handle.tree.as_ref())
+            .is_some_and(|tree| tree.kill()))
+    }
+
+    /// Kill the process trees of everything still registered, for when the
+    /// app exits
+    pub fn kill_all_trees(&self) {
+        if let Ok(processes) = self.processes.lock() {
+            for tree in processes.values().filter_map(|handle| handle.tree.as_ref()) {
+                tree.kill();
+            }
+        }
+    }
+
     /// Kill a process by PID using system commands (fallback method)
     pub fn kill_process_by_pid(&self, run_id: i64, pid: u32) -> Result<bool, String> {
         use log::{error, info, warn};
@@ -468,17 +673,193 @@ impl ProcessRegistry {
         }
     }
 
+    /// Wait for a registered process to exit and return its exit code.
+    ///
+    /// Returns None if the process isn't registered, has no child handle (e.g. it was
+    /// killed and cleared) or terminated without an exit code.
+    pub async fn wait_for_exit(&self, run_id: i64) -> Result<Option<i32>, String> {
+        let child_arc = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            match processes.get(&run_id) {
+                Some(handle) => handle.child.clone(),
+                None => return Ok(None),
+            }
+        };
+
+        loop {
+            {
+                let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
+                match child_guard.as_mut() {
+                    Some(child) => match child.try_wait() {
+                        Ok(Some(status)) => return Ok(status.code()),
+                        Ok(None) => {}
+                        Err(e) => return Err(e.to_string()),
+                    },
+                    None => return Ok(None),
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+    }
+
     /// Append to live output for a process
     pub fn append_live_output(&self, run_id: i64, output: &str) -> Result<(), String> {
-        let processes = self.processes.lock().map_err(|e| e.to_string())?;
-        if let Some(handle) = processes.get(&run_id) {
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        if let Some(handle) = processes.get_mut(&run_id) {
             let mut live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
             live_output.push_str(output);
             live_output.push('\n');
+            handle.last_output_at = Some(Utc::now());
+        }
+        Ok(())
+    }
+
+    fn record_output(&self, run_id: i64) -> Result<(), String> {
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        if let Some(handle) = processes.get_mut(&run_id) {
+            handle.last_output_at = Some(Utc::now());
+        }
+        Ok(())
+    }
+
+    /// Every running process that isn't paused with when it last wrote output,
+    /// or when it started or was last unpaused if that was later
+    pub fn output_activity(&self) -> Result<Vec<(ProcessInfo, DateTime<Utc>)>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        Ok(processes
+            .values()
+            .filter(|handle| handle.info.paused_at.is_none())
+            .map(|handle| {
+                let last_output = handle.last_output_at.unwrap_or(handle.info.started_at);
+                let last_output = last_output.max(handle.unpaused_at.unwrap_or(last_output));
+                (handle.info.clone(), last_output)
+            })
+            .collect())
+    }
+
+    /// Pause a process and the processes it spawned, given in `pids` with the
+    /// process itself first, until `unpause`; returns whether it is paused
+    pub fn pause(&self, run_id: i64, pids: &[u32]) -> Result<bool, String> {
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let Some(handle) = processes.get_mut(&run_id) else {
+            return Ok(false);
+        };
+        if handle.info.paused_at.is_some() {
+            return Ok(true);
         }
+        let paused: Vec<u32> = pids
+            .iter()
+            .copied()
+            .filter(|&pid| suspend_process(pid, true))
+            .collect();
+        if paused.is_empty() {
+            return Ok(false);
+        }
+        handle.info.paused_at = Some(Utc::now());
+        handle.paused_pids = paused;
+        Ok(true)
+    }
+
+    /// Let a paused process and the processes it spawned continue; returns
+    /// whether it was paused
+    pub fn unpause(&self, run_id: i64) -> Result<bool, String> {
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let Some(handle) = processes.get_mut(&run_id) else {
+            return Ok(false);
+        };
+        if handle.info.paused_at.take().is_none() {
+            return Ok(false);
+        }
+        for pid in std::mem::take(&mut handle.paused_pids).into_iter().rev() {
+            suspend_process(pid, false);
+        }
+        handle.unpaused_at = Some(Utc::now());
+        Ok(true)
+    }
+
+    /// Interrupt a process as if Ctrl+C was pressed, without waiting for it to
+    /// exit; returns whether it was signalled
+    pub fn interrupt(&self, run_id: i64) -> Result<bool, String> {
+        if self.get_process(run_id)?.is_none() {
+            return Ok(false);
+        }
+        self.mark_interrupted(run_id)?;
+        self.interrupt_tree(run_id)
+    }
+
+    fn interrupt_tree(&self, run_id: i64) -> Result<bool, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        Ok(processes.get(&run_id).is_some_and(interrupt_handle))
+    }
+
+    fn mark_interrupted(&self, run_id: i64) -> Result<(), String> {
+        let mut interrupted = self.interrupted.lock().map_err(|e| e.to_string())?;
+        interrupted.insert(run_id);
+        Ok(())
+    }
+
+    fn clear_interrupted(&self, run_id: i64) -> Result<(), String> {
+        let mut interrupted = self.interrupted.lock().map_err(|e| e.to_string())?;
+        interrupted.remove(&run_id);
         Ok(())
     }
 
+    /// Whether a process was interrupted, cancelled or killed on purpose,
+    /// even after it was unregistered
+    pub fn was_interrupted(&self, run_id: i64) -> bool {
+        self.interrupted
+            .lock()
+            .map(|interrupted| interrupted.contains(&run_id))
+            .unwrap_or(false)
+    }
+
+    /// Append to live output and emit the line while the buffer is held, so a
+    /// concurrent replay sees each line exactly once. Lines of unregistered processes
+    /// are still emitted.
+    pub fn append_and_emit_live_output(
+        &self,
+        run_id: i64,
+        output: &str,
+        emit: impl FnOnce(&str),
+    ) -> Result<(), String> {
+        let Some(live_output) = self.live_output_buffer(run_id)? else {
+            emit(output);
+            return Ok(());
+        };
+        {
+            let mut live_output = live_output.lock().map_err(|e| e.to_string())?;
+            live_output.push_str(output);
+            live_output.push('\n');
+            emit(output);
+        }
+        self.record_output(run_id)
+    }
+
+    /// Replay a process's buffered output, handed over as its lines. New output
+    /// is held back until the replay finishes. Returns the number of lines
+    /// replayed, or None if the process isn't running.
+    pub fn replay_live_output(
+        &self,
+        run_id: i64,
+        replay: impl FnOnce(Vec<&str>),
+    ) -> Result<Option<usize>, String> {
+        let Some(live_output) = self.live_output_buffer(run_id)? else {
+            return Ok(None);
+        };
+        let live_output = live_output.lock().map_err(|e| e.to_string())?;
+        let lines: Vec<&str> = live_output.lines().collect();
+        let count = lines.len();
+        replay(lines);
+        Ok(Some(count))
+    }
+
+    fn live_output_buffer(&self, run_id: i64) -> Result<Option<Arc<Mutex<String>>>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        Ok(processes
+            .get(&run_id)
+            .map(|handle| handle.live_output.clone()))
+    }
+
     /// Get live output for a process
     pub fn get_live_output(&self, run_id: i64) -> Result<String, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
@@ -535,3 +916,155 @@ impl Default for ProcessRegistryState {
         Self(Arc::new(ProcessRegistry::new()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_process_escalates_after_grace_period() {
+        let registry = ProcessRegistry::new();
+        let spawn = |run_id: i64, on_interrupt: &str| {
+            let script = format!(
+                "trap '{}' INT; while true; do sleep 0.05; done",
+                on_interrupt
+            );
+            let child = tokio::process::Command::new("sh")
+                .args(["-c", &script])
+                .spawn()
+                .unwrap();
+            let pid = child.id().unwrap();
+            registry
+                .register_process(
+                    run_id,
+                    1,
+                    "agent".to_string(),
+                    pid,
+                    "/work/app".to_string(),
+                    "task".to_string(),
+                    "sonnet".to_string(),
+                    child,
+                )
+                .unwrap();
+        };
+
+        // Exits cleanly once interrupted; the shells get a moment to set their traps
+        spawn(1, "exit 0");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let outcome = registry
+            .cancel_process(1, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(outcome, CancelOutcome::Interrupted);
+        // Still known as stopped on purpose once it is unregistered
+        assert!(registry.get_process(1).unwrap().is_none());
+        assert!(registry.was_interrupted(1));
+
+        // Ignores the interrupt, so it is killed after the grace period
+        spawn(2, "");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let outcome = registry
+            .cancel_process(2, Duration::from_millis(300))
+            .await
+            .unwrap();
+        assert_eq!(outcome, CancelOutcome::ForceKilled);
+        assert!(registry.get_process(2).unwrap().is_none());
+        assert_eq!(
+            registry
+                .cancel_process(2, Duration::from_secs(1))
+                .await
+                .unwrap(),
+            CancelOutcome::NotRunning
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pause_and_unpause() {
+        let registry = ProcessRegistry::new();
+        let child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        registry
+            .register_process(
+                1,
+                1,
+                "agent".to_string(),
+                pid,
+                "/work/app".to_string(),
+                "task".to_string(),
+                "sonnet".to_string(),
+                child,
+            )
+            .unwrap();
+        let status = |pid: u32| {
+            let mut system = System::new();
+            system.refresh_process(Pid::from_u32(pid));
+            system.process(Pid::from_u32(pid)).map(|p| p.status())
+        };
+
+        assert!(registry.pause(1, &[pid]).unwrap());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(status(pid), Some(ProcessStatus::Stop));
+        let info = registry.get_process(1).unwrap().unwrap();
+        assert!(info.paused_at.is_some());
+        assert!(registry.output_activity().unwrap().is_empty());
+
+        assert!(registry.unpause(1).unwrap());
+        assert!(!registry.unpause(1).unwrap());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_ne!(status(pid), Some(ProcessStatus::Stop));
+        assert_eq!(registry.output_activity().unwrap().len(), 1);
+
+        // A paused process is continued before it is stopped for good
+        registry.pause(1, &[pid]).unwrap();
+        assert_eq!(
+            registry.cancel_process(1, Duration::ZERO).await.unwrap(),
+            CancelOutcome::ForceKilled
+        );
+        assert!(registry.get_process(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_replay_live_output() {
+        let registry = ProcessRegistry::new();
+        let run_id = registry
+            .register_claude_session(
+                "abc".to_string(),
+                42,
+                "/work/app".to_string(),
+                "Fix the bug".to_string(),
+                "sonnet".to_string(),
+            )
+            .unwrap();
+
+        let mut emitted = Vec::new();
+        for line in ["{\"type\":\"system\"}", "{\"type\":\"assistant\"}"] {
+            registry
+                .append_and_emit_live_output(run_id, line, |l| emitted.push(l.to_string()))
+                .unwrap();
+        }
+        assert_eq!(emitted.len(), 2);
+
+        let mut replayed = Vec::new();
+        let count = registry
+            .replay_live_output(run_id, |lines| {
+                replayed = lines.into_iter().map(str::to_string).collect()
+            })
+            .unwrap();
+        assert_eq!(count, Some(2));
+        assert_eq!(replayed, emitted);
+
+        registry.unregister_process(run_id).unwrap();
+        assert_eq!(registry.replay_live_output(run_id, |_| {}).unwrap(), None);
+        // Output of a process that isn't registered is still emitted
+        let mut emitted = false;
+        registry
+            .append_and_emit_live_output(run_id, "late", |_| emitted = true)
+            .unwrap();
+        assert!(emitted);
+    }
+}
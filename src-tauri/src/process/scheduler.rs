@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::queue::{normalize_project_path, QueueVerdict};
+
+/// Limits shared by every Claude process the app starts, sessions and agent
+/// runs alike (0 means unlimited)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SchedulerSettings {
+    pub max_claude_processes: u32,
+    /// Count agent runs against the repository their project is in rather than
+    /// the project directory, so runs in two folders of one checkout wait for
+    /// each other. A git worktree is a repository of its own.
+    pub serialize_repositories: bool,
+}
+
+impl Default for SchedulerSettings {
+    fn default() -> Self {
+        Self {
+            max_claude_processes: 8,
+            serialize_repositories: true,
+        }
+    }
+}
+
+/// How long a project's resolved repository is trusted before git is asked again,
+/// so a directory that becomes a checkout, or a removed worktree, is picked up
+const REPOSITORY_CACHE_SECS: u64 = 60;
+
+/// Global scheduler for Claude processes
+#[derive(Default)]
+pub struct RunScheduler {
+    /// Repository each project path resolved to and when, so git runs once per path
+    repositories: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl RunScheduler {
+    /// The repository a project path belongs to: the top level of its git
+    /// checkout or worktree, or the path itself outside git.
+    ///
+    /// Only reads what `resolve_repositories` found, so it is safe to call
+    /// while holding the database lock; unresolved paths are their own key.
+    pub fn repository_key(&self, project_path: &str) -> String {
+        let project_path = normalize_project_path(project_path);
+        self.repositories
+            .lock()
+            .ok()
+            .and_then(|repositories| repositories.get(&project_path).map(|(key, _)| key.clone()))
+            .unwrap_or(project_path)
+    }
+
+    /// Look up the repositories of `project_paths` that aren't cached or whose
+    /// entry expired, running git on a blocking thread. Paths no longer asked
+    /// about are forgotten.
+    pub async fn resolve_repositories(&self, project_paths: Vec<String>) {
+        let project_paths: HashSet<String> = project_paths
+            .iter()
+            .map(|path| normalize_project_path(path))
+            .collect();
+        let stale: Vec<String> = match self.repositories.lock() {
+            Ok(mut repositories) => {
+                repositories.retain(|path, (_, resolved)| {
+                    project_paths.contains(path)
+                        && resolved.elapsed() < Duration::from_secs(REPOSITORY_CACHE_SECS)
+                });
+                project_paths
+                    .into_iter()
+                    .filter(|path| !repositories.contains_key(path))
+                    .collect()
+            }
+            Err(_) => return,
+        };
+        if stale.is_empty() {
+            return;
+        }
+
+        let resolved = tauri::async_runtime::spawn_blocking(move || {
+            stale
+                .into_iter()
+                .map(|path| {
+                    let key = git_toplevel(Path::new(&path))
+                        .map(|toplevel| normalize_project_path(&toplevel))
+                        .unwrap_or_else(|| path.clone());
+                    (path, key)
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+        if let Ok(mut repositories) = self.repositories.lock() {
+            let now = Instant::now();
+            for (path, key) in resolved {
+                repositories.insert(path, (key, now));
+            }
+        }
+    }
+}
+
+fn git_toplevel(dir: &Path) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let toplevel = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!toplevel.is_empty()).then_some(toplevel)
+}
+
+/// Hold back runnable queued runs that would take the number of Claude
+/// processes past the global limit, given `running` processes already
+pub fn cap_claude_processes(
+    plan: Vec<(i64, QueueVerdict)>,
+    running: usize,
+    settings: &SchedulerSettings,
+) -> Vec<(i64, QueueVerdict)> {
+    if settings.max_claude_processes == 0 {
+        return plan;
+    }
+    let mut free = (settings.max_claude_processes as usize).saturating_sub(running);
+    plan.into_iter()
+        .map(|(run_id, verdict)| match verdict {
+            QueueVerdict::Runnable if free == 0 => (run_id, QueueVerdict::GlobalLimit),
+            QueueVerdict::Runnable => {
+                free -= 1;
+                (run_id, verdict)
+            }
+            _ => (run_id, verdict),
+        })
+        .collect()
+}
+
+/// Refuse to start another Claude process once `running` have reached the
+/// global limit
+pub fn check_claude_capacity(running: usize, settings: &SchedulerSettings) -> Result<(), String> {
+    let limit = settings.max_claude_processes as usize;
+    if limit > 0 && running >= limit {
+        return Err(format!(
+            "{} Claude processes are already running, the most allowed at once",
+            running
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_cap_counts_all_claude_processes() {
+        let settings = SchedulerSettings {
+            max_claude_processes: 3,
+            serialize_repositories: true,
+        };
+        let plan = vec![
+            (1, QueueVerdict::Runnable),
+            (2, QueueVerdict::ProjectLimit),
+            (3, QueueVerdict::Runnable),
+        ];
+        assert_eq!(
+            cap_claude_processes(plan.clone(), 2, &settings),
+            vec![
+                (1, QueueVerdict::Runnable),
+                (2, QueueVerdict::ProjectLimit),
+                (3, QueueVerdict::GlobalLimit),
+            ]
+        );
+        assert!(check_claude_capacity(2, &settings).is_ok());
+        assert!(check_claude_capacity(3, &settings).is_err());
+
+        let unlimited = SchedulerSettings {
+            max_claude_processes: 0,
+            ..settings
+        };
+        assert_eq!(cap_claude_processes(plan.clone(), 10, &unlimited), plan);
+        assert!(check_claude_capacity(10, &unlimited).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_repository_key_is_the_git_toplevel() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(repo.join("app")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        let init = std::process::Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(&repo)
+            .status()
+            .unwrap();
+        assert!(init.success());
+
+        let scheduler = RunScheduler::default();
+        let path = |path: &Path| path.to_string_lossy().to_string();
+        // Nothing is resolved yet, so every path is its own repository
+        assert_eq!(
+            scheduler.repository_key(&path(&repo.join("app"))),
+            path(&repo.join("app"))
+        );
+
+        scheduler
+            .resolve_repositories(vec![
+                path(&repo),
+                path(&repo.join("app")),
+                format!("{}/", path(&outside)),
+            ])
+            .await;
+        let key = |dir: &Path| scheduler.repository_key(&path(dir));
+        assert_eq!(key(&repo.join("app")), key(&repo));
+        assert_ne!(key(&outside), key(&repo));
+        assert_eq!(
+            scheduler.repository_key(&format!("{}/", path(&outside))),
+            path(&outside)
+        );
+
+        // Paths that are no longer asked about are forgotten
+        scheduler.resolve_repositories(vec![path(&outside)]).await;
+        assert_eq!(key(&repo.join("app")), path(&repo.join("app")));
+    }
+}
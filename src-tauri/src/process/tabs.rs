@@ -0,0 +1,215 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use super::queue::normalize_project_path;
+
+/// A UI tab hosting an interactive Claude session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTab {
+    pub tab_id: String,
+    pub project_path: String,
+    /// Claude session shown in the tab, once known
+    pub session_id: Option<String>,
+    /// Process registry id of the tab's running process
+    pub run_id: Option<i64>,
+    pub running: bool,
+    pub focused: bool,
+    pub opened_at: DateTime<Utc>,
+}
+
+struct TabEntry {
+    tab: SessionTab,
+    /// Signals the tab's running process to stop
+    stop: Option<Arc<Notify>>,
+}
+
+#[derive(Default)]
+struct TabsInner {
+    tabs: HashMap<String, TabEntry>,
+    focused: Option<String>,
+}
+
+/// Which session tabs are open and which Claude process each one is bound to
+#[derive(Default)]
+pub struct SessionTabs {
+    inner: Mutex<TabsInner>,
+}
+
+impl SessionTabs {
+    fn view(inner: &TabsInner, entry: &TabEntry) -> SessionTab {
+        SessionTab {
+            running: entry.stop.is_some(),
+            focused: inner.focused.as_deref() == Some(entry.tab.tab_id.as_str()),
+            ..entry.tab.clone()
+        }
+    }
+
+    /// Open a tab for a project, optionally showing an existing session
+    pub fn open(
+        &self,
+        project_path: String,
+        session_id: Option<String>,
+    ) -> Result<SessionTab, String> {
+        let tab_id = uuid::Uuid::new_v4().to_string();
+        let entry = TabEntry {
+            tab: SessionTab {
+                tab_id: tab_id.clone(),
+                project_path,
+                session_id,
+                run_id: None,
+                running: false,
+                focused: false,
+                opened_at: Utc::now(),
+            },
+            stop: None,
+        };
+        let mut inner = self.inner.lock().map_err(|e| e.to_string())?;
+        let tab = Self::view(&inner, &entry);
+        inner.tabs.insert(tab_id, entry);
+        Ok(tab)
+    }
+
+    /// Open tabs in the order they were opened
+    pub fn list(&self) -> Result<Vec<SessionTab>, String> {
+        let inner = self.inner.lock().map_err(|e| e.to_string())?;
+        let mut tabs: Vec<SessionTab> = inner
+            .tabs
+            .values()
+            .map(|entry| Self::view(&inner, entry))
+            .collect();
+        tabs.sort_by_key(|tab| tab.opened_at);
+        Ok(tabs)
+    }
+
+    pub fn get(&self, tab_id: &str) -> Result<SessionTab, String> {
+        let inner = self.inner.lock().map_err(|e| e.to_string())?;
+        inner
+            .tabs
+            .get(tab_id)
+            .map(|entry| Self::view(&inner, entry))
+            .ok_or_else(|| format!("Session tab {} not found", tab_id))
+    }
+
+    pub fn focus(&self, tab_id: &str) -> Result<(), String> {
+        let mut inner = self.inner.lock().map_err(|e| e.to_string())?;
+        if !inner.tabs.contains_key(tab_id) {
+            return Err(format!("Session tab {} not found", tab_id));
+        }
+        inner.focused = Some(tab_id.to_string());
+        Ok(())
+    }
+
+    /// Close a tab, stopping its process if one is running
+    pub fn close(&self, tab_id: &str) -> Result<SessionTab, String> {
+        let mut inner = self.inner.lock().map_err(|e| e.to_string())?;
+        let entry = inner
+            .tabs
+            .remove(tab_id)
+            .ok_or_else(|| format!("Session tab {} not found", tab_id))?;
+        if inner.focused.as_deref() == Some(tab_id) {
+            inner.focused = None;
+        }
+        if let Some(stop) = &entry.stop {
+            stop.notify_one();
+        }
+        Ok(Self::view(&inner, &entry))
+    }
+
+    /// Claim a tab for a new process. With `project_lock`, no other tab may be running
+    /// in the same project. Returns the signal that stops the process.
+    pub fn start_process(&self, tab_id: &str, project_lock: bool) -> Result<Arc<Notify>, String> {
+        let mut inner = self.inner.lock().map_err(|e| e.to_string())?;
+        let entry = inner
+            .tabs
+            .get(tab_id)
+            .ok_or_else(|| format!("Session tab {} not found", tab_id))?;
+        if entry.stop.is_some() {
+            return Err("A Claude process is already running in this tab".to_string());
+        }
+        if project_lock {
+            let project = normalize_project_path(&entry.tab.project_path);
+            let busy = inner.tabs.values().any(|other| {
+                other.stop.is_some() && normalize_project_path(&other.tab.project_path) == project
+            });
+            if busy {
+                return Err(format!(
+                    "Another session is already running in {}",
+                    entry.tab.project_path
+                ));
+            }
+        }
+
+        let stop = Arc::new(Notify::new());
+        if let Some(entry) = inner.tabs.get_mut(tab_id) {
+            entry.stop = Some(stop.clone());
+        }
+        Ok(stop)
+    }
+
+    /// Record the session and registry run a tab's process turned out to be
+    pub fn bind_session(
+        &self,
+        tab_id: &str,
+        session_id: &str,
+        run_id: Option<i64>,
+    ) -> Result<(), String> {
+        let mut inner = self.inner.lock().map_err(|e| e.to_string())?;
+        if let Some(entry) = inner.tabs.get_mut(tab_id) {
+            entry.tab.session_id = Some(session_id.to_string());
+            entry.tab.run_id = run_id;
+        }
+        Ok(())
+    }
+
+    /// Release a tab once its process has exited
+    pub fn finish_process(&self, tab_id: &str) -> Result<(), String> {
+        let mut inner = self.inner.lock().map_err(|e| e.to_string())?;
+        if let Some(entry) = inner.tabs.get_mut(tab_id) {
+            entry.stop = None;
+            entry.tab.run_id = None;
+        }
+        Ok(())
+    }
+}
+
+/// Global session tab state
+#[derive(Default)]
+pub struct SessionTabsState(pub Arc<SessionTabs>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_lock() {
+        let tabs = SessionTabs::default();
+        let a = tabs.open("/work/api".to_string(), None).unwrap();
+        let b = tabs.open("/work/api/".to_string(), None).unwrap();
+        let c = tabs.open("/work/web".to_string(), None).unwrap();
+
+        tabs.start_process(&a.tab_id, true).unwrap();
+        assert!(tabs.start_process(&a.tab_id, false).is_err());
+        assert!(tabs.start_process(&b.tab_id, true).is_err());
+        tabs.start_process(&c.tab_id, true).unwrap();
+        // Without the lock sessions share a project freely
+        tabs.start_process(&b.tab_id, false).unwrap();
+
+        tabs.bind_session(&a.tab_id, "session-a", Some(7)).unwrap();
+        tabs.focus(&a.tab_id).unwrap();
+        let listed = tabs.list().unwrap();
+        assert_eq!(listed[0].session_id.as_deref(), Some("session-a"));
+        assert!(listed[0].focused && listed[0].running);
+
+        tabs.finish_process(&a.tab_id).unwrap();
+        tabs.finish_process(&b.tab_id).unwrap();
+        tabs.start_process(&b.tab_id, true).unwrap();
+
+        let closed = tabs.close(&a.tab_id).unwrap();
+        assert!(!closed.focused);
+        assert!(tabs.get(&a.tab_id).is_err());
+        assert_eq!(tabs.list().unwrap().len(), 2);
+    }
+}
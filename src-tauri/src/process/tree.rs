@@ -0,0 +1,181 @@
+/// Put a command's process in a process group of its own, so it and everything
+/// it starts can be signalled together. On Windows it gets a new process group
+/// on a hidden console, so it can be sent Ctrl+Break; its tree is tracked with
+/// a job object, attached once the process is running.
+pub fn own_process_group(cmd: &mut tokio::process::Command) {
+    own_std_process_group(cmd.as_std_mut());
+}
+
+/// `own_process_group` for a blocking `std::process::Command`
+pub fn own_std_process_group(cmd: &mut std::process::Command) {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(cmd, 0);
+    // Creation flags replace each other, so this keeps the console hidden too
+    #[cfg(windows)]
+    std::os::windows::process::CommandExt::creation_flags(
+        cmd,
+        super::win32::CREATE_NEW_PROCESS_GROUP | super::win32::CREATE_NO_WINDOW,
+    );
+    #[cfg(not(any(unix, windows)))]
+    let _ = cmd;
+}
+
+/// A process and everything it starts, killed together. Dropping the tree
+/// kills whatever is left of it, so nothing outlives the run it belongs to.
+pub struct ProcessTree {
+    #[cfg(unix)]
+    pgid: libc::pid_t,
+    #[cfg(windows)]
+    job: isize,
+    #[cfg(windows)]
+    pid: u32,
+}
+
+#[cfg(unix)]
+impl ProcessTree {
+    /// The tree of a process that leads its own process group, as processes
+    /// spawned with `own_process_group` or on a terminal do. Any other process
+    /// shares its group with unrelated ones, so it has no tree.
+    pub fn attach(pid: u32) -> Option<Self> {
+        let pid = pid as libc::pid_t;
+        // SAFETY: getpgid only reads the process group of a pid
+        let leads_group = pid > 0 && unsafe { libc::getpgid(pid) } == pid;
+        leads_group.then_some(Self { pgid: pid })
+    }
+
+    /// Interrupt every process in the tree as if Ctrl+C was pressed in its
+    /// terminal, so tool commands stop along with the process that ran them
+    pub fn interrupt(&self) -> bool {
+        // SAFETY: killpg only sends a signal to the group this tree owns
+        unsafe { libc::killpg(self.pgid, libc::SIGINT) == 0 }
+    }
+
+    /// Kill every process left in the tree
+    pub fn kill(&self) -> bool {
+        // SAFETY: killpg only sends a signal to the group this tree owns
+        unsafe { libc::killpg(self.pgid, libc::SIGKILL) == 0 }
+    }
+}
+
+#[cfg(windows)]
+impl ProcessTree {
+    /// Put a running process in a new kill-on-close job object; the processes
+    /// it starts from then on join the job too
+    pub fn attach(pid: u32) -> Option<Self> {
+        use super::win32::*;
+        // SAFETY: handles are only used after they were opened, the process
+        // handle is closed once assigned, and the job handle is owned by the tree
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null_mut(), std::ptr::null());
+            if job == 0 {
+                return None;
+            }
+            let tree = Self { job, pid };
+            let mut limits = ExtendedLimitInformation::default();
+            limits.basic.limit_flags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if SetInformationJobObject(
+                job,
+                JOB_OBJECT_EXTENDED_LIMIT_INFORMATION_CLASS,
+                &mut limits as *mut ExtendedLimitInformation as *mut std::ffi::c_void,
+                std::mem::size_of::<ExtendedLimitInformation>() as u32,
+            ) == 0
+            {
+                return None;
+            }
+            let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+            if process == 0 {
+                return None;
+            }
+            let assigned = AssignProcessToJobObject(job, process) != 0;
+            CloseHandle(process);
+            assigned.then_some(tree)
+        }
+    }
+
+    /// Send Ctrl+Break to the process group of the tree's root, as started by
+    /// `own_process_group`; the console passes it on to every process in it
+    pub fn interrupt(&self) -> bool {
+        super::win32::send_ctrl_break(self.pid)
+    }
+
+    /// Kill every process left in the tree
+    pub fn kill(&self) -> bool {
+        // SAFETY: the job handle is owned by the tree and still open
+        unsafe { super::win32::TerminateJobObject(self.job, 1) != 0 }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+impl ProcessTree {
+    pub fn attach(_pid: u32) -> Option<Self> {
+        None
+    }
+
+    pub fn interrupt(&self) -> bool {
+        false
+    }
+
+    pub fn kill(&self) -> bool {
+        false
+    }
+}
+
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        self.kill();
+        // Closing the last handle to the job kills what is left in it
+        #[cfg(windows)]
+        // SAFETY: the job handle is owned by the tree and closed only here
+        unsafe {
+            super::win32::CloseHandle(self.job);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_tree_kills_grandchildren() {
+        use sysinfo::{Pid, ProcessStatus, System};
+        use tokio::io::{AsyncBufReadExt, BufReader};
+
+        // Not leading a group of its own: no tree, so nothing else gets signalled
+        let mut loner = tokio::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        assert!(ProcessTree::attach(loner.id().unwrap()).is_none());
+        loner.kill().await.unwrap();
+
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", "sleep 30 & echo $!; wait"])
+            .stdout(std::process::Stdio::piped());
+        own_process_group(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+        let grandchild: u32 = stdout.next_line().await.unwrap().unwrap().parse().unwrap();
+
+        drop(ProcessTree::attach(child.id().unwrap()).unwrap());
+        assert!(!child.wait().await.unwrap().success());
+
+        let gone = |pid: u32| {
+            let pid = Pid::from_u32(pid);
+            let mut system = System::new();
+            !(system.refresh_process(pid)
+                && system
+                    .process(pid)
+                    .is_some_and(|process| process.status() != ProcessStatus::Zombie))
+        };
+        for _ in 0..50 {
+            if gone(grandchild) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("grandchild {} outlived its process tree", grandchild);
+    }
+}
@@ -0,0 +1,85 @@
+//! The Win32 process, job object, console and priority APIs used to manage
+//! Claude and the processes it starts on Windows.
+
+use std::ffi::c_void;
+use std::sync::Mutex;
+
+pub const PROCESS_TERMINATE: u32 = 0x0001;
+pub const PROCESS_SET_QUOTA: u32 = 0x0100;
+pub const PROCESS_SET_INFORMATION: u32 = 0x0200;
+pub const PROCESS_SUSPEND_RESUME: u32 = 0x0800;
+
+pub const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+pub const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+pub const CTRL_BREAK_EVENT: u32 = 1;
+
+pub const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x4000;
+
+pub const JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE: u32 = 0x2000;
+pub const JOB_OBJECT_EXTENDED_LIMIT_INFORMATION_CLASS: i32 = 9;
+
+#[repr(C)]
+#[derive(Default)]
+pub struct BasicLimitInformation {
+    pub per_process_user_time_limit: i64,
+    pub per_job_user_time_limit: i64,
+    pub limit_flags: u32,
+    pub minimum_working_set_size: usize,
+    pub maximum_working_set_size: usize,
+    pub active_process_limit: u32,
+    pub affinity: usize,
+    pub priority_class: u32,
+    pub scheduling_class: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+pub struct ExtendedLimitInformation {
+    pub basic: BasicLimitInformation,
+    pub io_counters: [u64; 6],
+    pub process_memory_limit: usize,
+    pub job_memory_limit: usize,
+    pub peak_process_memory_used: usize,
+    pub peak_job_memory_used: usize,
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    pub fn OpenProcess(desired_access: u32, inherit_handle: i32, process_id: u32) -> isize;
+    pub fn CloseHandle(handle: isize) -> i32;
+    pub fn SetPriorityClass(process: isize, priority_class: u32) -> i32;
+    pub fn CreateJobObjectW(attributes: *mut c_void, name: *const u16) -> isize;
+    pub fn SetInformationJobObject(job: isize, class: i32, info: *mut c_void, length: u32) -> i32;
+    pub fn AssignProcessToJobObject(job: isize, process: isize) -> i32;
+    pub fn TerminateJobObject(job: isize, exit_code: u32) -> i32;
+    pub fn AttachConsole(process_id: u32) -> i32;
+    pub fn FreeConsole() -> i32;
+    pub fn GenerateConsoleCtrlEvent(ctrl_event: u32, process_group_id: u32) -> i32;
+}
+
+#[link(name = "ntdll")]
+extern "system" {
+    pub fn NtSuspendProcess(process: isize) -> i32;
+    pub fn NtResumeProcess(process: isize) -> i32;
+}
+
+/// Send Ctrl+Break to the process group led by `pid`, which must have been
+/// started with `CREATE_NEW_PROCESS_GROUP`. Console events only reach
+/// processes sharing the sender's console, so this attaches to the group's
+/// console while sending; returns whether the event was sent.
+pub fn send_ctrl_break(pid: u32) -> bool {
+    // A process can be attached to only one console at a time
+    static CONSOLE: Mutex<()> = Mutex::new(());
+    let _console = CONSOLE.lock().unwrap_or_else(|e| e.into_inner());
+    // SAFETY: these calls only change which console this process is attached
+    // to, serialized by the lock, and signal the group led by `pid`
+    unsafe {
+        FreeConsole();
+        if AttachConsole(pid) == 0 {
+            return false;
+        }
+        let sent = GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) != 0;
+        FreeConsole();
+        sent
+    }
+}
@@ -0,0 +1,261 @@
+//! Live updates of Claude's projects directory
+//!
+//! Sessions started from a terminal write their transcripts to
+//! `~/.claude/projects/<project-id>/<session-id>.jsonl` without opcode knowing.
+//! A notify-based watcher observes that directory, debounces bursts of writes
+//! (a busy session appends many lines a second), and emits
+//! `claude-session-changed` for every touched session and
+//! `claude-projects-changed` with the affected project ids so the project and
+//! session lists can refresh themselves.
+
+use log::{info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
+
+/// How long the directory has to be quiet before changes are reported
+const DEBOUNCE_MS: u64 = 500;
+
+/// The running watcher; dropping it stops the watch
+#[derive(Default)]
+pub struct ProjectsWatchState(pub Mutex<Option<RecommendedWatcher>>);
+
+/// What happened to a session file
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionChangeKind {
+    Created,
+    Updated,
+    Removed,
+}
+
+/// Payload of `claude-session-changed`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SessionChange {
+    pub project_id: String,
+    pub session_id: String,
+    pub kind: SessionChangeKind,
+}
+
+/// A changed path under the projects directory
+#[derive(Debug, PartialEq)]
+pub enum ProjectsPath {
+    /// A project directory itself
+    Project(String),
+    /// A session transcript in a project
+    Session {
+        project_id: String,
+        session_id: String,
+    },
+}
+
+/// Work out what a changed path refers to. Only project directories and the
+/// session files directly inside them matter; agent sidechains and other files
+/// in deeper directories are ignored.
+pub fn classify_path(projects_dir: &Path, path: &Path) -> Option<ProjectsPath> {
+    let relative = path.strip_prefix(projects_dir).ok()?;
+    let parts: Vec<&str> = relative
+        .components()
+        .map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+
+    match parts[..] {
+        [project_id] if !project_id.starts_with('.') => {
+            Some(ProjectsPath::Project(project_id.to_string()))
+        }
+        [project_id, file_name] if !project_id.starts_with('.') => {
+            let session_id = file_name.strip_suffix(".jsonl")?;
+            if session_id.is_empty() || session_id.starts_with('.') {
+                return None;
+            }
+            Some(ProjectsPath::Session {
+                project_id: project_id.to_string(),
+                session_id: session_id.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Merge a new event for a session into what is already pending for it, so a
+/// file created and then written to is still reported as created
+fn merge_kind(pending: Option<SessionChangeKind>, next: SessionChangeKind) -> SessionChangeKind {
+    match (pending, next) {
+        (Some(SessionChangeKind::Created), SessionChangeKind::Updated) => {
+            SessionChangeKind::Created
+        }
+        (Some(SessionChangeKind::Removed), SessionChangeKind::Updated) => {
+            SessionChangeKind::Created
+        }
+        (_, next) => next,
+    }
+}
+
+/// Start watching `~/.claude/projects`, replacing any running watcher
+pub fn start_projects_watch(app: &AppHandle) -> Result<(), String> {
+    let projects_dir = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("projects");
+    if !projects_dir.is_dir() {
+        return Err(format!(
+            "Projects directory does not exist: {}",
+            projects_dir.display()
+        ));
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel::<(PathBuf, SessionChangeKind)>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            let kind = if event.kind.is_create() {
+                SessionChangeKind::Created
+            } else if event.kind.is_modify() {
+                SessionChangeKind::Updated
+            } else if event.kind.is_remove() {
+                SessionChangeKind::Removed
+            } else {
+                return;
+            };
+            for path in event.paths {
+                let _ = tx.send((path, kind));
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to create projects watcher: {}", e))?;
+
+    watcher
+        .watch(&projects_dir, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", projects_dir.display(), e))?;
+
+    {
+        let state = app.state::<ProjectsWatchState>();
+        let mut current = state.0.lock().map_err(|e| e.to_string())?;
+        *current = Some(watcher);
+    }
+
+    info!("Watching {} for session changes", projects_dir.display());
+    tauri::async_runtime::spawn(debounce_and_emit(app.clone(), projects_dir, rx));
+    Ok(())
+}
+
+/// Collect change bursts and report them once things settle down
+async fn debounce_and_emit(
+    app: AppHandle,
+    projects_dir: PathBuf,
+    mut rx: mpsc::UnboundedReceiver<(PathBuf, SessionChangeKind)>,
+) {
+    let debounce = tokio::time::Duration::from_millis(DEBOUNCE_MS);
+
+    // The channel closes when the watcher is dropped, which ends this task
+    while let Some(first) = rx.recv().await {
+        let mut projects = BTreeSet::new();
+        let mut sessions: BTreeMap<(String, String), SessionChangeKind> = BTreeMap::new();
+        let mut pending = Some(first);
+
+        loop {
+            if let Some((path, kind)) = pending.take() {
+                match classify_path(&projects_dir, &path) {
+                    Some(ProjectsPath::Project(project_id)) => {
+                        projects.insert(project_id);
+                    }
+                    Some(ProjectsPath::Session {
+                        project_id,
+                        session_id,
+                    }) => {
+                        projects.insert(project_id.clone());
+                        let key = (project_id, session_id);
+                        let merged = merge_kind(sessions.get(&key).copied(), kind);
+                        sessions.insert(key, merged);
+                    }
+                    None => {}
+                }
+            }
+
+            match tokio::time::timeout(debounce, rx.recv()).await {
+                Ok(Some(change)) => pending = Some(change),
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+
+        if projects.is_empty() {
+            continue;
+        }
+
+        for ((project_id, session_id), kind) in sessions {
+            // Renames and editors' atomic saves report events that don't match
+            // where the file ended up, so go by what is on disk now
+            let exists = projects_dir
+                .join(&project_id)
+                .join(format!("{}.jsonl", session_id))
+                .is_file();
+            let kind = match (exists, kind) {
+                (false, _) => SessionChangeKind::Removed,
+                (true, SessionChangeKind::Removed) => SessionChangeKind::Updated,
+                (true, kind) => kind,
+            };
+            let _ = app.emit(
+                "claude-session-changed",
+                SessionChange {
+                    project_id,
+                    session_id,
+                    kind,
+                },
+            );
+        }
+
+        let _ = app.emit(
+            "claude-projects-changed",
+            serde_json::json!({ "project_ids": projects }),
+        );
+    }
+}
+
+/// Start the projects watcher at startup; a missing directory is only logged
+/// since Claude creates it on first use
+pub fn start_projects_watcher(app: &AppHandle) {
+    if let Err(e) = start_projects_watch(app) {
+        warn!("Not watching Claude projects: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_path_and_merge_kind() {
+        let root = Path::new("/home/me/.claude/projects");
+        assert_eq!(
+            classify_path(root, &root.join("-work-api")),
+            Some(ProjectsPath::Project("-work-api".to_string()))
+        );
+        assert_eq!(
+            classify_path(root, &root.join("-work-api/0b1c.jsonl")),
+            Some(ProjectsPath::Session {
+                project_id: "-work-api".to_string(),
+                session_id: "0b1c".to_string(),
+            })
+        );
+        assert_eq!(classify_path(root, &root.join("-work-api/notes.txt")), None);
+        assert_eq!(
+            classify_path(root, &root.join("-work-api/0b1c/subagents/a.jsonl")),
+            None
+        );
+        assert_eq!(classify_path(root, &root.join(".DS_Store")), None);
+        assert_eq!(classify_path(root, Path::new("/tmp/x.jsonl")), None);
+        assert_eq!(classify_path(root, root), None);
+
+        use SessionChangeKind::*;
+        assert_eq!(merge_kind(Some(Created), Updated), Created);
+        assert_eq!(merge_kind(Some(Updated), Removed), Removed);
+        assert_eq!(merge_kind(None, Updated), Updated);
+    }
+}
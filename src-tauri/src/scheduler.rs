@@ -0,0 +1,423 @@
+//! Cron-style scheduling for CC agents
+//!
+//! This module provides:
+//! - A small parser for standard 5-field cron expressions
+//!   (`minute hour day-of-month month day-of-week`) plus the common
+//!   `@hourly`/`@daily`/`@weekly`/`@monthly`/`@yearly` shortcuts
+//! - A background task that launches headless agent runs when a schedule
+//!   stored in `agent_schedules` comes due
+//!
+//! Schedules are evaluated in the machine's local time zone, so `0 2 * * *`
+//! means 2am wherever the app is running.
+
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use log::{error, info, warn};
+use rusqlite::params;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands::agents::{execute_agent, AgentDb};
+use crate::process::ProcessRegistryState;
+
+/// How often the scheduler checks for due schedules
+const SCHEDULER_TICK_SECS: u64 = 20;
+
+/// Upper bound on how far ahead `next_after` searches (a little over 4 years,
+/// enough to find the next occurrence of `0 0 29 2 *`)
+const MAX_SEARCH_MINUTES: i64 = 60 * 24 * 366 * 5;
+
+/// A parsed cron expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether day-of-month was restricted (not `*`)
+    dom_restricted: bool,
+    /// Whether day-of-week was restricted (not `*`)
+    dow_restricted: bool,
+}
+
+impl CronSchedule {
+    /// Parse a 5-field cron expression or one of the `@` shortcuts
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expanded = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "Invalid cron expression '{}': expected 5 fields (minute hour day month weekday)",
+                expression
+            ));
+        }
+
+        let minutes = parse_field(fields[0], 0, 59, "minute")?;
+        let hours = parse_field(fields[1], 0, 23, "hour")?;
+        let days_of_month = parse_field(fields[2], 1, 31, "day of month")?;
+        let months = parse_field(fields[3], 1, 12, "month")?;
+        let mut days_of_week = parse_field(fields[4], 0, 7, "day of week")?;
+
+        // Both 0 and 7 mean Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes,
+            hours,
+            days_of_month,
+            months,
+            days_of_week,
+            dom_restricted: fields[2] != "*",
+            dow_restricted: fields[4] != "*",
+        })
+    }
+
+    /// Whether the given local time (truncated to the minute) matches this schedule
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        bit_set(self.minutes, time.minute())
+            && bit_set(self.hours, time.hour())
+            && bit_set(self.months, time.month())
+            && self.day_matches(&time.date())
+    }
+
+    /// Find the first matching local time strictly after `after`
+    pub fn next_after(&self, after: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut candidate = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = candidate + Duration::minutes(MAX_SEARCH_MINUTES);
+
+        while candidate < limit {
+            if !bit_set(self.months, candidate.month()) {
+                // Jump to the first minute of the next month
+                let (year, month) = if candidate.month() == 12 {
+                    (candidate.year() + 1, 1)
+                } else {
+                    (candidate.year(), candidate.month() + 1)
+                };
+                candidate = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.day_matches(&candidate.date()) {
+                candidate = candidate.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !bit_set(self.hours, candidate.hour()) {
+                candidate = candidate.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !bit_set(self.minutes, candidate.minute()) {
+                candidate += Duration::minutes(1);
+                continue;
+            }
+            return Some(candidate);
+        }
+
+        None
+    }
+
+    /// Day matching follows the usual cron rule: when both day-of-month and
+    /// day-of-week are restricted, either one matching is enough
+    fn day_matches(&self, date: &NaiveDate) -> bool {
+        let dom = bit_set(self.days_of_month, date.day());
+        let dow = bit_set(self.days_of_week, date.weekday().num_days_from_sunday());
+
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+}
+
+fn bit_set(mask: u64, value: u32) -> bool {
+    mask & (1u64 << value) != 0
+}
+
+/// Parse a single cron field (`*`, `5`, `1-5`, `*/15`, `10-40/10`, `1,2,3`) into a bitmask
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let mut mask = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("Invalid step '{}' in {} field", step, name))?;
+                if step == 0 {
+                    return Err(format!("Step cannot be zero in {} field", name));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, min, max, name)?,
+                parse_value(end, min, max, name)?,
+            )
+        } else {
+            let value = parse_value(range, min, max, name)?;
+            // "5/10" means "starting at 5, every 10"
+            if part.contains('/') {
+                (value, max)
+            } else {
+                (value, value)
+            }
+        };
+
+        if start > end {
+            return Err(format!("Invalid range '{}' in {} field", range, name));
+        }
+
+        let mut value = start;
+        while value <= end {
+            mask |= 1u64 << value;
+            value += step;
+        }
+    }
+
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, max: u32, name: &str) -> Result<u32, String> {
+    let parsed: u32 = value
+        .parse()
+        .map_err(|_| format!("Invalid value '{}' in {} field", value, name))?;
+    if parsed < min || parsed > max {
+        return Err(format!(
+            "Value {} out of range ({}-{}) in {} field",
+            parsed, min, max, name
+        ));
+    }
+    Ok(parsed)
+}
+
+/// A schedule row that is due to run
+struct DueSchedule {
+    id: i64,
+    agent_id: i64,
+    project_path: String,
+    task: Option<String>,
+    model: Option<String>,
+}
+
+/// Start the background scheduler loop
+pub fn start_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        info!("Agent scheduler started");
+        loop {
+            if let Err(e) = run_due_schedules(&app).await {
+                error!("Agent scheduler tick failed: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(SCHEDULER_TICK_SECS)).await;
+        }
+    });
+}
+
+/// Launch every enabled schedule that matches the current minute and hasn't fired yet
+async fn run_due_schedules(app: &AppHandle) -> Result<(), String> {
+    let now = Local::now().naive_local();
+    let current_minute = now
+        .with_second(0)
+        .and_then(|t| t.with_nanosecond(0))
+        .ok_or("Failed to truncate current time")?;
+    let current_minute_str = current_minute.format("%Y-%m-%d %H:%M").to_string();
+
+    let due = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, agent_id, cron_expression, project_path, task, model, last_run_at
+                 FROM agent_schedules WHERE enabled = 1",
+            )
+            .map_err(|e| e.to_string())?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let mut due = Vec::new();
+        for (id, agent_id, cron_expression, project_path, task, model, last_run_at) in rows {
+            let schedule = match CronSchedule::parse(&cron_expression) {
+                Ok(schedule) => schedule,
+                Err(e) => {
+                    warn!(
+                        "Skipping schedule {} with invalid cron expression: {}",
+                        id, e
+                    );
+                    continue;
+                }
+            };
+
+            if !schedule.matches(&current_minute)
+                || last_run_at.as_deref() == Some(current_minute_str.as_str())
+            {
+                continue;
+            }
+
+            // Mark the schedule as fired before launching so a slow spawn can't double-fire it
+            conn.execute(
+                "UPDATE agent_schedules SET last_run_at = ?1 WHERE id = ?2",
+                params![current_minute_str, id],
+            )
+            .map_err(|e| e.to_string())?;
+
+            due.push(DueSchedule {
+                id,
+                agent_id,
+                project_path,
+                task,
+                model,
+            });
+        }
+        due
+    };
+
+    for schedule in due {
+        let task = match schedule.task {
+            Some(task) => task,
+            None => {
+                let db = app.state::<AgentDb>();
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                match conn.query_row(
+                    "SELECT default_task FROM agents WHERE id = ?1",
+                    params![schedule.agent_id],
+                    |row| row.get::<_, Option<String>>(0),
+                ) {
+                    Ok(Some(task)) => task,
+                    _ => {
+                        warn!(
+                            "Schedule {} has no task and agent {} has no default task, skipping",
+                            schedule.id, schedule.agent_id
+                        );
+                        continue;
+                    }
+                }
+            }
+        };
+
+        info!(
+            "Launching scheduled run for agent {} (schedule {})",
+            schedule.agent_id, schedule.id
+        );
+
+        match execute_agent(
+            app.clone(),
+            schedule.agent_id,
+            schedule.project_path,
+            task,
+            schedule.model,
+            None,
+            None,
+            // Scheduled runs start unattended, so they yield to whatever the user is doing
+            Some(true),
+            app.state::<AgentDb>(),
+            app.state::<ProcessRegistryState>(),
+        )
+        .await
+        {
+            Ok(run_id) => {
+                let _ = app.emit(
+                    "agent-schedule-triggered",
+                    serde_json::json!({ "schedule_id": schedule.id, "run_id": run_id }),
+                );
+            }
+            Err(e) => error!("Scheduled run for schedule {} failed: {}", schedule.id, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert a naive local time to an RFC 3339 timestamp, skipping times that
+/// fall into a DST gap
+pub fn local_to_rfc3339(time: &NaiveDateTime) -> Option<String> {
+    Local
+        .from_local_datetime(time)
+        .earliest()
+        .map(|t| t.to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_expressions() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("0 2 * * *").is_ok());
+    }
+
+    #[test]
+    fn test_next_after_nightly() {
+        let schedule = CronSchedule::parse("0 2 * * *").unwrap();
+        assert_eq!(
+            schedule.next_after(&at(2024, 3, 10, 1, 30)),
+            Some(at(2024, 3, 10, 2, 0))
+        );
+        assert_eq!(
+            schedule.next_after(&at(2024, 3, 10, 2, 0)),
+            Some(at(2024, 3, 11, 2, 0))
+        );
+    }
+
+    #[test]
+    fn test_steps_lists_and_weekdays() {
+        let schedule = CronSchedule::parse("*/15 9-17 * * 1-5").unwrap();
+        // 2024-03-09 is a Saturday, so the next run is Monday morning
+        assert_eq!(
+            schedule.next_after(&at(2024, 3, 9, 12, 0)),
+            Some(at(2024, 3, 11, 9, 0))
+        );
+        assert!(schedule.matches(&at(2024, 3, 11, 17, 45)));
+        assert!(!schedule.matches(&at(2024, 3, 11, 17, 50)));
+
+        let sunday = CronSchedule::parse("0 0 * * 7").unwrap();
+        assert!(sunday.matches(&at(2024, 3, 10, 0, 0)));
+    }
+
+    #[test]
+    fn test_leap_day() {
+        let schedule = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            schedule.next_after(&at(2024, 3, 1, 0, 0)),
+            Some(at(2028, 2, 29, 0, 0))
+        );
+    }
+}
@@ -316,6 +316,24 @@ pub fn windows_to_wsl_path(path: &str) -> String {
     path.to_string()
 }
 
+/// Convert a WSL path under /mnt/<drive> to Windows path format
+/// e.g., /mnt/c/Users/user/project -> C:\Users\user\project
+/// Other paths are returned unchanged
+pub fn wsl_to_windows_path(wsl_path: &str) -> String {
+    let Some(rest) = wsl_path.strip_prefix("/mnt/") else {
+        return wsl_path.to_string();
+    };
+    let (drive, tail) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    match drive.chars().next() {
+        Some(letter) if drive.len() == 1 && letter.is_ascii_alphabetic() => format!(
+            "{}:\\{}",
+            letter.to_ascii_uppercase(),
+            tail.trim_start_matches('/').replace('/', "\\")
+        ),
+        _ => wsl_path.to_string(),
+    }
+}
+
 /// Create a command that runs through WSL
 /// Uses CREATE_NO_WINDOW flag to prevent terminal flashing
 #[cfg(windows)]
@@ -412,6 +430,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_wsl_to_windows_path() {
+        assert_eq!(
+            wsl_to_windows_path("/mnt/c/Users/test/project"),
+            r"C:\Users\test\project"
+        );
+        assert_eq!(wsl_to_windows_path("/mnt/d"), r"D:\");
+        assert_eq!(wsl_to_windows_path("/home/jordan"), "/home/jordan");
+        assert_eq!(wsl_to_windows_path("/mnt/wsl/shared"), "/mnt/wsl/shared");
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_windows_to_wsl_path() {
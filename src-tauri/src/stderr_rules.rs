@@ -0,0 +1,173 @@
+//! Classifying what Claude writes to stderr
+//!
+//! Claude's stderr mixes Node.js runtime warnings and debug chatter with the
+//! errors that actually stop a run. Each line is checked against an ordered
+//! list of rules, and the first rule whose pattern matches decides its
+//! category. A line no rule matches is reported as an error, so nothing
+//! unexpected is played down.
+
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// How much a stderr line matters
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum StderrSeverity {
+    Info,
+    Warning,
+    Error,
+    /// The run can't go on, or won't get anywhere until the user steps in
+    Fatal,
+}
+
+/// What kind of line a rule recognised
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StderrCategory {
+    /// A Node.js runtime warning, such as an experimental or deprecated API
+    NodeWarning,
+    /// Debug or progress output
+    Diagnostic,
+    /// A frame of a stack trace
+    StackTrace,
+    /// Not logged in, an invalid API key or an expired token
+    Auth,
+    RateLimit,
+    Network,
+    /// An uncaught exception or a crash of the Claude process
+    Crash,
+    Other,
+}
+
+impl StderrCategory {
+    pub fn severity(self) -> StderrSeverity {
+        match self {
+            StderrCategory::Diagnostic | StderrCategory::StackTrace => StderrSeverity::Info,
+            StderrCategory::NodeWarning => StderrSeverity::Warning,
+            StderrCategory::RateLimit | StderrCategory::Network | StderrCategory::Other => {
+                StderrSeverity::Error
+            }
+            StderrCategory::Auth | StderrCategory::Crash => StderrSeverity::Fatal,
+        }
+    }
+}
+
+/// Patterns checked in order; the first match decides a line's category
+const RULES: &[(&str, StderrCategory)] = &[
+    (
+        r"(?i)invalid api key|not logged in|please run /login|authentication_error|oauth token (has )?expired|\b401\b.*unauthorized|credit balance is too low",
+        StderrCategory::Auth,
+    ),
+    (
+        r"^\(node:\d+\) |(Experimental|Deprecation|MaxListenersExceeded)Warning|--trace-(warnings|deprecation)",
+        StderrCategory::NodeWarning,
+    ),
+    (r"^\s+at .+(\(.*\)|:\d+:\d+)$", StderrCategory::StackTrace),
+    (
+        r"(?i)rate.?limit|\b429\b|overloaded_error|usage limit reached",
+        StderrCategory::RateLimit,
+    ),
+    (
+        r"ECONNREFUSED|ECONNRESET|ETIMEDOUT|ENOTFOUND|EAI_AGAIN|getaddrinfo|socket hang up|(?i)fetch failed",
+        StderrCategory::Network,
+    ),
+    (
+        r"^(Uncaught )?(Error|TypeError|RangeError|ReferenceError|SyntaxError)\b|FATAL ERROR|Segmentation fault|panicked at|(?i)unhandled (promise )?rejection",
+        StderrCategory::Crash,
+    ),
+    (
+        r"^\[(DEBUG|INFO|TRACE)\]|^(debug|info):|^\s*$",
+        StderrCategory::Diagnostic,
+    ),
+];
+
+/// A line Claude wrote to stderr, classified
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StderrLine {
+    pub line: String,
+    pub severity: StderrSeverity,
+    pub category: StderrCategory,
+}
+
+fn rules() -> &'static [(Regex, StderrCategory)] {
+    static RULES_COMPILED: OnceLock<Vec<(Regex, StderrCategory)>> = OnceLock::new();
+    RULES_COMPILED.get_or_init(|| {
+        RULES
+            .iter()
+            .map(|(pattern, category)| {
+                (
+                    Regex::new(pattern).expect("stderr rule is a valid regex"),
+                    *category,
+                )
+            })
+            .collect()
+    })
+}
+
+/// Classify one line of stderr
+pub fn classify_stderr(line: &str) -> StderrLine {
+    let category = rules()
+        .iter()
+        .find(|(pattern, _)| pattern.is_match(line))
+        .map(|(_, category)| *category)
+        .unwrap_or(StderrCategory::Other);
+    StderrLine {
+        line: line.to_string(),
+        severity: category.severity(),
+        category,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_stderr() {
+        let category = |line: &str| classify_stderr(line).category;
+
+        assert_eq!(
+            category("(node:4242) ExperimentalWarning: The Fetch API is an experimental feature."),
+            StderrCategory::NodeWarning
+        );
+        assert_eq!(
+            category("(Use `node --trace-warnings ...` to show where the warning was created)"),
+            StderrCategory::NodeWarning
+        );
+        assert_eq!(
+            category("Invalid API key · Please run /login"),
+            StderrCategory::Auth
+        );
+        assert_eq!(
+            category(
+                "API Error: 429 {\"type\":\"error\",\"error\":{\"type\":\"rate_limit_error\"}}"
+            ),
+            StderrCategory::RateLimit
+        );
+        assert_eq!(
+            category("Error: connect ECONNREFUSED 127.0.0.1:443"),
+            StderrCategory::Network
+        );
+        assert_eq!(
+            category("TypeError: Cannot read properties of undefined (reading 'map')"),
+            StderrCategory::Crash
+        );
+        assert_eq!(
+            category("    at Object.<anonymous> (/usr/lib/node_modules/cli.js:12:5)"),
+            StderrCategory::StackTrace
+        );
+        assert_eq!(
+            category("[DEBUG] Loaded 3 hooks"),
+            StderrCategory::Diagnostic
+        );
+
+        let unknown = classify_stderr("something odd happened");
+        assert_eq!(unknown.category, StderrCategory::Other);
+        assert_eq!(unknown.severity, StderrSeverity::Error);
+        assert_eq!(
+            classify_stderr("Not logged in").severity,
+            StderrSeverity::Fatal
+        );
+    }
+}
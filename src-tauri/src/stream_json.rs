@@ -0,0 +1,283 @@
+//! Typed reading of Claude's `--output-format stream-json` output
+//!
+//! Each line Claude writes to stdout is one message. Known message kinds are
+//! read into typed variants, with tool calls and their results as content
+//! blocks of assistant and user messages. A message kind this version of
+//! opcode doesn't know, or a known one missing a field it needs, is kept as
+//! raw JSON. Only a line that isn't a JSON object is an error, so callers can
+//! surface it instead of silently dropping it.
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::fmt;
+
+use crate::transcript::{parse_block, ContentBlock};
+
+/// Longest part of an unparseable line kept in its error
+const ERROR_LINE_PREVIEW_BYTES: usize = 500;
+
+/// One message of a stream-json stream
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StreamMessage {
+    /// `system` / `init`, sent once when the session starts
+    Init(InitMessage),
+    /// Any other `system` message, such as a compaction boundary
+    System {
+        subtype: Option<String>,
+        raw: JsonValue,
+    },
+    Assistant(StreamTurn),
+    /// A user turn; in a stream this carries the results of tool calls
+    User(StreamTurn),
+    /// The final message of a run
+    Result(ResultMessage),
+    /// An error Claude reported on the stream instead of a result
+    Error {
+        message: String,
+        raw: JsonValue,
+    },
+    /// A message kind this version of opcode doesn't know
+    Unknown {
+        raw: JsonValue,
+    },
+}
+
+/// The session Claude started
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InitMessage {
+    pub session_id: String,
+    pub model: Option<String>,
+    pub cwd: Option<String>,
+    pub tools: Vec<String>,
+    pub permission_mode: Option<String>,
+}
+
+/// An assistant or user message
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct StreamTurn {
+    /// Id of the API message; streamed chunks of one message share it
+    pub id: Option<String>,
+    pub session_id: Option<String>,
+    /// The Task tool call this message belongs to, for subagent messages
+    pub parent_tool_use_id: Option<String>,
+    pub model: Option<String>,
+    pub content: Vec<ContentBlock>,
+    pub usage: Option<JsonValue>,
+}
+
+/// How a run ended
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct ResultMessage {
+    pub subtype: Option<String>,
+    pub is_error: bool,
+    /// Claude's final answer, or its error
+    pub result: Option<String>,
+    pub session_id: Option<String>,
+    pub total_cost_usd: Option<f64>,
+    pub duration_ms: Option<u64>,
+    pub num_turns: Option<u64>,
+}
+
+/// A line of output that isn't a stream-json message
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StreamParseError {
+    /// The start of the line
+    pub line: String,
+    pub error: String,
+}
+
+impl fmt::Display for StreamParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in stream line: {}", self.error, self.line)
+    }
+}
+
+impl std::error::Error for StreamParseError {}
+
+impl StreamTurn {
+    /// The tools called in this message, as (id, name, input)
+    pub fn tool_uses(&self) -> impl Iterator<Item = (Option<&str>, &str, &JsonValue)> {
+        self.content.iter().filter_map(|block| match block {
+            ContentBlock::ToolUse { id, name, input } => {
+                Some((id.as_deref(), name.as_str(), input))
+            }
+            _ => None,
+        })
+    }
+}
+
+fn string_field(value: &JsonValue, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+fn parse_turn(json: &JsonValue) -> Option<StreamTurn> {
+    let message = json.get("message")?;
+    let content = match message.get("content")? {
+        JsonValue::String(text) => vec![ContentBlock::Text { text: text.clone() }],
+        JsonValue::Array(blocks) => blocks.iter().map(parse_block).collect(),
+        _ => return None,
+    };
+    Some(StreamTurn {
+        id: string_field(message, "id"),
+        session_id: string_field(json, "session_id"),
+        parent_tool_use_id: string_field(json, "parent_tool_use_id"),
+        model: string_field(message, "model"),
+        content,
+        usage: message.get("usage").cloned(),
+    })
+}
+
+fn parse_init(json: &JsonValue) -> Option<InitMessage> {
+    Some(InitMessage {
+        session_id: string_field(json, "session_id")?,
+        model: string_field(json, "model"),
+        cwd: string_field(json, "cwd"),
+        tools: json
+            .get("tools")
+            .and_then(|tools| tools.as_array())
+            .map(|tools| {
+                tools
+                    .iter()
+                    .filter_map(|tool| tool.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        permission_mode: string_field(json, "permissionMode"),
+    })
+}
+
+fn parse_result(json: &JsonValue) -> ResultMessage {
+    ResultMessage {
+        subtype: string_field(json, "subtype"),
+        is_error: json
+            .get("is_error")
+            .and_then(|e| e.as_bool())
+            .unwrap_or(false),
+        result: string_field(json, "result"),
+        session_id: string_field(json, "session_id"),
+        total_cost_usd: json.get("total_cost_usd").and_then(|c| c.as_f64()),
+        duration_ms: json.get("duration_ms").and_then(|d| d.as_u64()),
+        num_turns: json.get("num_turns").and_then(|n| n.as_u64()),
+    }
+}
+
+/// The text of an `error` message, which is either a string or an object
+/// with a message
+fn error_text(json: &JsonValue) -> Option<String> {
+    match json.get("error") {
+        Some(JsonValue::String(message)) => Some(message.clone()),
+        Some(error) => string_field(error, "message"),
+        None => None,
+    }
+    .or_else(|| string_field(json, "message"))
+}
+
+/// Read a parsed stream line into a typed message
+pub fn parse_message(json: &JsonValue) -> StreamMessage {
+    let parsed = match json.get("type").and_then(|t| t.as_str()) {
+        Some("system") => match json.get("subtype").and_then(|s| s.as_str()) {
+            Some("init") => parse_init(json).map(StreamMessage::Init),
+            subtype => Some(StreamMessage::System {
+                subtype: subtype.map(str::to_string),
+                raw: json.clone(),
+            }),
+        },
+        Some("assistant") => parse_turn(json).map(StreamMessage::Assistant),
+        Some("user") => parse_turn(json).map(StreamMessage::User),
+        Some("result") => Some(StreamMessage::Result(parse_result(json))),
+        Some("error") => Some(StreamMessage::Error {
+            message: error_text(json).unwrap_or_else(|| "Unknown error".to_string()),
+            raw: json.clone(),
+        }),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| StreamMessage::Unknown { raw: json.clone() })
+}
+
+/// Parse one line of stream-json output into its JSON and typed message
+pub fn parse_line(line: &str) -> Result<(JsonValue, StreamMessage), StreamParseError> {
+    let error = |error: String| {
+        let mut end = ERROR_LINE_PREVIEW_BYTES.min(line.len());
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        StreamParseError {
+            line: line[..end].to_string(),
+            error,
+        }
+    };
+    let json = serde_json::from_str::<JsonValue>(line).map_err(|e| error(e.to_string()))?;
+    if !json.is_object() {
+        return Err(error("Expected a JSON object".to_string()));
+    }
+    let message = parse_message(&json);
+    Ok((json, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stream_lines() {
+        let (_, init) = parse_line(
+            r#"{"type":"system","subtype":"init","session_id":"abc","model":"claude-sonnet-4","tools":["Bash","Read"]}"#,
+        )
+        .unwrap();
+        let StreamMessage::Init(init) = init else {
+            panic!("expected init, got {:?}", init);
+        };
+        assert_eq!(init.session_id, "abc");
+        assert_eq!(init.tools, vec!["Bash", "Read"]);
+
+        let (_, assistant) = parse_line(
+            r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"text","text":"Listing"},{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}}]}}"#,
+        )
+        .unwrap();
+        let StreamMessage::Assistant(turn) = assistant else {
+            panic!("expected assistant, got {:?}", assistant);
+        };
+        let tools: Vec<_> = turn.tool_uses().collect();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].1, "Bash");
+        assert_eq!(tools[0].2["command"], "ls");
+
+        let (_, user) = parse_line(
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"a.txt","is_error":false}]}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            user,
+            StreamMessage::User(StreamTurn { ref content, .. })
+                if matches!(content[0], ContentBlock::ToolResult { .. })
+        ));
+
+        let (_, result) =
+            parse_line(r#"{"type":"result","subtype":"error_max_turns","is_error":true,"total_cost_usd":0.25}"#)
+                .unwrap();
+        let StreamMessage::Result(result) = result else {
+            panic!("expected result, got {:?}", result);
+        };
+        assert!(result.is_error);
+        assert_eq!(result.total_cost_usd, Some(0.25));
+
+        let (_, error) = parse_line(
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        )
+        .unwrap();
+        assert!(
+            matches!(error, StreamMessage::Error { ref message, .. } if message == "Overloaded")
+        );
+
+        // Unknown kinds and known kinds missing what they need are kept raw
+        let (_, unknown) = parse_line(r#"{"type":"stream_event","event":{}}"#).unwrap();
+        assert!(matches!(unknown, StreamMessage::Unknown { .. }));
+        let (_, no_session) = parse_line(r#"{"type":"system","subtype":"init"}"#).unwrap();
+        assert!(matches!(no_session, StreamMessage::Unknown { .. }));
+
+        let error = parse_line("Error: not logged in").unwrap_err();
+        assert_eq!(error.line, "Error: not logged in");
+        assert!(parse_line("[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use serde_json::{Map, Value as JsonValue};
+
+/// Claude Code release family that wrote a transcript line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SchemaVersion {
+    /// 0.x releases: flat `role`/`content` lines, snake_case ids and plain string
+    /// replies. Lines from before the `version` field existed are treated as this.
+    Legacy,
+    /// 1.x and later: entries wrap a Messages API `message` and use camelCase ids
+    Current,
+}
+
+impl SchemaVersion {
+    /// Work out the schema from the `version` field Claude Code stamps on each line
+    pub fn detect(entry: &JsonValue) -> Self {
+        let major = entry
+            .get("version")
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.split('.').next())
+            .and_then(|major| major.parse::<u32>().ok());
+        match major {
+            Some(major) if major >= 1 => Self::Current,
+            Some(_) => Self::Legacy,
+            // Summaries were never stamped; snake_case ids give old ones away
+            None if entry.get("session_id").is_some() || entry.get("leaf_uuid").is_some() => {
+                Self::Legacy
+            }
+            // Unversioned lines in the current shape are from tools that don't stamp one
+            None if entry.get("message").is_some() || entry.get("type").is_some() => Self::Current,
+            None => Self::Legacy,
+        }
+    }
+}
+
+/// Keys renamed to camelCase when the 1.0 schema landed
+const RENAMED_KEYS: &[(&str, &str)] = &[
+    ("session_id", "sessionId"),
+    ("parent_uuid", "parentUuid"),
+    ("is_sidechain", "isSidechain"),
+    ("user_type", "userType"),
+    ("leaf_uuid", "leafUuid"),
+    ("cost_usd", "costUSD"),
+    ("duration_ms", "durationMs"),
+    ("tool_use_result", "toolUseResult"),
+];
+
+/// Bring a transcript line up to the current schema, in place
+pub fn upgrade(entry: &mut JsonValue) {
+    match SchemaVersion::detect(entry) {
+        SchemaVersion::Legacy => upgrade_legacy(entry),
+        SchemaVersion::Current => {}
+    }
+    normalize_content(entry);
+}
+
+fn upgrade_legacy(entry: &mut JsonValue) {
+    let Some(object) = entry.as_object_mut() else {
+        return;
+    };
+
+    for (old, new) in RENAMED_KEYS {
+        if let Some(value) = object.remove(*old) {
+            object.entry(*new).or_insert(value);
+        }
+    }
+
+    // Flat `{"role": "user", "content": ...}` lines predate the message wrapper
+    if object.get("message").is_none() {
+        if let Some(role) = object
+            .get("role")
+            .and_then(|r| r.as_str())
+            .map(str::to_string)
+        {
+            let mut message = Map::new();
+            message.insert("role".to_string(), JsonValue::String(role.clone()));
+            for key in ["content", "model", "usage", "id", "stop_reason"] {
+                if let Some(value) = object.remove(key) {
+                    message.insert(key.to_string(), value);
+                }
+            }
+            object.remove("role");
+            object
+                .entry("type")
+                .or_insert_with(|| JsonValue::String(role));
+            object.insert("message".to_string(), JsonValue::Object(message));
+        }
+    }
+}
+
+/// Lenient fixes that apply to every version: assistant replies stored as a plain
+/// string become a text block, since renderers expect a block list there
+fn normalize_content(entry: &mut JsonValue) {
+    if entry.get("type").and_then(|t| t.as_str()) != Some("assistant") {
+        return;
+    }
+    let Some(content) = entry.get_mut("message").and_then(|m| m.get_mut("content")) else {
+        return;
+    };
+    if let JsonValue::String(text) = content {
+        *content = serde_json::json!([{ "type": "text", "text": text }]);
+    }
+}
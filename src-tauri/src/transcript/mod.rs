@@ -0,0 +1,323 @@
+//! Lenient parsing of Claude Code session transcripts
+//!
+//! The JSONL schema has changed across Claude Code releases. Each line is first
+//! upgraded to the current schema by the adapter for the version that wrote it,
+//! then read into typed entries. Anything that isn't understood, whether a whole
+//! entry or a single content block, is kept as raw JSON instead of failing the
+//! transcript.
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::io::BufRead;
+
+pub mod adapters;
+
+use adapters::upgrade;
+
+/// One line of a transcript
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TranscriptEntry {
+    User(MessageEntry),
+    Assistant(MessageEntry),
+    System(SystemEntry),
+    Summary(SummaryEntry),
+    /// An entry kind this version of opcode doesn't know
+    Unknown {
+        raw: JsonValue,
+    },
+}
+
+/// A user or assistant message
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct MessageEntry {
+    pub uuid: Option<String>,
+    pub parent_uuid: Option<String>,
+    pub session_id: Option<String>,
+    pub timestamp: Option<String>,
+    /// Claude Code version that wrote the line
+    pub version: Option<String>,
+    pub is_sidechain: bool,
+    pub model: Option<String>,
+    pub content: Vec<ContentBlock>,
+    pub usage: Option<JsonValue>,
+}
+
+/// A block of message content
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    ToolUse {
+        id: Option<String>,
+        name: String,
+        input: JsonValue,
+    },
+    ToolResult {
+        tool_use_id: Option<String>,
+        content: JsonValue,
+        is_error: bool,
+    },
+    Image {
+        source: JsonValue,
+    },
+    /// A block type this version of opcode doesn't know
+    Unknown {
+        raw: JsonValue,
+    },
+}
+
+/// A system notice such as a compaction boundary or hook output
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SystemEntry {
+    pub uuid: Option<String>,
+    pub subtype: Option<String>,
+    pub content: Option<String>,
+    pub timestamp: Option<String>,
+}
+
+/// A summary Claude wrote of the conversation up to `leaf_uuid`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SummaryEntry {
+    pub summary: String,
+    pub leaf_uuid: Option<String>,
+}
+
+fn string_field(value: &JsonValue, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+pub fn parse_block(block: &JsonValue) -> ContentBlock {
+    let parsed = match block.get("type").and_then(|t| t.as_str()) {
+        Some("text") => string_field(block, "text").map(|text| ContentBlock::Text { text }),
+        Some("thinking") => {
+            string_field(block, "thinking").map(|thinking| ContentBlock::Thinking { thinking })
+        }
+        Some("tool_use") => string_field(block, "name").map(|name| ContentBlock::ToolUse {
+            id: string_field(block, "id"),
+            name,
+            input: block.get("input").cloned().unwrap_or(JsonValue::Null),
+        }),
+        Some("tool_result") => Some(ContentBlock::ToolResult {
+            tool_use_id: string_field(block, "tool_use_id"),
+            content: block.get("content").cloned().unwrap_or(JsonValue::Null),
+            is_error: block
+                .get("is_error")
+                .and_then(|e| e.as_bool())
+                .unwrap_or(false),
+        }),
+        Some("image") => block
+            .get("source")
+            .cloned()
+            .map(|source| ContentBlock::Image { source }),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| ContentBlock::Unknown { raw: block.clone() })
+}
+
+fn parse_message(entry: &JsonValue) -> Option<MessageEntry> {
+    let message = entry.get("message")?;
+    let content = match message.get("content")? {
+        JsonValue::String(text) => vec![ContentBlock::Text { text: text.clone() }],
+        JsonValue::Array(blocks) => blocks.iter().map(parse_block).collect(),
+        _ => return None,
+    };
+    Some(MessageEntry {
+        uuid: string_field(entry, "uuid"),
+        parent_uuid: string_field(entry, "parentUuid"),
+        session_id: string_field(entry, "sessionId"),
+        timestamp: string_field(entry, "timestamp"),
+        version: string_field(entry, "version"),
+        is_sidechain: entry
+            .get("isSidechain")
+            .and_then(|s| s.as_bool())
+            .unwrap_or(false),
+        model: string_field(message, "model"),
+        content,
+        usage: message.get("usage").cloned(),
+    })
+}
+
+/// Read an already upgraded line into a typed entry
+fn parse_entry(entry: &JsonValue) -> Option<TranscriptEntry> {
+    match entry.get("type").and_then(|t| t.as_str())? {
+        "user" => parse_message(entry).map(TranscriptEntry::User),
+        "assistant" => parse_message(entry).map(TranscriptEntry::Assistant),
+        "system" => Some(TranscriptEntry::System(SystemEntry {
+            uuid: string_field(entry, "uuid"),
+            subtype: string_field(entry, "subtype"),
+            content: string_field(entry, "content"),
+            timestamp: string_field(entry, "timestamp"),
+        })),
+        "summary" => string_field(entry, "summary").map(|summary| {
+            TranscriptEntry::Summary(SummaryEntry {
+                summary,
+                leaf_uuid: string_field(entry, "leafUuid"),
+            })
+        }),
+        _ => None,
+    }
+}
+
+/// Upgrade a transcript line to the current schema. Lines that aren't JSON are
+/// dropped.
+pub fn upgrade_line(line: &str) -> Option<JsonValue> {
+    let mut entry = serde_json::from_str::<JsonValue>(line).ok()?;
+    upgrade(&mut entry);
+    Some(entry)
+}
+
+/// Parse a transcript line of any known schema version. Lines that aren't JSON
+/// are dropped; JSON that can't be understood is returned as `Unknown`.
+pub fn parse_line(line: &str) -> Option<TranscriptEntry> {
+    let entry = upgrade_line(line)?;
+    Some(parse_entry(&entry).unwrap_or(TranscriptEntry::Unknown { raw: entry }))
+}
+
+/// Parse a whole transcript
+pub fn parse_transcript(reader: impl BufRead) -> Vec<TranscriptEntry> {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| parse_line(&line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::adapters::SchemaVersion;
+    use super::*;
+
+    /// Transcripts written by different Claude Code releases, trimmed and scrubbed
+    const CORPUS: &[(&str, &str)] = &[
+        (
+            "legacy_0_2",
+            include_str!("../../tests/fixtures/transcripts/legacy_0_2.jsonl"),
+        ),
+        (
+            "v1_0",
+            include_str!("../../tests/fixtures/transcripts/v1_0.jsonl"),
+        ),
+        (
+            "v2_0",
+            include_str!("../../tests/fixtures/transcripts/v2_0.jsonl"),
+        ),
+    ];
+
+    fn texts(entry: &TranscriptEntry) -> Vec<&str> {
+        let (TranscriptEntry::User(message) | TranscriptEntry::Assistant(message)) = entry else {
+            return Vec::new();
+        };
+        message
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_corpus_parses_into_the_same_conversation() {
+        for (name, jsonl) in CORPUS {
+            let entries = parse_transcript(jsonl.as_bytes());
+            let messages: Vec<&TranscriptEntry> = entries
+                .iter()
+                .filter(|e| matches!(e, TranscriptEntry::User(_) | TranscriptEntry::Assistant(_)))
+                .collect();
+
+            // Every release records the same exchange: a prompt, a tool call, its
+            // result and a final answer
+            assert_eq!(messages.len(), 4, "{}", name);
+            assert!(matches!(messages[0], TranscriptEntry::User(_)), "{}", name);
+            assert_eq!(texts(messages[0]), vec!["List the TODOs"], "{}", name);
+            let TranscriptEntry::Assistant(call) = messages[1] else {
+                panic!("{}: expected an assistant tool call", name);
+            };
+            assert!(
+                call.content
+                    .iter()
+                    .any(|b| matches!(b, ContentBlock::ToolUse { name, .. } if name == "Grep")),
+                "{}",
+                name
+            );
+            let TranscriptEntry::User(result) = messages[2] else {
+                panic!("{}: expected a tool result", name);
+            };
+            assert!(
+                matches!(
+                    &result.content[0],
+                    ContentBlock::ToolResult {
+                        is_error: false,
+                        ..
+                    }
+                ),
+                "{}",
+                name
+            );
+            assert_eq!(texts(messages[3]), vec!["There are 2 TODOs."], "{}", name);
+            assert_eq!(
+                call.session_id.as_deref(),
+                Some("7f0c2d4e-session"),
+                "{}",
+                name
+            );
+
+            assert!(
+                entries
+                    .iter()
+                    .any(|e| matches!(e, TranscriptEntry::Summary(s) if s.summary == "TODO sweep")),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_kinds_degrade_to_raw_json() {
+        let entries = parse_transcript(CORPUS[2].1.as_bytes());
+        assert!(entries.iter().any(|e| matches!(
+            e,
+            TranscriptEntry::Unknown { raw } if raw["type"] == "file-history-snapshot"
+        )));
+        let blocks: Vec<&ContentBlock> = entries
+            .iter()
+            .filter_map(|e| match e {
+                TranscriptEntry::Assistant(m) => Some(&m.content),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert!(blocks.iter().any(|b| matches!(
+            b,
+            ContentBlock::Unknown { raw } if raw["type"] == "server_tool_use"
+        )));
+        assert!(blocks
+            .iter()
+            .any(|b| matches!(b, ContentBlock::Thinking { .. })));
+
+        assert_eq!(parse_line("not json"), None);
+        assert_eq!(
+            parse_line(r#"{"type":"user","message":{"content":42}}"#),
+            Some(TranscriptEntry::Unknown {
+                raw: serde_json::json!({"type": "user", "message": {"content": 42}})
+            })
+        );
+    }
+
+    #[test]
+    fn test_legacy_lines_are_upgraded_for_renderers() {
+        let upgraded: Vec<JsonValue> = CORPUS[0].1.lines().filter_map(upgrade_line).collect();
+        let reply = upgraded.iter().rfind(|e| e["type"] == "assistant").unwrap();
+        assert_eq!(reply["message"]["content"][0]["type"], "text");
+        assert_eq!(reply["sessionId"], "7f0c2d4e-session");
+        assert!(reply.get("session_id").is_none());
+        assert_eq!(SchemaVersion::detect(&upgraded[1]), SchemaVersion::Legacy);
+    }
+}
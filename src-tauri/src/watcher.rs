@@ -0,0 +1,367 @@
+//! File-watch triggered agent runs
+//!
+//! An agent can be attached to a project with a glob pattern. A notify-based
+//! watcher observes the project directory, debounces bursts of changes, and
+//! launches the agent with the list of changed files injected into its task.
+//!
+//! While a run started by a watch is still queued or running, further changes
+//! for that watch are ignored so an agent editing matching files can't trigger
+//! itself in a loop.
+
+use log::{error, info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use rusqlite::{params, Connection};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
+
+use crate::commands::agents::{execute_agent, AgentDb};
+use crate::process::ProcessRegistryState;
+
+/// Placeholder replaced with the changed file list in a watch's task
+pub const CHANGED_FILES_PLACEHOLDER: &str = "{{changed_files}}";
+
+/// Directories whose changes never trigger a watch
+const IGNORED_DIRECTORIES: &[&str] = &[".git", "node_modules", "target", ".claude"];
+
+/// A configured file watch as stored in agent_file_watches
+#[derive(Debug, Clone)]
+pub struct FileWatchConfig {
+    pub id: i64,
+    pub agent_id: i64,
+    pub project_path: String,
+    pub glob_pattern: String,
+    pub task: Option<String>,
+    pub model: Option<String>,
+    pub debounce_ms: u64,
+}
+
+/// Active watchers keyed by watch id; dropping a watcher stops it
+#[derive(Default)]
+pub struct FileWatchState(pub Mutex<HashMap<i64, RecommendedWatcher>>);
+
+/// Whether a changed path (relative to the project) matches the watch pattern
+pub fn path_matches(pattern: &glob::Pattern, relative: &Path) -> bool {
+    if relative.components().any(|c| {
+        IGNORED_DIRECTORIES
+            .iter()
+            .any(|ignored| c.as_os_str() == *ignored)
+    }) {
+        return false;
+    }
+
+    let options = glob::MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    pattern.matches_path_with(relative, options)
+}
+
+/// Build the task for a triggered run, injecting the changed files
+pub fn build_triggered_task(task: &str, changed_files: &[String]) -> String {
+    let file_list = changed_files
+        .iter()
+        .map(|f| format!("- {}", f))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if task.contains(CHANGED_FILES_PLACEHOLDER) {
+        task.replace(CHANGED_FILES_PLACEHOLDER, &file_list)
+    } else {
+        format!("{}\n\nChanged files:\n{}", task, file_list)
+    }
+}
+
+/// Load a single watch configuration
+fn load_watch(conn: &Connection, watch_id: i64) -> rusqlite::Result<FileWatchConfig> {
+    conn.query_row(
+        "SELECT id, agent_id, project_path, glob_pattern, task, model, debounce_ms FROM agent_file_watches WHERE id = ?1",
+        params![watch_id],
+        |row| {
+            Ok(FileWatchConfig {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                project_path: row.get(2)?,
+                glob_pattern: row.get(3)?,
+                task: row.get(4)?,
+                model: row.get(5)?,
+                debounce_ms: row.get::<_, i64>(6)?.max(0) as u64,
+            })
+        },
+    )
+}
+
+/// Start a watcher for the given watch, replacing any existing watcher for it
+pub fn start_file_watch(app: &AppHandle, config: FileWatchConfig) -> Result<(), String> {
+    let pattern = glob::Pattern::new(&config.glob_pattern)
+        .map_err(|e| format!("Invalid glob pattern '{}': {}", config.glob_pattern, e))?;
+    let project_path = PathBuf::from(&config.project_path);
+    if !project_path.is_dir() {
+        return Err(format!(
+            "Project path does not exist: {}",
+            config.project_path
+        ));
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel::<PathBuf>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    watcher
+        .watch(&project_path, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", config.project_path, e))?;
+
+    {
+        let state = app.state::<FileWatchState>();
+        let mut watchers = state.0.lock().map_err(|e| e.to_string())?;
+        watchers.insert(config.id, watcher);
+    }
+
+    info!(
+        "Watching {} for '{}' (watch {})",
+        config.project_path, config.glob_pattern, config.id
+    );
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(debounce_and_trigger(app, config, pattern, project_path, rx));
+    Ok(())
+}
+
+/// Stop the watcher for a watch, if one is running
+pub fn stop_file_watch(app: &AppHandle, watch_id: i64) {
+    let state = app.state::<FileWatchState>();
+    if let Ok(mut watchers) = state.0.lock() {
+        if watchers.remove(&watch_id).is_some() {
+            info!("Stopped file watch {}", watch_id);
+        }
+    };
+}
+
+/// Start watchers for every enabled watch (called at startup)
+pub fn start_file_watches(app: &AppHandle) {
+    let watch_ids: Vec<i64> = {
+        let db = app.state::<AgentDb>();
+        let Ok(conn) = db.0.lock() else {
+            return;
+        };
+        let mut stmt = match conn.prepare("SELECT id FROM agent_file_watches WHERE enabled = 1") {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                error!("Failed to load file watches: {}", e);
+                return;
+            }
+        };
+        let ids = match stmt.query_map([], |row| row.get(0)) {
+            Ok(rows) => rows.filter_map(Result::ok).collect(),
+            Err(e) => {
+                error!("Failed to load file watches: {}", e);
+                return;
+            }
+        };
+        ids
+    };
+
+    for watch_id in watch_ids {
+        let config = {
+            let db = app.state::<AgentDb>();
+            let Ok(conn) = db.0.lock() else {
+                return;
+            };
+            load_watch(&conn, watch_id)
+        };
+
+        match config {
+            Ok(config) => {
+                if let Err(e) = start_file_watch(app, config) {
+                    warn!("Failed to start file watch {}: {}", watch_id, e);
+                }
+            }
+            Err(e) => warn!("Failed to load file watch {}: {}", watch_id, e),
+        }
+    }
+}
+
+/// Collect change bursts and launch the agent once things settle down
+async fn debounce_and_trigger(
+    app: AppHandle,
+    config: FileWatchConfig,
+    pattern: glob::Pattern,
+    project_path: PathBuf,
+    mut rx: mpsc::UnboundedReceiver<PathBuf>,
+) {
+    let debounce = tokio::time::Duration::from_millis(config.debounce_ms.max(100));
+    let mut last_run_id: Option<i64> = None;
+
+    // The channel closes when the watcher is dropped, which ends this task
+    while let Some(first) = rx.recv().await {
+        let mut changed = BTreeSet::new();
+        let mut pending = Some(first);
+
+        loop {
+            if let Some(path) = pending.take() {
+                if let Ok(relative) = path.strip_prefix(&project_path) {
+                    if path_matches(&pattern, relative) {
+                        changed.insert(relative.to_string_lossy().replace('\\', "/"));
+                    }
+                }
+            }
+
+            match tokio::time::timeout(debounce, rx.recv()).await {
+                Ok(Some(path)) => pending = Some(path),
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+
+        if changed.is_empty() {
+            continue;
+        }
+
+        // Don't retrigger while our previous run is still in flight
+        if let Some(run_id) = last_run_id {
+            if run_in_flight(&app, run_id) {
+                info!(
+                    "File watch {} ignoring {} changes while run {} is active",
+                    config.id,
+                    changed.len(),
+                    run_id
+                );
+                continue;
+            }
+        }
+
+        let changed: Vec<String> = changed.into_iter().collect();
+        match trigger_watch_run(&app, &config, &changed).await {
+            Ok(run_id) => {
+                last_run_id = Some(run_id);
+                let _ = app.emit(
+                    "agent-file-watch-triggered",
+                    serde_json::json!({
+                        "watch_id": config.id,
+                        "run_id": run_id,
+                        "changed_files": changed,
+                    }),
+                );
+            }
+            Err(e) => error!("File watch {} failed to launch agent: {}", config.id, e),
+        }
+    }
+}
+
+fn run_in_flight(app: &AppHandle, run_id: i64) -> bool {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return false;
+    };
+    conn.query_row(
+        "SELECT status FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| row.get::<_, String>(0),
+    )
+    .map(|status| status == "queued" || status == "running")
+    .unwrap_or(false)
+}
+
+async fn trigger_watch_run(
+    app: &AppHandle,
+    config: &FileWatchConfig,
+    changed: &[String],
+) -> Result<i64, String> {
+    let task = match &config.task {
+        Some(task) => task.clone(),
+        None => {
+            let db = app.state::<AgentDb>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            conn.query_row(
+                "SELECT default_task FROM agents WHERE id = ?1",
+                params![config.agent_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .map_err(|e| e.to_string())?
+            .unwrap_or_else(|| "Review the changed files.".to_string())
+        }
+    };
+
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let _ = conn.execute(
+            "UPDATE agent_file_watches SET last_triggered_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![config.id],
+        );
+    }
+
+    info!(
+        "File watch {} launching agent {} for {} changed files",
+        config.id,
+        config.agent_id,
+        changed.len()
+    );
+
+    execute_agent(
+        app.clone(),
+        config.agent_id,
+        config.project_path.clone(),
+        build_triggered_task(&task, changed),
+        config.model.clone(),
+        None,
+        None,
+        None,
+        app.state::<AgentDb>(),
+        app.state::<ProcessRegistryState>(),
+    )
+    .await
+}
+
+/// Load and start a watch by id
+pub fn start_file_watch_by_id(app: &AppHandle, watch_id: i64) -> Result<(), String> {
+    let config = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_watch(&conn, watch_id).map_err(|e| e.to_string())?
+    };
+    start_file_watch(app, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_matches_respects_pattern_and_ignored_dirs() {
+        let pattern = glob::Pattern::new("src/**/*.rs").unwrap();
+        assert!(path_matches(&pattern, Path::new("src/commands/agents.rs")));
+        assert!(!path_matches(&pattern, Path::new("README.md")));
+
+        let any = glob::Pattern::new("**/*").unwrap();
+        assert!(!path_matches(&any, Path::new(".git/index")));
+        assert!(!path_matches(
+            &any,
+            Path::new("web/node_modules/x/index.js")
+        ));
+    }
+
+    #[test]
+    fn test_build_triggered_task() {
+        let files = vec!["a.rs".to_string(), "b.rs".to_string()];
+        assert_eq!(
+            build_triggered_task("Review:\n{{changed_files}}", &files),
+            "Review:\n- a.rs\n- b.rs"
+        );
+        assert_eq!(
+            build_triggered_task("Review these", &files),
+            "Review these\n\nChanged files:\n- a.rs\n- b.rs"
+        );
+    }
+}
@@ -1,11 +1,23 @@
 use clap::Parser;
+// The web server never launches MCP servers, but the MCP commands it shares
+// with the app refer to the proxy, so it comes from the library
+use opcode_lib::mcp_proxy;
 
 mod checkpoint;
 mod claude_binary;
 mod commands;
+mod keychain;
+mod mcp_supervisor;
+mod mcp_ws_bridge;
 mod process;
+mod scheduler;
 mod shell_environment;
+mod stderr_rules;
+mod stream_json;
+mod transcript;
+mod watcher;
 mod web_server;
+mod webhook;
 
 #[derive(Parser)]
 #[command(name = "opcode-web")]
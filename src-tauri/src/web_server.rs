@@ -123,7 +123,7 @@ async fn get_projects() -> Json<ApiResponse<Vec<commands::claude::Project>>> {
 async fn get_sessions(
     Path(project_id): Path<String>,
 ) -> Json<ApiResponse<Vec<commands::claude::Session>>> {
-    match commands::claude::get_project_sessions(project_id).await {
+    match commands::claude::read_project_sessions(project_id).await {
         Ok(sessions) => Json(ApiResponse::success(sessions)),
         Err(e) => Json(ApiResponse::error(e.to_string())),
     }
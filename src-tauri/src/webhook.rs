@@ -0,0 +1,413 @@
+//! Webhook-triggered agent runs
+//!
+//! An opt-in HTTP listener bound to localhost that lets CI jobs or git hosting
+//! webhooks launch an agent on a project and poll the resulting run. Every
+//! request must carry the configured token, either as `Authorization: Bearer`
+//! or in an `X-Opcode-Token` header.
+//!
+//! - `POST /hooks/agents/run` with `{ "agent": "...", "project": "...", "task": "...", "params": {...}, "model": "..." }`
+//! - `GET /hooks/runs/{run_id}` to poll status
+
+use axum::{
+    extract::{Path, State as AxumState},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use log::{error, info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+use crate::commands::agents::{execute_agent, AgentDb};
+use crate::keychain;
+use crate::process::ProcessRegistryState;
+
+/// Port the listener binds to unless configured otherwise
+pub const DEFAULT_WEBHOOK_PORT: u16 = 8765;
+
+/// Keychain account holding the webhook token
+const KEYCHAIN_ACCOUNT: &str = "webhook-token";
+
+/// app_settings key the token was stored under before it moved to the keychain
+const LEGACY_TOKEN_KEY: &str = "webhook_token";
+
+/// Webhook listener configuration stored in app_settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Shared secret callers must present, masked when shown; generated on
+    /// first enable and kept in the keychain
+    pub token: Option<String>,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_WEBHOOK_PORT,
+            token: None,
+        }
+    }
+}
+
+/// Handle to the running listener so it can be stopped or restarted
+#[derive(Default)]
+pub struct WebhookState(pub Mutex<Option<oneshot::Sender<()>>>);
+
+/// Body of a run request
+#[derive(Debug, Deserialize)]
+pub struct WebhookRunRequest {
+    /// Agent name (case-insensitive)
+    pub agent: String,
+    /// Project path, or the name of a project directory known to Claude
+    pub project: String,
+    /// Task template; the agent's default task is used when omitted
+    pub task: Option<String>,
+    /// Values for the agent's `{{name}}` parameters
+    #[serde(default)]
+    pub params: HashMap<String, serde_json::Value>,
+    pub model: Option<String>,
+}
+
+/// Load the webhook settings from app_settings. The token is in the keychain
+/// and left out; read it with [`webhook_token`].
+pub fn load_webhook_settings(conn: &Connection) -> WebhookSettings {
+    let read = |key: &str| -> Option<String> {
+        conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+    };
+
+    let mut settings = WebhookSettings::default();
+    if let Some(value) = read("webhook_enabled") {
+        settings.enabled = value == "true";
+    }
+    if let Some(port) = read("webhook_port").and_then(|v| v.parse().ok()) {
+        settings.port = port;
+    }
+    settings
+}
+
+/// The token callers must present, None until webhooks are first enabled
+pub fn webhook_token() -> Result<Option<String>, String> {
+    keychain::get_secret(KEYCHAIN_ACCOUNT)
+        .map_err(|e| format!("Failed to read webhook token: {}", e))
+}
+
+/// Store a new webhook token in the keychain
+pub fn set_webhook_token(token: &str) -> Result<(), String> {
+    keychain::set_secret(KEYCHAIN_ACCOUNT, token)
+        .map_err(|e| format!("Failed to store webhook token in keychain: {}", e))
+}
+
+/// Remove the webhook token from the keychain
+pub fn delete_webhook_token() -> Result<(), String> {
+    keychain::delete_secret(KEYCHAIN_ACCOUNT)
+        .map_err(|e| format!("Failed to remove webhook token from keychain: {}", e))
+}
+
+/// Move a token stored in app_settings by earlier versions into the keychain
+fn migrate_legacy_token(db: &AgentDb) -> Result<(), String> {
+    let legacy = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![LEGACY_TOKEN_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+    };
+    let Some(legacy) = legacy else {
+        return Ok(());
+    };
+    if !legacy.is_empty() && webhook_token()?.is_none() {
+        set_webhook_token(&legacy)?;
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM app_settings WHERE key = ?1",
+        params![LEGACY_TOKEN_KEY],
+    )
+    .map_err(|e| format!("Failed to remove webhook token from settings: {}", e))?;
+    info!("Moved the webhook token to the keychain");
+    Ok(())
+}
+
+/// Compare tokens without bailing out at the first differing byte
+fn tokens_match(expected: &str, provided: &str) -> bool {
+    let (a, b) = (expected.as_bytes(), provided.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Extract the caller's token from the request headers
+fn request_token(headers: &HeaderMap) -> Option<String> {
+    if let Some(value) = headers.get("authorization").and_then(|v| v.to_str().ok()) {
+        if let Some(token) = value.strip_prefix("Bearer ") {
+            return Some(token.trim().to_string());
+        }
+    }
+    headers
+        .get("x-opcode-token")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(json!({ "error": message.into() }))).into_response()
+}
+
+/// Reject the request unless it carries the configured token
+fn authorize(headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let expected = webhook_token().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    match (expected, request_token(headers)) {
+        (Some(expected), Some(provided)) if tokens_match(&expected, &provided) => Ok(()),
+        _ => Err((
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid webhook token".to_string(),
+        )),
+    }
+}
+
+/// Resolve a project given as a path or as the name of a known project
+async fn resolve_project(project: &str) -> Result<String, String> {
+    if std::path::Path::new(project).is_dir() {
+        return Ok(project.to_string());
+    }
+
+    let projects = crate::commands::claude::list_projects().await?;
+    projects
+        .into_iter()
+        .map(|p| p.path)
+        .find(|path| {
+            std::path::Path::new(path)
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(project))
+        })
+        .ok_or_else(|| format!("Unknown project '{}'", project))
+}
+
+async fn run_agent(
+    AxumState(app): AxumState<AppHandle>,
+    headers: HeaderMap,
+    Json(request): Json<WebhookRunRequest>,
+) -> Response {
+    if let Err((status, message)) = authorize(&headers) {
+        return error_response(status, message);
+    }
+
+    let agent = {
+        let db = app.state::<AgentDb>();
+        let conn = match db.0.lock() {
+            Ok(conn) => conn,
+            Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        };
+        conn.query_row(
+            "SELECT id, default_task FROM agents WHERE name = ?1 COLLATE NOCASE ORDER BY id LIMIT 1",
+            params![request.agent],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?)),
+        )
+        .optional()
+    };
+    let (agent_id, default_task) = match agent {
+        Ok(Some(agent)) => agent,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("Unknown agent '{}'", request.agent),
+            )
+        }
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+
+    let project_path = match resolve_project(&request.project).await {
+        Ok(path) => path,
+        Err(e) => return error_response(StatusCode::NOT_FOUND, e),
+    };
+
+    let Some(task) = request.task.or(default_task) else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "No task given and the agent has no default task",
+        );
+    };
+
+    info!(
+        "Webhook launching agent {} on {}",
+        request.agent, project_path
+    );
+
+    match execute_agent(
+        app.clone(),
+        agent_id,
+        project_path,
+        task,
+        request.model,
+        Some(request.params),
+        None,
+        None,
+        app.state::<AgentDb>(),
+        app.state::<ProcessRegistryState>(),
+    )
+    .await
+    {
+        Ok(run_id) => (
+            StatusCode::ACCEPTED,
+            Json(json!({
+                "run_id": run_id,
+                "status_url": format!("/hooks/runs/{}", run_id),
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Webhook failed to launch agent {}: {}", request.agent, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, e)
+        }
+    }
+}
+
+async fn run_status(
+    AxumState(app): AxumState<AppHandle>,
+    headers: HeaderMap,
+    Path(run_id): Path<i64>,
+) -> Response {
+    if let Err((status, message)) = authorize(&headers) {
+        return error_response(status, message);
+    }
+
+    let db = app.state::<AgentDb>();
+    let conn = match db.0.lock() {
+        Ok(conn) => conn,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+
+    let run = conn
+        .query_row(
+            "SELECT agent_name, project_path, status, session_id, failure_reason, created_at, completed_at
+             FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| {
+                Ok(json!({
+                    "run_id": run_id,
+                    "agent": row.get::<_, String>(0)?,
+                    "project_path": row.get::<_, String>(1)?,
+                    "status": row.get::<_, String>(2)?,
+                    "session_id": row.get::<_, String>(3)?,
+                    "failure_reason": row.get::<_, Option<String>>(4)?,
+                    "created_at": row.get::<_, String>(5)?,
+                    "completed_at": row.get::<_, Option<String>>(6)?,
+                }))
+            },
+        )
+        .optional();
+
+    match run {
+        Ok(Some(run)) => Json(run).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, format!("Run {} not found", run_id)),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Stop the listener if it is running
+pub fn stop_webhook_listener(app: &AppHandle) {
+    let state = app.state::<WebhookState>();
+    let shutdown = state.0.lock().ok().and_then(|mut guard| guard.take());
+    if let Some(shutdown) = shutdown {
+        let _ = shutdown.send(());
+        info!("Webhook listener stopped");
+    }
+}
+
+/// (Re)start the listener according to the current settings
+pub async fn restart_webhook_listener(app: &AppHandle) -> Result<(), String> {
+    stop_webhook_listener(app);
+
+    let settings = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_webhook_settings(&conn)
+    };
+    if !settings.enabled {
+        return Ok(());
+    }
+    if webhook_token()?.is_none() {
+        return Err("A webhook token must be generated before enabling webhooks".to_string());
+    }
+
+    // Only listen on loopback; expose it further with a tunnel or reverse proxy if needed
+    let addr = SocketAddr::from(([127, 0, 0, 1], settings.port));
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Failed to bind webhook listener on {}: {}", addr, e))?;
+
+    let router = Router::new()
+        .route("/hooks/agents/run", post(run_agent))
+        .route("/hooks/runs/{run_id}", get(run_status))
+        .with_state(app.clone());
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    {
+        let state = app.state::<WebhookState>();
+        let mut guard = state.0.lock().map_err(|e| e.to_string())?;
+        *guard = Some(shutdown_tx);
+    }
+
+    info!("Webhook listener running on http://{}", addr);
+    tauri::async_runtime::spawn(async move {
+        let server = axum::serve(listener, router).with_graceful_shutdown(async {
+            let _ = shutdown_rx.await;
+        });
+        if let Err(e) = server.await {
+            error!("Webhook listener failed: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// Start the listener at startup if webhooks are enabled
+pub fn start_webhook_listener(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = migrate_legacy_token(&app.state::<AgentDb>()) {
+            warn!("Webhook token not moved to the keychain: {}", e);
+        }
+        if let Err(e) = restart_webhook_listener(&app).await {
+            warn!("Webhook listener not started: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_token_accepts_bearer_and_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer abc".parse().unwrap());
+        assert_eq!(request_token(&headers).as_deref(), Some("abc"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-opcode-token", "xyz".parse().unwrap());
+        assert_eq!(request_token(&headers).as_deref(), Some("xyz"));
+
+        assert!(tokens_match("abc", "abc"));
+        assert!(!tokens_match("abc", "abd"));
+        assert!(!tokens_match("abc", "abcd"));
+    }
+}
@@ -27,6 +27,7 @@ import { Tabs, TabsList, TabsTrigger, TabsContent } from "@/components/ui/tabs";
 import { api, type Agent } from "@/lib/api";
 import { cn } from "@/lib/utils";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { streamLines } from "@/lib/streamEvents";
 import { StreamMessage } from "./StreamMessage";
 import { ExecutionControlBar } from "./ExecutionControlBar";
 import { ErrorBoundary } from "./ErrorBoundary";
@@ -319,16 +320,18 @@ export const AgentExecution: React.FC<AgentExecutionProps> = ({
       agentFeatureTracking.trackUsage();
       
       // Set up event listeners with run ID isolation
-      const outputUnlisten = await listen<string>(`agent-output:${executionRunId}`, (event) => {
-        try {
-          // Store raw JSONL
-          setRawJsonlOutput(prev => [...prev, event.payload]);
-          
-          // Parse and display
-          const message = JSON.parse(event.payload) as ClaudeStreamMessage;
-          setMessages(prev => [...prev, message]);
-        } catch (err) {
-          console.error("Failed to parse message:", err, event.payload);
+      const outputUnlisten = await listen<string | string[]>(`agent-output:${executionRunId}`, (event) => {
+        for (const line of streamLines(event.payload)) {
+          try {
+            // Store raw JSONL
+            setRawJsonlOutput(prev => [...prev, line]);
+            
+            // Parse and display
+            const message = JSON.parse(line) as ClaudeStreamMessage;
+            setMessages(prev => [...prev, message]);
+          } catch (err) {
+            console.error("Failed to parse message:", err, line);
+          }
         }
       });
 
@@ -19,6 +19,7 @@ import { Badge } from '@/components/ui/badge';
 import { Toast, ToastContainer } from '@/components/ui/toast';
 import { Popover } from '@/components/ui/popover';
 import { api, type AgentRunWithMetrics } from '@/lib/api';
+import { issueLinksJsonl, issueLinksMarkdown, loadRunIssueLinks } from '@/lib/issueLinks';
 import { useOutputCache } from '@/lib/outputCache';
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { StreamMessage } from './StreamMessage';
@@ -279,22 +280,24 @@ export function AgentRunOutputViewer({
       }, 100);
 
       // Set up live event listeners with run ID isolation
-      const outputUnlisten = await listen<string>(`agent-output:${run!.id}`, (event) => {
-        try {
-          // Skip messages during initial load phase
-          if (isInitialLoadRef.current) {
-            console.log('[AgentRunOutputViewer] Skipping message during initial load');
-            return;
+      const outputUnlisten = await listen<string | string[]>(`agent-output:${run!.id}`, (event) => {
+        // Skip messages during initial load phase
+        if (isInitialLoadRef.current) {
+          console.log('[AgentRunOutputViewer] Skipping message during initial load');
+          return;
+        }
+
+        for (const line of streamLines(event.payload)) {
+          try {
+            // Store raw JSONL
+            setRawJsonlOutput(prev => [...prev, line]);
+            
+            // Parse and display
+            const message = JSON.parse(line) as ClaudeStreamMessage;
+            setMessages(prev => [...prev, message]);
+          } catch (err) {
+            console.error("[AgentRunOutputViewer] Failed to parse message:", err, line);
           }
-          
-          // Store raw JSONL
-          setRawJsonlOutput(prev => [...prev, event.payload]);
-          
-          // Parse and display
-          const message = JSON.parse(event.payload) as ClaudeStreamMessage;
-          setMessages(prev => [...prev, message]);
-        } catch (err) {
-          console.error("[AgentRunOutputViewer] Failed to parse message:", err, event.payload);
         }
       });
 
@@ -320,7 +323,8 @@ export function AgentRunOutputViewer({
 
   // Copy functionality
   const handleCopyAsJsonl = async () => {
-    const jsonl = rawJsonlOutput.join('\n');
+    const issueLinks = await loadRunIssueLinks(run?.id);
+    const jsonl = rawJsonlOutput.join('\n') + issueLinksJsonl(issueLinks);
     await navigator.clipboard.writeText(jsonl);
     setCopyPopoverOpen(false);
     setToast({ message: 'Output copied as JSONL', type: 'success' });
@@ -335,6 +339,7 @@ export function AgentRunOutputViewer({
     if (run.metrics?.duration_ms) markdown += `**Duration:** ${(run.metrics.duration_ms / 1000).toFixed(2)}s\n`;
     if (run.metrics?.total_tokens) markdown += `**Total Tokens:** ${run.metrics.total_tokens}\n`;
     if (run.metrics?.cost_usd) markdown += `**Cost:** $${run.metrics.cost_usd.toFixed(4)} USD\n`;
+    markdown += issueLinksMarkdown(await loadRunIssueLinks(run.id));
     markdown += `\n---\n\n`;
 
     for (const msg of messages) {
@@ -15,6 +15,7 @@ import { Badge } from "@/components/ui/badge";
 import { Card, CardContent } from "@/components/ui/card";
 import { Popover } from "@/components/ui/popover";
 import { api, type AgentRunWithMetrics } from "@/lib/api";
+import { issueLinksJsonl, issueLinksMarkdown, loadRunIssueLinks } from "@/lib/issueLinks";
 import { cn } from "@/lib/utils";
 import { formatISOTimestamp } from "@/lib/date-utils";
 import { StreamMessage } from "./StreamMessage";
@@ -109,7 +110,11 @@ export const AgentRunView: React.FC<AgentRunViewProps> = ({
 
   const handleCopyAsJsonl = async () => {
     if (!run?.output) return;
-    await navigator.clipboard.writeText(run.output);
+    const issueLinks = await loadRunIssueLinks(run.id);
+    const jsonl = issueLinks.length
+      ? run.output.replace(/\n+$/, "") + issueLinksJsonl(issueLinks)
+      : run.output;
+    await navigator.clipboard.writeText(jsonl);
     setCopyPopoverOpen(false);
   };
 
@@ -124,7 +129,9 @@ export const AgentRunView: React.FC<AgentRunViewProps> = ({
       markdown += `**Tokens:** ${run.metrics.total_tokens || 'N/A'}\n`;
       markdown += `**Cost:** $${run.metrics.cost_usd?.toFixed(4) || 'N/A'}\n`;
     }
-    markdown += `**Date:** ${new Date(run.created_at).toISOString()}\n\n`;
+    markdown += `**Date:** ${new Date(run.created_at).toISOString()}\n`;
+    markdown += issueLinksMarkdown(await loadRunIssueLinks(run.id));
+    markdown += `\n`;
     markdown += `---\n\n`;
 
     for (const msg of messages) {
@@ -60,6 +60,7 @@ import type { ClaudeStreamMessage } from "./AgentExecution";
 import { useVirtualizer } from "@tanstack/react-virtual";
 import { useTrackEvent, useComponentMetrics, useWorkflowTracking } from "@/hooks";
 import { SessionPersistenceService } from "@/services/sessionPersistence";
+import { streamLines } from "@/lib/streamEvents";
 
 interface ClaudeCodeSessionProps {
   /**
@@ -444,19 +445,21 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
     
     // Set up session-specific listeners
     const outputUnlisten = await listen(`claude-output:${sessionId}`, async (event: any) => {
-      try {
-        console.log('[ClaudeCodeSession] Received claude-output on reconnect:', event.payload);
-        
-        if (!isMountedRef.current) return;
-        
-        // Store raw JSONL
-        setRawJsonlOutput(prev => [...prev, event.payload]);
-        
-        // Parse and display
-        const message = JSON.parse(event.payload) as ClaudeStreamMessage;
-        setMessages(prev => [...prev, message]);
-      } catch (err) {
-        console.error("Failed to parse message:", err, event.payload);
+      console.log('[ClaudeCodeSession] Received claude-output on reconnect:', event.payload);
+
+      if (!isMountedRef.current) return;
+
+      for (const line of streamLines(event.payload)) {
+        try {
+          // Store raw JSONL
+          setRawJsonlOutput(prev => [...prev, line]);
+          
+          // Parse and display
+          const message = JSON.parse(line) as ClaudeStreamMessage;
+          setMessages(prev => [...prev, message]);
+        } catch (err) {
+          console.error("Failed to parse message:", err, line);
+        }
       }
     });
 
@@ -546,7 +549,7 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
           console.log('[ClaudeCodeSession] Attaching session-specific listeners for', sid);
 
           const specificOutputUnlisten = await listen(`claude-output:${sid}`, (evt: any) => {
-            handleStreamMessage(evt.payload);
+            streamLines(evt.payload).forEach(handleStreamMessage);
           });
 
           const specificErrorUnlisten = await listen(`claude-error:${sid}`, (evt: any) => {
@@ -566,37 +569,39 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
 
         // Generic listeners (catch-all)
         const genericOutputUnlisten = await listen('claude-output', async (event: any) => {
-          handleStreamMessage(event.payload);
+          for (const line of streamLines(event.payload)) {
+            handleStreamMessage(line);
 
-          // Attempt to extract session_id on the fly (for the very first init)
-          try {
-            const msg = JSON.parse(event.payload) as ClaudeStreamMessage;
-            if (msg.type === 'system' && msg.subtype === 'init' && msg.session_id) {
-              if (!currentSessionId || currentSessionId !== msg.session_id) {
-                console.log('[ClaudeCodeSession] Detected new session_id from generic listener:', msg.session_id);
-                currentSessionId = msg.session_id;
-                setClaudeSessionId(msg.session_id);
-
-                // If we haven't extracted session info before, do it now
-                if (!extractedSessionInfo) {
-                  const projectId = projectPath.replace(/[^a-zA-Z0-9]/g, '-');
-                  setExtractedSessionInfo({ sessionId: msg.session_id, projectId });
+            // Attempt to extract session_id on the fly (for the very first init)
+            try {
+              const msg = JSON.parse(line) as ClaudeStreamMessage;
+              if (msg.type === 'system' && msg.subtype === 'init' && msg.session_id) {
+                if (!currentSessionId || currentSessionId !== msg.session_id) {
+                  console.log('[ClaudeCodeSession] Detected new session_id from generic listener:', msg.session_id);
+                  currentSessionId = msg.session_id;
+                  setClaudeSessionId(msg.session_id);
+
+                  // If we haven't extracted session info before, do it now
+                  if (!extractedSessionInfo) {
+                    const projectId = projectPath.replace(/[^a-zA-Z0-9]/g, '-');
+                    setExtractedSessionInfo({ sessionId: msg.session_id, projectId });
                   
-                  // Save session data for restoration
-                  SessionPersistenceService.saveSession(
-                    msg.session_id,
-                    projectId,
-                    projectPath,
-                    messages.length
-                  );
-                }
+                    // Save session data for restoration
+                    SessionPersistenceService.saveSession(
+                      msg.session_id,
+                      projectId,
+                      projectPath,
+                      messages.length
+                    );
+                  }
 
-                // Switch to session-specific listeners
-                await attachSessionSpecificListeners(msg.session_id);
+                  // Switch to session-specific listeners
+                  await attachSessionSpecificListeners(msg.session_id);
+                }
               }
+            } catch {
+              /* ignore parse errors */
             }
-          } catch {
-            /* ignore parse errors */
           }
         });
 
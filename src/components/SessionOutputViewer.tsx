@@ -8,6 +8,7 @@ import { Toast, ToastContainer } from '@/components/ui/toast';
 import { Popover } from '@/components/ui/popover';
 import { api } from '@/lib/api';
 import { useOutputCache } from '@/lib/outputCache';
+import { streamLines } from '@/lib/streamEvents';
 import type { AgentRun } from '@/lib/api';
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { StreamMessage } from './StreamMessage';
@@ -202,16 +203,18 @@ export function SessionOutputViewer({ session, onClose, className }: SessionOutp
       unlistenRefs.current = [];
 
       // Set up live event listeners with run ID isolation
-      const outputUnlisten = await listen<string>(`agent-output:${session.id}`, (event) => {
-        try {
-          // Store raw JSONL
-          setRawJsonlOutput(prev => [...prev, event.payload]);
-          
-          // Parse and display
-          const message = JSON.parse(event.payload) as ClaudeStreamMessage;
-          setMessages(prev => [...prev, message]);
-        } catch (err) {
-          console.error("Failed to parse message:", err, event.payload);
+      const outputUnlisten = await listen<string | string[]>(`agent-output:${session.id}`, (event) => {
+        for (const line of streamLines(event.payload)) {
+          try {
+            // Store raw JSONL
+            setRawJsonlOutput(prev => [...prev, line]);
+            
+            // Parse and display
+            const message = JSON.parse(line) as ClaudeStreamMessage;
+            setMessages(prev => [...prev, message]);
+          } catch (err) {
+            console.error("Failed to parse message:", err, line);
+          }
         }
       });
 
@@ -60,6 +60,7 @@ import { Card, CardContent } from "@/components/ui/card";
 import { detectLinks, makeLinksClickable } from "@/lib/linkDetector";
 import ReactMarkdown from "react-markdown";
 import { open } from "@tauri-apps/plugin-shell";
+import { api } from "@/lib/api";
 import { Tabs, TabsContent, TabsList, TabsTrigger } from "@/components/ui/tabs";
 import { Input } from "@/components/ui/input";
 import { motion, AnimatePresence } from "framer-motion";
@@ -344,6 +345,19 @@ export const LSResultWidget: React.FC<{ content: string }> = ({ content }) => {
   );
 };
 
+/**
+ * File path that opens in the configured editor when clicked
+ */
+const FilePathLink: React.FC<{ path: string; line?: number; className?: string }> = ({ path, line, className }) => (
+  <code
+    className={cn("cursor-pointer hover:underline", className)}
+    title="Open in editor"
+    onClick={() => api.openInEditor(path, line).catch(() => {})}
+  >
+    {path}
+  </code>
+);
+
 /**
  * Widget for Read tool
  */
@@ -370,9 +384,7 @@ export const ReadWidget: React.FC<{ filePath: string; result?: any }> = ({ fileP
         <div className="flex items-center gap-2 p-3 rounded-lg bg-muted/50">
           <FileText className="h-4 w-4 text-primary" />
           <span className="text-sm">File content:</span>
-          <code className="text-sm font-mono bg-background px-2 py-0.5 rounded flex-1 truncate">
-            {filePath}
-          </code>
+          <FilePathLink path={filePath} className="text-sm font-mono bg-background px-2 py-0.5 rounded flex-1 truncate" />
         </div>
         {resultContent && <ReadResultWidget content={resultContent} filePath={filePath} />}
       </div>
@@ -383,9 +395,7 @@ export const ReadWidget: React.FC<{ filePath: string; result?: any }> = ({ fileP
     <div className="flex items-center gap-2 p-3 rounded-lg bg-muted/50">
       <FileText className="h-4 w-4 text-primary" />
       <span className="text-sm">Reading file:</span>
-      <code className="text-sm font-mono bg-background px-2 py-0.5 rounded flex-1 truncate">
-        {filePath}
-      </code>
+      <FilePathLink path={filePath} className="text-sm font-mono bg-background px-2 py-0.5 rounded flex-1 truncate" />
       {!result && (
         <div className="ml-auto flex items-center gap-1 text-xs text-muted-foreground">
           <div className="h-2 w-2 bg-blue-500 rounded-full animate-pulse" />
@@ -1140,9 +1150,7 @@ export const EditWidget: React.FC<{
       <div className="flex items-center gap-2 mb-2">
         <FileEdit className="h-4 w-4 text-primary" />
         <span className="text-sm font-medium">Applying Edit to:</span>
-        <code className="text-sm font-mono bg-background px-2 py-0.5 rounded flex-1 truncate">
-          {file_path}
-        </code>
+        <FilePathLink path={file_path} className="text-sm font-mono bg-background px-2 py-0.5 rounded flex-1 truncate" />
       </div>
 
       <div className="rounded-lg border bg-background overflow-hidden text-xs font-mono">
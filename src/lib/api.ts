@@ -15,6 +15,200 @@ export interface ProcessInfo {
   project_path: string;
   task: string;
   model: string;
+  /** When the process was paused, null while it runs */
+  paused_at: string | null;
+}
+
+export interface ManagedProcess extends ProcessInfo {
+  uptime_secs: number;
+  alive: boolean;
+  memory_bytes: number;
+  cpu_percent: number;
+}
+
+export interface ChildProcess {
+  pid: number;
+  parent_pid: number;
+  name: string;
+  command: string[];
+  memory_bytes: number;
+  cpu_percent: number;
+}
+
+export interface ProcessDetail extends ManagedProcess {
+  command: string[];
+  cwd: string | null;
+  status: string | null;
+  children: ChildProcess[];
+  recent_output: string[];
+}
+
+/** A session or agent run that has written no output for a while, sent as `run-stalled` */
+export interface StalledRun {
+  run_id: number;
+  process_type: ProcessType;
+  pid: number;
+  project_path: string;
+  last_output_at: string;
+  idle_secs: number;
+}
+
+export interface StallDetectionSettings {
+  stall_after_minutes: number;
+}
+
+/** How a cancelled session or run was stopped, sent as `agent-cancellation:{runId}` and `claude-cancellation:{sessionId}` */
+export type CancelOutcome = "interrupted" | "force_killed" | "not_running";
+
+/** A line of Claude output that isn't a stream message, sent as `agent-stream-error:{runId}` and `claude-stream-error:{sessionId}` */
+export interface StreamParseError {
+  line: string;
+  error: string;
+}
+
+/** A classified line of Claude stderr, sent as `agent-stderr:{runId}`, `claude-stderr:{sessionId}` and `tab-stderr:{tabId}` */
+export interface StderrLine {
+  line: string;
+  severity: "info" | "warning" | "error" | "fatal";
+  category:
+    | "node_warning"
+    | "diagnostic"
+    | "stack_trace"
+    | "auth"
+    | "rate_limit"
+    | "network"
+    | "crash"
+    | "other";
+}
+
+export interface CancellationSettings {
+  grace_period_secs: number;
+}
+
+/** A stored output log, named `agent-run-{runId}`, `session-{sessionId}` or `session-pid-{pid}` */
+export interface RunOutputLogInfo {
+  name: string;
+  path: string;
+  size_bytes: number;
+  modified_at: string;
+}
+
+export interface StreamBatchingSettings {
+  flush_interval_ms: number;
+  max_batch_size: number;
+  max_message_bytes: number;
+}
+
+/** What of a session's stream output is forwarded to the UI */
+export interface StreamFilter {
+  /** Tool results larger than this are replaced by a note of their size; 0 forwards them whole */
+  max_tool_result_bytes: number;
+  /** Forward only assistant text and the names of the tools called */
+  only_text_and_tool_names: boolean;
+}
+
+/** Which editor files are opened in */
+export interface EditorSettings {
+  preset: "vscode" | "jetbrains" | "vim" | "custom";
+  /** Command line of the custom editor, with {{path}}, {{line}} and {{column}} placeholders */
+  command_template?: string | null;
+}
+
+/** Issue tracker a session or run can be linked to */
+export type IssueTracker = "jira" | "linear" | "github";
+
+/** An issue linked to a session or agent run */
+export interface IssueLink {
+  id: number;
+  target: "session" | "run";
+  /** Session ID, or the run ID as text */
+  target_id: string;
+  tracker: IssueTracker;
+  /** PROJ-123, ENG-42 or owner/repo#7 */
+  issue_key: string;
+  url: string;
+  /** Comment on the issue when the run finishes */
+  post_comment: boolean;
+  commented_at: string | null;
+  created_at: string;
+}
+
+/** Where bare issue keys point and how Jira is signed in to */
+export interface IssueTrackerSettings {
+  /** Jira site that keys like PROJ-123 belong to */
+  jira_base_url?: string | null;
+  /** Account the Jira Cloud API token belongs to */
+  jira_email?: string | null;
+  /** Linear workspace keys belong to when no Jira site is set */
+  linear_workspace?: string | null;
+}
+
+/** An agent installed as a git hook in a project */
+export interface InstalledGitHook {
+  hook: "pre_commit" | "pre_push";
+  agent_id: number;
+  path: string;
+}
+
+/** A Slack, Discord or webhook target that finished runs are reported to */
+export interface NotificationTarget {
+  /** Omitted when creating a channel */
+  id?: number;
+  name: string;
+  kind: "webhook" | "slack" | "discord";
+  /** Kept in the keychain and returned masked; saving the masked URL back keeps the stored one */
+  url: string;
+  /** JSON body with {{placeholders}}; Slack and Discord channels get a default message without one */
+  payload_template?: string | null;
+  events: ("success" | "failure" | "needs_input")[];
+  /** Report runs of every agent, not only those linked to the channel */
+  global: boolean;
+  enabled: boolean;
+}
+
+/** Which desktop notifications are shown; the run kinds cover runs without a notification rule and are off by default */
+export interface DesktopNotificationSettings {
+  session_finished: boolean;
+  session_failed: boolean;
+  run_succeeded: boolean;
+  run_failed: boolean;
+  run_needs_input: boolean;
+  /** Stay quiet while the app window has focus; runs with a notification rule of their own always notify */
+  only_when_unfocused: boolean;
+}
+
+/** Something the user can do from a notification; sent on `notification-action` for navigation */
+export type NotificationAction =
+  | { type: "open_session"; session_id: string; project_path: string }
+  | { type: "open_run"; run_id: number }
+  | { type: "view_diff"; run_id: number }
+  | { type: "approve"; run_id: number; tools: string[] };
+
+/** A notification and the actions it offers; sent on `notification-posted` */
+export interface ActionableNotification {
+  id: string;
+  kind: "session_finished" | "session_failed" | "run_succeeded" | "run_failed" | "run_needs_input";
+  title: string;
+  body: string;
+  actions: NotificationAction[];
+  created_at: string;
+}
+
+/** Whether agent runs are spawned on a pseudo-terminal, and the size it starts with */
+export interface PtyModeSettings {
+  enabled: boolean;
+  cols: number;
+  rows: number;
+}
+
+/**
+ * Limits shared by every Claude process, sessions and agent runs alike
+ */
+export interface SchedulerSettings {
+  /** 0 means unlimited */
+  max_claude_processes: number;
+  /** Serialize agent runs by git repository rather than by project directory */
+  serialize_repositories: boolean;
 }
 
 /**
@@ -176,6 +370,9 @@ export interface AgentExport {
   };
 }
 
+/** What to do when an imported agent's name is already taken; defaults to "rename" */
+export type ImportConflictStrategy = "rename" | "overwrite" | "skip";
+
 export interface GitHubAgentFile {
   name: string;
   path: string;
@@ -200,6 +397,14 @@ export interface AgentRun {
   completed_at?: string;
 }
 
+/** A pull request opened from an agent run's changes */
+export interface RunPullRequest {
+  run_id: number;
+  branch: string;
+  base: string;
+  url: string;
+}
+
 export interface AgentRunMetrics {
   duration_ms?: number;
   total_tokens?: number;
@@ -254,14 +459,124 @@ export interface DailyUsage {
   date: string;
   total_cost: number;
   total_tokens: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_tokens: number;
+  cache_read_tokens: number;
   models_used: string[];
 }
 
+export interface AgentCost {
+  agent_id: number;
+  agent_name: string;
+  agent_icon: string;
+  run_count: number;
+  total_cost: number;
+  average_cost: number;
+  total_tokens: number;
+}
+
+export interface ModelComparison {
+  model: string;
+  total_cost: number;
+  total_tokens: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_tokens: number;
+  cache_read_tokens: number;
+  session_count: number;
+  average_turns: number;
+  average_session_cost: number;
+  run_count: number;
+  completed_runs: number;
+  failed_runs: number;
+  success_rate: number | null;
+}
+
+/** Activity of one day, for a contributions-style heatmap */
+export interface ActivityDay {
+  /** YYYY-MM-DD */
+  date: string;
+  /** Sessions whose first message was on this day */
+  sessions_started: number;
+  agent_runs: number;
+  cost: number;
+}
+
+export interface CustomModelPricing {
+  model: string;
+  input: number;
+  output: number;
+  cache_write_5m?: number | null;
+  cache_write_1h?: number | null;
+  cache_read?: number | null;
+}
+
+export interface PricingSettings {
+  models: CustomModelPricing[];
+  currency: string;
+  usd_rate: number;
+}
+
+export interface UsageRetentionSettings {
+  detail_months: number | null;
+}
+
+export interface MonthlyUsage {
+  month: string;
+  project_path: string;
+  model: string;
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_tokens: number;
+  cache_read_tokens: number;
+  cost: number;
+  message_count: number;
+  session_count: number;
+}
+
+export interface UsagePurgeReport {
+  cutoff: string;
+  rolled_up_entries: number;
+  bytes_freed: number;
+}
+
+export interface CostAnomaly {
+  id: number;
+  kind: "session" | "agent_run";
+  subject_id: string;
+  project_path: string;
+  cost_usd: number;
+  median_cost_usd: number;
+  ratio: number;
+  reason: string;
+  detected_at: string;
+  dismissed_at: string | null;
+}
+
+export interface UsageImportSummary {
+  machine: string;
+  imported: number;
+  duplicates: number;
+}
+
+export interface UsageImport {
+  machine: string;
+  entry_count: number;
+  total_cost: number;
+  first_date: string;
+  last_date: string;
+}
+
 export interface ProjectUsage {
   project_path: string;
   project_name: string;
   total_cost: number;
   total_tokens: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_tokens: number;
+  cache_read_tokens: number;
   session_count: number;
   last_used: string;
 }
@@ -408,6 +723,10 @@ export interface ServerStatus {
   error?: string;
   /** Last checked timestamp */
   last_checked?: number;
+  /** Times the server crashed since its session started */
+  crashes?: number;
+  /** Times the server was restarted after a crash */
+  restarts?: number;
 }
 
 /**
@@ -448,6 +767,12 @@ export interface SlashCommand {
   description?: string;
   /** Allowed tools from frontmatter */
   allowed_tools: string[];
+  /** Hint for the arguments, shown when completing the command */
+  argument_hint?: string;
+  /** Model the command runs with instead of the session's */
+  model?: string;
+  /** What is wrong with the command's frontmatter */
+  problems: string[];
   /** Whether the command has bash commands (!) */
   has_bash_commands: boolean;
   /** Whether the command has file references (@) */
@@ -456,6 +781,14 @@ export interface SlashCommand {
   accepts_arguments: boolean;
 }
 
+/**
+ * A slash command as resolved in a project, with the same-named commands it hides
+ */
+export interface EffectiveSlashCommand extends SlashCommand {
+  /** IDs of the lower-precedence commands with the same name */
+  shadows: string[];
+}
+
 /**
  * Result of adding a server
  */
@@ -795,208 +1128,947 @@ export const api = {
         hooks
       });
     } catch (error) {
-      console.error("Failed to update agent:", error);
+      console.error("Failed to update agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Deletes an agent
+   * @param id - The agent ID to delete
+   * @returns Promise resolving when the agent is deleted
+   */
+  async deleteAgent(id: number): Promise<void> {
+    try {
+      return await apiCall('delete_agent', { id });
+    } catch (error) {
+      console.error("Failed to delete agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets a single agent by ID
+   * @param id - The agent ID
+   * @returns Promise resolving to the agent
+   */
+  async getAgent(id: number): Promise<Agent> {
+    try {
+      return await apiCall<Agent>('get_agent', { id });
+    } catch (error) {
+      console.error("Failed to get agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Exports a single agent to JSON format
+   * @param id - The agent ID to export
+   * @returns Promise resolving to the JSON string
+   */
+  async exportAgent(id: number): Promise<string> {
+    try {
+      return await apiCall<string>('export_agent', { id });
+    } catch (error) {
+      console.error("Failed to export agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Imports an agent from JSON data
+   * @param jsonData - The JSON string containing the agent export
+   * @param onConflict - What to do when an agent with the same name exists
+   * @returns Promise resolving to the imported agent
+   */
+  async importAgent(jsonData: string, onConflict?: ImportConflictStrategy): Promise<Agent> {
+    try {
+      return await apiCall<Agent>('import_agent', { jsonData, onConflict });
+    } catch (error) {
+      console.error("Failed to import agent:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Imports an agent from a file
+   * @param filePath - The path to the JSON file
+   * @param onConflict - What to do when an agent with the same name exists
+   * @returns Promise resolving to the imported agent
+   */
+  async importAgentFromFile(
+    filePath: string,
+    onConflict?: ImportConflictStrategy
+  ): Promise<Agent> {
+    try {
+      return await apiCall<Agent>('import_agent_from_file', { filePath, onConflict });
+    } catch (error) {
+      console.error("Failed to import agent from file:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Executes an agent
+   * @param agentId - The agent ID to execute
+   * @param projectPath - The project path to run the agent in
+   * @param task - The task description
+   * @param model - Optional model override
+   * @param background - Start the run at reduced CPU and I/O priority
+   * @returns Promise resolving to the run ID when execution starts
+   */
+  async executeAgent(agentId: number, projectPath: string, task: string, model?: string, background?: boolean): Promise<number> {
+    try {
+      return await apiCall<number>('execute_agent', { agentId, projectPath, task, model, background });
+    } catch (error) {
+      console.error("Failed to execute agent:", error);
+      // Return a sentinel value to indicate error
+      throw new Error(`Failed to execute agent: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Lists agent runs without metrics (basic info only)
+   * @param agentId - Optional agent ID to filter runs
+   * @returns Promise resolving to an array of agent runs
+   */
+  async listAgentRuns(agentId?: number): Promise<AgentRunWithMetrics[]> {
+    try {
+      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs', { agentId });
+    } catch (error) {
+      console.error("Failed to list agent runs:", error);
+      // Return empty array instead of throwing to prevent UI crashes
+      return [];
+    }
+  },
+
+  /**
+   * Lists agent runs with metrics (includes token counts and duration)
+   * @param agentId - Optional agent ID to filter runs
+   * @returns Promise resolving to an array of agent runs with metrics
+   */
+  async listAgentRunsWithMetrics(agentId?: number): Promise<AgentRunWithMetrics[]> {
+    try {
+      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs_with_metrics', { agentId });
+    } catch (error) {
+      console.error("Failed to list agent runs with metrics:", error);
+      // Return empty array instead of throwing to prevent UI crashes
+      return [];
+    }
+  },
+
+  /**
+   * Gets a single agent run by ID with metrics
+   * @param id - The run ID
+   * @returns Promise resolving to the agent run with metrics
+   */
+  async getAgentRun(id: number): Promise<AgentRunWithMetrics> {
+    try {
+      return await apiCall<AgentRunWithMetrics>('get_agent_run', { id });
+    } catch (error) {
+      console.error("Failed to get agent run:", error);
+      throw new Error(`Failed to get agent run: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Gets a single agent run by ID with real-time metrics from JSONL
+   * @param id - The run ID
+   * @returns Promise resolving to the agent run with metrics
+   */
+  async getAgentRunWithRealTimeMetrics(id: number): Promise<AgentRunWithMetrics> {
+    try {
+      return await apiCall<AgentRunWithMetrics>('get_agent_run_with_real_time_metrics', { id });
+    } catch (error) {
+      console.error("Failed to get agent run with real-time metrics:", error);
+      throw new Error(`Failed to get agent run with real-time metrics: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Lists all currently running agent sessions
+   * @returns Promise resolving to list of running agent sessions
+   */
+  async listRunningAgentSessions(): Promise<AgentRun[]> {
+    try {
+      return await apiCall<AgentRun[]>('list_running_sessions');
+    } catch (error) {
+      console.error("Failed to list running agent sessions:", error);
+      throw new Error(`Failed to list running agent sessions: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Kills a running agent session
+   * @param runId - The run ID to kill
+   * @returns Promise resolving to whether the session was successfully killed
+   */
+  async killAgentSession(runId: number): Promise<boolean> {
+    try {
+      return await apiCall<boolean>('kill_agent_session', { runId });
+    } catch (error) {
+      console.error("Failed to kill agent session:", error);
+      throw new Error(`Failed to kill agent session: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Commits a completed run's changes to a new branch, pushes it and opens a pull request
+   * @param runId - The run ID
+   * @param base - Branch to open the pull request against; defaults to the project's current branch
+   * @param title - Title of the pull request; defaults to one generated from the run's task
+   * @param draft - Whether to open it as a draft
+   * @returns Promise resolving to the opened pull request
+   */
+  async createRunPullRequest(runId: number, base?: string, title?: string, draft?: boolean): Promise<RunPullRequest> {
+    try {
+      return await apiCall<RunPullRequest>("create_run_pull_request", { runId, base, title, draft });
+    } catch (error) {
+      console.error("Failed to create pull request:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets whether a GitHub token for opening pull requests is stored in the keychain
+   * @returns Promise resolving to whether a token is set
+   */
+  async hasGithubToken(): Promise<boolean> {
+    try {
+      return await apiCall<boolean>("has_github_token");
+    } catch (error) {
+      console.error("Failed to check GitHub token:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Stores the GitHub token used to open pull requests; null removes it and falls back to the gh CLI
+   * @param token - The GitHub token
+   */
+  async setGithubToken(token: string | null): Promise<void> {
+    try {
+      return await apiCall("set_github_token", { token });
+    } catch (error) {
+      console.error("Failed to save GitHub token:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Links an issue to a session or agent run
+   * @param target - What the issue is linked to
+   * @param targetId - The session ID, or the run ID as text
+   * @param issue - An issue URL, owner/repo#123 reference or bare key like PROJ-123
+   * @param postComment - Whether a run comments on the issue when it finishes
+   * @returns Promise resolving to the link
+   */
+  async linkIssue(
+    target: "session" | "run",
+    targetId: string,
+    issue: string,
+    postComment: boolean
+  ): Promise<IssueLink> {
+    try {
+      return await apiCall<IssueLink>("link_issue", { target, targetId, issue, postComment });
+    } catch (error) {
+      console.error("Failed to link issue:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists the issues linked to a session or agent run
+   * @param target - What the issues are linked to
+   * @param targetId - The session ID, or the run ID as text
+   * @returns Promise resolving to the links
+   */
+  async listIssueLinks(target: "session" | "run", targetId: string): Promise<IssueLink[]> {
+    try {
+      return await apiCall<IssueLink[]>("list_issue_links", { target, targetId });
+    } catch (error) {
+      console.error("Failed to list issue links:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Removes an issue link
+   * @param linkId - The link ID
+   */
+  async unlinkIssue(linkId: number): Promise<void> {
+    try {
+      return await apiCall("unlink_issue", { linkId });
+    } catch (error) {
+      console.error("Failed to remove issue link:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Comments on a linked issue now with a summary of the session or run and its changes
+   * @param linkId - The link ID
+   * @returns Promise resolving to the updated link
+   */
+  async postIssueComment(linkId: number): Promise<IssueLink> {
+    try {
+      return await apiCall<IssueLink>("post_issue_comment", { linkId });
+    } catch (error) {
+      console.error("Failed to comment on issue:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets where bare issue keys point and how Jira is signed in to
+   * @returns Promise resolving to the issue tracker settings
+   */
+  async getIssueTrackerSettings(): Promise<IssueTrackerSettings> {
+    try {
+      return await apiCall<IssueTrackerSettings>("get_issue_tracker_settings");
+    } catch (error) {
+      console.error("Failed to get issue tracker settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves where bare issue keys point and how Jira is signed in to
+   * @param settings - The issue tracker settings
+   */
+  async saveIssueTrackerSettings(settings: IssueTrackerSettings): Promise<void> {
+    try {
+      return await apiCall("save_issue_tracker_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save issue tracker settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Checks whether an API token for commenting on a tracker's issues is stored in the keychain
+   * @param tracker - The issue tracker
+   * @returns Promise resolving to whether a token is stored
+   */
+  async hasIssueTrackerToken(tracker: IssueTracker): Promise<boolean> {
+    try {
+      return await apiCall<boolean>("has_issue_tracker_token", { tracker });
+    } catch (error) {
+      console.error("Failed to check issue tracker token:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Stores the API token used to comment on a tracker's issues in the keychain
+   * @param tracker - The issue tracker
+   * @param token - The token, or null to remove it
+   */
+  async setIssueTrackerToken(tracker: IssueTracker, token: string | null): Promise<void> {
+    try {
+      return await apiCall("set_issue_tracker_token", { tracker, token });
+    } catch (error) {
+      console.error("Failed to save issue tracker token:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Installs a git hook in a project that runs an agent and stops the commit or push when it fails
+   * @param projectPath - The project path
+   * @param hook - Which hook to install
+   * @param agentId - The agent to run
+   * @param task - Task for the agent; defaults to the agent's default task
+   * @returns Promise resolving to the installed hook
+   */
+  async installGitHook(projectPath: string, hook: InstalledGitHook["hook"], agentId: number, task?: string): Promise<InstalledGitHook> {
+    try {
+      return await apiCall<InstalledGitHook>("install_git_hook", { projectPath, hook, agentId, task });
+    } catch (error) {
+      console.error("Failed to install git hook:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Removes opcode's agent from a project's git hook
+   * @param projectPath - The project path
+   * @param hook - Which hook to remove it from
+   */
+  async uninstallGitHook(projectPath: string, hook: InstalledGitHook["hook"]): Promise<void> {
+    try {
+      return await apiCall("uninstall_git_hook", { projectPath, hook });
+    } catch (error) {
+      console.error("Failed to uninstall git hook:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists the agents installed as git hooks in a project
+   * @param projectPath - The project path
+   * @returns Promise resolving to the installed hooks
+   */
+  async listGitHooks(projectPath: string): Promise<InstalledGitHook[]> {
+    try {
+      return await apiCall<InstalledGitHook[]>("list_git_hooks", { projectPath });
+    } catch (error) {
+      console.error("Failed to list git hooks:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Opens a file in the configured editor, e.g. from a path in a tool result or diff
+   * @param path - The file path; relative paths are taken from the project
+   * @param line - Optional line to jump to
+   * @param column - Optional column to jump to
+   * @param projectPath - Optional project that relative paths belong to
+   */
+  async openInEditor(path: string, line?: number, column?: number, projectPath?: string): Promise<void> {
+    try {
+      return await apiCall("open_in_editor", { path, line, column, projectPath });
+    } catch (error) {
+      console.error("Failed to open file in editor:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the editor files are opened in
+   * @returns Promise resolving to the editor settings
+   */
+  async getEditorSettings(): Promise<EditorSettings> {
+    try {
+      return await apiCall<EditorSettings>("get_editor_settings");
+    } catch (error) {
+      console.error("Failed to get editor settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the editor files are opened in
+   * @param settings - The editor settings
+   */
+  async saveEditorSettings(settings: EditorSettings): Promise<void> {
+    try {
+      return await apiCall("save_editor_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save editor settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets how long cancelled sessions and runs get to finish after being interrupted
+   * @returns Promise resolving to the cancellation settings
+   */
+  async getCancellationSettings(): Promise<CancellationSettings> {
+    try {
+      return await apiCall<CancellationSettings>("get_cancellation_settings");
+    } catch (error) {
+      console.error("Failed to get cancellation settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves how long cancelled sessions and runs get to finish after being interrupted
+   * @param settings - The cancellation settings
+   */
+  async setCancellationSettings(settings: CancellationSettings): Promise<void> {
+    try {
+      return await apiCall("set_cancellation_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save cancellation settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets how stream output is batched and truncated before it reaches the UI
+   * @returns Promise resolving to the stream batching settings
+   */
+  async getStreamBatchingSettings(): Promise<StreamBatchingSettings> {
+    try {
+      return await apiCall<StreamBatchingSettings>("get_stream_batching_settings");
+    } catch (error) {
+      console.error("Failed to get stream batching settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves how stream output is batched and truncated before it reaches the UI
+   * @param settings - The stream batching settings
+   */
+  async setStreamBatchingSettings(settings: StreamBatchingSettings): Promise<void> {
+    try {
+      return await apiCall("set_stream_batching_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save stream batching settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the stream filter of a session, or the default one if it has none
+   * @param sessionId - The session ID
+   * @returns Promise resolving to the session's stream filter
+   */
+  async getSessionStreamFilter(sessionId: string): Promise<StreamFilter> {
+    try {
+      return await apiCall<StreamFilter>("get_session_stream_filter", { sessionId });
+    } catch (error) {
+      console.error("Failed to get session stream filter:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets the stream filter of a session; null goes back to the default one
+   * @param sessionId - The session ID
+   * @param filter - The stream filter
+   */
+  async setSessionStreamFilter(sessionId: string, filter: StreamFilter | null): Promise<void> {
+    try {
+      return await apiCall("set_session_stream_filter", { sessionId, filter });
+    } catch (error) {
+      console.error("Failed to save session stream filter:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the stream filter of sessions without one of their own
+   * @returns Promise resolving to the default stream filter
+   */
+  async getDefaultStreamFilter(): Promise<StreamFilter> {
+    try {
+      return await apiCall<StreamFilter>("get_default_stream_filter");
+    } catch (error) {
+      console.error("Failed to get default stream filter:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets the stream filter of sessions without one of their own
+   * @param filter - The stream filter
+   */
+  async setDefaultStreamFilter(filter: StreamFilter): Promise<void> {
+    try {
+      return await apiCall("set_default_stream_filter", { filter });
+    } catch (error) {
+      console.error("Failed to save default stream filter:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets which desktop notifications are shown
+   * @returns Promise resolving to the notification settings
+   */
+  async getNotificationSettings(): Promise<DesktopNotificationSettings> {
+    try {
+      return await apiCall<DesktopNotificationSettings>("get_notification_settings");
+    } catch (error) {
+      console.error("Failed to get notification settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets which desktop notifications are shown
+   * @param settings - The notification settings
+   */
+  async saveNotificationSettings(settings: DesktopNotificationSettings): Promise<void> {
+    try {
+      return await apiCall("save_notification_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save notification settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists notifications whose actions are still on offer, oldest first
+   * @returns Promise resolving to the pending notifications
+   */
+  async listPendingNotifications(): Promise<ActionableNotification[]> {
+    try {
+      return await apiCall<ActionableNotification[]>("list_pending_notifications");
+    } catch (error) {
+      console.error("Failed to list notifications:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Takes one of a notification's actions; approving resumes the run, anything else
+   * comes back as a `notification-action` event
+   * @param notificationId - The notification ID
+   * @param actionIndex - Index of the action in the notification's actions
+   */
+  async performNotificationAction(notificationId: string, actionIndex: number): Promise<void> {
+    try {
+      return await apiCall("perform_notification_action", { notificationId, actionIndex });
+    } catch (error) {
+      console.error("Failed to perform notification action:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Forgets a notification without taking any of its actions
+   * @param notificationId - The notification ID
+   */
+  async dismissNotification(notificationId: string): Promise<void> {
+    try {
+      return await apiCall("dismiss_notification", { notificationId });
+    } catch (error) {
+      console.error("Failed to dismiss notification:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists every notification channel
+   * @returns Promise resolving to the channels
+   */
+  async listNotificationChannels(): Promise<NotificationTarget[]> {
+    try {
+      return await apiCall<NotificationTarget[]>("list_notification_channels");
+    } catch (error) {
+      console.error("Failed to list notification channels:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Creates a notification channel, or updates it when it has an ID
+   * @param channel - The channel
+   * @returns Promise resolving to the saved channel
+   */
+  async saveNotificationChannel(channel: NotificationTarget): Promise<NotificationTarget> {
+    try {
+      return await apiCall<NotificationTarget>("save_notification_channel", { channel });
+    } catch (error) {
+      console.error("Failed to save notification channel:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Deletes a notification channel
+   * @param channelId - The channel ID
+   */
+  async deleteNotificationChannel(channelId: number): Promise<void> {
+    try {
+      return await apiCall("delete_notification_channel", { channelId });
+    } catch (error) {
+      console.error("Failed to delete notification channel:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Posts a sample message to a channel
+   * @param channel - The channel to test
+   */
+  async testNotificationChannel(channel: NotificationTarget): Promise<void> {
+    try {
+      return await apiCall("test_notification_channel", { channel });
+    } catch (error) {
+      console.error("Failed to test notification channel:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the channels an agent's runs are reported to, besides global ones
+   * @param agentId - The agent ID
+   * @returns Promise resolving to the channel IDs
+   */
+  async getAgentNotificationChannels(agentId: number): Promise<number[]> {
+    try {
+      return await apiCall<number[]>("get_agent_notification_channels", { agentId });
+    } catch (error) {
+      console.error("Failed to get agent notification channels:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets the channels an agent's runs are reported to, besides global ones
+   * @param agentId - The agent ID
+   * @param channelIds - The channel IDs
+   */
+  async setAgentNotificationChannels(agentId: number, channelIds: number[]): Promise<void> {
+    try {
+      return await apiCall("set_agent_notification_channels", { agentId, channelIds });
+    } catch (error) {
+      console.error("Failed to save agent notification channels:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets whether agent runs are spawned on a pseudo-terminal
+   * @returns Promise resolving to the PTY mode settings
+   */
+  async getPtyModeSettings(): Promise<PtyModeSettings> {
+    try {
+      return await apiCall<PtyModeSettings>("get_pty_mode_settings");
+    } catch (error) {
+      console.error("Failed to get PTY mode settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves whether agent runs are spawned on a pseudo-terminal
+   * @param settings - The PTY mode settings
+   */
+  async setPtyModeSettings(settings: PtyModeSettings): Promise<void> {
+    try {
+      return await apiCall("set_pty_mode_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save PTY mode settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Passes the UI's terminal size on to an agent run on a pseudo-terminal
+   * @param runId - The run ID
+   * @param cols - Terminal width in columns
+   * @param rows - Terminal height in rows
+   */
+  async resizeRunTerminal(runId: number, cols: number, rows: number): Promise<void> {
+    try {
+      return await apiCall("resize_run_terminal", { runId, cols, rows });
+    } catch (error) {
+      console.error("Failed to resize run terminal:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the limits shared by all Claude processes
+   * @returns Promise resolving to the scheduler settings
+   */
+  async getSchedulerSettings(): Promise<SchedulerSettings> {
+    try {
+      return await apiCall<SchedulerSettings>("get_scheduler_settings");
+    } catch (error) {
+      console.error("Failed to get scheduler settings:", error);
       throw error;
     }
   },
 
   /**
-   * Deletes an agent
-   * @param id - The agent ID to delete
-   * @returns Promise resolving when the agent is deleted
+   * Saves the limits shared by all Claude processes
+   * @param settings - The scheduler settings
    */
-  async deleteAgent(id: number): Promise<void> {
+  async saveSchedulerSettings(settings: SchedulerSettings): Promise<void> {
     try {
-      return await apiCall('delete_agent', { id });
+      return await apiCall("save_scheduler_settings", { settings });
     } catch (error) {
-      console.error("Failed to delete agent:", error);
+      console.error("Failed to save scheduler settings:", error);
       throw error;
     }
   },
 
   /**
-   * Gets a single agent by ID
-   * @param id - The agent ID
-   * @returns Promise resolving to the agent
+   * Gets the status of a specific agent session
+   * @param runId - The run ID to check
+   * @returns Promise resolving to the session status or null if not found
    */
-  async getAgent(id: number): Promise<Agent> {
+  async getSessionStatus(runId: number): Promise<string | null> {
     try {
-      return await apiCall<Agent>('get_agent', { id });
+      return await apiCall<string | null>('get_session_status', { runId });
     } catch (error) {
-      console.error("Failed to get agent:", error);
-      throw error;
+      console.error("Failed to get session status:", error);
+      throw new Error(`Failed to get session status: ${error instanceof Error ? error.message : 'Unknown error'}`);
     }
   },
 
   /**
-   * Exports a single agent to JSON format
-   * @param id - The agent ID to export
-   * @returns Promise resolving to the JSON string
+   * Cleanup finished processes and update their status
+   * @returns Promise resolving to list of run IDs that were cleaned up
    */
-  async exportAgent(id: number): Promise<string> {
+  async cleanupFinishedProcesses(): Promise<number[]> {
     try {
-      return await apiCall<string>('export_agent', { id });
+      return await apiCall<number[]>('cleanup_finished_processes');
     } catch (error) {
-      console.error("Failed to export agent:", error);
+      console.error("Failed to cleanup finished processes:", error);
+      throw new Error(`Failed to cleanup finished processes: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
+  /**
+   * Lists every Claude session and agent run spawned by opcode with its resource usage
+   * @returns Promise resolving to the processes, longest running first
+   */
+  async listManagedProcesses(): Promise<ManagedProcess[]> {
+    try {
+      return await apiCall<ManagedProcess[]>("list_managed_processes");
+    } catch (error) {
+      console.error("Failed to list managed processes:", error);
       throw error;
     }
   },
 
   /**
-   * Imports an agent from JSON data
-   * @param jsonData - The JSON string containing the agent export
-   * @returns Promise resolving to the imported agent
+   * Gets a managed process with its command line, child processes and recent output
+   * @param runId - The run ID of the process
+   * @returns Promise resolving to the process details
    */
-  async importAgent(jsonData: string): Promise<Agent> {
+  async inspectManagedProcess(runId: number): Promise<ProcessDetail> {
     try {
-      return await apiCall<Agent>('import_agent', { jsonData });
+      return await apiCall<ProcessDetail>("inspect_managed_process", { runId });
     } catch (error) {
-      console.error("Failed to import agent:", error);
+      console.error("Failed to inspect managed process:", error);
       throw error;
     }
   },
 
   /**
-   * Imports an agent from a file
-   * @param filePath - The path to the JSON file
-   * @returns Promise resolving to the imported agent
+   * Interrupts a managed process as if Ctrl+C was pressed, without force-killing it
+   * @param runId - The run ID of the process
+   * @returns Promise resolving to whether the process was signalled
    */
-  async importAgentFromFile(filePath: string): Promise<Agent> {
+  async interruptManagedProcess(runId: number): Promise<boolean> {
     try {
-      return await apiCall<Agent>('import_agent_from_file', { filePath });
+      return await apiCall<boolean>("interrupt_managed_process", { runId });
     } catch (error) {
-      console.error("Failed to import agent from file:", error);
+      console.error("Failed to interrupt managed process:", error);
       throw error;
     }
   },
 
   /**
-   * Executes an agent
-   * @param agentId - The agent ID to execute
-   * @param projectPath - The project path to run the agent in
-   * @param task - The task description
-   * @param model - Optional model override
-   * @returns Promise resolving to the run ID when execution starts
+   * Pauses a managed process and everything it spawned until it is unpaused
+   * @param runId - The run ID of the process
+   * @returns Promise resolving to whether the process is paused
    */
-  async executeAgent(agentId: number, projectPath: string, task: string, model?: string): Promise<number> {
+  async pauseManagedProcess(runId: number): Promise<boolean> {
     try {
-      return await apiCall<number>('execute_agent', { agentId, projectPath, task, model });
+      return await apiCall<boolean>("pause_managed_process", { runId });
     } catch (error) {
-      console.error("Failed to execute agent:", error);
-      // Return a sentinel value to indicate error
-      throw new Error(`Failed to execute agent: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to pause managed process:", error);
+      throw error;
     }
   },
 
   /**
-   * Lists agent runs without metrics (basic info only)
-   * @param agentId - Optional agent ID to filter runs
-   * @returns Promise resolving to an array of agent runs
+   * Lets a paused managed process continue
+   * @param runId - The run ID of the process
+   * @returns Promise resolving to whether the process was paused
    */
-  async listAgentRuns(agentId?: number): Promise<AgentRunWithMetrics[]> {
+  async unpauseManagedProcess(runId: number): Promise<boolean> {
     try {
-      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs', { agentId });
+      return await apiCall<boolean>("unpause_managed_process", { runId });
     } catch (error) {
-      console.error("Failed to list agent runs:", error);
-      // Return empty array instead of throwing to prevent UI crashes
-      return [];
+      console.error("Failed to unpause managed process:", error);
+      throw error;
     }
   },
 
   /**
-   * Lists agent runs with metrics (includes token counts and duration)
-   * @param agentId - Optional agent ID to filter runs
-   * @returns Promise resolving to an array of agent runs with metrics
+   * Stops a managed process
+   * @param runId - The run ID of the process
+   * @returns Promise resolving to whether the process was stopped
    */
-  async listAgentRunsWithMetrics(agentId?: number): Promise<AgentRunWithMetrics[]> {
+  async killManagedProcess(runId: number): Promise<boolean> {
     try {
-      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs_with_metrics', { agentId });
+      return await apiCall<boolean>("kill_managed_process", { runId });
     } catch (error) {
-      console.error("Failed to list agent runs with metrics:", error);
-      // Return empty array instead of throwing to prevent UI crashes
-      return [];
+      console.error("Failed to kill managed process:", error);
+      throw error;
     }
   },
 
   /**
-   * Gets a single agent run by ID with metrics
-   * @param id - The run ID
-   * @returns Promise resolving to the agent run with metrics
+   * Stops every managed process working in a project
+   * @param projectPath - The project path
+   * @returns Promise resolving to the number of processes stopped
    */
-  async getAgentRun(id: number): Promise<AgentRunWithMetrics> {
+  async killProjectProcesses(projectPath: string): Promise<number> {
     try {
-      return await apiCall<AgentRunWithMetrics>('get_agent_run', { id });
+      return await apiCall<number>("kill_project_processes", { projectPath });
     } catch (error) {
-      console.error("Failed to get agent run:", error);
-      throw new Error(`Failed to get agent run: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to kill project processes:", error);
+      throw error;
     }
   },
 
   /**
-   * Gets a single agent run by ID with real-time metrics from JSONL
-   * @param id - The run ID
-   * @returns Promise resolving to the agent run with metrics
+   * Lists running sessions and agent runs that are possibly stalled
+   * @returns Promise resolving to the stalled runs, quietest first
    */
-  async getAgentRunWithRealTimeMetrics(id: number): Promise<AgentRunWithMetrics> {
+  async listStalledRuns(): Promise<StalledRun[]> {
     try {
-      return await apiCall<AgentRunWithMetrics>('get_agent_run_with_real_time_metrics', { id });
+      return await apiCall<StalledRun[]>("list_stalled_runs");
     } catch (error) {
-      console.error("Failed to get agent run with real-time metrics:", error);
-      throw new Error(`Failed to get agent run with real-time metrics: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to list stalled runs:", error);
+      throw error;
     }
   },
 
   /**
-   * Lists all currently running agent sessions
-   * @returns Promise resolving to list of running agent sessions
+   * Gets after how long without output a run is reported as possibly stalled
+   * @returns Promise resolving to the stall detection settings
    */
-  async listRunningAgentSessions(): Promise<AgentRun[]> {
+  async getStallDetectionSettings(): Promise<StallDetectionSettings> {
     try {
-      return await apiCall<AgentRun[]>('list_running_sessions');
+      return await apiCall<StallDetectionSettings>("get_stall_detection_settings");
     } catch (error) {
-      console.error("Failed to list running agent sessions:", error);
-      throw new Error(`Failed to list running agent sessions: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to get stall detection settings:", error);
+      throw error;
     }
   },
 
   /**
-   * Kills a running agent session
-   * @param runId - The run ID to kill
-   * @returns Promise resolving to whether the session was successfully killed
+   * Saves after how long without output a run is reported as possibly stalled
+   * @param settings - The stall detection settings
    */
-  async killAgentSession(runId: number): Promise<boolean> {
+  async setStallDetectionSettings(settings: StallDetectionSettings): Promise<void> {
     try {
-      return await apiCall<boolean>('kill_agent_session', { runId });
+      return await apiCall("set_stall_detection_settings", { settings });
     } catch (error) {
-      console.error("Failed to kill agent session:", error);
-      throw new Error(`Failed to kill agent session: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to save stall detection settings:", error);
+      throw error;
     }
   },
 
   /**
-   * Gets the status of a specific agent session
-   * @param runId - The run ID to check
-   * @returns Promise resolving to the session status or null if not found
+   * Lists the stored raw output logs of agent runs and sessions
+   * @returns Promise resolving to the logs, newest first
    */
-  async getSessionStatus(runId: number): Promise<string | null> {
+  async listRunOutputLogs(): Promise<RunOutputLogInfo[]> {
     try {
-      return await apiCall<string | null>('get_session_status', { runId });
+      return await apiCall<RunOutputLogInfo[]>("list_run_output_logs");
     } catch (error) {
-      console.error("Failed to get session status:", error);
-      throw new Error(`Failed to get session status: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to list run output logs:", error);
+      throw error;
     }
   },
 
   /**
-   * Cleanup finished processes and update their status
-   * @returns Promise resolving to list of run IDs that were cleaned up
+   * Reads the last lines of a stored output log, each prefixed with its time and stream
+   * @param name - The log name, e.g. `agent-run-42` or `session-<sessionId>`
+   * @param lines - Optional number of lines to read
+   * @returns Promise resolving to the log lines, oldest first
    */
-  async cleanupFinishedProcesses(): Promise<number[]> {
+  async readRunOutputLog(name: string, lines?: number): Promise<string[]> {
     try {
-      return await apiCall<number[]>('cleanup_finished_processes');
+      return await apiCall<string[]>("read_run_output_log", { name, lines });
     } catch (error) {
-      console.error("Failed to cleanup finished processes:", error);
-      throw new Error(`Failed to cleanup finished processes: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error("Failed to read run output log:", error);
+      throw error;
     }
   },
 
@@ -1126,11 +2198,12 @@ export const api = {
 
   /**
    * Gets overall usage statistics
+   * @param agentId - Optional agent whose runs to limit the usage to
    * @returns Promise resolving to usage statistics
    */
-  async getUsageStats(): Promise<UsageStats> {
+  async getUsageStats(agentId?: number): Promise<UsageStats> {
     try {
-      return await apiCall<UsageStats>("get_usage_stats");
+      return await apiCall<UsageStats>("get_usage_stats", { agentId });
     } catch (error) {
       console.error("Failed to get usage stats:", error);
       throw error;
@@ -1141,17 +2214,223 @@ export const api = {
    * Gets usage statistics filtered by date range
    * @param startDate - Start date (ISO format)
    * @param endDate - End date (ISO format)
+   * @param agentId - Optional agent whose runs to limit the usage to
    * @returns Promise resolving to usage statistics
    */
-  async getUsageByDateRange(startDate: string, endDate: string): Promise<UsageStats> {
+  async getUsageByDateRange(startDate: string, endDate: string, agentId?: number): Promise<UsageStats> {
     try {
-      return await apiCall<UsageStats>("get_usage_by_date_range", { startDate, endDate });
+      return await apiCall<UsageStats>("get_usage_by_date_range", { startDate, endDate, agentId });
     } catch (error) {
       console.error("Failed to get usage by date range:", error);
       throw error;
     }
   },
 
+  /**
+   * Gets the total and average cost of each agent's runs
+   * @returns Promise resolving to the agents, most expensive first
+   */
+  async getAgentCosts(): Promise<AgentCost[]> {
+    try {
+      return await apiCall<AgentCost[]>("get_agent_costs");
+    } catch (error) {
+      console.error("Failed to get agent costs:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Compares the models used in a date range by cost, tokens, turns and agent run success rate
+   * @param startDate - Optional start date (ISO format)
+   * @param endDate - Optional end date (ISO format)
+   * @returns Promise resolving to the models, most expensive first
+   */
+  async getModelComparison(startDate?: string, endDate?: string): Promise<ModelComparison[]> {
+    try {
+      return await apiCall<ModelComparison[]>("get_model_comparison", { startDate, endDate });
+    } catch (error) {
+      console.error("Failed to get model comparison:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets sessions started, agent runs and cost for each day of the past year
+   * @returns Promise resolving to every day, oldest first
+   */
+  async getActivityHeatmap(): Promise<ActivityDay[]> {
+    try {
+      return await apiCall<ActivityDay[]>("get_activity_heatmap");
+    } catch (error) {
+      console.error("Failed to get activity heatmap:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Exports the usage indexed on this machine for import on another
+   * @param filePath - File to write the export to
+   * @param machine - Optional label for this machine, defaulting to its hostname
+   * @returns Promise resolving to the number of usage entries exported
+   */
+  async exportUsage(filePath: string, machine?: string): Promise<number> {
+    try {
+      return await apiCall<number>("export_usage", { filePath, machine });
+    } catch (error) {
+      console.error("Failed to export usage:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Merges a usage export from another machine into the usage dashboard
+   * @param filePath - The export to import
+   * @returns Promise resolving to how many entries were new
+   */
+  async importUsage(filePath: string): Promise<UsageImportSummary> {
+    try {
+      return await apiCall<UsageImportSummary>("import_usage", { filePath });
+    } catch (error) {
+      console.error("Failed to import usage:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists the machines whose usage has been imported
+   * @returns Promise resolving to the imports by machine
+   */
+  async listUsageImports(): Promise<UsageImport[]> {
+    try {
+      return await apiCall<UsageImport[]>("list_usage_imports");
+    } catch (error) {
+      console.error("Failed to list usage imports:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Removes the usage imported from a machine
+   * @param machine - The machine whose usage to remove
+   * @returns Promise resolving to the number of entries removed
+   */
+  async removeUsageImport(machine: string): Promise<number> {
+    try {
+      return await apiCall<number>("remove_usage_import", { machine });
+    } catch (error) {
+      console.error("Failed to remove usage import:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets how many months of per-message usage are kept
+   * @returns Promise resolving to the usage retention settings
+   */
+  async getUsageRetention(): Promise<UsageRetentionSettings> {
+    try {
+      return await apiCall<UsageRetentionSettings>("get_usage_retention");
+    } catch (error) {
+      console.error("Failed to get usage retention:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets how many months of per-message usage are kept before rolling up to monthly totals
+   * @param settings - The usage retention settings
+   */
+  async setUsageRetention(settings: UsageRetentionSettings): Promise<void> {
+    try {
+      return await apiCall<void>("set_usage_retention", { settings });
+    } catch (error) {
+      console.error("Failed to set usage retention:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Rolls up usage into monthly totals and compacts the database
+   * @param before - Optional date (YYYY-MM-DD) to roll up before, defaulting to the retention period
+   * @returns Promise resolving to what was rolled up
+   */
+  async purgeUsage(before?: string): Promise<UsagePurgeReport> {
+    try {
+      return await apiCall<UsagePurgeReport>("purge_usage", { before });
+    } catch (error) {
+      console.error("Failed to purge usage:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the monthly totals of rolled up usage
+   * @returns Promise resolving to the monthly totals, newest first
+   */
+  async getUsageRollups(): Promise<MonthlyUsage[]> {
+    try {
+      return await apiCall<MonthlyUsage[]>("get_usage_rollups");
+    } catch (error) {
+      console.error("Failed to get usage rollups:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists sessions and agent runs flagged as costing far more than usual
+   * @param projectPath - Optional project to limit the list to
+   * @param includeDismissed - Whether to include dismissed anomalies
+   * @returns Promise resolving to the anomalies, newest first
+   */
+  async listCostAnomalies(projectPath?: string, includeDismissed?: boolean): Promise<CostAnomaly[]> {
+    try {
+      return await apiCall<CostAnomaly[]>("list_cost_anomalies", { projectPath, includeDismissed });
+    } catch (error) {
+      console.error("Failed to list cost anomalies:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Dismisses a cost anomaly
+   * @param id - The ID of the anomaly
+   */
+  async dismissCostAnomaly(id: number): Promise<void> {
+    try {
+      return await apiCall("dismiss_cost_anomaly", { id });
+    } catch (error) {
+      console.error("Failed to dismiss cost anomaly:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the custom model prices and the currency costs are displayed in
+   * @returns Promise resolving to the pricing settings
+   */
+  async getPricingSettings(): Promise<PricingSettings> {
+    try {
+      return await apiCall<PricingSettings>("get_pricing_settings");
+    } catch (error) {
+      console.error("Failed to get pricing settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the custom model prices and display currency, repricing indexed usage
+   * @param settings - The pricing settings
+   * @returns Promise resolving to the number of usage entries whose cost changed
+   */
+  async savePricingSettings(settings: PricingSettings): Promise<number> {
+    try {
+      return await apiCall<number>("save_pricing_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save pricing settings:", error);
+      throw error;
+    }
+  },
+
   /**
    * Gets usage statistics grouped by session
    * @param since - Optional start date (YYYYMMDD)
@@ -1442,7 +2721,8 @@ export const api = {
     args: string[] = [],
     env: Record<string, string> = {},
     url?: string,
-    scope: string = "local"
+    scope: string = "local",
+    secrets?: Record<string, string>
   ): Promise<AddServerResult> {
     try {
       return await apiCall<AddServerResult>("mcp_add", {
@@ -1452,7 +2732,8 @@ export const api = {
         args,
         env,
         url,
-        scope
+        scope,
+        secrets
       });
     } catch (error) {
       console.error("Failed to add MCP server:", error);
@@ -1919,11 +3200,12 @@ export const api = {
   /**
    * Gets a single slash command by ID
    * @param commandId - Unique identifier of the command
+   * @param projectPath - Optional project path for project commands
    * @returns Promise resolving to the slash command
    */
-  async slashCommandGet(commandId: string): Promise<SlashCommand> {
+  async slashCommandGet(commandId: string, projectPath?: string): Promise<SlashCommand> {
     try {
-      return await apiCall<SlashCommand>("slash_command_get", { commandId });
+      return await apiCall<SlashCommand>("slash_command_get", { commandId, projectPath });
     } catch (error) {
       console.error("Failed to get slash command:", error);
       throw error;
@@ -1939,6 +3221,8 @@ export const api = {
    * @param description - Optional description
    * @param allowedTools - List of allowed tools for this command
    * @param projectPath - Required for project scope commands
+   * @param argumentHint - Optional hint for the command's arguments
+   * @param model - Optional model the command runs with
    * @returns Promise resolving to the saved command
    */
   async slashCommandSave(
@@ -1948,7 +3232,9 @@ export const api = {
     content: string,
     description: string | undefined,
     allowedTools: string[],
-    projectPath?: string
+    projectPath?: string,
+    argumentHint?: string,
+    model?: string
   ): Promise<SlashCommand> {
     try {
       return await apiCall<SlashCommand>("slash_command_save", {
@@ -1958,7 +3244,9 @@ export const api = {
         content,
         description,
         allowedTools,
-        projectPath
+        projectPath,
+        argumentHint,
+        model
       });
     } catch (error) {
       console.error("Failed to save slash command:", error);
@@ -1981,6 +3269,21 @@ export const api = {
     }
   },
 
+  /**
+   * Lists the slash commands available in a project, project commands taking
+   * precedence over user commands of the same name
+   * @param projectPath - The project to resolve commands for
+   * @returns Promise resolving to the effective commands
+   */
+  async slashCommandsEffective(projectPath: string): Promise<EffectiveSlashCommand[]> {
+    try {
+      return await apiCall<EffectiveSlashCommand[]>("slash_commands_effective", { projectPath });
+    } catch (error) {
+      console.error("Failed to list effective slash commands:", error);
+      throw error;
+    }
+  },
+
   // Shell Environment API methods (Windows WSL/Git Bash support)
 
   /**
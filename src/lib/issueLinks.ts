@@ -0,0 +1,31 @@
+import { api, type IssueLink } from "./api";
+
+/**
+ * Issues linked to an agent run, for including in its exports; an export
+ * goes ahead without them if they can't be loaded
+ */
+export async function loadRunIssueLinks(runId: number | undefined): Promise<IssueLink[]> {
+  if (runId === undefined) return [];
+  try {
+    return await api.listIssueLinks("run", String(runId));
+  } catch (error) {
+    console.error("Failed to load issue links:", error);
+    return [];
+  }
+}
+
+/** `**Issues:**` line of a Markdown export, empty without links */
+export function issueLinksMarkdown(links: IssueLink[]): string {
+  if (links.length === 0) return "";
+  return `**Issues:** ${links.map((link) => `[${link.issue_key}](${link.url})`).join(", ")}\n`;
+}
+
+/** `issue_links` line appended to a JSONL export, empty without links */
+export function issueLinksJsonl(links: IssueLink[]): string {
+  if (links.length === 0) return "";
+  const line = {
+    type: "issue_links",
+    links: links.map(({ tracker, issue_key, url }) => ({ tracker, issue_key, url })),
+  };
+  return `\n${JSON.stringify(line)}`;
+}
@@ -0,0 +1,7 @@
+/**
+ * Lines carried by a stream output event. Busy streams are coalesced by the
+ * backend, so an event carries either a single line or an array of lines.
+ */
+export function streamLines(payload: string | string[]): string[] {
+  return Array.isArray(payload) ? payload : [payload];
+}